@@ -55,6 +55,21 @@ enum Command {
         #[clap(flatten)]
         settings: MeshSettings,
     },
+
+    /// Step through VM point evaluation interactively
+    Debug {
+        /// X coordinate
+        #[clap(short, default_value_t = 0.0, allow_hyphen_values = true)]
+        x: f32,
+
+        /// Y coordinate
+        #[clap(short, default_value_t = 0.0, allow_hyphen_values = true)]
+        y: f32,
+
+        /// Z coordinate
+        #[clap(short, default_value_t = 0.0, allow_hyphen_values = true)]
+        z: f32,
+    },
 }
 
 #[derive(ValueEnum, Clone)]
@@ -117,15 +132,16 @@ fn run3d<S: fidget::eval::Shape>(
     settings: &ImageSettings,
     isometric: bool,
     mode_color: bool,
-) -> Vec<u8> {
+) -> Result<Vec<u8>> {
     let mut mat = nalgebra::Transform3::identity();
     if !isometric {
         *mat.matrix_mut().get_mut((3, 2)).unwrap() = 0.3;
     }
     let cfg = fidget::render::RenderConfig {
-        image_size: settings.size as usize,
+        image_size: (settings.size as usize).into(),
         tile_sizes: S::tile_sizes_3d().to_vec(),
         threads: settings.threads,
+        bounds: Some(fidget::shape::Bounds::centered(1.0)),
         ..Default::default()
     };
     let shape = shape.apply_transform(mat.into());
@@ -133,7 +149,7 @@ fn run3d<S: fidget::eval::Shape>(
     let mut depth = vec![];
     let mut color = vec![];
     for _ in 0..settings.n {
-        (depth, color) = fidget::render::render3d(shape.clone(), &cfg);
+        (depth, color) = fidget::render::render3d(shape.clone(), &cfg)?;
     }
 
     let out = if mode_color {
@@ -163,7 +179,7 @@ fn run3d<S: fidget::eval::Shape>(
             .collect()
     };
 
-    out
+    Ok(out)
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -173,7 +189,7 @@ fn run2d<S: fidget::eval::Shape>(
     settings: &ImageSettings,
     brute: bool,
     sdf: bool,
-) -> Vec<u8> {
+) -> Result<Vec<u8>> {
     if brute {
         let tape = shape.float_slice_tape(Default::default());
         let mut eval = S::new_float_slice_eval();
@@ -195,15 +211,17 @@ fn run2d<S: fidget::eval::Shape>(
             out = values.iter().map(|v| *v <= 0.0).collect();
         }
         // Convert from Vec<bool> to an image
-        out.into_iter()
+        Ok(out
+            .into_iter()
             .map(|b| if b { [u8::MAX; 4] } else { [0, 0, 0, 255] })
             .flat_map(|i| i.into_iter())
-            .collect()
+            .collect())
     } else {
         let cfg = fidget::render::RenderConfig {
-            image_size: settings.size as usize,
+            image_size: (settings.size as usize).into(),
             tile_sizes: S::tile_sizes_2d().to_vec(),
             threads: settings.threads,
+            bounds: Some(fidget::shape::Bounds::centered(1.0)),
             ..Default::default()
         };
         if sdf {
@@ -212,24 +230,24 @@ fn run2d<S: fidget::eval::Shape>(
                 image = fidget::render::render2d::<
                     _,
                     fidget::render::SdfRenderMode,
-                >(shape.clone(), &cfg);
+                >(shape.clone(), &cfg)?;
             }
-            image
+            Ok(image
                 .into_iter()
                 .flat_map(|a| [a[0], a[1], a[2], 255].into_iter())
-                .collect()
+                .collect())
         } else {
             let mut image = vec![];
             for _ in 0..settings.n {
                 image = fidget::render::render2d::<
                     _,
                     fidget::render::DebugRenderMode,
-                >(shape.clone(), &cfg);
+                >(shape.clone(), &cfg)?;
             }
-            image
+            Ok(image
                 .into_iter()
                 .flat_map(|p| p.as_debug_color().into_iter())
-                .collect()
+                .collect())
         }
     }
 }
@@ -239,19 +257,136 @@ fn run2d<S: fidget::eval::Shape>(
 fn run_mesh<S: fidget::eval::Shape>(
     shape: S,
     settings: &MeshSettings,
-) -> fidget::mesh::Mesh {
+) -> Result<fidget::mesh::Mesh> {
     let mut mesh = fidget::mesh::Mesh::new();
 
     for _ in 0..settings.n {
         let settings = fidget::mesh::Settings {
             threads: settings.threads,
             depth: settings.depth,
+            bounds: Some(fidget::shape::Bounds::centered(1.0)),
             ..Default::default()
         };
-        let octree = fidget::mesh::Octree::build(&shape, settings);
+        let octree = fidget::mesh::Octree::build(&shape, settings)?;
         mesh = octree.walk_dual(settings);
     }
-    mesh
+    Ok(mesh)
+}
+
+const DEBUG_HELP: &str = "commands:
+  s [n]    step one (or n) clauses
+  c        continue to the next breakpoint
+  n        continue to the first clause which produces NaN
+  b <i>    set a breakpoint before clause i
+  d <i>    delete a breakpoint
+  r        print registers and memory
+  l        list clauses around the current one
+  q        quit";
+
+/// Runs an interactive debugger over VM point evaluation
+fn run_debug(
+    ctx: &Context,
+    root: fidget::context::Node,
+    p: [f32; 3],
+) -> Result<()> {
+    use std::io::{BufRead, Write};
+
+    let shape = fidget::vm::VmShape::new(ctx, root)?;
+    let mut dbg = fidget::vm::VmDebugger::new(&shape, &p)?;
+    let list = |dbg: &fidget::vm::VmDebugger<{ u8::MAX as usize }>| {
+        let start = dbg.pc().saturating_sub(3);
+        for i in start..(dbg.pc() + 4).min(dbg.len()) {
+            let mark = if i == dbg.pc() { '>' } else { ' ' };
+            let bp = if dbg.breakpoints().any(|b| b == i) {
+                '*'
+            } else {
+                ' '
+            };
+            println!("{mark}{bp}{i:>5}: {:?}", dbg.clause(i).unwrap());
+        }
+    };
+    println!("{} clauses; type `h` for help", dbg.len());
+    list(&dbg);
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(dbg) ");
+        std::io::stdout().flush()?;
+        let Some(line) = lines.next() else { break };
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let cmd = words.next().unwrap_or("s");
+        let arg = words.next().map(|w| w.parse::<usize>());
+        match (cmd, arg) {
+            ("s", n) => {
+                for _ in 0..n.unwrap_or(Ok(1))? {
+                    if let Some(op) = dbg.step() {
+                        let v = dbg.output(dbg.pc() - 1).unwrap();
+                        match dbg.last_choice() {
+                            Some(c) => println!("  {op:?} = {v} ({c:?})"),
+                            None => println!("  {op:?} = {v}"),
+                        }
+                    }
+                }
+            }
+            ("c", None) => match dbg.resume() {
+                Some(i) => println!("breakpoint at clause {i}"),
+                None => println!("done"),
+            },
+            ("n", None) => match dbg.run_until(|_, v| v.is_nan()) {
+                Some(i) if dbg.output(i).is_some_and(|v| v.is_nan()) => {
+                    println!("NaN at clause {i}: {:?}", dbg.clause(i).unwrap())
+                }
+                Some(i) => println!("breakpoint at clause {i}"),
+                None => println!("done"),
+            },
+            ("b", Some(i)) => dbg.set_breakpoint(i?),
+            ("d", Some(i)) => {
+                dbg.clear_breakpoint(i?);
+            }
+            ("r", None) => {
+                for (i, v) in dbg.registers().iter().enumerate() {
+                    if !v.is_nan() {
+                        println!("  r{i} = {v}");
+                    }
+                }
+                let n = dbg.registers().len();
+                for (i, v) in dbg.memory().iter().enumerate() {
+                    if !v.is_nan() {
+                        println!("  m{} = {v}", i + n);
+                    }
+                }
+            }
+            ("l", None) => list(&dbg),
+            ("q", None) => break,
+            _ => println!("{DEBUG_HELP}"),
+        }
+        if let Some(v) = dbg.value() {
+            println!("result: {v}");
+
+            // Compare against the JIT, to catch miscompiles
+            #[cfg(feature = "jit")]
+            {
+                use fidget::eval::{Shape, TracingEvaluator};
+                let shape = fidget::jit::JitShape::new(ctx, root)?;
+                let mut eval = fidget::jit::JitShape::new_point_eval();
+                let (j, _) = eval.eval(
+                    &shape.point_tape(Default::default()),
+                    p[0],
+                    p[1],
+                    p[2],
+                )?;
+                if j.to_bits() == v.to_bits() || (j.is_nan() && v.is_nan()) {
+                    println!("JIT agrees");
+                } else {
+                    println!("JIT disagrees: {j}");
+                }
+            }
+            break;
+        }
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -276,12 +411,12 @@ fn main() -> Result<()> {
                 EvalMode::Jit => {
                     let shape = fidget::jit::JitShape::new(&ctx, root)?;
                     info!("Built shape in {:?}", start.elapsed());
-                    run2d(shape, &settings, brute, sdf)
+                    run2d(shape, &settings, brute, sdf)?
                 }
                 EvalMode::Vm => {
                     let shape = fidget::vm::VmShape::new(&ctx, root)?;
                     info!("Built shape in {:?}", start.elapsed());
-                    run2d(shape, &settings, brute, sdf)
+                    run2d(shape, &settings, brute, sdf)?
                 }
             };
 
@@ -313,12 +448,12 @@ fn main() -> Result<()> {
                 EvalMode::Jit => {
                     let shape = fidget::jit::JitShape::new(&ctx, root)?;
                     info!("Built shape in {:?}", start.elapsed());
-                    run3d(shape, &settings, isometric, color)
+                    run3d(shape, &settings, isometric, color)?
                 }
                 EvalMode::Vm => {
                     let shape = fidget::vm::VmShape::new(&ctx, root)?;
                     info!("Built shape in {:?}", start.elapsed());
-                    run3d(shape, &settings, isometric, color)
+                    run3d(shape, &settings, isometric, color)?
                 }
             };
             info!(
@@ -347,12 +482,12 @@ fn main() -> Result<()> {
                 EvalMode::Jit => {
                     let shape = fidget::jit::JitShape::new(&ctx, root)?;
                     info!("Built shape in {:?}", start.elapsed());
-                    run_mesh(shape, &settings)
+                    run_mesh(shape, &settings)?
                 }
                 EvalMode::Vm => {
                     let shape = fidget::vm::VmShape::new(&ctx, root)?;
                     info!("Built shape in {:?}", start.elapsed());
-                    run_mesh(shape, &settings)
+                    run_mesh(shape, &settings)?
                 }
             };
             info!(
@@ -367,6 +502,7 @@ fn main() -> Result<()> {
                 mesh.write_stl(&mut std::fs::File::create(out)?)?;
             }
         }
+        Command::Debug { x, y, z } => run_debug(&ctx, root, [x, y, z])?,
     }
 
     Ok(())
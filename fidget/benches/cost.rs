@@ -0,0 +1,111 @@
+//! Compares the cost model against measured evaluation times
+//!
+//! Each benchmark ID includes the estimated cost from
+//! `fidget::compiler::RegTape::estimated_cost`, so the model can be checked
+//! by comparing the estimate with the measured time per point.
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+};
+use fidget::{
+    compiler::Flavor,
+    context::{Context, Node},
+    eval::{BulkEvaluator, EzShape, MathShape, Shape, TracingEvaluator},
+    types::Interval,
+};
+
+const PROSPERO: &str = include_str!("../../models/prospero.vm");
+
+/// Number of points used for slice evaluation
+const N: usize = 1024;
+
+fn run_bench<S: Shape + MathShape>(
+    c: &mut Criterion,
+    ctx: &Context,
+    node: Node,
+    test_name: &str,
+    name: &str,
+    estimate: impl Fn(&S, Flavor) -> f64,
+) {
+    let shape = S::new(ctx, node).unwrap();
+    let id = |flavor| {
+        let est = estimate(&shape, flavor);
+        BenchmarkId::new(name, format!("{flavor:?} (est. {est:.1})"))
+    };
+    let mut group = c.benchmark_group(test_name);
+
+    let mut eval = S::new_point_eval();
+    let tape = shape.ez_point_tape();
+    group.bench_function(id(Flavor::Point), |b| {
+        b.iter(|| black_box(eval.eval(&tape, 0.1, 0.2, 0.3).unwrap().0))
+    });
+
+    let mut eval = S::new_interval_eval();
+    let tape = shape.ez_interval_tape();
+    let (x, y, z) = (
+        Interval::new(0.0, 0.1),
+        Interval::new(0.1, 0.2),
+        Interval::new(0.2, 0.3),
+    );
+    group.bench_function(id(Flavor::Interval), |b| {
+        b.iter(|| black_box(eval.eval(&tape, x, y, z).unwrap().0))
+    });
+
+    // Slice estimates are per element, so compare them with the throughput
+    let data = (0..N).map(|i| i as f32 / N as f32).collect::<Vec<f32>>();
+    let mut eval = S::new_float_slice_eval();
+    let tape = shape.ez_float_slice_tape();
+    group.throughput(criterion::Throughput::Elements(N as u64));
+    group.bench_function(id(Flavor::FloatSlice), |b| {
+        b.iter(|| {
+            black_box(eval.eval(&tape, &data, &data, &data).unwrap());
+        })
+    });
+
+    let mut eval = S::new_grad_slice_eval();
+    let tape = shape.ez_grad_slice_tape();
+    group.bench_function(id(Flavor::GradSlice), |b| {
+        b.iter(|| {
+            black_box(eval.eval(&tape, &data, &data, &data).unwrap());
+        })
+    });
+}
+
+fn test_shape(c: &mut Criterion, ctx: &Context, node: Node, name: &str) {
+    run_bench::<fidget::vm::VmShape>(c, ctx, node, name, "vm", |s, f| {
+        s.estimated_cost(f)
+    });
+    #[cfg(feature = "jit")]
+    run_bench::<fidget::jit::JitShape>(c, ctx, node, name, "jit", |s, f| {
+        s.estimated_cost(f)
+    });
+}
+
+pub fn test_sin(c: &mut Criterion) {
+    let mut ctx = Context::new();
+    let x = ctx.x();
+    let f = ctx.sin(x).unwrap();
+    test_shape(c, &ctx, f, "cost model (sin)");
+}
+
+pub fn test_sphere(c: &mut Criterion) {
+    let mut ctx = Context::new();
+    let x = ctx.x();
+    let y = ctx.y();
+    let z = ctx.z();
+    let x2 = ctx.square(x).unwrap();
+    let y2 = ctx.square(y).unwrap();
+    let z2 = ctx.square(z).unwrap();
+    let r = ctx.add(x2, y2).unwrap();
+    let r = ctx.add(r, z2).unwrap();
+    let r = ctx.sqrt(r).unwrap();
+    let f = ctx.sub(r, 0.5).unwrap();
+    test_shape(c, &ctx, f, "cost model (sphere)");
+}
+
+pub fn test_prospero(c: &mut Criterion) {
+    let (ctx, root) = Context::from_text(PROSPERO.as_bytes()).unwrap();
+    test_shape(c, &ctx, root, "cost model (prospero)");
+}
+
+criterion_group!(benches, test_sin, test_sphere, test_prospero);
+criterion_main!(benches);
@@ -17,19 +17,20 @@ pub fn colonnade_octree_thread_sweep(c: &mut Criterion) {
         let cfg = &fidget::mesh::Settings {
             depth: 6,
             threads: threads.try_into().unwrap(),
+            bounds: Some(fidget::shape::Bounds::centered(1.0)),
             ..Default::default()
         };
         #[cfg(feature = "jit")]
         group.bench_function(BenchmarkId::new("jit", threads), move |b| {
             b.iter(|| {
                 let cfg = *cfg;
-                black_box(fidget::mesh::Octree::build(shape_jit, cfg))
+                black_box(fidget::mesh::Octree::build(shape_jit, cfg).unwrap())
             })
         });
         group.bench_function(BenchmarkId::new("vm", threads), move |b| {
             b.iter(|| {
                 let cfg = *cfg;
-                black_box(fidget::mesh::Octree::build(shape_vm, cfg))
+                black_box(fidget::mesh::Octree::build(shape_vm, cfg).unwrap())
             })
         });
     }
@@ -40,9 +41,10 @@ pub fn colonnade_mesh(c: &mut Criterion) {
     let shape_vm = &fidget::vm::VmShape::new(&ctx, root).unwrap();
     let cfg = fidget::mesh::Settings {
         depth: 8,
+        bounds: Some(fidget::shape::Bounds::centered(1.0)),
         ..Default::default()
     };
-    let octree = &fidget::mesh::Octree::build(shape_vm, cfg);
+    let octree = &fidget::mesh::Octree::build(shape_vm, cfg).unwrap();
 
     let mut group =
         c.benchmark_group("speed vs threads (colonnade, meshing) (depth 8)");
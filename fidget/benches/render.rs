@@ -17,8 +17,9 @@ pub fn prospero_size_sweep(c: &mut Criterion) {
         c.benchmark_group("speed vs image size (prospero, 2d) (8 threads)");
     for size in [256, 512, 768, 1024, 1280, 1546, 1792, 2048] {
         let cfg = &fidget::render::RenderConfig {
-            image_size: size,
+            image_size: size.into(),
             tile_sizes: fidget::vm::VmShape::tile_sizes_2d().to_vec(),
+            bounds: Some(fidget::shape::Bounds::centered(1.0)),
             ..Default::default()
         };
         group.bench_function(BenchmarkId::new("vm", size), move |b| {
@@ -27,24 +28,29 @@ pub fn prospero_size_sweep(c: &mut Criterion) {
                 black_box(fidget::render::render2d::<
                     _,
                     fidget::render::BitRenderMode,
-                >(tape, cfg))
+                >(tape, cfg)
+                .unwrap())
             })
         });
 
         #[cfg(feature = "jit")]
         {
             let cfg = &fidget::render::RenderConfig {
-                image_size: size,
+                image_size: size.into(),
                 tile_sizes: fidget::jit::JitShape::tile_sizes_2d().to_vec(),
+                bounds: Some(fidget::shape::Bounds::centered(1.0)),
                 ..Default::default()
             };
             group.bench_function(BenchmarkId::new("jit", size), move |b| {
                 b.iter(|| {
                     let tape = shape_jit.clone();
-                    black_box(fidget::render::render2d::<
-                        _,
-                        fidget::render::BitRenderMode,
-                    >(tape, cfg))
+                    black_box(
+                        fidget::render::render2d::<
+                            _,
+                            fidget::render::BitRenderMode,
+                        >(tape, cfg)
+                        .unwrap(),
+                    )
                 })
             });
         }
@@ -62,9 +68,10 @@ pub fn prospero_thread_sweep(c: &mut Criterion) {
         c.benchmark_group("speed vs threads (prospero, 2d) (1024 x 1024)");
     for threads in [1, 2, 4, 8, 16] {
         let cfg = &fidget::render::RenderConfig {
-            image_size: 1024,
+            image_size: 1024.into(),
             tile_sizes: fidget::vm::VmShape::tile_sizes_2d().to_vec(),
             threads: threads.try_into().unwrap(),
+            bounds: Some(fidget::shape::Bounds::centered(1.0)),
             ..Default::default()
         };
         group.bench_function(BenchmarkId::new("vm", threads), move |b| {
@@ -73,24 +80,29 @@ pub fn prospero_thread_sweep(c: &mut Criterion) {
                 black_box(fidget::render::render2d::<
                     _,
                     fidget::render::BitRenderMode,
-                >(tape, cfg))
+                >(tape, cfg)
+                .unwrap())
             })
         });
         #[cfg(feature = "jit")]
         {
             let cfg = &fidget::render::RenderConfig {
-                image_size: 1024,
+                image_size: 1024.into(),
                 tile_sizes: fidget::jit::JitShape::tile_sizes_2d().to_vec(),
                 threads: threads.try_into().unwrap(),
+                bounds: Some(fidget::shape::Bounds::centered(1.0)),
                 ..Default::default()
             };
             group.bench_function(BenchmarkId::new("jit", threads), move |b| {
                 b.iter(|| {
                     let tape = shape_jit.clone();
-                    black_box(fidget::render::render2d::<
-                        _,
-                        fidget::render::BitRenderMode,
-                    >(tape, cfg))
+                    black_box(
+                        fidget::render::render2d::<
+                            _,
+                            fidget::render::BitRenderMode,
+                        >(tape, cfg)
+                        .unwrap(),
+                    )
                 })
             });
         }
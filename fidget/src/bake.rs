@@ -0,0 +1,609 @@
+//! Baking shapes into 3D textures
+//!
+//! Baked textures are intended for GPU consumption, e.g. sphere-tracing a
+//! complex model at runtime in a game engine.  The texture can be either dense
+//! (every texel is stored) or sparse (only bricks of texels near the surface
+//! are stored), and may optionally include gradients.
+//!
+//! ```
+//! use fidget::{
+//!     bake::{to_texture3d, TextureOptions},
+//!     context::Tree,
+//!     eval::MathShape,
+//!     shape::Bounds,
+//!     vm::VmShape,
+//! };
+//!
+//! let (x, y, z) = Tree::axes();
+//! let sphere = (x.square() + y.square() + z.square()).sqrt() - 0.5;
+//! let shape = VmShape::from_tree(&sphere);
+//!
+//! let opts = TextureOptions::default();
+//! let tex = to_texture3d(&shape, Bounds::centered(1.0), [16, 16, 16], &opts)?;
+//! assert!(tex.value(8, 8, 8) < 0.0);
+//! assert!(tex.value(0, 0, 0) > 0.0);
+//! # Ok::<(), fidget::Error>(())
+//! ```
+use crate::{
+    eval::{BulkEvaluator, Shape, TracingEvaluator},
+    shape::Bounds,
+    Error,
+};
+
+/// Options for [`to_texture3d`]
+pub struct TextureOptions {
+    /// Whether to store gradients alongside field values
+    ///
+    /// If this is `true`, each texel is `[v, dx, dy, dz]`; otherwise, it's
+    /// a single value `v`.
+    pub gradients: bool,
+
+    /// If present, build a sparse texture instead of a dense texture
+    pub sparse: Option<SparseOptions>,
+
+    /// Number of threads to use; 8 by default
+    #[cfg(not(target_arch = "wasm32"))]
+    pub threads: std::num::NonZeroUsize,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            gradients: false,
+            sparse: None,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            threads: std::num::NonZeroUsize::new(8).unwrap(),
+        }
+    }
+}
+
+impl TextureOptions {
+    #[cfg(target_arch = "wasm32")]
+    fn threads(&self) -> usize {
+        1
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn threads(&self) -> usize {
+        self.threads.get()
+    }
+}
+
+/// Options for building a sparse texture
+#[derive(Copy, Clone, Debug)]
+pub struct SparseOptions {
+    /// Width of each cubical brick, in texels
+    pub brick_size: usize,
+
+    /// Distance from the surface (in model units) within which bricks are
+    /// stored densely
+    ///
+    /// Bricks which are proven (with interval arithmetic) to be entirely
+    /// farther than this from the surface are stored as a single
+    /// [`Brick::Uniform`] value.
+    pub narrow_band: f32,
+}
+
+/// A single brick in a [`SparseData`] texture
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Brick {
+    /// Brick with a single value for every texel
+    ///
+    /// The value is a conservative bound from interval evaluation: for bricks
+    /// outside the shape, it's the lower bound of the field (so it never
+    /// over-estimates the distance to the surface); for bricks inside the
+    /// shape, it's the upper bound.
+    Uniform(f32),
+    /// Brick which is stored densely, at the given index in
+    /// [`SparseData::bricks`]
+    Dense(usize),
+}
+
+/// Sparse texture storage, split into cubical bricks
+#[derive(Clone, Debug)]
+pub struct SparseData {
+    /// Width of each brick, in texels
+    pub brick_size: usize,
+    /// Number of bricks on each axis
+    pub brick_dims: [usize; 3],
+    /// One item per brick, in X-major order
+    pub table: Vec<Brick>,
+    /// Densely stored bricks, each with `brick_size³ * channels` values in
+    /// X-major order
+    ///
+    /// Bricks on the upper edges of the texture may extend beyond its
+    /// dimensions; those texels are evaluated (outside the original region)
+    /// so that every brick is the same size.
+    pub bricks: Vec<f32>,
+}
+
+/// Storage for a [`Texture3d`]
+#[derive(Clone, Debug)]
+pub enum TextureData {
+    /// Every texel is stored, in X-major order
+    Dense(Vec<f32>),
+    /// Only bricks near the surface are stored
+    Sparse(SparseData),
+}
+
+/// A 3D texture of field values, built by [`to_texture3d`]
+///
+/// # Filtering
+/// Texels are cell-centered: texel `(i, j, k)` holds the field sampled at the
+/// center of the corresponding cell of the region, i.e. normalized texture
+/// coordinate `(i + 0.5) / dims[0]` (and so on for each axis).  This matches
+/// GPU sampling conventions, so a hardware trilinear sampler (with
+/// clamp-to-edge addressing) reconstructs the field using
+/// [`Texture3d::uvw`] to convert from model coordinates.
+#[derive(Clone, Debug)]
+pub struct Texture3d {
+    /// Number of texels on each axis
+    pub dims: [usize; 3],
+    /// Lower corner of the region, in model coordinates
+    pub lower: [f32; 3],
+    /// Upper corner of the region, in model coordinates
+    pub upper: [f32; 3],
+    /// Whether each texel includes gradients (see [`TextureOptions`])
+    pub gradients: bool,
+    /// Minimum and maximum field values in the texture (ignoring NaN)
+    ///
+    /// This can be used to normalize values when converting to a fixed-point
+    /// texture format.
+    pub range: [f32; 2],
+    /// Texel storage
+    pub data: TextureData,
+}
+
+impl Texture3d {
+    /// Number of `f32` values per texel (1, or 4 with gradients)
+    pub fn channels(&self) -> usize {
+        if self.gradients {
+            4
+        } else {
+            1
+        }
+    }
+
+    /// Size of a single texel, in model units
+    pub fn texel_size(&self) -> [f32; 3] {
+        std::array::from_fn(|i| {
+            (self.upper[i] - self.lower[i]) / self.dims[i] as f32
+        })
+    }
+
+    /// Returns the position of a texel's center, in model coordinates
+    pub fn texel_center(&self, i: usize, j: usize, k: usize) -> [f32; 3] {
+        let size = self.texel_size();
+        let index = [i, j, k];
+        std::array::from_fn(|a| {
+            self.lower[a] + (index[a] as f32 + 0.5) * size[a]
+        })
+    }
+
+    /// Converts from model coordinates to normalized texture coordinates
+    pub fn uvw(&self, p: [f32; 3]) -> [f32; 3] {
+        std::array::from_fn(|i| {
+            (p[i] - self.lower[i]) / (self.upper[i] - self.lower[i])
+        })
+    }
+
+    /// Returns the texel at the given position as `[v, dx, dy, dz]`
+    ///
+    /// Gradients are zero if they weren't baked, or if the texel is in a
+    /// [`Brick::Uniform`] brick.
+    ///
+    /// # Panics
+    /// If the position is out of bounds
+    pub fn texel(&self, i: usize, j: usize, k: usize) -> [f32; 4] {
+        assert!(i < self.dims[0] && j < self.dims[1] && k < self.dims[2]);
+        let c = self.channels();
+        let slice = match &self.data {
+            TextureData::Dense(d) => {
+                let index = i + self.dims[0] * (j + self.dims[1] * k);
+                &d[index * c..][..c]
+            }
+            TextureData::Sparse(s) => {
+                let b = s.brick_size;
+                let [bx, by, _] = s.brick_dims;
+                let brick = i / b + bx * (j / b + by * (k / b));
+                match s.table[brick] {
+                    Brick::Uniform(v) => return [v, 0.0, 0.0, 0.0],
+                    Brick::Dense(index) => {
+                        let (i, j, k) = (i % b, j % b, k % b);
+                        let offset = index * b.pow(3) + i + b * (j + b * k);
+                        &s.bricks[offset * c..][..c]
+                    }
+                }
+            }
+        };
+        let mut out = [0.0; 4];
+        out[..c].copy_from_slice(slice);
+        out
+    }
+
+    /// Returns the field value at the given texel
+    ///
+    /// # Panics
+    /// If the position is out of bounds
+    pub fn value(&self, i: usize, j: usize, k: usize) -> f32 {
+        self.texel(i, j, k)[0]
+    }
+}
+
+/// Bakes a shape into a 3D texture
+///
+/// The texture covers `region` with `dims` texels on each axis, sampled at
+/// texel centers (see [`Texture3d`] for details).
+///
+/// Returns an error if the region is invalid, if any dimension is zero, or if
+/// the sparse options have an empty brick size or a negative narrow band.
+pub fn to_texture3d<S: Shape>(
+    shape: &S,
+    region: Bounds<3>,
+    dims: [usize; 3],
+    opts: &TextureOptions,
+) -> Result<Texture3d, Error> {
+    region.check()?;
+    if dims.contains(&0) {
+        return Err(Error::InvalidTexture("dimensions must be non-zero"));
+    }
+    if let Some(s) = &opts.sparse {
+        if s.brick_size == 0 {
+            return Err(Error::InvalidTexture("brick size must be non-zero"));
+        } else if s.narrow_band.is_nan() || s.narrow_band < 0.0 {
+            return Err(Error::InvalidTexture(
+                "narrow band must be non-negative",
+            ));
+        }
+    }
+
+    let lower = region.lower();
+    let upper = region.upper();
+    let mut tex = Texture3d {
+        dims,
+        lower: [lower[0], lower[1], lower[2]],
+        upper: [upper[0], upper[1], upper[2]],
+        gradients: opts.gradients,
+        range: [f32::INFINITY, f32::NEG_INFINITY],
+        data: TextureData::Dense(vec![]),
+    };
+
+    let baker = Baker::new(shape, &tex);
+    let [nx, ny, nz] = dims;
+    let c = tex.channels();
+    let data = match opts.sparse {
+        None => {
+            // One job per Z slice
+            let out = baker.run(nz, opts.threads(), |w, k| {
+                let mut pts = Vec::with_capacity(nx * ny);
+                for j in 0..ny {
+                    for i in 0..nx {
+                        pts.push([i, j, k]);
+                    }
+                }
+                w.eval(&pts)
+            })?;
+            let mut data = vec![0.0; nx * ny * nz * c];
+            for (k, values) in out {
+                data[k * nx * ny * c..][..values.len()]
+                    .copy_from_slice(&values);
+            }
+            TextureData::Dense(data)
+        }
+        Some(s) => {
+            // One job per brick
+            let b = s.brick_size;
+            let brick_dims = [nx.div_ceil(b), ny.div_ceil(b), nz.div_ceil(b)];
+            let [bx, by, bz] = brick_dims;
+            let out = baker.run(bx * by * bz, opts.threads(), |w, index| {
+                let corner = [
+                    (index % bx) * b,
+                    (index / bx % by) * b,
+                    (index / (bx * by)) * b,
+                ];
+                let i = w.interval(corner, b)?;
+                if i.has_nan()
+                    || (i.lower() <= s.narrow_band
+                        && i.upper() >= -s.narrow_band)
+                {
+                    let mut pts = Vec::with_capacity(b.pow(3));
+                    for k in 0..b {
+                        for j in 0..b {
+                            for i in 0..b {
+                                pts.push([
+                                    corner[0] + i,
+                                    corner[1] + j,
+                                    corner[2] + k,
+                                ]);
+                            }
+                        }
+                    }
+                    w.eval(&pts).map(Ok)
+                } else if i.lower() > 0.0 {
+                    Ok(Err(i.lower()))
+                } else {
+                    Ok(Err(i.upper()))
+                }
+            })?;
+            let mut table = vec![Brick::Uniform(0.0); out.len()];
+            let mut bricks = vec![];
+            for (index, v) in out {
+                table[index] = match v {
+                    Ok(values) => {
+                        bricks.extend(values);
+                        Brick::Dense(bricks.len() / (b.pow(3) * c) - 1)
+                    }
+                    Err(v) => Brick::Uniform(v),
+                };
+            }
+            TextureData::Sparse(SparseData {
+                brick_size: b,
+                brick_dims,
+                table,
+                bricks,
+            })
+        }
+    };
+    tex.data = data;
+
+    // Find the value range, only looking at texels within the texture
+    for k in 0..nz {
+        for j in 0..ny {
+            for i in 0..nx {
+                let v = tex.value(i, j, k);
+                if !v.is_nan() {
+                    tex.range[0] = tex.range[0].min(v);
+                    tex.range[1] = tex.range[1].max(v);
+                }
+            }
+        }
+    }
+    Ok(tex)
+}
+
+/// Shared state for baking, with tapes that are used by every worker
+struct Baker<'a, S: Shape> {
+    float_tape: <S::FloatSliceEval as BulkEvaluator>::Tape,
+    grad_tape: Option<<S::GradSliceEval as BulkEvaluator>::Tape>,
+    interval_tape: <S::IntervalEval as TracingEvaluator>::Tape,
+    tex: &'a Texture3d,
+}
+
+/// Per-thread evaluators and scratch buffers
+struct Worker<'a, 'b, S: Shape> {
+    baker: &'b Baker<'a, S>,
+    float_eval: S::FloatSliceEval,
+    grad_eval: S::GradSliceEval,
+    interval_eval: S::IntervalEval,
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    zs: Vec<f32>,
+}
+
+impl<'a, S: Shape> Baker<'a, S> {
+    fn new(shape: &S, tex: &'a Texture3d) -> Self {
+        Self {
+            float_tape: shape.float_slice_tape(Default::default()),
+            grad_tape: tex
+                .gradients
+                .then(|| shape.grad_slice_tape(Default::default())),
+            interval_tape: shape.interval_tape(Default::default()),
+            tex,
+        }
+    }
+
+    /// Runs `jobs` jobs on a pool of threads, returning `(index, output)`
+    /// pairs in arbitrary order
+    fn run<T: Send>(
+        &self,
+        jobs: usize,
+        threads: usize,
+        f: impl Fn(&mut Worker<S>, usize) -> Result<T, Error> + Sync,
+    ) -> Result<Vec<(usize, T)>, Error> {
+        crate::exec::run_indexed(
+            jobs,
+            threads,
+            || Worker {
+                baker: self,
+                float_eval: S::new_float_slice_eval(),
+                grad_eval: S::new_grad_slice_eval(),
+                interval_eval: S::new_interval_eval(),
+                xs: vec![],
+                ys: vec![],
+                zs: vec![],
+            },
+            f,
+        )
+    }
+}
+
+impl<S: Shape> Worker<'_, '_, S> {
+    /// Evaluates the field at the given texel centers
+    fn eval(&mut self, pts: &[[usize; 3]]) -> Result<Vec<f32>, Error> {
+        self.xs.clear();
+        self.ys.clear();
+        self.zs.clear();
+        for &[i, j, k] in pts {
+            let [x, y, z] = self.baker.tex.texel_center(i, j, k);
+            self.xs.push(x);
+            self.ys.push(y);
+            self.zs.push(z);
+        }
+        if let Some(tape) = &self.baker.grad_tape {
+            let out =
+                self.grad_eval.eval(tape, &self.xs, &self.ys, &self.zs)?;
+            Ok(out.iter().flat_map(|g| [g.v, g.dx, g.dy, g.dz]).collect())
+        } else {
+            let tape = &self.baker.float_tape;
+            let out =
+                self.float_eval.eval(tape, &self.xs, &self.ys, &self.zs)?;
+            Ok(out.to_vec())
+        }
+    }
+
+    /// Evaluates the field over the texel centers of an `n³` brick
+    fn interval(
+        &mut self,
+        corner: [usize; 3],
+        n: usize,
+    ) -> Result<crate::types::Interval, Error> {
+        let [i, j, k] = corner;
+        let lo = self.baker.tex.texel_center(i, j, k);
+        let hi = self.baker.tex.texel_center(i + n - 1, j + n - 1, k + n - 1);
+        let (out, _) = self.interval_eval.eval(
+            &self.baker.interval_tape,
+            [lo[0], hi[0]],
+            [lo[1], hi[1]],
+            [lo[2], hi[2]],
+        )?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Tree, eval::MathShape, vm::VmShape};
+
+    fn sphere(r: f64) -> VmShape {
+        let (x, y, z) = Tree::axes();
+        let t = (x.square() + y.square() + z.square()).sqrt() - r;
+        VmShape::from_tree(&t)
+    }
+
+    #[test]
+    fn texture_dense() {
+        let shape = sphere(0.5);
+        let opts = TextureOptions::default();
+        let tex = to_texture3d(&shape, Bounds::centered(1.0), [8, 4, 4], &opts)
+            .unwrap();
+        assert_eq!(tex.channels(), 1);
+        assert_eq!(tex.texel_size(), [0.25, 0.5, 0.5]);
+        assert_eq!(tex.texel_center(0, 0, 0), [-0.875, -0.75, -0.75]);
+        assert_eq!(tex.uvw([0.0, 0.0, 0.0]), [0.5, 0.5, 0.5]);
+        let TextureData::Dense(d) = &tex.data else {
+            panic!("expected dense data")
+        };
+        assert_eq!(d.len(), 8 * 4 * 4);
+
+        for k in 0..4 {
+            for j in 0..4 {
+                for i in 0..8 {
+                    let [x, y, z] = tex.texel_center(i, j, k);
+                    let v = (x * x + y * y + z * z).sqrt() - 0.5;
+                    assert!((tex.value(i, j, k) - v).abs() < 1e-6);
+                }
+            }
+        }
+        assert!(tex.range[0] < 0.0 && tex.range[1] > 0.0);
+    }
+
+    #[test]
+    fn texture_gradients() {
+        let shape = sphere(0.5);
+        let opts = TextureOptions {
+            gradients: true,
+            ..Default::default()
+        };
+        let tex = to_texture3d(&shape, Bounds::centered(1.0), [4, 4, 4], &opts)
+            .unwrap();
+        assert_eq!(tex.channels(), 4);
+        let [v, dx, dy, dz] = tex.texel(3, 3, 3);
+        let [x, y, z] = tex.texel_center(3, 3, 3);
+        let r = (x * x + y * y + z * z).sqrt();
+        assert!((v - (r - 0.5)).abs() < 1e-6);
+        assert!((dx - x / r).abs() < 1e-6);
+        assert!((dy - y / r).abs() < 1e-6);
+        assert!((dz - z / r).abs() < 1e-6);
+    }
+
+    #[test]
+    fn texture_sparse() {
+        let shape = sphere(0.5);
+        let dense = to_texture3d(
+            &shape,
+            Bounds::centered(1.0),
+            [32, 32, 32],
+            &TextureOptions::default(),
+        )
+        .unwrap();
+        for threads in [1, 4] {
+            let opts = TextureOptions {
+                sparse: Some(SparseOptions {
+                    brick_size: 4,
+                    narrow_band: 0.1,
+                }),
+                threads: threads.try_into().unwrap(),
+                ..Default::default()
+            };
+            let sparse = to_texture3d(
+                &shape,
+                Bounds::centered(1.0),
+                [32, 32, 32],
+                &opts,
+            )
+            .unwrap();
+            let TextureData::Sparse(s) = &sparse.data else {
+                panic!("expected sparse data")
+            };
+            assert_eq!(s.brick_dims, [8, 8, 8]);
+            assert_eq!(s.table.len(), 512);
+            let count = s
+                .table
+                .iter()
+                .filter(|b| matches!(b, Brick::Dense(..)))
+                .count();
+            assert!(count > 0 && count < 512);
+            assert_eq!(s.bricks.len(), count * 64);
+
+            // Uniform bricks are conservative, and dense bricks are exact
+            for k in 0..32 {
+                for j in 0..32 {
+                    for i in 0..32 {
+                        let d = dense.value(i, j, k);
+                        let v = sparse.value(i, j, k);
+                        if d > 0.0 {
+                            assert!(v <= d && v > 0.0);
+                        } else {
+                            assert!(v >= d && v < 0.0);
+                        }
+                        if d.abs() < 0.1 {
+                            assert_eq!(v, d);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn texture_errors() {
+        let shape = sphere(0.5);
+        let opts = TextureOptions::default();
+        assert!(matches!(
+            to_texture3d(&shape, Bounds::centered(1.0), [4, 0, 4], &opts),
+            Err(Error::InvalidTexture(..))
+        ));
+        assert!(matches!(
+            to_texture3d(
+                &shape,
+                Bounds::new([0.0; 3].into(), -1.0),
+                [4, 4, 4],
+                &opts
+            ),
+            Err(Error::InvalidBounds)
+        ));
+        let opts = TextureOptions {
+            sparse: Some(SparseOptions {
+                brick_size: 0,
+                narrow_band: 0.0,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            to_texture3d(&shape, Bounds::centered(1.0), [4, 4, 4], &opts),
+            Err(Error::InvalidTexture(..))
+        ));
+    }
+}
@@ -679,3 +679,99 @@ impl<const N: usize> RegisterAllocator<N> {
         self.op_out_only(out, |out| RegOp::Input(out, i));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{compiler::SsaTape, Context};
+
+    /// Checks that spilling never emits a redundant `Load` (of a value that's
+    /// already in the register) or a dead `Store` (which is never loaded)
+    fn check_spills(tape: &RegTape) {
+        let mut held = vec![UNASSIGNED; u8::MAX as usize];
+        for op in tape.iter().rev() {
+            match *op {
+                RegOp::Load(reg, mem) => {
+                    assert_ne!(held[reg as usize], mem, "redundant load");
+                    held[reg as usize] = mem;
+                }
+                RegOp::Store(reg, mem) => {
+                    held.iter_mut().filter(|m| **m == mem).for_each(|m| {
+                        *m = UNASSIGNED;
+                    });
+                    held[reg as usize] = mem;
+                }
+                RegOp::Input(out, ..)
+                | RegOp::CopyImm(out, ..)
+                | RegOp::NegReg(out, ..)
+                | RegOp::AbsReg(out, ..)
+                | RegOp::RecipReg(out, ..)
+                | RegOp::SqrtReg(out, ..)
+                | RegOp::SquareReg(out, ..)
+                | RegOp::FloorReg(out, ..)
+                | RegOp::CeilReg(out, ..)
+                | RegOp::RoundReg(out, ..)
+                | RegOp::CopyReg(out, ..)
+                | RegOp::SinReg(out, ..)
+                | RegOp::CosReg(out, ..)
+                | RegOp::TanReg(out, ..)
+                | RegOp::AsinReg(out, ..)
+                | RegOp::AcosReg(out, ..)
+                | RegOp::AtanReg(out, ..)
+                | RegOp::ExpReg(out, ..)
+                | RegOp::LnReg(out, ..)
+                | RegOp::NotReg(out, ..)
+                | RegOp::AddRegImm(out, ..)
+                | RegOp::MulRegImm(out, ..)
+                | RegOp::DivRegImm(out, ..)
+                | RegOp::DivImmReg(out, ..)
+                | RegOp::SubImmReg(out, ..)
+                | RegOp::SubRegImm(out, ..)
+                | RegOp::ModRegImm(out, ..)
+                | RegOp::ModImmReg(out, ..)
+                | RegOp::AtanRegImm(out, ..)
+                | RegOp::AtanImmReg(out, ..)
+                | RegOp::MinRegImm(out, ..)
+                | RegOp::MaxRegImm(out, ..)
+                | RegOp::AndRegImm(out, ..)
+                | RegOp::OrRegImm(out, ..)
+                | RegOp::CompareRegImm(out, ..)
+                | RegOp::CompareImmReg(out, ..)
+                | RegOp::AddRegReg(out, ..)
+                | RegOp::MulRegReg(out, ..)
+                | RegOp::DivRegReg(out, ..)
+                | RegOp::SubRegReg(out, ..)
+                | RegOp::ModRegReg(out, ..)
+                | RegOp::AtanRegReg(out, ..)
+                | RegOp::MinRegReg(out, ..)
+                | RegOp::MaxRegReg(out, ..)
+                | RegOp::AndRegReg(out, ..)
+                | RegOp::OrRegReg(out, ..)
+                | RegOp::CompareRegReg(out, ..) => {
+                    held[out as usize] = UNASSIGNED;
+                }
+            }
+        }
+        let mut live = vec![false; tape.slot_count()];
+        for op in tape.iter() {
+            match *op {
+                RegOp::Load(_, mem) => live[mem as usize] = true,
+                RegOp::Store(_, mem) => {
+                    assert!(live[mem as usize], "dead store");
+                    live[mem as usize] = false;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn test_spills() {
+        const COLONNADE: &str = include_str!("../../../../models/colonnade.vm");
+        let (ctx, root) = Context::from_text(COLONNADE.as_bytes()).unwrap();
+        let ssa = SsaTape::new(&ctx, root).unwrap();
+        let tape = RegTape::new::<8>(&ssa);
+        assert!(tape.iter().any(|op| matches!(op, RegOp::Load(..))));
+        check_spills(&tape);
+    }
+}
@@ -0,0 +1,322 @@
+//! Direct construction of SSA tapes, without a [`Context`](crate::Context)
+use crate::{
+    compiler::{SsaOp, SsaTape, MAX_INPUTS, MAX_TAPE_INDEX},
+    Error,
+};
+use std::{collections::HashSet, sync::Arc};
+
+/// Builder for an [`SsaTape`], for compilers which emit operations directly
+///
+/// Building a [`Context`](crate::Context) and then flattening it into a tape
+/// keeps two copies of the program in memory; this builder only stores the
+/// tape itself.
+///
+/// Operations are pushed in evaluation order, and each one must write to the
+/// next unused slot (see [`next_slot`](Self::next_slot)), so slots are
+/// numbered `0, 1, 2, ...` as they're defined.  Arguments must refer to slots
+/// which have already been defined.  The last operation pushed is the tape's
+/// output.
+///
+/// ```
+/// use fidget::{
+///     compiler::{SsaOp, SsaTapeBuilder},
+///     eval::{Shape, TracingEvaluator},
+///     vm::{SsaShape, VmShape},
+/// };
+///
+/// // max(x + y, 0.5)
+/// let mut b = SsaTapeBuilder::new();
+/// let x = b.push(SsaOp::Input(b.next_slot(), 0))?;
+/// let y = b.push(SsaOp::Input(b.next_slot(), 1))?;
+/// let s = b.push(SsaOp::AddRegReg(b.next_slot(), x, y))?;
+/// b.push(SsaOp::MaxRegImm(b.next_slot(), s, 0.5))?;
+/// let ssa = b.finish()?;
+/// assert_eq!(ssa.choice_count, 1);
+///
+/// let shape = VmShape::from_ssa(ssa)?;
+/// let mut eval = VmShape::new_point_eval();
+/// let (v, _) = eval.eval(&shape, 1.0, 2.0, 0.0)?;
+/// assert_eq!(v, 3.0);
+///
+/// // Arguments must be defined before they're used
+/// let mut b = SsaTapeBuilder::new();
+/// assert!(b.push(SsaOp::NegReg(0, 1)).is_err());
+/// # Ok::<(), fidget::Error>(())
+/// ```
+#[derive(Default)]
+pub struct SsaTapeBuilder {
+    /// Operations, in evaluation order
+    tape: Vec<SsaOp>,
+
+    /// Number of choice operations in the tape
+    choice_count: usize,
+
+    /// Names of the tape's inputs, or `None` to use `X`, `Y`, and `Z`
+    inputs: Option<Arc<[Arc<str>]>>,
+}
+
+impl SsaTapeBuilder {
+    /// Builds a new tape with inputs `X`, `Y`, and `Z`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a new tape with an explicit list of inputs
+    ///
+    /// [`SsaOp::Input`] operations index into this list.  Returns
+    /// [`Error::DuplicateName`] if a name is repeated, and
+    /// [`Error::TooManyInputs`] if there are more than [`MAX_INPUTS`].
+    pub fn with_inputs(inputs: &[&str]) -> Result<Self, Error> {
+        if inputs.len() > MAX_INPUTS {
+            return Err(Error::TooManyInputs(inputs.len()));
+        }
+        let mut seen = HashSet::new();
+        if !inputs.iter().all(|name| seen.insert(name)) {
+            return Err(Error::DuplicateName);
+        }
+        Ok(Self {
+            inputs: Some(inputs.iter().map(|&s| Arc::from(s)).collect()),
+            ..Self::default()
+        })
+    }
+
+    /// Reserves capacity for at least `n` more operations
+    pub fn reserve(&mut self, n: usize) {
+        self.tape.reserve(n);
+    }
+
+    /// Returns the number of operations pushed so far
+    pub fn len(&self) -> usize {
+        self.tape.len()
+    }
+
+    /// Checks whether no operations have been pushed
+    pub fn is_empty(&self) -> bool {
+        self.tape.is_empty()
+    }
+
+    /// Returns the slot which must be written by the next operation
+    ///
+    /// # Panics
+    /// If the tape is already full (see [`MAX_TAPE_INDEX`]); in that case,
+    /// [`push`](Self::push) would also fail.
+    pub fn next_slot(&self) -> u32 {
+        u32::try_from(self.tape.len()).unwrap()
+    }
+
+    /// Pushes an operation onto the tape, returning its output slot
+    ///
+    /// Returns [`Error::InvalidTape`] if the operation doesn't write to
+    /// [`next_slot`](Self::next_slot), if it reads a slot which isn't yet
+    /// defined, or if it reads an input which doesn't exist.  Returns
+    /// [`Error::TooManySlots`] or [`Error::TooManyChoices`] if the tape is
+    /// full.
+    pub fn push(&mut self, op: SsaOp) -> Result<u32, Error> {
+        if self.tape.len() >= MAX_TAPE_INDEX {
+            return Err(Error::TooManySlots(self.tape.len()));
+        }
+        let mut tmp = op;
+        let (out, lhs, rhs) = slots_mut(&mut tmp);
+        if *out != self.next_slot() {
+            return Err(Error::InvalidTape("SSA output is not the next slot"));
+        }
+        if [lhs, rhs].into_iter().flatten().any(|i| *i >= *out) {
+            return Err(Error::InvalidTape(
+                "SSA slot is used before definition",
+            ));
+        }
+        if let SsaOp::Input(_, i) = op {
+            let count = self.inputs.as_ref().map(|i| i.len()).unwrap_or(3);
+            if i as usize >= count {
+                return Err(Error::InvalidTape("SSA input is out of range"));
+            }
+        }
+        if op.has_choice() {
+            if self.choice_count >= MAX_TAPE_INDEX {
+                return Err(Error::TooManyChoices(self.choice_count));
+            }
+            self.choice_count += 1;
+        }
+        self.tape.push(op);
+        Ok(op.output())
+    }
+
+    /// Finishes building the tape, using the last operation as its output
+    ///
+    /// Operations which don't contribute to the output are removed, and slots
+    /// are renumbered so that the output is slot 0 (as expected by
+    /// [`SsaTape`]).  The tape isn't otherwise optimized; use a
+    /// [`Peephole`](crate::compiler::Peephole) optimizer for that.
+    ///
+    /// Returns [`Error::InvalidTape`] if the tape is empty.
+    pub fn finish(self) -> Result<SsaTape, Error> {
+        let Self {
+            mut tape, inputs, ..
+        } = self;
+        if tape.is_empty() {
+            return Err(Error::InvalidTape("SSA tape is empty"));
+        }
+
+        // Walk backwards from the output, marking every slot that it uses.
+        // Arguments always precede their users, so one pass is enough.
+        const DEAD: u32 = u32::MAX;
+        let mut bind = vec![DEAD; tape.len()];
+        *bind.last_mut().unwrap() = 0;
+        for i in (0..tape.len()).rev() {
+            if bind[i] != DEAD {
+                let (_, lhs, rhs) = slots_mut(&mut tape[i]);
+                for a in [lhs, rhs].into_iter().flatten() {
+                    bind[*a as usize] = 0;
+                }
+            }
+        }
+
+        // Number the live slots in reverse-evaluation order, so that the
+        // output is slot 0
+        for (i, b) in bind.iter_mut().rev().filter(|b| **b != DEAD).enumerate()
+        {
+            *b = i as u32;
+        }
+
+        // Compact the tape in place, with the output first
+        tape.reverse();
+        let mut choice_count = 0;
+        let mut n = 0;
+        for i in 0..tape.len() {
+            let mut op = tape[i];
+            let (out, lhs, rhs) = slots_mut(&mut op);
+            if bind[*out as usize] == DEAD {
+                continue;
+            }
+            for s in [Some(out), lhs, rhs].into_iter().flatten() {
+                *s = bind[*s as usize];
+            }
+            choice_count += op.has_choice() as usize;
+            tape[n] = op;
+            n += 1;
+        }
+        tape.truncate(n);
+
+        Ok(SsaTape {
+            tape,
+            choice_count,
+            names: Default::default(),
+            inputs,
+        })
+    }
+}
+
+/// Returns mutable references to an operation's output and argument slots
+fn slots_mut(op: &mut SsaOp) -> (&mut u32, Option<&mut u32>, Option<&mut u32>) {
+    match op {
+        SsaOp::Input(out, ..) | SsaOp::CopyImm(out, ..) => (out, None, None),
+        SsaOp::NegReg(out, a)
+        | SsaOp::AbsReg(out, a)
+        | SsaOp::RecipReg(out, a)
+        | SsaOp::SqrtReg(out, a)
+        | SsaOp::SquareReg(out, a)
+        | SsaOp::FloorReg(out, a)
+        | SsaOp::CeilReg(out, a)
+        | SsaOp::RoundReg(out, a)
+        | SsaOp::CopyReg(out, a)
+        | SsaOp::SinReg(out, a)
+        | SsaOp::CosReg(out, a)
+        | SsaOp::TanReg(out, a)
+        | SsaOp::AsinReg(out, a)
+        | SsaOp::AcosReg(out, a)
+        | SsaOp::AtanReg(out, a)
+        | SsaOp::ExpReg(out, a)
+        | SsaOp::LnReg(out, a)
+        | SsaOp::NotReg(out, a)
+        | SsaOp::AddRegImm(out, a, ..)
+        | SsaOp::MulRegImm(out, a, ..)
+        | SsaOp::DivRegImm(out, a, ..)
+        | SsaOp::DivImmReg(out, a, ..)
+        | SsaOp::SubImmReg(out, a, ..)
+        | SsaOp::SubRegImm(out, a, ..)
+        | SsaOp::AtanRegImm(out, a, ..)
+        | SsaOp::AtanImmReg(out, a, ..)
+        | SsaOp::MinRegImm(out, a, ..)
+        | SsaOp::MaxRegImm(out, a, ..)
+        | SsaOp::CompareRegImm(out, a, ..)
+        | SsaOp::CompareImmReg(out, a, ..)
+        | SsaOp::ModRegImm(out, a, ..)
+        | SsaOp::ModImmReg(out, a, ..)
+        | SsaOp::AndRegImm(out, a, ..)
+        | SsaOp::OrRegImm(out, a, ..) => (out, Some(a), None),
+        SsaOp::AddRegReg(out, a, b)
+        | SsaOp::MulRegReg(out, a, b)
+        | SsaOp::DivRegReg(out, a, b)
+        | SsaOp::SubRegReg(out, a, b)
+        | SsaOp::AtanRegReg(out, a, b)
+        | SsaOp::MinRegReg(out, a, b)
+        | SsaOp::MaxRegReg(out, a, b)
+        | SsaOp::CompareRegReg(out, a, b)
+        | SsaOp::ModRegReg(out, a, b)
+        | SsaOp::AndRegReg(out, a, b)
+        | SsaOp::OrRegReg(out, a, b) => (out, Some(a), Some(b)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::{MathShape, Shape, TracingEvaluator},
+        vm::{SsaShape, VmShape},
+    };
+
+    #[test]
+    fn test_builder() {
+        // min(x * y, z) - 1, with an unused operation
+        let mut b = SsaTapeBuilder::new();
+        let x = b.push(SsaOp::Input(b.next_slot(), 0)).unwrap();
+        let y = b.push(SsaOp::Input(b.next_slot(), 1)).unwrap();
+        let z = b.push(SsaOp::Input(b.next_slot(), 2)).unwrap();
+        b.push(SsaOp::SinReg(b.next_slot(), x)).unwrap();
+        let m = b.push(SsaOp::MulRegReg(b.next_slot(), x, y)).unwrap();
+        let m = b.push(SsaOp::MinRegReg(b.next_slot(), m, z)).unwrap();
+        b.push(SsaOp::SubRegImm(b.next_slot(), m, 1.0)).unwrap();
+        assert_eq!(b.len(), 7);
+
+        // Invalid operations are rejected, without changing the tape
+        let next = b.next_slot();
+        for op in [
+            SsaOp::NegReg(next + 1, x),
+            SsaOp::NegReg(next, next),
+            SsaOp::AddRegReg(next, x, next + 3),
+            SsaOp::Input(next, 3),
+        ] {
+            assert!(matches!(b.push(op), Err(Error::InvalidTape(..))));
+        }
+        assert_eq!(b.len(), 7);
+
+        let ssa = b.finish().unwrap();
+        assert_eq!(ssa.len(), 6);
+        assert_eq!(ssa.choice_count, 1);
+        assert_eq!(ssa.tape[0].output(), 0);
+        ssa.check().unwrap();
+
+        let (tx, ty, tz) = Tree::axes();
+        let expected = VmShape::from_tree(&((tx * ty).min(tz) - 1.0));
+        let shape = VmShape::from_ssa(ssa).unwrap();
+        let mut eval = VmShape::new_point_eval();
+        for (x, y, z) in [(1.0, 2.0, 3.0), (2.0, 3.0, 1.0), (-1.0, 0.5, 0.0)] {
+            let (a, _) = eval.eval(&shape, x, y, z).unwrap();
+            let (b, _) = eval.eval(&expected, x, y, z).unwrap();
+            assert_eq!(a, b);
+        }
+
+        assert!(SsaTapeBuilder::new().finish().is_err());
+        assert!(matches!(
+            SsaTapeBuilder::with_inputs(&["a", "a"]),
+            Err(Error::DuplicateName)
+        ));
+        let mut b = SsaTapeBuilder::with_inputs(&["a"]).unwrap();
+        assert!(b.push(SsaOp::Input(0, 1)).is_err());
+        b.push(SsaOp::Input(0, 0)).unwrap();
+        let ssa = b.finish().unwrap();
+        assert_eq!(ssa.input_count(), 1);
+    }
+}
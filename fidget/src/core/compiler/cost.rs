@@ -0,0 +1,214 @@
+//! Per-opcode cost model for register-allocated tapes
+use crate::compiler::{RegOp, RegTape};
+
+/// Kind of evaluation, used when estimating costs
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Flavor {
+    /// Single-point tracing evaluation
+    Point,
+    /// Single-interval tracing evaluation
+    Interval,
+    /// Bulk evaluation of many points
+    FloatSlice,
+    /// Bulk evaluation of many points with partial derivatives
+    GradSlice,
+}
+
+/// Evaluation backend
+///
+/// This is used when estimating costs, and to select a backend at runtime (see
+/// [`DynShape`](crate::dynamic::DynShape)).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Backend {
+    /// The interpreter in [`fidget::vm`](crate::vm)
+    Vm,
+    /// The JIT compiler in `fidget::jit`
+    Jit,
+}
+
+impl Default for Backend {
+    /// Returns [`Backend::Jit`] if it's available, or [`Backend::Vm`] otherwise
+    ///
+    /// The JIT is available when the `jit` feature is enabled (except on
+    /// `wasm32`) and the CPU supports it (see `fidget::jit::is_supported`).
+    fn default() -> Self {
+        #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+        if crate::jit::is_supported() {
+            return Backend::Jit;
+        }
+        Backend::Vm
+    }
+}
+
+/// Broad classes of operations with similar costs
+#[derive(Copy, Clone)]
+enum OpClass {
+    /// Inputs and register copies
+    Copy,
+    /// Loads and stores to memory slots
+    Memory,
+    /// Addition, subtraction, negation, and absolute value
+    Add,
+    /// Multiplication and squaring
+    Mul,
+    /// Operations which may record a choice, and comparisons
+    Choice,
+    /// Division, reciprocal, and square root
+    Div,
+    /// Rounding and modulo
+    Round,
+    /// Trigonometric, exponential, and logarithmic functions
+    Transcendental,
+}
+
+impl OpClass {
+    fn of(op: &RegOp) -> Self {
+        match op {
+            RegOp::Input(..) | RegOp::CopyReg(..) | RegOp::CopyImm(..) => {
+                OpClass::Copy
+            }
+            RegOp::Load(..) | RegOp::Store(..) => OpClass::Memory,
+            RegOp::NegReg(..)
+            | RegOp::AbsReg(..)
+            | RegOp::AddRegImm(..)
+            | RegOp::SubImmReg(..)
+            | RegOp::SubRegImm(..)
+            | RegOp::AddRegReg(..)
+            | RegOp::SubRegReg(..) => OpClass::Add,
+            RegOp::SquareReg(..)
+            | RegOp::MulRegImm(..)
+            | RegOp::MulRegReg(..) => OpClass::Mul,
+            RegOp::NotReg(..)
+            | RegOp::MinRegImm(..)
+            | RegOp::MaxRegImm(..)
+            | RegOp::AndRegImm(..)
+            | RegOp::OrRegImm(..)
+            | RegOp::CompareRegImm(..)
+            | RegOp::CompareImmReg(..)
+            | RegOp::MinRegReg(..)
+            | RegOp::MaxRegReg(..)
+            | RegOp::AndRegReg(..)
+            | RegOp::OrRegReg(..)
+            | RegOp::CompareRegReg(..) => OpClass::Choice,
+            RegOp::RecipReg(..)
+            | RegOp::SqrtReg(..)
+            | RegOp::DivRegImm(..)
+            | RegOp::DivImmReg(..)
+            | RegOp::DivRegReg(..) => OpClass::Div,
+            RegOp::FloorReg(..)
+            | RegOp::CeilReg(..)
+            | RegOp::RoundReg(..)
+            | RegOp::ModRegReg(..)
+            | RegOp::ModRegImm(..)
+            | RegOp::ModImmReg(..) => OpClass::Round,
+            RegOp::SinReg(..)
+            | RegOp::CosReg(..)
+            | RegOp::TanReg(..)
+            | RegOp::AsinReg(..)
+            | RegOp::AcosReg(..)
+            | RegOp::AtanReg(..)
+            | RegOp::ExpReg(..)
+            | RegOp::LnReg(..)
+            | RegOp::AtanRegReg(..)
+            | RegOp::AtanRegImm(..)
+            | RegOp::AtanImmReg(..) => OpClass::Transcendental,
+        }
+    }
+}
+
+/// Cost of each [`OpClass`], indexed by backend and flavor
+///
+/// These numbers were calibrated against the `cost` benchmark (on the `sin`
+/// and `sphere` models, on an x86-64 Linux machine), and are roughly
+/// nanoseconds per point.  The VM's slice rows were also fit against the
+/// `prospero` model, because its SIMD evaluators make choice operations
+/// (which record a trace per point) relatively more expensive.  The VM pays
+/// for instruction dispatch on every operation, which is amortized over many
+/// points in the slice evaluators.
+/// The JIT calls out to Rust functions for transcendental operations, which
+/// is especially expensive for slices, because every SIMD lane makes its own
+/// call.
+const COSTS: [[[f32; 8]; 4]; 2] = [
+    // Vm
+    [
+        [2.0, 3.0, 3.0, 3.0, 3.5, 4.7, 3.5, 8.7], // Point
+        [2.0, 3.0, 4.0, 8.0, 6.0, 18.0, 6.0, 27.0], // Interval
+        [0.16, 0.16, 0.18, 0.18, 0.45, 0.23, 0.23, 2.9], // FloatSlice
+        [0.7, 0.7, 0.8, 1.1, 1.15, 1.4, 0.9, 10.2], // GradSlice
+    ],
+    // Jit
+    [
+        [0.5, 0.5, 0.5, 0.5, 0.8, 1.4, 1.0, 8.1], // Point
+        [0.5, 0.5, 0.8, 1.5, 1.5, 3.6, 2.0, 24.2], // Interval
+        [0.01, 0.02, 0.03, 0.03, 0.05, 0.18, 0.1, 170.0], // FloatSlice
+        [0.3, 0.3, 0.6, 1.2, 0.8, 2.1, 1.0, 28.0], // GradSlice
+    ],
+];
+
+impl RegOp {
+    /// Returns the estimated cost of this operation
+    ///
+    /// Costs are in arbitrary units (roughly nanoseconds per point), and are
+    /// only meaningful relative to each other; see
+    /// [`RegTape::estimated_cost`].
+    pub fn estimated_cost(&self, flavor: Flavor, backend: Backend) -> f32 {
+        COSTS[backend as usize][flavor as usize][OpClass::of(self) as usize]
+    }
+}
+
+impl RegTape {
+    /// Estimates the cost of evaluating this tape
+    ///
+    /// The estimate is the sum of per-opcode costs from a fixed table for each
+    /// backend and evaluation flavor, in arbitrary units (roughly nanoseconds
+    /// per point, or per element for slice evaluators).  It's meant for
+    /// comparing tapes and picking strategies (e.g. whether a tape is cheap
+    /// enough to skip further simplification), rather than as a precise
+    /// prediction of run time: very large tapes tend to run 2-3× slower than
+    /// predicted, because they don't fit in cache.
+    pub fn estimated_cost(&self, flavor: Flavor, backend: Backend) -> f64 {
+        self.iter()
+            .map(|op| op.estimated_cost(flavor, backend) as f64)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Context, vm::VmData};
+
+    #[test]
+    fn test_estimated_cost() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let sum = ctx.add(x, y).unwrap();
+        let sin = ctx.sin(sum).unwrap();
+
+        let a = VmData::<255>::new(&ctx, sum).unwrap();
+        let b = VmData::<255>::new(&ctx, sin).unwrap();
+        for flavor in [
+            Flavor::Point,
+            Flavor::Interval,
+            Flavor::FloatSlice,
+            Flavor::GradSlice,
+        ] {
+            let vm = a.estimated_cost(flavor, Backend::Vm);
+            let jit = a.estimated_cost(flavor, Backend::Jit);
+            assert!(vm > 0.0);
+            assert!(jit < vm);
+            assert!(b.estimated_cost(flavor, Backend::Vm) > vm);
+        }
+
+        // Bulk evaluation amortizes per-operation overhead
+        assert!(
+            b.estimated_cost(Flavor::FloatSlice, Backend::Vm)
+                < b.estimated_cost(Flavor::Point, Backend::Vm)
+        );
+        assert!(
+            b.estimated_cost(Flavor::Interval, Backend::Vm)
+                > b.estimated_cost(Flavor::Point, Backend::Vm)
+        );
+    }
+}
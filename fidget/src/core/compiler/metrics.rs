@@ -0,0 +1,266 @@
+//! Static metrics for register-allocated tapes
+use crate::compiler::{RegOp, RegTape};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Summary statistics for a [`RegTape`]
+///
+/// These are computed by a single pass over the tape, and are meant for
+/// comparing planning strategies (e.g. different register limits) or deciding
+/// when a shape is large enough to be worth splitting.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TapeMetrics {
+    /// Number of instructions of each opcode, keyed by [`RegOp`] variant name
+    pub ops: BTreeMap<&'static str, usize>,
+    /// Number of [`RegOp::Load`] instructions (reloading spilled values)
+    pub loads: usize,
+    /// Number of [`RegOp::Store`] instructions (spilling values to memory)
+    pub stores: usize,
+    /// Number of distinct memory slots used by loads and stores
+    pub memory_slots: usize,
+    /// Number of instructions which may record a choice (`min`, `max`, `and`,
+    /// and `or`)
+    pub choices: usize,
+    /// Largest number of registers which are live at the same time
+    pub peak_registers: usize,
+}
+
+impl RegTape {
+    /// Computes summary metrics for this tape
+    ///
+    /// ```
+    /// # use fidget::{context::Context, vm::VmData};
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let sum = ctx.add(x, y).unwrap();
+    /// let out = ctx.min(sum, x).unwrap();
+    ///
+    /// let data = VmData::<255>::new(&ctx, out).unwrap();
+    /// let m = data.metrics();
+    /// assert_eq!(m.ops["Input"], 2);
+    /// assert_eq!(m.choices, 1);
+    /// assert_eq!(m.loads + m.stores, 0);
+    /// assert_eq!(m.peak_registers, 2);
+    /// ```
+    pub fn metrics(&self) -> TapeMetrics {
+        let mut out = TapeMetrics::default();
+        let mut slots = BTreeSet::new();
+
+        // Walk the tape root-first (i.e. backwards in evaluation order): a
+        // register becomes live when it's read, and dies when it's written.
+        let mut live = [false; 256];
+        let mut count = 0;
+        for op in self.iter() {
+            let (name, w, r) = op.summary();
+            *out.ops.entry(name).or_default() += 1;
+            match *op {
+                RegOp::Load(_, mem) => {
+                    out.loads += 1;
+                    slots.insert(mem);
+                }
+                RegOp::Store(_, mem) => {
+                    out.stores += 1;
+                    slots.insert(mem);
+                }
+                RegOp::MinRegImm(..)
+                | RegOp::MaxRegImm(..)
+                | RegOp::AndRegImm(..)
+                | RegOp::OrRegImm(..)
+                | RegOp::MinRegReg(..)
+                | RegOp::MaxRegReg(..)
+                | RegOp::AndRegReg(..)
+                | RegOp::OrRegReg(..) => out.choices += 1,
+                _ => (),
+            }
+
+            if let Some(w) = w {
+                // The root's output is live when evaluation finishes
+                if !std::mem::replace(&mut live[w as usize], true) {
+                    count += 1;
+                }
+                out.peak_registers = out.peak_registers.max(count);
+                live[w as usize] = false;
+                count -= 1;
+            }
+            for r in r.into_iter().flatten() {
+                if !std::mem::replace(&mut live[r as usize], true) {
+                    count += 1;
+                }
+            }
+            out.peak_registers = out.peak_registers.max(count);
+        }
+        out.memory_slots = slots.len();
+        out
+    }
+}
+
+impl RegOp {
+    /// Returns the opcode name, and the registers written and read
+    fn summary(&self) -> (&'static str, Option<u8>, [Option<u8>; 2]) {
+        match *self {
+            RegOp::Input(out, _) => ("Input", Some(out), [None; 2]),
+            RegOp::CopyImm(out, _) => ("CopyImm", Some(out), [None; 2]),
+            RegOp::Load(reg, _) => ("Load", Some(reg), [None; 2]),
+            RegOp::Store(reg, _) => ("Store", None, [Some(reg), None]),
+
+            RegOp::NegReg(out, arg) => ("NegReg", Some(out), [Some(arg), None]),
+            RegOp::AbsReg(out, arg) => ("AbsReg", Some(out), [Some(arg), None]),
+            RegOp::RecipReg(out, arg) => {
+                ("RecipReg", Some(out), [Some(arg), None])
+            }
+            RegOp::SqrtReg(out, arg) => {
+                ("SqrtReg", Some(out), [Some(arg), None])
+            }
+            RegOp::SquareReg(out, arg) => {
+                ("SquareReg", Some(out), [Some(arg), None])
+            }
+            RegOp::FloorReg(out, arg) => {
+                ("FloorReg", Some(out), [Some(arg), None])
+            }
+            RegOp::CeilReg(out, arg) => {
+                ("CeilReg", Some(out), [Some(arg), None])
+            }
+            RegOp::RoundReg(out, arg) => {
+                ("RoundReg", Some(out), [Some(arg), None])
+            }
+            RegOp::CopyReg(out, arg) => {
+                ("CopyReg", Some(out), [Some(arg), None])
+            }
+            RegOp::SinReg(out, arg) => ("SinReg", Some(out), [Some(arg), None]),
+            RegOp::CosReg(out, arg) => ("CosReg", Some(out), [Some(arg), None]),
+            RegOp::TanReg(out, arg) => ("TanReg", Some(out), [Some(arg), None]),
+            RegOp::AsinReg(out, arg) => {
+                ("AsinReg", Some(out), [Some(arg), None])
+            }
+            RegOp::AcosReg(out, arg) => {
+                ("AcosReg", Some(out), [Some(arg), None])
+            }
+            RegOp::AtanReg(out, arg) => {
+                ("AtanReg", Some(out), [Some(arg), None])
+            }
+            RegOp::ExpReg(out, arg) => ("ExpReg", Some(out), [Some(arg), None]),
+            RegOp::LnReg(out, arg) => ("LnReg", Some(out), [Some(arg), None]),
+            RegOp::NotReg(out, arg) => ("NotReg", Some(out), [Some(arg), None]),
+
+            RegOp::AddRegImm(out, arg, _) => {
+                ("AddRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::MulRegImm(out, arg, _) => {
+                ("MulRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::DivRegImm(out, arg, _) => {
+                ("DivRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::DivImmReg(out, arg, _) => {
+                ("DivImmReg", Some(out), [Some(arg), None])
+            }
+            RegOp::SubImmReg(out, arg, _) => {
+                ("SubImmReg", Some(out), [Some(arg), None])
+            }
+            RegOp::SubRegImm(out, arg, _) => {
+                ("SubRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::ModRegImm(out, arg, _) => {
+                ("ModRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::ModImmReg(out, arg, _) => {
+                ("ModImmReg", Some(out), [Some(arg), None])
+            }
+            RegOp::AtanRegImm(out, arg, _) => {
+                ("AtanRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::AtanImmReg(out, arg, _) => {
+                ("AtanImmReg", Some(out), [Some(arg), None])
+            }
+            RegOp::MinRegImm(out, arg, _) => {
+                ("MinRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::MaxRegImm(out, arg, _) => {
+                ("MaxRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::AndRegImm(out, arg, _) => {
+                ("AndRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::OrRegImm(out, arg, _) => {
+                ("OrRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::CompareRegImm(out, arg, _) => {
+                ("CompareRegImm", Some(out), [Some(arg), None])
+            }
+            RegOp::CompareImmReg(out, arg, _) => {
+                ("CompareImmReg", Some(out), [Some(arg), None])
+            }
+
+            RegOp::AddRegReg(out, lhs, rhs) => {
+                ("AddRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::MulRegReg(out, lhs, rhs) => {
+                ("MulRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::DivRegReg(out, lhs, rhs) => {
+                ("DivRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::SubRegReg(out, lhs, rhs) => {
+                ("SubRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::ModRegReg(out, lhs, rhs) => {
+                ("ModRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::AtanRegReg(out, lhs, rhs) => {
+                ("AtanRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::MinRegReg(out, lhs, rhs) => {
+                ("MinRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::MaxRegReg(out, lhs, rhs) => {
+                ("MaxRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::AndRegReg(out, lhs, rhs) => {
+                ("AndRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::OrRegReg(out, lhs, rhs) => {
+                ("OrRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+            RegOp::CompareRegReg(out, lhs, rhs) => {
+                ("CompareRegReg", Some(out), [Some(lhs), Some(rhs)])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{context::Context, vm::VmData};
+
+    #[test]
+    fn test_metrics() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let a = ctx.mul(x, y).unwrap();
+        let b = ctx.mul(y, z).unwrap();
+        let c = ctx.mul(x, z).unwrap();
+        let ab = ctx.add(a, b).unwrap();
+        let out = ctx.max(ab, c).unwrap();
+
+        let data = VmData::<255>::new(&ctx, out).unwrap();
+        let m = data.metrics();
+        assert_eq!(m.ops.values().sum::<usize>(), data.len());
+        assert_eq!(m.ops["Input"], 3);
+        assert_eq!(m.ops["MulRegReg"], 3);
+        assert_eq!(m.choices, 1);
+        assert_eq!(m.loads + m.stores + m.memory_slots, 0);
+        assert_eq!(m.peak_registers, 4);
+
+        // With fewer registers, values are spilled to memory
+        let data = VmData::<3>::new(&ctx, out).unwrap();
+        let m = data.metrics();
+        assert_eq!(m.ops.values().sum::<usize>(), data.len());
+        assert!(m.loads > 0);
+        assert!(m.stores > 0);
+        assert!(m.memory_slots > 0);
+        assert_eq!(m.ops["Load"], m.loads);
+        assert!(m.peak_registers <= 3);
+    }
+}
@@ -4,14 +4,31 @@
 //! - A math graph (specified as a [`Context`](crate::Context) and
 //!   [`Node`](crate::context::Node)) is flattened into an [`SsaTape`], i.e. a
 //!   set of operations in single-static assignment form.
+//! - The [`SsaTape`] is cleaned up by a [peephole optimizer](Peephole), which
+//!   performs local rewrites (e.g. forwarding copies and folding negation).
 //! - The [`SsaTape`] goes through [register allocation](RegisterAllocator) and
 //!   becomes a [`RegTape`], planned with some number of registers.
+//!
+//! Compilers for other languages can skip the first stage, emitting an
+//! [`SsaTape`] directly with an [`SsaTapeBuilder`].
 
 mod alloc;
 pub use alloc::RegisterAllocator;
 
+mod builder;
+pub use builder::SsaTapeBuilder;
+
+mod cost;
+pub use cost::{Backend, Flavor};
+
+mod metrics;
+pub use metrics::TapeMetrics;
+
 mod op;
 
+mod peephole;
+pub use peephole::Peephole;
+
 mod lru;
 pub(crate) use lru::Lru;
 pub use op::{RegOp, SsaOp};
@@ -20,7 +37,7 @@ mod reg_tape;
 mod ssa_tape;
 
 pub use reg_tape::RegTape;
-pub use ssa_tape::SsaTape;
+pub use ssa_tape::{SsaTape, MAX_INPUTS, MAX_TAPE_INDEX};
 
 #[cfg(test)]
 mod test {
@@ -30,4 +47,20 @@ mod test {
         assert_eq!(std::mem::size_of::<RegOp>(), 8);
         assert_eq!(std::mem::size_of::<SsaOp>(), 16);
     }
+
+    #[test]
+    fn test_opcodes() {
+        // Opcodes are part of the public API, so they must never change
+        assert_eq!(SsaOp::Input(1, 2).opcode(), 0);
+        assert_eq!(SsaOp::CopyReg(1, 2).opcode(), 18);
+        assert_eq!(SsaOp::AddRegImm(1, 2, 3.0).opcode(), 19);
+        assert_eq!(SsaOp::AddRegReg(1, 2, 3).opcode(), 37);
+        assert_eq!(SsaOp::CompareRegReg(1, 2, 3).opcode(), 45);
+        assert_eq!(SsaOp::CopyImm(1, 2.0).opcode(), 46);
+        assert_eq!(RegOp::Input(1, 2).opcode(), 0);
+        assert_eq!(RegOp::MinRegReg(1, 2, 3).opcode(), 41);
+        assert_eq!(RegOp::CopyImm(1, 2.0).opcode(), 46);
+        assert_eq!(RegOp::Load(1, 2).opcode(), 128);
+        assert_eq!(RegOp::Store(1, 2).opcode(), 129);
+    }
 }
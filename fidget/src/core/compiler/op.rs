@@ -16,134 +16,147 @@ macro_rules! opcodes {
         pub enum $name:ident<$t:ty> {
             $(
                 $(#[$($a:meta)*])*
-                $foo:ident($($i:ty),*)
+                $foo:ident($($i:ty),*) = $d:literal
              ),*
             ,
         }
     ) => {
         $(#[$($attrss)*])*
+        #[repr(u8)]
         pub enum $name {
             #[doc = "Read one of the inputs (X, Y, Z)"]
-            Input($t, $t),
+            Input($t, $t) = 0,
 
             #[doc = "Negate the given register"]
-            NegReg($t, $t),
+            NegReg($t, $t) = 1,
 
             #[doc = "Take the absolute value of the given register"]
-            AbsReg($t, $t),
+            AbsReg($t, $t) = 2,
 
             #[doc = "Take the reciprocal of the given register (1.0 / value)"]
-            RecipReg($t, $t),
+            RecipReg($t, $t) = 3,
 
             #[doc = "Take the square root of the given register"]
-            SqrtReg($t, $t),
+            SqrtReg($t, $t) = 4,
 
             #[doc = "Square the given register"]
-            SquareReg($t, $t),
+            SquareReg($t, $t) = 5,
 
             #[doc = "Returns the largest integer less than or equal to `self`"]
-            FloorReg($t, $t),
+            FloorReg($t, $t) = 6,
 
             #[doc = "Returns the smallest integer greater than or equal to `self`"]
-            CeilReg($t, $t),
+            CeilReg($t, $t) = 7,
 
             #[doc = "Returns the nearest integer to `self`. If a value is half-way between two integers, round away from `0.0`."]
-            RoundReg($t, $t),
+            RoundReg($t, $t) = 8,
 
             #[doc = "Computes the sine of the given register (in radians)"]
-            SinReg($t, $t),
+            SinReg($t, $t) = 9,
 
             #[doc = "Computes the cosine of the given register (in radians)"]
-            CosReg($t, $t),
+            CosReg($t, $t) = 10,
 
             #[doc = "Computes the tangent of the given register (in radians)"]
-            TanReg($t, $t),
+            TanReg($t, $t) = 11,
 
             #[doc = "Computes the arcsin of the given register (in radians)"]
-            AsinReg($t, $t),
+            AsinReg($t, $t) = 12,
 
             #[doc = "Computes the arccos of the given register (in radians)"]
-            AcosReg($t, $t),
+            AcosReg($t, $t) = 13,
 
             #[doc = "Computes the arctangent of the given register (in radians)"]
-            AtanReg($t, $t),
+            AtanReg($t, $t) = 14,
 
             #[doc = "Computes the exponential function of the given register"]
-            ExpReg($t, $t),
+            ExpReg($t, $t) = 15,
 
             #[doc = "Computes the natural log of the given register"]
-            LnReg($t, $t),
+            LnReg($t, $t) = 16,
 
             #[doc = "Computes the logical negation of the given register"]
-            NotReg($t, $t),
+            NotReg($t, $t) = 17,
 
             #[doc = "Copies the given register"]
-            CopyReg($t, $t),
+            CopyReg($t, $t) = 18,
 
             #[doc = "Add a register and an immediate"]
-            AddRegImm($t, $t, f32),
+            AddRegImm($t, $t, f32) = 19,
             #[doc = "Multiply a register and an immediate"]
-            MulRegImm($t, $t, f32),
+            MulRegImm($t, $t, f32) = 20,
             #[doc = "Divides a register and an immediate"]
-            DivRegImm($t, $t, f32),
+            DivRegImm($t, $t, f32) = 21,
             #[doc = "Divides an immediate by a register"]
-            DivImmReg($t, $t, f32),
+            DivImmReg($t, $t, f32) = 22,
             #[doc = "Subtract a register from an immediate"]
-            SubImmReg($t, $t, f32),
+            SubImmReg($t, $t, f32) = 23,
             #[doc = "Subtract an immediate from a register"]
-            SubRegImm($t, $t, f32),
+            SubRegImm($t, $t, f32) = 24,
             #[doc = "Take the module (least nonnegative remainder) of two registers"]
-            ModRegReg($t, $t, $t),
+            ModRegReg($t, $t, $t) = 25,
             #[doc = "Take the module (least nonnegative remainder) of a register and an immediate"]
-            ModRegImm($t, $t, f32),
+            ModRegImm($t, $t, f32) = 26,
             #[doc = "Take the module (least nonnegative remainder) of an immediate and a register"]
-            ModImmReg($t, $t, f32),
+            ModImmReg($t, $t, f32) = 27,
             #[doc = "atan2 of a position `(y, x)` specified as register, register"]
-            AtanRegReg($t, $t, $t),
+            AtanRegReg($t, $t, $t) = 28,
             #[doc = "atan2 of a position `(y, x)` specified as register, immediate"]
-            AtanRegImm($t, $t, f32),
+            AtanRegImm($t, $t, f32) = 29,
             #[doc = "atan2 of a position `(y, x)` specified as immediate, register"]
-            AtanImmReg($t, $t, f32),
+            AtanImmReg($t, $t, f32) = 30,
             #[doc = "Compute the minimum of a register and an immediate"]
-            MinRegImm($t, $t, f32),
+            MinRegImm($t, $t, f32) = 31,
             #[doc = "Compute the maximum of a register and an immediate"]
-            MaxRegImm($t, $t, f32),
+            MaxRegImm($t, $t, f32) = 32,
             #[doc = "Multiplies the two values, short-circuiting if either is 0"]
-            AndRegImm($t, $t, f32),
+            AndRegImm($t, $t, f32) = 33,
             #[doc = "Add two values, short-circuiting if either is 0"]
-            OrRegImm($t, $t, f32),
+            OrRegImm($t, $t, f32) = 34,
             #[doc = "Compares a register with an immediate"]
-            CompareRegImm($t, $t, f32),
+            CompareRegImm($t, $t, f32) = 35,
             #[doc = "Compares an immediate with a register"]
-            CompareImmReg($t, $t, f32),
+            CompareImmReg($t, $t, f32) = 36,
 
             #[doc = "Add two registers"]
-            AddRegReg($t, $t, $t),
+            AddRegReg($t, $t, $t) = 37,
             #[doc = "Multiply two registers"]
-            MulRegReg($t, $t, $t),
+            MulRegReg($t, $t, $t) = 38,
             #[doc = "Divides two registers"]
-            DivRegReg($t, $t, $t),
+            DivRegReg($t, $t, $t) = 39,
             #[doc = "Subtract one register from another"]
-            SubRegReg($t, $t, $t),
+            SubRegReg($t, $t, $t) = 40,
             #[doc = "Take the minimum of two registers"]
-            MinRegReg($t, $t, $t),
+            MinRegReg($t, $t, $t) = 41,
             #[doc = "Take the maximum of two registers"]
-            MaxRegReg($t, $t, $t),
+            MaxRegReg($t, $t, $t) = 42,
             #[doc = "Multiply two values, short-circuiting if either is 0"]
-            AndRegReg($t, $t, $t),
+            AndRegReg($t, $t, $t) = 43,
             #[doc = "Add two values, short-circuiting if either is 0"]
-            OrRegReg($t, $t, $t),
+            OrRegReg($t, $t, $t) = 44,
             #[doc = "Compares two registers"]
-            CompareRegReg($t, $t, $t),
+            CompareRegReg($t, $t, $t) = 45,
 
             #[doc = "Copy an immediate to a register"]
-            CopyImm($t, f32),
+            CopyImm($t, f32) = 46,
 
             $(
                 $(#[$($a)*])*
-                $foo($($i),*)
+                $foo($($i),*) = $d
              ),*
         }
+
+        impl $name {
+            #[doc = "Returns the opcode of this operation"]
+            #[doc = ""]
+            #[doc = "Opcodes are stable; see the type-level documentation."]
+            pub fn opcode(&self) -> u8 {
+                // SAFETY: the enum is `#[repr(u8)]`, so its layout begins with
+                // a `u8` discriminant (see the Rust reference on primitive
+                // representations of enums with fields).
+                unsafe { *(self as *const Self).cast::<u8>() }
+            }
+        }
     };
 }
 
@@ -156,12 +169,80 @@ opcodes!(
     /// - RHS register (or immediate for `*Imm`)
     ///
     /// Each "register" represents an SSA slot, which is never reused.
-    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+    ///
+    /// # Opcodes
+    /// Each variant has an explicit discriminant, returned by
+    /// [`opcode`](SsaOp::opcode), so that external tools (e.g. transpilers)
+    /// can identify operations without depending on declaration order.
+    /// Opcodes are stable: they're never renumbered or reused, and new
+    /// operations are given new opcodes.  Variants which are shared with
+    /// [`RegOp`] have the same opcode in both enums.
+    #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
     pub enum SsaOp<u32> {
         // default variants
     }
 );
 
+impl RegOp {
+    /// Returns the slot written by this operation
+    ///
+    /// This is the output register for every operation except
+    /// [`Store`](RegOp::Store), which writes to a memory slot.
+    pub fn output(&self) -> u32 {
+        match self {
+            RegOp::Store(_, mem) => *mem,
+            RegOp::Load(out, ..)
+            | RegOp::Input(out, ..)
+            | RegOp::CopyImm(out, ..)
+            | RegOp::NegReg(out, ..)
+            | RegOp::AbsReg(out, ..)
+            | RegOp::RecipReg(out, ..)
+            | RegOp::SqrtReg(out, ..)
+            | RegOp::SquareReg(out, ..)
+            | RegOp::FloorReg(out, ..)
+            | RegOp::CeilReg(out, ..)
+            | RegOp::RoundReg(out, ..)
+            | RegOp::CopyReg(out, ..)
+            | RegOp::SinReg(out, ..)
+            | RegOp::CosReg(out, ..)
+            | RegOp::TanReg(out, ..)
+            | RegOp::AsinReg(out, ..)
+            | RegOp::AcosReg(out, ..)
+            | RegOp::AtanReg(out, ..)
+            | RegOp::ExpReg(out, ..)
+            | RegOp::LnReg(out, ..)
+            | RegOp::NotReg(out, ..)
+            | RegOp::AddRegImm(out, ..)
+            | RegOp::MulRegImm(out, ..)
+            | RegOp::DivRegImm(out, ..)
+            | RegOp::DivImmReg(out, ..)
+            | RegOp::SubImmReg(out, ..)
+            | RegOp::SubRegImm(out, ..)
+            | RegOp::AddRegReg(out, ..)
+            | RegOp::MulRegReg(out, ..)
+            | RegOp::DivRegReg(out, ..)
+            | RegOp::SubRegReg(out, ..)
+            | RegOp::AtanRegReg(out, ..)
+            | RegOp::AtanRegImm(out, ..)
+            | RegOp::AtanImmReg(out, ..)
+            | RegOp::MinRegImm(out, ..)
+            | RegOp::MaxRegImm(out, ..)
+            | RegOp::MinRegReg(out, ..)
+            | RegOp::MaxRegReg(out, ..)
+            | RegOp::CompareRegReg(out, ..)
+            | RegOp::CompareRegImm(out, ..)
+            | RegOp::CompareImmReg(out, ..)
+            | RegOp::ModRegReg(out, ..)
+            | RegOp::ModRegImm(out, ..)
+            | RegOp::ModImmReg(out, ..)
+            | RegOp::AndRegImm(out, ..)
+            | RegOp::AndRegReg(out, ..)
+            | RegOp::OrRegImm(out, ..)
+            | RegOp::OrRegReg(out, ..) => *out as u32,
+        }
+    }
+}
+
 impl SsaOp {
     /// Returns the output pseudo-register
     pub fn output(&self) -> u32 {
@@ -279,13 +360,19 @@ opcodes!(
     ///
     /// We have a maximum of 256 registers, though some tapes (e.g. ones
     /// targeting physical hardware) may choose to use fewer.
+    ///
+    /// # Opcodes
+    /// Opcodes are stable, and match [`SsaOp`] for shared variants (see its
+    /// documentation for details).  Operations which only exist in
+    /// register-allocated tapes ([`Load`](RegOp::Load) and
+    /// [`Store`](RegOp::Store)) are numbered from 128.
     #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
     pub enum RegOp<u8> {
         // default variants
         /// Read from a memory slot to a register
-        Load(u8, u32),
+        Load(u8, u32) = 128,
 
         /// Write from a register to a memory slot
-        Store(u8, u32),
+        Store(u8, u32) = 129,
     }
 );
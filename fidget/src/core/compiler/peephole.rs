@@ -0,0 +1,595 @@
+//! Peephole optimization of SSA tapes
+use crate::compiler::{SsaOp, SsaTape};
+
+const UNASSIGNED: u32 = u32::MAX;
+
+/// Peephole optimizer for [`SsaTape`]s
+///
+/// This performs local rewrites which shorten the tape without changing its
+/// results:
+/// - Copies are forwarded to their users, e.g. `a + copy(b)` becomes `a + b`
+/// - Constants are forwarded into immediates, e.g. `a * copy(2)` becomes
+///   `a * 2`
+/// - Negation is folded into its users, e.g. `a + (-b)` becomes `a - b`,
+///   `(-a) * 2` becomes `a * -2`, and `-(-a)` becomes `a`
+/// - Redundant sign operations are removed, e.g. `abs(-a)` becomes `abs(a)`
+///   and `square(abs(a))` becomes `square(a)`
+/// - `sqrt(square(a))` becomes `abs(a)`
+/// - Arithmetic on constants is folded, e.g. `copy(2) * 3` becomes `copy(6)`
+///   (constants appear in a tape when an input is fixed, e.g. by
+///   [`VmData::plan_2d`](crate::vm::VmData::plan_2d))
+///
+/// These patterns are rare in a freshly built tape, but common after
+/// [simplification](crate::vm::VmData::simplify), where `min` and `max` nodes
+/// collapse into copies of one of their arguments.
+///
+/// Operations which are unused after rewriting are removed, and the remaining
+/// slots are renumbered to stay contiguous.  Choice operations are never
+/// removed or reordered, so the tape's choice count and order are unchanged.
+///
+/// `square(sqrt(a))` is _not_ folded, because it is `NaN` for `a < 0`.
+#[derive(Default)]
+pub struct Peephole {
+    /// Position of each slot's defining operation in the input tape
+    defs: Vec<u32>,
+
+    /// Map from slots in the input tape to slots in the output tape
+    bind: Vec<u32>,
+
+    /// Number of slots assigned in the output tape
+    count: u32,
+
+    /// Constant value of each slot in the input tape, if known
+    consts: Vec<Option<f32>>,
+}
+
+impl Peephole {
+    /// Builds a new optimizer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Optimizes the given tape in place
+    ///
+    /// The tape's first operation must write to slot 0 (the output), which is
+    /// true of every tape built by [`SsaTape::new`] or by simplification.
+    pub fn run(&mut self, tape: &mut SsaTape) {
+        let len = tape.tape.len();
+        if len == 0 {
+            return;
+        }
+        assert_eq!(tape.tape[0].output(), 0);
+        self.fold_constants(tape);
+
+        self.defs.clear();
+        self.defs.resize(len, UNASSIGNED);
+        for (i, op) in tape.tape.iter().enumerate() {
+            self.defs[op.output() as usize] = i as u32;
+        }
+        self.bind.clear();
+        self.bind.resize(len, UNASSIGNED);
+        self.bind[0] = 0;
+        self.count = 1;
+
+        // Operations are only ever removed, and each operation's arguments are
+        // defined later in the tape, so we can compact the tape in place
+        // without clobbering any definitions that we'll need later.
+        let mut choice_count = 0;
+        let mut n = 0;
+        for i in 0..len {
+            let mut op = tape.tape[i];
+            let Some(out) = self.active(op.output()) else {
+                continue;
+            };
+            while let Some(next) = self.rewrite(&tape.tape, op) {
+                op = next;
+            }
+            if let SsaOp::CopyReg(_, src) = op {
+                // Bind the source to our output slot, removing the copy,
+                // unless the source is already in use
+                let src = self.resolve(&tape.tape, src);
+                match self.active(src) {
+                    Some(src) => op = SsaOp::CopyReg(out, src),
+                    None => {
+                        self.bind[src as usize] = out;
+                        continue;
+                    }
+                }
+            } else {
+                self.remap(&tape.tape, &mut op, out);
+            }
+            choice_count += op.has_choice() as usize;
+            tape.tape[n] = op;
+            n += 1;
+        }
+        tape.tape.truncate(n);
+        assert_eq!(self.count as usize, n);
+        debug_assert_eq!(choice_count, tape.choice_count);
+        tape.choice_count = choice_count;
+
+        let names = std::mem::take(&mut tape.names);
+        tape.names = names
+            .into_iter()
+            .filter_map(|(i, name)| self.active(i).map(|j| (j, name)))
+            .collect();
+    }
+
+    /// Replaces arithmetic on constants with the resulting constant
+    ///
+    /// This walks the tape in evaluation order, so that constants propagate
+    /// through chains of operations; the main pass then forwards them into
+    /// immediates.  Choice operations are never folded.
+    fn fold_constants(&mut self, tape: &mut SsaTape) {
+        self.consts.clear();
+        self.consts.resize(tape.tape.len(), None);
+        for op in tape.tape.iter_mut().rev() {
+            let c = |i: u32| self.consts[i as usize];
+            let c2 = |a: u32, b: u32| c(a).zip(c(b));
+            let v = match *op {
+                SsaOp::CopyImm(_, v) => Some(v),
+                SsaOp::CopyReg(_, a) => c(a),
+                SsaOp::NegReg(_, a) => c(a).map(|a| -a),
+                SsaOp::AbsReg(_, a) => c(a).map(|a| a.abs()),
+                SsaOp::SquareReg(_, a) => c(a).map(|a| a * a),
+                SsaOp::AddRegImm(_, a, v) => c(a).map(|a| a + v),
+                SsaOp::SubRegImm(_, a, v) => c(a).map(|a| a - v),
+                SsaOp::SubImmReg(_, a, v) => c(a).map(|a| v - a),
+                SsaOp::MulRegImm(_, a, v) => c(a).map(|a| a * v),
+                SsaOp::DivRegImm(_, a, v) => c(a).map(|a| a / v),
+                SsaOp::DivImmReg(_, a, v) => c(a).map(|a| v / a),
+                SsaOp::AddRegReg(_, a, b) => c2(a, b).map(|(a, b)| a + b),
+                SsaOp::SubRegReg(_, a, b) => c2(a, b).map(|(a, b)| a - b),
+                SsaOp::MulRegReg(_, a, b) => c2(a, b).map(|(a, b)| a * b),
+                SsaOp::DivRegReg(_, a, b) => c2(a, b).map(|(a, b)| a / b),
+                _ => None,
+            };
+            if let Some(v) = v {
+                let out = op.output();
+                self.consts[out as usize] = Some(v);
+                *op = SsaOp::CopyImm(out, v);
+            }
+        }
+    }
+
+    /// Returns the output slot bound to the given input slot, if any
+    fn active(&self, i: u32) -> Option<u32> {
+        match self.bind.get(i as usize) {
+            Some(&j) if j != UNASSIGNED => Some(j),
+            _ => None,
+        }
+    }
+
+    /// Returns the output slot bound to the given input slot, binding it to
+    /// the next available slot if it's not yet in use
+    fn get_or_insert_active(&mut self, i: u32) -> u32 {
+        if self.bind[i as usize] == UNASSIGNED {
+            self.bind[i as usize] = self.count;
+            self.count += 1;
+        }
+        self.bind[i as usize]
+    }
+
+    /// Returns the operation which defines the given slot
+    fn def(&self, tape: &[SsaOp], i: u32) -> SsaOp {
+        tape[self.defs[i as usize] as usize]
+    }
+
+    /// Looks through copies and double negation to find an equivalent slot
+    fn resolve(&self, tape: &[SsaOp], mut i: u32) -> u32 {
+        loop {
+            match self.def(tape, i) {
+                SsaOp::CopyReg(_, src) => i = src,
+                SsaOp::NegReg(_, arg) => match self.def(tape, arg) {
+                    SsaOp::NegReg(_, src) => i = src,
+                    _ => return i,
+                },
+                _ => return i,
+            }
+        }
+    }
+
+    /// If the given slot is a negation, returns the negated slot
+    fn neg(&self, tape: &[SsaOp], i: u32) -> Option<u32> {
+        match self.def(tape, self.resolve(tape, i)) {
+            SsaOp::NegReg(_, arg) => Some(arg),
+            _ => None,
+        }
+    }
+
+    /// If the given slot is a negation or absolute value, returns its argument
+    fn sign(&self, tape: &[SsaOp], i: u32) -> Option<u32> {
+        match self.def(tape, self.resolve(tape, i)) {
+            SsaOp::NegReg(_, arg) | SsaOp::AbsReg(_, arg) => Some(arg),
+            _ => None,
+        }
+    }
+
+    /// If the given slot has a constant value, returns it
+    fn imm(&self, tape: &[SsaOp], mut i: u32) -> Option<f32> {
+        let mut negate = false;
+        let mut abs = false;
+        loop {
+            match self.def(tape, self.resolve(tape, i)) {
+                SsaOp::CopyImm(_, v) => {
+                    let v = if abs { v.abs() } else { v };
+                    return Some(if negate { -v } else { v });
+                }
+                SsaOp::NegReg(_, arg) => {
+                    // Negation is irrelevant once we've taken an abs
+                    negate ^= !abs;
+                    i = arg;
+                }
+                SsaOp::AbsReg(_, arg) => {
+                    abs = true;
+                    i = arg;
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Attempts to rewrite a single operation, returning the new operation
+    ///
+    /// Slots in the returned operation are from the input tape.
+    fn rewrite(&self, tape: &[SsaOp], op: SsaOp) -> Option<SsaOp> {
+        let neg = |i| self.neg(tape, i);
+        let imm = |i| self.imm(tape, i);
+
+        // Forwards a constant argument into an immediate, where possible.
+        // Argument order follows `SsaOp` conventions: `f.0` is `reg op imm`,
+        // and `f.1` is `imm op reg`.
+        type ImmFn = fn(u32, u32, f32) -> SsaOp;
+        let forward = |out, lhs, rhs, f: (ImmFn, Option<ImmFn>)| {
+            if let Some(v) = imm(rhs) {
+                Some(f.0(out, lhs, v))
+            } else {
+                f.1.zip(imm(lhs)).map(|(f, v)| f(out, rhs, v))
+            }
+        };
+
+        match op {
+            SsaOp::NegReg(out, arg) => imm(arg)
+                .map(|v| SsaOp::CopyImm(out, -v))
+                .or_else(|| neg(arg).map(|a| SsaOp::CopyReg(out, a))),
+            SsaOp::AbsReg(out, arg) => {
+                imm(arg).map(|v| SsaOp::CopyImm(out, v.abs())).or_else(|| {
+                    self.sign(tape, arg).map(|a| SsaOp::AbsReg(out, a))
+                })
+            }
+            SsaOp::SquareReg(out, arg) => {
+                self.sign(tape, arg).map(|a| SsaOp::SquareReg(out, a))
+            }
+            SsaOp::SqrtReg(out, arg) => {
+                match self.def(tape, self.resolve(tape, arg)) {
+                    SsaOp::SquareReg(_, a) => Some(SsaOp::AbsReg(out, a)),
+                    _ => None,
+                }
+            }
+
+            SsaOp::AddRegImm(out, arg, v) => {
+                neg(arg).map(|a| SsaOp::SubImmReg(out, a, v))
+            }
+            SsaOp::SubImmReg(out, arg, v) => {
+                neg(arg).map(|a| SsaOp::AddRegImm(out, a, v))
+            }
+            SsaOp::MulRegImm(out, arg, v) => {
+                neg(arg).map(|a| SsaOp::MulRegImm(out, a, -v))
+            }
+            SsaOp::DivRegImm(out, arg, v) => {
+                neg(arg).map(|a| SsaOp::DivRegImm(out, a, -v))
+            }
+            SsaOp::DivImmReg(out, arg, v) => {
+                neg(arg).map(|a| SsaOp::DivImmReg(out, a, -v))
+            }
+
+            SsaOp::AddRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::AddRegImm, Some(SsaOp::AddRegImm)),
+            )
+            .or_else(|| neg(rhs).map(|b| SsaOp::SubRegReg(out, lhs, b)))
+            .or_else(|| neg(lhs).map(|a| SsaOp::SubRegReg(out, rhs, a))),
+            SsaOp::SubRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::SubRegImm, Some(SsaOp::SubImmReg)),
+            )
+            .or_else(|| neg(rhs).map(|b| SsaOp::AddRegReg(out, lhs, b))),
+            SsaOp::MulRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::MulRegImm, Some(SsaOp::MulRegImm)),
+            )
+            .or_else(|| {
+                neg(lhs)
+                    .zip(neg(rhs))
+                    .map(|(a, b)| SsaOp::MulRegReg(out, a, b))
+            }),
+            SsaOp::DivRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::DivRegImm, Some(SsaOp::DivImmReg)),
+            )
+            .or_else(|| {
+                neg(lhs)
+                    .zip(neg(rhs))
+                    .map(|(a, b)| SsaOp::DivRegReg(out, a, b))
+            }),
+            SsaOp::AtanRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::AtanRegImm, Some(SsaOp::AtanImmReg)),
+            ),
+            SsaOp::CompareRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::CompareRegImm, Some(SsaOp::CompareImmReg)),
+            ),
+            SsaOp::ModRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::ModRegImm, Some(SsaOp::ModImmReg)),
+            ),
+            // Swapping arguments to `min` and `max` changes the meaning of
+            // their choices, but choices are always interpreted relative to
+            // the tape which produced them, so this is fine.
+            SsaOp::MinRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::MinRegImm, Some(SsaOp::MinRegImm)),
+            ),
+            SsaOp::MaxRegReg(out, lhs, rhs) => forward(
+                out,
+                lhs,
+                rhs,
+                (SsaOp::MaxRegImm, Some(SsaOp::MaxRegImm)),
+            ),
+            // `and` and `or` aren't commutative, and have no `ImmReg` form
+            SsaOp::AndRegReg(out, lhs, rhs) => {
+                forward(out, lhs, rhs, (SsaOp::AndRegImm, None))
+            }
+            SsaOp::OrRegReg(out, lhs, rhs) => {
+                forward(out, lhs, rhs, (SsaOp::OrRegImm, None))
+            }
+            _ => None,
+        }
+    }
+
+    /// Remaps an operation's output and arguments into the output tape
+    fn remap(&mut self, tape: &[SsaOp], op: &mut SsaOp, new_out: u32) {
+        let mut arg = |i: &mut u32| {
+            *i = self.get_or_insert_active(self.resolve(tape, *i));
+        };
+        match op {
+            SsaOp::Input(out, ..) | SsaOp::CopyImm(out, ..) => {
+                *out = new_out;
+            }
+            SsaOp::NegReg(out, a)
+            | SsaOp::AbsReg(out, a)
+            | SsaOp::RecipReg(out, a)
+            | SsaOp::SqrtReg(out, a)
+            | SsaOp::SquareReg(out, a)
+            | SsaOp::FloorReg(out, a)
+            | SsaOp::CeilReg(out, a)
+            | SsaOp::RoundReg(out, a)
+            | SsaOp::CopyReg(out, a)
+            | SsaOp::SinReg(out, a)
+            | SsaOp::CosReg(out, a)
+            | SsaOp::TanReg(out, a)
+            | SsaOp::AsinReg(out, a)
+            | SsaOp::AcosReg(out, a)
+            | SsaOp::AtanReg(out, a)
+            | SsaOp::ExpReg(out, a)
+            | SsaOp::LnReg(out, a)
+            | SsaOp::NotReg(out, a)
+            | SsaOp::AddRegImm(out, a, ..)
+            | SsaOp::MulRegImm(out, a, ..)
+            | SsaOp::DivRegImm(out, a, ..)
+            | SsaOp::DivImmReg(out, a, ..)
+            | SsaOp::SubImmReg(out, a, ..)
+            | SsaOp::SubRegImm(out, a, ..)
+            | SsaOp::AtanRegImm(out, a, ..)
+            | SsaOp::AtanImmReg(out, a, ..)
+            | SsaOp::MinRegImm(out, a, ..)
+            | SsaOp::MaxRegImm(out, a, ..)
+            | SsaOp::CompareRegImm(out, a, ..)
+            | SsaOp::CompareImmReg(out, a, ..)
+            | SsaOp::ModRegImm(out, a, ..)
+            | SsaOp::ModImmReg(out, a, ..)
+            | SsaOp::AndRegImm(out, a, ..)
+            | SsaOp::OrRegImm(out, a, ..) => {
+                *out = new_out;
+                arg(a);
+            }
+            SsaOp::AddRegReg(out, a, b)
+            | SsaOp::MulRegReg(out, a, b)
+            | SsaOp::DivRegReg(out, a, b)
+            | SsaOp::SubRegReg(out, a, b)
+            | SsaOp::AtanRegReg(out, a, b)
+            | SsaOp::MinRegReg(out, a, b)
+            | SsaOp::MaxRegReg(out, a, b)
+            | SsaOp::CompareRegReg(out, a, b)
+            | SsaOp::ModRegReg(out, a, b)
+            | SsaOp::AndRegReg(out, a, b)
+            | SsaOp::OrRegReg(out, a, b) => {
+                *out = new_out;
+                arg(a);
+                arg(b);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Context,
+        eval::{EzShape, MathShape, Shape, TracingEvaluator},
+        vm::VmShape,
+    };
+
+    fn optimize(ctx: &Context, node: crate::context::Node) -> SsaTape {
+        let mut tape = SsaTape::new(ctx, node).unwrap();
+        Peephole::new().run(&mut tape);
+        tape
+    }
+
+    #[test]
+    fn test_fold_neg() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let ny = ctx.neg(y).unwrap();
+        let sum = ctx.add(x, ny).unwrap();
+        let tape = optimize(&ctx, sum);
+        assert_eq!(tape.len(), 3);
+        assert!(matches!(tape.tape[0], SsaOp::SubRegReg(0, ..)));
+
+        let nny = ctx.neg(ny).unwrap();
+        let tape = optimize(&ctx, nny);
+        assert_eq!(tape.len(), 1);
+        assert!(matches!(tape.tape[0], SsaOp::Input(0, 1)));
+
+        let nx = ctx.neg(x).unwrap();
+        let prod = ctx.mul(nx, ny).unwrap();
+        let tape = optimize(&ctx, prod);
+        assert_eq!(tape.len(), 3);
+        assert!(matches!(tape.tape[0], SsaOp::MulRegReg(0, ..)));
+
+        let prod = ctx.mul(nx, 2.0).unwrap();
+        let tape = optimize(&ctx, prod);
+        assert_eq!(tape.len(), 2);
+        assert!(matches!(tape.tape[0], SsaOp::MulRegImm(0, 1, -2.0)));
+
+        let a = ctx.abs(nx).unwrap();
+        let s = ctx.square(a).unwrap();
+        let r = ctx.sqrt(s).unwrap();
+        let tape = optimize(&ctx, r);
+        assert_eq!(tape.len(), 2);
+        assert!(matches!(tape.tape[0], SsaOp::AbsReg(0, 1)));
+        assert!(matches!(tape.tape[1], SsaOp::Input(1, 0)));
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        // (z * 2 + 1) / x, with z fixed to 3 after construction
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let z = ctx.z();
+        let a = ctx.mul(z, 2.0).unwrap();
+        let b = ctx.add(a, 1.0).unwrap();
+        let c = ctx.square(b).unwrap();
+        let out = ctx.div(c, x).unwrap();
+        let mut tape = SsaTape::new(&ctx, out).unwrap();
+        for op in tape.tape.iter_mut() {
+            if let SsaOp::Input(out, 2) = *op {
+                *op = SsaOp::CopyImm(out, 3.0);
+            }
+        }
+        Peephole::new().run(&mut tape);
+        assert_eq!(tape.len(), 2);
+        assert!(matches!(tape.tape[0], SsaOp::DivImmReg(0, 1, 49.0)));
+        assert!(matches!(tape.tape[1], SsaOp::Input(1, 0)));
+    }
+
+    #[test]
+    fn test_simplified() {
+        // min(-x, y) + z
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let nx = ctx.neg(x).unwrap();
+        let m = ctx.min(nx, y).unwrap();
+        let sum = ctx.add(m, z).unwrap();
+        let shape = VmShape::new(&ctx, sum).unwrap();
+        assert_eq!(shape.size(), 6);
+
+        // Picking `-x` leaves `z - x`, without a copy
+        let mut eval = VmShape::new_point_eval();
+        let (v, trace) = eval.eval(&shape, 1.0, 2.0, 3.0).unwrap();
+        assert_eq!(v, 2.0);
+        let next = shape.ez_simplify(trace.unwrap()).unwrap();
+        assert_eq!(next.size(), 3);
+        assert_eq!(next.choice_count(), 0);
+        assert!(next
+            .data()
+            .iter_asm()
+            .any(|op| matches!(op, crate::compiler::RegOp::SubRegReg(..))));
+
+        // max(x, 2) * y, picking the constant, becomes `y * 2`
+        let m = ctx.max(x, 2.0).unwrap();
+        let prod = ctx.mul(m, y).unwrap();
+        let shape = VmShape::new(&ctx, prod).unwrap();
+        let (v, trace) = eval.eval(&shape, 1.0, 3.0, 0.0).unwrap();
+        assert_eq!(v, 6.0);
+        let next = shape.ez_simplify(trace.unwrap()).unwrap();
+        assert_eq!(next.size(), 2);
+        assert_eq!(
+            next.data().iter_asm().last(),
+            Some(crate::compiler::RegOp::MulRegImm(0, 0, 2.0))
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let nx = ctx.neg(x).unwrap();
+        let ny = ctx.neg(y).unwrap();
+        let c = ctx.constant(1.5);
+        let nc = ctx.neg(c).unwrap();
+        let anc = ctx.abs(nc).unwrap();
+
+        let a = ctx.sub(z, ny).unwrap();
+        let b = ctx.div(nx, ny).unwrap();
+        let d = ctx.sub(anc, nx).unwrap();
+        let e = ctx.atan2(anc, ny).unwrap();
+        let f = ctx.square(nx).unwrap();
+        let f = ctx.sqrt(f).unwrap();
+        let g = ctx.div(3.0, nx).unwrap();
+        let h = ctx.compare(ny, anc).unwrap();
+        let i = ctx.modulo(anc, ny).unwrap();
+        let mut out = ctx.min(a, b).unwrap();
+        for (j, n) in [d, e, f, g, h, i].into_iter().enumerate() {
+            out = if j % 2 == 0 {
+                ctx.max(out, n).unwrap()
+            } else {
+                ctx.add(out, n).unwrap()
+            };
+        }
+
+        let shape = VmShape::new(&ctx, out).unwrap();
+        let mut eval = VmShape::new_point_eval();
+        let tape = shape.ez_point_tape();
+        for i in 0..8 {
+            for j in 0..8 {
+                let (x, y, z) = (i as f32 - 3.7, j as f32 - 2.1, 0.4);
+                let (v, trace) = eval.eval(&tape, x, y, z).unwrap();
+                let expected = ctx.eval_xyz(out, x as f64, y as f64, z as f64);
+                let expected = expected.unwrap() as f32;
+                assert!(
+                    (v - expected).abs() < 1e-5,
+                    "{v} != {expected} at {x}, {y}"
+                );
+
+                // Simplified tapes give the same result
+                let next = shape.ez_simplify(trace.unwrap()).unwrap();
+                assert!(next.size() <= shape.size());
+                let (w, _) = eval.eval(&next.ez_point_tape(), x, y, z).unwrap();
+                assert_eq!(v, w);
+            }
+        }
+    }
+}
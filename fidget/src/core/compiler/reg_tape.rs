@@ -1,5 +1,8 @@
 //! Tape used for evaluation
-use crate::compiler::{RegOp, RegisterAllocator, SsaTape};
+use crate::{
+    compiler::{RegOp, RegisterAllocator, SsaTape},
+    Error,
+};
 use serde::{Deserialize, Serialize};
 
 /// Low-level tape for use with the Fidget virtual machine (or to be lowered
@@ -20,8 +23,8 @@ impl RegTape {
     ///
     /// Note that if you _also_ want to simplify the tape, it's more efficient
     /// to use [`VmData::simplify`](crate::vm::VmData::simplify), which
-    /// simultaneously simplifies **and** performs register allocation in a
-    /// single pass.
+    /// simplifies, optimizes, **and** performs register allocation while
+    /// reusing a single workspace.
     pub fn new<const N: usize>(ssa: &SsaTape) -> Self {
         let mut alloc = RegisterAllocator::<N>::new(ssa.len());
         for &op in ssa.iter() {
@@ -73,6 +76,83 @@ impl RegTape {
     pub fn iter(&self) -> std::slice::Iter<'_, RegOp> {
         self.into_iter()
     }
+
+    /// Checks that the tape is well-formed for a machine with `N` registers
+    ///
+    /// Tapes built with [`RegTape::new`] are always valid; this is used when
+    /// loading a tape from elsewhere (e.g. during deserialization), because
+    /// evaluators (and the JIT in particular) trust register and memory
+    /// indices without further checks.
+    pub(crate) fn check<const N: usize>(&self) -> Result<(), Error> {
+        if self.slot_count == 0 {
+            return Err(Error::InvalidTape("register tape has no slots"));
+        }
+        let reg_ok = |r: u8| (r as usize) < N && (r as u32) < self.slot_count;
+        for op in &self.tape {
+            let ok = match *op {
+                RegOp::Input(out, i) => {
+                    reg_ok(out) && (i as u32) < self.var_count
+                }
+                RegOp::Load(reg, mem) | RegOp::Store(reg, mem) => {
+                    reg_ok(reg) && mem as usize >= N && mem < self.slot_count
+                }
+                RegOp::CopyImm(out, ..) => reg_ok(out),
+                RegOp::NegReg(out, arg)
+                | RegOp::AbsReg(out, arg)
+                | RegOp::RecipReg(out, arg)
+                | RegOp::SqrtReg(out, arg)
+                | RegOp::SquareReg(out, arg)
+                | RegOp::FloorReg(out, arg)
+                | RegOp::CeilReg(out, arg)
+                | RegOp::RoundReg(out, arg)
+                | RegOp::CopyReg(out, arg)
+                | RegOp::SinReg(out, arg)
+                | RegOp::CosReg(out, arg)
+                | RegOp::TanReg(out, arg)
+                | RegOp::AsinReg(out, arg)
+                | RegOp::AcosReg(out, arg)
+                | RegOp::AtanReg(out, arg)
+                | RegOp::ExpReg(out, arg)
+                | RegOp::LnReg(out, arg)
+                | RegOp::NotReg(out, arg)
+                | RegOp::AddRegImm(out, arg, ..)
+                | RegOp::MulRegImm(out, arg, ..)
+                | RegOp::DivRegImm(out, arg, ..)
+                | RegOp::DivImmReg(out, arg, ..)
+                | RegOp::SubImmReg(out, arg, ..)
+                | RegOp::SubRegImm(out, arg, ..)
+                | RegOp::AtanRegImm(out, arg, ..)
+                | RegOp::AtanImmReg(out, arg, ..)
+                | RegOp::MinRegImm(out, arg, ..)
+                | RegOp::MaxRegImm(out, arg, ..)
+                | RegOp::CompareRegImm(out, arg, ..)
+                | RegOp::CompareImmReg(out, arg, ..)
+                | RegOp::ModRegImm(out, arg, ..)
+                | RegOp::ModImmReg(out, arg, ..)
+                | RegOp::AndRegImm(out, arg, ..)
+                | RegOp::OrRegImm(out, arg, ..) => reg_ok(out) && reg_ok(arg),
+                RegOp::AddRegReg(out, lhs, rhs)
+                | RegOp::MulRegReg(out, lhs, rhs)
+                | RegOp::DivRegReg(out, lhs, rhs)
+                | RegOp::SubRegReg(out, lhs, rhs)
+                | RegOp::AtanRegReg(out, lhs, rhs)
+                | RegOp::MinRegReg(out, lhs, rhs)
+                | RegOp::MaxRegReg(out, lhs, rhs)
+                | RegOp::CompareRegReg(out, lhs, rhs)
+                | RegOp::ModRegReg(out, lhs, rhs)
+                | RegOp::AndRegReg(out, lhs, rhs)
+                | RegOp::OrRegReg(out, lhs, rhs) => {
+                    reg_ok(out) && reg_ok(lhs) && reg_ok(rhs)
+                }
+            };
+            if !ok {
+                return Err(Error::InvalidTape(
+                    "register or memory slot is out of range",
+                ));
+            }
+        }
+        Ok(())
+    }
     #[inline]
     pub(crate) fn push(&mut self, op: RegOp) {
         self.tape.push(op)
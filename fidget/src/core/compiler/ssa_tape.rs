@@ -6,7 +6,30 @@ use crate::{
 };
 use serde::{Deserialize, Serialize};
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Largest number of slots or choices which may be used by a single tape
+///
+/// Slot and choice indices are stored as `u32`, with `u32::MAX` reserved as a
+/// sentinel for unassigned slots during register allocation.
+pub const MAX_TAPE_INDEX: usize = u32::MAX as usize - 1;
+
+/// Largest number of named inputs which may be used by a single tape
+///
+/// Register-allocated tapes store input indices as `u8`.
+pub const MAX_INPUTS: usize = u8::MAX as usize + 1;
+
+/// Converts an index to a `u32`, returning `err` if it is out of range
+fn checked_index(i: usize, err: fn(usize) -> Error) -> Result<u32, Error> {
+    if i < MAX_TAPE_INDEX {
+        Ok(i as u32)
+    } else {
+        Err(err(i))
+    }
+}
 
 /// Instruction tape, storing [opcodes in SSA form](crate::compiler::SsaOp)
 ///
@@ -25,6 +48,17 @@ pub struct SsaTape {
 
     /// Number of choice operations in the tape
     pub choice_count: usize,
+
+    /// Names of [defined](crate::Context::define) nodes, keyed by SSA slot
+    ///
+    /// This is only used when pretty-printing the tape.
+    pub names: BTreeMap<u32, Arc<str>>,
+
+    /// Names of the tape's inputs, in argument order
+    ///
+    /// If this is `None`, then the inputs are `X`, `Y`, and `Z`.
+    #[serde(default)]
+    pub inputs: Option<Arc<[Arc<str>]>>,
 }
 
 impl SsaTape {
@@ -32,10 +66,45 @@ impl SsaTape {
     ///
     /// This should always succeed unless the `root` is from a different
     /// `Context`, in which case `Error::BadNode` will be returned.
+    ///
+    /// Extremely large expressions return [`Error::TooManySlots`] or
+    /// [`Error::TooManyChoices`] if they would overflow a `u32` index.
     pub fn new(ctx: &Context, root: Node) -> Result<Self, Error> {
+        Self::build(ctx, root, None)
+    }
+
+    /// Flattens a subtree of the graph, with an explicit list of inputs
+    ///
+    /// Each variable in the expression is mapped to its position in `inputs`,
+    /// so the tape's evaluators take one argument per name (in that order),
+    /// rather than `X`, `Y`, and `Z`.
+    ///
+    /// Returns [`Error::UnknownVariable`] if the expression uses a variable
+    /// that's not in `inputs`, [`Error::DuplicateName`] if a name is repeated,
+    /// and [`Error::TooManyInputs`] if there are more than [`MAX_INPUTS`].
+    pub fn new_with_inputs(
+        ctx: &Context,
+        root: Node,
+        inputs: &[&str],
+    ) -> Result<Self, Error> {
+        if inputs.len() > MAX_INPUTS {
+            return Err(Error::TooManyInputs(inputs.len()));
+        }
+        let mut seen = HashSet::new();
+        if !inputs.iter().all(|name| seen.insert(name)) {
+            return Err(Error::DuplicateName);
+        }
+        Self::build(ctx, root, Some(inputs))
+    }
+
+    fn build(
+        ctx: &Context,
+        root: Node,
+        inputs: Option<&[&str]>,
+    ) -> Result<Self, Error> {
         let mut mapping = HashMap::new();
         let mut parent_count: HashMap<Node, usize> = HashMap::new();
-        let mut slot_count = 0;
+        let mut slot_count: usize = 0;
 
         // Get either a node or constant index
         #[derive(Copy, Clone)]
@@ -57,7 +126,7 @@ impl SsaTape {
                     mapping.insert(node, Slot::Immediate(c.0 as f32))
                 }
                 _ => {
-                    let i = slot_count;
+                    let i = checked_index(slot_count, Error::TooManySlots)?;
                     slot_count += 1;
                     mapping.insert(node, Slot::Reg(i))
                 }
@@ -74,6 +143,11 @@ impl SsaTape {
         let mut todo = vec![root];
         let mut choice_count = 0;
         let mut tape = vec![];
+        let mut names = BTreeMap::new();
+        let mut defs: HashMap<Node, &str> = HashMap::new();
+        for (name, node) in ctx.definitions() {
+            defs.entry(node).or_insert(name);
+        }
         while let Some(node) = todo.pop() {
             if *parent_count.get(&node).unwrap_or(&0) > 0 || !seen.insert(node)
             {
@@ -89,15 +163,18 @@ impl SsaTape {
                 // Constants are skipped, because they become immediates
                 continue;
             };
+            if let Some(name) = defs.get(&node) {
+                names.insert(i, Arc::<str>::from(*name));
+            }
             let op = match op {
                 Op::Input(..) => {
-                    let arg = match ctx.var_name(node).unwrap().unwrap() {
-                        "X" => 0,
-                        "Y" => 1,
-                        "Z" => 2,
-                        i => panic!("Unexpected input index: {i}"),
-                    };
-                    SsaOp::Input(i, arg)
+                    let name = ctx.var_name(node).unwrap().unwrap();
+                    let arg = match inputs {
+                        Some(inputs) => inputs.iter().position(|n| *n == name),
+                        None => ["X", "Y", "Z"].iter().position(|n| *n == name),
+                    }
+                    .ok_or_else(|| Error::UnknownVariable(name.to_owned()))?;
+                    SsaOp::Input(i, arg as u32)
                 }
                 Op::Const(..) => {
                     unreachable!("skipped above")
@@ -177,6 +254,7 @@ impl SsaTape {
                             | BinaryOpcode::And
                             | BinaryOpcode::Or
                     ) {
+                        checked_index(choice_count, Error::TooManyChoices)?;
                         choice_count += 1;
                     }
 
@@ -232,7 +310,13 @@ impl SsaTape {
             tape.push(SsaOp::CopyImm(0, c));
         }
 
-        Ok(SsaTape { tape, choice_count })
+        Ok(SsaTape {
+            tape,
+            choice_count,
+            names,
+            inputs: inputs
+                .map(|inputs| inputs.iter().map(|&s| Arc::from(s)).collect()),
+        })
     }
 
     /// Checks whether the tape is empty
@@ -256,13 +340,115 @@ impl SsaTape {
     pub fn reset(&mut self) {
         self.tape.clear();
         self.choice_count = 0;
+        self.names.clear();
+        self.inputs = None;
+    }
+
+    /// Returns the number of input slots
+    ///
+    /// This is the length of [`inputs`](Self::inputs), or 3 if the tape uses
+    /// `X`, `Y`, and `Z`.
+    pub fn input_count(&self) -> usize {
+        self.inputs.as_ref().map(|i| i.len()).unwrap_or(3)
+    }
+
+    /// Checks that the tape is well-formed
+    ///
+    /// Tapes built with [`SsaTape::new`] are always valid; this is used when
+    /// loading a tape from elsewhere (e.g. during deserialization).
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        match self.tape.first() {
+            None => return Err(Error::InvalidTape("SSA tape is empty")),
+            Some(op) if op.output() != 0 => {
+                return Err(Error::InvalidTape("SSA tape must begin at slot 0"))
+            }
+            Some(..) => (),
+        }
+        if self.input_count() > MAX_INPUTS {
+            return Err(Error::InvalidTape("SSA tape has too many inputs"));
+        }
+        let mut choice_count = 0;
+        for op in &self.tape {
+            if let SsaOp::Input(_, i) = *op {
+                if i as usize >= self.input_count() {
+                    return Err(Error::InvalidTape(
+                        "SSA input is out of range",
+                    ));
+                }
+            }
+            let (out, lhs, rhs) = match *op {
+                SsaOp::Input(out, ..) | SsaOp::CopyImm(out, ..) => {
+                    (out, None, None)
+                }
+                SsaOp::NegReg(out, arg)
+                | SsaOp::AbsReg(out, arg)
+                | SsaOp::RecipReg(out, arg)
+                | SsaOp::SqrtReg(out, arg)
+                | SsaOp::SquareReg(out, arg)
+                | SsaOp::FloorReg(out, arg)
+                | SsaOp::CeilReg(out, arg)
+                | SsaOp::RoundReg(out, arg)
+                | SsaOp::CopyReg(out, arg)
+                | SsaOp::SinReg(out, arg)
+                | SsaOp::CosReg(out, arg)
+                | SsaOp::TanReg(out, arg)
+                | SsaOp::AsinReg(out, arg)
+                | SsaOp::AcosReg(out, arg)
+                | SsaOp::AtanReg(out, arg)
+                | SsaOp::ExpReg(out, arg)
+                | SsaOp::LnReg(out, arg)
+                | SsaOp::NotReg(out, arg)
+                | SsaOp::AddRegImm(out, arg, ..)
+                | SsaOp::MulRegImm(out, arg, ..)
+                | SsaOp::DivRegImm(out, arg, ..)
+                | SsaOp::DivImmReg(out, arg, ..)
+                | SsaOp::SubImmReg(out, arg, ..)
+                | SsaOp::SubRegImm(out, arg, ..)
+                | SsaOp::AtanRegImm(out, arg, ..)
+                | SsaOp::AtanImmReg(out, arg, ..)
+                | SsaOp::MinRegImm(out, arg, ..)
+                | SsaOp::MaxRegImm(out, arg, ..)
+                | SsaOp::CompareRegImm(out, arg, ..)
+                | SsaOp::CompareImmReg(out, arg, ..)
+                | SsaOp::ModRegImm(out, arg, ..)
+                | SsaOp::ModImmReg(out, arg, ..)
+                | SsaOp::AndRegImm(out, arg, ..)
+                | SsaOp::OrRegImm(out, arg, ..) => (out, Some(arg), None),
+                SsaOp::AddRegReg(out, lhs, rhs)
+                | SsaOp::MulRegReg(out, lhs, rhs)
+                | SsaOp::DivRegReg(out, lhs, rhs)
+                | SsaOp::SubRegReg(out, lhs, rhs)
+                | SsaOp::AtanRegReg(out, lhs, rhs)
+                | SsaOp::MinRegReg(out, lhs, rhs)
+                | SsaOp::MaxRegReg(out, lhs, rhs)
+                | SsaOp::CompareRegReg(out, lhs, rhs)
+                | SsaOp::ModRegReg(out, lhs, rhs)
+                | SsaOp::AndRegReg(out, lhs, rhs)
+                | SsaOp::OrRegReg(out, lhs, rhs) => (out, Some(lhs), Some(rhs)),
+            };
+            // Slots are numbered densely, so every index must be in the tape
+            if [Some(out), lhs, rhs]
+                .into_iter()
+                .flatten()
+                .any(|i| i as usize >= self.tape.len())
+            {
+                return Err(Error::InvalidTape("SSA slot is out of range"));
+            }
+            if op.has_choice() {
+                choice_count += 1;
+            }
+        }
+        if choice_count != self.choice_count {
+            return Err(Error::InvalidTape("SSA choice count is incorrect"));
+        }
+        Ok(())
     }
     /// Pretty-prints the given tape to `stdout`
     pub fn pretty_print(&self) {
         for &op in self.tape.iter().rev() {
-            match op {
+            let line = match op {
                 SsaOp::Input(out, i) => {
-                    println!("${out} = INPUT {i}");
+                    format!("${out} = INPUT {i}")
                 }
                 SsaOp::NegReg(out, arg)
                 | SsaOp::AbsReg(out, arg)
@@ -303,7 +489,7 @@ impl SsaTape {
                         SsaOp::CopyReg(..) => "COPY",
                         _ => unreachable!(),
                     };
-                    println!("${out} = {op} ${arg}");
+                    format!("${out} = {op} ${arg}")
                 }
 
                 SsaOp::AddRegReg(out, lhs, rhs)
@@ -329,7 +515,7 @@ impl SsaTape {
                         SsaOp::OrRegReg(..) => "OR",
                         _ => unreachable!(),
                     };
-                    println!("${out} = {op} ${lhs} ${rhs}");
+                    format!("${out} = {op} ${lhs} ${rhs}")
                 }
 
                 SsaOp::AddRegImm(out, arg, imm)
@@ -364,23 +550,27 @@ impl SsaTape {
                         _ => unreachable!(),
                     };
                     if swap {
-                        println!("${out} = {op} {imm} ${arg}");
+                        format!("${out} = {op} {imm} ${arg}")
                     } else {
-                        println!("${out} = {op} ${arg} {imm}");
+                        format!("${out} = {op} ${arg} {imm}")
                     }
                 }
                 SsaOp::CompareRegReg(out, lhs, rhs) => {
-                    println!("${out} = COMPARE {lhs} {rhs}")
+                    format!("${out} = COMPARE {lhs} {rhs}")
                 }
                 SsaOp::CompareRegImm(out, arg, imm) => {
-                    println!("${out} = COMPARE {arg} {imm}")
+                    format!("${out} = COMPARE {arg} {imm}")
                 }
                 SsaOp::CompareImmReg(out, arg, imm) => {
-                    println!("${out} = COMPARE {imm} {arg}")
+                    format!("${out} = COMPARE {imm} {arg}")
                 }
                 SsaOp::CopyImm(out, imm) => {
-                    println!("${out} = COPY {imm}");
+                    format!("${out} = COPY {imm}")
                 }
+            };
+            match self.names.get(&op.output()) {
+                Some(name) => println!("{line:<24} # {name}"),
+                None => println!("{line}"),
             }
         }
     }
@@ -417,4 +607,39 @@ mod test {
         let tape = SsaTape::new(&ctx, x_squared).unwrap();
         assert_eq!(tape.len(), 2);
     }
+
+    #[test]
+    fn test_names() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let x2 = ctx.square(x).unwrap();
+        let x2 = ctx.define("x2", x2).unwrap();
+        let c = ctx.define("c", 2.0).unwrap();
+        let out = ctx.add(x2, y).unwrap();
+        let out = ctx.mul(out, c).unwrap();
+
+        let tape = SsaTape::new(&ctx, out).unwrap();
+        assert_eq!(tape.names.len(), 1);
+        let (slot, name) = tape.names.iter().next().unwrap();
+        assert_eq!(&**name, "x2");
+        assert!(tape
+            .iter()
+            .any(|op| matches!(op, SsaOp::SquareReg(out, _) if out == slot)));
+    }
+
+    #[test]
+    fn test_checked_index() {
+        assert_eq!(checked_index(0, Error::TooManySlots).unwrap(), 0);
+        let last = MAX_TAPE_INDEX - 1;
+        assert_eq!(
+            checked_index(last, Error::TooManySlots).unwrap() as usize,
+            last
+        );
+        assert!(matches!(
+            checked_index(MAX_TAPE_INDEX, Error::TooManyChoices),
+            Err(Error::TooManyChoices(i)) if i == MAX_TAPE_INDEX
+        ));
+        assert!(checked_index(usize::MAX, Error::TooManySlots).is_err());
+    }
 }
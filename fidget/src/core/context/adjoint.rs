@@ -0,0 +1,247 @@
+//! Reverse-mode differentiation over many variables
+use crate::{
+    context::{BinaryOpcode, Context, Node, Op, UnaryOpcode},
+    Error,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Operation in an [`AdjointTape`], with arguments as indices into the tape
+#[derive(Copy, Clone, Debug)]
+enum AdjointOp {
+    Input(usize),
+    Const(f64),
+    Unary(UnaryOpcode, usize),
+    Binary(BinaryOpcode, usize, usize),
+}
+
+/// A flattened expression for computing gradients with respect to every
+/// variable in a single backward sweep
+///
+/// [`Context::eval_grad`] does the same work, but it walks the whole
+/// [`Context`] and allocates maps on every call; an `AdjointTape` is built
+/// once and then evaluated (by an [`AdjointEval`]) as many times as needed,
+/// e.g. during each step of gradient descent over shape parameters.
+///
+/// Unlike tapes for [`Shape`](crate::eval::Shape) evaluation, this works with
+/// any number of custom variables, and evaluates in double precision.
+///
+/// ```
+/// # use fidget::context::{Context, AdjointTape, AdjointEval};
+/// let mut ctx = Context::new();
+/// let x = ctx.x();
+/// let r = ctx.var("r").unwrap();
+/// let x2 = ctx.square(x).unwrap();
+/// let op = ctx.sub(x2, r).unwrap();
+///
+/// let tape = AdjointTape::new(&ctx, op).unwrap();
+/// assert_eq!(tape.vars(), ["X", "r"]);
+///
+/// let mut eval = AdjointEval::default();
+/// let (v, grad) = eval.eval(&tape, &[3.0, 2.0]).unwrap();
+/// assert_eq!(v, 7.0);
+/// assert_eq!(grad, [6.0, -1.0]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct AdjointTape {
+    /// Operations in evaluation order (children before parents), with the
+    /// root as the last item
+    ops: Vec<AdjointOp>,
+    /// Variable names, in alphabetical order
+    vars: Vec<String>,
+}
+
+impl AdjointTape {
+    /// Flattens the expression rooted at `root`
+    pub fn new(ctx: &Context, root: Node) -> Result<Self, Error> {
+        // Find every reachable node, then flatten them in index order (which
+        // is also topological order, because children are always created
+        // before their parents)
+        let mut seen = BTreeSet::new();
+        let mut todo = vec![root];
+        while let Some(node) = todo.pop() {
+            if seen.insert(node) {
+                let op = ctx.get_op(node).ok_or(Error::BadNode)?;
+                todo.extend(op.iter_children());
+            }
+        }
+
+        let vars: BTreeSet<&str> = seen
+            .iter()
+            .filter_map(|n| ctx.var_name(*n).unwrap())
+            .collect();
+        let var_index: BTreeMap<&str, usize> =
+            vars.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+
+        let mut index = BTreeMap::new();
+        let mut ops = Vec::with_capacity(seen.len());
+        for node in seen {
+            let op = match *ctx.get_op(node).unwrap() {
+                Op::Input(..) => {
+                    let name = ctx.var_name(node).unwrap().unwrap();
+                    AdjointOp::Input(var_index[name])
+                }
+                Op::Const(c) => AdjointOp::Const(c.0),
+                Op::Unary(op, a) => AdjointOp::Unary(op, index[&a]),
+                Op::Binary(op, a, b) => {
+                    AdjointOp::Binary(op, index[&a], index[&b])
+                }
+            };
+            index.insert(node, ops.len());
+            ops.push(op);
+        }
+        Ok(Self {
+            ops,
+            vars: vars.into_iter().map(str::to_owned).collect(),
+        })
+    }
+
+    /// Returns the variables used by this tape, in alphabetical order
+    ///
+    /// This is the order of inputs to [`AdjointEval::eval`], and of the
+    /// gradient that it returns.
+    pub fn vars(&self) -> &[String] {
+        &self.vars
+    }
+
+    /// Looks up the index of a variable by name
+    pub fn var_index(&self, name: &str) -> Option<usize> {
+        self.vars.binary_search_by(|v| v.as_str().cmp(name)).ok()
+    }
+
+    /// Returns the number of operations in the tape
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Checks whether the tape is empty
+    ///
+    /// This is always `false`, because a tape contains at least its root.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// Evaluator for an [`AdjointTape`]
+///
+/// The evaluator owns scratch storage, which is reused between calls.
+#[derive(Default)]
+pub struct AdjointEval {
+    values: Vec<f64>,
+    adjoints: Vec<f64>,
+    grad: Vec<f64>,
+}
+
+impl AdjointEval {
+    /// Evaluates the tape and its gradient
+    ///
+    /// `vars` must have one value per variable, in the order given by
+    /// [`AdjointTape::vars`].  Returns the value and a slice of partial
+    /// derivatives (in the same order), computed with one forward and one
+    /// backward pass regardless of the number of variables.
+    pub fn eval(
+        &mut self,
+        tape: &AdjointTape,
+        vars: &[f64],
+    ) -> Result<(f64, &[f64]), Error> {
+        if vars.len() != tape.vars.len() {
+            return Err(Error::BadVarSlice(vars.len(), tape.vars.len()));
+        }
+        self.values.clear();
+        for op in &tape.ops {
+            let v = match *op {
+                AdjointOp::Input(i) => vars[i],
+                AdjointOp::Const(c) => c,
+                AdjointOp::Unary(op, a) => op.eval(self.values[a]),
+                AdjointOp::Binary(op, a, b) => {
+                    op.eval(self.values[a], self.values[b])
+                }
+            };
+            self.values.push(v);
+        }
+
+        self.adjoints.clear();
+        self.adjoints.resize(tape.ops.len(), 0.0);
+        self.grad.clear();
+        self.grad.resize(tape.vars.len(), 0.0);
+        *self.adjoints.last_mut().unwrap() = 1.0;
+        for (i, op) in tape.ops.iter().enumerate().rev() {
+            // Skip unused nodes, and branches which weren't chosen (whose
+            // derivatives may be infinite)
+            let d = self.adjoints[i];
+            if d == 0.0 {
+                continue;
+            }
+            match *op {
+                AdjointOp::Input(v) => self.grad[v] += d,
+                AdjointOp::Const(..) => (),
+                AdjointOp::Unary(op, a) => {
+                    self.adjoints[a] += d * op.deriv(self.values[a]);
+                }
+                AdjointOp::Binary(op, a, b) => {
+                    let (da, db) = op.deriv(self.values[a], self.values[b]);
+                    self.adjoints[a] += d * da;
+                    self.adjoints[b] += d * db;
+                }
+            }
+        }
+        Ok((*self.values.last().unwrap(), &self.grad))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_adjoint_matches_eval_grad() {
+        // A sum of many parameterized circles, with a min to exercise choices
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let mut out = None;
+        for i in 0..20 {
+            let cx = ctx.var(&format!("cx{i}")).unwrap();
+            let r = ctx.var(&format!("r{i}")).unwrap();
+            let dx = ctx.sub(x, cx).unwrap();
+            let dx2 = ctx.square(dx).unwrap();
+            let y2 = ctx.square(y).unwrap();
+            let d = ctx.add(dx2, y2).unwrap();
+            let d = ctx.sqrt(d).unwrap();
+            let d = ctx.sub(d, r).unwrap();
+            out = Some(match out {
+                Some(o) => ctx.min(o, d).unwrap(),
+                None => d,
+            });
+        }
+        let root = out.unwrap();
+
+        let tape = AdjointTape::new(&ctx, root).unwrap();
+        assert_eq!(tape.vars().len(), 42);
+        assert_eq!(tape.var_index("X"), Some(0));
+        assert!(tape.var_index("q").is_none());
+
+        let mut eval = AdjointEval::default();
+        assert!(matches!(
+            eval.eval(&tape, &[0.0]),
+            Err(Error::BadVarSlice(1, 42))
+        ));
+
+        for k in 0..3 {
+            let vars: Vec<f64> = (0..tape.vars().len())
+                .map(|i| ((i * 7 + k * 3) % 11) as f64 / 5.0 - 1.0)
+                .collect();
+            let named = tape
+                .vars()
+                .iter()
+                .cloned()
+                .zip(vars.iter().cloned())
+                .collect();
+            let (v, grad) = eval.eval(&tape, &vars).unwrap();
+            let (v_ref, grad_ref) = ctx.eval_grad(root, &named).unwrap();
+            assert_eq!(v, v_ref);
+            for (name, g) in tape.vars().iter().zip(grad) {
+                assert_eq!(*g, grad_ref[name], "mismatch for {name}");
+            }
+        }
+    }
+}
@@ -1,10 +1,10 @@
 //! Container types with strongly-typed indexes.
-use crate::Error;
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 /// Stores a set of `(V, I)` tuples, with lookup in both directions.
 ///
-/// Implemented using a `Vec<V>` and a `HashMap<V, I>`.
+/// Implemented using a `Vec<V>` and a `HashMap<V, I>`, optionally layered on
+/// top of a frozen, shared parent map (see [`IndexMap::freeze`]).
 ///
 /// The index type `I` should be a wrapper around a `usize` and be convertible
 /// in both directions using the `Index` trait; it is typically passed around
@@ -15,6 +15,8 @@ use std::collections::HashMap;
 /// in the `Vec` and once in the `HashMap`).
 #[derive(Clone, Debug)]
 pub(crate) struct IndexMap<V, Index> {
+    /// Shared items, which occupy indexes `0..parent.len()`
+    parent: Option<Arc<IndexMap<V, Index>>>,
     data: Vec<V>,
     map: HashMap<V, Index>,
 }
@@ -22,6 +24,7 @@ pub(crate) struct IndexMap<V, Index> {
 impl<V, Index> Default for IndexMap<V, Index> {
     fn default() -> Self {
         Self {
+            parent: None,
             data: vec![],
             map: HashMap::new(),
         }
@@ -38,47 +41,94 @@ where
     V: Eq + std::hash::Hash + Clone,
     I: Eq + std::hash::Hash + Copy + Index,
 {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            parent: None,
+            data: Vec::with_capacity(capacity),
+            map: HashMap::with_capacity(capacity),
+        }
+    }
+
     pub fn clear(&mut self) {
+        self.parent = None;
         self.data.clear();
         self.map.clear();
     }
 
+    pub fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+        self.map.reserve(additional);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+        self.map.shrink_to_fit();
+    }
+
+    /// Returns the number of items that can be stored without reallocating
+    pub fn capacity(&self) -> usize {
+        self.parent_len() + self.data.capacity().min(self.map.capacity())
+    }
+
+    fn parent_len(&self) -> usize {
+        self.parent.as_ref().map(|p| p.len()).unwrap_or(0)
+    }
+
     pub fn len(&self) -> usize {
-        self.data.len()
+        self.parent_len() + self.data.len()
     }
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.len() == 0
     }
     pub fn get_by_index(&self, i: I) -> Option<&V> {
-        self.data.get(i.get())
+        let offset = self.parent_len();
+        match i.get().checked_sub(offset) {
+            Some(j) => self.data.get(j),
+            None => self.parent.as_ref().unwrap().get_by_index(i),
+        }
+    }
+    /// Looks up the handle for a value, if it's in the map
+    fn get_index(&self, v: &V) -> Option<I> {
+        self.map
+            .get(v)
+            .copied()
+            .or_else(|| self.parent.as_ref().and_then(|p| p.get_index(v)))
     }
     /// Insert the given value into the map, returning a handle.
     ///
     /// If the value is already in the map, the handle will be to the existing
     /// instance (so it will not be inserted twice).
     pub fn insert(&mut self, v: V) -> I {
+        if let Some(i) = self.parent.as_ref().and_then(|p| p.get_index(&v)) {
+            return i;
+        }
+        let offset = self.parent_len();
         *self.map.entry(v.clone()).or_insert_with(|| {
-            let out = I::new(self.data.len());
+            let out = I::new(offset + self.data.len());
             self.data.push(v);
             out
         })
     }
 
-    /// Removes the last value stored in the container.
+    pub fn keys(&self) -> impl Iterator<Item = I> {
+        (0..self.len()).map(I::new)
+    }
+
+    /// Freezes the current contents into a shared parent
     ///
-    /// This is _usually_ the most recently inserted value, except when
-    /// `insert` is called on a duplicate.
-    pub fn pop(&mut self) -> Result<V, Error> {
-        match self.data.pop() {
-            Some(v) => {
-                self.map.remove(&v);
-                Ok(v)
-            }
-            None => Err(Error::EmptyMap),
+    /// Future insertions are stored locally.  The returned map shares the
+    /// same parent, so it's a cheap copy of this map; handles are valid in
+    /// both maps, but handles created after the freeze are not shared.
+    pub fn freeze(&mut self) -> Self {
+        if !self.data.is_empty() || self.parent.is_none() {
+            let prev = std::mem::take(self);
+            self.parent = Some(Arc::new(prev));
+        }
+        Self {
+            parent: self.parent.clone(),
+            data: vec![],
+            map: HashMap::new(),
         }
-    }
-    pub fn keys(&self) -> impl Iterator<Item = I> {
-        (0..self.data.len()).map(I::new)
     }
 }
 
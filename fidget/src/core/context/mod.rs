@@ -14,17 +14,21 @@
 //!   objects for evaluation.
 //!
 //! In other words, the typical workflow is `Tree → (Context, Node) → Shape`.
+mod adjoint;
 mod indexed;
 mod op;
+mod rewrite;
 mod tree;
 
+pub use adjoint::{AdjointEval, AdjointTape};
 use indexed::{define_index, Index, IndexMap, IndexVec};
 pub use op::{BinaryOpcode, Op, UnaryOpcode};
+pub use rewrite::{Captures, Pattern, RewriteRule, Rewriter, Rule};
 pub use tree::{Tree, TreeOp};
 
-use crate::Error;
+use crate::{types::Interval, Error};
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::Write;
 use std::io::{BufRead, BufReader, Read};
 use std::sync::Arc;
@@ -43,6 +47,58 @@ define_index!(VarNode, "An index in the `Context::vars` map");
 pub struct Context {
     ops: IndexMap<Op, Node>,
     vars: IndexMap<String, VarNode>,
+    names: BTreeMap<String, Node>,
+
+    /// Known value ranges, either annotated with [`Context::set_range`] or
+    /// derived from annotated children
+    ranges: BTreeMap<Node, Interval>,
+
+    /// Whether to canonicalize affine expressions during construction
+    affine: bool,
+}
+
+/// Set of inputs used by an expression, returned by [`Context::inputs`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Inputs {
+    /// Whether the expression uses the X axis
+    pub x: bool,
+    /// Whether the expression uses the Y axis
+    pub y: bool,
+    /// Whether the expression uses the Z axis
+    pub z: bool,
+    /// Names of any other variables used by the expression
+    pub vars: BTreeSet<String>,
+}
+
+impl Inputs {
+    /// Checks whether the expression is independent of all inputs
+    pub fn is_constant(&self) -> bool {
+        !(self.x || self.y || self.z) && self.vars.is_empty()
+    }
+
+    /// Checks whether the expression is independent of the Z axis
+    ///
+    /// Such an expression can be rendered with a 2D renderer without losing
+    /// any information.
+    pub fn is_2d(&self) -> bool {
+        !self.z
+    }
+
+    /// Checks that every input has a value in the given set of bindings
+    ///
+    /// Returns [`Error::UnknownVariable`] for the first missing input.
+    pub fn check_bindings<'a, I>(&self, names: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let bound: BTreeSet<&str> = names.into_iter().collect();
+        let axes = [(self.x, "X"), (self.y, "Y"), (self.z, "Z")];
+        axes.into_iter()
+            .filter_map(|(used, name)| used.then_some(name))
+            .chain(self.vars.iter().map(String::as_str))
+            .find(|name| !bound.contains(name))
+            .map_or(Ok(()), |name| Err(Error::UnknownVariable(name.to_owned())))
+    }
 }
 
 impl Context {
@@ -51,6 +107,148 @@ impl Context {
         Self::default()
     }
 
+    /// Build a new empty context with space for at least `capacity` nodes
+    ///
+    /// This avoids repeated reallocation when building very large expressions.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            ops: IndexMap::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Reserves space for at least `additional` more nodes
+    pub fn reserve(&mut self, additional: usize) {
+        self.ops.reserve(additional);
+    }
+
+    /// Returns the number of nodes that can be stored without reallocating
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::with_capacity(1000);
+    /// assert!(ctx.capacity() >= 1000);
+    /// ctx.reserve(2000);
+    /// assert!(ctx.capacity() >= 2000);
+    /// ctx.shrink_to_fit();
+    /// assert_eq!(ctx.capacity(), 0);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.ops.capacity()
+    }
+
+    /// Shrinks the context's storage as much as possible
+    ///
+    /// This is useful for releasing memory after a large context has been
+    /// [cleared](Self::clear), or after tapes have been extracted from it;
+    /// existing [`Node`] handles remain valid.
+    pub fn shrink_to_fit(&mut self) {
+        self.ops.shrink_to_fit();
+        self.vars.shrink_to_fit();
+    }
+
+    /// Copies the subgraph rooted at `root` into a new, compact context
+    ///
+    /// Only nodes which are reachable from `root` are copied, so this can be
+    /// used to discard garbage after many rounds of editing.  Named
+    /// definitions (see [`Context::define`]) are kept if their node is
+    /// reachable, as are value ranges (see [`Context::set_range`]); the affine
+    /// canonicalization setting is preserved.
+    ///
+    /// Returns the new context and the root node within it.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let _garbage = ctx.mul(x, y).unwrap();
+    /// let sum = ctx.add(x, 1.0).unwrap();
+    /// assert_eq!(ctx.len(), 5);
+    ///
+    /// let (new_ctx, new_sum) = ctx.extract(sum).unwrap();
+    /// assert_eq!(new_ctx.len(), 3);
+    /// assert_eq!(new_ctx.eval_xyz(new_sum, 2.0, 0.0, 0.0).unwrap(), 3.0);
+    /// ```
+    pub fn extract(&self, root: Node) -> Result<(Context, Node), Error> {
+        self.check_node(root)?;
+
+        // Find every reachable node, then copy them in index order (which is
+        // also topological order, because children are always inserted first)
+        let mut seen = BTreeSet::new();
+        let mut todo = vec![root];
+        while let Some(node) = todo.pop() {
+            if seen.insert(node) {
+                todo.extend(self.get_op(node).unwrap().iter_children());
+            }
+        }
+
+        let mut out = Context::with_capacity(seen.len());
+        out.affine = self.affine;
+        let mut remap = HashMap::with_capacity(seen.len());
+        for node in seen {
+            let op = match *self.get_op(node).unwrap() {
+                Op::Input(v) => {
+                    let name = self.vars.get_by_index(v).unwrap();
+                    Op::Input(out.vars.insert(name.clone()))
+                }
+                Op::Const(c) => Op::Const(c),
+                Op::Binary(op, a, b) => Op::Binary(op, remap[&a], remap[&b]),
+                Op::Unary(op, a) => Op::Unary(op, remap[&a]),
+            };
+            remap.insert(node, out.ops.insert(op));
+        }
+        for (name, node) in &self.names {
+            if let Some(n) = remap.get(node) {
+                out.names.insert(name.clone(), *n);
+            }
+        }
+        for (node, r) in &self.ranges {
+            if let Some(n) = remap.get(node) {
+                out.ranges.insert(*n, *r);
+            }
+        }
+        Ok((out, remap[&root]))
+    }
+
+    /// Returns a cheap copy-on-write child of this context
+    ///
+    /// The current contents are frozen into shared storage, so forking doesn't
+    /// copy any nodes; the parent and child then grow independently.  Every
+    /// [`Node`] from before the fork is valid in both contexts, but nodes
+    /// created afterwards must only be used with the context that made them.
+    ///
+    /// This is useful for speculative edits: build into a fork, then replace
+    /// the original context with it on success (or drop it on failure).
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let good = ctx.add(x, 1.0).unwrap();
+    ///
+    /// let mut child = ctx.fork();
+    /// let edit = child.mul(good, 2.0).unwrap();
+    /// assert_eq!(child.eval_xyz(edit, 1.0, 0.0, 0.0).unwrap(), 4.0);
+    ///
+    /// // The parent is unchanged, and still evaluates the last-good model
+    /// assert_eq!(ctx.len(), 3);
+    /// assert_eq!(ctx.eval_xyz(good, 1.0, 0.0, 0.0).unwrap(), 2.0);
+    ///
+    /// // Accept the edit
+    /// ctx = child;
+    /// assert_eq!(ctx.eval_xyz(edit, 1.0, 0.0, 0.0).unwrap(), 4.0);
+    /// ```
+    pub fn fork(&mut self) -> Context {
+        Context {
+            ops: self.ops.freeze(),
+            vars: self.vars.freeze(),
+            names: self.names.clone(),
+            ranges: self.ranges.clone(),
+            affine: self.affine,
+        }
+    }
+
     /// Clears the context
     ///
     /// All [`Node`] and [`VarNode`] handles from this context are invalidated.
@@ -65,6 +263,37 @@ impl Context {
     pub fn clear(&mut self) {
         self.ops.clear();
         self.vars.clear();
+        self.names.clear();
+        self.ranges.clear();
+    }
+
+    /// Enables or disables affine canonicalization (disabled by default)
+    ///
+    /// When enabled, [`add`](Self::add), [`sub`](Self::sub),
+    /// [`mul`](Self::mul), [`div`](Self::div), and [`neg`](Self::neg) track
+    /// expressions of the form `a·n + b` (where `a` and `b` are constants) and
+    /// emit them in a canonical form.  This means that equivalent expressions
+    /// like `2 * (x + 1)` and `2 * x + 2` are deduplicated into the same node,
+    /// which helps when building large arrays of translated primitives.
+    ///
+    /// Canonicalization reassociates floating-point arithmetic, so results may
+    /// differ from the non-canonicalized expression by a rounding error.  It
+    /// also assumes that values are finite: for example, `(x + 1) - x` is
+    /// reduced to the constant `1`.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// ctx.set_affine_canonicalization(true);
+    /// let x = ctx.x();
+    /// let a = ctx.add(x, 1.0).unwrap();
+    /// let a = ctx.mul(a, 2.0).unwrap();
+    ///
+    /// let b = ctx.mul(x, 2.0).unwrap();
+    /// let b = ctx.add(b, 2.0).unwrap();
+    /// assert_eq!(a, b);
+    /// ```
+    pub fn set_affine_canonicalization(&mut self, enabled: bool) {
+        self.affine = enabled;
     }
 
     /// Returns the number of [`Op`] nodes in the context
@@ -93,20 +322,6 @@ impl Context {
         self.get_op(node).ok_or(Error::BadNode).map(|_| ())
     }
 
-    /// Erases the most recently added node from the tree.
-    ///
-    /// A few caveats apply, so this must be used with caution:
-    /// - Existing handles to the node will be invalidated
-    /// - The most recently added node must be unique
-    ///
-    /// In practice, this is only used to delete temporary operation nodes
-    /// during constant folding.  Such nodes which have no handles (because
-    /// they are never returned) and are guaranteed to be unique (because we
-    /// never store them persistently).
-    fn pop(&mut self) -> Result<(), Error> {
-        self.ops.pop().map(|_| ())
-    }
-
     /// Looks up the constant associated with the given node.
     ///
     /// If the node is invalid for this tree, returns an error; if the node is
@@ -139,6 +354,469 @@ impl Context {
         }
     }
 
+    ////////////////////////////////////////////////////////////////////////////
+    // Input dependencies
+    /// Returns the set of inputs on which the given node depends
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let sum = ctx.add(x, y).unwrap();
+    /// let inputs = ctx.inputs(sum).unwrap();
+    /// assert!(inputs.x && inputs.y && !inputs.z);
+    /// assert!(inputs.is_2d());
+    /// ```
+    pub fn inputs(&self, node: Node) -> Result<Inputs, Error> {
+        self.check_node(node)?;
+        let mut out = Inputs::default();
+        for v in self.input_vars(node) {
+            match self.get_var_by_index(v)? {
+                "X" => out.x = true,
+                "Y" => out.y = true,
+                "Z" => out.z = true,
+                s => {
+                    out.vars.insert(s.to_owned());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Returns, for each input, the set of nodes in the subgraph of `root`
+    /// which depend on it
+    ///
+    /// Nodes which don't depend on any input (e.g. constants) are not included
+    /// in any set; inputs which aren't used by `root` are not included in the
+    /// map.
+    pub fn input_dependents(
+        &self,
+        root: Node,
+    ) -> Result<BTreeMap<String, BTreeSet<Node>>, Error> {
+        self.check_node(root)?;
+
+        // Find every node in the subgraph, then walk them in index order, which
+        // is also topological order (children are always inserted first).
+        let mut seen = BTreeSet::new();
+        let mut todo = vec![root];
+        while let Some(node) = todo.pop() {
+            if seen.insert(node) {
+                todo.extend(self.get_op(node).unwrap().iter_children());
+            }
+        }
+
+        let mut deps: BTreeMap<Node, BTreeSet<VarNode>> = BTreeMap::new();
+        let mut out: BTreeMap<String, BTreeSet<Node>> = BTreeMap::new();
+        for node in seen {
+            let vs: BTreeSet<VarNode> = match self.get_op(node).unwrap() {
+                Op::Input(v) => [*v].into(),
+                op => op
+                    .iter_children()
+                    .flat_map(|c| deps[&c].iter().cloned())
+                    .collect(),
+            };
+            for v in &vs {
+                let name = self.get_var_by_index(*v)?;
+                out.entry(name.to_owned()).or_default().insert(node);
+            }
+            deps.insert(node, vs);
+        }
+        Ok(out)
+    }
+
+    /// Returns the set of variables reachable from the given (valid) node
+    fn input_vars(&self, node: Node) -> BTreeSet<VarNode> {
+        let mut seen = BTreeSet::new();
+        let mut out = BTreeSet::new();
+        let mut todo = vec![node];
+        while let Some(node) = todo.pop() {
+            if !seen.insert(node) {
+                continue;
+            }
+            let op = self.get_op(node).unwrap();
+            if let Op::Input(v) = op {
+                out.insert(*v);
+            }
+            todo.extend(op.iter_children());
+        }
+        out
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Named definitions
+    /// Binds a name to the given node, returning the node
+    ///
+    /// Names are purely descriptive: they don't change the math expression,
+    /// but are preserved by [`Context::to_text`] and shown when
+    /// [pretty-printing a tape](crate::compiler::SsaTape::pretty_print).
+    ///
+    /// Calling `define` with a name that is already bound replaces the
+    /// previous definition; see [`Context::redefine`] to also update
+    /// expressions which use it.
+    ///
+    /// Names must be non-empty, must not contain whitespace, and must not
+    /// begin with `#` or `_` (which are used by the text format for comments
+    /// and anonymous nodes respectively).
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let y2 = ctx.square(y).unwrap();
+    /// let sum = ctx.add(x2, y2).unwrap();
+    /// let r2 = ctx.define("r2", sum).unwrap();
+    /// assert_eq!(ctx.get_defined("r2"), Some(r2));
+    /// assert_eq!(ctx.defined_name(r2), Some("r2"));
+    /// ```
+    pub fn define<A: IntoNode>(
+        &mut self,
+        name: &str,
+        a: A,
+    ) -> Result<Node, Error> {
+        if name.is_empty()
+            || name.starts_with(['#', '_'])
+            || name.contains(char::is_whitespace)
+        {
+            return Err(Error::BadName(name.to_owned()));
+        }
+        let a = a.into_node(self)?;
+        self.names.insert(name.to_owned(), a);
+        Ok(a)
+    }
+
+    /// Looks up the node bound to the given name
+    pub fn get_defined(&self, name: &str) -> Option<Node> {
+        self.names.get(name).cloned()
+    }
+
+    /// Looks up a name bound to the given node
+    ///
+    /// If multiple names are bound to the same node, the first in
+    /// alphabetical order is returned.
+    pub fn defined_name(&self, n: Node) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|(_, v)| **v == n)
+            .map(|(k, _)| k.as_str())
+    }
+
+    /// Iterates over all named definitions, in alphabetical order
+    pub fn definitions(&self) -> impl Iterator<Item = (&str, Node)> {
+        self.names.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+
+    /// Updates a named definition, rebuilding `root` to use the new value
+    ///
+    /// Every use of the previous definition within `root` is replaced with
+    /// `a`; other named definitions which depend on the previous definition
+    /// are also updated.  Returns the new root.
+    ///
+    /// Returns [`Error::UnknownVariable`] if the name is not defined.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let r = ctx.define("r", 1.0).unwrap();
+    /// let out = ctx.sub(x, r).unwrap();
+    /// assert_eq!(ctx.eval_xyz(out, 3.0, 0.0, 0.0).unwrap(), 2.0);
+    ///
+    /// let out = ctx.redefine("r", 2.5, out).unwrap();
+    /// assert_eq!(ctx.eval_xyz(out, 3.0, 0.0, 0.0).unwrap(), 0.5);
+    /// ```
+    pub fn redefine<A: IntoNode>(
+        &mut self,
+        name: &str,
+        a: A,
+        root: Node,
+    ) -> Result<Node, Error> {
+        let prev = self
+            .get_defined(name)
+            .ok_or_else(|| Error::UnknownVariable(name.to_owned()))?;
+        let a = a.into_node(self)?;
+        self.check_node(root)?;
+
+        let mut cache = BTreeMap::new();
+        cache.insert(prev, a);
+        let names = self
+            .names
+            .iter()
+            .filter(|(k, _)| k.as_str() != name)
+            .map(|(k, v)| (k.clone(), *v))
+            .collect::<Vec<_>>();
+        for (k, v) in names {
+            let v = self.substitute(v, &mut cache)?;
+            self.names.insert(k, v);
+        }
+        self.names.insert(name.to_owned(), a);
+        self.substitute(root, &mut cache)
+    }
+
+    /// Rebuilds the subgraph rooted at `node`, replacing nodes using `cache`
+    ///
+    /// `cache` is pre-populated with substitutions and is extended with every
+    /// node visited during the rebuild.
+    fn substitute(
+        &mut self,
+        node: Node,
+        cache: &mut BTreeMap<Node, Node>,
+    ) -> Result<Node, Error> {
+        if let Some(n) = cache.get(&node) {
+            return Ok(*n);
+        }
+        let out = match *self.get_op(node).ok_or(Error::BadNode)? {
+            Op::Input(..) | Op::Const(..) => node,
+            Op::Unary(op, a) => {
+                let a = self.substitute(a, cache)?;
+                self.op_unary(a, op)?
+            }
+            Op::Binary(op, a, b) => {
+                let a = self.substitute(a, cache)?;
+                let b = self.substitute(b, cache)?;
+                self.op_binary(a, b, op)?
+            }
+        };
+        cache.insert(node, out);
+        Ok(out)
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Value ranges
+    /// Annotates a node with a known range of values
+    ///
+    /// This is a promise that the node's value always falls within `range`
+    /// (e.g. a variable bound to a field sampled from `[-1, 1]`).  It doesn't
+    /// change the math expression, but it's used by constant folding when
+    /// building new nodes from this one, and to tighten the results of
+    /// [`Context::eval_interval`].  Ranges are propagated to nodes built
+    /// afterwards, so annotations should be made before building the
+    /// expressions which use them.
+    ///
+    /// If the node already has a known range, the two are intersected.
+    /// Returns [`Error::BadRange`] if `range` contains `NaN` or doesn't
+    /// overlap the node's existing range.
+    ///
+    /// ```
+    /// # use fidget::{context::Context, types::Interval};
+    /// let mut ctx = Context::new();
+    /// let t = ctx.var("t").unwrap();
+    /// ctx.set_range(t, Interval::new(-1.0, 1.0)).unwrap();
+    ///
+    /// // `t` is never greater than 2, so the `min` folds away
+    /// let out = ctx.min(t, 2.0).unwrap();
+    /// assert_eq!(out, t);
+    ///
+    /// // Derived nodes have a known range, too
+    /// let sum = ctx.add(t, 3.0).unwrap();
+    /// assert_eq!(ctx.range(sum), Some(Interval::new(2.0, 4.0)));
+    /// ```
+    pub fn set_range(&mut self, n: Node, range: Interval) -> Result<(), Error> {
+        self.check_node(n)?;
+        if range.has_nan() {
+            return Err(Error::BadRange);
+        }
+        let range = match self.range(n) {
+            Some(prev) => intersect(prev, range).ok_or(Error::BadRange)?,
+            None => range,
+        };
+        self.ranges.insert(n, range);
+        Ok(())
+    }
+
+    /// Returns the known range of a node, if any
+    ///
+    /// Constants have a single-value range; other nodes only have a range if
+    /// it was annotated with [`Context::set_range`] or derived from annotated
+    /// children.
+    pub fn range(&self, n: Node) -> Option<Interval> {
+        match self.get_op(n)? {
+            Op::Const(c) => Some(Interval::from(c.0 as f32)),
+            _ => self.ranges.get(&n).cloned(),
+        }
+    }
+
+    /// Attempts to simplify a unary operation based on its argument's range
+    fn fold_range_unary(
+        &mut self,
+        a: Node,
+        op: UnaryOpcode,
+    ) -> Result<Option<Node>, Error> {
+        let Some(r) = self.range(a) else {
+            return Ok(None);
+        };
+        let out = match op {
+            UnaryOpcode::Abs if r.lower() >= 0.0 => Some(a),
+            UnaryOpcode::Abs if r.upper() <= 0.0 => {
+                Some(self.op_unary(a, UnaryOpcode::Neg)?)
+            }
+            UnaryOpcode::Not if !r.contains(0.0) => Some(self.constant(0.0)),
+            _ => None,
+        };
+        Ok(out)
+    }
+
+    /// Attempts to simplify a binary operation based on its arguments' ranges
+    fn fold_range_binary(
+        &mut self,
+        a: Node,
+        b: Node,
+        op: BinaryOpcode,
+    ) -> Option<Node> {
+        let ra = self.range(a)?;
+        match op {
+            BinaryOpcode::And if !ra.contains(0.0) => return Some(b),
+            BinaryOpcode::Or if !ra.contains(0.0) => return Some(a),
+            _ => (),
+        }
+        let rb = self.range(b)?;
+        match op {
+            BinaryOpcode::Min if ra.upper() <= rb.lower() => Some(a),
+            BinaryOpcode::Min if rb.upper() <= ra.lower() => Some(b),
+            BinaryOpcode::Max if ra.lower() >= rb.upper() => Some(a),
+            BinaryOpcode::Max if rb.lower() >= ra.upper() => Some(b),
+            BinaryOpcode::Compare if ra.upper() < rb.lower() => {
+                Some(self.constant(-1.0))
+            }
+            BinaryOpcode::Compare if ra.lower() > rb.upper() => {
+                Some(self.constant(1.0))
+            }
+            _ => None,
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Sign-equivalent rewriting
+    /// Builds an expression with the same sign as `root`, removing `sqrt`
+    /// operations where possible
+    ///
+    /// Distance fields for spheres, circles, and capsules are typically of the
+    /// form `sqrt(q) - r`, and the `sqrt` often dominates evaluation cost.
+    /// When only the sign of the field matters (e.g. for occupancy rendering),
+    /// it can be rewritten as `q - r²`, which is negative, zero, or positive
+    /// at exactly the same points.
+    ///
+    /// This rewrite is applied to `sqrt(q) - r` and `r - sqrt(q)` (where `q`
+    /// and `r` are known to be non-negative), and to `sqrt(q)` itself.  It
+    /// descends through operations whose output sign depends only on their
+    /// inputs' signs (see [`BinaryOpcode::preserves_sign`] and
+    /// [`UnaryOpcode::preserves_sign`]), e.g. `min`, `max`, and `neg`; other
+    /// subtrees are left unchanged.
+    ///
+    /// The result is not a distance field, so it shouldn't be used where
+    /// distances matter (e.g. meshing or SDF rendering).
+    ///
+    /// ```
+    /// # use fidget::context::{Context, Tree};
+    /// let (x, y, z) = Tree::axes();
+    /// let sphere = (x.square() + y.square() + z.square()).sqrt() - 0.5;
+    /// let mut ctx = Context::new();
+    /// let root = ctx.import(&sphere.min(x.clone() - 2.0));
+    /// let sq = ctx.sign_equivalent(root).unwrap();
+    /// assert!(!ctx.to_text(sq).unwrap().contains("sqrt"));
+    ///
+    /// for p in [0.25, 0.5, 0.75] {
+    ///     let a = ctx.eval_xyz(root, p, 0.0, 0.0).unwrap();
+    ///     let b = ctx.eval_xyz(sq, p, 0.0, 0.0).unwrap();
+    ///     assert_eq!(a.signum(), b.signum());
+    /// }
+    /// ```
+    pub fn sign_equivalent(&mut self, root: Node) -> Result<Node, Error> {
+        self.check_node(root)?;
+        let mut cache = HashMap::new();
+        self.sign_equivalent_inner(root, &mut cache)
+    }
+
+    fn sign_equivalent_inner(
+        &mut self,
+        node: Node,
+        cache: &mut HashMap<Node, Node>,
+    ) -> Result<Node, Error> {
+        if let Some(n) = cache.get(&node) {
+            return Ok(*n);
+        }
+        let out = match *self.get_op(node).ok_or(Error::BadNode)? {
+            Op::Unary(UnaryOpcode::Sqrt, q) if self.is_nonnegative(q) => q,
+            Op::Binary(BinaryOpcode::Sub, a, b) => {
+                match (self.sqrt_arg(a), self.sqrt_arg(b)) {
+                    (Some(q), _) if self.is_nonnegative(b) => {
+                        let r2 = self.square(b)?;
+                        self.sub(q, r2)?
+                    }
+                    (_, Some(q)) if self.is_nonnegative(a) => {
+                        let r2 = self.square(a)?;
+                        self.sub(r2, q)?
+                    }
+                    _ => node,
+                }
+            }
+            Op::Binary(BinaryOpcode::Add, a, b) => {
+                // `sqrt(q) + c` where `c` is a non-positive constant, which is
+                // how subtraction by a constant may be stored
+                let (s, c) = if self.const_value(b)?.is_some() {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                match (self.sqrt_arg(s), self.const_value(c)?) {
+                    (Some(q), Some(c)) if c <= 0.0 => self.sub(q, c * c)?,
+                    _ => node,
+                }
+            }
+            Op::Unary(op, a) if op.preserves_sign() => {
+                let a = self.sign_equivalent_inner(a, cache)?;
+                self.op_unary(a, op)?
+            }
+            Op::Binary(op, a, b) if op.preserves_sign() => {
+                let a = self.sign_equivalent_inner(a, cache)?;
+                let b = self.sign_equivalent_inner(b, cache)?;
+                self.op_binary(a, b, op)?
+            }
+            _ => node,
+        };
+        cache.insert(node, out);
+        Ok(out)
+    }
+
+    /// Returns `q` if the node is `sqrt(q)` and `q` is known to be
+    /// non-negative
+    fn sqrt_arg(&self, n: Node) -> Option<Node> {
+        match *self.get_op(n)? {
+            Op::Unary(UnaryOpcode::Sqrt, q) if self.is_nonnegative(q) => {
+                Some(q)
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks whether a node is known to be non-negative (or `NaN`)
+    ///
+    /// This uses the node's range (if known) and its structure, e.g. a sum of
+    /// squares is non-negative.
+    fn is_nonnegative(&self, n: Node) -> bool {
+        if self.range(n).is_some_and(|r| r.lower() >= 0.0) {
+            return true;
+        }
+        match self.get_op(n) {
+            Some(Op::Unary(
+                UnaryOpcode::Square
+                | UnaryOpcode::Abs
+                | UnaryOpcode::Sqrt
+                | UnaryOpcode::Exp,
+                _,
+            )) => true,
+            Some(Op::Binary(
+                BinaryOpcode::Add
+                | BinaryOpcode::Mul
+                | BinaryOpcode::Min
+                | BinaryOpcode::Max,
+                a,
+                b,
+            )) => self.is_nonnegative(*a) && self.is_nonnegative(*b),
+            _ => false,
+        }
+    }
+
     ////////////////////////////////////////////////////////////////////////////
     // Primitives
     /// Constructs or finds a variable node named "X"
@@ -166,6 +844,22 @@ impl Context {
         self.ops.insert(Op::Input(v))
     }
 
+    /// Constructs or finds a custom variable node with the given name
+    ///
+    /// Custom variables may be used with [`Context::eval`] and
+    /// [`Context::eval_interval`], but are not yet supported when building a
+    /// [`Shape`](crate::eval::Shape).
+    ///
+    /// Returns [`Error::ReservedName`] for `X`, `Y`, and `Z`; use
+    /// [`Context::x`] (etc) for those instead.
+    pub fn var(&mut self, name: &str) -> Result<Node, Error> {
+        if matches!(name, "X" | "Y" | "Z") {
+            return Err(Error::ReservedName);
+        }
+        let v = self.vars.insert(name.to_owned());
+        Ok(self.ops.insert(Op::Input(v)))
+    }
+
     /// Returns a node representing the given constant value.
     /// ```
     /// # let mut ctx = fidget::context::Context::new();
@@ -181,17 +875,23 @@ impl Context {
     /// Find or create a [Node] for the given unary operation, with constant
     /// folding.
     fn op_unary(&mut self, a: Node, op: UnaryOpcode) -> Result<Node, Error> {
-        let op_a = *self.get_op(a).ok_or(Error::BadNode)?;
-        let n = self.ops.insert(Op::Unary(op, a));
-        let out = if matches!(op_a, Op::Const(_)) {
-            let v = self.eval(n, &BTreeMap::new())?;
-            self.pop().unwrap(); // removes `n`
-            self.constant(v)
-        } else {
-            n
+        let out = match *self.get_op(a).ok_or(Error::BadNode)? {
+            Op::Const(c) => self.constant(op.eval(c.0)),
+            _ if self.ranges.is_empty() => self.ops.insert(Op::Unary(op, a)),
+            _ => {
+                if let Some(n) = self.fold_range_unary(a, op)? {
+                    return Ok(n);
+                }
+                let out = self.ops.insert(Op::Unary(op, a));
+                if let Some(r) = self.range(a) {
+                    self.derive_range(out, op.eval_interval(r));
+                }
+                out
+            }
         };
         Ok(out)
     }
+
     /// Find or create a [Node] for the given binary operation, with constant
     /// folding.
     fn op_binary(
@@ -200,32 +900,157 @@ impl Context {
         b: Node,
         op: BinaryOpcode,
     ) -> Result<Node, Error> {
-        self.op_binary_f(a, b, |lhs, rhs| Op::Binary(op, lhs, rhs))
-    }
-
-    /// Find or create a [Node] for a generic binary operation (represented by a
-    /// thunk), with constant folding.
-    fn op_binary_f<F>(&mut self, a: Node, b: Node, f: F) -> Result<Node, Error>
-    where
-        F: Fn(Node, Node) -> Op,
-    {
         let op_a = *self.get_op(a).ok_or(Error::BadNode)?;
         let op_b = *self.get_op(b).ok_or(Error::BadNode)?;
+        let out = match (op_a, op_b) {
+            (Op::Const(a), Op::Const(b)) => self.constant(op.eval(a.0, b.0)),
+            _ if self.ranges.is_empty() => {
+                self.ops.insert(Op::Binary(op, a, b))
+            }
+            _ => {
+                if let Some(n) = self.fold_range_binary(a, b, op) {
+                    return Ok(n);
+                }
+                let out = self.ops.insert(Op::Binary(op, a, b));
+                if let (Some(ra), Some(rb)) = (self.range(a), self.range(b)) {
+                    self.derive_range(out, op.eval_interval(ra, rb));
+                }
+                out
+            }
+        };
+        Ok(out)
+    }
 
-        // This call to `insert` should always insert the node, because we
-        // don't permanently store operations in the tree that could be
-        // constant-folded (indeed, we pop the node right afterwards)
-        let n = self.ops.insert(f(a, b));
-        let out = if matches!((op_a, op_b), (Op::Const(_), Op::Const(_))) {
-            let v = self.eval(n, &BTreeMap::new())?;
-            self.pop().unwrap(); // removes `n`
-            self.constant(v)
-        } else {
-            n
+    /// Records a range derived from a node's children
+    ///
+    /// Ranges containing `NaN` are discarded, and an existing range (which
+    /// may have been annotated by the user) is tightened but never widened.
+    fn derive_range(&mut self, n: Node, range: Interval) {
+        if range.has_nan() {
+            return;
+        }
+        let range = match self.ranges.get(&n) {
+            Some(prev) => intersect(*prev, range).unwrap_or(*prev),
+            None => range,
+        };
+        self.ranges.insert(n, range);
+    }
+
+    /// Decomposes a node into an affine form `a·n + b`
+    ///
+    /// Returns `(a, n, b)`, where `n` is `None` for constant nodes.
+    fn affine_form(
+        &self,
+        node: Node,
+    ) -> Result<(f64, Option<Node>, f64), Error> {
+        let op = self.get_op(node).ok_or(Error::BadNode)?;
+        let out = match *op {
+            Op::Const(c) => (0.0, None, c.0),
+            Op::Unary(UnaryOpcode::Neg, x) => {
+                let (a, n, b) = self.affine_form(x)?;
+                (-a, n, -b)
+            }
+            Op::Binary(op, lhs, rhs) => {
+                match (op, self.const_value(lhs)?, self.const_value(rhs)?) {
+                    (BinaryOpcode::Add, Some(c), None) => {
+                        let (a, n, b) = self.affine_form(rhs)?;
+                        (a, n, b + c)
+                    }
+                    (BinaryOpcode::Add, None, Some(c)) => {
+                        let (a, n, b) = self.affine_form(lhs)?;
+                        (a, n, b + c)
+                    }
+                    (BinaryOpcode::Sub, Some(c), None) => {
+                        let (a, n, b) = self.affine_form(rhs)?;
+                        (-a, n, c - b)
+                    }
+                    (BinaryOpcode::Sub, None, Some(c)) => {
+                        let (a, n, b) = self.affine_form(lhs)?;
+                        (a, n, b - c)
+                    }
+                    (BinaryOpcode::Mul, Some(c), None) => {
+                        let (a, n, b) = self.affine_form(rhs)?;
+                        (a * c, n, b * c)
+                    }
+                    (BinaryOpcode::Mul, None, Some(c)) => {
+                        let (a, n, b) = self.affine_form(lhs)?;
+                        (a * c, n, b * c)
+                    }
+                    (BinaryOpcode::Div, None, Some(c)) => {
+                        let (a, n, b) = self.affine_form(lhs)?;
+                        (a / c, n, b / c)
+                    }
+                    _ => (1.0, Some(node), 0.0),
+                }
+            }
+            Op::Input(..) | Op::Unary(..) => (1.0, Some(node), 0.0),
         };
         Ok(out)
     }
 
+    /// Builds the canonical node for an affine form `a·n + b`
+    fn affine_node(
+        &mut self,
+        (a, n, b): (f64, Option<Node>, f64),
+    ) -> Result<Node, Error> {
+        let n = match n {
+            Some(n) if a != 0.0 => n,
+            _ => return Ok(self.constant(b)),
+        };
+        let n = if a == 1.0 {
+            n
+        } else if a == -1.0 {
+            self.op_unary(n, UnaryOpcode::Neg)?
+        } else {
+            let a = self.constant(a);
+            self.op_binary(n, a, BinaryOpcode::Mul)?
+        };
+        if b == 0.0 {
+            Ok(n)
+        } else {
+            let b = self.constant(b);
+            self.op_binary(n, b, BinaryOpcode::Add)
+        }
+    }
+
+    /// Attempts to build an affine combination of two nodes
+    ///
+    /// Returns `Ok(None)` if canonicalization is disabled, or if the result
+    /// is not affine in a single non-constant node.
+    fn affine_binary(
+        &mut self,
+        lhs: Node,
+        rhs: Node,
+        op: BinaryOpcode,
+    ) -> Result<Option<Node>, Error> {
+        if !self.affine {
+            return Ok(None);
+        }
+        let (la, ln, lb) = self.affine_form(lhs)?;
+        let (ra, rn, rb) = self.affine_form(rhs)?;
+        let n = match (ln, rn) {
+            (Some(a), Some(b)) if a == b => Some(a),
+            (Some(a), None) | (None, Some(a)) => Some(a),
+            // Constant folding is handled elsewhere
+            (None, None) => return Ok(None),
+            (Some(..), Some(..)) => return Ok(None),
+        };
+        let form = match op {
+            BinaryOpcode::Add => (la + ra, n, lb + rb),
+            BinaryOpcode::Sub => (la - ra, n, lb - rb),
+            BinaryOpcode::Mul if rn.is_none() => (la * rb, n, lb * rb),
+            BinaryOpcode::Mul if ln.is_none() => (ra * lb, n, rb * lb),
+            BinaryOpcode::Div if rn.is_none() => (la / rb, n, lb / rb),
+            _ => return Ok(None),
+        };
+        // Bail out if the coefficients are degenerate (e.g. after division by
+        // zero), because `0·inf` would turn into a NaN.
+        if !(form.0.is_finite() && form.2.is_finite()) {
+            return Ok(None);
+        }
+        self.affine_node(form).map(Some)
+    }
+
     /// Find or create a [Node] for the given commutative operation, with
     /// constant folding; deduplication is encouraged by sorting `a` and `b`.
     fn op_binary_commutative(
@@ -252,13 +1077,16 @@ impl Context {
     ) -> Result<Node, Error> {
         let a: Node = a.into_node(self)?;
         let b: Node = b.into_node(self)?;
+        if let Some(n) = self.affine_binary(a, b, BinaryOpcode::Add)? {
+            return Ok(n);
+        }
         if a == b {
             let two = self.constant(2.0);
             self.mul(a, two)
         } else {
             match (self.const_value(a)?, self.const_value(b)?) {
-                (Some(zero), _) if zero == 0.0 => Ok(b),
-                (_, Some(zero)) if zero == 0.0 => Ok(a),
+                (Some(0.0), _) => Ok(b),
+                (_, Some(0.0)) => Ok(a),
                 _ => self.op_binary_commutative(a, b, BinaryOpcode::Add),
             }
         }
@@ -279,14 +1107,17 @@ impl Context {
     ) -> Result<Node, Error> {
         let a = a.into_node(self)?;
         let b = b.into_node(self)?;
+        if let Some(n) = self.affine_binary(a, b, BinaryOpcode::Mul)? {
+            return Ok(n);
+        }
         if a == b {
             self.square(a)
         } else {
             match (self.const_value(a)?, self.const_value(b)?) {
-                (Some(one), _) if one == 1.0 => Ok(b),
-                (_, Some(one)) if one == 1.0 => Ok(a),
-                (Some(zero), _) if zero == 0.0 => Ok(a),
-                (_, Some(zero)) if zero == 0.0 => Ok(b),
+                (Some(1.0), _) => Ok(b),
+                (_, Some(1.0)) => Ok(a),
+                (Some(0.0), _) => Ok(a),
+                (_, Some(0.0)) => Ok(b),
                 _ => self.op_binary_commutative(a, b, BinaryOpcode::Mul),
             }
         }
@@ -435,6 +1266,10 @@ impl Context {
     /// ```
     pub fn neg<A: IntoNode>(&mut self, a: A) -> Result<Node, Error> {
         let a = a.into_node(self)?;
+        if self.affine {
+            let (m, n, b) = self.affine_form(a)?;
+            return self.affine_node((-m, n, -b));
+        }
         self.op_unary(a, UnaryOpcode::Neg)
     }
 
@@ -608,10 +1443,13 @@ impl Context {
     ) -> Result<Node, Error> {
         let a = a.into_node(self)?;
         let b = b.into_node(self)?;
+        if let Some(n) = self.affine_binary(a, b, BinaryOpcode::Sub)? {
+            return Ok(n);
+        }
 
         match (self.const_value(a)?, self.const_value(b)?) {
-            (Some(zero), _) if zero == 0.0 => self.neg(b),
-            (_, Some(zero)) if zero == 0.0 => Ok(a),
+            (Some(0.0), _) => self.neg(b),
+            (_, Some(0.0)) => Ok(a),
             _ => self.op_binary(a, b, BinaryOpcode::Sub),
         }
     }
@@ -632,10 +1470,13 @@ impl Context {
     ) -> Result<Node, Error> {
         let a = a.into_node(self)?;
         let b = b.into_node(self)?;
+        if let Some(n) = self.affine_binary(a, b, BinaryOpcode::Div)? {
+            return Ok(n);
+        }
 
         match (self.const_value(a)?, self.const_value(b)?) {
-            (Some(zero), _) if zero == 0.0 => Ok(a),
-            (_, Some(one)) if one == 1.0 => Ok(a),
+            (Some(0.0), _) => Ok(a),
+            (_, Some(1.0)) => Ok(a),
             _ => self.op_binary(a, b, BinaryOpcode::Div),
         }
     }
@@ -788,72 +1629,178 @@ impl Context {
         let v = match self.get_op(node).ok_or(Error::BadNode)? {
             Op::Input(v) => {
                 let var_name = self.vars.get_by_index(*v).unwrap();
-                *vars.get(var_name).unwrap()
+                *vars
+                    .get(var_name)
+                    .ok_or_else(|| Error::UnknownVariable(var_name.clone()))?
             }
             Op::Const(c) => c.0,
 
             Op::Binary(op, a, b) => {
                 let a = get(*a)?;
                 let b = get(*b)?;
-                match op {
-                    BinaryOpcode::Add => a + b,
-                    BinaryOpcode::Sub => a - b,
-                    BinaryOpcode::Mul => a * b,
-                    BinaryOpcode::Div => a / b,
-                    BinaryOpcode::Atan => a.atan2(b),
-                    BinaryOpcode::Min => a.min(b),
-                    BinaryOpcode::Max => a.max(b),
-                    BinaryOpcode::Compare => a
-                        .partial_cmp(&b)
-                        .map(|i| i as i8 as f64)
-                        .unwrap_or(f64::NAN),
-                    BinaryOpcode::Mod => a.rem_euclid(b),
-                    BinaryOpcode::And => {
-                        if a == 0.0 {
-                            a
-                        } else {
-                            b
-                        }
-                    }
-                    BinaryOpcode::Or => {
-                        if a != 0.0 {
-                            a
-                        } else {
-                            b
-                        }
-                    }
-                }
+                op.eval(a, b)
             }
 
             // Unary operations
             Op::Unary(op, a) => {
                 let a = get(*a)?;
-                match op {
-                    UnaryOpcode::Neg => -a,
-                    UnaryOpcode::Abs => a.abs(),
-                    UnaryOpcode::Recip => 1.0 / a,
-                    UnaryOpcode::Sqrt => a.sqrt(),
-                    UnaryOpcode::Square => a * a,
-                    UnaryOpcode::Floor => a.floor(),
-                    UnaryOpcode::Ceil => a.ceil(),
-                    UnaryOpcode::Round => a.round(),
-                    UnaryOpcode::Sin => a.sin(),
-                    UnaryOpcode::Cos => a.cos(),
-                    UnaryOpcode::Tan => a.tan(),
-                    UnaryOpcode::Asin => a.asin(),
-                    UnaryOpcode::Acos => a.acos(),
-                    UnaryOpcode::Atan => a.atan(),
-                    UnaryOpcode::Exp => a.exp(),
-                    UnaryOpcode::Ln => a.ln(),
-                    UnaryOpcode::Not => (a == 0.0).into(),
-                }
+                op.eval(a)
+            }
+        };
+
+        cache[node] = Some(v);
+        Ok(v)
+    }
+
+    /// Evaluates the given node over intervals for each variable
+    ///
+    /// `vars` must include every input used by `root`, including `X`, `Y`, and
+    /// `Z` (if used); passing an interval for a custom variable lets you check
+    /// properties which hold over a range of parameter values.  Returns
+    /// [`Error::UnknownVariable`] if an input is missing.
+    ///
+    /// Like [`Context::eval`], this is extremely inefficient.
+    ///
+    /// ```
+    /// # use fidget::{context::Context, types::Interval};
+    /// # use std::collections::BTreeMap;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let t = ctx.var("thickness").unwrap();
+    /// let op = ctx.sub(x, t).unwrap();
+    ///
+    /// // For any thickness in [1, 2], this is positive when x is in [3, 4]
+    /// let vars = BTreeMap::from([
+    ///     ("X".to_owned(), Interval::new(3.0, 4.0)),
+    ///     ("thickness".to_owned(), Interval::new(1.0, 2.0)),
+    /// ]);
+    /// let v = ctx.eval_interval(op, &vars).unwrap();
+    /// assert_eq!(v, Interval::new(1.0, 3.0));
+    /// ```
+    pub fn eval_interval(
+        &self,
+        root: Node,
+        vars: &BTreeMap<String, Interval>,
+    ) -> Result<Interval, Error> {
+        let mut cache = vec![None; self.ops.len()].into();
+        self.eval_interval_inner(root, vars, &mut cache)
+    }
+
+    fn eval_interval_inner(
+        &self,
+        node: Node,
+        vars: &BTreeMap<String, Interval>,
+        cache: &mut IndexVec<Option<Interval>, Node>,
+    ) -> Result<Interval, Error> {
+        if node.0 >= cache.len() {
+            return Err(Error::BadNode);
+        }
+        if let Some(v) = cache[node] {
+            return Ok(v);
+        }
+        let mut get = |n: Node| self.eval_interval_inner(n, vars, cache);
+        let v = match self.get_op(node).ok_or(Error::BadNode)? {
+            Op::Input(v) => {
+                let var_name = self.vars.get_by_index(*v).unwrap();
+                *vars
+                    .get(var_name)
+                    .ok_or_else(|| Error::UnknownVariable(var_name.clone()))?
+            }
+            Op::Const(c) => Interval::from(c.0 as f32),
+            Op::Binary(op, a, b) => {
+                let a = get(*a)?;
+                let b = get(*b)?;
+                op.eval_interval(a, b)
+            }
+            Op::Unary(op, a) => {
+                let a = get(*a)?;
+                op.eval_interval(a)
             }
         };
+        let v = match self.ranges.get(&node) {
+            Some(r) if !v.has_nan() => intersect(v, *r).unwrap_or(v),
+            _ => v,
+        };
 
         cache[node] = Some(v);
         Ok(v)
     }
 
+    /// Evaluates the given node and its partial derivatives
+    ///
+    /// `vars` must include every input used by `root` (as in
+    /// [`Context::eval`]); the result is the value and a map from each of
+    /// those inputs to `∂root / ∂input`.  Inputs which are passed in but not
+    /// used by `root` have a partial derivative of zero.
+    ///
+    /// Unlike [`Grad`](crate::types::Grad)-based evaluators, this works for
+    /// custom variables, e.g. to fit shape parameters with gradient descent.
+    /// Derivatives are accumulated in reverse mode, so the cost doesn't depend
+    /// on the number of variables; use an [`AdjointTape`] to evaluate the same
+    /// expression many times.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// # use std::collections::BTreeMap;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let r = ctx.var("r").unwrap();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let op = ctx.sub(x2, r).unwrap();
+    ///
+    /// let vars = BTreeMap::from([
+    ///     ("X".to_owned(), 3.0),
+    ///     ("r".to_owned(), 2.0),
+    /// ]);
+    /// let (v, grad) = ctx.eval_grad(op, &vars).unwrap();
+    /// assert_eq!(v, 7.0);
+    /// assert_eq!(grad["X"], 6.0);
+    /// assert_eq!(grad["r"], -1.0);
+    /// ```
+    pub fn eval_grad(
+        &self,
+        root: Node,
+        vars: &BTreeMap<String, f64>,
+    ) -> Result<(f64, BTreeMap<String, f64>), Error> {
+        let mut cache = vec![None; self.ops.len()].into();
+        let v = self.eval_inner(root, vars, &mut cache)?;
+
+        // Every node reachable from `root` now has a cached value, so we can
+        // accumulate adjoints in reverse order of node creation (children are
+        // always created before their parents).
+        let mut adjoint = vec![0.0; self.ops.len()];
+        adjoint[root.0] = 1.0;
+        let mut out: BTreeMap<String, f64> =
+            vars.keys().map(|k| (k.clone(), 0.0)).collect();
+        for i in (0..=root.0).rev() {
+            // Skip unused nodes, and branches which weren't chosen (whose
+            // derivatives may be infinite)
+            let d = adjoint[i];
+            if d == 0.0 || cache[Node(i)].is_none() {
+                continue;
+            }
+            match self.get_op(Node(i)).unwrap() {
+                Op::Input(v) => {
+                    let name = self.vars.get_by_index(*v).unwrap();
+                    *out.get_mut(name).unwrap() += d;
+                }
+                Op::Const(..) => (),
+                Op::Unary(op, a) => {
+                    let va = cache[*a].unwrap();
+                    adjoint[a.0] += d * op.deriv(va);
+                }
+                Op::Binary(op, a, b) => {
+                    let va = cache[*a].unwrap();
+                    let vb = cache[*b].unwrap();
+                    let (da, db) = op.deriv(va, vb);
+                    adjoint[a.0] += d * da;
+                    adjoint[b.0] += d * db;
+                }
+            }
+        }
+        Ok((v, out))
+    }
+
     /// Parses a flat text representation of a math tree. For example, the
     /// circle `(- (+ (square x) (square y)) 1)` can be parsed from
     /// ```
@@ -901,6 +1848,7 @@ impl Context {
                 "var-z" => ctx.z(),
                 "abs" => ctx.abs(pop()?)?,
                 "neg" => ctx.neg(pop()?)?,
+                "recip" => ctx.recip(pop()?)?,
                 "sqrt" => ctx.sqrt(pop()?)?,
                 "square" => ctx.square(pop()?)?,
                 "floor" => ctx.floor(pop()?)?,
@@ -926,7 +1874,14 @@ impl Context {
                 "mod" => ctx.modulo(pop()?, pop()?)?,
                 "and" => ctx.and(pop()?, pop()?)?,
                 "or" => ctx.or(pop()?, pop()?)?,
-                op => return Err(Error::UnknownOpcode(op.to_owned())),
+                "define" => {
+                    let node = pop()?;
+                    ctx.define(&i, node)?
+                }
+                op => match op.strip_prefix("var-") {
+                    Some(name) => ctx.var(name)?,
+                    None => return Err(Error::UnknownOpcode(op.to_owned())),
+                },
             };
             seen.insert(i, node);
             last = Some(node);
@@ -937,6 +1892,108 @@ impl Context {
         }
     }
 
+    /// Converts the subgraph rooted at `root` into the flat text format
+    ///
+    /// The output can be parsed by [`Context::from_text`].  Anonymous nodes are
+    /// labelled as `_N`; named definitions (see [`Context::define`]) are
+    /// emitted as `define` lines and referenced by name in later clauses.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let x2 = ctx.define("x2", x2).unwrap();
+    /// let out = ctx.sub(x2, 1.0).unwrap();
+    ///
+    /// let txt = ctx.to_text(out).unwrap();
+    /// assert!(txt.contains("x2 define"));
+    ///
+    /// let (ctx, root) = Context::from_text(txt.as_bytes()).unwrap();
+    /// assert_eq!(ctx.eval_xyz(root, 3.0, 0.0, 0.0).unwrap(), 8.0);
+    /// assert!(ctx.get_defined("x2").is_some());
+    /// ```
+    pub fn to_text(&self, root: Node) -> Result<String, Error> {
+        self.check_node(root)?;
+
+        // Find every node in the subgraph; because nodes can only refer to
+        // previously-inserted nodes, sorting by index gives a valid order.
+        let mut seen = std::collections::BTreeSet::new();
+        let mut todo = vec![root];
+        while let Some(node) = todo.pop() {
+            if seen.insert(node) {
+                todo.extend(self.get_op(node).unwrap().iter_children());
+            }
+        }
+
+        let mut labels: BTreeMap<Node, String> = BTreeMap::new();
+        let mut out = String::new();
+        for node in seen {
+            let label = format!("_{}", node.get());
+            write!(out, "{label} ").unwrap();
+            match self.get_op(node).unwrap() {
+                Op::Const(c) => write!(out, "const {}", c.0).unwrap(),
+                Op::Input(v) => {
+                    let v = self.get_var_by_index(*v)?;
+                    if matches!(v, "X" | "Y" | "Z") {
+                        write!(out, "var-{}", v.to_lowercase()).unwrap();
+                    } else {
+                        write!(out, "var-{v}").unwrap();
+                    }
+                }
+                Op::Unary(op, a) => {
+                    let op = match op {
+                        UnaryOpcode::Neg => "neg",
+                        UnaryOpcode::Abs => "abs",
+                        UnaryOpcode::Recip => "recip",
+                        UnaryOpcode::Sqrt => "sqrt",
+                        UnaryOpcode::Square => "square",
+                        UnaryOpcode::Floor => "floor",
+                        UnaryOpcode::Ceil => "ceil",
+                        UnaryOpcode::Round => "round",
+                        UnaryOpcode::Sin => "sin",
+                        UnaryOpcode::Cos => "cos",
+                        UnaryOpcode::Tan => "tan",
+                        UnaryOpcode::Asin => "asin",
+                        UnaryOpcode::Acos => "acos",
+                        UnaryOpcode::Atan => "atan",
+                        UnaryOpcode::Exp => "exp",
+                        UnaryOpcode::Ln => "ln",
+                        UnaryOpcode::Not => "not",
+                    };
+                    write!(out, "{op} {}", labels[a]).unwrap();
+                }
+                Op::Binary(op, a, b) => {
+                    let op = match op {
+                        BinaryOpcode::Add => "add",
+                        BinaryOpcode::Sub => "sub",
+                        BinaryOpcode::Mul => "mul",
+                        BinaryOpcode::Div => "div",
+                        BinaryOpcode::Atan => "atan2",
+                        BinaryOpcode::Min => "min",
+                        BinaryOpcode::Max => "max",
+                        BinaryOpcode::Compare => "compare",
+                        BinaryOpcode::Mod => "mod",
+                        BinaryOpcode::And => "and",
+                        BinaryOpcode::Or => "or",
+                    };
+                    write!(out, "{op} {} {}", labels[a], labels[b]).unwrap();
+                }
+            }
+            out += "\n";
+
+            // Named definitions are emitted right after the node, and are then
+            // used to refer to it in subsequent clauses.
+            let mut label = label;
+            for (name, _) in self.names.iter().filter(|(_, v)| **v == node) {
+                writeln!(out, "{name} define {label}").unwrap();
+                label = name.clone();
+            }
+            labels.insert(node, label);
+        }
+        Ok(out)
+    }
+
     /// Converts the entire context into a GraphViz drawing
     pub fn dot(&self) -> String {
         let mut out = "digraph mygraph{\n".to_owned();
@@ -1138,6 +2195,13 @@ impl Context {
     }
 }
 
+/// Returns the intersection of two intervals, or `None` if they don't overlap
+fn intersect(a: Interval, b: Interval) -> Option<Interval> {
+    let lower = a.lower().max(b.lower());
+    let upper = a.upper().min(b.upper());
+    (lower <= upper).then(|| Interval::new(lower, upper))
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// Helper trait for things that can be converted into a [`Node`] given a
 /// [`Context`].
@@ -1187,6 +2251,13 @@ mod test {
     use crate::vm::VmData;
 
     // This can't be in a doctest, because it uses a private function
+    #[test]
+    fn context_is_sync() {
+        // Tape generation borrows the context from many threads at once
+        fn check<T: Send + Sync>() {}
+        check::<Context>();
+    }
+
     #[test]
     fn test_get_op() {
         let mut ctx = Context::new();
@@ -1222,4 +2293,548 @@ mod test {
         let tape = VmData::<255>::new(&ctx, x_squared).unwrap();
         assert_eq!(tape.len(), 2);
     }
+
+    #[test]
+    fn test_eval_grad() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let cx = ctx.var("cx").unwrap();
+        let r = ctx.var("r").unwrap();
+
+        // Rounded, rotated shape with a few parameters
+        let dx = ctx.sub(x, cx).unwrap();
+        let a = ctx.atan2(y, dx).unwrap();
+        let s = ctx.sin(a).unwrap();
+        let dx2 = ctx.square(dx).unwrap();
+        let y2 = ctx.square(y).unwrap();
+        let d = ctx.add(dx2, y2).unwrap();
+        let d = ctx.sqrt(d).unwrap();
+        let rs = ctx.mul(r, s).unwrap();
+        let e = ctx.exp(rs).unwrap();
+        let f = ctx.sub(d, e).unwrap();
+        let f = ctx.max(f, y).unwrap();
+
+        let vars = BTreeMap::from([
+            ("X".to_owned(), 0.7),
+            ("Y".to_owned(), -0.3),
+            ("cx".to_owned(), 0.2),
+            ("r".to_owned(), 1.5),
+        ]);
+        let (v, grad) = ctx.eval_grad(f, &vars).unwrap();
+        assert_eq!(v, ctx.eval(f, &vars).unwrap());
+        assert_eq!(grad.len(), 4);
+        for name in vars.keys() {
+            let eps = 1e-6;
+            let mut hi = vars.clone();
+            *hi.get_mut(name).unwrap() += eps;
+            let mut lo = vars.clone();
+            *lo.get_mut(name).unwrap() -= eps;
+            let fd = (ctx.eval(f, &hi).unwrap() - ctx.eval(f, &lo).unwrap())
+                / (2.0 * eps);
+            assert!((fd - grad[name]).abs() < 1e-6, "{name}: {fd} != {grad:?}");
+        }
+
+        // Unchosen branches don't contribute (even with infinite derivatives)
+        let z = ctx.sqrt(y).unwrap();
+        let m = ctx.min(r, z).unwrap();
+        let vars =
+            BTreeMap::from([("Y".to_owned(), 0.0), ("r".to_owned(), -1.0)]);
+        let (v, grad) = ctx.eval_grad(m, &vars).unwrap();
+        assert_eq!(v, -1.0);
+        assert_eq!(grad["Y"], 0.0);
+        assert_eq!(grad["r"], 1.0);
+    }
+
+    #[test]
+    fn test_sign_equivalent() {
+        let (x, y, z) = Tree::axes();
+        let dist = |cx: f64| {
+            ((x.clone() - cx).square() + y.square() + z.square()).sqrt()
+        };
+        let sphere = |cx: f64, r: f64| dist(cx) - r;
+        let shape = sphere(0.0, 0.5)
+            .min(sphere(1.0, 0.25))
+            .max((Tree::constant(0.1) - dist(0.5)).neg())
+            * 2.0;
+
+        let mut ctx = Context::new();
+        let root = ctx.import(&shape);
+        let sq = ctx.sign_equivalent(root).unwrap();
+        assert!(!ctx.to_text(sq).unwrap().contains("sqrt"));
+        for i in 0..=40 {
+            let p = i as f64 / 20.0 - 0.5;
+            for q in [0.0, 0.1, 0.3] {
+                let a = ctx.eval_xyz(root, p, q, 0.0).unwrap();
+                let b = ctx.eval_xyz(sq, p, q, 0.0).unwrap();
+                assert_eq!(a.signum(), b.signum(), "mismatch at ({p}, {q})");
+            }
+        }
+
+        // `sqrt` of a value which may be negative is left alone
+        let root = ctx.import(&(x.sqrt() - 1.0));
+        assert_eq!(ctx.sign_equivalent(root).unwrap(), root);
+
+        // So is `sqrt` which isn't in a sign-preserving position
+        let root = ctx.import(&(sphere(0.0, 0.5) + x.clone()));
+        assert_eq!(ctx.sign_equivalent(root).unwrap(), root);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let f = ctx.var("field").unwrap();
+        assert!(ctx.range(f).is_none());
+        let two = ctx.constant(2.0);
+        assert_eq!(ctx.range(two), Some(Interval::from(2.0)));
+
+        ctx.set_range(f, Interval::new(-1.0, 1.0)).unwrap();
+        assert!(matches!(
+            ctx.set_range(f, Interval::new(2.0, 3.0)),
+            Err(Error::BadRange)
+        ));
+        assert!(matches!(
+            ctx.set_range(f, f32::NAN.into()),
+            Err(Error::BadRange)
+        ));
+
+        // Annotations are intersected
+        ctx.set_range(f, Interval::new(-0.5, 2.0)).unwrap();
+        assert_eq!(ctx.range(f), Some(Interval::new(-0.5, 1.0)));
+
+        // Folding based on annotated and derived ranges
+        assert_eq!(ctx.max(f, -3.0).unwrap(), f);
+        let g = ctx.add(f, 1.0).unwrap();
+        assert_eq!(ctx.range(g), Some(Interval::new(0.5, 2.0)));
+        assert_eq!(ctx.abs(g).unwrap(), g);
+        assert_eq!(ctx.min(g, 5.0).unwrap(), g);
+        let c = ctx.compare(g, 0.0).unwrap();
+        assert_eq!(ctx.const_value(c).unwrap(), Some(1.0));
+        let n = ctx.not(g).unwrap();
+        assert_eq!(ctx.const_value(n).unwrap(), Some(0.0));
+        assert_eq!(ctx.and(g, x).unwrap(), x);
+        assert_eq!(ctx.or(g, x).unwrap(), g);
+
+        // Nodes without a known range are left alone
+        let h = ctx.min(f, x).unwrap();
+        assert!(ctx.range(h).is_none());
+        assert_ne!(ctx.abs(f).unwrap(), f);
+
+        // Interval evaluation is tightened by the annotation
+        let vars = BTreeMap::from([
+            ("X".to_owned(), Interval::new(0.0, 1.0)),
+            ("field".to_owned(), Interval::new(-10.0, 10.0)),
+        ]);
+        let v = ctx.eval_interval(g, &vars).unwrap();
+        assert_eq!(v, Interval::new(0.5, 2.0));
+
+        // Ranges survive extraction
+        let (new_ctx, new_g) = ctx.extract(g).unwrap();
+        assert_eq!(new_ctx.range(new_g), ctx.range(g));
+    }
+
+    #[test]
+    fn test_eval_interval() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let t = ctx.var("thickness").unwrap();
+        assert_eq!(ctx.var("thickness").unwrap(), t);
+        assert!(matches!(ctx.var("X"), Err(Error::ReservedName)));
+
+        // Annulus with a variable thickness
+        let r = ctx.square(x).unwrap();
+        let r2 = ctx.square(y).unwrap();
+        let r = ctx.add(r, r2).unwrap();
+        let r = ctx.sqrt(r).unwrap();
+        let r = ctx.sub(r, 3.0).unwrap();
+        let r = ctx.abs(r).unwrap();
+        let shape = ctx.sub(r, t).unwrap();
+
+        let mut vars = BTreeMap::from([
+            ("X".to_owned(), Interval::new(0.0, 0.5)),
+            ("Y".to_owned(), Interval::new(0.0, 0.5)),
+            ("thickness".to_owned(), Interval::new(1.0, 2.0)),
+        ]);
+        let v = ctx.eval_interval(shape, &vars).unwrap();
+        assert!(v.lower() > 0.0, "expected empty region, got {v:?}");
+
+        vars.insert("thickness".to_owned(), Interval::new(1.0, 3.0));
+        let v = ctx.eval_interval(shape, &vars).unwrap();
+        assert!(v.contains(0.0));
+
+        // The interval contains every point sample
+        for (x, y, t) in [(0.0, 0.0, 1.0), (0.5, 0.25, 2.5), (0.1, 0.4, 3.0)] {
+            let p = ctx
+                .eval(
+                    shape,
+                    &BTreeMap::from([
+                        ("X".to_owned(), x),
+                        ("Y".to_owned(), y),
+                        ("thickness".to_owned(), t),
+                    ]),
+                )
+                .unwrap();
+            assert!(v.contains(p as f32), "{p} is not in {v:?}");
+        }
+
+        vars.remove("thickness");
+        assert!(matches!(
+            ctx.eval_interval(shape, &vars),
+            Err(Error::UnknownVariable(s)) if s == "thickness"
+        ));
+
+        // Custom variables round-trip through the text format, but aren't
+        // supported by tapes.
+        let (ctx2, root2) =
+            Context::from_text(ctx.to_text(shape).unwrap().as_bytes()).unwrap();
+        assert!(ctx2.inputs(root2).unwrap().vars.contains("thickness"));
+        assert!(matches!(
+            <crate::vm::VmShape as crate::eval::MathShape>::new(&ctx, shape),
+            Err(Error::UnknownVariable(s)) if s == "thickness"
+        ));
+    }
+
+    #[test]
+    fn test_inputs() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let c = ctx.constant(1.0);
+        let xy = ctx.mul(x, y).unwrap();
+        let xyc = ctx.add(xy, c).unwrap();
+        let out = ctx.max(xyc, z).unwrap();
+
+        let i = ctx.inputs(c).unwrap();
+        assert!(i.is_constant());
+        assert!(i.check_bindings([]).is_ok());
+
+        let i = ctx.inputs(xyc).unwrap();
+        assert_eq!((i.x, i.y, i.z), (true, true, false));
+        assert!(i.is_2d());
+        assert!(!i.is_constant());
+        assert!(i.check_bindings(["X", "Y"]).is_ok());
+        assert!(matches!(
+            i.check_bindings(["X", "Z"]),
+            Err(Error::UnknownVariable(s)) if s == "Y"
+        ));
+
+        let i = ctx.inputs(out).unwrap();
+        assert!(!i.is_2d());
+        assert!(i.vars.is_empty());
+        assert!(ctx.inputs(Node(1000)).is_err());
+
+        let deps = ctx.input_dependents(out).unwrap();
+        assert_eq!(deps.len(), 3);
+        assert_eq!(deps["X"], [x, xy, xyc, out].into());
+        assert_eq!(deps["Y"], [y, xy, xyc, out].into());
+        assert_eq!(deps["Z"], [z, out].into());
+
+        let deps = ctx.input_dependents(xy).unwrap();
+        assert_eq!(deps.len(), 2);
+        assert!(!deps.contains_key("Z"));
+
+        // Evaluating without a required binding is an error, not a panic
+        let mut vars = BTreeMap::new();
+        vars.insert("X".to_owned(), 1.0);
+        assert!(matches!(
+            ctx.eval(xy, &vars),
+            Err(Error::UnknownVariable(s)) if s == "Y"
+        ));
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let mut garbage = y;
+        for i in 0..100 {
+            garbage = ctx.add(garbage, i as f64).unwrap();
+        }
+        let a = ctx.mul(x, 2.0).unwrap();
+        ctx.define("a", a).unwrap();
+        ctx.define("g", garbage).unwrap();
+        let b = ctx.max(a, z).unwrap();
+        let root = ctx.sub(b, a).unwrap();
+        assert!(ctx.len() > 100);
+
+        let (new_ctx, new_root) = ctx.extract(root).unwrap();
+        assert_eq!(new_ctx.len(), 6);
+        assert!(new_root.0 < new_ctx.len());
+        for (px, pz) in [(1.0, 0.0), (-1.0, 3.0), (2.0, 5.0)] {
+            assert_eq!(
+                ctx.eval_xyz(root, px, 0.0, pz).unwrap(),
+                new_ctx.eval_xyz(new_root, px, 0.0, pz).unwrap()
+            );
+        }
+        assert_eq!(
+            new_ctx.definitions().map(|(n, _)| n).collect::<Vec<_>>(),
+            vec!["a"]
+        );
+        assert!(!new_ctx.inputs(new_root).unwrap().is_2d());
+        assert!(ctx.extract(Node(10_000)).is_err());
+
+        // Extracting an input or constant produces a one-node context
+        let (c, n) = ctx.extract(y).unwrap();
+        assert_eq!(c.len(), 1);
+        assert_eq!(c.var_name(n).unwrap(), Some("Y"));
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let r = ctx.var("r").unwrap();
+        let a = ctx.sub(x, r).unwrap();
+        ctx.define("a", a).unwrap();
+
+        let mut child = ctx.fork();
+        assert_eq!(child.len(), ctx.len());
+
+        // Existing nodes and variables are deduplicated through the parent
+        assert_eq!(child.x(), x);
+        assert_eq!(child.var("r").unwrap(), r);
+        assert_eq!(child.sub(x, r).unwrap(), a);
+        assert_eq!(child.get_defined("a"), Some(a));
+        assert_eq!(child.len(), ctx.len());
+
+        // New nodes only exist in the child
+        let y = child.y();
+        let b = child.mul(a, y).unwrap();
+        assert_eq!(child.len(), ctx.len() + 2);
+        assert!(ctx.get_op(b).is_none());
+
+        // The parent can keep growing without affecting the child
+        let c = ctx.add(a, 1.0).unwrap();
+        assert_eq!(child.get_op(b), Some(&Op::Binary(BinaryOpcode::Mul, a, y)));
+        assert_ne!(ctx.get_op(c), child.get_op(c));
+
+        // Forks can be nested, and tapes can be built from them
+        let mut grandchild = child.fork();
+        let z = grandchild.z();
+        let d = grandchild.add(b, z).unwrap();
+        let vars = BTreeMap::from([
+            ("X".to_owned(), 3.0),
+            ("Y".to_owned(), 2.0),
+            ("Z".to_owned(), 1.0),
+            ("r".to_owned(), 1.0),
+        ]);
+        assert_eq!(grandchild.eval(d, &vars).unwrap(), 5.0);
+        let e = grandchild.square(y).unwrap();
+        let tape = VmData::<255>::new(&grandchild, e).unwrap();
+        assert_eq!(tape.len(), 2);
+
+        // Forking without any edits is also fine
+        let f = grandchild.fork();
+        assert_eq!(f.len(), grandchild.len());
+        assert_eq!(f.get_op(e), grandchild.get_op(e));
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut ctx = Context::with_capacity(100);
+        let cap = ctx.capacity();
+        assert!(cap >= 100);
+
+        let mut x = ctx.x();
+        for i in 0..100 {
+            x = ctx.add(x, i as f64).unwrap();
+        }
+        assert!(ctx.len() > 100);
+        assert!(ctx.capacity() >= ctx.len());
+
+        // Handles remain valid after shrinking
+        ctx.reserve(10_000);
+        assert!(ctx.capacity() >= ctx.len() + 10_000);
+        ctx.shrink_to_fit();
+        assert!(ctx.capacity() < ctx.len() + 10_000);
+        assert_eq!(ctx.eval_xyz(x, 1.0, 0.0, 0.0).unwrap(), 4951.0);
+
+        ctx.clear();
+        ctx.shrink_to_fit();
+        assert_eq!(ctx.capacity(), 0);
+    }
+
+    #[test]
+    fn test_unary_constant_folding() {
+        let mut ctx = Context::new();
+        let a = ctx.constant(4.0);
+        let b = ctx.sqrt(a).unwrap();
+        assert_eq!(ctx.const_value(b).unwrap(), Some(2.0));
+        let c = ctx.neg(b).unwrap();
+        assert_eq!(ctx.const_value(c).unwrap(), Some(-2.0));
+        let d = ctx.abs(c).unwrap();
+        assert_eq!(d, b);
+        assert_eq!(ctx.len(), 3);
+
+        // Transcendental functions are folded as well
+        let zero = ctx.constant(0.0);
+        for op in [
+            Context::sin,
+            Context::cos,
+            Context::tan,
+            Context::asin,
+            Context::acos,
+            Context::atan,
+            Context::exp,
+            Context::ln,
+        ] {
+            let n = op(&mut ctx, zero).unwrap();
+            assert!(ctx.const_value(n).unwrap().is_some());
+        }
+
+        // Nested expressions on constants never produce operation nodes
+        let e = ctx.exp(1.0).unwrap();
+        let f = ctx.ln(e).unwrap();
+        let g = ctx.add(f, a).unwrap();
+        let h = ctx.square(g).unwrap();
+        let h = ctx.const_value(h).unwrap().unwrap();
+        assert!((h - 25.0).abs() < 1e-12);
+        assert!((0..ctx.len())
+            .map(Node)
+            .all(|n| matches!(ctx.get_op(n), Some(Op::Const(..)))));
+    }
+
+    #[test]
+    fn test_affine_canonicalization() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let a = ctx.add(x, 1.0).unwrap();
+        let a = ctx.mul(a, 2.0).unwrap();
+        let b = ctx.mul(x, 2.0).unwrap();
+        let b = ctx.add(b, 2.0).unwrap();
+        assert_ne!(a, b, "canonicalization should be disabled by default");
+
+        let mut ctx = Context::new();
+        ctx.set_affine_canonicalization(true);
+        let x = ctx.x();
+        let y = ctx.y();
+
+        let a = ctx.add(x, 1.0).unwrap();
+        let a = ctx.mul(2.0, a).unwrap();
+        let b = ctx.mul(x, 2.0).unwrap();
+        let b = ctx.add(2.0, b).unwrap();
+        assert_eq!(a, b);
+
+        // (x - 3) / 2 + 1.5 = x * 0.5
+        let c = ctx.sub(x, 3.0).unwrap();
+        let c = ctx.div(c, 2.0).unwrap();
+        let c = ctx.add(c, 1.5).unwrap();
+        let d = ctx.mul(x, 0.5).unwrap();
+        assert_eq!(c, d);
+
+        // -(1 - x) = x - 1
+        let e = ctx.sub(1.0, x).unwrap();
+        let e = ctx.neg(e).unwrap();
+        let f = ctx.sub(x, 1.0).unwrap();
+        assert_eq!(e, f);
+        let g = ctx.neg(e).unwrap();
+        let h = ctx.sub(1.0, x).unwrap();
+        assert_eq!(g, h);
+
+        // Same base node on both sides
+        let i = ctx.add(x, 1.0).unwrap();
+        let i = ctx.sub(i, x).unwrap();
+        assert_eq!(ctx.const_value(i).unwrap(), Some(1.0));
+        let j = ctx.add(f, x).unwrap();
+        assert_eq!(ctx.eval_xyz(j, 2.0, 0.0, 0.0).unwrap(), 3.0);
+
+        // Non-affine combinations are unchanged
+        let k = ctx.add(a, y).unwrap();
+        assert_eq!(ctx.eval_xyz(k, 1.0, 3.0, 0.0).unwrap(), 7.0);
+        let l = ctx.mul(x, y).unwrap();
+        assert_eq!(ctx.eval_xyz(l, 2.0, 3.0, 0.0).unwrap(), 6.0);
+
+        // Division by zero is not canonicalized
+        let m = ctx.div(x, 0.0).unwrap();
+        assert_eq!(ctx.eval_xyz(m, 1.0, 0.0, 0.0).unwrap(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_define() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        assert!(ctx.define("", x).is_err());
+        assert!(ctx.define("_0", x).is_err());
+        assert!(ctx.define("#x", x).is_err());
+        assert!(ctx.define("x y", x).is_err());
+        assert!(ctx.define("x", Node(1000)).is_err());
+
+        let a = ctx.define("a", x).unwrap();
+        assert_eq!(a, x);
+        assert_eq!(ctx.get_defined("a"), Some(x));
+        assert_eq!(ctx.get_defined("b"), None);
+
+        let y = ctx.y();
+        ctx.define("a", y).unwrap();
+        assert_eq!(ctx.get_defined("a"), Some(y));
+        assert_eq!(ctx.defined_name(x), None);
+        assert_eq!(ctx.defined_name(y), Some("a"));
+
+        ctx.clear();
+        assert_eq!(ctx.get_defined("a"), None);
+    }
+
+    #[test]
+    fn test_redefine() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let r = ctx.mul(x, 0.5).unwrap();
+        let r = ctx.define("r", r).unwrap();
+        let x2 = ctx.square(x).unwrap();
+        let y2 = ctx.square(y).unwrap();
+        let sum = ctx.add(x2, y2).unwrap();
+        let sum = ctx.define("sum", sum).unwrap();
+        let out = ctx.sub(sum, r).unwrap();
+        let r_plus = ctx.add(r, 1.0).unwrap();
+        ctx.define("r_plus", r_plus).unwrap();
+        assert_eq!(ctx.eval_xyz(out, 1.0, 2.0, 0.0).unwrap(), 4.5);
+
+        // Redefining `r` updates both the root and dependent definitions
+        let out = ctx.redefine("r", y, out).unwrap();
+        assert_eq!(ctx.eval_xyz(out, 1.0, 2.0, 0.0).unwrap(), 3.0);
+        assert_eq!(ctx.get_defined("r"), Some(y));
+        assert_eq!(ctx.get_defined("sum"), Some(sum));
+        let r_plus = ctx.get_defined("r_plus").unwrap();
+        assert_eq!(ctx.eval_xyz(r_plus, 1.0, 2.0, 0.0).unwrap(), 3.0);
+
+        assert!(ctx.redefine("q", 3.0, out).is_err());
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        const HI: &str = include_str!("../../../../models/hi.vm");
+        let (ctx, root) = Context::from_text(HI.as_bytes()).unwrap();
+        let txt = ctx.to_text(root).unwrap();
+        let (ctx2, root2) = Context::from_text(txt.as_bytes()).unwrap();
+        assert_eq!(ctx.len(), ctx2.len());
+        for (x, y) in [(0.0, 0.0), (0.5, 0.5), (0.3, 0.7), (-1.0, 0.2)] {
+            assert_eq!(
+                ctx.eval_xyz(root, x, y, 0.0).unwrap(),
+                ctx2.eval_xyz(root2, x, y, 0.0).unwrap()
+            );
+        }
+
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let x = ctx.define("px", x).unwrap();
+        let r = ctx.recip(x).unwrap();
+        let r = ctx.define("inv", r).unwrap();
+        let out = ctx.define("out", r).unwrap();
+        let txt = ctx.to_text(out).unwrap();
+        assert_eq!(
+            txt,
+            "_0 var-x\npx define _0\n_1 recip px\ninv define _1\nout define inv\n"
+        );
+        let (ctx, root) = Context::from_text(txt.as_bytes()).unwrap();
+        assert_eq!(ctx.eval_xyz(root, 4.0, 0.0, 0.0).unwrap(), 0.25);
+        assert_eq!(ctx.get_defined("out"), Some(root));
+        assert_eq!(ctx.get_defined("inv"), Some(root));
+        assert!(ctx.get_defined("px").is_some());
+    }
 }
@@ -1,4 +1,7 @@
-use crate::context::{indexed::Index, Node, VarNode};
+use crate::{
+    context::{indexed::Index, Node, VarNode},
+    types::Interval,
+};
 use ordered_float::OrderedFloat;
 
 /// A one-argument math operation
@@ -24,6 +27,111 @@ pub enum UnaryOpcode {
     Not,
 }
 
+impl UnaryOpcode {
+    /// Applies the operation to a single value
+    ///
+    /// This is the reference implementation used for constant folding and by
+    /// [`Context::eval`](crate::context::Context::eval).
+    pub fn eval(self, a: f64) -> f64 {
+        match self {
+            UnaryOpcode::Neg => -a,
+            UnaryOpcode::Abs => a.abs(),
+            UnaryOpcode::Recip => 1.0 / a,
+            UnaryOpcode::Sqrt => a.sqrt(),
+            UnaryOpcode::Square => a * a,
+            UnaryOpcode::Floor => a.floor(),
+            UnaryOpcode::Ceil => a.ceil(),
+            UnaryOpcode::Round => a.round(),
+            UnaryOpcode::Sin => a.sin(),
+            UnaryOpcode::Cos => a.cos(),
+            UnaryOpcode::Tan => a.tan(),
+            UnaryOpcode::Asin => a.asin(),
+            UnaryOpcode::Acos => a.acos(),
+            UnaryOpcode::Atan => a.atan(),
+            UnaryOpcode::Exp => a.exp(),
+            UnaryOpcode::Ln => a.ln(),
+            UnaryOpcode::Not => (a == 0.0).into(),
+        }
+    }
+
+    /// Applies the operation to an interval
+    ///
+    /// This is the reference implementation used by
+    /// [`Context::eval_interval`](crate::context::Context::eval_interval).
+    pub fn eval_interval(self, a: Interval) -> Interval {
+        match self {
+            UnaryOpcode::Neg => -a,
+            UnaryOpcode::Abs => a.abs(),
+            UnaryOpcode::Recip => a.recip(),
+            UnaryOpcode::Sqrt => a.sqrt(),
+            UnaryOpcode::Square => a.square(),
+            UnaryOpcode::Floor => a.floor(),
+            UnaryOpcode::Ceil => a.ceil(),
+            UnaryOpcode::Round => a.round(),
+            UnaryOpcode::Sin => a.sin(),
+            UnaryOpcode::Cos => a.cos(),
+            UnaryOpcode::Tan => a.tan(),
+            UnaryOpcode::Asin => a.asin(),
+            UnaryOpcode::Acos => a.acos(),
+            UnaryOpcode::Atan => a.atan(),
+            UnaryOpcode::Exp => a.exp(),
+            UnaryOpcode::Ln => a.ln(),
+            UnaryOpcode::Not => {
+                if !a.contains(0.0) {
+                    Interval::new(0.0, 0.0)
+                } else if a.lower() == 0.0 && a.upper() == 0.0 {
+                    Interval::new(1.0, 1.0)
+                } else {
+                    Interval::new(0.0, 1.0)
+                }
+            }
+        }
+    }
+
+    /// Returns the derivative of the operation at `a`
+    ///
+    /// Discontinuous operations (`floor`, `ceil`, `round`, `not`) have a
+    /// derivative of zero.  This is the reference implementation used by
+    /// [`Context::eval_grad`](crate::context::Context::eval_grad).
+    pub fn deriv(self, a: f64) -> f64 {
+        match self {
+            UnaryOpcode::Neg => -1.0,
+            UnaryOpcode::Abs => {
+                if a < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                }
+            }
+            UnaryOpcode::Recip => -1.0 / (a * a),
+            UnaryOpcode::Sqrt => 0.5 / a.sqrt(),
+            UnaryOpcode::Square => 2.0 * a,
+            UnaryOpcode::Floor
+            | UnaryOpcode::Ceil
+            | UnaryOpcode::Round
+            | UnaryOpcode::Not => 0.0,
+            UnaryOpcode::Sin => a.cos(),
+            UnaryOpcode::Cos => -a.sin(),
+            UnaryOpcode::Tan => 1.0 / a.cos().powi(2),
+            UnaryOpcode::Asin => 1.0 / (1.0 - a * a).sqrt(),
+            UnaryOpcode::Acos => -1.0 / (1.0 - a * a).sqrt(),
+            UnaryOpcode::Atan => 1.0 / (a * a + 1.0),
+            UnaryOpcode::Exp => a.exp(),
+            UnaryOpcode::Ln => 1.0 / a,
+        }
+    }
+
+    /// Checks whether the sign of the result depends only on the argument's
+    /// sign (negative, zero, or positive)
+    ///
+    /// If so, the argument may be replaced by any expression with the same
+    /// sign everywhere without changing the sign of the result; this is used
+    /// by [`Context::sign_equivalent`](crate::context::Context::sign_equivalent).
+    pub fn preserves_sign(self) -> bool {
+        matches!(self, UnaryOpcode::Neg | UnaryOpcode::Not)
+    }
+}
+
 /// A two-argument math operation
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
@@ -41,6 +149,150 @@ pub enum BinaryOpcode {
     Or,
 }
 
+impl BinaryOpcode {
+    /// Applies the operation to a pair of values
+    ///
+    /// This is the reference implementation used for constant folding and by
+    /// [`Context::eval`](crate::context::Context::eval).
+    pub fn eval(self, a: f64, b: f64) -> f64 {
+        match self {
+            BinaryOpcode::Add => a + b,
+            BinaryOpcode::Sub => a - b,
+            BinaryOpcode::Mul => a * b,
+            BinaryOpcode::Div => a / b,
+            BinaryOpcode::Atan => a.atan2(b),
+            BinaryOpcode::Min => a.min(b),
+            BinaryOpcode::Max => a.max(b),
+            BinaryOpcode::Compare => a
+                .partial_cmp(&b)
+                .map(|i| i as i8 as f64)
+                .unwrap_or(f64::NAN),
+            BinaryOpcode::Mod => a.rem_euclid(b),
+            BinaryOpcode::And => {
+                if a == 0.0 {
+                    a
+                } else {
+                    b
+                }
+            }
+            BinaryOpcode::Or => {
+                if a != 0.0 {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+    }
+
+    /// Applies the operation to a pair of intervals
+    ///
+    /// This is the reference implementation used by
+    /// [`Context::eval_interval`](crate::context::Context::eval_interval).
+    pub fn eval_interval(self, a: Interval, b: Interval) -> Interval {
+        match self {
+            BinaryOpcode::Add => a + b,
+            BinaryOpcode::Sub => a - b,
+            BinaryOpcode::Mul => a * b,
+            BinaryOpcode::Div => a / b,
+            BinaryOpcode::Atan => a.atan2(b),
+            BinaryOpcode::Min => a.min_choice(b).0,
+            BinaryOpcode::Max => a.max_choice(b).0,
+            BinaryOpcode::Compare => {
+                if a.has_nan() || b.has_nan() {
+                    f32::NAN.into()
+                } else if a.upper() < b.lower() {
+                    Interval::from(-1.0)
+                } else if a.lower() > b.upper() {
+                    Interval::from(1.0)
+                } else {
+                    Interval::new(-1.0, 1.0)
+                }
+            }
+            BinaryOpcode::Mod => a.rem_euclid(b),
+            BinaryOpcode::And => a.and_choice(b).0,
+            BinaryOpcode::Or => a.or_choice(b).0,
+        }
+    }
+
+    /// Returns the partial derivatives of the operation at `(a, b)`
+    ///
+    /// Choice operations take the derivative of whichever side is selected
+    /// (with ties going to `b`, matching [`Grad::min`](crate::types::Grad::min)),
+    /// and `compare` has a derivative of zero.  This is the reference
+    /// implementation used by
+    /// [`Context::eval_grad`](crate::context::Context::eval_grad).
+    pub fn deriv(self, a: f64, b: f64) -> (f64, f64) {
+        match self {
+            BinaryOpcode::Add => (1.0, 1.0),
+            BinaryOpcode::Sub => (1.0, -1.0),
+            BinaryOpcode::Mul => (b, a),
+            BinaryOpcode::Div => (1.0 / b, -a / (b * b)),
+            BinaryOpcode::Atan => {
+                let d = a * a + b * b;
+                (b / d, -a / d)
+            }
+            BinaryOpcode::Min => {
+                if a < b {
+                    (1.0, 0.0)
+                } else {
+                    (0.0, 1.0)
+                }
+            }
+            BinaryOpcode::Max => {
+                if a > b {
+                    (1.0, 0.0)
+                } else {
+                    (0.0, 1.0)
+                }
+            }
+            BinaryOpcode::Compare => (0.0, 0.0),
+            BinaryOpcode::Mod => (1.0, -a.div_euclid(b)),
+            BinaryOpcode::And => {
+                if a == 0.0 {
+                    (1.0, 0.0)
+                } else {
+                    (0.0, 1.0)
+                }
+            }
+            BinaryOpcode::Or => {
+                if a != 0.0 {
+                    (1.0, 0.0)
+                } else {
+                    (0.0, 1.0)
+                }
+            }
+        }
+    }
+
+    /// Checks whether the operation's arguments can be swapped without changing
+    /// its result
+    pub fn is_commutative(self) -> bool {
+        matches!(
+            self,
+            BinaryOpcode::Add
+                | BinaryOpcode::Mul
+                | BinaryOpcode::Min
+                | BinaryOpcode::Max
+        )
+    }
+
+    /// Checks whether the sign of the result depends only on the arguments'
+    /// signs (negative, zero, or positive)
+    ///
+    /// See [`UnaryOpcode::preserves_sign`] for details.
+    pub fn preserves_sign(self) -> bool {
+        matches!(
+            self,
+            BinaryOpcode::Mul
+                | BinaryOpcode::Min
+                | BinaryOpcode::Max
+                | BinaryOpcode::And
+                | BinaryOpcode::Or
+        )
+    }
+}
+
 /// An operation in a math expression
 ///
 /// `Op`s should be constructed by calling functions on
@@ -0,0 +1,521 @@
+//! User-defined rewrite rules, applied by [`Context::optimize`]
+use crate::{
+    context::{BinaryOpcode, Context, Node, Op, UnaryOpcode},
+    Error,
+};
+use std::collections::HashMap;
+
+/// Pattern which matches (or builds) part of a math expression
+///
+/// Patterns are used on both sides of a [`Rule`]: the left-hand side is
+/// matched against a node, binding subexpressions to numbered captures, and
+/// the right-hand side is instantiated using those captures.
+///
+/// Binary patterns for commutative operations (see
+/// [`BinaryOpcode::is_commutative`]) match their arguments in either order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    /// Matches any node, binding it to the given capture slot
+    ///
+    /// If a slot appears more than once, every occurrence must match the same
+    /// node.  When instantiated, this is replaced by the captured node.
+    Capture(usize),
+    /// Matches a constant with exactly this value
+    Const(f64),
+    /// Matches the variable with the given name (e.g. `"X"`)
+    Var(String),
+    /// Matches a unary operation
+    Unary(UnaryOpcode, Box<Pattern>),
+    /// Matches a binary operation
+    Binary(BinaryOpcode, Box<Pattern>, Box<Pattern>),
+}
+
+impl From<f64> for Pattern {
+    fn from(v: f64) -> Self {
+        Pattern::Const(v)
+    }
+}
+
+impl Pattern {
+    /// Builds a pattern for a unary operation
+    pub fn unary<A: Into<Pattern>>(op: UnaryOpcode, a: A) -> Self {
+        Pattern::Unary(op, Box::new(a.into()))
+    }
+
+    /// Builds a pattern for a binary operation
+    pub fn binary<A: Into<Pattern>, B: Into<Pattern>>(
+        op: BinaryOpcode,
+        a: A,
+        b: B,
+    ) -> Self {
+        Pattern::Binary(op, Box::new(a.into()), Box::new(b.into()))
+    }
+
+    /// Matches the pattern against a node, returning captured nodes
+    pub fn matches(&self, ctx: &Context, node: Node) -> Option<Captures> {
+        let mut out = Captures::default();
+        self.match_inner(ctx, node, &mut out).then_some(out)
+    }
+
+    fn match_inner(
+        &self,
+        ctx: &Context,
+        node: Node,
+        out: &mut Captures,
+    ) -> bool {
+        let Some(op) = ctx.get_op(node) else {
+            return false;
+        };
+        match (self, *op) {
+            (Pattern::Capture(i), _) => match out.get(*i) {
+                Some(n) => n == node,
+                None => {
+                    out.set(*i, node);
+                    true
+                }
+            },
+            (Pattern::Const(v), Op::Const(c)) => c.0 == *v,
+            (Pattern::Var(name), Op::Input(..)) => {
+                ctx.var_name(node).ok().flatten() == Some(name.as_str())
+            }
+            (Pattern::Unary(p, a), Op::Unary(op, na)) => {
+                *p == op && a.match_inner(ctx, na, out)
+            }
+            (Pattern::Binary(p, a, b), Op::Binary(op, na, nb)) if *p == op => {
+                let prev = out.clone();
+                if a.match_inner(ctx, na, out) && b.match_inner(ctx, nb, out) {
+                    return true;
+                }
+                *out = prev;
+                op.is_commutative()
+                    && a.match_inner(ctx, nb, out)
+                    && b.match_inner(ctx, na, out)
+            }
+            _ => false,
+        }
+    }
+
+    /// Builds the expression described by this pattern
+    ///
+    /// Returns [`Error::UnboundCapture`] if the pattern uses a capture which
+    /// isn't present in `captures`.
+    pub fn build(
+        &self,
+        ctx: &mut Context,
+        captures: &Captures,
+    ) -> Result<Node, Error> {
+        match self {
+            Pattern::Capture(i) => {
+                captures.get(*i).ok_or(Error::UnboundCapture(*i))
+            }
+            Pattern::Const(v) => Ok(ctx.constant(*v)),
+            Pattern::Var(name) => match name.as_str() {
+                "X" => Ok(ctx.x()),
+                "Y" => Ok(ctx.y()),
+                "Z" => Ok(ctx.z()),
+                _ => ctx.var(name),
+            },
+            Pattern::Unary(op, a) => {
+                let a = a.build(ctx, captures)?;
+                ctx.op_unary(a, *op)
+            }
+            Pattern::Binary(op, a, b) => {
+                let a = a.build(ctx, captures)?;
+                let b = b.build(ctx, captures)?;
+                if op.is_commutative() {
+                    ctx.op_binary_commutative(a, b, *op)
+                } else {
+                    ctx.op_binary(a, b, *op)
+                }
+            }
+        }
+    }
+
+    /// Returns the largest capture slot used in this pattern
+    fn max_capture(&self) -> Option<usize> {
+        match self {
+            Pattern::Capture(i) => Some(*i),
+            Pattern::Const(..) | Pattern::Var(..) => None,
+            Pattern::Unary(_, a) => a.max_capture(),
+            Pattern::Binary(_, a, b) => a.max_capture().max(b.max_capture()),
+        }
+    }
+
+    /// Checks whether the given capture slot is bound by this pattern
+    fn binds(&self, i: usize) -> bool {
+        match self {
+            Pattern::Capture(j) => i == *j,
+            Pattern::Const(..) | Pattern::Var(..) => false,
+            Pattern::Unary(_, a) => a.binds(i),
+            Pattern::Binary(_, a, b) => a.binds(i) || b.binds(i),
+        }
+    }
+}
+
+/// Nodes captured by [`Pattern::matches`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Captures(Vec<Option<Node>>);
+
+impl Captures {
+    /// Returns the node bound to the given capture slot, if any
+    pub fn get(&self, i: usize) -> Option<Node> {
+        self.0.get(i).copied().flatten()
+    }
+
+    /// Binds a node to the given capture slot
+    pub fn set(&mut self, i: usize, node: Node) {
+        if self.0.len() <= i {
+            self.0.resize(i + 1, None);
+        }
+        self.0[i] = Some(node);
+    }
+}
+
+/// A rewrite rule, which may replace a node with an equivalent node
+///
+/// Most rules can be written as a [`Rule`]; this trait allows for rules with
+/// arbitrary logic (e.g. conditions on constants or ranges), and is
+/// implemented for functions with the same signature as
+/// [`apply`](RewriteRule::apply).
+pub trait RewriteRule {
+    /// Attempts to rewrite the given node
+    ///
+    /// Returns `Ok(None)` if the rule doesn't apply.  The returned node must
+    /// be equivalent to `node`; it may be built from new nodes in `ctx`.
+    fn apply(
+        &self,
+        ctx: &mut Context,
+        node: Node,
+    ) -> Result<Option<Node>, Error>;
+}
+
+impl<F> RewriteRule for F
+where
+    F: Fn(&mut Context, Node) -> Result<Option<Node>, Error>,
+{
+    fn apply(
+        &self,
+        ctx: &mut Context,
+        node: Node,
+    ) -> Result<Option<Node>, Error> {
+        self(ctx, node)
+    }
+}
+
+/// A rewrite rule built from a pair of patterns
+///
+/// ```
+/// use fidget::context::{
+///     BinaryOpcode, Context, Pattern, Rewriter, Rule, UnaryOpcode,
+/// };
+///
+/// // sin(a)² + cos(a)² = 1
+/// let a = Pattern::Capture(0);
+/// let lhs = Pattern::binary(
+///     BinaryOpcode::Add,
+///     Pattern::unary(
+///         UnaryOpcode::Square,
+///         Pattern::unary(UnaryOpcode::Sin, a.clone()),
+///     ),
+///     Pattern::unary(UnaryOpcode::Square, Pattern::unary(UnaryOpcode::Cos, a)),
+/// );
+/// let mut rw = Rewriter::new();
+/// rw.register(Rule::new(lhs, 1.0.into())?);
+///
+/// let mut ctx = Context::new();
+/// let x = ctx.x();
+/// let y = ctx.y();
+/// let t = ctx.mul(x, y)?;
+/// let (s, c) = (ctx.sin(t)?, ctx.cos(t)?);
+/// let (s2, c2) = (ctx.square(s)?, ctx.square(c)?);
+/// let sum = ctx.add(c2, s2)?;
+/// let root = ctx.add(sum, x)?;
+///
+/// let out = ctx.optimize(root, &rw)?;
+/// assert_eq!(out, ctx.add(x, 1.0)?);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Rule {
+    lhs: Pattern,
+    rhs: Pattern,
+}
+
+impl Rule {
+    /// Builds a new rule which rewrites `lhs` into `rhs`
+    ///
+    /// Returns [`Error::UnboundCapture`] if `rhs` uses a capture which isn't
+    /// bound by `lhs`.
+    pub fn new(lhs: Pattern, rhs: Pattern) -> Result<Self, Error> {
+        for i in 0..=rhs.max_capture().unwrap_or(0) {
+            if rhs.binds(i) && !lhs.binds(i) {
+                return Err(Error::UnboundCapture(i));
+            }
+        }
+        Ok(Self { lhs, rhs })
+    }
+}
+
+impl RewriteRule for Rule {
+    fn apply(
+        &self,
+        ctx: &mut Context,
+        node: Node,
+    ) -> Result<Option<Node>, Error> {
+        match self.lhs.matches(ctx, node) {
+            Some(c) => self.rhs.build(ctx, &c).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A set of rewrite rules, applied by [`Context::optimize`]
+pub struct Rewriter {
+    rules: Vec<Box<dyn RewriteRule>>,
+    max_steps: usize,
+}
+
+impl Default for Rewriter {
+    fn default() -> Self {
+        Self {
+            rules: vec![],
+            max_steps: 10_000,
+        }
+    }
+}
+
+impl Rewriter {
+    /// Builds an empty rewriter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule
+    ///
+    /// Rules are tried in the order that they were registered.
+    pub fn register<R: RewriteRule + 'static>(&mut self, rule: R) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Sets the maximum number of rewrites in a single call to
+    /// [`Context::optimize`] (10,000 by default)
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+}
+
+impl Context {
+    /// Rewrites the expression rooted at `root` with user-defined rules
+    ///
+    /// The expression is rebuilt from the bottom up.  At each node, rules are
+    /// applied repeatedly until none of them match; rewritten nodes are then
+    /// optimized in turn, so that rules can enable each other.
+    ///
+    /// Rule sets which don't converge are cut short:
+    /// - If rewriting a node returns to an earlier form of that node, the
+    ///   cycle is broken and the current form is kept
+    /// - While a node is being rewritten, references to it within its own
+    ///   replacement are left as-is (rather than rewritten recursively)
+    /// - After [`Rewriter::set_max_steps`] rewrites, no further rules are
+    ///   applied
+    ///
+    /// In each case, the result is still equivalent to `root` (assuming that
+    /// every rule is valid), though it may not be fully optimized.
+    pub fn optimize(
+        &mut self,
+        root: Node,
+        rewriter: &Rewriter,
+    ) -> Result<Node, Error> {
+        self.check_node(root)?;
+        let mut cache = HashMap::new();
+        let mut steps = 0;
+        self.optimize_inner(root, rewriter, &mut cache, &mut steps)
+    }
+
+    fn optimize_inner(
+        &mut self,
+        node: Node,
+        rewriter: &Rewriter,
+        cache: &mut HashMap<Node, Node>,
+        steps: &mut usize,
+    ) -> Result<Node, Error> {
+        if let Some(n) = cache.get(&node) {
+            return Ok(*n);
+        }
+        // Guard against the node appearing within its own replacement
+        cache.insert(node, node);
+
+        let mut out = match *self.get_op(node).ok_or(Error::BadNode)? {
+            Op::Input(..) | Op::Const(..) => node,
+            Op::Unary(op, a) => {
+                let a = self.optimize_inner(a, rewriter, cache, steps)?;
+                self.op_unary(a, op)?
+            }
+            Op::Binary(op, a, b) => {
+                let a = self.optimize_inner(a, rewriter, cache, steps)?;
+                let b = self.optimize_inner(b, rewriter, cache, steps)?;
+                if op.is_commutative() {
+                    self.op_binary_commutative(a, b, op)?
+                } else {
+                    self.op_binary(a, b, op)?
+                }
+            }
+        };
+        cache.insert(node, out);
+
+        let mut seen = vec![node, out];
+        'outer: while *steps < rewriter.max_steps {
+            for rule in &rewriter.rules {
+                let Some(next) = rule.apply(self, out)? else {
+                    continue;
+                };
+                if next == out {
+                    continue;
+                }
+                *steps += 1;
+                if seen.contains(&next) {
+                    break 'outer;
+                }
+                seen.push(next);
+                cache.insert(node, next);
+                out = self.optimize_inner(next, rewriter, cache, steps)?;
+                cache.insert(node, out);
+                continue 'outer;
+            }
+            break;
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pattern_match() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let sum = ctx.add(y, x).unwrap();
+
+        // Commutative patterns match in either order
+        let p = Pattern::binary(
+            BinaryOpcode::Add,
+            Pattern::Var("X".to_owned()),
+            Pattern::Capture(0),
+        );
+        let c = p.matches(&ctx, sum).unwrap();
+        assert_eq!(c.get(0), Some(y));
+
+        // Repeated captures must match the same node
+        let p = Pattern::binary(
+            BinaryOpcode::Atan,
+            Pattern::Capture(0),
+            Pattern::Capture(0),
+        );
+        let a = ctx.atan2(y, x).unwrap();
+        assert!(p.matches(&ctx, a).is_none());
+        let a = ctx.atan2(x, x).unwrap();
+        assert_eq!(p.matches(&ctx, a).unwrap().get(0), Some(x));
+
+        // Non-commutative patterns only match in order
+        let diff = ctx.sub(y, x).unwrap();
+        let p = Pattern::binary(
+            BinaryOpcode::Sub,
+            Pattern::Var("X".to_owned()),
+            Pattern::Capture(0),
+        );
+        assert!(p.matches(&ctx, diff).is_none());
+
+        assert!(matches!(
+            Rule::new(Pattern::Capture(0), Pattern::Capture(1)),
+            Err(Error::UnboundCapture(1))
+        ));
+    }
+
+    #[test]
+    fn test_optimize() {
+        // max(a, -a) = abs(a), applied bottom-up
+        let mut rw = Rewriter::new();
+        let a = Pattern::Capture(0);
+        rw.register(
+            Rule::new(
+                Pattern::binary(
+                    BinaryOpcode::Max,
+                    a.clone(),
+                    Pattern::unary(UnaryOpcode::Neg, a.clone()),
+                ),
+                Pattern::unary(UnaryOpcode::Abs, a),
+            )
+            .unwrap(),
+        );
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let nx = ctx.neg(x).unwrap();
+        let ax = ctx.max(nx, x).unwrap();
+        let nax = ctx.neg(ax).unwrap();
+        let root = ctx.max(ax, nax).unwrap();
+        let out = ctx.optimize(root, &rw).unwrap();
+        let expected = ctx.abs(x).unwrap();
+        let expected = ctx.abs(expected).unwrap();
+        assert_eq!(out, expected);
+        for v in [-1.0, 0.5, 3.0] {
+            assert_eq!(
+                ctx.eval_xyz(out, v, 0.0, 0.0).unwrap(),
+                ctx.eval_xyz(root, v, 0.0, 0.0).unwrap()
+            );
+        }
+
+        // Function rules may use arbitrary logic
+        let mut rw = Rewriter::new();
+        rw.register(|ctx: &mut Context, n: Node| {
+            let abs_arg = |n| match ctx.get_op(n) {
+                Some(Op::Unary(UnaryOpcode::Abs, a)) => Some(*a),
+                _ => None,
+            };
+            Ok(abs_arg(n).filter(|a| abs_arg(*a).is_some()))
+        });
+        let out = ctx.optimize(out, &rw).unwrap();
+        assert_eq!(out, ctx.abs(x).unwrap());
+    }
+
+    #[test]
+    fn test_optimize_termination() {
+        // Commutation loops forever without the cycle check
+        let mut rw = Rewriter::new();
+        let (a, b) = (Pattern::Capture(0), Pattern::Capture(1));
+        rw.register(
+            Rule::new(
+                Pattern::binary(BinaryOpcode::Sub, a.clone(), b.clone()),
+                Pattern::unary(
+                    UnaryOpcode::Neg,
+                    Pattern::binary(BinaryOpcode::Sub, b, a),
+                ),
+            )
+            .unwrap(),
+        );
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let root = ctx.sub(x, y).unwrap();
+        let out = ctx.optimize(root, &rw).unwrap();
+        assert_eq!(
+            ctx.eval_xyz(out, 3.0, 1.0, 0.0).unwrap(),
+            ctx.eval_xyz(root, 3.0, 1.0, 0.0).unwrap()
+        );
+
+        // Rules which grow the expression forever hit the step limit
+        let mut rw = Rewriter::new();
+        rw.register(
+            Rule::new(
+                Pattern::Capture(0),
+                Pattern::binary(BinaryOpcode::Max, Pattern::Capture(0), 0.0),
+            )
+            .unwrap(),
+        );
+        rw.set_max_steps(100);
+        let out = ctx.optimize(x, &rw).unwrap();
+        assert_eq!(ctx.eval_xyz(out, 3.0, 0.0, 0.0).unwrap(), 3.0);
+    }
+}
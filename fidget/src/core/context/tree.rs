@@ -1,5 +1,9 @@
 //! Context-free math trees
 use super::op::{BinaryOpcode, UnaryOpcode};
+use crate::{
+    types::{Length, Radians},
+    Error,
+};
 use std::sync::Arc;
 
 /// Opcode type for trees
@@ -146,6 +150,94 @@ impl Tree {
             z: z.0,
         }))
     }
+
+    /// Returns cylindrical coordinates `(r, θ, z)` as functions of `(x, y, z)`
+    ///
+    /// `θ` is in the range `[-π, π]`, measured counter-clockwise from the +X
+    /// axis.  `r` is built from squares (rather than multiplication), so its
+    /// interval remains non-negative and tight when a region straddles the
+    /// Z axis.
+    pub fn cylindrical() -> (Self, Self, Self) {
+        let (x, y, z) = Self::axes();
+        let r = (x.square() + y.square()).sqrt();
+        let theta = y.atan2(x);
+        (r, theta, z)
+    }
+
+    /// Returns spherical coordinates `(r, θ, φ)` as functions of `(x, y, z)`
+    ///
+    /// `θ` is the azimuthal angle in the range `[-π, π]` (as in
+    /// [`cylindrical`](Self::cylindrical)), and `φ` is the polar angle from
+    /// the +Z axis in the range `[0, π]`.
+    ///
+    /// `φ` is computed with `atan2` rather than `acos(z / r)`, so it remains
+    /// well-defined at the origin and has a bounded interval near it.
+    pub fn spherical() -> (Self, Self, Self) {
+        let (x, y, z) = Self::axes();
+        let rho = (x.square() + y.square()).sqrt();
+        let r = (x.square() + y.square() + z.square()).sqrt();
+        let theta = y.atan2(x);
+        let phi = rho.atan2(z);
+        (r, theta, phi)
+    }
+
+    /// Interprets this tree in cylindrical coordinates
+    ///
+    /// The tree's X, Y, and Z axes are treated as `r`, `θ`, and `z`
+    /// respectively (see [`cylindrical`](Self::cylindrical)); the result is a
+    /// tree in Cartesian space.
+    pub fn to_cylindrical(&self) -> Tree {
+        let (r, theta, z) = Self::cylindrical();
+        self.remap_xyz(r, theta, z)
+    }
+
+    /// Interprets this tree in spherical coordinates
+    ///
+    /// The tree's X, Y, and Z axes are treated as `r`, `θ`, and `φ`
+    /// respectively (see [`spherical`](Self::spherical)); the result is a
+    /// tree in Cartesian space.
+    pub fn to_spherical(&self) -> Tree {
+        let (r, theta, phi) = Self::spherical();
+        self.remap_xyz(r, theta, phi)
+    }
+
+    /// Rotates this tree counter-clockwise about the Z axis
+    ///
+    /// The angle may be given as [`Radians`] or
+    /// [`Degrees`](crate::types::Degrees).
+    pub fn rotate_z<A: Into<Radians>>(&self, angle: A) -> Tree {
+        let a = angle.into().get();
+        let (x, y, z) = Self::axes();
+        let (s, c) = a.sin_cos();
+        self.remap_xyz(x.clone() * c + y.clone() * s, y * c - x * s, z)
+    }
+
+    /// Twists this tree about the Z axis
+    ///
+    /// Each XY slice is rotated counter-clockwise by `angle` for every
+    /// `length` of distance along the Z axis (with no rotation at `z = 0`).
+    ///
+    /// Returns [`Error::InvalidLength`] if `length` is zero or not finite,
+    /// since the twist rate would be infinite or NaN.
+    pub fn twist_z<A: Into<Radians>, L: Into<Length>>(
+        &self,
+        angle: A,
+        length: L,
+    ) -> Result<Tree, Error> {
+        let length = length.into().get();
+        if length == 0.0 || !length.is_finite() {
+            return Err(Error::InvalidLength);
+        }
+        let rate = angle.into().get() / length;
+        let (x, y, z) = Self::axes();
+        let t = z.clone() * rate;
+        let (s, c) = (t.sin(), t.cos());
+        Ok(self.remap_xyz(
+            x.clone() * c.clone() + y.clone() * s.clone(),
+            y * c - x * s,
+            z,
+        ))
+    }
 }
 
 /// See [`Context`](crate::Context) for documentation of these functions
@@ -293,6 +385,130 @@ mod test {
         assert_eq!(x1, x2);
     }
 
+    #[test]
+    fn test_cylindrical() {
+        use std::f64::consts::PI;
+        let mut ctx = Context::new();
+
+        // A cylinder of radius 1, in cylindrical coordinates
+        let s = ctx.import(&(Tree::x() - 1.0).to_cylindrical());
+        assert_eq!(ctx.eval_xyz(s, 0.0, 0.0, 5.0).unwrap(), -1.0);
+        assert_eq!(ctx.eval_xyz(s, 0.0, 2.0, -3.0).unwrap(), 1.0);
+        assert_eq!(ctx.eval_xyz(s, -3.0, 4.0, 0.0).unwrap(), 4.0);
+
+        let (r, theta, z) = Tree::cylindrical();
+        let theta = ctx.import(&theta);
+        assert_eq!(ctx.eval_xyz(theta, 1.0, 0.0, 0.0).unwrap(), 0.0);
+        assert_eq!(ctx.eval_xyz(theta, 0.0, 1.0, 0.0).unwrap(), PI / 2.0);
+        assert_eq!(ctx.eval_xyz(theta, -1.0, 0.0, 0.0).unwrap(), PI);
+        assert_eq!(ctx.eval_xyz(theta, 0.0, -1.0, 0.0).unwrap(), -PI / 2.0);
+
+        let r = ctx.import(&r);
+        assert_eq!(ctx.eval_xyz(r, 0.0, 0.0, 1.0).unwrap(), 0.0);
+        let z = ctx.import(&z);
+        assert_eq!(ctx.eval_xyz(z, 1.0, 2.0, 3.0).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_spherical() {
+        use std::f64::consts::PI;
+        let mut ctx = Context::new();
+
+        // A sphere of radius 2, in spherical coordinates
+        let s = ctx.import(&(Tree::x() - 2.0).to_spherical());
+        assert_eq!(ctx.eval_xyz(s, 0.0, 0.0, 0.0).unwrap(), -2.0);
+        assert_eq!(ctx.eval_xyz(s, 0.0, 0.0, -2.0).unwrap(), 0.0);
+        assert_eq!(ctx.eval_xyz(s, 2.0, 3.0, 6.0).unwrap(), 5.0);
+
+        let (_r, theta, phi) = Tree::spherical();
+        let theta = ctx.import(&theta);
+        assert_eq!(ctx.eval_xyz(theta, 0.0, 1.0, 1.0).unwrap(), PI / 2.0);
+
+        let phi = ctx.import(&phi);
+        assert_eq!(ctx.eval_xyz(phi, 0.0, 0.0, 1.0).unwrap(), 0.0);
+        assert_eq!(ctx.eval_xyz(phi, 1.0, 0.0, 0.0).unwrap(), PI / 2.0);
+        assert_eq!(ctx.eval_xyz(phi, 0.0, 0.0, -1.0).unwrap(), PI);
+        assert_eq!(ctx.eval_xyz(phi, 0.0, 0.0, 0.0).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_rotate_twist() {
+        use crate::types::{Degrees, Length, Radians};
+        let mut ctx = Context::new();
+        let (x, y, z) = Tree::axes();
+        let sphere =
+            ((x - 1.0).square() + y.square() + z.square()).sqrt() - 0.25;
+
+        // Both angle units give the same result
+        for c in [
+            sphere.rotate_z(Degrees(90.0)),
+            sphere.rotate_z(Radians(std::f64::consts::FRAC_PI_2)),
+        ] {
+            let c = ctx.import(&c);
+            let v = ctx.eval_xyz(c, 0.0, 1.0, 0.0).unwrap();
+            assert!((v + 0.25).abs() < 1e-9);
+            assert!(ctx.eval_xyz(c, 1.0, 0.0, 0.0).unwrap() > 0.5);
+        }
+
+        // At z = 0 the twist does nothing; at z = 2 it's a half turn
+        let (x, y, _) = Tree::axes();
+        let bar = (x - 1.0).square() + y.square();
+        let t = ctx.import(&bar.twist_z(Degrees(90.0), Length(1.0)).unwrap());
+        assert!(ctx.eval_xyz(t, 1.0, 0.0, 0.0).unwrap().abs() < 1e-9);
+        assert!(ctx.eval_xyz(t, 0.0, 1.0, 1.0).unwrap().abs() < 1e-9);
+        assert!(ctx.eval_xyz(t, -1.0, 0.0, 2.0).unwrap().abs() < 1e-9);
+        assert!(ctx.eval_xyz(t, 1.0, 0.0, 2.0).unwrap() > 1.0);
+
+        // Degenerate lengths are rejected
+        for length in [0.0, -0.0, f64::INFINITY, f64::NAN] {
+            assert!(matches!(
+                bar.twist_z(Degrees(90.0), Length(length)),
+                Err(Error::InvalidLength)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_coordinate_intervals() {
+        use crate::{
+            eval::{EzShape, MathShape, Shape, TracingEvaluator},
+            types::Interval,
+            vm::VmShape,
+        };
+        let i = Interval::new(-1.0, 1.0);
+
+        // Intervals which straddle the axis must be non-negative and tight
+        for t in [
+            (Tree::x() - 1.0).to_cylindrical(),
+            (Tree::x() - 1.0).to_spherical(),
+        ] {
+            let mut ctx = Context::new();
+            let node = ctx.import(&t);
+            let shape = VmShape::new(&ctx, node).unwrap();
+            let tape = shape.ez_interval_tape();
+            let mut eval = VmShape::new_interval_eval();
+            let (out, _) = eval.eval(&tape, i, i, Interval::from(0.0)).unwrap();
+            assert_eq!(out.lower(), -1.0);
+            assert!(
+                out.upper() > 0.0 && out.upper() < 1.0,
+                "bad interval {out}"
+            );
+        }
+
+        // Because of atan2, the angular terms never produce NaN, even when
+        // evaluated over a region containing the origin.
+        let (_r, theta, phi) = Tree::spherical();
+        for t in [theta, phi] {
+            let mut ctx = Context::new();
+            let node = ctx.import(&t);
+            let shape = VmShape::new(&ctx, node).unwrap();
+            let tape = shape.ez_interval_tape();
+            let mut eval = VmShape::new_interval_eval();
+            let (out, _) = eval.eval(&tape, i, i, i).unwrap();
+            assert!(!out.has_nan());
+        }
+    }
+
     #[test]
     fn test_remap_xyz() {
         // Remapping X
@@ -45,6 +45,53 @@ pub trait BulkEvaluator: Default {
         z: &[f32],
     ) -> Result<&[Self::Data], Error>;
 
+    /// Evaluates many points, given one slice per variable slot
+    ///
+    /// Slices are given in slot order (see [`Tape::vars`]), which allows for
+    /// tapes with inputs other than `X`, `Y`, and `Z`.
+    ///
+    /// The default implementation only supports `X`, `Y`, `Z` tapes, returning
+    /// [`Error::BadVarSlice`] unless exactly three slices are given.
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[&[f32]],
+    ) -> Result<&[Self::Data], Error> {
+        match *vars {
+            [x, y, z] => self.eval(tape, x, y, z),
+            _ => Err(Error::BadVarSlice(vars.len(), 3)),
+        }
+    }
+
+    /// Evaluates many points from strided buffers, writing into a strided
+    /// output
+    ///
+    /// This lets callers evaluate directly from and into their own storage
+    /// (e.g. interleaved `xyz` positions, or one channel of an image) without
+    /// copying the data into separate arrays first.  The default
+    /// implementation gathers the inputs into temporary buffers and scatters
+    /// the result; evaluators may override it to read and write in place.
+    ///
+    /// Returns an error if the inputs and output are of different lengths.
+    fn eval_strided(
+        &mut self,
+        tape: &Self::Tape,
+        x: Strided<f32>,
+        y: Strided<f32>,
+        z: Strided<f32>,
+        mut out: StridedMut<Self::Data>,
+    ) -> Result<(), Error> {
+        self.check_strided_arguments(&x, &y, &z, &out)?;
+        let x = x.iter().cloned().collect::<Vec<f32>>();
+        let y = y.iter().cloned().collect::<Vec<f32>>();
+        let z = z.iter().cloned().collect::<Vec<f32>>();
+        let r = self.eval(tape, &x, &y, &z)?;
+        for (i, v) in r.iter().enumerate() {
+            out[i] = *v;
+        }
+        Ok(())
+    }
+
     /// Build a new empty evaluator
     fn new() -> Self {
         Self::default()
@@ -66,4 +113,191 @@ pub trait BulkEvaluator: Default {
             Ok(())
         }
     }
+
+    /// Helper function to return an error if strided inputs and output are of
+    /// different lengths
+    fn check_strided_arguments<T>(
+        &self,
+        xs: &Strided<f32>,
+        ys: &Strided<f32>,
+        zs: &Strided<f32>,
+        out: &StridedMut<T>,
+    ) -> Result<(), Error> {
+        if xs.len() != ys.len() || ys.len() != zs.len() || zs.len() != out.len()
+        {
+            Err(Error::MismatchedSlices)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A strided view of values in a buffer
+///
+/// Item `i` of the view is `data[offset + i * stride]`.
+///
+/// ```
+/// # use fidget::eval::Strided;
+/// // Interleaved XYZ positions
+/// let pos = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+/// let y = Strided::new(&pos, 1, 3, 2).unwrap();
+/// assert_eq!(y.iter().cloned().collect::<Vec<_>>(), [1.0, 4.0]);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Strided<'a, T> {
+    data: &'a [T],
+    offset: usize,
+    stride: usize,
+    len: usize,
+}
+
+/// Checks that a strided view with the given parameters fits in a buffer
+fn check_strided(
+    buf: usize,
+    offset: usize,
+    stride: usize,
+    len: usize,
+) -> Result<(), Error> {
+    let end = match len {
+        0 => Some(offset),
+        n => (n - 1)
+            .checked_mul(stride)
+            .and_then(|i| i.checked_add(offset))
+            .and_then(|i| i.checked_add(1)),
+    };
+    match end {
+        Some(end) if stride > 0 && end <= buf => Ok(()),
+        _ => Err(Error::BadStride),
+    }
+}
+
+impl<'a, T> Strided<'a, T> {
+    /// Builds a new strided view
+    ///
+    /// Returns [`Error::BadStride`] if the stride is zero or the view would
+    /// extend past the end of `data`.
+    pub fn new(
+        data: &'a [T],
+        offset: usize,
+        stride: usize,
+        len: usize,
+    ) -> Result<Self, Error> {
+        check_strided(data.len(), offset, stride, len)?;
+        Ok(Self {
+            data,
+            offset,
+            stride,
+            len,
+        })
+    }
+
+    /// Returns the number of items in the view
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether the view is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the view as a contiguous slice, if its stride is 1
+    pub fn as_slice(&self) -> Option<&'a [T]> {
+        (self.stride == 1).then(|| &self.data[self.offset..][..self.len])
+    }
+
+    /// Iterates over items in the view
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + 'a {
+        let data = self.data;
+        let (offset, stride) = (self.offset, self.stride);
+        (0..self.len).map(move |i| &data[offset + i * stride])
+    }
+}
+
+impl<'a, T> From<&'a [T]> for Strided<'a, T> {
+    fn from(data: &'a [T]) -> Self {
+        Self {
+            data,
+            offset: 0,
+            stride: 1,
+            len: data.len(),
+        }
+    }
+}
+
+impl<T> std::ops::Index<usize> for Strided<'_, T> {
+    type Output = T;
+    fn index(&self, i: usize) -> &T {
+        assert!(i < self.len, "index {i} out of range");
+        &self.data[self.offset + i * self.stride]
+    }
+}
+
+/// A mutable strided view of values in a buffer
+///
+/// This is the output equivalent of [`Strided`].
+#[derive(Debug)]
+pub struct StridedMut<'a, T> {
+    data: &'a mut [T],
+    offset: usize,
+    stride: usize,
+    len: usize,
+}
+
+impl<'a, T> StridedMut<'a, T> {
+    /// Builds a new mutable strided view
+    ///
+    /// Returns [`Error::BadStride`] if the stride is zero or the view would
+    /// extend past the end of `data`.
+    pub fn new(
+        data: &'a mut [T],
+        offset: usize,
+        stride: usize,
+        len: usize,
+    ) -> Result<Self, Error> {
+        check_strided(data.len(), offset, stride, len)?;
+        Ok(Self {
+            data,
+            offset,
+            stride,
+            len,
+        })
+    }
+
+    /// Returns the number of items in the view
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether the view is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'a, T> From<&'a mut [T]> for StridedMut<'a, T> {
+    fn from(data: &'a mut [T]) -> Self {
+        let len = data.len();
+        Self {
+            data,
+            offset: 0,
+            stride: 1,
+            len,
+        }
+    }
+}
+
+impl<T> std::ops::Index<usize> for StridedMut<'_, T> {
+    type Output = T;
+    fn index(&self, i: usize) -> &T {
+        assert!(i < self.len, "index {i} out of range");
+        &self.data[self.offset + i * self.stride]
+    }
+}
+
+impl<T> std::ops::IndexMut<usize> for StridedMut<'_, T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        assert!(i < self.len, "index {i} out of range");
+        &mut self.data[self.offset + i * self.stride]
+    }
 }
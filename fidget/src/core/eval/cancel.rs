@@ -0,0 +1,206 @@
+//! Evaluation which can be interrupted with a [`CancelToken`]
+//!
+//! A single call to [`BulkEvaluator::eval`] runs to completion, which can take
+//! a while for large slices.  [`CancellableEval`] splits the work into chunks
+//! and checks a token between them, so that (for example) a UI can abandon
+//! stale evaluations when its inputs change.
+use crate::{
+    eval::{BulkEvaluator, TracingEvaluator},
+    exec::CancelToken,
+    Error,
+};
+
+/// Wrapper around an evaluator which stops early if a token is cancelled
+///
+/// Evaluation also stops if it's run as part of an
+/// [`Executor`](crate::exec::Executor) job which is cancelled.  In either
+/// case, the evaluation function returns [`Error::Cancelled`], and the
+/// contents of the output slice are unspecified.
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::{CancellableEval, EzShape, MathShape, Shape},
+///     exec::CancelToken,
+///     vm::VmShape,
+/// };
+///
+/// let (x, y, _z) = Tree::axes();
+/// let shape = VmShape::from_tree(&(x + y));
+/// let tape = shape.ez_float_slice_tape();
+///
+/// let cancel = CancelToken::new();
+/// let mut eval = CancellableEval::<<VmShape as Shape>::FloatSliceEval>::new(
+///     cancel.clone(),
+/// );
+/// let mut out = [0.0; 2];
+/// eval.eval(&tape, &[1.0, 2.0], &[3.0, 4.0], &[0.0, 0.0], &mut out)?;
+/// assert_eq!(out, [4.0, 6.0]);
+///
+/// cancel.cancel();
+/// let r = eval.eval(&tape, &[1.0, 2.0], &[3.0, 4.0], &[0.0, 0.0], &mut out);
+/// assert!(matches!(r, Err(fidget::Error::Cancelled)));
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub struct CancellableEval<E> {
+    eval: E,
+    cancel: CancelToken,
+}
+
+impl<E: Default> CancellableEval<E> {
+    /// Number of points evaluated between checks of the token
+    pub const CHUNK_SIZE: usize = 4096;
+
+    /// Builds a new evaluator, which checks the given token
+    pub fn new(cancel: CancelToken) -> Self {
+        Self {
+            eval: E::default(),
+            cancel,
+        }
+    }
+
+    /// Returns the token checked by this evaluator
+    pub fn token(&self) -> &CancelToken {
+        &self.cancel
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self.cancel.is_cancelled() || crate::exec::is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<E: BulkEvaluator> CancellableEval<E> {
+    /// Evaluates many points, writing results into `out`
+    ///
+    /// Returns an error if the `x`, `y`, `z`, and `out` slices are of
+    /// different lengths, or [`Error::Cancelled`] if the token is cancelled
+    /// before evaluation finishes.
+    pub fn eval(
+        &mut self,
+        tape: &E::Tape,
+        x: &[f32],
+        y: &[f32],
+        z: &[f32],
+        out: &mut [E::Data],
+    ) -> Result<(), Error> {
+        if x.len() != y.len() || y.len() != z.len() || z.len() != out.len() {
+            return Err(Error::MismatchedSlices);
+        }
+        self.check()?;
+        for start in (0..x.len()).step_by(Self::CHUNK_SIZE) {
+            let end = (start + Self::CHUNK_SIZE).min(x.len());
+            let r = self.eval.eval(
+                tape,
+                &x[start..end],
+                &y[start..end],
+                &z[start..end],
+            )?;
+            out[start..end].copy_from_slice(r);
+            self.check()?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: TracingEvaluator> CancellableEval<E> {
+    /// Evaluates each of many positions (e.g. the cells of a subdivided
+    /// region, for interval evaluation), writing results into `out`
+    ///
+    /// Each position is given as `[x, y, z]`.  Returns an error if `positions`
+    /// and `out` are of different lengths, or [`Error::Cancelled`] if the token
+    /// is cancelled before evaluation finishes.
+    pub fn eval_each(
+        &mut self,
+        tape: &E::Tape,
+        positions: &[[E::Data; 3]],
+        out: &mut [E::Data],
+    ) -> Result<(), Error> {
+        if positions.len() != out.len() {
+            return Err(Error::MismatchedSlices);
+        }
+        self.check()?;
+        for (i, &[x, y, z]) in positions.iter().enumerate() {
+            out[i] = self.eval.eval(tape, x, y, z)?.0;
+            if i % Self::CHUNK_SIZE == Self::CHUNK_SIZE - 1 {
+                self.check()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::{EzShape, MathShape, Shape},
+        exec::{Executor, Priority},
+        types::Interval,
+        vm::VmShape,
+    };
+
+    #[test]
+    fn test_cancellable_eval() {
+        let (x, y, z) = Tree::axes();
+        let shape = VmShape::from_tree(&(x * y + z));
+        let n = 10_000;
+        let xs = (0..n).map(|i| i as f32 / 100.0).collect::<Vec<_>>();
+        let ys = vec![2.0; n];
+        let zs = vec![1.0; n];
+        let mut out = vec![0.0; n];
+
+        let cancel = CancelToken::new();
+        let mut eval =
+            CancellableEval::<<VmShape as Shape>::FloatSliceEval>::new(
+                cancel.clone(),
+            );
+        let tape = shape.ez_float_slice_tape();
+        eval.eval(&tape, &xs, &ys, &zs, &mut out).unwrap();
+        let mut single = VmShape::new_float_slice_eval();
+        assert_eq!(out, single.eval(&tape, &xs, &ys, &zs).unwrap());
+        assert!(matches!(
+            eval.eval(&tape, &xs, &ys, &zs, &mut out[1..]),
+            Err(Error::MismatchedSlices)
+        ));
+
+        let mut ieval =
+            CancellableEval::<<VmShape as Shape>::IntervalEval>::new(
+                cancel.clone(),
+            );
+        let itape = shape.ez_interval_tape();
+        let regions = (0..8)
+            .map(|i| {
+                let lo = i as f32;
+                [Interval::new(lo, lo + 1.0), 2.0.into(), 1.0.into()]
+            })
+            .collect::<Vec<_>>();
+        let mut iout = vec![Interval::from(0.0); regions.len()];
+        ieval.eval_each(&itape, &regions, &mut iout).unwrap();
+        assert_eq!(iout[3], Interval::new(7.0, 9.0));
+
+        // Jobs on a cancelled executor are also cancelled
+        let exec = Executor::new(1.try_into().unwrap());
+        let job = CancelToken::new();
+        job.cancel();
+        let r = exec.run(Priority::Normal, &job, || {
+            eval.eval(&tape, &xs, &ys, &zs, &mut out)
+        });
+        assert!(matches!(r, Err(Error::Cancelled)));
+
+        cancel.cancel();
+        assert!(eval.token().is_cancelled());
+        assert!(matches!(
+            eval.eval(&tape, &xs, &ys, &zs, &mut out),
+            Err(Error::Cancelled)
+        ));
+        assert!(matches!(
+            ieval.eval_each(&itape, &regions, &mut iout),
+            Err(Error::Cancelled)
+        ));
+    }
+}
@@ -0,0 +1,354 @@
+//! Differential testing between evaluator families
+//!
+//! [`compare_families`] builds the same expression with two [`Shape`]
+//! implementations, evaluates both over a sample region (with every evaluator
+//! flavor), and reports where they disagree.  This is useful when writing a
+//! new evaluator: an existing family (e.g. [`VmShape`](crate::vm::VmShape))
+//! acts as a reference, without needing hand-written expected values.
+use crate::{
+    context::{Context, Node},
+    eval::{BulkEvaluator, EzShape, MathShape, Shape, TracingEvaluator},
+    shape::Bounds,
+    types::{Grad, Interval},
+    Error,
+};
+
+/// Relative tolerance for values (scaled by `max(1, |v|)`)
+pub const VALUE_TOLERANCE: f32 = 1e-5;
+
+/// Relative tolerance for partial derivatives (scaled by `max(1, |v|)`)
+///
+/// Derivatives are built from several rounded partial results, so they're
+/// allowed to diverge further than values.
+pub const DERIV_TOLERANCE: f32 = 1e-4;
+
+/// Number of samples per axis for point, float slice, and gradient evaluation
+pub const POINT_SAMPLES: usize = 8;
+
+/// Number of subdivisions per axis for interval evaluation
+///
+/// The full region is also evaluated, in addition to each subdivision.
+pub const INTERVAL_SUBDIVISIONS: usize = 4;
+
+/// A single disagreement between two evaluator families
+#[derive(Clone, Debug, PartialEq)]
+pub enum Divergence {
+    /// Point evaluation at the given position
+    Point {
+        /// Position, as `[x, y, z]`
+        pos: [f32; 3],
+        /// Result from the first family
+        a: f32,
+        /// Result from the second family
+        b: f32,
+    },
+    /// Float slice evaluation at the given position
+    FloatSlice {
+        /// Position, as `[x, y, z]`
+        pos: [f32; 3],
+        /// Result from the first family
+        a: f32,
+        /// Result from the second family
+        b: f32,
+    },
+    /// Gradient slice evaluation at the given position
+    GradSlice {
+        /// Position, as `[x, y, z]`
+        pos: [f32; 3],
+        /// Result from the first family
+        a: Grad,
+        /// Result from the second family
+        b: Grad,
+    },
+    /// Interval evaluation over the given region
+    Interval {
+        /// Region, as `[x, y, z]`
+        region: [Interval; 3],
+        /// Result from the first family
+        a: Interval,
+        /// Result from the second family
+        b: Interval,
+    },
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Divergence::Point {
+                pos: [x, y, z],
+                a,
+                b,
+            } => {
+                write!(f, "point ({x}, {y}, {z}): {a} != {b}")
+            }
+            Divergence::FloatSlice {
+                pos: [x, y, z],
+                a,
+                b,
+            } => {
+                write!(f, "float slice ({x}, {y}, {z}): {a} != {b}")
+            }
+            Divergence::GradSlice {
+                pos: [x, y, z],
+                a,
+                b,
+            } => {
+                write!(f, "grad slice ({x}, {y}, {z}): {a} != {b}")
+            }
+            Divergence::Interval {
+                region: [x, y, z],
+                a,
+                b,
+            } => {
+                write!(f, "interval ({x}, {y}, {z}): {a} != {b}")
+            }
+        }
+    }
+}
+
+/// Results from [`compare_families`]
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Number of comparisons made, across every evaluator flavor
+    pub samples: usize,
+    /// Every comparison where the two families disagreed
+    pub divergences: Vec<Divergence>,
+}
+
+impl Report {
+    /// Checks whether the two families agreed everywhere
+    pub fn is_ok(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// Compares two evaluator families on the same expression
+///
+/// Both families build a shape from `node`, then evaluate it:
+/// - With point, float slice, and gradient slice evaluators on a grid of
+///   [`POINT_SAMPLES`] points per axis, spanning `region` (including its
+///   corners)
+/// - With interval evaluators on the full region and on each cell of an
+///   [`INTERVAL_SUBDIVISIONS`]-per-axis grid
+///
+/// Results match if they're within [`VALUE_TOLERANCE`] (or
+/// [`DERIV_TOLERANCE`] for partial derivatives), are infinities of the same
+/// sign, or are both `NaN`.  Interval results are compared by their bounds.
+///
+/// Returns an error if either family fails to build a shape or evaluate it;
+/// otherwise, disagreements are collected into the [`Report`].
+///
+/// ```
+/// use fidget::{
+///     context::Tree, eval::check::compare_families, shape::Bounds,
+///     vm::{GenericVmShape, VmShape},
+/// };
+///
+/// let (x, y, z) = Tree::axes();
+/// let mut ctx = fidget::Context::new();
+/// let node = ctx.import(&((x.square() + y.square() + z).sqrt() - 0.5));
+/// let report = compare_families::<VmShape, GenericVmShape<3>>(
+///     &ctx,
+///     node,
+///     Bounds::centered(1.0),
+/// )?;
+/// assert!(report.is_ok(), "{:?}", report.divergences);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub fn compare_families<A, B>(
+    ctx: &Context,
+    node: Node,
+    region: Bounds<3>,
+) -> Result<Report, Error>
+where
+    A: Shape + MathShape,
+    B: Shape + MathShape,
+{
+    region.check()?;
+    let a = A::new(ctx, node)?;
+    let b = B::new(ctx, node)?;
+    let mut out = Report::default();
+
+    let axes = [0, 1, 2].map(|i| {
+        Interval::new(
+            region.center[i] - region.size,
+            region.center[i] + region.size,
+        )
+    });
+    let [xs, ys, zs] = grid(axes, POINT_SAMPLES);
+
+    // Point evaluation
+    let (mut pa, mut pb) = (A::new_point_eval(), B::new_point_eval());
+    let (ta, tb) = (a.ez_point_tape(), b.ez_point_tape());
+    for i in 0..xs.len() {
+        let (x, y, z) = (xs[i], ys[i], zs[i]);
+        let (va, _) = pa.eval(&ta, x, y, z)?;
+        let (vb, _) = pb.eval(&tb, x, y, z)?;
+        out.samples += 1;
+        if !close(va, vb, VALUE_TOLERANCE) {
+            out.divergences.push(Divergence::Point {
+                pos: [x, y, z],
+                a: va,
+                b: vb,
+            });
+        }
+    }
+
+    // Float slice evaluation
+    let (mut fa, mut fb) =
+        (A::new_float_slice_eval(), B::new_float_slice_eval());
+    let ra = fa.eval(&a.ez_float_slice_tape(), &xs, &ys, &zs)?;
+    let rb = fb.eval(&b.ez_float_slice_tape(), &xs, &ys, &zs)?;
+    for (i, (&va, &vb)) in ra.iter().zip(rb).enumerate() {
+        out.samples += 1;
+        if !close(va, vb, VALUE_TOLERANCE) {
+            out.divergences.push(Divergence::FloatSlice {
+                pos: [xs[i], ys[i], zs[i]],
+                a: va,
+                b: vb,
+            });
+        }
+    }
+
+    // Gradient slice evaluation
+    let (mut ga, mut gb) = (A::new_grad_slice_eval(), B::new_grad_slice_eval());
+    let ra = ga.eval(&a.ez_grad_slice_tape(), &xs, &ys, &zs)?;
+    let rb = gb.eval(&b.ez_grad_slice_tape(), &xs, &ys, &zs)?;
+    for (i, (&va, &vb)) in ra.iter().zip(rb).enumerate() {
+        out.samples += 1;
+        let ok = close(va.v, vb.v, VALUE_TOLERANCE)
+            && (0..3).all(|j| close(va.d(j), vb.d(j), DERIV_TOLERANCE));
+        if !ok {
+            out.divergences.push(Divergence::GradSlice {
+                pos: [xs[i], ys[i], zs[i]],
+                a: va,
+                b: vb,
+            });
+        }
+    }
+
+    // Interval evaluation
+    let (mut ia, mut ib) = (A::new_interval_eval(), B::new_interval_eval());
+    let (ta, tb) = (a.ez_interval_tape(), b.ez_interval_tape());
+    for [x, y, z] in std::iter::once(axes).chain(cells(axes)) {
+        let (va, _) = ia.eval(&ta, x, y, z)?;
+        let (vb, _) = ib.eval(&tb, x, y, z)?;
+        out.samples += 1;
+        let ok = (va.has_nan() && vb.has_nan())
+            || (close(va.lower(), vb.lower(), VALUE_TOLERANCE)
+                && close(va.upper(), vb.upper(), VALUE_TOLERANCE));
+        if !ok {
+            out.divergences.push(Divergence::Interval {
+                region: [x, y, z],
+                a: va,
+                b: vb,
+            });
+        }
+    }
+
+    Ok(out)
+}
+
+/// Checks whether two values match, within a relative tolerance
+fn close(a: f32, b: f32, tolerance: f32) -> bool {
+    if a.is_nan() || b.is_nan() {
+        a.is_nan() && b.is_nan()
+    } else if a.is_infinite() || b.is_infinite() {
+        a == b
+    } else {
+        (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.0)
+    }
+}
+
+/// Returns `n` evenly spaced samples in the interval, including its endpoints
+fn samples(i: Interval, n: usize) -> impl Iterator<Item = f32> {
+    (0..n).map(move |j| i.lerp(j as f32 / (n - 1) as f32))
+}
+
+/// Returns a grid of `n` samples per axis, as `[xs, ys, zs]` slices
+fn grid(axes: [Interval; 3], n: usize) -> [Vec<f32>; 3] {
+    let mut out = [vec![], vec![], vec![]];
+    for x in samples(axes[0], n) {
+        for y in samples(axes[1], n) {
+            for z in samples(axes[2], n) {
+                out[0].push(x);
+                out[1].push(y);
+                out[2].push(z);
+            }
+        }
+    }
+    out
+}
+
+/// Returns every cell from subdividing the region along each axis
+fn cells(axes: [Interval; 3]) -> impl Iterator<Item = [Interval; 3]> {
+    let n = INTERVAL_SUBDIVISIONS;
+    let step = move |i: Interval, j: usize| {
+        Interval::new(
+            i.lerp(j as f32 / n as f32),
+            i.lerp((j + 1) as f32 / n as f32),
+        )
+    };
+    (0..n.pow(3)).map(move |j| {
+        [
+            step(axes[0], j % n),
+            step(axes[1], (j / n) % n),
+            step(axes[2], j / (n * n)),
+        ]
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Tree, vm::GenericVmShape, vm::VmShape};
+
+    #[test]
+    fn test_compare_families() {
+        let (x, y, z) = Tree::axes();
+        let t = ((x.square() + y.square() + z.square()).sqrt() - 0.5)
+            .min(x.clone().sin() * y.clone().cos())
+            .max(z.clone() / (x.clone() - 0.25));
+        let mut ctx = Context::new();
+        let node = ctx.import(&t);
+
+        let r = compare_families::<VmShape, GenericVmShape<3>>(
+            &ctx,
+            node,
+            Bounds::centered(1.0),
+        )
+        .unwrap();
+        assert!(r.is_ok(), "{:?}", r.divergences);
+        assert_eq!(
+            r.samples,
+            POINT_SAMPLES.pow(3) * 3 + INTERVAL_SUBDIVISIONS.pow(3) + 1
+        );
+
+        #[cfg(feature = "jit")]
+        {
+            let r = compare_families::<VmShape, crate::jit::JitShape>(
+                &ctx,
+                node,
+                Bounds::centered(1.0),
+            )
+            .unwrap();
+            assert!(r.is_ok(), "{:?}", r.divergences);
+        }
+
+        // Tolerances are relative for large values, and NaN matches NaN
+        assert!(close(1e6, 1e6 + 1.0, VALUE_TOLERANCE));
+        assert!(!close(0.0, 1e-3, VALUE_TOLERANCE));
+        assert!(close(f32::NAN, f32::NAN, VALUE_TOLERANCE));
+        assert!(!close(f32::NAN, 0.0, VALUE_TOLERANCE));
+        assert!(!close(f32::INFINITY, f32::NEG_INFINITY, VALUE_TOLERANCE));
+
+        assert!(matches!(
+            compare_families::<VmShape, VmShape>(
+                &ctx,
+                node,
+                Bounds::centered(0.0)
+            ),
+            Err(Error::InvalidBounds)
+        ));
+    }
+}
@@ -26,17 +26,34 @@ use crate::{
     Context, Error,
 };
 
+// The test suites are generic functions named after what they test, so
+// documenting each one individually would be noise.
 #[cfg(any(test, feature = "eval-tests"))]
+#[allow(missing_docs)]
 pub mod test;
 
+pub mod check;
+
 mod bulk;
+mod cancel;
+mod multi;
+mod pool;
+mod subdiv;
+mod threshold;
 mod tracing;
 mod transform;
+mod vars;
 
 // Re-export a few things
-pub use bulk::BulkEvaluator;
+pub use bulk::{BulkEvaluator, Strided, StridedMut};
+pub use cancel::CancellableEval;
+pub use multi::MultiTapeEval;
+pub use pool::{SimplifyEntry, SimplifyPool};
+pub use subdiv::{eval_interval_subdiv, eval_interval_subdiv_par};
+pub use threshold::SimplifyThreshold;
 pub use tracing::TracingEvaluator;
 pub use transform::TransformedShape;
+pub use vars::{VarSlot, Vars};
 
 /// A shape represents an implicit surface
 ///
@@ -102,6 +119,9 @@ pub trait Shape: Send + Sync + Clone {
     }
 
     /// Associated type for evaluating many gradients in one call
+    ///
+    /// Each result includes the value as well as its partial derivatives,
+    /// which are computed together in a single pass through the tape.
     type GradSliceEval: BulkEvaluator<Data = Grad, TapeStorage = Self::TapeStorage>
         + Send
         + Sync;
@@ -157,6 +177,16 @@ pub trait Shape: Send + Sync + Clone {
     /// shapes, it's typically the length of the tape,
     fn size(&self) -> usize;
 
+    /// Returns the number of choices (e.g. `min` and `max` nodes) in the shape
+    ///
+    /// Simplification can only remove branches which follow a choice, so
+    /// this is used to skip simplification when it wouldn't help (see
+    /// [`SimplifyThreshold`]).  By default, it returns `None`, indicating that
+    /// the count is unknown.
+    fn choice_count(&self) -> Option<usize> {
+        None
+    }
+
     /// Recommended tile sizes for 3D rendering
     fn tile_sizes_3d() -> &'static [usize];
 
@@ -173,6 +203,65 @@ pub trait Shape: Send + Sync + Clone {
         true
     }
 
+    /// Returns a shape specialized for 2D evaluation, with `Z` fixed
+    ///
+    /// The specialized shape treats `Z` as a constant with the given value, so
+    /// its tapes only read `X` and `Y` (and can be evaluated with
+    /// [`eval_vars`](TracingEvaluator::eval_vars) and two values); the `z`
+    /// argument to [`eval`](TracingEvaluator::eval) is ignored.
+    ///
+    /// This is used automatically by the 2D renderer.  By default, it returns
+    /// `None`, indicating that specialization isn't supported.
+    ///
+    /// It's also useful when slicing a 3D shape at a series of Z values, since
+    /// it's much cheaper than rebuilding the shape from a [`Context`]:
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::{EzShape, MathShape, Shape, TracingEvaluator},
+    ///     vm::VmShape,
+    /// };
+    ///
+    /// let (x, y, z) = Tree::axes();
+    /// let shape = VmShape::from_tree(&(x.square() + y.square() + z.square()));
+    /// let mut eval = VmShape::new_point_eval();
+    /// for z in [-0.5, 0.0, 0.5] {
+    ///     let slice = shape.plan_2d(z).unwrap();
+    ///     assert!(slice.size() <= shape.size());
+    ///     let (v, _) = eval.eval(&slice.ez_point_tape(), 0.5, 0.25, 0.0)?;
+    ///     let (w, _) = eval.eval(&shape.ez_point_tape(), 0.5, 0.25, z)?;
+    ///     assert_eq!(v, w);
+    /// }
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    #[doc(alias = "slice_z")]
+    fn plan_2d(&self, _z: f32) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Returns a shape with the given input frozen to a constant
+    ///
+    /// `var` names one of the shape's inputs (`X`, `Y`, and `Z` for most
+    /// shapes).  The specialized shape treats that input as a constant with the
+    /// given value, folding it through the rest of the expression; the input
+    /// keeps its slot, so the new shape is evaluated with the same arguments
+    /// (and ignores that input's value).
+    ///
+    /// This is cheaper than rebuilding the shape from a [`Context`], e.g. when
+    /// interactively changing a parameter.  By default, it returns `None`,
+    /// indicating that specialization isn't supported; shapes which support it
+    /// also return `None` if `var` isn't one of their inputs.
+    fn specialize(&self, _var: &str, _value: f32) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
     /// Associated type returned when applying a transform
     ///
     /// This is normally [`TransformedShape<Self>`](TransformedShape), but if
@@ -219,6 +308,38 @@ pub trait EzShape: Shape {
     fn ez_simplify(&self, trace: &Self::Trace) -> Result<Self, Error>
     where
         Self: Sized;
+
+    /// Returns the variable slots which affect this shape within a region
+    ///
+    /// The shape is evaluated over the given intervals, then simplified using
+    /// the resulting trace; slots which are not used by the simplified tape
+    /// cannot change the result anywhere within the region.  Use
+    /// [`Vars::dropped`] to compare against the unsimplified tape.
+    ///
+    /// ```
+    /// # use fidget::{context::Context, types::Interval, vm::VmShape};
+    /// # use fidget::eval::{EzShape, MathShape, Tape};
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let y = ctx.add(y, 10.0).unwrap();
+    /// let min = ctx.min(x, y).unwrap();
+    /// let shape = VmShape::new(&ctx, min).unwrap();
+    ///
+    /// let i = Interval::new(0.0, 1.0);
+    /// let vars = shape.ez_region_vars(i, i, i).unwrap();
+    /// let all = shape.ez_point_tape().vars();
+    /// let dropped: Vec<_> = all.dropped(&vars).map(|v| v.name).collect();
+    /// assert_eq!(dropped, ["Y"]);
+    /// ```
+    fn ez_region_vars(
+        &self,
+        x: Interval,
+        y: Interval,
+        z: Interval,
+    ) -> Result<Vars, Error>
+    where
+        Self: Sized;
 }
 
 impl<S: Shape> EzShape for S {
@@ -248,6 +369,21 @@ impl<S: Shape> EzShape for S {
         let mut workspace = Default::default();
         self.simplify(trace, Default::default(), &mut workspace)
     }
+
+    fn ez_region_vars(
+        &self,
+        x: Interval,
+        y: Interval,
+        z: Interval,
+    ) -> Result<Vars, Error> {
+        let tape = self.ez_interval_tape();
+        let mut eval = Self::new_interval_eval();
+        let (_, trace) = eval.eval(&tape, x, y, z)?;
+        Ok(match trace {
+            Some(trace) => self.ez_simplify(trace)?.ez_interval_tape().vars(),
+            None => tape.vars(),
+        })
+    }
 }
 
 /// A [`Shape`] which can be built from a math expression
@@ -266,20 +402,81 @@ pub trait MathShape {
         let node = ctx.import(t);
         Self::new(&ctx, node).unwrap()
     }
+
+    /// Builds shapes for many nodes in the same context, in parallel
+    ///
+    /// Tape generation only borrows the [`Context`], so independent roots
+    /// (e.g. every shape drawn by a script) can be imported into a single
+    /// context and then compiled concurrently.  Shapes are returned in the
+    /// same order as `nodes`; if any node fails, one of the errors is returned.
+    ///
+    /// On `wasm32`, shapes are built sequentially.
+    fn new_many(ctx: &Context, nodes: &[Node]) -> Result<Vec<Self>, Error>
+    where
+        Self: Sized + Send,
+    {
+        #[cfg(target_arch = "wasm32")]
+        let out = nodes.iter().map(|n| Self::new(ctx, *n)).collect();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let out = {
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            let threads = std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1);
+            let threads = crate::exec::threads(threads).min(nodes.len());
+            let next = AtomicUsize::new(0);
+            let mut out: Vec<_> = std::thread::scope(|s| {
+                // Don't hold a slot while waiting on the workers
+                crate::exec::idle();
+                let mut handles = vec![];
+                for _ in 0..threads {
+                    let job = crate::exec::current();
+                    let next = &next;
+                    handles.push(s.spawn(move || {
+                        let _guard = crate::exec::enter(job);
+                        let mut out = vec![];
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= nodes.len() {
+                                break;
+                            }
+                            let r = crate::exec::checkpoint()
+                                .and_then(|_| Self::new(ctx, nodes[i]));
+                            out.push((i, r));
+                        }
+                        out
+                    }));
+                }
+                handles
+                    .into_iter()
+                    .flat_map(|h| h.join().unwrap())
+                    .collect()
+            });
+            out.sort_by_key(|(i, _)| *i);
+            out.into_iter().map(|(_, r)| r).collect()
+        };
+        out
+    }
 }
 
 /// A tape represents something that can be evaluated by an evaluator
 ///
-/// The only property enforced on the trait is that we must have some way to
-/// recycle its internal storage.  This matters most for JIT evaluators, whose
-/// tapes are regions of executable memory-mapped RAM (which is expensive to map
-/// and unmap).
+/// The trait requires some way to recycle the tape's internal storage.  This
+/// matters most for JIT evaluators, whose tapes are regions of executable
+/// memory-mapped RAM (which is expensive to map and unmap).
+///
+/// Tapes also describe their variable slots, so that callers can bind
+/// arguments by name (see [`Vars`]).
 pub trait Tape {
     /// Associated type for this tape's data storage
     type Storage: Default;
 
     /// Retrieves the internal storage from this tape
     fn recycle(self) -> Self::Storage;
+
+    /// Returns an ordered description of this tape's variable slots
+    fn vars(&self) -> Vars;
 }
 
 /// Represents the trace captured by a tracing evaluation
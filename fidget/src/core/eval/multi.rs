@@ -0,0 +1,142 @@
+//! Evaluates several tapes at the same set of points
+//!
+//! Scenes are often built from many separate shapes, which are all evaluated
+//! at the same positions.  Doing so one shape at a time walks the full input
+//! arrays once per shape; [`MultiTapeEval`] instead splits the points into
+//! chunks, then evaluates every tape over each chunk, so that the inputs (and
+//! the evaluator's scratch data) stay in cache.
+use crate::{eval::BulkEvaluator, Error};
+use std::borrow::Borrow;
+
+/// Bulk evaluator which runs many tapes over the same points
+///
+/// Results are stored in tape-major order: the result for tape `t` at point
+/// `i` is at index `t * n + i`, where `n` is the number of points.
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::{EzShape, MathShape, MultiTapeEval, Shape},
+///     vm::VmShape,
+/// };
+///
+/// let (x, y, _z) = Tree::axes();
+/// let a = VmShape::from_tree(&(x.clone() + y.clone()));
+/// let b = VmShape::from_tree(&(x * y));
+/// let tapes = [a.ez_float_slice_tape(), b.ez_float_slice_tape()];
+///
+/// let mut eval = MultiTapeEval::<<VmShape as Shape>::FloatSliceEval>::new();
+/// let out = eval.eval(&tapes, &[1.0, 2.0], &[3.0, 4.0], &[0.0, 0.0])?;
+/// assert_eq!(out, [4.0, 6.0, 3.0, 8.0]);
+/// assert_eq!(eval.output(1), [3.0, 8.0]);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub struct MultiTapeEval<E: BulkEvaluator> {
+    eval: E,
+    out: Vec<E::Data>,
+    len: usize,
+}
+
+impl<E: BulkEvaluator> Default for MultiTapeEval<E> {
+    fn default() -> Self {
+        Self {
+            eval: E::default(),
+            out: vec![],
+            len: 0,
+        }
+    }
+}
+
+impl<E: BulkEvaluator> MultiTapeEval<E> {
+    /// Number of points evaluated for each tape before moving to the next
+    pub const CHUNK_SIZE: usize = 256;
+
+    /// Builds a new evaluator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluates every tape at each point
+    ///
+    /// Returns results in tape-major order (see [`MultiTapeEval`]), or an
+    /// error if the `x`, `y`, and `z` slices are of different lengths.
+    pub fn eval<T: Borrow<E::Tape>>(
+        &mut self,
+        tapes: &[T],
+        x: &[f32],
+        y: &[f32],
+        z: &[f32],
+    ) -> Result<&[E::Data], Error> {
+        if x.len() != y.len() || y.len() != z.len() {
+            return Err(Error::MismatchedSlices);
+        }
+        let n = x.len();
+        self.len = n;
+        self.out.resize(tapes.len() * n, f32::NAN.into());
+
+        for start in (0..n).step_by(Self::CHUNK_SIZE) {
+            let end = (start + Self::CHUNK_SIZE).min(n);
+            let (xs, ys, zs) = (&x[start..end], &y[start..end], &z[start..end]);
+            for (t, tape) in tapes.iter().enumerate() {
+                let r = self.eval.eval(tape.borrow(), xs, ys, zs)?;
+                self.out[t * n + start..t * n + end].copy_from_slice(r);
+            }
+        }
+        Ok(&self.out)
+    }
+
+    /// Returns the results for a single tape from the most recent evaluation
+    ///
+    /// # Panics
+    /// If `t` is not a valid tape index
+    pub fn output(&self, t: usize) -> &[E::Data] {
+        &self.out[t * self.len..(t + 1) * self.len]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::{EzShape, MathShape, Shape},
+        vm::VmShape,
+    };
+
+    #[test]
+    fn test_multi_tape_eval() {
+        let (x, y, z) = Tree::axes();
+        let shapes = [
+            VmShape::from_tree(&(x.clone() + y.clone())),
+            VmShape::from_tree(&(x.clone() * z.clone())),
+            VmShape::from_tree(&(y.max(z) - x)),
+        ];
+        let tapes = shapes
+            .iter()
+            .map(|s| s.ez_float_slice_tape())
+            .collect::<Vec<_>>();
+
+        // Use enough points to span multiple chunks
+        let n = 1000;
+        let xs = (0..n).map(|i| i as f32 / 10.0).collect::<Vec<_>>();
+        let ys = (0..n).map(|i| (i % 7) as f32).collect::<Vec<_>>();
+        let zs = (0..n).map(|i| -(i as f32) / 3.0).collect::<Vec<_>>();
+
+        let mut multi =
+            MultiTapeEval::<<VmShape as Shape>::FloatSliceEval>::new();
+        let out = multi.eval(&tapes, &xs, &ys, &zs).unwrap().to_vec();
+        assert_eq!(out.len(), n * shapes.len());
+
+        let mut single = VmShape::new_float_slice_eval();
+        for (t, tape) in tapes.iter().enumerate() {
+            let expected = single.eval(tape, &xs, &ys, &zs).unwrap();
+            assert_eq!(&out[t * n..(t + 1) * n], expected);
+            assert_eq!(multi.output(t), expected);
+        }
+
+        assert!(matches!(
+            multi.eval(&tapes, &xs, &ys[1..], &zs),
+            Err(Error::MismatchedSlices)
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+//! Shared pool of workspaces and storage for shape simplification
+//!
+//! Simplification needs a [`Shape::Workspace`] (scratch data used during the
+//! call) and a [`Shape::Storage`] (which becomes the new shape's tape, and is
+//! reclaimed with [`Shape::recycle`] when that shape is dropped).  Both are
+//! expensive to allocate from scratch, so worker threads should reuse them.
+//! [`SimplifyPool`] keeps them in lock-free free lists, so that any number of
+//! threads can share a single pool.
+use crate::{eval::Shape, Error};
+use crossbeam_deque::{Injector, Steal};
+
+/// A workspace and storage pair, handed out by a [`SimplifyPool`]
+pub struct SimplifyEntry<S: Shape> {
+    /// Workspace used during simplification
+    pub workspace: S::Workspace,
+    /// Storage which will be consumed by the simplified shape
+    pub storage: S::Storage,
+}
+
+/// Thread-safe pool of simplification workspaces and storage
+///
+/// Entries which aren't available in the pool are built with
+/// [`Default::default`], so the pool never blocks.
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::{EzShape, MathShape, Shape, SimplifyPool, TracingEvaluator},
+///     vm::VmShape,
+/// };
+///
+/// let (x, y, _z) = Tree::axes();
+/// let shape = VmShape::from_tree(&x.min(y));
+/// let pool = SimplifyPool::<VmShape>::new();
+///
+/// let mut eval = VmShape::new_point_eval();
+/// let (_, trace) = eval.eval(&shape.ez_point_tape(), 0.0, 1.0, 0.0)?;
+/// let next = pool.simplify(&shape, trace.unwrap())?;
+/// assert!(next.size() < shape.size());
+///
+/// // Once we're done with the simplified shape, its storage is returned
+/// pool.recycle(next);
+/// assert_eq!(pool.storage_len(), 1);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub struct SimplifyPool<S: Shape> {
+    workspaces: Injector<S::Workspace>,
+    storage: Injector<S::Storage>,
+}
+
+impl<S: Shape> Default for SimplifyPool<S> {
+    fn default() -> Self {
+        Self {
+            workspaces: Injector::new(),
+            storage: Injector::new(),
+        }
+    }
+}
+
+impl<S: Shape> SimplifyPool<S> {
+    /// Builds a new (empty) pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a workspace and storage from the pool
+    ///
+    /// Each is built with [`Default::default`] if the pool has none left.
+    pub fn take(&self) -> SimplifyEntry<S> {
+        SimplifyEntry {
+            workspace: pop(&self.workspaces),
+            storage: pop(&self.storage),
+        }
+    }
+
+    /// Returns a workspace and storage to the pool
+    pub fn give(&self, entry: SimplifyEntry<S>) {
+        self.workspaces.push(entry.workspace);
+        self.storage.push(entry.storage);
+    }
+
+    /// Simplifies a shape, using a workspace and storage from the pool
+    ///
+    /// The workspace is returned to the pool afterwards; the storage is
+    /// consumed by the new shape, and can be returned with
+    /// [`recycle`](Self::recycle).
+    pub fn simplify(&self, shape: &S, trace: &S::Trace) -> Result<S, Error> {
+        let SimplifyEntry {
+            mut workspace,
+            storage,
+        } = self.take();
+        let out = shape.simplify(trace, storage, &mut workspace);
+        self.workspaces.push(workspace);
+        out
+    }
+
+    /// Reclaims a shape's storage into the pool, if possible
+    ///
+    /// Returns `true` if storage was reclaimed; this may fail if the shape has
+    /// been cloned (see [`Shape::recycle`]).
+    pub fn recycle(&self, shape: S) -> bool {
+        match shape.recycle() {
+            Some(s) => {
+                self.storage.push(s);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the number of workspaces in the pool
+    pub fn workspace_len(&self) -> usize {
+        self.workspaces.len()
+    }
+
+    /// Returns the number of storage items in the pool
+    pub fn storage_len(&self) -> usize {
+        self.storage.len()
+    }
+}
+
+/// Pops an item from the queue, or builds a new one if it's empty
+fn pop<T: Default>(q: &Injector<T>) -> T {
+    loop {
+        match q.steal() {
+            Steal::Success(t) => return t,
+            Steal::Empty => return T::default(),
+            Steal::Retry => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::{EzShape, MathShape, TracingEvaluator},
+        vm::VmShape,
+    };
+
+    #[test]
+    fn test_simplify_pool() {
+        let (x, y, z) = Tree::axes();
+        let shape = VmShape::from_tree(&x.min(y).max(z));
+        let pool = SimplifyPool::<VmShape>::new();
+
+        let entry = pool.take();
+        pool.give(entry);
+        assert_eq!(pool.workspace_len(), 1);
+        assert_eq!(pool.storage_len(), 1);
+
+        // Threads share the pool, reusing its workspaces and storage
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let (pool, shape) = (&pool, &shape);
+                s.spawn(move || {
+                    let mut eval = VmShape::new_point_eval();
+                    let tape = shape.ez_point_tape();
+                    for i in 0..100 {
+                        let p = (t * 100 + i) as f32 / 100.0 - 2.0;
+                        let (v, trace) = eval.eval(&tape, p, -p, 0.5).unwrap();
+                        let next =
+                            pool.simplify(shape, trace.unwrap()).unwrap();
+                        assert!(next.size() < shape.size());
+                        let (w, _) = eval
+                            .eval(&next.ez_point_tape(), p, -p, 0.5)
+                            .unwrap();
+                        assert_eq!(v, w);
+                        assert!(pool.recycle(next));
+                    }
+                });
+            }
+        });
+        assert!(pool.workspace_len() <= 4);
+        assert!(pool.storage_len() <= 4);
+
+        // Shapes which are still shared can't be recycled
+        let next = shape.clone();
+        assert!(!pool.recycle(next));
+    }
+}
@@ -0,0 +1,224 @@
+//! Interval evaluation with subdivision, for tighter bounds
+//!
+//! Interval arithmetic overestimates a function's range, and the error grows
+//! with the width of the input intervals.  Splitting the region into smaller
+//! cells, evaluating each one, and taking the union of the results gives a
+//! tighter (but still conservative) bound, at the cost of more evaluations.
+use crate::{
+    eval::{EzShape, Shape, TracingEvaluator},
+    types::Interval,
+    Error,
+};
+
+/// Evaluates an interval bound, subdividing the region `subdiv` times
+///
+/// Each subdivision splits the widest axis in half, so the region is divided
+/// into `2^subdiv` cells; the result is the union of their bounds.  With
+/// `subdiv = 0`, this is equivalent to a single interval evaluation.
+///
+/// See [`eval_interval_subdiv_par`] for a parallel version.
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::{eval_interval_subdiv, MathShape},
+///     types::Interval,
+///     vm::VmShape,
+/// };
+///
+/// // x * x - x is not tightly bounded by interval arithmetic
+/// let x = Tree::x();
+/// let shape = VmShape::from_tree(&(x.square() - x));
+/// let r = Interval::new(0.0, 1.0);
+/// let coarse = eval_interval_subdiv(&shape, r, 0.0.into(), 0.0.into(), 0)?;
+/// let fine = eval_interval_subdiv(&shape, r, 0.0.into(), 0.0.into(), 6)?;
+/// assert_eq!(coarse, Interval::new(-1.0, 1.0));
+/// assert!(fine.width() < 0.5);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub fn eval_interval_subdiv<S: Shape>(
+    shape: &S,
+    x: Interval,
+    y: Interval,
+    z: Interval,
+    subdiv: usize,
+) -> Result<Interval, Error> {
+    let mut eval = S::new_interval_eval();
+    let tape = shape.ez_interval_tape();
+    let mut out = None;
+    for [x, y, z] in cells([x, y, z], subdiv) {
+        let (v, _) = eval.eval(&tape, x, y, z)?;
+        out = Some(union(out, v));
+    }
+    Ok(out.unwrap())
+}
+
+/// Evaluates an interval bound with subdivision, using multiple threads
+///
+/// The results are identical to [`eval_interval_subdiv`]; the `2^subdiv`
+/// cells are distributed across (up to) `threads` worker threads, each of
+/// which builds its own evaluator and tape and reuses them for every cell that
+/// it evaluates.
+///
+/// If this is run as part of an [`Executor`](crate::exec::Executor) job, the
+/// thread count is limited to the executor's slots, and this returns
+/// [`Error::Cancelled`] if the job is cancelled.
+///
+/// On `wasm32`, cells are evaluated on the current thread.
+pub fn eval_interval_subdiv_par<S: Shape>(
+    shape: &S,
+    x: Interval,
+    y: Interval,
+    z: Interval,
+    subdiv: usize,
+    threads: std::num::NonZeroUsize,
+) -> Result<Interval, Error> {
+    let cells = cells([x, y, z], subdiv).collect::<Vec<_>>();
+    let threads = crate::exec::threads(threads.get()).min(cells.len());
+
+    let out = if threads <= 1 || cfg!(target_arch = "wasm32") {
+        eval_cells(shape, &cells, &std::sync::atomic::AtomicUsize::new(0))
+    } else {
+        let next = std::sync::atomic::AtomicUsize::new(0);
+        std::thread::scope(|s| {
+            // Don't hold a slot while waiting on the workers
+            crate::exec::idle();
+            let mut handles = vec![];
+            for _ in 0..threads {
+                let job = crate::exec::current();
+                let (cells, next) = (&cells, &next);
+                handles.push(s.spawn(move || {
+                    let _guard = crate::exec::enter(job);
+                    eval_cells(shape, cells, next)
+                }));
+            }
+            handles.into_iter().map(|h| h.join().unwrap()).try_fold(
+                None,
+                |acc, r| {
+                    Ok::<_, Error>(match (acc, r?) {
+                        (acc, None) => acc,
+                        (acc, Some(v)) => Some(union(acc, v)),
+                    })
+                },
+            )
+        })
+    }?;
+    Ok(out.unwrap())
+}
+
+/// Number of cells claimed by a worker at a time
+const CHUNK_SIZE: usize = 64;
+
+/// Evaluates cells (claimed in chunks from `next`) until none are left
+///
+/// Returns the union of every evaluated cell, or `None` if no cells were
+/// claimed by this worker.
+fn eval_cells<S: Shape>(
+    shape: &S,
+    cells: &[[Interval; 3]],
+    next: &std::sync::atomic::AtomicUsize,
+) -> Result<Option<Interval>, Error> {
+    use std::sync::atomic::Ordering;
+    let mut eval = S::new_interval_eval();
+    let tape = shape.ez_interval_tape();
+    let mut out = None;
+    loop {
+        let start = next.fetch_add(CHUNK_SIZE, Ordering::Relaxed);
+        if start >= cells.len() {
+            break;
+        }
+        crate::exec::checkpoint()?;
+        let end = (start + CHUNK_SIZE).min(cells.len());
+        for &[x, y, z] in &cells[start..end] {
+            let (v, _) = eval.eval(&tape, x, y, z)?;
+            out = Some(union(out, v));
+        }
+    }
+    Ok(out)
+}
+
+/// Returns the `2^subdiv` cells from repeatedly splitting the widest axis
+fn cells(
+    region: [Interval; 3],
+    subdiv: usize,
+) -> impl Iterator<Item = [Interval; 3]> {
+    let mut out = vec![region];
+    for _ in 0..subdiv {
+        out = out
+            .into_iter()
+            .flat_map(|r| {
+                let axis = (0..3)
+                    .max_by(|a, b| r[*a].width().total_cmp(&r[*b].width()))
+                    .unwrap();
+                let (lo, hi) = r[axis].split();
+                let (mut a, mut b) = (r, r);
+                a[axis] = lo;
+                b[axis] = hi;
+                [a, b]
+            })
+            .collect();
+    }
+    out.into_iter()
+}
+
+/// Returns the smallest interval containing both inputs
+///
+/// If either input contains `NaN`, the result is `NaN`.
+fn union(a: Option<Interval>, b: Interval) -> Interval {
+    match a {
+        None => b,
+        Some(a) if a.has_nan() || b.has_nan() => f32::NAN.into(),
+        Some(a) => {
+            Interval::new(a.lower().min(b.lower()), a.upper().max(b.upper()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::MathShape,
+        exec::{CancelToken, Executor, Priority},
+        vm::VmShape,
+    };
+
+    #[test]
+    fn test_eval_interval_subdiv() {
+        let (x, y, z) = Tree::axes();
+        let t = (x.square() - x.clone()) * y.clone().sin() + z.clone() * y;
+        let shape = VmShape::from_tree(&t);
+        let r = [
+            Interval::new(-1.0, 2.0),
+            Interval::new(0.0, 3.0),
+            Interval::new(-0.5, 0.5),
+        ];
+
+        let threads = 4.try_into().unwrap();
+        let mut prev: Option<Interval> = None;
+        for subdiv in [0, 1, 4, 9] {
+            let a =
+                eval_interval_subdiv(&shape, r[0], r[1], r[2], subdiv).unwrap();
+            let b = eval_interval_subdiv_par(
+                &shape, r[0], r[1], r[2], subdiv, threads,
+            )
+            .unwrap();
+            assert_eq!(a, b);
+            // Subdivision only ever tightens the bounds
+            if let Some(p) = prev {
+                assert!(a.lower() >= p.lower() && a.upper() <= p.upper());
+            }
+            prev = Some(a);
+        }
+        assert_eq!(cells(r, 9).count(), 512);
+
+        let exec = Executor::new(2.try_into().unwrap());
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let out = exec.run(Priority::Normal, &cancel, || {
+            eval_interval_subdiv_par(&shape, r[0], r[1], r[2], 4, threads)
+        });
+        assert!(matches!(out, Err(Error::Cancelled)));
+    }
+}
@@ -0,0 +1,422 @@
+//! Golden conformance suite for complete expressions
+//!
+//! The other test suites check each opcode in isolation; this one evaluates a
+//! fixed corpus of complete expressions (see [`corpus`]) and compares every
+//! evaluation flavor against a double-precision reference
+//! ([`Context::eval`](crate::Context::eval) and
+//! [`Context::eval_grad`](crate::Context::eval_grad)), with a tolerance for
+//! each expression.  It also checks that traces and simplification behave:
+//! simplified shapes must produce the same values within the region where they
+//! were traced.
+//!
+//! A new backend can claim conformance by passing
+//! [`conformance_tests!`](crate::conformance_tests), which runs this suite
+//! along with every per-opcode suite.
+//!
+//! If the `eval-tests` feature is set, then this exposes a standard test suite
+//! for shapes; otherwise, the module has no public exports.
+use crate::{
+    context::{Context, Node, Tree},
+    eval::{BulkEvaluator, EzShape, MathShape, Shape, TracingEvaluator},
+    types::Interval,
+};
+use std::collections::BTreeMap;
+
+/// A single expression in the conformance corpus
+pub struct Golden {
+    /// Human-readable name, used in failure messages
+    pub name: &'static str,
+    /// Expression to evaluate
+    pub tree: Tree,
+    /// Relative tolerance for values (scaled by `max(1, |expected|)`)
+    pub tolerance: f32,
+}
+
+/// Returns the corpus of expressions used by the conformance suite
+///
+/// The corpus covers every opcode, spills registers (with a union of many
+/// spheres), and includes `min` / `max` / `and` / `or` clauses whose choices
+/// are exercised during interval evaluation.
+pub fn corpus() -> Vec<Golden> {
+    let (x, y, z) = Tree::axes();
+    let sphere = |cx: f64, cy: f64, cz: f64, r: f64| {
+        ((x.clone() - cx).square()
+            + (y.clone() - cy).square()
+            + (z.clone() - cz).square())
+        .sqrt()
+            - r
+    };
+    let boxed = (x.abs() - 0.4).max(y.abs() - 0.3).max(z.abs() - 0.2);
+    let gyroid = (x.clone() * 5.0).sin() * (y.clone() * 5.0).cos()
+        + (y.clone() * 5.0).sin() * (z.clone() * 5.0).cos()
+        + (z.clone() * 5.0).sin() * (x.clone() * 5.0).cos();
+    let many = (0..40).fold(Tree::constant(f64::INFINITY), |acc, i| {
+        let t = i as f64 / 40.0 * std::f64::consts::TAU;
+        acc.min(sphere(
+            t.cos() * 0.6,
+            t.sin() * 0.6,
+            (t * 3.0).sin() * 0.3,
+            0.1,
+        ))
+    });
+
+    vec![
+        Golden {
+            name: "sphere",
+            tree: sphere(0.0, 0.0, 0.0, 0.5),
+            tolerance: 1e-6,
+        },
+        Golden {
+            name: "box",
+            tree: boxed.clone(),
+            tolerance: 1e-6,
+        },
+        Golden {
+            name: "csg",
+            tree: boxed
+                .max(sphere(0.2, 0.1, 0.0, 0.3).neg())
+                .min(sphere(-0.5, 0.5, 0.25, 0.2)),
+            tolerance: 1e-6,
+        },
+        Golden {
+            name: "gyroid",
+            tree: gyroid,
+            tolerance: 1e-5,
+        },
+        Golden {
+            name: "rational",
+            tree: (x.clone() * y.clone() + 1.0)
+                / (z.square() + 0.25)
+                / (x.clone() - 3.0),
+            tolerance: 1e-5,
+        },
+        Golden {
+            name: "softmax",
+            tree: (x.exp() + (y.clone() * 2.0).exp()).ln() - z.clone(),
+            tolerance: 1e-5,
+        },
+        Golden {
+            name: "trig",
+            tree: (y.atan2(x.clone()) + (x.clone() * 0.9).asin()
+                - (z.clone() * 0.8).acos())
+                * (y.clone() * 0.5).tan()
+                + z.atan(),
+            tolerance: 1e-5,
+        },
+        Golden {
+            name: "rounding",
+            tree: (x.clone() * 4.0).floor() * 0.25 + y.modulo(0.5)
+                - (z.clone() * 3.0).ceil()
+                + (x.clone() * 2.5).round(),
+            tolerance: 1e-6,
+        },
+        Golden {
+            name: "logic",
+            tree: x
+                .compare(y.clone())
+                .and(z.clone() + 2.0)
+                .or(y.clone() * 3.0)
+                + (x.clone() - 0.25).not(),
+            tolerance: 1e-6,
+        },
+        Golden {
+            name: "recip",
+            tree: Tree::constant(1.0) / (x.square() + y.square() + 0.1)
+                - z.neg(),
+            tolerance: 1e-5,
+        },
+        Golden {
+            name: "many_spheres",
+            tree: many,
+            tolerance: 1e-6,
+        },
+    ]
+}
+
+/// Returns sample positions along one axis of `[lo, hi]`
+///
+/// Samples are offset from the grid so that they avoid discontinuities (e.g.
+/// in `floor`) and ties (e.g. in `min`) at round numbers.
+fn samples(lo: f32, hi: f32, n: usize) -> impl Iterator<Item = f32> {
+    (0..n).map(move |i| lo + (i as f32 + 0.37) / n as f32 * (hi - lo))
+}
+
+/// Returns sample positions in a 3D region, as separate X / Y / Z arrays
+fn grid(lo: f32, hi: f32, n: usize) -> [Vec<f32>; 3] {
+    let mut out = [vec![], vec![], vec![]];
+    for x in samples(lo, hi, n) {
+        for y in samples(lo, hi, n) {
+            for z in samples(lo, hi, n) {
+                out[0].push(x);
+                out[1].push(y);
+                out[2].push(z);
+            }
+        }
+    }
+    out
+}
+
+/// Evaluates the reference value and gradient with double precision
+fn reference(ctx: &Context, node: Node, x: f32, y: f32, z: f32) -> [f64; 4] {
+    let vars = BTreeMap::from([
+        ("X".to_owned(), x as f64),
+        ("Y".to_owned(), y as f64),
+        ("Z".to_owned(), z as f64),
+    ]);
+    let (v, grad) = ctx.eval_grad(node, &vars).unwrap();
+    let d = |k: &str| grad.get(k).cloned().unwrap_or(0.0);
+    [v, d("X"), d("Y"), d("Z")]
+}
+
+/// Checks whether `actual` matches `expected` within a relative tolerance
+fn close(actual: f32, expected: f64, tolerance: f32) -> bool {
+    let expected = expected as f32;
+    if expected.is_nan() || actual.is_nan() {
+        expected.is_nan() && actual.is_nan()
+    } else if expected.is_infinite() || actual.is_infinite() {
+        expected == actual
+    } else {
+        (actual - expected).abs() <= tolerance * expected.abs().max(1.0)
+    }
+}
+
+/// Helper struct to put constrains on our `Shape` object
+pub struct TestConformance<S>(std::marker::PhantomData<*const S>);
+
+impl<S> TestConformance<S>
+where
+    S: Shape + MathShape,
+{
+    pub fn test_golden_point() {
+        let mut eval = S::new_point_eval();
+        for g in corpus() {
+            let mut ctx = Context::new();
+            let node = ctx.import(&g.tree);
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_point_tape();
+            let [xs, ys, zs] = grid(-1.0, 1.0, 7);
+            for ((&x, &y), &z) in xs.iter().zip(&ys).zip(&zs) {
+                let expected = reference(&ctx, node, x, y, z)[0];
+                let (v, _) = eval.eval(&tape, x, y, z).unwrap();
+                assert!(
+                    close(v, expected, g.tolerance),
+                    "{}: point ({x}, {y}, {z}) gave {v}, expected {expected}",
+                    g.name,
+                );
+            }
+        }
+    }
+
+    pub fn test_golden_float_slice() {
+        let mut eval = S::new_float_slice_eval();
+        for g in corpus() {
+            let mut ctx = Context::new();
+            let node = ctx.import(&g.tree);
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_float_slice_tape();
+            let [xs, ys, zs] = grid(-1.0, 1.0, 7);
+            let out = eval.eval(&tape, &xs, &ys, &zs).unwrap();
+            for (i, &v) in out.iter().enumerate() {
+                let (x, y, z) = (xs[i], ys[i], zs[i]);
+                let expected = reference(&ctx, node, x, y, z)[0];
+                assert!(
+                    close(v, expected, g.tolerance),
+                    "{}: float slice ({x}, {y}, {z}) gave {v}, \
+                     expected {expected}",
+                    g.name,
+                );
+            }
+        }
+    }
+
+    pub fn test_golden_grad_slice() {
+        let mut eval = S::new_grad_slice_eval();
+        for g in corpus() {
+            let mut ctx = Context::new();
+            let node = ctx.import(&g.tree);
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_grad_slice_tape();
+            let [xs, ys, zs] = grid(-1.0, 1.0, 7);
+            let out = eval.eval(&tape, &xs, &ys, &zs).unwrap();
+            for (i, v) in out.iter().enumerate() {
+                let (x, y, z) = (xs[i], ys[i], zs[i]);
+                let expected = reference(&ctx, node, x, y, z);
+                // Derivatives are less precise than values, because they're
+                // built from several (rounded) partial results
+                let tolerances = [g.tolerance, g.tolerance * 10.0];
+                for (j, actual) in
+                    [v.v, v.dx, v.dy, v.dz].into_iter().enumerate()
+                {
+                    assert!(
+                        close(
+                            actual,
+                            expected[j],
+                            tolerances[(j > 0) as usize]
+                        ),
+                        "{}: grad slice ({x}, {y}, {z}) gave {v}, \
+                         expected {expected:?}",
+                        g.name,
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn test_golden_interval() {
+        let mut eval = S::new_interval_eval();
+        for g in corpus() {
+            let mut ctx = Context::new();
+            let node = ctx.import(&g.tree);
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_interval_tape();
+            for (x, y, z) in regions() {
+                let (i, _) = eval.eval(&tape, x, y, z).unwrap();
+                if i.has_nan() {
+                    // NaN is a valid (if unhelpful) conservative result
+                    continue;
+                }
+                for (px, py, pz) in region_samples(x, y, z) {
+                    let v = reference(&ctx, node, px, py, pz)[0] as f32;
+                    let slack = g.tolerance * v.abs().max(1.0);
+                    assert!(
+                        v.is_nan()
+                            || (v >= i.lower() - slack
+                                && v <= i.upper() + slack),
+                        "{}: interval ({x}, {y}, {z}) gave {i}, but \
+                         ({px}, {py}, {pz}) is {v}",
+                        g.name,
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn test_golden_simplify() {
+        let mut point = S::new_point_eval();
+        let mut interval = S::new_interval_eval();
+        let mut simplified = 0;
+        for g in corpus() {
+            let mut ctx = Context::new();
+            let node = ctx.import(&g.tree);
+            let shape = S::new(&ctx, node).unwrap();
+            let point_tape = shape.ez_point_tape();
+            let tape = shape.ez_interval_tape();
+            for (x, y, z) in regions() {
+                let (_, trace) = interval.eval(&tape, x, y, z).unwrap();
+                let Some(trace) = trace else {
+                    continue;
+                };
+                let next = shape.ez_simplify(trace).unwrap();
+                assert!(next.size() <= shape.size(), "{}", g.name);
+                simplified += (next.size() < shape.size()) as usize;
+
+                // Within the traced region, the simplified shape must match
+                let next_tape = next.ez_point_tape();
+                for (px, py, pz) in region_samples(x, y, z) {
+                    let (a, _) = point.eval(&point_tape, px, py, pz).unwrap();
+                    let (b, _) = point.eval(&next_tape, px, py, pz).unwrap();
+                    assert!(
+                        close(b, a as f64, g.tolerance),
+                        "{}: simplified over ({x}, {y}, {z}) gave {b} at \
+                         ({px}, {py}, {pz}), expected {a}",
+                        g.name,
+                    );
+                }
+            }
+        }
+        // Many regions of the corpus should allow for simplification
+        assert!(simplified > 50, "only {simplified} regions were simplified");
+    }
+
+    pub fn test_golden_choices() {
+        let (x, y, _z) = Tree::axes();
+        let cases = [
+            (x.min(y.clone() + 10.0), 0.0, true),
+            (x.max(y.clone() + 10.0), 10.0, false),
+            (x.min(y.clone() - 10.0), -10.0, false),
+            (x.max(y.clone() - 10.0), 0.0, true),
+            (x.and(y.clone()), 0.0, false),
+            (x.or(y.clone()), 0.0, true),
+        ];
+        let mut point = S::new_point_eval();
+        let mut interval = S::new_interval_eval();
+        for (tree, offset, picks_x) in cases {
+            let mut ctx = Context::new();
+            let node = ctx.import(&tree);
+            let shape = S::new(&ctx, node).unwrap();
+
+            // X is nonzero in [1, 2], and Y is in [0.5, 0.75]; each case is
+            // built so that one branch is always taken
+            let (i, trace) = interval
+                .eval(
+                    &shape.ez_interval_tape(),
+                    Interval::new(1.0, 2.0),
+                    Interval::new(0.5, 0.75),
+                    Interval::new(0.0, 0.0),
+                )
+                .unwrap();
+            let next = shape.ez_simplify(trace.unwrap()).unwrap();
+            assert!(next.size() < shape.size());
+            let expected = if picks_x {
+                Interval::new(1.0, 2.0)
+            } else {
+                Interval::new(0.5 + offset, 0.75 + offset)
+            };
+            assert_eq!(i, expected);
+
+            let (v, _) =
+                point.eval(&next.ez_point_tape(), 1.5, 0.6, 0.0).unwrap();
+            assert_eq!(v, if picks_x { 1.5 } else { 0.6 + offset });
+        }
+    }
+}
+
+/// Returns a set of regions which tile `[-1, +1]` on every axis
+fn regions() -> impl Iterator<Item = (Interval, Interval, Interval)> {
+    let step = |i: usize| {
+        let lo = -1.0 + i as f32 * 0.5;
+        Interval::new(lo, lo + 0.5)
+    };
+    (0..64).map(move |i| (step(i % 4), step((i / 4) % 4), step(i / 16)))
+}
+
+/// Returns sample points within a region
+fn region_samples(
+    x: Interval,
+    y: Interval,
+    z: Interval,
+) -> impl Iterator<Item = (f32, f32, f32)> {
+    samples(x.lower(), x.upper(), 3).flat_map(move |px| {
+        samples(y.lower(), y.upper(), 3).flat_map(move |py| {
+            samples(z.lower(), z.upper(), 3).map(move |pz| (px, py, pz))
+        })
+    })
+}
+
+#[macro_export]
+macro_rules! conformance_test {
+    ($i:ident, $t:ty) => {
+        #[test]
+        fn $i() {
+            $crate::eval::test::conformance::TestConformance::<$t>::$i()
+        }
+    };
+}
+
+/// Runs every evaluator test suite, plus the golden conformance suite
+#[macro_export]
+macro_rules! conformance_tests {
+    ($t:ty) => {
+        $crate::point_tests!($t);
+        $crate::interval_tests!($t);
+        $crate::float_slice_tests!($t);
+        $crate::grad_slice_tests!($t);
+
+        $crate::conformance_test!(test_golden_point, $t);
+        $crate::conformance_test!(test_golden_float_slice, $t);
+        $crate::conformance_test!(test_golden_grad_slice, $t);
+        $crate::conformance_test!(test_golden_interval, $t);
+        $crate::conformance_test!(test_golden_simplify, $t);
+        $crate::conformance_test!(test_golden_choices, $t);
+    };
+}
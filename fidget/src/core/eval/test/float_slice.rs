@@ -6,7 +6,8 @@
 use super::{build_stress_fn, test_args, CanonicalBinaryOp, CanonicalUnaryOp};
 use crate::{
     context::Context,
-    eval::{BulkEvaluator, EzShape, MathShape, Shape},
+    eval::{BulkEvaluator, EzShape, MathShape, Shape, Strided, StridedMut},
+    Error,
 };
 
 /// Helper struct to put constrains on our `Shape` object
@@ -120,6 +121,64 @@ where
         assert_eq!(out, [2.0, 8.0, 8.0, -2.0, -4.0, -6.0, 0.0]);
     }
 
+    pub fn test_strided() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let xy = ctx.mul(x, y).unwrap();
+        let out = ctx.sub(xy, z).unwrap();
+
+        let shape = S::new(&ctx, out).unwrap();
+        let mut eval = S::new_float_slice_eval();
+        let tape = shape.ez_float_slice_tape();
+
+        // Interleaved XYZ positions, writing into every other pixel
+        let pos = (0..15).map(|i| i as f32).collect::<Vec<f32>>();
+        let mut image = [-1.0; 10];
+        eval.eval_strided(
+            &tape,
+            Strided::new(&pos, 0, 3, 5).unwrap(),
+            Strided::new(&pos, 1, 3, 5).unwrap(),
+            Strided::new(&pos, 2, 3, 5).unwrap(),
+            StridedMut::new(&mut image, 1, 2, 5).unwrap(),
+        )
+        .unwrap();
+        let expected = pos.chunks(3).map(|p| p[0] * p[1] - p[2]);
+        for (i, e) in expected.enumerate() {
+            assert_eq!(image[i * 2], -1.0);
+            assert_eq!(image[i * 2 + 1], e);
+        }
+
+        // Contiguous views match normal evaluation
+        let xs = [1.0, 2.0, 3.0];
+        let ys = [4.0, 5.0, 6.0];
+        let zs = [7.0, 8.0, 9.0];
+        let mut out = [0.0; 3];
+        eval.eval_strided(
+            &tape,
+            xs[..].into(),
+            ys[..].into(),
+            zs[..].into(),
+            (&mut out[..]).into(),
+        )
+        .unwrap();
+        assert_eq!(out, eval.eval(&tape, &xs, &ys, &zs).unwrap());
+
+        assert!(matches!(Strided::new(&pos, 1, 3, 6), Err(Error::BadStride)));
+        assert!(matches!(Strided::new(&pos, 0, 0, 2), Err(Error::BadStride)));
+        assert!(matches!(
+            eval.eval_strided(
+                &tape,
+                xs[..].into(),
+                ys[..].into(),
+                zs[..2].into(),
+                (&mut out[..]).into(),
+            ),
+            Err(Error::MismatchedSlices)
+        ));
+    }
+
     pub fn test_f_sin() {
         let mut ctx = Context::new();
         let a = ctx.x();
@@ -397,6 +456,7 @@ macro_rules! float_slice_tests {
         $crate::float_slice_test!(test_give_take, $t);
         $crate::float_slice_test!(test_vectorized, $t);
         $crate::float_slice_test!(test_f_sin, $t);
+        $crate::float_slice_test!(test_strided, $t);
         $crate::float_slice_test!(test_f_stress, $t);
 
         mod f_unary {
@@ -5,7 +5,7 @@
 use super::{build_stress_fn, test_args, CanonicalBinaryOp, CanonicalUnaryOp};
 use crate::{
     context::Context,
-    eval::{BulkEvaluator, EzShape, MathShape, Shape},
+    eval::{BulkEvaluator, EzShape, MathShape, Shape, Strided, StridedMut},
     types::Grad,
 };
 
@@ -32,6 +32,32 @@ where
         );
     }
 
+    pub fn test_g_strided() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let out = ctx.mul(x, y).unwrap();
+        let shape = S::new(&ctx, out).unwrap();
+
+        let mut eval = S::new_grad_slice_eval();
+        let tape = shape.ez_grad_slice_tape();
+
+        // Interleaved XY positions (with Z fixed)
+        let pos = [1.0, 2.0, 3.0, 4.0];
+        let z = [0.0; 2];
+        let mut out = [Grad::from(0.0); 2];
+        eval.eval_strided(
+            &tape,
+            Strided::new(&pos, 0, 2, 2).unwrap(),
+            Strided::new(&pos, 1, 2, 2).unwrap(),
+            z[..].into(),
+            StridedMut::new(&mut out, 0, 1, 2).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(out[0], Grad::new(2.0, 2.0, 1.0, 0.0));
+        assert_eq!(out[1], Grad::new(12.0, 4.0, 3.0, 0.0));
+    }
+
     pub fn test_g_y() {
         let mut ctx = Context::new();
         let y = ctx.y();
@@ -673,6 +699,7 @@ macro_rules! grad_slice_tests {
     ($t:ty) => {
         $crate::grad_test!(test_g_circle, $t);
         $crate::grad_test!(test_g_x, $t);
+        $crate::grad_test!(test_g_strided, $t);
         $crate::grad_test!(test_g_y, $t);
         $crate::grad_test!(test_g_z, $t);
         $crate::grad_test!(test_g_abs, $t);
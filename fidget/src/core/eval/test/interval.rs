@@ -11,7 +11,7 @@ use crate::{
     context::Context,
     eval::{EzShape, MathShape, Shape, Tape, TracingEvaluator},
     types::Interval,
-    vm::Choice,
+    vm::{Choice, PackedChoices},
 };
 
 /// Helper struct to put constrains on our `Shape` object
@@ -20,7 +20,7 @@ pub struct TestInterval<S>(std::marker::PhantomData<*const S>);
 impl<S> TestInterval<S>
 where
     for<'a> S: Shape + MathShape,
-    <S as Shape>::Trace: AsRef<[Choice]>,
+    <S as Shape>::Trace: AsRef<PackedChoices>,
 {
     pub fn test_interval() {
         let mut ctx = Context::new();
@@ -157,7 +157,13 @@ where
         let tape = shape.ez_interval_tape();
 
         let mut eval = S::new_interval_eval();
-        assert_eq!(eval.eval_x(&tape, [0.0, 1.0]), [-1.0, 1.0].into());
+        eval.eval_x(&tape, [0.0, 1.0])
+            .compare_eq([0.0, 1f32.sin()].into());
+        eval.eval_x(&tape, [0.0, 2.0]).compare_eq([0.0, 1.0].into());
+        eval.eval_x(&tape, [-4.0, 0.0])
+            .compare_eq([-1.0, 4f32.sin().abs()].into());
+        eval.eval_x(&tape, [0.0, 7.0])
+            .compare_eq([-1.0, 1.0].into());
 
         let y = ctx.y();
         let y = ctx.mul(y, 2.0).unwrap();
@@ -166,7 +172,10 @@ where
         let shape = S::new(&ctx, s).unwrap();
         let tape = shape.ez_interval_tape();
 
-        assert_eq!(eval.eval_x(&tape, [0.0, 3.0]), [-1.0, 4.0].into());
+        eval.eval_x(&tape, [0.0, 3.0]).compare_eq([0.0, 3.0].into());
+        let (v, _) =
+            eval.eval(&tape, [0.0, 3.0], [0.0, 1.0], [0.0; 2]).unwrap();
+        v.compare_eq([0.0, 4.0].into());
     }
 
     pub fn test_i_neg() {
@@ -509,7 +518,7 @@ where
 
     pub fn test_i_and()
     where
-        <S as Shape>::Trace: AsRef<[Choice]>,
+        <S as Shape>::Trace: AsRef<PackedChoices>,
     {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -546,7 +555,7 @@ where
 
     pub fn test_i_or()
     where
-        <S as Shape>::Trace: AsRef<[Choice]>,
+        <S as Shape>::Trace: AsRef<PackedChoices>,
     {
         let mut ctx = Context::new();
         let x = ctx.x();
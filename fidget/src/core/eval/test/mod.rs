@@ -1,4 +1,5 @@
 //! Test suites for each evaluator type
+pub mod conformance;
 pub mod float_slice;
 pub mod grad_slice;
 pub mod interval;
@@ -6,7 +6,7 @@ use super::{build_stress_fn, test_args, CanonicalBinaryOp, CanonicalUnaryOp};
 use crate::{
     context::Context,
     eval::{EzShape, MathShape, Shape, TracingEvaluator},
-    vm::Choice,
+    vm::{Choice, PackedChoices},
 };
 
 /// Helper struct to put constrains on our `Shape` object
@@ -14,7 +14,7 @@ pub struct TestPoint<S>(std::marker::PhantomData<*const S>);
 impl<S> TestPoint<S>
 where
     S: Shape + MathShape,
-    <S as Shape>::Trace: AsRef<[Choice]>,
+    <S as Shape>::Trace: AsRef<PackedChoices>,
     <S as Shape>::Trace: From<Vec<Choice>>,
 {
     pub fn test_constant() {
@@ -63,7 +63,7 @@ where
 
     pub fn test_p_min()
     where
-        <S as Shape>::Trace: AsRef<[Choice]>,
+        <S as Shape>::Trace: AsRef<PackedChoices>,
     {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -96,7 +96,7 @@ where
 
     pub fn test_p_max()
     where
-        <S as Shape>::Trace: AsRef<[Choice]>,
+        <S as Shape>::Trace: AsRef<PackedChoices>,
     {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -130,7 +130,7 @@ where
 
     pub fn test_p_and()
     where
-        <S as Shape>::Trace: AsRef<[Choice]>,
+        <S as Shape>::Trace: AsRef<PackedChoices>,
     {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -167,7 +167,7 @@ where
 
     pub fn test_p_or()
     where
-        <S as Shape>::Trace: AsRef<[Choice]>,
+        <S as Shape>::Trace: AsRef<PackedChoices>,
     {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -204,7 +204,7 @@ where
 
     pub fn test_p_sin()
     where
-        <S as Shape>::Trace: AsRef<[Choice]>,
+        <S as Shape>::Trace: AsRef<PackedChoices>,
     {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -256,7 +256,7 @@ where
 
     pub fn test_push()
     where
-        <S as Shape>::Trace: AsRef<[Choice]>,
+        <S as Shape>::Trace: AsRef<PackedChoices>,
     {
         let mut ctx = Context::new();
         let x = ctx.x();
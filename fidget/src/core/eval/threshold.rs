@@ -0,0 +1,63 @@
+//! Thresholds for skipping simplification of small shapes
+use crate::eval::Shape;
+
+/// Thresholds below which callers skip [`Shape::simplify`]
+///
+/// Simplifying a shape has a fixed cost (tracing, building a new tape, and
+/// planning it for evaluation), but can only save time in proportion to the
+/// work that's removed.  For short tapes, or tapes with few choices, that
+/// cost is often larger than the savings.
+///
+/// This is consulted by the renderers (through
+/// [`RenderConfig::simplify_threshold`](crate::render::RenderConfig)) and the
+/// mesher (through
+/// [`Settings::simplify_threshold`](crate::mesh::Settings)); use
+/// [`should_simplify`](Self::should_simplify) to apply it elsewhere.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SimplifyThreshold {
+    /// Minimum number of choices in the shape
+    ///
+    /// Shapes which don't report a choice count (see [`Shape::choice_count`])
+    /// always pass this check.
+    pub min_choices_for_simplify: usize,
+
+    /// Minimum length of the shape (see [`Shape::size`])
+    pub min_tape_len_for_simplify: usize,
+}
+
+impl SimplifyThreshold {
+    /// Thresholds which always simplify, regardless of the shape
+    pub const ALWAYS: Self = Self {
+        min_choices_for_simplify: 0,
+        min_tape_len_for_simplify: 0,
+    };
+
+    /// Checks whether the given shape is worth simplifying
+    ///
+    /// ```
+    /// use fidget::{context::Tree, eval::{MathShape, SimplifyThreshold}, vm::VmShape};
+    ///
+    /// let (x, y, _z) = Tree::axes();
+    /// let t = SimplifyThreshold::default();
+    ///
+    /// // Shapes without choices can't be simplified
+    /// let shape = VmShape::from_tree(&(x.clone() + y.clone()));
+    /// assert!(!t.should_simplify(&shape));
+    /// assert!(SimplifyThreshold::ALWAYS.should_simplify(&shape));
+    /// ```
+    pub fn should_simplify<S: Shape>(&self, shape: &S) -> bool {
+        shape.size() >= self.min_tape_len_for_simplify
+            && shape
+                .choice_count()
+                .is_none_or(|c| c >= self.min_choices_for_simplify)
+    }
+}
+
+impl Default for SimplifyThreshold {
+    fn default() -> Self {
+        Self {
+            min_choices_for_simplify: 1,
+            min_tape_len_for_simplify: 0,
+        }
+    }
+}
@@ -41,6 +41,24 @@ pub trait TracingEvaluator: Default {
         z: F,
     ) -> Result<(Self::Data, Option<&Self::Trace>), Error>;
 
+    /// Evaluates the given tape with one value per variable slot
+    ///
+    /// Values are given in slot order (see [`Tape::vars`]), which allows for
+    /// tapes with inputs other than `X`, `Y`, and `Z`.
+    ///
+    /// The default implementation only supports `X`, `Y`, `Z` tapes, returning
+    /// [`Error::BadVarSlice`] unless exactly three values are given.
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[Self::Data],
+    ) -> Result<(Self::Data, Option<&Self::Trace>), Error> {
+        match *vars {
+            [x, y, z] => self.eval(tape, x, y, z),
+            _ => Err(Error::BadVarSlice(vars.len(), 3)),
+        }
+    }
+
     /// Build a new empty evaluator
     fn new() -> Self {
         Self::default()
@@ -55,6 +73,7 @@ pub trait TracingEvaluator: Default {
         }
     }
 
+    /// Helper function to evaluate with only an X value, for unit tests
     #[cfg(test)]
     fn eval_x<J: Into<Self::Data>>(
         &mut self,
@@ -65,6 +84,7 @@ pub trait TracingEvaluator: Default {
             .unwrap()
             .0
     }
+    /// Helper function to evaluate with X and Y values, for unit tests
     #[cfg(test)]
     fn eval_xy<J: Into<Self::Data>>(
         &mut self,
@@ -1,5 +1,5 @@
 use crate::{
-    eval::{BulkEvaluator, Interval, Shape, Tape, TracingEvaluator},
+    eval::{BulkEvaluator, Interval, Shape, Tape, TracingEvaluator, Vars},
     Error,
 };
 use nalgebra::{Matrix4, Point3, Vector3};
@@ -49,6 +49,19 @@ impl<T: Tape> Tape for TransformedTape<T> {
     fn recycle(self) -> Self::Storage {
         self.tape.recycle()
     }
+    fn vars(&self) -> Vars {
+        // Each inner axis reads every outer axis with a nonzero coefficient in
+        // its row (or in the projective row)
+        let inner = self.tape.vars();
+        let mut used = [false; 3];
+        for (j, u) in used.iter_mut().enumerate() {
+            *u = inner
+                .iter()
+                .any(|v| v.used && self.mat[(v.index, j)] != 0.0)
+                || (inner.iter().any(|v| v.used) && self.mat[(3, j)] != 0.0);
+        }
+        Vars::new(used)
+    }
 }
 
 /// A generic [`TracingEvaluator`] which applies a transform matrix
@@ -169,6 +182,9 @@ impl<S: Shape> Shape for TransformedShape<S> {
     fn size(&self) -> usize {
         self.shape.size()
     }
+    fn choice_count(&self) -> Option<usize> {
+        self.shape.choice_count()
+    }
     fn recycle(self) -> Option<Self::Storage> {
         self.shape.recycle()
     }
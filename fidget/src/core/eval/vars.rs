@@ -0,0 +1,384 @@
+//! Descriptions of the variable slots in a tape
+use crate::Error;
+use std::sync::Arc;
+
+/// Names of the variable slots, in index order
+const NAMES: [&str; 3] = ["X", "Y", "Z"];
+
+/// A single variable slot in a [`Tape`](super::Tape)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VarSlot<'a> {
+    /// Variable name
+    pub name: &'a str,
+    /// Index of the slot, i.e. its position in the evaluator's arguments
+    pub index: usize,
+    /// Whether the tape reads from this slot
+    ///
+    /// This may be `false` after simplification, e.g. if the only use of a
+    /// variable was in a branch of a `min` or `max` which was pruned.
+    pub used: bool,
+    /// Default value for the slot, if any
+    ///
+    /// See [`Vars::with_default`] and [`Vars::set`] for details.
+    pub default: Option<f32>,
+}
+
+/// Ordered description of the variable slots in a [`Tape`](super::Tape)
+///
+/// Evaluators take one argument per slot.  By default, the slots are `X`, `Y`,
+/// and `Z` (in that order); tapes built with an explicit list of inputs (e.g.
+/// [`VmShape::new_with_inputs`](crate::vm::GenericVmShape::new_with_inputs))
+/// have one slot per input instead.  Each slot records whether the tape
+/// actually reads it.
+///
+/// Only VM tapes support an explicit list of inputs; other backends (e.g. the
+/// JIT) always use `X`, `Y`, and `Z`, and their `eval_vars` functions return
+/// [`Error::BadVarSlice`] unless given exactly three values.
+///
+/// ```
+/// # use fidget::{context::Context, vm::VmShape};
+/// # use fidget::eval::{EzShape, MathShape, Shape, Tape, TracingEvaluator};
+/// let mut ctx = Context::new();
+/// let x = ctx.x();
+/// let y = ctx.y();
+/// let sum = ctx.add(x, y).unwrap();
+/// let shape = VmShape::new(&ctx, sum).unwrap();
+///
+/// let tape = shape.ez_point_tape();
+/// let vars = tape.vars();
+/// let used: Vec<_> =
+///     vars.iter().filter(|v| v.used).map(|v| v.name).collect();
+/// assert_eq!(used, ["X", "Y"]);
+///
+/// let args = vars.bind([("X", 1.0), ("Y", 2.0)]).unwrap();
+/// let mut eval = VmShape::new_point_eval();
+/// let (v, _) = eval.eval(&tape, args[0], args[1], args[2]).unwrap();
+/// assert_eq!(v, 3.0);
+///
+/// // Missing and unknown names are both reported
+/// assert!(vars.bind([("X", 1.0), ("W", 2.0)]).is_err());
+/// ```
+///
+/// Slots may also carry default values, which are used when a slot isn't
+/// bound by name.  Defaults can be updated by index with [`Vars::set`], which
+/// is cheaper than binding by name (e.g. when updating a value every frame):
+///
+/// ```
+/// # use fidget::{context::Context, vm::VmShape};
+/// # use fidget::eval::{EzShape, MathShape, Shape, Tape, TracingEvaluator};
+/// # let mut ctx = Context::new();
+/// # let x = ctx.x();
+/// # let y = ctx.y();
+/// # let sum = ctx.add(x, y).unwrap();
+/// # let shape = VmShape::new(&ctx, sum).unwrap();
+/// # let tape = shape.ez_point_tape();
+/// let mut vars = tape.vars().with_default("Y", 10.0).unwrap();
+/// let y = vars.get("Y").unwrap().index;
+///
+/// let mut eval = VmShape::new_point_eval();
+/// for t in [1.0, 2.0] {
+///     vars.set(y, t).unwrap();
+///     let args = vars.bind([("X", 1.0)]).unwrap();
+///     let (v, _) = eval.eval(&tape, args[0], args[1], args[2]).unwrap();
+///     assert_eq!(v, 1.0 + t);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vars {
+    /// Slot names, or `None` for `X`, `Y`, and `Z`
+    names: Option<Arc<[Arc<str>]>>,
+    used: Vec<bool>,
+    defaults: Vec<Option<f32>>,
+}
+
+impl Default for Vars {
+    fn default() -> Self {
+        Self::new([false; 3])
+    }
+}
+
+impl Vars {
+    /// Builds a new description, given which of `X`, `Y`, and `Z` are used
+    pub fn new(used: [bool; 3]) -> Self {
+        Self {
+            names: None,
+            used: used.to_vec(),
+            defaults: vec![None; 3],
+        }
+    }
+
+    /// Builds a new description with named slots
+    ///
+    /// # Panics
+    /// If `names` and `used` are of different lengths
+    pub(crate) fn with_names(names: Arc<[Arc<str>]>, used: Vec<bool>) -> Self {
+        assert_eq!(names.len(), used.len());
+        let defaults = vec![None; used.len()];
+        Self {
+            names: Some(names),
+            used,
+            defaults,
+        }
+    }
+
+    /// Returns the name of the slot at the given index
+    fn name(&self, index: usize) -> &str {
+        match &self.names {
+            Some(names) => &names[index],
+            None => NAMES[index],
+        }
+    }
+
+    /// Declares a default value for the named slot
+    ///
+    /// Returns [`Error::UnknownVariable`] if there is no slot with that name.
+    pub fn with_default(
+        mut self,
+        name: &str,
+        value: f32,
+    ) -> Result<Self, Error> {
+        let index = self
+            .get(name)
+            .ok_or_else(|| Error::UnknownVariable(name.to_owned()))?
+            .index;
+        self.defaults[index] = Some(value);
+        Ok(self)
+    }
+
+    /// Sets the default value of a slot by index
+    ///
+    /// Slot indices are stable (see [`VarSlot::index`]), so callers can look
+    /// up a slot once, then update its value without comparing names.
+    ///
+    /// Returns [`Error::BadVarIndex`] if the index is out of range.
+    #[inline]
+    pub fn set(&mut self, index: usize, value: f32) -> Result<(), Error> {
+        let n = self.defaults.len();
+        let d = self
+            .defaults
+            .get_mut(index)
+            .ok_or(Error::BadVarIndex(index, n))?;
+        *d = Some(value);
+        Ok(())
+    }
+
+    /// Returns the number of slots
+    ///
+    /// This includes slots which are not used by the tape.
+    pub fn len(&self) -> usize {
+        self.used.len()
+    }
+
+    /// Checks whether there are no slots
+    ///
+    /// This is only `true` for tapes built with an empty list of inputs.
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty()
+    }
+
+    /// Iterates over every slot, in index order
+    pub fn iter(&self) -> impl Iterator<Item = VarSlot<'_>> + '_ {
+        (0..self.len()).map(|index| VarSlot {
+            name: self.name(index),
+            index,
+            used: self.used[index],
+            default: self.defaults[index],
+        })
+    }
+
+    /// Looks up a slot by name
+    pub fn get(&self, name: &str) -> Option<VarSlot<'_>> {
+        self.iter().find(|v| v.name == name)
+    }
+
+    /// Iterates over slots which are used here but not in `simplified`
+    ///
+    /// This is typically called on the [`Vars`] of a shape's original tape,
+    /// passing the [`Vars`] of a simplified tape (e.g. from
+    /// [`EzShape::ez_region_vars`](super::EzShape::ez_region_vars)); the
+    /// resulting slots don't affect the result in the simplified region.
+    pub fn dropped<'a>(
+        &'a self,
+        simplified: &'a Vars,
+    ) -> impl Iterator<Item = VarSlot<'a>> + 'a {
+        self.iter()
+            .zip(simplified.iter())
+            .filter(|(a, b)| a.used && !b.used)
+            .map(|(a, _)| a)
+    }
+
+    /// Builds an argument array from a set of named values
+    ///
+    /// The result has one value per slot, in index order.  Every used slot must
+    /// be given a value, either here or as a default (which is overridden by
+    /// a value given here).  Unused slots without a default may be omitted, in
+    /// which case they're filled with zero.
+    ///
+    /// Returns [`Error::BadBindings`] listing every missing and unknown name
+    /// (in alphabetical order) if the bindings don't match the tape.
+    pub fn bind<'a, T, I>(&self, values: I) -> Result<Vec<T>, Error>
+    where
+        T: Copy + From<f32>,
+        I: IntoIterator<Item = (&'a str, T)>,
+    {
+        let mut out: Vec<Option<T>> =
+            self.defaults.iter().map(|d| d.map(T::from)).collect();
+        let mut extra = vec![];
+        for (name, v) in values {
+            match self.get(name) {
+                Some(slot) => out[slot.index] = Some(v),
+                None => extra.push(name.to_owned()),
+            }
+        }
+        let missing: Vec<String> = self
+            .iter()
+            .filter(|v| v.used && out[v.index].is_none())
+            .map(|v| v.name.to_owned())
+            .collect();
+        if missing.is_empty() && extra.is_empty() {
+            Ok(out.into_iter().map(|v| v.unwrap_or(T::from(0.0))).collect())
+        } else {
+            extra.sort();
+            extra.dedup();
+            Err(Error::BadBindings { missing, extra })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Context,
+        eval::{EzShape, MathShape, Shape, Tape, TracingEvaluator},
+        types::Interval,
+        vm::VmShape,
+    };
+
+    #[test]
+    fn test_bind() {
+        let vars = Vars::new([true, false, true]);
+        assert_eq!(
+            vars.bind([("Z", 3.0), ("X", 1.0)]).unwrap(),
+            [1.0, 0.0, 3.0]
+        );
+        assert_eq!(
+            vars.bind([("X", 1.0), ("Y", 2.0), ("Z", 3.0)]).unwrap(),
+            [1.0, 2.0, 3.0]
+        );
+
+        let err = vars.bind([("Y", 2.0), ("r", 1.0), ("a", 0.0)]);
+        let Err(Error::BadBindings { missing, extra }) = err else {
+            panic!("unexpected result {err:?}");
+        };
+        assert_eq!(missing, ["X", "Z"]);
+        assert_eq!(extra, ["a", "r"]);
+
+        let err = vars.bind::<f32, _>([]);
+        assert!(err.is_err());
+        assert!(Vars::default().bind::<f32, _>([]).is_ok());
+    }
+
+    #[test]
+    fn test_defaults() {
+        let vars = Vars::new([true, true, false])
+            .with_default("Y", 2.0)
+            .unwrap();
+        assert_eq!(vars.get("Y").unwrap().default, Some(2.0));
+        assert_eq!(vars.get("X").unwrap().default, None);
+
+        // Defaults fill in missing values, and are overridden by bindings
+        assert_eq!(vars.bind([("X", 1.0)]).unwrap(), [1.0, 2.0, 0.0]);
+        assert_eq!(
+            vars.bind([("X", 1.0), ("Y", 3.0)]).unwrap(),
+            [1.0, 3.0, 0.0]
+        );
+        let err = vars.bind::<f32, _>([]);
+        let Err(Error::BadBindings { missing, .. }) = err else {
+            panic!("unexpected result {err:?}");
+        };
+        assert_eq!(missing, ["X"]);
+
+        // Defaults are converted for non-`f32` bindings
+        let i = vars.bind([("X", Interval::new(0.0, 1.0))]).unwrap();
+        assert_eq!(i[1], Interval::from(2.0));
+
+        let mut vars = vars;
+        vars.set(0, 5.0).unwrap();
+        vars.set(2, 6.0).unwrap();
+        assert_eq!(vars.bind::<f32, _>([]).unwrap(), [5.0, 2.0, 6.0]);
+        assert!(matches!(vars.set(3, 1.0), Err(Error::BadVarIndex(3, 3))));
+        assert!(matches!(
+            vars.with_default("W", 1.0),
+            Err(Error::UnknownVariable(..))
+        ));
+    }
+
+    #[test]
+    fn test_tape_vars() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let min = ctx.min(x, y).unwrap();
+        let shape = VmShape::new(&ctx, min).unwrap();
+
+        let tape = shape.ez_point_tape();
+        let used = |vars: Vars| vars.iter().map(|v| v.used).collect::<Vec<_>>();
+        assert_eq!(used(tape.vars()), [true, true, false]);
+
+        // After simplification, only one branch remains
+        let mut eval = VmShape::new_point_eval();
+        let (v, trace) = eval.eval(&tape, 1.0, 2.0, 0.0).unwrap();
+        assert_eq!(v, 1.0);
+        let next = shape.ez_simplify(trace.unwrap()).unwrap();
+        let tape = next.ez_point_tape();
+        assert_eq!(used(tape.vars()), [true, false, false]);
+        assert_eq!(tape.vars().bind([("X", 4.0)]).unwrap(), [4.0, 0.0, 0.0]);
+
+        // Transforms can mix axes together
+        let mut mat = nalgebra::Matrix4::identity();
+        mat.swap_rows(0, 2);
+        let shape = next.clone().apply_transform(mat);
+        let tape = shape.ez_point_tape();
+        assert_eq!(used(tape.vars()), [false, false, true]);
+
+        let mat = nalgebra::Matrix4::new_rotation(nalgebra::Vector3::new(
+            0.0, 0.0, 1.0,
+        ));
+        let shape = next.apply_transform(mat);
+        let tape = shape.ez_point_tape();
+        assert_eq!(used(tape.vars()), [true, true, false]);
+    }
+
+    #[test]
+    fn test_region_vars() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let y10 = ctx.add(y, 10.0).unwrap();
+        let min = ctx.min(x, y10).unwrap();
+        let out = ctx.mul(min, z).unwrap();
+        let shape = VmShape::new(&ctx, out).unwrap();
+        let all = shape.ez_point_tape().vars();
+
+        let i = |a, b| Interval::new(a, b);
+        let vars = shape
+            .ez_region_vars(i(0.0, 1.0), i(0.0, 1.0), i(0.0, 1.0))
+            .unwrap();
+        let dropped: Vec<_> = all.dropped(&vars).map(|v| v.name).collect();
+        assert_eq!(dropped, ["Y"]);
+
+        let vars = shape
+            .ez_region_vars(i(20.0, 21.0), i(0.0, 1.0), i(0.0, 1.0))
+            .unwrap();
+        let dropped: Vec<_> = all.dropped(&vars).map(|v| v.name).collect();
+        assert_eq!(dropped, ["X"]);
+
+        let vars = shape
+            .ez_region_vars(i(0.0, 20.0), i(0.0, 1.0), i(0.0, 1.0))
+            .unwrap();
+        assert_eq!(vars, all);
+        assert_eq!(all.dropped(&vars).count(), 0);
+    }
+}
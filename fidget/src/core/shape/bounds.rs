@@ -1,3 +1,8 @@
+use crate::{
+    eval::{Shape, TracingEvaluator},
+    types::Interval,
+    Error,
+};
 use nalgebra::{
     allocator::Allocator, Const, DefaultAllocator, DimNameAdd, DimNameSub,
     DimNameSum, OVector, Transform, U1,
@@ -7,6 +12,19 @@ use nalgebra::{
 ///
 /// Right now, all spatial operations take place in a cubical region, so we
 /// specify bounds as a center point and region size.
+///
+/// Rendering and meshing only ever evaluate a shape within its bounds;
+/// anything outside of the region is clipped.  This matters for unbounded
+/// shapes (e.g. half-spaces or periodic lattices), which are always clipped;
+/// use [`Bounds::clips`] to check whether a shape extends past the region.
+///
+/// Bounds must have a finite center and a positive, finite size.  Functions
+/// which take bounds will return [`Error::InvalidBounds`] otherwise; this can
+/// be checked ahead of time with [`Bounds::check`].
+///
+/// There's deliberately no default region: render and meshing configurations
+/// store their bounds as an `Option`, and return [`Error::MissingBounds`] if
+/// they aren't specified.
 #[derive(Copy, Clone, Debug)]
 pub struct Bounds<const N: usize> {
     /// Center of the bounds
@@ -19,11 +37,119 @@ pub struct Bounds<const N: usize> {
     pub size: f32,
 }
 
-impl<const N: usize> Default for Bounds<N> {
-    /// By default, the bounds are the `[-1, +1]` region
-    fn default() -> Self {
+impl<const N: usize> Bounds<N> {
+    /// Builds a new bounded region from a center point and size
+    pub fn new(center: OVector<f32, Const<N>>, size: f32) -> Self {
+        Self { center, size }
+    }
+
+    /// Builds a region centered on the origin, spanning `[-size, +size]`
+    pub fn centered(size: f32) -> Self {
         let center = OVector::<f32, Const<N>>::zeros();
-        Self { center, size: 1.0 }
+        Self { center, size }
+    }
+
+    /// Checks that the bounds are valid
+    ///
+    /// Valid bounds have a finite center and a positive, finite size.
+    ///
+    /// ```
+    /// # use fidget::shape::Bounds;
+    /// # use nalgebra::Vector2;
+    /// assert!(Bounds::<2>::centered(1.0).check().is_ok());
+    /// assert!(Bounds::new(Vector2::zeros(), 0.0).check().is_err());
+    /// assert!(Bounds::new(Vector2::new(f32::NAN, 0.0), 1.0).check().is_err());
+    /// ```
+    pub fn check(&self) -> Result<(), Error> {
+        if self.size.is_finite()
+            && self.size > 0.0
+            && self.center.iter().all(|c| c.is_finite())
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidBounds)
+        }
+    }
+
+    /// Returns the lower corner of the bounds
+    pub fn lower(&self) -> OVector<f32, Const<N>> {
+        self.center.add_scalar(-self.size)
+    }
+
+    /// Returns the upper corner of the bounds
+    pub fn upper(&self) -> OVector<f32, Const<N>> {
+        self.center.add_scalar(self.size)
+    }
+
+    /// Checks whether the given point is within the bounds (inclusive)
+    pub fn contains(&self, p: &OVector<f32, Const<N>>) -> bool {
+        p.iter()
+            .zip(self.center.iter())
+            .all(|(p, c)| (p - c).abs() <= self.size)
+    }
+
+    /// Returns the bounds as an interval on each axis
+    pub fn intervals(&self) -> [Interval; N] {
+        std::array::from_fn(|i| {
+            Interval::new(
+                self.center[i] - self.size,
+                self.center[i] + self.size,
+            )
+        })
+    }
+
+    /// Returns intervals for each face of the bounds, padded to 3D
+    ///
+    /// Axes beyond `N` are set to zero.
+    fn faces(&self) -> impl Iterator<Item = [Interval; 3]> + '_ {
+        assert!(N <= 3, "faces are only defined for 2D and 3D bounds");
+        let full = self.intervals();
+        (0..N).flat_map(move |axis| {
+            [full[axis].lower(), full[axis].upper()].map(move |v| {
+                std::array::from_fn(|i| match i {
+                    i if i == axis => Interval::from(v),
+                    i if i < N => full[i],
+                    _ => Interval::from(0.0),
+                })
+            })
+        })
+    }
+
+    /// Checks whether the given shape may extend past the bounds
+    ///
+    /// This evaluates the shape with interval arithmetic on each face of the
+    /// bounds.  If any face may be inside the shape (or on its surface), then
+    /// the shape is clipped by the bounds and this function returns `true`.
+    /// This is conservative: because interval arithmetic is conservative, it
+    /// may return `true` for shapes that are not actually clipped.
+    ///
+    /// For 2D bounds, the shape is evaluated at `z = 0`.
+    ///
+    /// # Panics
+    /// If `N > 3`
+    ///
+    /// ```
+    /// # use fidget::{context::Tree, eval::MathShape, shape::Bounds,
+    /// #              vm::VmShape};
+    /// let (x, y) = (Tree::x(), Tree::y());
+    /// let circle = (x.square() + y.square()).sqrt() - 0.5;
+    /// let shape = VmShape::from_tree(&circle);
+    /// assert!(!Bounds::<2>::centered(1.0).clips(&shape).unwrap());
+    ///
+    /// let half_space = VmShape::from_tree(&Tree::x());
+    /// assert!(Bounds::<2>::centered(1.0).clips(&half_space).unwrap());
+    /// ```
+    pub fn clips<S: Shape>(&self, shape: &S) -> Result<bool, Error> {
+        self.check()?;
+        let tape = shape.interval_tape(Default::default());
+        let mut eval = S::new_interval_eval();
+        for [x, y, z] in self.faces() {
+            let (i, _) = eval.eval(&tape, x, y, z)?;
+            if i.lower() <= 0.0 || i.lower().is_nan() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
     }
 }
 
@@ -56,8 +182,53 @@ mod test {
     use nalgebra::{Point2, Vector2};
 
     #[test]
-    fn bounds_default() {
-        let b = Bounds::default();
+    fn bounds_check() {
+        assert!(Bounds::<3>::centered(1.0).check().is_ok());
+        for size in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(Bounds::new(Vector2::zeros(), size).check().is_err());
+        }
+        let b = Bounds::new(Vector2::new(1.0, f32::INFINITY), 1.0);
+        assert!(b.check().is_err());
+    }
+
+    #[test]
+    fn bounds_corners() {
+        let b = Bounds::new(Vector2::new(1.0, 2.0), 0.5);
+        assert_eq!(b.lower(), Vector2::new(0.5, 1.5));
+        assert_eq!(b.upper(), Vector2::new(1.5, 2.5));
+        assert!(b.contains(&Vector2::new(1.25, 2.5)));
+        assert!(!b.contains(&Vector2::new(1.25, 2.6)));
+        let [x, y] = b.intervals();
+        assert_eq!((x.lower(), x.upper()), (0.5, 1.5));
+        assert_eq!((y.lower(), y.upper()), (1.5, 2.5));
+    }
+
+    #[test]
+    fn bounds_clips() {
+        use crate::{context::Tree, eval::MathShape, vm::VmShape};
+        let (x, y, z) = (Tree::x(), Tree::y(), Tree::z());
+        let sphere = VmShape::from_tree(
+            &((x.square() + y.square() + z.square()).sqrt() - 0.5),
+        );
+        let b = Bounds::<3>::centered(1.0);
+        assert!(!b.clips(&sphere).unwrap());
+
+        let b = Bounds::new(nalgebra::Vector3::new(0.5, 0.0, 0.0), 1.0);
+        assert!(b.clips(&sphere).unwrap());
+
+        let b = Bounds::new(nalgebra::Vector3::zeros(), 0.25);
+        assert!(b.clips(&sphere).unwrap());
+
+        let b = Bounds::new(nalgebra::Vector3::zeros(), 0.0);
+        assert!(b.clips(&sphere).is_err());
+
+        let lattice = VmShape::from_tree(&(x.modulo(1.0) - 0.5));
+        assert!(Bounds::<2>::centered(1.0).clips(&lattice).unwrap());
+    }
+
+    #[test]
+    fn bounds_unit() {
+        let b = Bounds::centered(1.0);
         let t = b.transform();
         assert_eq!(
             t.transform_point(&Point2::new(-1.0, -1.0)),
@@ -0,0 +1,263 @@
+use crate::vm::Choice;
+
+/// A value with `K` tangents, for forward-mode differentiation
+///
+/// Unlike [`Grad`](super::Grad), the tangents aren't tied to the `x`, `y`, and
+/// `z` axes: each input is seeded with caller-supplied directions, and each
+/// tangent in the result is the directional derivative along the matching
+/// seed.  For example, seeding the inputs with a ray direction gives the
+/// derivative along that ray in a single pass.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Dual<const K: usize> {
+    /// Value of the function
+    pub v: f32,
+    /// Tangents (directional derivatives) of the function
+    pub d: [f32; K],
+}
+
+impl<const K: usize> Default for Dual<K> {
+    fn default() -> Self {
+        Self::from(0.0)
+    }
+}
+
+impl<const K: usize> Dual<K> {
+    /// Constructs a new value with the given tangents
+    pub fn new(v: f32, d: [f32; K]) -> Self {
+        Self { v, d }
+    }
+
+    /// Chain rule, given the derivative of the outer function
+    fn chain(self, v: f32, d: f32) -> Self {
+        Self {
+            v,
+            d: self.d.map(|t| t * d),
+        }
+    }
+
+    /// Absolute value
+    pub fn abs(self) -> Self {
+        if self.v < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    /// Square
+    pub fn square(self) -> Self {
+        self.chain(self.v * self.v, 2.0 * self.v)
+    }
+
+    /// Square root
+    pub fn sqrt(self) -> Self {
+        let v = self.v.sqrt();
+        self.chain(v, 0.5 / v)
+    }
+
+    /// Reciprocal
+    pub fn recip(self) -> Self {
+        self.chain(1.0 / self.v, -1.0 / (self.v * self.v))
+    }
+
+    /// Sine
+    pub fn sin(self) -> Self {
+        self.chain(self.v.sin(), self.v.cos())
+    }
+
+    /// Cosine
+    pub fn cos(self) -> Self {
+        self.chain(self.v.cos(), -self.v.sin())
+    }
+
+    /// Tangent
+    pub fn tan(self) -> Self {
+        self.chain(self.v.tan(), 1.0 / self.v.cos().powi(2))
+    }
+
+    /// Arcsin
+    pub fn asin(self) -> Self {
+        self.chain(self.v.asin(), 1.0 / (1.0 - self.v.powi(2)).sqrt())
+    }
+
+    /// Arccos
+    pub fn acos(self) -> Self {
+        self.chain(self.v.acos(), -1.0 / (1.0 - self.v.powi(2)).sqrt())
+    }
+
+    /// Arctangent
+    pub fn atan(self) -> Self {
+        self.chain(self.v.atan(), 1.0 / (self.v.powi(2) + 1.0))
+    }
+
+    /// Exponential function
+    pub fn exp(self) -> Self {
+        let v = self.v.exp();
+        self.chain(v, v)
+    }
+
+    /// Natural log
+    pub fn ln(self) -> Self {
+        self.chain(self.v.ln(), 1.0 / self.v)
+    }
+
+    /// Largest value that is less-than-or-equal to this value
+    pub fn floor(self) -> Self {
+        self.v.floor().into()
+    }
+
+    /// Smallest value that is greater-than-or-equal to this value
+    pub fn ceil(self) -> Self {
+        self.v.ceil().into()
+    }
+
+    /// Rounded value
+    pub fn round(self) -> Self {
+        self.v.round().into()
+    }
+
+    /// Logical not (1 if the value is zero, 0 otherwise)
+    pub fn logical_not(self) -> Self {
+        f32::from(self.v == 0.0).into()
+    }
+
+    /// Three-way comparison, returning -1, 0, 1, or `NaN`
+    pub fn compare(self, rhs: Self) -> Self {
+        self.v
+            .partial_cmp(&rhs.v)
+            .map(|c| c as i8 as f32)
+            .unwrap_or(f32::NAN)
+            .into()
+    }
+
+    /// Least non-negative remainder
+    pub fn rem_euclid(self, rhs: Self) -> Self {
+        let e = self.v.div_euclid(rhs.v);
+        Self {
+            v: self.v.rem_euclid(rhs.v),
+            d: std::array::from_fn(|i| self.d[i] - rhs.d[i] * e),
+        }
+    }
+
+    /// Four-quadrant arctangent
+    pub fn atan2(self, x: Self) -> Self {
+        let y = self;
+        let d = x.v.powi(2) + y.v.powi(2);
+        Self {
+            v: y.v.atan2(x.v),
+            d: std::array::from_fn(|i| (x.v * y.d[i] - y.v * x.d[i]) / d),
+        }
+    }
+
+    /// Minimum of two values, with the same choice semantics as the point
+    /// evaluator
+    ///
+    /// If the values are equal, the tangents of `rhs` are used.
+    pub fn min_choice(self, rhs: Self) -> (Self, Choice) {
+        if self.v < rhs.v {
+            (self, Choice::Left)
+        } else if rhs.v < self.v {
+            (rhs, Choice::Right)
+        } else if self.v.is_nan() || rhs.v.is_nan() {
+            (f32::NAN.into(), Choice::Both)
+        } else {
+            (rhs, Choice::Both)
+        }
+    }
+
+    /// Maximum of two values, with the same choice semantics as the point
+    /// evaluator
+    ///
+    /// If the values are equal, the tangents of `rhs` are used.
+    pub fn max_choice(self, rhs: Self) -> (Self, Choice) {
+        if self.v > rhs.v {
+            (self, Choice::Left)
+        } else if rhs.v > self.v {
+            (rhs, Choice::Right)
+        } else if self.v.is_nan() || rhs.v.is_nan() {
+            (f32::NAN.into(), Choice::Both)
+        } else {
+            (rhs, Choice::Both)
+        }
+    }
+
+    /// Short-circuiting logical and
+    pub fn and_choice(self, rhs: Self) -> (Self, Choice) {
+        if self.v == 0.0 {
+            (self, Choice::Left)
+        } else {
+            (rhs, Choice::Right)
+        }
+    }
+
+    /// Short-circuiting logical or
+    pub fn or_choice(self, rhs: Self) -> (Self, Choice) {
+        if self.v != 0.0 {
+            (self, Choice::Left)
+        } else {
+            (rhs, Choice::Right)
+        }
+    }
+}
+
+/// Builds a constant value, with tangents of zero
+impl<const K: usize> From<f32> for Dual<K> {
+    fn from(v: f32) -> Self {
+        Self { v, d: [0.0; K] }
+    }
+}
+
+impl<const K: usize> std::ops::Add<Dual<K>> for Dual<K> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            v: self.v + rhs.v,
+            d: std::array::from_fn(|i| self.d[i] + rhs.d[i]),
+        }
+    }
+}
+
+impl<const K: usize> std::ops::Sub<Dual<K>> for Dual<K> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            v: self.v - rhs.v,
+            d: std::array::from_fn(|i| self.d[i] - rhs.d[i]),
+        }
+    }
+}
+
+impl<const K: usize> std::ops::Mul<Dual<K>> for Dual<K> {
+    type Output = Self;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            v: self.v * rhs.v,
+            d: std::array::from_fn(|i| self.v * rhs.d[i] + rhs.v * self.d[i]),
+        }
+    }
+}
+
+impl<const K: usize> std::ops::Div<Dual<K>> for Dual<K> {
+    type Output = Self;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        let d = rhs.v.powi(2);
+        Self {
+            v: self.v / rhs.v,
+            d: std::array::from_fn(|i| {
+                (rhs.v * self.d[i] - self.v * rhs.d[i]) / d
+            }),
+        }
+    }
+}
+
+impl<const K: usize> std::ops::Neg for Dual<K> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            v: -self.v,
+            d: self.d.map(|t| -t),
+        }
+    }
+}
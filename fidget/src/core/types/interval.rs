@@ -89,81 +89,142 @@ impl Interval {
     }
     /// Computes the sine of the interval
     ///
-    /// Right now, this always returns the maximum range of `[-1, 1]`
+    /// See [`Interval::cos`] for details on range reduction and rounding.
     pub fn sin(self) -> Self {
-        if self.has_nan() {
-            f32::NAN.into()
-        } else {
-            // TODO: make this smarter
-            Interval::new(-1.0, 1.0)
-        }
+        self.periodic(f64::sin, std::f64::consts::FRAC_PI_2)
     }
     /// Computes the cosine of the interval
     ///
-    /// Right now, this always returns the maximum range of `[-1, 1]`
+    /// The range is split at multiples of π (where `cos` has its extrema), so
+    /// the result is tight: it includes `±1` only if the interval contains a
+    /// maximum or minimum, and is otherwise bounded by the endpoint values.
+    /// Bounds are rounded outwards by one `f32` ulp.
     pub fn cos(self) -> Self {
+        self.periodic(f64::cos, 0.0)
+    }
+    /// Evaluates a sinusoid `f` with a period of 2π
+    ///
+    /// `f` must have a maximum of 1 at `phase` and a minimum of -1 at
+    /// `phase + π`.  Math is done in `f64` for accurate range reduction.
+    fn periodic(self, f: fn(f64) -> f64, phase: f64) -> Self {
+        use std::f64::consts::PI;
         if self.has_nan() {
-            f32::NAN.into()
-        } else {
-            // TODO: make this smarter
-            Interval::new(-1.0, 1.0)
+            return f32::NAN.into();
+        }
+        let (lo, hi) = (self.lower as f64, self.upper as f64);
+        if !(lo.is_finite() && hi.is_finite()) || hi - lo >= 2.0 * PI {
+            return Interval::new(-1.0, 1.0);
+        }
+        let (a, b) = (f(lo), f(hi));
+        let mut lower = a.min(b);
+        let mut upper = a.max(b);
+
+        // Extrema are at phase + n·π: maxima for even n, minima for odd n.
+        // Because the range is narrower than 2π, it contains at most two.
+        let n = ((lo - phase) / PI).ceil();
+        for n in [n, n + 1.0] {
+            if n * PI + phase <= hi {
+                if n.rem_euclid(2.0) == 0.0 {
+                    upper = 1.0;
+                } else {
+                    lower = -1.0;
+                }
+            }
         }
+        let lower = (lower as f32).next_down().max(-1.0);
+        let upper = (upper as f32).next_up().min(1.0);
+        Interval::new(lower, upper)
     }
     /// Computes the tangent of the interval
     ///
-    /// Returns the `NAN` interval if the result contains a undefined point
+    /// `tan` is increasing between its poles (at π/2 + n·π), so the result is
+    /// bounded by the endpoint values.  Returns the `NAN` interval if the input
+    /// contains a pole.
     pub fn tan(self) -> Self {
-        let size = self.upper - self.lower;
-        if size >= std::f32::consts::PI {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        if self.has_nan() {
+            return f32::NAN.into();
+        }
+        let (lo, hi) = (self.lower as f64, self.upper as f64);
+        if !(lo.is_finite() && hi.is_finite()) || hi - lo >= PI {
+            return f32::NAN.into();
+        }
+        let n = ((lo - FRAC_PI_2) / PI).ceil();
+        if n * PI + FRAC_PI_2 <= hi {
             f32::NAN.into()
         } else {
-            let lower = self.lower.tan();
-            let upper = self.upper.tan();
-            if upper >= lower {
-                Interval::new(lower, upper)
-            } else {
-                f32::NAN.into()
-            }
+            Self::outward(lo.tan(), hi.tan())
         }
     }
     /// Computes the arcsine of the interval
     ///
     /// Returns the `NAN` interval if the input is invalid
     pub fn asin(self) -> Self {
-        if self.lower < -1.0 || self.upper > 1.0 {
+        use std::f32::consts::FRAC_PI_2;
+        if self.has_nan() || self.lower < -1.0 || self.upper > 1.0 {
             f32::NAN.into()
         } else {
-            Interval::new(self.lower.asin(), self.upper.asin())
+            let (lo, hi) = (self.lower as f64, self.upper as f64);
+            Self::outward(lo.asin(), hi.asin()).clamp(-FRAC_PI_2, FRAC_PI_2)
         }
     }
     /// Computes the arccosine of the interval
     ///
     /// Returns the `NAN` interval if the input is invalid
     pub fn acos(self) -> Self {
-        if self.lower < -1.0 || self.upper > 1.0 {
+        if self.has_nan() || self.lower < -1.0 || self.upper > 1.0 {
             f32::NAN.into()
         } else {
-            Interval::new(self.upper.acos(), self.lower.acos())
+            let (lo, hi) = (self.lower as f64, self.upper as f64);
+            Self::outward(hi.acos(), lo.acos()).clamp(0.0, std::f32::consts::PI)
         }
     }
     /// Computes the arctangent of the interval
     pub fn atan(self) -> Self {
-        Interval::new(self.lower.atan(), self.upper.atan())
+        use std::f32::consts::FRAC_PI_2;
+        if self.has_nan() {
+            f32::NAN.into()
+        } else {
+            let (lo, hi) = (self.lower as f64, self.upper as f64);
+            Self::outward(lo.atan(), hi.atan()).clamp(-FRAC_PI_2, FRAC_PI_2)
+        }
     }
     /// Computes the exponent function applied to the interval
     pub fn exp(self) -> Self {
-        Interval::new(self.lower.exp(), self.upper.exp())
+        if self.has_nan() {
+            f32::NAN.into()
+        } else {
+            let (lo, hi) = (self.lower as f64, self.upper as f64);
+            Self::outward(lo.exp(), hi.exp()).clamp(0.0, f32::INFINITY)
+        }
     }
     /// Computes the natural log of the input interval
     ///
     /// Returns the `NAN` interval if the input contains zero
     pub fn ln(self) -> Self {
-        if self.lower <= 0.0 {
+        if self.has_nan() || self.lower <= 0.0 {
             f32::NAN.into()
         } else {
-            Interval::new(self.lower.ln(), self.upper.ln())
+            let (lo, hi) = (self.lower as f64, self.upper as f64);
+            Self::outward(lo.ln(), hi.ln())
         }
     }
+    /// Builds an interval from `f64` bounds, rounding outwards by one `f32`
+    /// ulp
+    ///
+    /// Transcendental functions are computed in `f64` then rounded outwards,
+    /// so that the result contains the (possibly less accurate) `f32` values
+    /// computed by point evaluators.
+    fn outward(lower: f64, upper: f64) -> Self {
+        Interval::new((lower as f32).next_down(), (upper as f32).next_up())
+    }
+    /// Clamps both bounds to the given range
+    fn clamp(self, lower: f32, upper: f32) -> Self {
+        Interval::new(
+            self.lower.clamp(lower, upper),
+            self.upper.clamp(lower, upper),
+        )
+    }
     /// Calculates the square root of the interval
     ///
     /// If the interval contains values below 0, returns a `NAN` interval.
@@ -364,12 +425,29 @@ impl Interval {
     }
 
     /// Four-quadrant arctangent
+    ///
+    /// `self` is the `y` coordinate, and `x` is the `x` coordinate.  If the
+    /// region doesn't touch the branch cut along the negative X axis (or the
+    /// origin), `atan2` is continuous, and its extrema are at the region's
+    /// corners; otherwise, the result is `[-π, π]`.
     pub fn atan2(self, x: Self) -> Self {
+        use std::f32::consts::PI;
         if self.has_nan() || x.has_nan() {
             f32::NAN.into()
+        } else if x.lower <= 0.0 && self.lower <= 0.0 && self.upper >= 0.0 {
+            // This also catches signed zeros, where atan2(±0, -0) = ±π
+            Interval::new(-PI, PI)
         } else {
-            // TODO optimize this further
-            Interval::new(-std::f32::consts::PI, std::f32::consts::PI)
+            let mut lower = f64::INFINITY;
+            let mut upper = f64::NEG_INFINITY;
+            for y in [self.lower, self.upper] {
+                for x in [x.lower, x.upper] {
+                    let v = (y as f64).atan2(x as f64);
+                    lower = lower.min(v);
+                    upper = upper.max(v);
+                }
+            }
+            Self::outward(lower, upper).clamp(-PI, PI)
         }
     }
 }
@@ -464,6 +542,92 @@ impl std::ops::Div<Interval> for Interval {
     }
 }
 
+/// Result of [`Interval::div_extended`]
+///
+/// When the divisor spans zero, the true result of division is either a
+/// half-infinite interval or a pair of disjoint intervals; this type stores
+/// the latter, rather than discarding all information (as the `/` operator
+/// does by returning `NaN`).
+///
+/// The interval type defaults to [`Interval`]; dividing
+/// [`Interval64`](super::Interval64) values returns an
+/// `ExtendedDiv<Interval64>`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExtendedDiv<I = Interval> {
+    /// The result is a single (possibly unbounded) interval
+    Single(I),
+    /// The result is the union of two disjoint intervals, `(-∞, a] ∪ [b, ∞)`
+    Split(I, I),
+}
+
+impl ExtendedDiv {
+    /// Returns a single interval which contains the entire result
+    ///
+    /// A [`Split`](ExtendedDiv::Split) result is widened to `(-∞, ∞)`
+    pub fn hull(self) -> Interval {
+        match self {
+            Self::Single(i) => i,
+            Self::Split(..) => Interval::new(-f32::INFINITY, f32::INFINITY),
+        }
+    }
+}
+
+impl Interval {
+    /// Divides by an interval, with special handling for divisors containing 0
+    ///
+    /// If the divisor does not contain 0, this is the same as the `/` operator.
+    /// Otherwise, the result is computed with extended interval arithmetic;
+    /// for example, `[1, 2] / [0, 4]` is `[0.25, ∞)` and `[1, 2] / [-1, 4]` is
+    /// `(-∞, -1] ∪ [0.25, ∞)`.
+    ///
+    /// If the numerator also contains 0, the result is `(-∞, ∞)`; dividing by
+    /// exactly `[0, 0]` (or by an interval with `NaN` bounds) returns `NaN`.
+    pub fn div_extended(self, rhs: Self) -> ExtendedDiv {
+        if self.has_nan()
+            || rhs.has_nan()
+            || (rhs.lower == 0.0 && rhs.upper == 0.0)
+        {
+            return ExtendedDiv::Single(f32::NAN.into());
+        } else if rhs.lower > 0.0 || rhs.upper < 0.0 {
+            return ExtendedDiv::Single(self / rhs);
+        } else if self.contains(0.0) {
+            return ExtendedDiv::Single(Interval::new(
+                -f32::INFINITY,
+                f32::INFINITY,
+            ));
+        }
+        let inf = f32::INFINITY;
+        // `n` is the numerator bound which is closest to zero; the result is
+        // built by dividing it by the nonzero bound(s) of the divisor.
+        let n = if self.lower > 0.0 {
+            self.lower
+        } else {
+            self.upper
+        };
+        let lo = rhs.lower != 0.0;
+        let hi = rhs.upper != 0.0;
+        let half = |d: f32| {
+            if n / d < 0.0 {
+                Interval::new(-inf, n / d)
+            } else {
+                Interval::new(n / d, inf)
+            }
+        };
+        match (lo, hi) {
+            (true, true) => {
+                let (a, b) = (n / rhs.lower, n / rhs.upper);
+                ExtendedDiv::Split(
+                    Interval::new(-inf, a.min(b)),
+                    Interval::new(a.max(b), inf),
+                )
+            }
+            (true, false) => ExtendedDiv::Single(half(rhs.lower)),
+            (false, true) => ExtendedDiv::Single(half(rhs.upper)),
+            (false, false) => unreachable!(),
+        }
+    }
+}
+
 impl std::ops::Sub<Interval> for Interval {
     type Output = Self;
     fn sub(self, rhs: Self) -> Self {
@@ -490,4 +654,227 @@ mod test {
         assert_eq!(v, [0.0, 1.0].into());
         assert_eq!(c, Choice::Both);
     }
+
+    #[test]
+    fn test_div_extended() {
+        let inf = f32::INFINITY;
+        let i = |a, b| Interval::new(a, b);
+        let single = |a, b| ExtendedDiv::Single(Interval::new(a, b));
+
+        assert_eq!(i(1.0, 2.0).div_extended(i(2.0, 4.0)), single(0.25, 1.0));
+        assert_eq!(i(1.0, 2.0).div_extended(i(0.0, 4.0)), single(0.25, inf));
+        assert_eq!(i(1.0, 2.0).div_extended(i(-4.0, 0.0)), single(-inf, -0.25));
+        assert_eq!(
+            i(-2.0, -1.0).div_extended(i(0.0, 4.0)),
+            single(-inf, -0.25)
+        );
+        assert_eq!(i(-2.0, -1.0).div_extended(i(-4.0, 0.0)), single(0.25, inf));
+        assert_eq!(
+            i(1.0, 2.0).div_extended(i(-1.0, 4.0)),
+            ExtendedDiv::Split(i(-inf, -1.0), i(0.25, inf))
+        );
+        assert_eq!(
+            i(-2.0, -1.0).div_extended(i(-1.0, 4.0)),
+            ExtendedDiv::Split(i(-inf, -0.25), i(1.0, inf))
+        );
+        assert_eq!(i(-1.0, 2.0).div_extended(i(-1.0, 4.0)), single(-inf, inf));
+        assert!(i(1.0, 2.0).div_extended(i(0.0, 0.0)).hull().has_nan());
+        assert_eq!(i(1.0, 2.0).div_extended(i(-1.0, 4.0)).hull(), i(-inf, inf));
+    }
+
+    /// Checks `f(i)` against dense samples (plus any extrema) within `i`
+    fn check_trig(
+        i: Interval,
+        f: fn(Interval) -> Interval,
+        g: fn(f64) -> f64,
+        extrema: f64,
+    ) {
+        use std::f64::consts::PI;
+        let out = f(i);
+        let (lo, hi) = (i.lower() as f64, i.upper() as f64);
+        let mut samples: Vec<f64> =
+            (0..=64).map(|j| lo + (hi - lo) * j as f64 / 64.0).collect();
+        let mut n = ((lo - extrema) / PI).ceil();
+        while n * PI + extrema <= hi && samples.len() < 80 {
+            samples.push(n * PI + extrema);
+            n += 1.0;
+        }
+        let mut min = f64::INFINITY;
+        let mut max = -f64::INFINITY;
+        for x in samples {
+            let v = g(x);
+            assert!(
+                out.contains(v as f32),
+                "{v} = f({x}) is not in {out:?} for input {i:?}"
+            );
+            min = min.min(v);
+            max = max.max(v);
+        }
+        // Bounds are tight, within rounding
+        assert!((out.lower() as f64 - min).abs() < 1e-6, "{i:?} -> {out:?}");
+        assert!((out.upper() as f64 - max).abs() < 1e-6, "{i:?} -> {out:?}");
+    }
+
+    #[test]
+    fn test_sin_cos() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        let cos = |i: Interval| i.cos();
+        let sin = |i: Interval| i.sin();
+
+        // Boundaries just around every multiple of π/2 (including the
+        // extrema of both functions) over a few periods
+        for k in -12..=12 {
+            let c = k as f32 * FRAC_PI_2;
+            for (dl, dh) in [
+                (-1e-3, 1e-3),
+                (-0.5, 0.0),
+                (0.0, 0.5),
+                (-1e-6, 0.0),
+                (0.0, 1e-6),
+                (-0.1, 2.0),
+                (-2.0, 0.1),
+                (-1.0, 3.0),
+                (0.0, 0.0),
+            ] {
+                let i = Interval::new(c + dl, c + dh);
+                check_trig(i, cos, f64::cos, 0.0);
+                check_trig(i, sin, f64::sin, std::f64::consts::FRAC_PI_2);
+            }
+        }
+
+        // Scanning windows of varying widths across several periods
+        for w in [0.01, 0.3, 1.0, 2.5, 3.1, 3.2, 5.0, 6.2] {
+            for j in 0..200 {
+                let lo = -10.0 + j as f32 * 0.1;
+                let i = Interval::new(lo, lo + w);
+                check_trig(i, cos, f64::cos, 0.0);
+                check_trig(i, sin, f64::sin, std::f64::consts::FRAC_PI_2);
+            }
+        }
+
+        // Specific cases
+        assert_eq!(Interval::new(0.0, 7.0).cos(), Interval::new(-1.0, 1.0));
+        let v = Interval::new(0.1, PI - 0.1).sin();
+        assert!(v.upper() == 1.0 && v.lower() > 0.09);
+        let v = Interval::new(0.1, 0.2).cos();
+        assert!(v.upper() < 1.0 && v.lower() > 0.97);
+        let v = Interval::new(-f32::INFINITY, 0.0).sin();
+        assert_eq!(v, Interval::new(-1.0, 1.0));
+        assert!(Interval::from(f32::NAN).sin().has_nan());
+        assert!(Interval::from(f32::NAN).cos().has_nan());
+
+        // Large magnitudes
+        for lo in [1e4f32, -1e5, 123456.7, 1e7] {
+            let i = Interval::new(lo, lo + 0.5);
+            check_trig(i, cos, f64::cos, 0.0);
+            check_trig(i, sin, f64::sin, std::f64::consts::FRAC_PI_2);
+        }
+    }
+
+    /// Checks `f(i)` against dense `f32` samples within `i`
+    ///
+    /// Returns the range of sampled values.
+    fn check_unary(
+        i: Interval,
+        f: fn(Interval) -> Interval,
+        g: fn(f32) -> f32,
+    ) -> (f32, f32) {
+        let out = f(i);
+        let mut min = f32::INFINITY;
+        let mut max = -f32::INFINITY;
+        for j in 0..=256 {
+            let x = i.lerp(j as f32 / 256.0).clamp(i.lower(), i.upper());
+            let v = g(x);
+            assert!(out.contains(v), "{v} = f({x}) is not in {out} for {i}");
+            min = min.min(v);
+            max = max.max(v);
+        }
+        (min, max)
+    }
+
+    #[test]
+    fn test_transcendental() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        let i = Interval::new;
+        let tight = |out: Interval, (min, max): (f32, f32)| {
+            let close =
+                |a: f32, b: f32| (a - b).abs() <= 1e-5 * b.abs().max(1.0);
+            assert!(close(out.lower(), min), "{out} != [{min}, {max}]");
+            assert!(close(out.upper(), max), "{out} != [{min}, {max}]");
+        };
+
+        // Monotonic functions are bounded by their endpoints
+        for r in [i(-3.0, -1.0), i(-1.0, 2.0), i(0.5, 10.0), i(0.0, 0.0)] {
+            let out = r.exp();
+            tight(out, check_unary(r, Interval::exp, f32::exp));
+            let out2 = r.atan();
+            tight(out2, check_unary(r, Interval::atan, f32::atan));
+        }
+        for r in [i(1e-3, 1.0), i(0.5, 100.0), i(2.0, 2.0)] {
+            tight(r.ln(), check_unary(r, Interval::ln, f32::ln));
+        }
+        for r in [i(-1.0, 1.0), i(-0.5, 0.25), i(0.9, 1.0)] {
+            tight(r.asin(), check_unary(r, Interval::asin, f32::asin));
+            tight(r.acos(), check_unary(r, Interval::acos, f32::acos));
+        }
+        assert!(i(0.0, 1.0).ln().has_nan());
+        assert!(i(-2.0, 0.5).asin().has_nan());
+        assert!(i(0.0, 1.5).acos().has_nan());
+        assert_eq!(i(-1.0, 1.0).asin(), i(-FRAC_PI_2, FRAC_PI_2));
+        assert_eq!(i(-f32::INFINITY, 0.0).exp().lower(), 0.0);
+
+        // Tangent tracks its monotonic segments, between poles
+        for r in [i(-1.5, 1.5), i(1.6, 4.7), i(-4.0, -1.6), i(100.0, 101.0)] {
+            tight(r.tan(), check_unary(r, Interval::tan, f32::tan));
+        }
+        for r in [i(1.0, 2.0), i(-2.0, -1.0), i(0.0, 3.5), i(4.0, 4.8)] {
+            assert!(r.tan().has_nan(), "{r} contains a pole");
+        }
+
+        // atan2 is bounded by the region's corners, away from the branch cut
+        let check_atan2 = |y: Interval, x: Interval| {
+            let out = y.atan2(x);
+            let mut min = f32::INFINITY;
+            let mut max = -f32::INFINITY;
+            for j in 0..=32 {
+                for k in 0..=32 {
+                    let py =
+                        y.lerp(j as f32 / 32.0).clamp(y.lower(), y.upper());
+                    let px =
+                        x.lerp(k as f32 / 32.0).clamp(x.lower(), x.upper());
+                    let v = py.atan2(px);
+                    assert!(
+                        out.contains(v),
+                        "atan2({py}, {px}) = {v} is not in {out}"
+                    );
+                    min = min.min(v);
+                    max = max.max(v);
+                }
+            }
+            (out, (min, max))
+        };
+        for (y, x) in [
+            (i(1.0, 2.0), i(1.0, 2.0)),
+            (i(1.0, 2.0), i(-3.0, 2.0)),
+            (i(-2.0, -1.0), i(-3.0, 2.0)),
+            (i(-1.0, 1.0), i(0.5, 2.0)),
+            (i(0.5, 1.0), i(-2.0, -1.0)),
+            (i(-1.0, -0.5), i(-2.0, -1.0)),
+            (i(0.0, 1.0), i(1e-3, 1.0)),
+        ] {
+            let (out, range) = check_atan2(y, x);
+            tight(out, range);
+        }
+        // Regions touching the branch cut or origin span the full range
+        for (y, x) in [
+            (i(-1.0, 1.0), i(-2.0, -1.0)),
+            (i(-1.0, 1.0), i(-1.0, 1.0)),
+            (i(0.0, 1.0), i(-2.0, -1.0)),
+            (i(0.0, 1.0), i(0.0, 1.0)),
+        ] {
+            let (out, _) = check_atan2(y, x);
+            assert_eq!(out, i(-PI, PI));
+        }
+        assert!(i(0.0, 1.0).atan2(f32::NAN.into()).has_nan());
+    }
 }
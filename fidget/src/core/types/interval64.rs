@@ -0,0 +1,627 @@
+use super::ExtendedDiv;
+use crate::vm::Choice;
+
+/// Double-precision equivalent of [`Interval`](super::Interval)
+///
+/// Stores a range, with conservative calculations to guarantee that it always
+/// contains the actual value.
+///
+/// # Warning
+/// This implementation does not set rounding modes, so it may not be _perfect_.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Interval64 {
+    lower: f64,
+    upper: f64,
+}
+
+impl std::fmt::Debug for Interval64 {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> Result<(), std::fmt::Error> {
+        f.debug_tuple("")
+            .field(&self.lower)
+            .field(&self.upper)
+            .finish()
+    }
+}
+
+impl Interval64 {
+    /// Builds a new interval
+    ///
+    /// There are two kinds of valid interval:
+    /// - `[lower, upper]` where `lower <= upper`
+    /// - `[NaN, NaN]`
+    ///
+    /// # Panics
+    /// Panics if the resulting interval would be invalid
+    #[inline]
+    pub fn new(lower: f64, upper: f64) -> Self {
+        assert!(
+            upper >= lower || (lower.is_nan() && upper.is_nan()),
+            "invalid interval [{lower}, {upper}]"
+        );
+        Self { lower, upper }
+    }
+    /// Returns the lower bound of the interval
+    #[inline]
+    pub fn lower(&self) -> f64 {
+        self.lower
+    }
+    /// Returns the upper bound of the interval
+    #[inline]
+    pub fn upper(&self) -> f64 {
+        self.upper
+    }
+    /// Checks whether the given value is (strictly) contained in the interval
+    #[inline]
+    pub fn contains(&self, v: f64) -> bool {
+        v >= self.lower && v <= self.upper
+    }
+    /// Returns `true` if either bound of the interval is `NaN`
+    pub fn has_nan(&self) -> bool {
+        self.lower.is_nan() || self.upper.is_nan()
+    }
+    /// Calculates the absolute value of the interval
+    pub fn abs(self) -> Self {
+        if self.lower < 0.0 {
+            if self.upper > 0.0 {
+                Interval64::new(0.0, self.upper.max(-self.lower))
+            } else {
+                Interval64::new(-self.upper, -self.lower)
+            }
+        } else {
+            self
+        }
+    }
+    /// Squares the interval
+    ///
+    /// Note that this has tighter bounds than multiplication, because we know
+    /// that both sides of the multiplication are the same value.
+    pub fn square(self) -> Self {
+        if self.upper < 0.0 {
+            Interval64::new(self.upper.powi(2), self.lower.powi(2))
+        } else if self.lower > 0.0 {
+            Interval64::new(self.lower.powi(2), self.upper.powi(2))
+        } else if self.has_nan() {
+            f64::NAN.into()
+        } else {
+            Interval64::new(0.0, self.lower.abs().max(self.upper.abs()).powi(2))
+        }
+    }
+    /// Computes the sine of the interval
+    ///
+    /// See [`Interval64::cos`] for details on range reduction and rounding.
+    pub fn sin(self) -> Self {
+        self.periodic(f64::sin, std::f64::consts::FRAC_PI_2)
+    }
+    /// Computes the cosine of the interval
+    ///
+    /// The range is split at multiples of π (where `cos` has its extrema), so
+    /// the result is tight: it includes `±1` only if the interval contains a
+    /// maximum or minimum, and is otherwise bounded by the endpoint values.
+    /// Bounds are rounded outwards by one `f64` ulp.
+    pub fn cos(self) -> Self {
+        self.periodic(f64::cos, 0.0)
+    }
+    /// Evaluates a sinusoid `f` with a period of 2π
+    ///
+    /// `f` must have a maximum of 1 at `phase` and a minimum of -1 at
+    /// `phase + π`.
+    fn periodic(self, f: fn(f64) -> f64, phase: f64) -> Self {
+        use std::f64::consts::PI;
+        if self.has_nan() {
+            return f64::NAN.into();
+        }
+        let (lo, hi) = (self.lower, self.upper);
+        if !(lo.is_finite() && hi.is_finite()) || hi - lo >= 2.0 * PI {
+            return Interval64::new(-1.0, 1.0);
+        }
+        let (a, b) = (f(lo), f(hi));
+        let mut lower = a.min(b);
+        let mut upper = a.max(b);
+
+        // Extrema are at phase + n·π: maxima for even n, minima for odd n.
+        // Because the range is narrower than 2π, it contains at most two.
+        let n = ((lo - phase) / PI).ceil();
+        for n in [n, n + 1.0] {
+            if n * PI + phase <= hi {
+                if n.rem_euclid(2.0) == 0.0 {
+                    upper = 1.0;
+                } else {
+                    lower = -1.0;
+                }
+            }
+        }
+        let lower = lower.next_down().max(-1.0);
+        let upper = upper.next_up().min(1.0);
+        Interval64::new(lower, upper)
+    }
+    /// Computes the tangent of the interval
+    ///
+    /// `tan` is increasing between its poles (at π/2 + n·π), so the result is
+    /// bounded by the endpoint values.  Returns the `NAN` interval if the input
+    /// contains a pole.
+    pub fn tan(self) -> Self {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        if self.has_nan() {
+            return f64::NAN.into();
+        }
+        let (lo, hi) = (self.lower, self.upper);
+        if !(lo.is_finite() && hi.is_finite()) || hi - lo >= PI {
+            return f64::NAN.into();
+        }
+        let n = ((lo - FRAC_PI_2) / PI).ceil();
+        if n * PI + FRAC_PI_2 <= hi {
+            f64::NAN.into()
+        } else {
+            Self::outward(lo.tan(), hi.tan())
+        }
+    }
+    /// Computes the arcsine of the interval
+    ///
+    /// Returns the `NAN` interval if the input is invalid
+    pub fn asin(self) -> Self {
+        use std::f64::consts::FRAC_PI_2;
+        if self.has_nan() || self.lower < -1.0 || self.upper > 1.0 {
+            f64::NAN.into()
+        } else {
+            Self::outward(self.lower.asin(), self.upper.asin())
+                .clamp(-FRAC_PI_2, FRAC_PI_2)
+        }
+    }
+    /// Computes the arccosine of the interval
+    ///
+    /// Returns the `NAN` interval if the input is invalid
+    pub fn acos(self) -> Self {
+        if self.has_nan() || self.lower < -1.0 || self.upper > 1.0 {
+            f64::NAN.into()
+        } else {
+            Self::outward(self.upper.acos(), self.lower.acos())
+                .clamp(0.0, std::f64::consts::PI)
+        }
+    }
+    /// Computes the arctangent of the interval
+    pub fn atan(self) -> Self {
+        use std::f64::consts::FRAC_PI_2;
+        if self.has_nan() {
+            f64::NAN.into()
+        } else {
+            Self::outward(self.lower.atan(), self.upper.atan())
+                .clamp(-FRAC_PI_2, FRAC_PI_2)
+        }
+    }
+    /// Computes the exponent function applied to the interval
+    pub fn exp(self) -> Self {
+        if self.has_nan() {
+            f64::NAN.into()
+        } else {
+            Self::outward(self.lower.exp(), self.upper.exp())
+                .clamp(0.0, f64::INFINITY)
+        }
+    }
+    /// Computes the natural log of the input interval
+    ///
+    /// Returns the `NAN` interval if the input contains zero
+    pub fn ln(self) -> Self {
+        if self.has_nan() || self.lower <= 0.0 {
+            f64::NAN.into()
+        } else {
+            Self::outward(self.lower.ln(), self.upper.ln())
+        }
+    }
+    /// Builds an interval, rounding both bounds outwards by one `f64` ulp
+    ///
+    /// The standard library's transcendental functions aren't correctly
+    /// rounded, so this keeps their results conservative.
+    fn outward(lower: f64, upper: f64) -> Self {
+        Interval64::new(lower.next_down(), upper.next_up())
+    }
+    /// Clamps both bounds to the given range
+    fn clamp(self, lower: f64, upper: f64) -> Self {
+        Interval64::new(
+            self.lower.clamp(lower, upper),
+            self.upper.clamp(lower, upper),
+        )
+    }
+    /// Calculates the square root of the interval
+    ///
+    /// If the interval contains values below 0, returns a `NAN` interval.
+    pub fn sqrt(self) -> Self {
+        if self.lower < 0.0 {
+            f64::NAN.into()
+        } else {
+            Interval64::new(self.lower.sqrt(), self.upper.sqrt())
+        }
+    }
+    /// Calculates the reciprocal of the interval
+    ///
+    /// If the interval includes 0, returns the `NAN` interval
+    pub fn recip(self) -> Self {
+        if self.lower > 0.0 || self.upper < 0.0 {
+            Interval64::new(1.0 / self.upper, 1.0 / self.lower)
+        } else {
+            f64::NAN.into()
+        }
+    }
+    /// Calculates the minimum of two intervals
+    ///
+    /// Returns both the result and a [`Choice`] indicating whether one side is
+    /// always less than the other.
+    ///
+    /// If either side is `NAN`, returns the `NAN` interval and `Choice::Both`.
+    pub fn min_choice(self, rhs: Self) -> (Self, Choice) {
+        if self.has_nan() || rhs.has_nan() {
+            return (f64::NAN.into(), Choice::Both);
+        }
+        let choice = if self.upper < rhs.lower {
+            Choice::Left
+        } else if rhs.upper < self.lower {
+            Choice::Right
+        } else {
+            Choice::Both
+        };
+        (
+            Interval64::new(
+                self.lower.min(rhs.lower),
+                self.upper.min(rhs.upper),
+            ),
+            choice,
+        )
+    }
+    /// Calculates the maximum of two intervals
+    ///
+    /// Returns both the result and a [`Choice`] indicating whether one side is
+    /// always greater than the other.
+    ///
+    /// If either side is `NAN`, returns the `NAN` interval and `Choice::Both`.
+    pub fn max_choice(self, rhs: Self) -> (Self, Choice) {
+        if self.has_nan() || rhs.has_nan() {
+            return (f64::NAN.into(), Choice::Both);
+        }
+        let choice = if self.lower > rhs.upper {
+            Choice::Left
+        } else if rhs.lower > self.upper {
+            Choice::Right
+        } else {
+            Choice::Both
+        };
+        (
+            Interval64::new(
+                self.lower.max(rhs.lower),
+                self.upper.max(rhs.upper),
+            ),
+            choice,
+        )
+    }
+
+    /// Calculates the short-circuiting `AND` of two intervals
+    ///
+    /// Returns both the result and a [`Choice`] indicating whether one side is
+    /// always selected.  An unambiguous 0 in `self` selects itself; an
+    /// unambiguous 1 selects the opposite branch.
+    pub fn and_choice(self, rhs: Self) -> (Self, Choice) {
+        if self.lower == 0.0 && self.upper == 0.0 {
+            (0.0.into(), Choice::Left)
+        } else if !self.contains(0.0) {
+            (rhs, Choice::Right)
+        } else {
+            // The output will either be the RHS or zero, so extend the interval
+            // to include zero in it.
+            (
+                if rhs.has_nan() {
+                    f64::NAN.into()
+                } else {
+                    Interval64::new(rhs.lower.min(0.0), rhs.upper.max(0.0))
+                },
+                Choice::Both,
+            )
+        }
+    }
+
+    /// Calculates the short-circuiting `OR` of two intervals
+    ///
+    /// Returns both the result and a [`Choice`] indicating whether one side is
+    /// always selected.  An unambiguous 0 in `self` selects the opposite
+    /// branch; an unambiguous 1 selects itself.
+    pub fn or_choice(self, rhs: Self) -> (Self, Choice) {
+        if !self.contains(0.0) {
+            (self, Choice::Left)
+        } else if self.lower == 0.0 && self.upper == 0.0 {
+            (rhs, Choice::Right)
+        } else {
+            // The output could be anywhere in either interval
+            (
+                if rhs.has_nan() {
+                    f64::NAN.into()
+                } else {
+                    Interval64::new(
+                        self.lower.min(rhs.lower),
+                        self.upper.max(rhs.upper),
+                    )
+                },
+                Choice::Both,
+            )
+        }
+    }
+
+    /// Returns the midpoint of the interval
+    pub fn midpoint(self) -> f64 {
+        (self.lower + self.upper) / 2.0
+    }
+
+    /// Splits the interval at the midpoint
+    ///
+    /// ```
+    /// # use fidget::types::Interval64;
+    /// let a = Interval64::new(0.0, 1.0);
+    /// let (lo, hi) = a.split();
+    /// assert_eq!(lo, Interval64::new(0.0, 0.5));
+    /// assert_eq!(hi, Interval64::new(0.5, 1.0));
+    /// ```
+    pub fn split(self) -> (Self, Self) {
+        let mid = self.midpoint();
+        (
+            Interval64::new(self.lower, mid),
+            Interval64::new(mid, self.upper),
+        )
+    }
+
+    /// Linear interpolation from `lower` to `upper`
+    ///
+    /// ```
+    /// # use fidget::types::Interval64;
+    /// let a = Interval64::new(0.0, 2.0);
+    /// assert_eq!(a.lerp(0.5), 1.0);
+    /// assert_eq!(a.lerp(0.75), 1.5);
+    /// assert_eq!(a.lerp(2.0), 4.0);
+    /// ```
+    pub fn lerp(self, frac: f64) -> f64 {
+        self.lower * (1.0 - frac) + self.upper * frac
+    }
+
+    /// Calculates the width of the interval
+    ///
+    /// ```
+    /// # use fidget::types::Interval64;
+    /// let a = Interval64::new(2.0, 3.0);
+    /// assert_eq!(a.width(), 1.0);
+    /// let b = Interval64::new(2.0, 5.0);
+    /// assert_eq!(b.width(), 3.0);
+    /// ```
+    pub fn width(self) -> f64 {
+        self.upper - self.lower
+    }
+
+    /// Least non-negative remainder
+    pub fn rem_euclid(&self, other: Interval64) -> Self {
+        if self.has_nan() || other.has_nan() || other.contains(0.0) {
+            f64::NAN.into()
+        } else {
+            // TODO optimize this
+            Interval64::new(0.0, other.abs().upper())
+        }
+    }
+
+    /// Largest value that is less-than-or-equal to this value
+    pub fn floor(&self) -> Self {
+        Interval64::new(self.lower.floor(), self.upper.floor())
+    }
+
+    /// Smallest value that is greater-than-or-equal to this value
+    pub fn ceil(&self) -> Self {
+        Interval64::new(self.lower.ceil(), self.upper.ceil())
+    }
+
+    /// Rounded value
+    pub fn round(&self) -> Self {
+        Interval64::new(self.lower.round(), self.upper.round())
+    }
+
+    /// Four-quadrant arctangent
+    ///
+    /// `self` is the `y` coordinate, and `x` is the `x` coordinate.  If the
+    /// region doesn't touch the branch cut along the negative X axis (or the
+    /// origin), `atan2` is continuous, and its extrema are at the region's
+    /// corners; otherwise, the result is `[-π, π]`.
+    pub fn atan2(self, x: Self) -> Self {
+        use std::f64::consts::PI;
+        if self.has_nan() || x.has_nan() {
+            f64::NAN.into()
+        } else if x.lower <= 0.0 && self.lower <= 0.0 && self.upper >= 0.0 {
+            // This also catches signed zeros, where atan2(±0, -0) = ±π
+            Interval64::new(-PI, PI)
+        } else {
+            let mut lower = f64::INFINITY;
+            let mut upper = f64::NEG_INFINITY;
+            for y in [self.lower, self.upper] {
+                for x in [x.lower, x.upper] {
+                    let v = y.atan2(x);
+                    lower = lower.min(v);
+                    upper = upper.max(v);
+                }
+            }
+            Self::outward(lower, upper).clamp(-PI, PI)
+        }
+    }
+
+    /// Divides by an interval, with special handling for divisors containing 0
+    ///
+    /// See [`Interval::div_extended`](super::Interval::div_extended) for
+    /// details.
+    pub fn div_extended(self, rhs: Self) -> ExtendedDiv<Interval64> {
+        let inf = f64::INFINITY;
+        if self.has_nan()
+            || rhs.has_nan()
+            || (rhs.lower == 0.0 && rhs.upper == 0.0)
+        {
+            return ExtendedDiv::Single(f64::NAN.into());
+        } else if rhs.lower > 0.0 || rhs.upper < 0.0 {
+            return ExtendedDiv::Single(self / rhs);
+        } else if self.contains(0.0) {
+            return ExtendedDiv::Single(Interval64::new(-inf, inf));
+        }
+        // `n` is the numerator bound which is closest to zero; the result is
+        // built by dividing it by the nonzero bound(s) of the divisor.
+        let n = if self.lower > 0.0 {
+            self.lower
+        } else {
+            self.upper
+        };
+        let lo = rhs.lower != 0.0;
+        let hi = rhs.upper != 0.0;
+        let half = |d: f64| {
+            if n / d < 0.0 {
+                Interval64::new(-inf, n / d)
+            } else {
+                Interval64::new(n / d, inf)
+            }
+        };
+        match (lo, hi) {
+            (true, true) => {
+                let (a, b) = (n / rhs.lower, n / rhs.upper);
+                ExtendedDiv::Split(
+                    Interval64::new(-inf, a.min(b)),
+                    Interval64::new(a.max(b), inf),
+                )
+            }
+            (true, false) => ExtendedDiv::Single(half(rhs.lower)),
+            (false, true) => ExtendedDiv::Single(half(rhs.upper)),
+            (false, false) => unreachable!(),
+        }
+    }
+}
+
+impl ExtendedDiv<Interval64> {
+    /// Returns a single interval which contains the entire result
+    ///
+    /// A [`Split`](ExtendedDiv::Split) result is widened to `(-∞, ∞)`
+    pub fn hull(self) -> Interval64 {
+        match self {
+            Self::Single(i) => i,
+            Self::Split(..) => Interval64::new(-f64::INFINITY, f64::INFINITY),
+        }
+    }
+}
+
+impl std::fmt::Display for Interval64 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.lower, self.upper)
+    }
+}
+
+impl From<[f64; 2]> for Interval64 {
+    fn from(i: [f64; 2]) -> Interval64 {
+        Interval64::new(i[0], i[1])
+    }
+}
+
+impl From<f64> for Interval64 {
+    fn from(f: f64) -> Self {
+        Interval64::new(f, f)
+    }
+}
+
+impl std::ops::Add<Interval64> for Interval64 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Interval64::new(self.lower + rhs.lower, self.upper + rhs.upper)
+    }
+}
+
+impl std::ops::Mul<Interval64> for Interval64 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        if self.has_nan() || rhs.has_nan() {
+            return f64::NAN.into();
+        }
+        let mut out = [0.0; 4];
+        let mut k = 0;
+        for i in [self.lower, self.upper] {
+            for j in [rhs.lower, rhs.upper] {
+                out[k] = i * j;
+                k += 1;
+            }
+        }
+        let mut lower = out[0];
+        let mut upper = out[0];
+        for &v in &out[1..] {
+            lower = lower.min(v);
+            upper = upper.max(v);
+        }
+        Interval64::new(lower, upper)
+    }
+}
+
+impl std::ops::Mul<f64> for Interval64 {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self {
+        if self.has_nan() || rhs.is_nan() {
+            f64::NAN.into()
+        } else if rhs < 0.0 {
+            Interval64::new(self.upper * rhs, self.lower * rhs)
+        } else {
+            Interval64::new(self.lower * rhs, self.upper * rhs)
+        }
+    }
+}
+
+impl std::ops::Div<Interval64> for Interval64 {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        if self.has_nan() {
+            return f64::NAN.into();
+        }
+        if rhs.lower > 0.0 || rhs.upper < 0.0 {
+            let mut out = [0.0; 4];
+            let mut k = 0;
+            for i in [self.lower, self.upper] {
+                for j in [rhs.lower, rhs.upper] {
+                    out[k] = i / j;
+                    k += 1;
+                }
+            }
+            let mut lower = out[0];
+            let mut upper = out[0];
+            for &v in &out[1..] {
+                lower = lower.min(v);
+                upper = upper.max(v);
+            }
+            Interval64::new(lower, upper)
+        } else {
+            f64::NAN.into()
+        }
+    }
+}
+
+impl std::ops::Sub<Interval64> for Interval64 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Interval64::new(self.lower - rhs.upper, self.upper - rhs.lower)
+    }
+}
+
+impl std::ops::Neg for Interval64 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Interval64::new(-self.upper, -self.lower)
+    }
+}
+
+impl From<[f32; 2]> for Interval64 {
+    fn from(i: [f32; 2]) -> Interval64 {
+        Interval64::new(i[0] as f64, i[1] as f64)
+    }
+}
+
+impl From<f32> for Interval64 {
+    fn from(f: f32) -> Self {
+        Interval64::from(f as f64)
+    }
+}
+
+impl From<super::Interval> for Interval64 {
+    fn from(i: super::Interval) -> Self {
+        Interval64::new(i.lower() as f64, i.upper() as f64)
+    }
+}
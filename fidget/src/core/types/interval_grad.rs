@@ -0,0 +1,375 @@
+use super::Interval;
+use crate::vm::Choice;
+
+/// An interval with associated bounds on its partial derivatives
+///
+/// This is the interval equivalent of [`Grad`](super::Grad): each field is an
+/// interval which contains every value (or partial derivative) of the
+/// function over some region of space.
+///
+/// Discontinuous operations (`floor`, `ceil`, `round`, `compare`, `not`) have
+/// a derivative of zero, matching [`Grad`](super::Grad); their jumps are not
+/// included in the derivative bounds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IntervalGrad {
+    /// Bounds on the value of the function
+    pub v: Interval,
+    /// Bounds on the partial derivative with respect to `x`
+    pub dx: Interval,
+    /// Bounds on the partial derivative with respect to `y`
+    pub dy: Interval,
+    /// Bounds on the partial derivative with respect to `z`
+    pub dz: Interval,
+}
+
+impl std::fmt::Display for IntervalGrad {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {}, {}, {})", self.v, self.dx, self.dy, self.dz)
+    }
+}
+
+impl IntervalGrad {
+    /// Constructs a new interval gradient
+    pub fn new(v: Interval, dx: Interval, dy: Interval, dz: Interval) -> Self {
+        Self { v, dx, dy, dz }
+    }
+
+    /// Looks up a partial derivative by index (0 = x, 1 = y, 2 = z)
+    ///
+    /// # Panics
+    /// If the index is not in the 0-2 range
+    pub fn d(&self, i: usize) -> Interval {
+        match i {
+            0 => self.dx,
+            1 => self.dy,
+            2 => self.dz,
+            _ => panic!("invalid index {i}"),
+        }
+    }
+
+    /// Checks whether the function is monotonic along the given axis
+    ///
+    /// Returns `Some(true)` if it is non-decreasing, `Some(false)` if it is
+    /// non-increasing, and `None` if neither can be proven.
+    pub fn monotonic(&self, axis: usize) -> Option<bool> {
+        let d = self.d(axis);
+        if d.lower() >= 0.0 {
+            Some(true)
+        } else if d.upper() <= 0.0 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Returns an upper bound on the magnitude of the gradient
+    ///
+    /// This is a Lipschitz bound for the function over the region (e.g. for
+    /// picking a safe step size when sphere tracing), or `NaN` if the bound is
+    /// unknown.
+    pub fn lipschitz(&self) -> f32 {
+        let m = |i: Interval| i.abs().upper();
+        (m(self.dx).powi(2) + m(self.dy).powi(2) + m(self.dz).powi(2)).sqrt()
+    }
+
+    /// Applies `f` to each partial derivative
+    fn map_d<F: Fn(Interval) -> Interval>(self, v: Interval, f: F) -> Self {
+        Self {
+            v,
+            dx: f(self.dx),
+            dy: f(self.dy),
+            dz: f(self.dz),
+        }
+    }
+
+    /// Chain rule, given the derivative of the outer function
+    fn chain(self, v: Interval, d: Interval) -> Self {
+        self.map_d(v, |i| i * d)
+    }
+
+    /// Absolute value
+    pub fn abs(self) -> Self {
+        if self.v.lower() >= 0.0 {
+            self
+        } else if self.v.upper() <= 0.0 {
+            -self
+        } else {
+            self.chain(self.v.abs(), Interval::new(-1.0, 1.0))
+        }
+    }
+
+    /// Square
+    pub fn square(self) -> Self {
+        self.chain(self.v.square(), self.v * 2.0)
+    }
+
+    /// Square root
+    pub fn sqrt(self) -> Self {
+        let v = self.v.sqrt();
+        self.map_d(v, |i| i / (v * 2.0))
+    }
+
+    /// Reciprocal
+    pub fn recip(self) -> Self {
+        let d = -self.v.square();
+        self.map_d(self.v.recip(), |i| i / d)
+    }
+
+    /// Sine
+    pub fn sin(self) -> Self {
+        self.chain(self.v.sin(), self.v.cos())
+    }
+
+    /// Cosine
+    pub fn cos(self) -> Self {
+        self.chain(self.v.cos(), -self.v.sin())
+    }
+
+    /// Tangent
+    pub fn tan(self) -> Self {
+        let c = self.v.cos().square();
+        self.map_d(self.v.tan(), |i| i / c)
+    }
+
+    /// Arcsin
+    pub fn asin(self) -> Self {
+        let r = (Interval::from(1.0) - self.v.square()).sqrt();
+        self.map_d(self.v.asin(), |i| i / r)
+    }
+
+    /// Arccos
+    pub fn acos(self) -> Self {
+        let r = (Interval::from(1.0) - self.v.square()).sqrt();
+        self.map_d(self.v.acos(), |i| -(i / r))
+    }
+
+    /// Arctangent
+    pub fn atan(self) -> Self {
+        let r = self.v.square() + Interval::from(1.0);
+        self.map_d(self.v.atan(), |i| i / r)
+    }
+
+    /// Exponential function
+    pub fn exp(self) -> Self {
+        let v = self.v.exp();
+        self.chain(v, v)
+    }
+
+    /// Natural log
+    pub fn ln(self) -> Self {
+        self.map_d(self.v.ln(), |i| i / self.v)
+    }
+
+    /// Largest value that is less-than-or-equal to this value
+    pub fn floor(self) -> Self {
+        Self::from(self.v.floor())
+    }
+
+    /// Smallest value that is greater-than-or-equal to this value
+    pub fn ceil(self) -> Self {
+        Self::from(self.v.ceil())
+    }
+
+    /// Rounded value
+    pub fn round(self) -> Self {
+        Self::from(self.v.round())
+    }
+
+    /// Least non-negative remainder
+    pub fn rem_euclid(self, rhs: Self) -> Self {
+        // The quotient is `floor(a / b)` or `ceil(a / b)` (depending on the
+        // sign of `b`), so we use the union of both.
+        let q = self.v / rhs.v;
+        let e = if q.has_nan() {
+            q
+        } else {
+            Interval::new(q.lower().floor(), q.upper().ceil())
+        };
+        Self {
+            v: self.v.rem_euclid(rhs.v),
+            dx: self.dx - rhs.dx * e,
+            dy: self.dy - rhs.dy * e,
+            dz: self.dz - rhs.dz * e,
+        }
+    }
+
+    /// Four-quadrant arctangent
+    pub fn atan2(self, x: Self) -> Self {
+        let y = self;
+        let d = x.v.square() + y.v.square();
+        Self {
+            v: y.v.atan2(x.v),
+            dx: (x.v * y.dx - y.v * x.dx) / d,
+            dy: (x.v * y.dy - y.v * x.dy) / d,
+            dz: (x.v * y.dz - y.v * x.dz) / d,
+        }
+    }
+
+    /// Picks derivatives based on a [`Choice`]
+    fn select(self, rhs: Self, v: Interval, choice: Choice) -> Self {
+        match choice {
+            Choice::Left => Self { v, ..self },
+            Choice::Right => Self { v, ..rhs },
+            _ => Self {
+                v,
+                dx: hull(self.dx, rhs.dx),
+                dy: hull(self.dy, rhs.dy),
+                dz: hull(self.dz, rhs.dz),
+            },
+        }
+    }
+
+    /// Calculates the minimum of two values
+    ///
+    /// If either side may be chosen, the derivatives are the union of both
+    /// sides' derivatives.
+    pub fn min_choice(self, rhs: Self) -> (Self, Choice) {
+        let (v, choice) = self.v.min_choice(rhs.v);
+        (self.select(rhs, v, choice), choice)
+    }
+
+    /// Calculates the maximum of two values
+    ///
+    /// If either side may be chosen, the derivatives are the union of both
+    /// sides' derivatives.
+    pub fn max_choice(self, rhs: Self) -> (Self, Choice) {
+        let (v, choice) = self.v.max_choice(rhs.v);
+        (self.select(rhs, v, choice), choice)
+    }
+
+    /// Logical and, with the same choice semantics as [`Interval::and_choice`]
+    pub fn and_choice(self, rhs: Self) -> (Self, Choice) {
+        let (v, choice) = self.v.and_choice(rhs.v);
+        (self.select(rhs, v, choice), choice)
+    }
+
+    /// Logical or, with the same choice semantics as [`Interval::or_choice`]
+    pub fn or_choice(self, rhs: Self) -> (Self, Choice) {
+        let (v, choice) = self.v.or_choice(rhs.v);
+        (self.select(rhs, v, choice), choice)
+    }
+
+    /// Divides by an interval gradient, allowing divisors which contain 0
+    ///
+    /// The value and each partial derivative are the
+    /// [hull](super::ExtendedDiv::hull) of the corresponding
+    /// [`Interval::div_extended`]; if the divisor does not contain 0, this is
+    /// the same as the `/` operator.
+    ///
+    /// A partial derivative whose numerator (in the quotient rule) is exactly
+    /// zero stays zero, e.g. `1 / x` has a `y` derivative of zero even if `x`
+    /// contains 0.
+    pub fn div_extended(self, rhs: Self) -> Self {
+        let d = rhs.v.square();
+        let zero = Interval::from(0.0);
+        let f = |n: Interval| {
+            if n == zero {
+                n
+            } else {
+                n.div_extended(d).hull()
+            }
+        };
+        Self {
+            v: self.v.div_extended(rhs.v).hull(),
+            dx: f(rhs.v * self.dx - self.v * rhs.dx),
+            dy: f(rhs.v * self.dy - self.v * rhs.dy),
+            dz: f(rhs.v * self.dz - self.v * rhs.dz),
+        }
+    }
+}
+
+/// Returns the smallest interval containing both inputs
+fn hull(a: Interval, b: Interval) -> Interval {
+    if a.has_nan() || b.has_nan() {
+        f32::NAN.into()
+    } else {
+        Interval::new(a.lower().min(b.lower()), a.upper().max(b.upper()))
+    }
+}
+
+impl From<f32> for IntervalGrad {
+    fn from(v: f32) -> Self {
+        Self::from(Interval::from(v))
+    }
+}
+
+/// Builds a constant value, with derivatives of zero
+impl From<Interval> for IntervalGrad {
+    fn from(v: Interval) -> Self {
+        let zero = Interval::from(0.0);
+        Self {
+            v,
+            dx: zero,
+            dy: zero,
+            dz: zero,
+        }
+    }
+}
+
+/// Builds a constant value, with derivatives of zero
+impl From<[f32; 2]> for IntervalGrad {
+    fn from(v: [f32; 2]) -> Self {
+        Self::from(Interval::from(v))
+    }
+}
+
+impl std::ops::Add<IntervalGrad> for IntervalGrad {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            v: self.v + rhs.v,
+            dx: self.dx + rhs.dx,
+            dy: self.dy + rhs.dy,
+            dz: self.dz + rhs.dz,
+        }
+    }
+}
+
+impl std::ops::Sub<IntervalGrad> for IntervalGrad {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            v: self.v - rhs.v,
+            dx: self.dx - rhs.dx,
+            dy: self.dy - rhs.dy,
+            dz: self.dz - rhs.dz,
+        }
+    }
+}
+
+impl std::ops::Mul<IntervalGrad> for IntervalGrad {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            v: self.v * rhs.v,
+            dx: self.v * rhs.dx + rhs.v * self.dx,
+            dy: self.v * rhs.dy + rhs.v * self.dy,
+            dz: self.v * rhs.dz + rhs.v * self.dz,
+        }
+    }
+}
+
+impl std::ops::Div<IntervalGrad> for IntervalGrad {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        let d = rhs.v.square();
+        Self {
+            v: self.v / rhs.v,
+            dx: (rhs.v * self.dx - self.v * rhs.dx) / d,
+            dy: (rhs.v * self.dy - self.v * rhs.dy) / d,
+            dz: (rhs.v * self.dz - self.v * rhs.dz) / d,
+        }
+    }
+}
+
+impl std::ops::Neg for IntervalGrad {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            v: -self.v,
+            dx: -self.dx,
+            dy: -self.dy,
+            dz: -self.dz,
+        }
+    }
+}
@@ -1,6 +1,16 @@
 //! Custom types used during evaluation
 
+mod dual;
 mod grad;
+mod grad64;
 mod interval;
+mod interval64;
+mod interval_grad;
+mod units;
+pub use dual::Dual;
 pub use grad::Grad;
-pub use interval::Interval;
+pub use grad64::Grad64;
+pub use interval::{ExtendedDiv, Interval};
+pub use interval64::Interval64;
+pub use interval_grad::IntervalGrad;
+pub use units::{Degrees, Length, Radians};
@@ -0,0 +1,79 @@
+//! Unit-tagged scalars for angles and lengths
+//!
+//! APIs which take an angle accept `impl Into<Radians>`, so either unit can be
+//! passed without a manual conversion (and a bare `f64` is rejected at compile
+//! time).  The inner value is public, so `Radians(x)` is the escape hatch for
+//! an angle which is already a raw number in radians.
+
+/// An angle in radians
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Radians(pub f64);
+
+/// An angle in degrees
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Degrees(pub f64);
+
+/// A distance in model units
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Length(pub f64);
+
+impl From<Degrees> for Radians {
+    fn from(d: Degrees) -> Self {
+        Radians(d.0.to_radians())
+    }
+}
+
+impl From<Radians> for Degrees {
+    fn from(r: Radians) -> Self {
+        Degrees(r.0.to_degrees())
+    }
+}
+
+impl Radians {
+    /// Returns the raw value, in radians
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl Degrees {
+    /// Returns the raw value, in degrees
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl Length {
+    /// Returns the raw value, in model units
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl std::ops::Neg for Radians {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Radians(-self.0)
+    }
+}
+
+impl std::ops::Neg for Degrees {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Degrees(-self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn angle_conversions() {
+        let r: Radians = Degrees(180.0).into();
+        assert_eq!(r, Radians(std::f64::consts::PI));
+        let d: Degrees = Radians(std::f64::consts::FRAC_PI_2).into();
+        assert!((d.get() - 90.0).abs() < 1e-12);
+        assert_eq!(-Degrees(30.0), Degrees(-30.0));
+    }
+}
@@ -28,18 +28,24 @@ pub enum Choice {
     Both = 3,
 }
 
-impl std::ops::BitOrAssign<Choice> for Choice {
-    fn bitor_assign(&mut self, other: Self) {
-        *self = match (*self as u8) | (other as u8) {
+impl Choice {
+    /// Converts from the low two bits of a `u8`
+    const fn from_bits(b: u8) -> Self {
+        match b & 0b11 {
             0 => Self::Unknown,
             1 => Self::Left,
             2 => Self::Right,
-            3 => Self::Both,
-            _ => unreachable!(),
+            _ => Self::Both,
         }
     }
 }
 
+impl std::ops::BitOrAssign<Choice> for Choice {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = Self::from_bits((*self as u8) | (other as u8));
+    }
+}
+
 impl std::ops::Not for Choice {
     type Output = Choice;
     fn not(self) -> Self {
@@ -54,12 +60,225 @@ impl std::ops::Not for Choice {
 
 impl std::ops::BitAndAssign<Choice> for Choice {
     fn bitand_assign(&mut self, other: Self) {
-        *self = match (*self as u8) | ((!other as u8) & 0b11) {
-            0 => Self::Unknown,
-            1 => Self::Left,
-            2 => Self::Right,
-            3 => Self::Both,
-            _ => unreachable!(),
+        *self = Self::from_bits((*self as u8) | (!other as u8));
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Array of [`Choice`] values, packed into 2 bits apiece
+///
+/// Shapes with many `min` / `max` clauses produce long traces, which are
+/// stored for every tile during rendering; packing them (rather than storing
+/// one byte per choice) keeps them cache-friendly.
+///
+/// Unused bits in the final byte are always zero, so two arrays with the same
+/// choices compare equal.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct PackedChoices {
+    data: Vec<u8>,
+    len: usize,
+}
+
+impl PackedChoices {
+    /// Builds a new empty array
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of choices in the array
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether the array is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the choice at the given index
+    ///
+    /// # Panics
+    /// If the index is out of bounds
+    #[inline]
+    pub fn get(&self, i: usize) -> Choice {
+        assert!(i < self.len, "index {i} out of bounds ({})", self.len);
+        Choice::from_bits(self.data[i / 4] >> ((i % 4) * 2))
+    }
+
+    /// Sets the choice at the given index
+    ///
+    /// # Panics
+    /// If the index is out of bounds
+    #[inline]
+    pub fn set(&mut self, i: usize, c: Choice) {
+        assert!(i < self.len, "index {i} out of bounds ({})", self.len);
+        let shift = (i % 4) * 2;
+        let b = &mut self.data[i / 4];
+        *b = (*b & !(0b11 << shift)) | ((c as u8) << shift);
+    }
+
+    /// Accumulates a choice at the given index, i.e. `self[i] |= c`
+    ///
+    /// # Panics
+    /// If the index is out of bounds
+    #[inline]
+    pub fn or(&mut self, i: usize, c: Choice) {
+        assert!(i < self.len, "index {i} out of bounds ({})", self.len);
+        self.data[i / 4] |= (c as u8) << ((i % 4) * 2);
+    }
+
+    /// Fills the array with the given value
+    pub fn fill(&mut self, c: Choice) {
+        self.data.fill((c as u8) * 0b01010101);
+        self.clear_tail();
+    }
+
+    /// Resizes the array, using the new value if it needs to be extended
+    pub fn resize(&mut self, n: usize, c: Choice) {
+        let prev = self.len;
+        self.data.resize(n.div_ceil(4), 0);
+        self.len = n;
+        for i in prev..n {
+            self.set(i, c);
         }
+        self.clear_tail();
+    }
+
+    /// Replaces the contents of this array with an unpacked slice
+    pub fn copy_from_slice(&mut self, choices: &[Choice]) {
+        self.data.clear();
+        self.data.extend(choices.chunks(4).map(|c| {
+            c.iter()
+                .enumerate()
+                .fold(0, |b, (i, c)| b | ((*c as u8) << (i * 2)))
+        }));
+        self.len = choices.len();
+    }
+
+    /// Iterates over choices in the array
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = Choice> + ExactSizeIterator + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+
+    /// Returns the packed bytes, with the first choice in the low bits
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns a cursor which accumulates choices in order
+    pub(crate) fn cursor(&mut self) -> ChoiceCursor<'_> {
+        ChoiceCursor {
+            choices: self,
+            index: 0,
+        }
+    }
+
+    /// Zeroes unused bits in the final byte
+    fn clear_tail(&mut self) {
+        if !self.len.is_multiple_of(4) {
+            if let Some(b) = self.data.last_mut() {
+                *b &= (1 << ((self.len % 4) * 2)) - 1;
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for PackedChoices {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<Choice> for PackedChoices {
+    fn from_iter<I: IntoIterator<Item = Choice>>(iter: I) -> Self {
+        let mut out = Self::new();
+        for c in iter {
+            out.resize(out.len + 1, c);
+        }
+        out
+    }
+}
+
+impl From<&[Choice]> for PackedChoices {
+    fn from(choices: &[Choice]) -> Self {
+        let mut out = Self::new();
+        out.copy_from_slice(choices);
+        out
+    }
+}
+
+impl PartialEq<[Choice]> for PackedChoices {
+    fn eq(&self, other: &[Choice]) -> bool {
+        self.iter().eq(other.iter().cloned())
+    }
+}
+
+impl<const N: usize> PartialEq<[Choice; N]> for PackedChoices {
+    fn eq(&self, other: &[Choice; N]) -> bool {
+        self == other.as_slice()
+    }
+}
+
+/// Writes choices into a [`PackedChoices`] array in order
+///
+/// This is used by tracing evaluators, which encounter choices in tape order.
+pub(crate) struct ChoiceCursor<'a> {
+    choices: &'a mut PackedChoices,
+    index: usize,
+}
+
+impl ChoiceCursor<'_> {
+    /// Accumulates a choice into the next slot, then advances the cursor
+    #[inline]
+    pub fn push(&mut self, c: Choice) {
+        self.choices.or(self.index, c);
+        self.index += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn packed_choices() {
+        let mut p = PackedChoices::new();
+        assert!(p.is_empty());
+        p.resize(6, Choice::Left);
+        assert_eq!(p, [Choice::Left; 6]);
+        assert_eq!(p.as_bytes(), &[0b01010101, 0b0101]);
+
+        p.set(1, Choice::Right);
+        p.or(4, Choice::Right);
+        assert_eq!(
+            p,
+            [
+                Choice::Left,
+                Choice::Right,
+                Choice::Left,
+                Choice::Left,
+                Choice::Both,
+                Choice::Left
+            ]
+        );
+        assert_eq!(p.iter().next_back(), Some(Choice::Left));
+
+        p.resize(3, Choice::Unknown);
+        assert_eq!(p.as_bytes(), &[0b011001]);
+        p.fill(Choice::Both);
+        assert_eq!(p.as_bytes(), &[0b111111]);
+
+        let q = PackedChoices::from([Choice::Both; 3].as_slice());
+        assert_eq!(p, q);
+        let r: PackedChoices = [Choice::Both; 3].into_iter().collect();
+        assert_eq!(p, r);
+
+        let mut c = p.cursor();
+        c.push(Choice::Left);
+        c.push(Choice::Unknown);
+        assert_eq!(p, [Choice::Both; 3]);
     }
 }
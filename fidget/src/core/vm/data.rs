@@ -1,8 +1,12 @@
 //! General-purpose tapes for use during evaluation or further compilation
 use crate::{
-    compiler::{RegOp, RegTape, RegisterAllocator, SsaOp, SsaTape},
+    compiler::{
+        Backend, Flavor, Peephole, RegOp, RegTape, RegisterAllocator, SsaOp,
+        SsaTape, TapeMetrics,
+    },
     context::{Context, Node},
-    vm::Choice,
+    eval::Vars,
+    vm::{Choice, PackedChoices},
     Error,
 };
 use serde::{Deserialize, Serialize};
@@ -58,20 +62,179 @@ use serde::{Deserialize, Serialize};
 /// Despite this peek at its internals, users are unlikely to touch `VmData`
 /// directly; a [`VmShape`](crate::vm::VmShape) wraps the `VmData` and
 /// implements our common traits.
-#[derive(Default, Serialize, Deserialize)]
+///
+/// # Serialization
+/// `VmData` implements [`Serialize`] and [`Deserialize`], so a planned tape
+/// can be cached on disk or sent to another process without rebuilding it
+/// from a [`Context`].  The register count `N` is not stored, so data must be
+/// deserialized with the same `N` that was used to build it.
+///
+/// Deserialization checks that the tapes are well-formed (e.g. that every
+/// register is below `N`), returning an error if they are not:
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::{EzShape, Shape, TracingEvaluator},
+///     vm::{VmData, VmShape},
+///     Context,
+/// };
+///
+/// let mut ctx = Context::new();
+/// let sum = ctx.import(&(Tree::x() + Tree::y()));
+/// let data = VmData::<255>::new(&ctx, sum)?;
+/// let bytes = serde_json::to_vec(&data).unwrap();
+///
+/// let data: VmData<255> = serde_json::from_slice(&bytes).unwrap();
+/// let shape = VmShape::from(data);
+/// let mut eval = VmShape::new_point_eval();
+/// let (v, _) = eval.eval(&shape.ez_point_tape(), 1.0, 2.0, 0.0)?;
+/// assert_eq!(v, 3.0);
+///
+/// // This tape uses two registers, so it can't be loaded as a one-register
+/// // tape
+/// assert!(serde_json::from_slice::<VmData<1>>(&bytes).is_err());
+/// # Ok::<(), fidget::Error>(())
+/// ```
+#[derive(Default, Serialize)]
 pub struct VmData<const N: usize = { u8::MAX as usize }> {
     ssa: SsaTape,
     asm: RegTape,
 }
 
+impl<'de, const N: usize> Deserialize<'de> for VmData<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        d: D,
+    ) -> Result<Self, D::Error> {
+        /// Unchecked tape data, which is validated before use
+        #[derive(Deserialize)]
+        struct RawVmData {
+            ssa: SsaTape,
+            asm: RegTape,
+        }
+        let RawVmData { ssa, asm } = RawVmData::deserialize(d)?;
+        ssa.check().map_err(serde::de::Error::custom)?;
+        asm.check::<N>().map_err(serde::de::Error::custom)?;
+        Ok(Self { ssa, asm })
+    }
+}
+
 impl<const N: usize> VmData<N> {
     /// Builds a new tape for the given node
     pub fn new(context: &Context, node: Node) -> Result<Self, Error> {
-        let ssa = SsaTape::new(context, node)?;
+        let mut ssa = SsaTape::new(context, node)?;
+        Peephole::new().run(&mut ssa);
         let asm = RegTape::new::<N>(&ssa);
         Ok(Self { ssa, asm })
     }
 
+    /// Builds a new tape for the given node, with an explicit list of inputs
+    ///
+    /// See [`SsaTape::new_with_inputs`] for details.
+    pub fn new_with_inputs(
+        context: &Context,
+        node: Node,
+        inputs: &[&str],
+    ) -> Result<Self, Error> {
+        let mut ssa = SsaTape::new_with_inputs(context, node, inputs)?;
+        Peephole::new().run(&mut ssa);
+        let asm = RegTape::new::<N>(&ssa);
+        Ok(Self { ssa, asm })
+    }
+
+    /// Builds a tape from an SSA tape, allocating registers
+    ///
+    /// The SSA tape is used as-is (without further optimization), so it should
+    /// come from another [`VmData`] (see [`VmData::ssa`]) or from an
+    /// [`SsaTapeBuilder`](crate::compiler::SsaTapeBuilder).
+    pub fn from_ssa(ssa: SsaTape) -> Self {
+        let asm = RegTape::new::<N>(&ssa);
+        Self { ssa, asm }
+    }
+
+    /// Returns the SSA tape from which this tape was planned
+    pub fn ssa(&self) -> &SsaTape {
+        &self.ssa
+    }
+
+    /// Re-plans this tape with a different register limit
+    ///
+    /// This reuses the (already optimized) SSA tape, so only register
+    /// allocation is repeated.
+    ///
+    /// ```
+    /// use fidget::{context::Tree, vm::VmData, Context};
+    ///
+    /// let (x, y, z) = Tree::axes();
+    /// let mut ctx = Context::new();
+    /// let root = ctx.import(&((x.clone() + y.clone()) * (y * z) + x));
+    /// let data = VmData::<255>::new(&ctx, root)?;
+    /// let small = data.convert::<3>();
+    ///
+    /// // Fewer registers means that values are spilled to memory
+    /// assert!(small.len() > data.len());
+    /// assert_eq!(small.ssa().tape.len(), data.ssa().tape.len());
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn convert<const M: usize>(&self) -> VmData<M> {
+        VmData::from_ssa(self.ssa.clone())
+    }
+
+    /// Builds a copy of this tape with `Z` fixed to the given value
+    ///
+    /// Reads from `Z` are replaced with the constant `z`, which is then folded
+    /// into its users (see [`Peephole`]).  The resulting tape has two inputs,
+    /// `X` and `Y`, so it needs one fewer register for its inputs.
+    ///
+    /// Returns `None` if the tape was built with named inputs (see
+    /// [`VmData::new_with_inputs`]).
+    #[doc(alias = "slice_z")]
+    pub fn plan_2d(&self, z: f32) -> Option<Self> {
+        if self.ssa.inputs.is_some() {
+            return None;
+        }
+        let mut ssa = self.substitute(2, z);
+        ssa.inputs = Some(["X".into(), "Y".into()].into());
+        let asm = RegTape::new::<N>(&ssa);
+        Some(Self { ssa, asm })
+    }
+
+    /// Builds a copy of this tape with the given input frozen to a constant
+    ///
+    /// `var` is the name of an input: one of `X`, `Y`, or `Z` for tapes built
+    /// with [`VmData::new`], or one of the names passed to
+    /// [`VmData::new_with_inputs`].  Reads from that input are replaced with
+    /// `value`, which is then folded into its users (see [`Peephole`]), and
+    /// registers are re-allocated for the (usually shorter) tape.
+    ///
+    /// The input keeps its slot, so the new tape is evaluated with the same
+    /// arguments as the original (and ignores the frozen input's value).
+    ///
+    /// Returns [`Error::UnknownVariable`] if `var` is not an input.
+    pub fn specialize(&self, var: &str, value: f32) -> Result<Self, Error> {
+        let i = match &self.ssa.inputs {
+            Some(names) => names.iter().position(|n| &**n == var),
+            None => ["X", "Y", "Z"].iter().position(|n| *n == var),
+        }
+        .ok_or_else(|| Error::UnknownVariable(var.to_owned()))?;
+        let ssa = self.substitute(i as u32, value);
+        let asm = RegTape::new::<N>(&ssa);
+        Ok(Self { ssa, asm })
+    }
+
+    /// Returns an optimized SSA tape with input `i` replaced by `value`
+    fn substitute(&self, i: u32, value: f32) -> SsaTape {
+        let mut ssa = self.ssa.clone();
+        for op in ssa.tape.iter_mut() {
+            if let SsaOp::Input(out, j) = *op {
+                if j == i {
+                    *op = SsaOp::CopyImm(out, value);
+                }
+            }
+        }
+        Peephole::new().run(&mut ssa);
+        ssa
+    }
+
     /// Returns the length of the internal VM tape
     pub fn len(&self) -> usize {
         self.asm.len()
@@ -100,13 +263,74 @@ impl<const N: usize> VmData<N> {
         self.asm.var_count()
     }
 
+    /// Describes the variable slots of the inner VM tape
+    ///
+    /// A slot is marked as used if the tape contains an input operation that
+    /// reads from it.
+    pub fn vars(&self) -> Vars {
+        let mut used = vec![false; self.ssa.input_count()];
+        for op in self.asm.iter() {
+            if let RegOp::Input(_, i) = op {
+                if let Some(u) = used.get_mut(*i as usize) {
+                    *u = true;
+                }
+            }
+        }
+        match &self.ssa.inputs {
+            Some(names) => Vars::with_names(names.clone(), used),
+            None => Vars::new(used.try_into().unwrap()),
+        }
+    }
+
+    /// Returns the Cartesian axis (0-2) of the given input slot, if any
+    ///
+    /// Inputs are matched to axes by name, so this is `None` for slots other
+    /// than `X`, `Y`, and `Z` (or if the index is out of range).
+    pub(crate) fn input_axis(&self, i: usize) -> Option<usize> {
+        match &self.ssa.inputs {
+            Some(names) => {
+                let name = names.get(i)?;
+                ["X", "Y", "Z"].iter().position(|a| *a == &**name)
+            }
+            None => (i < 3).then_some(i),
+        }
+    }
+
+    /// Estimates the cost of evaluating the inner VM tape
+    ///
+    /// See [`RegTape::estimated_cost`] for details.
+    pub fn estimated_cost(&self, flavor: Flavor, backend: Backend) -> f64 {
+        self.asm.estimated_cost(flavor, backend)
+    }
+
+    /// Computes summary metrics for the inner VM tape
+    ///
+    /// See [`RegTape::metrics`] for details.
+    pub fn metrics(&self) -> TapeMetrics {
+        self.asm.metrics()
+    }
+
     /// Simplifies both inner tapes, using the provided choice array
     ///
+    /// The simplified SSA tape is cleaned up with a [`Peephole`] optimizer
+    /// before register allocation.
+    ///
     /// To minimize allocations, this function takes a [`VmWorkspace`] and
     /// spare [`VmData`]; it will reuse those allocations.
+    ///
+    /// # Allocation
+    /// Once the workspace and spare tape have grown large enough (i.e. after
+    /// they've been used to simplify this tape once), simplification performs
+    /// no heap allocation.  Input names (from [`VmData::new_with_inputs`]) are
+    /// shared with the new tape, and slot names (from [`Context::define`]) are
+    /// reference-counted rather than copied, though the map of slot names is
+    /// rebuilt if the tape has any.  The same is true for
+    /// [`Shape::simplify`](crate::eval::Shape::simplify) on VM and JIT shapes,
+    /// if the storage is recycled from a previous shape (the shape's `Arc` is
+    /// reused as well).
     pub fn simplify(
         &self,
-        choices: &[Choice],
+        choices: &PackedChoices,
         workspace: &mut VmWorkspace<N>,
         mut tape: VmData<N>,
     ) -> Result<Self, Error> {
@@ -273,20 +497,33 @@ impl<const N: usize> VmData<N> {
                     *arg = workspace.get_or_insert_active(*arg);
                 }
             }
-            workspace.alloc.op(op);
             ops_out.push(op);
         }
 
         assert_eq!(workspace.count as usize, ops_out.len());
-        let asm_tape = workspace.alloc.finalize();
-
-        Ok(VmData {
-            ssa: SsaTape {
-                tape: ops_out,
-                choice_count,
-            },
-            asm: asm_tape,
-        })
+
+        // Carry over names for any slots which survived simplification
+        let mut names = tape.ssa.names;
+        names.extend(self.ssa.names.iter().filter_map(|(i, name)| {
+            workspace.active(*i).map(|j| (j, name.clone()))
+        }));
+        let inputs = self.ssa.inputs.clone();
+
+        // Simplification often leaves behind copies and negations which can
+        // be folded into their users, so clean up before register allocation
+        let mut ssa = SsaTape {
+            tape: ops_out,
+            choice_count,
+            names,
+            inputs,
+        };
+        workspace.peephole.run(&mut ssa);
+        for &op in ssa.iter() {
+            workspace.alloc.op(op);
+        }
+        let asm = workspace.alloc.finalize();
+
+        Ok(VmData { ssa, asm })
     }
 
     /// Produces an iterator that visits [`RegOp`] values in evaluation order
@@ -312,6 +549,9 @@ pub struct VmWorkspace<const N: usize> {
     /// Register allocator
     pub(crate) alloc: RegisterAllocator<N>,
 
+    /// Peephole optimizer, applied before register allocation
+    pub(crate) peephole: Peephole,
+
     /// Current bindings from SSA variables to registers
     pub(crate) bind: Vec<u32>,
 
@@ -326,6 +566,7 @@ impl<const N: usize> Default for VmWorkspace<N> {
     fn default() -> Self {
         Self {
             alloc: RegisterAllocator::empty(),
+            peephole: Peephole::new(),
             bind: vec![],
             count: 0,
         }
@@ -0,0 +1,294 @@
+//! Step-by-step debugger for VM point evaluation
+use super::{check_vars, point_op, Choice, GenericVmShape, SlotArray};
+use crate::{compiler::RegOp, Error};
+use std::collections::BTreeSet;
+
+/// Step-by-step debugger for VM point evaluation
+///
+/// The debugger executes the same clauses as [`VmPointEval`](super::VmPointEval)
+/// (with the same semantics), but one at a time, so that the register file can
+/// be inspected between clauses.  Clauses are numbered in execution order,
+/// starting from 0.
+///
+/// ```
+/// use fidget::{context::Tree, eval::MathShape, vm::{VmDebugger, VmShape}};
+///
+/// let (x, y, _z) = Tree::axes();
+/// let shape = VmShape::from_tree(&(x.square() + y));
+/// let mut dbg = VmDebugger::new(&shape, &[2.0, 1.0, 0.0])?;
+///
+/// // Stop before the final clause
+/// dbg.set_breakpoint(dbg.len() - 1);
+/// assert_eq!(dbg.resume(), Some(dbg.len() - 1));
+/// assert!(dbg.registers().contains(&4.0));
+///
+/// dbg.step();
+/// assert_eq!(dbg.value(), Some(5.0));
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub struct VmDebugger<const N: usize> {
+    ops: Vec<RegOp>,
+    var_count: usize,
+    vars: Vec<f32>,
+    slots: Vec<f32>,
+    choices: Vec<Choice>,
+    last_choice: Option<Choice>,
+    pc: usize,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl<const N: usize> VmDebugger<N> {
+    /// Builds a debugger for the given shape, stopped before its first clause
+    ///
+    /// `vars` are the input values, in the same order as for
+    /// [`eval_vars`](crate::eval::TracingEvaluator::eval_vars).
+    pub fn new(shape: &GenericVmShape<N>, vars: &[f32]) -> Result<Self, Error> {
+        let tape = shape.data();
+        check_vars(vars, tape.var_count())?;
+        Ok(Self {
+            ops: tape.iter_asm().collect(),
+            var_count: tape.var_count(),
+            vars: vars.to_vec(),
+            slots: vec![f32::NAN; tape.slot_count()],
+            choices: vec![],
+            last_choice: None,
+            pc: 0,
+            breakpoints: BTreeSet::new(),
+        })
+    }
+
+    /// Returns the number of clauses in the tape
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Checks whether the tape is empty
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Returns the index of the next clause to be executed
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Returns the given clause
+    pub fn clause(&self, i: usize) -> Option<RegOp> {
+        self.ops.get(i).cloned()
+    }
+
+    /// Returns the next clause to be executed, or `None` if evaluation is done
+    pub fn next_clause(&self) -> Option<RegOp> {
+        self.clause(self.pc)
+    }
+
+    /// Checks whether every clause has been executed
+    pub fn is_done(&self) -> bool {
+        self.pc >= self.ops.len()
+    }
+
+    /// Executes a single clause, returning it
+    ///
+    /// Returns `None` (and does nothing) if evaluation is done.
+    pub fn step(&mut self) -> Option<RegOp> {
+        let op = self.next_clause()?;
+        let mut v = SlotArray(&mut self.slots);
+        self.last_choice = point_op(op, &mut v, &self.vars);
+        self.choices.extend(self.last_choice);
+        self.pc += 1;
+        Some(op)
+    }
+
+    /// Returns the choice made by the most recently executed clause
+    ///
+    /// This is `None` if that clause wasn't a choice (`min`, `max`, `and`, or
+    /// `or`) operation, or if no clauses have been executed.
+    pub fn last_choice(&self) -> Option<Choice> {
+        self.last_choice
+    }
+
+    /// Returns the value written by the given clause, if it has been executed
+    ///
+    /// Registers are reused, so this is only valid until a later clause
+    /// overwrites the same slot; it's mostly useful for the most recently
+    /// executed clause (`self.pc() - 1`).
+    pub fn output(&self, clause: usize) -> Option<f32> {
+        if clause < self.pc {
+            let op = self.ops[clause];
+            Some(self.slots[op.output() as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Executes clauses until `stop` returns `true`, or reaching a breakpoint
+    /// or the end of the tape
+    ///
+    /// `stop` is called after each clause with the clause and the value that
+    /// it wrote, e.g. `|_op, v| v.is_nan()` stops at the first clause which
+    /// produces a `NaN`.  Returns the index of the clause which triggered
+    /// `stop` (which has been executed) or the breakpoint (which hasn't), or
+    /// `None` if execution ran to the end of the tape.
+    pub fn run_until<F: FnMut(RegOp, f32) -> bool>(
+        &mut self,
+        mut stop: F,
+    ) -> Option<usize> {
+        loop {
+            let op = self.step()?;
+            if stop(op, self.output(self.pc - 1).unwrap()) {
+                return Some(self.pc - 1);
+            }
+            if self.is_done() {
+                return None;
+            } else if self.breakpoints.contains(&self.pc) {
+                return Some(self.pc);
+            }
+        }
+    }
+
+    /// Executes clauses until reaching a breakpoint or the end of the tape
+    ///
+    /// At least one clause is executed (so resuming from a breakpoint moves
+    /// past it).  Returns the breakpoint's clause index if execution stopped
+    /// at a breakpoint, or `None` if it ran to the end of the tape.
+    pub fn resume(&mut self) -> Option<usize> {
+        self.run_until(|_, _| false)
+    }
+
+    /// Stops execution before the given clause
+    pub fn set_breakpoint(&mut self, clause: usize) {
+        self.breakpoints.insert(clause);
+    }
+
+    /// Removes a breakpoint, returning `true` if it was present
+    pub fn clear_breakpoint(&mut self, clause: usize) -> bool {
+        self.breakpoints.remove(&clause)
+    }
+
+    /// Iterates over breakpoints, in order
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().cloned()
+    }
+
+    /// Returns the register file
+    ///
+    /// Registers which haven't been written yet are `NaN`.
+    pub fn registers(&self) -> &[f32] {
+        &self.slots[..self.slots.len().min(N)]
+    }
+
+    /// Returns memory slots (used when the tape spills registers)
+    ///
+    /// Slot `i` in this array is addressed as `N + i` by
+    /// [`RegOp::Load`] and [`RegOp::Store`].
+    pub fn memory(&self) -> &[f32] {
+        &self.slots[self.slots.len().min(N)..]
+    }
+
+    /// Returns the choices made by `min` / `max` / `and` / `or` clauses so far
+    pub fn choices(&self) -> &[Choice] {
+        &self.choices
+    }
+
+    /// Returns the result of evaluation, if every clause has been executed
+    pub fn value(&self) -> Option<f32> {
+        if self.is_done() {
+            self.slots.first().cloned()
+        } else {
+            None
+        }
+    }
+
+    /// Returns to the start of the tape with new input values
+    ///
+    /// Breakpoints are kept.
+    pub fn restart(&mut self, vars: &[f32]) -> Result<(), Error> {
+        check_vars(vars, self.var_count)?;
+        self.vars = vars.to_vec();
+        self.slots.fill(f32::NAN);
+        self.choices.clear();
+        self.last_choice = None;
+        self.pc = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::{MathShape, Shape, TracingEvaluator},
+        vm::VmShape,
+    };
+
+    #[test]
+    fn test_debugger() {
+        let (x, y, z) = Tree::axes();
+        let t = (x.square() + y.square() + z.square()).sqrt() - 0.5;
+        let t = t.min(y.clone() - x.clone()).max(z * x - y);
+
+        let mut eval = VmShape::new_point_eval();
+        let shape = VmShape::from_tree(&t);
+        let mut dbg = VmDebugger::new(&shape, &[0.0; 3]).unwrap();
+        for p in [[0.0, 0.0, 0.0], [1.0, -0.5, 0.25], [-0.3, 2.0, 1.0]] {
+            dbg.restart(&p).unwrap();
+            assert!(dbg.value().is_none());
+            while dbg.step().is_some() {}
+            let (v, _) = eval.eval(&shape, p[0], p[1], p[2]).unwrap();
+            assert_eq!(dbg.value(), Some(v));
+            assert_eq!(dbg.choices().len(), 2);
+        }
+
+        // Breakpoints stop execution before the given clause
+        dbg.restart(&[1.0, 2.0, 3.0]).unwrap();
+        dbg.set_breakpoint(3);
+        dbg.set_breakpoint(5);
+        assert_eq!(dbg.resume(), Some(3));
+        assert_eq!(dbg.pc(), 3);
+        assert_eq!(dbg.resume(), Some(5));
+        assert!(dbg.clear_breakpoint(3));
+        assert_eq!(dbg.breakpoints().collect::<Vec<_>>(), [5]);
+        assert_eq!(dbg.resume(), None);
+        assert!(dbg.is_done());
+        assert!(dbg.step().is_none());
+
+        // Choices are reported as they're made
+        dbg.restart(&[1.0, 2.0, 3.0]).unwrap();
+        let mut seen = vec![];
+        while let Some(op) = dbg.step() {
+            let c = dbg.last_choice();
+            assert_eq!(
+                c.is_some(),
+                matches!(op, RegOp::MinRegReg(..) | RegOp::MaxRegReg(..))
+            );
+            seen.extend(c);
+        }
+        assert_eq!(seen, dbg.choices());
+
+        // Conditional stops
+        let x = Tree::x();
+        let shape = VmShape::from_tree(&(x.sqrt() + x));
+        let mut dbg = VmDebugger::new(&shape, &[-1.0, 0.0, 0.0]).unwrap();
+        let i = dbg.run_until(|_, v| v.is_nan()).unwrap();
+        assert!(matches!(dbg.clause(i), Some(RegOp::SqrtReg(..))));
+        assert!(dbg.output(i).unwrap().is_nan());
+        assert!(dbg.output(i + 1).is_none());
+        assert_eq!(dbg.run_until(|_, v| v > 100.0), None);
+
+        // Tapes which spill into memory are debugged the same way
+        let shape = GenericVmShape::<3>::from_tree(&t);
+        let mut dbg = VmDebugger::new(&shape, &[1.0, -0.5, 0.25]).unwrap();
+        assert_eq!(dbg.registers().len(), 3);
+        assert!(!dbg.memory().is_empty());
+        dbg.resume();
+        let (v, _) =
+            eval.eval(&VmShape::from_tree(&t), 1.0, -0.5, 0.25).unwrap();
+        assert_eq!(dbg.value(), Some(v));
+
+        assert!(matches!(
+            VmDebugger::new(&shape, &[1.0]),
+            Err(Error::BadVarSlice(1, 3))
+        ));
+    }
+}
@@ -0,0 +1,1475 @@
+//! Double-precision VM evaluators
+//!
+//! These evaluators run the same tapes as their single-precision equivalents,
+//! but do all of their arithmetic in `f64`.  This avoids catastrophic
+//! cancellation when a model has large coordinates with small features (e.g.
+//! millimeter details in a meter-scale assembly).
+//!
+//! Inputs to the bulk evaluators are still `&[f32]`, and immediate values in
+//! the tape are stored as `f32`; only intermediate results are widened.
+use super::{
+    check_vars, interval_op, BulkVmEval, Choice, GenericVmShape, SlotArray,
+    TracingVmEval, VmFloatSliceEval, VmGradSliceEval, VmIntervalEval,
+    VmPointEval, VmTrace,
+};
+use crate::{
+    compiler::RegOp,
+    eval::{BulkEvaluator, TracingEvaluator},
+    types::{Grad64, Interval64},
+    Error,
+};
+
+/// A family of VM evaluators which share a numeric precision
+///
+/// This makes it possible to write code which is generic over precision:
+///
+/// ```
+/// use fidget::{
+///     context::Context,
+///     eval::{MathShape, TracingEvaluator},
+///     vm::{Double, Family, Single, VmShape},
+/// };
+///
+/// fn eval_point<F: Family>(shape: &VmShape, x: f32) -> f64
+/// where
+///     <F::PointEval<255> as TracingEvaluator>::Data: Into<f64>,
+/// {
+///     let mut eval = F::PointEval::<255>::new();
+///     let (v, _) = eval.eval(shape, x, 0.0, 0.0).unwrap();
+///     v.into()
+/// }
+///
+/// let mut ctx = Context::new();
+/// let x = ctx.x();
+/// let big = ctx.add(x, 16777216.0)?;
+/// let out = ctx.sub(big, 16777216.0)?;
+/// let shape = VmShape::new(&ctx, out)?;
+///
+/// // At this magnitude, f32 can't represent the fractional part
+/// assert_eq!(eval_point::<Single>(&shape, 0.5), 0.0);
+/// assert_eq!(eval_point::<Double>(&shape, 0.5), 0.5);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub trait Family {
+    /// Tracing evaluator for single points
+    type PointEval<const N: usize>: TracingEvaluator<
+            Tape = GenericVmShape<N>,
+            Trace = VmTrace,
+            TapeStorage = (),
+        > + Send
+        + Sync;
+
+    /// Tracing evaluator for intervals
+    type IntervalEval<const N: usize>: TracingEvaluator<
+            Tape = GenericVmShape<N>,
+            Trace = VmTrace,
+            TapeStorage = (),
+        > + Send
+        + Sync;
+
+    /// Bulk evaluator for point values
+    type FloatSliceEval<const N: usize>: BulkEvaluator<Tape = GenericVmShape<N>, TapeStorage = ()>
+        + Send
+        + Sync;
+
+    /// Bulk evaluator for gradients
+    type GradSliceEval<const N: usize>: BulkEvaluator<Tape = GenericVmShape<N>, TapeStorage = ()>
+        + Send
+        + Sync;
+}
+
+/// Single-precision (`f32`) VM evaluators
+///
+/// These are the evaluators used by [`VmShape`](super::VmShape)'s
+/// [`Shape`](crate::eval::Shape) implementation.
+pub struct Single;
+impl Family for Single {
+    type PointEval<const N: usize> = VmPointEval<N>;
+    type IntervalEval<const N: usize> = VmIntervalEval<N>;
+    type FloatSliceEval<const N: usize> = VmFloatSliceEval<N>;
+    type GradSliceEval<const N: usize> = VmGradSliceEval<N>;
+}
+
+/// Double-precision (`f64`) VM evaluators
+pub struct Double;
+impl Family for Double {
+    type PointEval<const N: usize> = VmPointEval64<N>;
+    type IntervalEval<const N: usize> = VmIntervalEval64<N>;
+    type FloatSliceEval<const N: usize> = VmFloatSliceEval64<N>;
+    type GradSliceEval<const N: usize> = VmGradSliceEval64<N>;
+}
+
+/// Double-precision VM-based tracing evaluator for single points
+#[derive(Default)]
+pub struct VmPointEval64<const N: usize>(TracingVmEval<f64>);
+impl<const N: usize> TracingEvaluator for VmPointEval64<N> {
+    type Data = f64;
+    type Tape = GenericVmShape<N>;
+    type Trace = VmTrace;
+    type TapeStorage = ();
+
+    fn eval<F: Into<f64>>(
+        &mut self,
+        tape: &Self::Tape,
+        x: F,
+        y: F,
+        z: F,
+    ) -> Result<(f64, Option<&VmTrace>), Error> {
+        let x = x.into();
+        let y = y.into();
+        let z = z.into();
+        let tape = tape.0.as_ref();
+        self.check_arguments(tape.var_count())?;
+        self.0.resize_slots(tape);
+
+        let mut choices = self.0.choices.choices_mut().cursor();
+        let mut simplify = false;
+        let mut v = SlotArray(&mut self.0.slots);
+        for op in tape.iter_asm() {
+            match op {
+                RegOp::Input(out, i) => {
+                    v[out] = match i {
+                        0 => x,
+                        1 => y,
+                        2 => z,
+                        _ => panic!("Invalid input: {}", i),
+                    }
+                }
+                RegOp::NegReg(out, arg) => {
+                    v[out] = -v[arg];
+                }
+                RegOp::AbsReg(out, arg) => {
+                    v[out] = v[arg].abs();
+                }
+                RegOp::RecipReg(out, arg) => {
+                    v[out] = 1.0 / v[arg];
+                }
+                RegOp::SqrtReg(out, arg) => {
+                    v[out] = v[arg].sqrt();
+                }
+                RegOp::SquareReg(out, arg) => {
+                    let s = v[arg];
+                    v[out] = s * s;
+                }
+                RegOp::FloorReg(out, arg) => {
+                    v[out] = v[arg].floor();
+                }
+                RegOp::CeilReg(out, arg) => {
+                    v[out] = v[arg].ceil();
+                }
+                RegOp::RoundReg(out, arg) => {
+                    v[out] = v[arg].round();
+                }
+                RegOp::SinReg(out, arg) => {
+                    v[out] = v[arg].sin();
+                }
+                RegOp::CosReg(out, arg) => {
+                    v[out] = v[arg].cos();
+                }
+                RegOp::TanReg(out, arg) => {
+                    v[out] = v[arg].tan();
+                }
+                RegOp::AsinReg(out, arg) => {
+                    v[out] = v[arg].asin();
+                }
+                RegOp::AcosReg(out, arg) => {
+                    v[out] = v[arg].acos();
+                }
+                RegOp::AtanReg(out, arg) => {
+                    v[out] = v[arg].atan();
+                }
+                RegOp::ExpReg(out, arg) => {
+                    v[out] = v[arg].exp();
+                }
+                RegOp::LnReg(out, arg) => {
+                    v[out] = v[arg].ln();
+                }
+                RegOp::NotReg(out, arg) => v[out] = (v[arg] == 0.0).into(),
+                RegOp::CopyReg(out, arg) => {
+                    v[out] = v[arg];
+                }
+                RegOp::AddRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = v[arg] + imm;
+                }
+                RegOp::MulRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = v[arg] * imm;
+                }
+                RegOp::DivRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = v[arg] / imm;
+                }
+                RegOp::DivImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = imm / v[arg];
+                }
+                RegOp::AtanRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = v[arg].atan2(imm);
+                }
+                RegOp::AtanImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = imm.atan2(v[arg]);
+                }
+                RegOp::AtanRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs].atan2(v[rhs]);
+                }
+                RegOp::SubImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = imm - v[arg];
+                }
+                RegOp::SubRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = v[arg] - imm;
+                }
+                RegOp::MinRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    let a = v[arg];
+                    let (choice, value) = if a < imm {
+                        (Choice::Left, a)
+                    } else if imm < a {
+                        (Choice::Right, imm)
+                    } else {
+                        (
+                            Choice::Both,
+                            if a.is_nan() || imm.is_nan() {
+                                f64::NAN
+                            } else {
+                                imm
+                            },
+                        )
+                    };
+                    v[out] = value;
+                    choices.push(choice);
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::MaxRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    let a = v[arg];
+                    let (choice, value) = if a > imm {
+                        (Choice::Left, a)
+                    } else if imm > a {
+                        (Choice::Right, imm)
+                    } else {
+                        (
+                            Choice::Both,
+                            if a.is_nan() || imm.is_nan() {
+                                f64::NAN
+                            } else {
+                                imm
+                            },
+                        )
+                    };
+                    v[out] = value;
+                    choices.push(choice);
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::AndRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    let a = v[arg];
+                    let (choice, value) = if a == 0.0 {
+                        (Choice::Left, a)
+                    } else {
+                        (Choice::Right, imm)
+                    };
+                    v[out] = value;
+                    choices.push(choice);
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::OrRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    let a = v[arg];
+                    let (choice, value) = if a != 0.0 {
+                        (Choice::Left, a)
+                    } else {
+                        (Choice::Right, imm)
+                    };
+                    v[out] = value;
+                    choices.push(choice);
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::ModRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs].rem_euclid(v[rhs]);
+                }
+                RegOp::ModRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = v[arg].rem_euclid(imm);
+                }
+                RegOp::ModImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = imm.rem_euclid(v[arg]);
+                }
+                RegOp::AddRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs] + v[rhs];
+                }
+                RegOp::MulRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs] * v[rhs];
+                }
+                RegOp::DivRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs] / v[rhs];
+                }
+                RegOp::CompareRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs]
+                        .partial_cmp(&v[rhs])
+                        .map(|c| c as i8 as f64)
+                        .unwrap_or(f64::NAN)
+                }
+                RegOp::CompareRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = v[arg]
+                        .partial_cmp(&imm)
+                        .map(|c| c as i8 as f64)
+                        .unwrap_or(f64::NAN)
+                }
+                RegOp::CompareImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    v[out] = imm
+                        .partial_cmp(&v[arg])
+                        .map(|c| c as i8 as f64)
+                        .unwrap_or(f64::NAN)
+                }
+                RegOp::SubRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs] - v[rhs];
+                }
+                RegOp::MinRegReg(out, lhs, rhs) => {
+                    let a = v[lhs];
+                    let b = v[rhs];
+                    let (choice, value) = if a < b {
+                        (Choice::Left, a)
+                    } else if b < a {
+                        (Choice::Right, b)
+                    } else {
+                        (
+                            Choice::Both,
+                            if a.is_nan() || b.is_nan() {
+                                f64::NAN
+                            } else {
+                                b
+                            },
+                        )
+                    };
+                    v[out] = value;
+                    choices.push(choice);
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::MaxRegReg(out, lhs, rhs) => {
+                    let a = v[lhs];
+                    let b = v[rhs];
+                    let (choice, value) = if a > b {
+                        (Choice::Left, a)
+                    } else if b > a {
+                        (Choice::Right, b)
+                    } else {
+                        (
+                            Choice::Both,
+                            if a.is_nan() || b.is_nan() {
+                                f64::NAN
+                            } else {
+                                b
+                            },
+                        )
+                    };
+                    v[out] = value;
+                    choices.push(choice);
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::AndRegReg(out, lhs, rhs) => {
+                    let a = v[lhs];
+                    let b = v[rhs];
+                    let (choice, value) = if a == 0.0 {
+                        (Choice::Left, a)
+                    } else {
+                        (Choice::Right, b)
+                    };
+                    v[out] = value;
+                    choices.push(choice);
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::OrRegReg(out, lhs, rhs) => {
+                    let a = v[lhs];
+                    let b = v[rhs];
+                    let (choice, value) = if a != 0.0 {
+                        (Choice::Left, a)
+                    } else {
+                        (Choice::Right, b)
+                    };
+                    v[out] = value;
+                    choices.push(choice);
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::CopyImm(out, imm) => {
+                    let imm = imm as f64;
+                    v[out] = imm;
+                }
+                RegOp::Load(out, mem) => {
+                    v[out] = v[mem];
+                }
+                RegOp::Store(out, mem) => {
+                    v[mem] = v[out];
+                }
+            }
+        }
+        Ok((
+            self.0.slots[0],
+            if simplify {
+                Some(&self.0.choices)
+            } else {
+                None
+            },
+        ))
+    }
+}
+
+/// Double-precision VM-based tracing evaluator for intervals
+#[derive(Default)]
+pub struct VmIntervalEval64<const N: usize> {
+    eval: TracingVmEval<Interval64>,
+    extended_div: bool,
+}
+
+impl<const N: usize> VmIntervalEval64<N> {
+    /// Enables or disables extended interval division
+    ///
+    /// See [`VmIntervalEval::set_extended_div`] for details; when enabled,
+    /// division uses [`Interval64::div_extended`].
+    pub fn set_extended_div(&mut self, enabled: bool) {
+        self.extended_div = enabled;
+    }
+}
+
+impl<const N: usize> TracingEvaluator for VmIntervalEval64<N> {
+    type Data = Interval64;
+    type Tape = GenericVmShape<N>;
+    type Trace = VmTrace;
+    type TapeStorage = ();
+
+    fn eval<F: Into<Interval64>>(
+        &mut self,
+        tape: &Self::Tape,
+        x: F,
+        y: F,
+        z: F,
+    ) -> Result<(Interval64, Option<&VmTrace>), Error> {
+        self.eval_vars(tape, &[x.into(), y.into(), z.into()])
+    }
+
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[Interval64],
+    ) -> Result<(Interval64, Option<&VmTrace>), Error> {
+        let tape = tape.0.as_ref();
+        check_vars(vars, tape.var_count())?;
+        self.eval.resize_slots(tape);
+
+        let ext = self.extended_div;
+        let mut simplify = false;
+        let mut v = SlotArray(&mut self.eval.slots);
+        let mut choices = self.eval.choices.choices_mut().cursor();
+        for op in tape.iter_asm() {
+            if let Some(choice) = interval_op(op, &mut v, vars, ext) {
+                choices.push(choice);
+                simplify |= choice != Choice::Both;
+            }
+        }
+        Ok((
+            self.eval.slots[0],
+            if simplify {
+                Some(&self.eval.choices)
+            } else {
+                None
+            },
+        ))
+    }
+}
+
+/// Double-precision VM-based bulk evaluator, yielding point values
+#[derive(Default)]
+pub struct VmFloatSliceEval64<const N: usize>(BulkVmEval<f64>);
+impl<const N: usize> BulkEvaluator for VmFloatSliceEval64<N> {
+    type Data = f64;
+    type Tape = GenericVmShape<N>;
+    type TapeStorage = ();
+
+    fn eval(
+        &mut self,
+        tape: &Self::Tape,
+        xs: &[f32],
+        ys: &[f32],
+        zs: &[f32],
+    ) -> Result<&[f64], Error> {
+        let tape = tape.0.as_ref();
+        self.check_arguments(xs, ys, zs, tape.var_count())?;
+        self.0.resize_slots(tape, xs.len());
+        assert_eq!(xs.len(), ys.len());
+        assert_eq!(ys.len(), zs.len());
+
+        let size = xs.len();
+
+        let mut v = SlotArray(&mut self.0.slots);
+        for op in tape.iter_asm() {
+            match op {
+                RegOp::Input(out, i) => {
+                    let src = match i {
+                        0 => xs,
+                        1 => ys,
+                        2 => zs,
+                        _ => panic!("Invalid input: {}", i),
+                    };
+                    for (o, s) in v[out][0..size].iter_mut().zip(src) {
+                        *o = *s as f64;
+                    }
+                }
+                RegOp::NegReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = -v[arg][i];
+                    }
+                }
+                RegOp::AbsReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].abs();
+                    }
+                }
+                RegOp::RecipReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = 1.0 / v[arg][i];
+                    }
+                }
+                RegOp::SqrtReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sqrt();
+                    }
+                }
+                RegOp::SquareReg(out, arg) => {
+                    for i in 0..size {
+                        let s = v[arg][i];
+                        v[out][i] = s * s;
+                    }
+                }
+                RegOp::FloorReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].floor();
+                    }
+                }
+                RegOp::CeilReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ceil();
+                    }
+                }
+                RegOp::RoundReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].round();
+                    }
+                }
+                RegOp::SinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sin();
+                    }
+                }
+                RegOp::CosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cos();
+                    }
+                }
+                RegOp::TanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].tan();
+                    }
+                }
+                RegOp::AsinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].asin();
+                    }
+                }
+                RegOp::AcosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].acos();
+                    }
+                }
+                RegOp::AtanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan();
+                    }
+                }
+                RegOp::ExpReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].exp();
+                    }
+                }
+                RegOp::LnReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ln();
+                    }
+                }
+                RegOp::NotReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = (v[arg][i] == 0.0).into();
+                    }
+                }
+                RegOp::CopyReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i];
+                    }
+                }
+                RegOp::AddRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] + imm;
+                    }
+                }
+                RegOp::MulRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] * imm;
+                    }
+                }
+                RegOp::DivRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] / imm;
+                    }
+                }
+                RegOp::DivImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = imm / v[arg][i];
+                    }
+                }
+                RegOp::AtanRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan2(imm);
+                    }
+                }
+                RegOp::AtanImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = imm.atan2(v[arg][i]);
+                    }
+                }
+                RegOp::AtanRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                    }
+                }
+                RegOp::SubImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = imm - v[arg][i];
+                    }
+                }
+                RegOp::SubRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] - imm;
+                    }
+                }
+                RegOp::CompareImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = imm
+                            .partial_cmp(&v[arg][i])
+                            .map(|c| c as i8 as f64)
+                            .unwrap_or(f64::NAN)
+                    }
+                }
+                RegOp::CompareRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i]
+                            .partial_cmp(&imm)
+                            .map(|c| c as i8 as f64)
+                            .unwrap_or(f64::NAN)
+                    }
+                }
+                RegOp::MinRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].is_nan() || imm.is_nan() {
+                            f64::NAN
+                        } else {
+                            v[arg][i].min(imm)
+                        };
+                    }
+                }
+                RegOp::MaxRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].is_nan() || imm.is_nan() {
+                            f64::NAN
+                        } else {
+                            v[arg][i].max(imm)
+                        };
+                    }
+                }
+                RegOp::AndRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] =
+                            if v[arg][i] == 0.0 { v[arg][i] } else { imm };
+                    }
+                }
+                RegOp::OrRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] =
+                            if v[arg][i] != 0.0 { v[arg][i] } else { imm };
+                    }
+                }
+                RegOp::ModRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                    }
+                }
+                RegOp::ModRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].rem_euclid(imm);
+                    }
+                }
+                RegOp::ModImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = imm.rem_euclid(v[arg][i]);
+                    }
+                }
+                RegOp::AddRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] + v[rhs][i];
+                    }
+                }
+                RegOp::MulRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] * v[rhs][i];
+                    }
+                }
+                RegOp::DivRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] / v[rhs][i];
+                    }
+                }
+                RegOp::SubRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] - v[rhs][i];
+                    }
+                }
+                RegOp::CompareRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i]
+                            .partial_cmp(&v[rhs][i])
+                            .map(|c| c as i8 as f64)
+                            .unwrap_or(f64::NAN)
+                    }
+                }
+                RegOp::MinRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i].is_nan() || v[rhs][i].is_nan()
+                        {
+                            f64::NAN
+                        } else {
+                            v[lhs][i].min(v[rhs][i])
+                        };
+                    }
+                }
+                RegOp::MaxRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i].is_nan() || v[rhs][i].is_nan()
+                        {
+                            f64::NAN
+                        } else {
+                            v[lhs][i].max(v[rhs][i])
+                        };
+                    }
+                }
+                RegOp::AndRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i] == 0.0 {
+                            v[lhs][i]
+                        } else {
+                            v[rhs][i]
+                        };
+                    }
+                }
+                RegOp::OrRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i] != 0.0 {
+                            v[lhs][i]
+                        } else {
+                            v[rhs][i]
+                        };
+                    }
+                }
+                RegOp::CopyImm(out, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = imm;
+                    }
+                }
+                RegOp::Load(out, mem) => {
+                    for i in 0..size {
+                        v[out][i] = v[mem][i];
+                    }
+                }
+                RegOp::Store(out, mem) => {
+                    for i in 0..size {
+                        v[mem][i] = v[out][i];
+                    }
+                }
+            }
+        }
+        Ok(&self.0.slots[0])
+    }
+}
+
+/// Double-precision VM-based bulk evaluator, yielding gradient values
+#[derive(Default)]
+pub struct VmGradSliceEval64<const N: usize>(BulkVmEval<Grad64>);
+impl<const N: usize> BulkEvaluator for VmGradSliceEval64<N> {
+    type Data = Grad64;
+    type Tape = GenericVmShape<N>;
+    type TapeStorage = ();
+
+    fn eval(
+        &mut self,
+        tape: &Self::Tape,
+        xs: &[f32],
+        ys: &[f32],
+        zs: &[f32],
+    ) -> Result<&[Grad64], Error> {
+        let tape = tape.0.as_ref();
+        self.check_arguments(xs, ys, zs, tape.var_count())?;
+        self.0.resize_slots(tape, xs.len());
+        assert_eq!(xs.len(), ys.len());
+        assert_eq!(ys.len(), zs.len());
+
+        let size = xs.len();
+        let mut v = SlotArray(&mut self.0.slots);
+        for op in tape.iter_asm() {
+            match op {
+                RegOp::Input(out, j) => {
+                    for i in 0..size {
+                        v[out][i] = match j {
+                            0 => Grad64::new(xs[i] as f64, 1.0, 0.0, 0.0),
+                            1 => Grad64::new(ys[i] as f64, 0.0, 1.0, 0.0),
+                            2 => Grad64::new(zs[i] as f64, 0.0, 0.0, 1.0),
+                            _ => panic!("Invalid input: {}", i),
+                        }
+                    }
+                }
+                RegOp::NegReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = -v[arg][i];
+                    }
+                }
+                RegOp::AbsReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].abs();
+                    }
+                }
+                RegOp::RecipReg(out, arg) => {
+                    let one: Grad64 = 1.0.into();
+                    for i in 0..size {
+                        v[out][i] = one / v[arg][i];
+                    }
+                }
+                RegOp::SqrtReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sqrt();
+                    }
+                }
+                RegOp::SquareReg(out, arg) => {
+                    for i in 0..size {
+                        let s = v[arg][i];
+                        v[out][i] = s * s;
+                    }
+                }
+                RegOp::FloorReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].floor();
+                    }
+                }
+                RegOp::CeilReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ceil();
+                    }
+                }
+                RegOp::RoundReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].round();
+                    }
+                }
+                RegOp::SinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sin();
+                    }
+                }
+                RegOp::CosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cos();
+                    }
+                }
+                RegOp::TanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].tan();
+                    }
+                }
+                RegOp::AsinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].asin();
+                    }
+                }
+                RegOp::AcosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].acos();
+                    }
+                }
+                RegOp::AtanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan();
+                    }
+                }
+                RegOp::ExpReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].exp();
+                    }
+                }
+                RegOp::LnReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ln();
+                    }
+                }
+                RegOp::NotReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = f64::from(v[arg][i].v == 0.0).into();
+                    }
+                }
+                RegOp::CopyReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i];
+                    }
+                }
+                RegOp::AddRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] + imm.into();
+                    }
+                }
+                RegOp::MulRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] * imm.into();
+                    }
+                }
+                RegOp::DivRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] / imm.into();
+                    }
+                }
+                RegOp::DivImmReg(out, arg, imm) => {
+                    let imm = Grad64::from(imm);
+                    for i in 0..size {
+                        v[out][i] = imm / v[arg][i];
+                    }
+                }
+                RegOp::AtanRegImm(out, arg, imm) => {
+                    let imm = Grad64::from(imm);
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan2(imm);
+                    }
+                }
+                RegOp::AtanImmReg(out, arg, imm) => {
+                    let imm = Grad64::from(imm);
+                    for i in 0..size {
+                        v[out][i] = imm.atan2(v[arg][i]);
+                    }
+                }
+                RegOp::AtanRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                    }
+                }
+                RegOp::SubImmReg(out, arg, imm) => {
+                    let imm: Grad64 = imm.into();
+                    for i in 0..size {
+                        v[out][i] = imm - v[arg][i];
+                    }
+                }
+                RegOp::SubRegImm(out, arg, imm) => {
+                    let imm: Grad64 = imm.into();
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] - imm;
+                    }
+                }
+                RegOp::CompareImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        let p = imm
+                            .partial_cmp(&v[arg][i].v)
+                            .map(|c| c as i8 as f64)
+                            .unwrap_or(f64::NAN);
+                        v[out][i] = Grad64::new(p, 0.0, 0.0, 0.0);
+                    }
+                }
+                RegOp::CompareRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        let p = v[arg][i]
+                            .v
+                            .partial_cmp(&imm)
+                            .map(|c| c as i8 as f64)
+                            .unwrap_or(f64::NAN);
+                        v[out][i] = Grad64::new(p, 0.0, 0.0, 0.0);
+                    }
+                }
+                RegOp::MinRegImm(out, arg, imm) => {
+                    let imm: Grad64 = imm.into();
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].v.is_nan() || imm.v.is_nan() {
+                            f64::NAN.into()
+                        } else {
+                            v[arg][i].min(imm)
+                        };
+                    }
+                }
+                RegOp::MaxRegImm(out, arg, imm) => {
+                    let imm: Grad64 = imm.into();
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].v.is_nan() || imm.v.is_nan() {
+                            f64::NAN.into()
+                        } else {
+                            v[arg][i].max(imm)
+                        };
+                    }
+                }
+                RegOp::ModRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                    }
+                }
+                RegOp::ModRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].rem_euclid(imm.into());
+                    }
+                }
+                RegOp::ModImmReg(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = Grad64::from(imm).rem_euclid(v[arg][i]);
+                    }
+                }
+                RegOp::AddRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] + v[rhs][i];
+                    }
+                }
+                RegOp::MulRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] * v[rhs][i];
+                    }
+                }
+                RegOp::AndRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i].v == 0.0 {
+                            v[lhs][i]
+                        } else {
+                            v[rhs][i]
+                        };
+                    }
+                }
+                RegOp::AndRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].v == 0.0 {
+                            v[arg][i]
+                        } else {
+                            imm.into()
+                        };
+                    }
+                }
+                RegOp::OrRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i].v != 0.0 {
+                            v[lhs][i]
+                        } else {
+                            v[rhs][i]
+                        };
+                    }
+                }
+                RegOp::OrRegImm(out, arg, imm) => {
+                    let imm = imm as f64;
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].v != 0.0 {
+                            v[arg][i]
+                        } else {
+                            imm.into()
+                        };
+                    }
+                }
+                RegOp::DivRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] / v[rhs][i];
+                    }
+                }
+                RegOp::SubRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] - v[rhs][i];
+                    }
+                }
+                RegOp::CompareRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        let p = v[lhs][i]
+                            .v
+                            .partial_cmp(&v[rhs][i].v)
+                            .map(|c| c as i8 as f64)
+                            .unwrap_or(f64::NAN);
+                        v[out][i] = Grad64::new(p, 0.0, 0.0, 0.0);
+                    }
+                }
+                RegOp::MinRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] =
+                            if v[lhs][i].v.is_nan() || v[rhs][i].v.is_nan() {
+                                f64::NAN.into()
+                            } else {
+                                v[lhs][i].min(v[rhs][i])
+                            };
+                    }
+                }
+                RegOp::MaxRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] =
+                            if v[lhs][i].v.is_nan() || v[rhs][i].v.is_nan() {
+                                f64::NAN.into()
+                            } else {
+                                v[lhs][i].max(v[rhs][i])
+                            };
+                    }
+                }
+                RegOp::CopyImm(out, imm) => {
+                    let imm: Grad64 = imm.into();
+                    for i in 0..size {
+                        v[out][i] = imm;
+                    }
+                }
+                RegOp::Load(out, mem) => {
+                    for i in 0..size {
+                        v[out][i] = v[mem][i];
+                    }
+                }
+                RegOp::Store(out, mem) => {
+                    for i in 0..size {
+                        v[mem][i] = v[out][i];
+                    }
+                }
+            }
+        }
+        Ok(&self.0.slots[0])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::{Context, Node, Tree},
+        eval::{MathShape, Shape, TransformedShape},
+        types::{Grad, Interval},
+        vm::{VmData, VmShape, VmWorkspace},
+    };
+    use std::sync::Arc;
+
+    /// Shape which is evaluated with the double-precision evaluators
+    ///
+    /// Results are rounded back to `f32` (intervals are rounded outwards), so
+    /// that the standard evaluator test suites can be run against the `f64`
+    /// code paths.
+    #[derive(Clone)]
+    struct DoubleShape(VmShape);
+
+    #[derive(Default)]
+    struct DoublePointEval(VmPointEval64<255>);
+    impl TracingEvaluator for DoublePointEval {
+        type Data = f32;
+        type Tape = VmShape;
+        type Trace = VmTrace;
+        type TapeStorage = ();
+
+        fn eval<F: Into<f32>>(
+            &mut self,
+            tape: &VmShape,
+            x: F,
+            y: F,
+            z: F,
+        ) -> Result<(f32, Option<&VmTrace>), Error> {
+            let (x, y, z) = (x.into(), y.into(), z.into());
+            let (v, trace) = self.0.eval(tape, x, y, z)?;
+            Ok((v as f32, trace))
+        }
+    }
+
+    /// Rounds an `f64` interval to `f32`
+    ///
+    /// Inexact bounds are rounded outwards by one `f32` ulp (like the
+    /// transcendental functions in [`Interval`]), so that the result also
+    /// contains the reference values computed in `f32` by the test suites.
+    fn narrow(i: Interval64) -> Interval {
+        if i.has_nan() {
+            return f32::NAN.into();
+        }
+        let (lower, upper) = (i.lower() as f32, i.upper() as f32);
+        Interval::new(
+            if lower as f64 == i.lower() {
+                lower
+            } else {
+                lower.next_down()
+            },
+            if upper as f64 == i.upper() {
+                upper
+            } else {
+                upper.next_up()
+            },
+        )
+    }
+
+    #[derive(Default)]
+    struct DoubleIntervalEval(VmIntervalEval64<255>);
+    impl TracingEvaluator for DoubleIntervalEval {
+        type Data = Interval;
+        type Tape = VmShape;
+        type Trace = VmTrace;
+        type TapeStorage = ();
+
+        fn eval<F: Into<Interval>>(
+            &mut self,
+            tape: &VmShape,
+            x: F,
+            y: F,
+            z: F,
+        ) -> Result<(Interval, Option<&VmTrace>), Error> {
+            let (x, y, z) = (x.into(), y.into(), z.into());
+            let (v, trace) =
+                self.0.eval(tape, Interval64::from(x), y.into(), z.into())?;
+            Ok((narrow(v), trace))
+        }
+    }
+
+    #[derive(Default)]
+    struct DoubleFloatSliceEval(VmFloatSliceEval64<255>, Vec<f32>);
+    impl BulkEvaluator for DoubleFloatSliceEval {
+        type Data = f32;
+        type Tape = VmShape;
+        type TapeStorage = ();
+
+        fn eval(
+            &mut self,
+            tape: &VmShape,
+            xs: &[f32],
+            ys: &[f32],
+            zs: &[f32],
+        ) -> Result<&[f32], Error> {
+            let out = self.0.eval(tape, xs, ys, zs)?;
+            self.1.clear();
+            self.1.extend(out.iter().map(|v| *v as f32));
+            Ok(&self.1)
+        }
+    }
+
+    #[derive(Default)]
+    struct DoubleGradSliceEval(VmGradSliceEval64<255>, Vec<Grad>);
+    impl BulkEvaluator for DoubleGradSliceEval {
+        type Data = Grad;
+        type Tape = VmShape;
+        type TapeStorage = ();
+
+        fn eval(
+            &mut self,
+            tape: &VmShape,
+            xs: &[f32],
+            ys: &[f32],
+            zs: &[f32],
+        ) -> Result<&[Grad], Error> {
+            let out = self.0.eval(tape, xs, ys, zs)?;
+            self.1.clear();
+            self.1.extend(out.iter().map(|g| {
+                Grad::new(g.v as f32, g.dx as f32, g.dy as f32, g.dz as f32)
+            }));
+            Ok(&self.1)
+        }
+    }
+
+    impl Shape for DoubleShape {
+        type Trace = VmTrace;
+        type Storage = Arc<VmData<255>>;
+        type Workspace = VmWorkspace<255>;
+        type TapeStorage = ();
+
+        type PointEval = DoublePointEval;
+        type IntervalEval = DoubleIntervalEval;
+        type FloatSliceEval = DoubleFloatSliceEval;
+        type GradSliceEval = DoubleGradSliceEval;
+
+        fn point_tape(&self, _storage: ()) -> VmShape {
+            self.0.clone()
+        }
+        fn interval_tape(&self, _storage: ()) -> VmShape {
+            self.0.clone()
+        }
+        fn float_slice_tape(&self, _storage: ()) -> VmShape {
+            self.0.clone()
+        }
+        fn grad_slice_tape(&self, _storage: ()) -> VmShape {
+            self.0.clone()
+        }
+        fn simplify(
+            &self,
+            trace: &VmTrace,
+            storage: Self::Storage,
+            workspace: &mut Self::Workspace,
+        ) -> Result<Self, Error> {
+            self.0.simplify(trace, storage, workspace).map(Self)
+        }
+        fn recycle(self) -> Option<Self::Storage> {
+            self.0.recycle()
+        }
+        fn size(&self) -> usize {
+            self.0.size()
+        }
+        fn tile_sizes_3d() -> &'static [usize] {
+            VmShape::tile_sizes_3d()
+        }
+        fn tile_sizes_2d() -> &'static [usize] {
+            VmShape::tile_sizes_2d()
+        }
+
+        type TransformedShape = TransformedShape<Self>;
+        fn apply_transform(
+            self,
+            mat: nalgebra::Matrix4<f32>,
+        ) -> Self::TransformedShape {
+            TransformedShape::new(self, mat)
+        }
+    }
+
+    impl MathShape for DoubleShape {
+        fn new(ctx: &Context, node: Node) -> Result<Self, Error> {
+            VmShape::new(ctx, node).map(Self)
+        }
+    }
+
+    #[test]
+    fn test_double_extended_div() {
+        let (x, y, _z) = Tree::axes();
+        let shape = VmShape::from_tree(&(y / x.clone() - 0.5));
+        let mut eval = VmIntervalEval64::<255>::new();
+        let (i, _) =
+            eval.eval(&shape, [0.0, 1.0], [1.0, 2.0], [0.0; 2]).unwrap();
+        assert!(i.has_nan());
+
+        eval.set_extended_div(true);
+        let (i, _) =
+            eval.eval(&shape, [0.0, 1.0], [1.0, 2.0], [0.0; 2]).unwrap();
+        assert_eq!(i, Interval64::new(0.5, f64::INFINITY));
+
+        let shape = VmShape::from_tree(&(1.0 / x));
+        let (i, _) =
+            eval.eval(&shape, [-2.0, 0.0], [0.0; 2], [0.0; 2]).unwrap();
+        assert_eq!(i, Interval64::new(-f64::INFINITY, -0.5));
+    }
+
+    // The point, float slice, and gradient slice suites compare against values
+    // computed in `f32` with a small absolute tolerance, which the more
+    // accurate `f64` results don't meet at large magnitudes (e.g. `tan` near
+    // its poles); those evaluators are checked by the golden tests instead.
+    crate::interval_tests!(DoubleShape);
+    crate::conformance_test!(test_golden_point, DoubleShape);
+    crate::conformance_test!(test_golden_float_slice, DoubleShape);
+    crate::conformance_test!(test_golden_grad_slice, DoubleShape);
+    crate::conformance_test!(test_golden_interval, DoubleShape);
+    crate::conformance_test!(test_golden_simplify, DoubleShape);
+    crate::conformance_test!(test_golden_choices, DoubleShape);
+
+    #[test]
+    fn test_double_matches_single() {
+        let (x, y, z) = Tree::axes();
+        let shapes = [
+            (x.square() + y.square() + z.square()).sqrt() - 0.5,
+            x.clone() * y.clone() + z.sin() * 2.0,
+            (x.clone() - 0.2).max(y.clone() / (z.clone() + 3.0)),
+            x.clone().atan2(y.clone() + 0.1) + (z.exp() * x.clone()).abs(),
+        ];
+        let mut p32 = VmPointEval::<255>::new();
+        let mut p64 = VmPointEval64::<255>::new();
+        let mut ieval = VmIntervalEval64::<255>::new();
+        let mut feval = VmFloatSliceEval64::<255>::new();
+        let mut g32 = VmGradSliceEval::<255>::new();
+        let mut g64 = VmGradSliceEval64::<255>::new();
+
+        let mut xs = vec![];
+        let mut ys = vec![];
+        let mut zs = vec![];
+        for i in 0..=4 {
+            for j in 0..=4 {
+                for k in 0..=4 {
+                    xs.push(i as f32 / 4.0 - 0.5);
+                    ys.push(j as f32 / 4.0 - 0.5);
+                    zs.push(k as f32 / 4.0 - 0.5);
+                }
+            }
+        }
+        for t in shapes {
+            let shape = VmShape::from_tree(&t);
+            let (range, _) = ieval
+                .eval(&shape, [-0.5, 0.5], [-0.5, 0.5], [-0.5, 0.5])
+                .unwrap();
+            let fs = feval.eval(&shape, &xs, &ys, &zs).unwrap().to_vec();
+            let g_single = g32.eval(&shape, &xs, &ys, &zs).unwrap().to_vec();
+            let g_double = g64.eval(&shape, &xs, &ys, &zs).unwrap();
+            for i in 0..xs.len() {
+                let (a, _) = p32.eval(&shape, xs[i], ys[i], zs[i]).unwrap();
+                let (b, _) = p64.eval(&shape, xs[i], ys[i], zs[i]).unwrap();
+                assert!((a as f64 - b).abs() < 1e-5, "{a} != {b}");
+                assert_eq!(b, fs[i]);
+                assert_eq!(b, g_double[i].v);
+                assert!(range.contains(b), "{b} is not in {range}");
+
+                let (g, h) = (g_single[i], g_double[i]);
+                for (u, v) in [(g.dx, h.dx), (g.dy, h.dy), (g.dz, h.dz)] {
+                    let ok = (u as f64 - v).abs() < 1e-4
+                        || (u.is_nan() && v.is_nan());
+                    assert!(ok, "{u} != {v}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_double_cancellation() {
+        // A translation that round-trips through a large offset
+        let x = Tree::x();
+        let c = 16777216.0; // 2^24
+        let t = ((x + c) - c) - 0.25;
+        let shape = VmShape::from_tree(&t);
+
+        let mut p32 = VmPointEval::<255>::new();
+        let (a, _) = p32.eval(&shape, 0.5, 0.0, 0.0).unwrap();
+        assert_eq!(a, -0.25); // x was lost to rounding
+
+        let mut p64 = VmPointEval64::<255>::new();
+        let (b, _) = p64.eval(&shape, 0.5, 0.0, 0.0).unwrap();
+        assert_eq!(b, 0.25);
+
+        let mut ieval = VmIntervalEval64::<255>::new();
+        let (b, _) =
+            ieval.eval(&shape, [0.5, 0.75], [0.0; 2], [0.0; 2]).unwrap();
+        assert_eq!(b, Interval64::new(0.25, 0.5));
+
+        let mut feval = VmFloatSliceEval64::<255>::new();
+        let b = feval
+            .eval(&shape, &[0.5, 1.0], &[0.0; 2], &[0.0; 2])
+            .unwrap();
+        assert_eq!(b, [0.25, 0.75]);
+    }
+}
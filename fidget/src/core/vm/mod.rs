@@ -1,22 +1,32 @@
 //! Simple virtual machine for shape evaluation
 use crate::{
-    compiler::RegOp,
-    context::Node,
+    compiler::{Backend, Flavor, RegOp, SsaTape, TapeMetrics},
+    context::{BinaryOpcode, Node, UnaryOpcode},
     eval::{
-        BulkEvaluator, MathShape, Shape, Tape, Trace, TracingEvaluator,
-        TransformedShape,
+        BulkEvaluator, MathShape, Shape, Strided, StridedMut, Tape, Trace,
+        TracingEvaluator, TransformedShape, Vars,
     },
-    types::{Grad, Interval},
+    types::{Dual, Grad, Interval, Interval64, IntervalGrad},
     Context, Error,
 };
 use nalgebra::Matrix4;
+use simd::GradX8;
 use std::sync::Arc;
+use wide::f32x8;
 
 mod choice;
 mod data;
+mod debug;
+mod double;
+mod simd;
 
-pub use choice::Choice;
+pub use choice::{Choice, PackedChoices};
 pub use data::{VmData, VmWorkspace};
+pub use debug::VmDebugger;
+pub use double::{
+    Double, Family, Single, VmFloatSliceEval64, VmGradSliceEval64,
+    VmIntervalEval64, VmPointEval64,
+};
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -28,20 +38,79 @@ pub use data::{VmData, VmWorkspace};
 /// All of the associated [`Tape`] types simply clone the internal `Arc`;
 /// there's no separate planning required to generate a tape.
 ///
+/// Register indices are bytes, so at most 255 values are held in registers.
+/// Larger tapes don't fail: additional live values are spilled to memory
+/// slots (with `u32` indices) using [`RegOp::Store`] and [`RegOp::Load`], at
+/// the cost of extra instructions (see [`VmData::metrics`]).
 pub type VmShape = GenericVmShape<{ u8::MAX as usize }>;
 
+/// Shapes which are planned from an SSA tape
+///
+/// Evaluator families built on [`VmData`] (e.g. the VM and JIT) share the same
+/// SSA form, differing only in their register limits.  Converting between
+/// them with [`SsaShape::convert`] reuses the SSA tape, so only register
+/// allocation is repeated, rather than rebuilding the tape from a
+/// [`Context`].
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::{MathShape, Shape, TracingEvaluator},
+///     vm::{GenericVmShape, SsaShape, VmShape},
+/// };
+///
+/// let (x, y, z) = Tree::axes();
+/// let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - z));
+/// let small: GenericVmShape<4> = shape.convert()?;
+///
+/// let mut eval = GenericVmShape::<4>::new_point_eval();
+/// let (v, _) = eval.eval(&small, 3.0, 4.0, 1.0)?;
+/// assert_eq!(v, 4.0);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub trait SsaShape: Sized {
+    /// Returns the SSA tape from which this shape was planned
+    fn ssa(&self) -> &SsaTape;
+
+    /// Plans a shape from an SSA tape
+    ///
+    /// The SSA tape is used as-is, so it should come from another shape (see
+    /// [`VmData::from_ssa`]).
+    ///
+    /// Returns an error if this family can't evaluate the tape (e.g. the JIT
+    /// only supports tapes with `X`, `Y`, and `Z` inputs).
+    fn from_ssa(ssa: SsaTape) -> Result<Self, Error>;
+
+    /// Converts this shape into a different family, re-planning registers
+    fn convert<T: SsaShape>(&self) -> Result<T, Error> {
+        T::from_ssa(self.ssa().clone())
+    }
+}
+
+impl<const N: usize> SsaShape for GenericVmShape<N> {
+    fn ssa(&self) -> &SsaTape {
+        self.0.ssa()
+    }
+    fn from_ssa(ssa: SsaTape) -> Result<Self, Error> {
+        Ok(VmData::from_ssa(ssa).into())
+    }
+}
+
 impl<const N: usize> Tape for GenericVmShape<N> {
     type Storage = ();
     fn recycle(self) -> Self::Storage {
         // nothing to do here
     }
+    fn vars(&self) -> Vars {
+        self.0.vars()
+    }
 }
 
 /// A trace captured by a VM evaluation
 ///
-/// This is a thin wrapper around a [`Vec<Choice>`](Choice).
+/// This is a thin wrapper around a [`PackedChoices`] array.
 #[derive(Clone, Default, Eq, PartialEq)]
-pub struct VmTrace(Vec<Choice>);
+pub struct VmTrace(PackedChoices);
 
 impl VmTrace {
     /// Fills the trace with the given value
@@ -52,37 +121,32 @@ impl VmTrace {
     pub fn resize(&mut self, n: usize, v: Choice) {
         self.0.resize(n, v);
     }
-    /// Returns the inner choice slice
-    pub fn as_slice(&self) -> &[Choice] {
-        self.0.as_slice()
-    }
-    /// Returns the inner choice slice as a mutable reference
-    pub fn as_mut_slice(&mut self) -> &mut [Choice] {
-        self.0.as_mut_slice()
+    /// Returns the inner packed choice array
+    pub fn choices(&self) -> &PackedChoices {
+        &self.0
     }
-    /// Returns a pointer to the allocated choice array
-    pub fn as_mut_ptr(&mut self) -> *mut Choice {
-        self.0.as_mut_ptr()
+    /// Returns the inner packed choice array as a mutable reference
+    pub fn choices_mut(&mut self) -> &mut PackedChoices {
+        &mut self.0
     }
 }
 
 impl Trace for VmTrace {
     fn copy_from(&mut self, other: &VmTrace) {
-        self.0.resize(other.0.len(), Choice::Unknown);
-        self.0.copy_from_slice(&other.0);
+        self.0.clone_from(&other.0);
     }
 }
 
 #[cfg(test)]
 impl From<Vec<Choice>> for VmTrace {
     fn from(v: Vec<Choice>) -> Self {
-        Self(v)
+        Self(v.as_slice().into())
     }
 }
 
 #[cfg(test)]
-impl AsRef<[Choice]> for VmTrace {
-    fn as_ref(&self) -> &[Choice] {
+impl AsRef<PackedChoices> for VmTrace {
+    fn as_ref(&self) -> &PackedChoices {
         &self.0
     }
 }
@@ -101,23 +165,82 @@ impl<const N: usize> From<VmData<N>> for GenericVmShape<N> {
 }
 
 impl<const N: usize> GenericVmShape<N> {
+    /// Builds a new shape whose inputs are the given variables, in order
+    ///
+    /// Evaluators take one argument per input (using
+    /// [`TracingEvaluator::eval_vars`] or [`BulkEvaluator::eval_vars`]), rather
+    /// than `X`, `Y`, and `Z`; see [`VmData::new_with_inputs`] for details.
+    ///
+    /// ```
+    /// use fidget::{
+    ///     eval::{BulkEvaluator, EzShape, Shape, TracingEvaluator},
+    ///     vm::VmShape,
+    ///     Context,
+    /// };
+    ///
+    /// // A circle whose radius grows over time
+    /// let mut ctx = Context::new();
+    /// let x = ctx.var("x")?;
+    /// let y = ctx.var("y")?;
+    /// let t = ctx.var("t")?;
+    /// let x2 = ctx.square(x)?;
+    /// let y2 = ctx.square(y)?;
+    /// let r = ctx.add(x2, y2)?;
+    /// let r = ctx.sqrt(r)?;
+    /// let out = ctx.sub(r, t)?;
+    /// let shape = VmShape::new_with_inputs(&ctx, out, &["x", "y", "t"])?;
+    ///
+    /// let mut eval = VmShape::new_point_eval();
+    /// let (v, _) = eval.eval_vars(&shape.ez_point_tape(), &[3.0, 4.0, 2.0])?;
+    /// assert_eq!(v, 3.0);
+    ///
+    /// let mut eval = VmShape::new_float_slice_eval();
+    /// let tape = shape.ez_float_slice_tape();
+    /// let out = eval.eval_vars(&tape, &[&[3.0, 0.0], &[4.0, 1.0], &[1.0; 2]])?;
+    /// assert_eq!(out, [4.0, 0.0]);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn new_with_inputs(
+        ctx: &Context,
+        node: Node,
+        inputs: &[&str],
+    ) -> Result<Self, Error> {
+        let d = VmData::new_with_inputs(ctx, node, inputs)?;
+        Ok(Self(Arc::new(d)))
+    }
+
+    /// Simplifies the shape, reusing `storage` for the new tape
+    ///
+    /// If `storage` is uniquely owned (e.g. because it was returned by
+    /// [`GenericVmShape::recycle`]), then both its `Arc` and the [`VmData`]
+    /// within it are reused; see [`VmData::simplify`] for details.
     pub(crate) fn simplify_inner(
         &self,
-        choices: &[Choice],
-        storage: VmData<N>,
+        choices: &PackedChoices,
+        mut storage: Arc<VmData<N>>,
         workspace: &mut VmWorkspace<N>,
     ) -> Result<Self, Error> {
-        let d = self.0.simplify(choices, workspace, storage)?;
-        Ok(Self(Arc::new(d)))
+        #[cfg(feature = "stats")]
+        let _timer = crate::stats::SimplifyTimer::start();
+        if Arc::get_mut(&mut storage).is_none() {
+            storage = Arc::default();
+        }
+        let data = Arc::get_mut(&mut storage).unwrap();
+        *data = self.0.simplify(choices, workspace, std::mem::take(data))?;
+        Ok(Self(storage))
     }
     /// Returns a characteristic size (the length of the inner assembly tape)
     pub fn size(&self) -> usize {
         self.0.len()
     }
 
-    /// Reclaim the inner `VmData` if there's only a single reference
-    pub fn recycle(self) -> Option<VmData<N>> {
-        Arc::try_unwrap(self.0).ok()
+    /// Reclaim the inner `Arc<VmData>` if there's only a single reference
+    ///
+    /// The `Arc` itself is returned (rather than the [`VmData`]) so that its
+    /// allocation can also be reused during simplification.
+    pub fn recycle(mut self) -> Option<Arc<VmData<N>>> {
+        Arc::get_mut(&mut self.0)?;
+        Some(self.0)
     }
 
     /// Borrows the inner [`VmData`]
@@ -134,11 +257,64 @@ impl<const N: usize> GenericVmShape<N> {
     pub fn var_count(&self) -> usize {
         self.0.var_count()
     }
+
+    /// Estimates the cost of evaluating this tape with the VM
+    ///
+    /// See [`RegTape::estimated_cost`](crate::compiler::RegTape::estimated_cost)
+    /// for details.
+    pub fn estimated_cost(&self, flavor: Flavor) -> f64 {
+        self.0.estimated_cost(flavor, Backend::Vm)
+    }
+
+    /// Computes summary metrics for this tape
+    ///
+    /// See [`RegTape::metrics`](crate::compiler::RegTape::metrics) for details.
+    pub fn metrics(&self) -> TapeMetrics {
+        self.0.metrics()
+    }
+
+    /// Returns an upper bound on the magnitude of the gradient over a box
+    ///
+    /// This runs a single pass of interval gradient evaluation (see
+    /// [`VmIntervalGradEval`]) over the region, and returns
+    /// [`IntervalGrad::lipschitz`] for the result: `|f(a) - f(b)|` is at most
+    /// this value times `|a - b|` for any two points in the box, which gives a
+    /// safe step scale for sphere tracing or adaptive sampling.  The bound is
+    /// `NaN` if it can't be computed (e.g. if a derivative is unbounded in the
+    /// region).
+    ///
+    /// Discontinuous operations (`floor`, `compare`, etc) have a derivative of
+    /// zero, so their jumps aren't included in the bound.
+    ///
+    /// ```
+    /// use fidget::{context::Tree, eval::MathShape, vm::VmShape};
+    ///
+    /// let (x, y, _z) = Tree::axes();
+    /// let shape = VmShape::from_tree(&(x * 3.0 - y * 4.0));
+    /// let r = [-1.0, 1.0];
+    /// assert_eq!(shape.lipschitz_bound(r, r, r)?, 5.0);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn lipschitz_bound<I: Into<Interval>>(
+        &self,
+        x: I,
+        y: I,
+        z: I,
+    ) -> Result<f32, Error> {
+        let mut eval = VmIntervalGradEval::<N>::default();
+        let (out, _) = eval.eval(
+            self,
+            IntervalGrad::from(x.into()),
+            IntervalGrad::from(y.into()),
+            IntervalGrad::from(z.into()),
+        )?;
+        Ok(out.lipschitz())
+    }
 }
 
 impl<const N: usize> Shape for GenericVmShape<N> {
     type FloatSliceEval = VmFloatSliceEval<N>;
-    type Storage = VmData<N>;
+    type Storage = Arc<VmData<N>>;
     type Workspace = VmWorkspace<N>;
 
     type TapeStorage = ();
@@ -162,10 +338,10 @@ impl<const N: usize> Shape for GenericVmShape<N> {
     fn simplify(
         &self,
         trace: &VmTrace,
-        storage: VmData<N>,
+        storage: Arc<VmData<N>>,
         workspace: &mut Self::Workspace,
     ) -> Result<Self, Error> {
-        self.simplify_inner(trace.as_slice(), storage, workspace)
+        self.simplify_inner(trace.choices(), storage, workspace)
     }
 
     fn recycle(self) -> Option<Self::Storage> {
@@ -176,6 +352,10 @@ impl<const N: usize> Shape for GenericVmShape<N> {
         GenericVmShape::size(self)
     }
 
+    fn choice_count(&self) -> Option<usize> {
+        Some(GenericVmShape::choice_count(self))
+    }
+
     fn tile_sizes_3d() -> &'static [usize] {
         &[256, 128, 64, 32, 16, 8]
     }
@@ -184,6 +364,15 @@ impl<const N: usize> Shape for GenericVmShape<N> {
         &[256, 128, 64, 32, 16, 8]
     }
 
+    fn plan_2d(&self, z: f32) -> Option<Self> {
+        self.0.plan_2d(z).map(|d| Self(Arc::new(d)))
+    }
+
+    fn specialize(&self, var: &str, value: f32) -> Option<Self> {
+        let d = self.0.specialize(var, value).ok()?;
+        Some(Self(Arc::new(d)))
+    }
+
     type TransformedShape = TransformedShape<Self>;
     fn apply_transform(self, mat: Matrix4<f32>) -> Self::TransformedShape {
         TransformedShape::new(self, mat)
@@ -226,6 +415,26 @@ impl<T> std::ops::IndexMut<u32> for SlotArray<'_, T> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Returns an error if `vars` has too few values for the given tape
+fn check_vars<T>(vars: &[T], var_count: usize) -> Result<(), Error> {
+    if vars.len() < var_count {
+        Err(Error::BadVarSlice(vars.len(), var_count))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the length of each slice in `vars`, or an error if they differ
+fn check_var_slices(vars: &[&[f32]], var_count: usize) -> Result<usize, Error> {
+    check_vars(vars, var_count)?;
+    let n = vars.first().map(|v| v.len()).unwrap_or(0);
+    if vars.iter().any(|v| v.len() != n) {
+        Err(Error::MismatchedSlices)
+    } else {
+        Ok(n)
+    }
+}
+
 /// Generic VM evaluator for tracing evaluation
 struct TracingVmEval<T> {
     slots: Vec<T>,
@@ -251,7 +460,94 @@ impl<T: From<f32> + Clone> TracingVmEval<T> {
 
 /// VM-based tracing evaluator for intervals
 #[derive(Default)]
-pub struct VmIntervalEval<const N: usize>(TracingVmEval<Interval>);
+pub struct VmIntervalEval<const N: usize> {
+    eval: TracingVmEval<Interval>,
+    extended_div: bool,
+}
+
+impl<const N: usize> VmIntervalEval<N> {
+    /// Enables or disables extended interval division
+    ///
+    /// By default, dividing by an interval which contains zero returns `NaN`.
+    /// With extended division enabled, the result is instead the conservative
+    /// (possibly unbounded) hull from [`Interval::div_extended`], e.g.
+    /// `[1, 2] / [0, 4] = [0.25, ∞)`, which preserves information for
+    /// downstream operations and improves pruning of rational expressions.
+    pub fn set_extended_div(&mut self, enabled: bool) {
+        self.extended_div = enabled;
+    }
+
+    /// Performs interval evaluation, recording the result of every clause
+    ///
+    /// This is much slower than [`eval_vars`](TracingEvaluator::eval_vars),
+    /// and is meant for debugging, e.g. finding the subexpression which makes
+    /// a result unbounded (see [`IntervalReport::first_unbounded`]).
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::{MathShape, Shape},
+    ///     types::Interval,
+    ///     vm::VmShape,
+    /// };
+    ///
+    /// let (x, y, _z) = Tree::axes();
+    /// let shape = VmShape::from_tree(&((x.clone() / y) + x.square()));
+    /// let mut eval = VmShape::new_interval_eval();
+    /// let i = Interval::new(-1.0, 1.0);
+    /// let report = eval.eval_i_traced(&shape, &[i, i, i])?;
+    /// assert!(report.value.has_nan());
+    ///
+    /// // The division by an interval containing zero is to blame
+    /// let (op, _) = report.clauses[report.first_unbounded().unwrap()];
+    /// assert!(format!("{op:?}").starts_with("Div"));
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn eval_i_traced(
+        &mut self,
+        tape: &GenericVmShape<N>,
+        vars: &[Interval],
+    ) -> Result<IntervalReport, Error> {
+        let tape = tape.0.as_ref();
+        check_vars(vars, tape.var_count())?;
+        self.eval.resize_slots(tape);
+
+        let mut clauses = Vec::with_capacity(tape.len());
+        let mut v = SlotArray(&mut self.eval.slots);
+        for op in tape.iter_asm() {
+            interval_op(op, &mut v, vars, self.extended_div);
+            clauses.push((op, v[op.output()]));
+        }
+        Ok(IntervalReport {
+            clauses,
+            value: self.eval.slots[0],
+        })
+    }
+}
+
+/// Per-clause results from [`VmIntervalEval::eval_i_traced`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntervalReport {
+    /// Every clause in the tape (in evaluation order), along with the interval
+    /// that it wrote
+    pub clauses: Vec<(RegOp, Interval)>,
+
+    /// Result of evaluation
+    pub value: Interval,
+}
+
+impl IntervalReport {
+    /// Returns the index of the first clause with an unbounded result
+    ///
+    /// A result is unbounded if either end is infinite or `NaN`.  Later clauses
+    /// usually just propagate an unbounded input, so this is normally the
+    /// clause responsible (unless the inputs were themselves unbounded).
+    pub fn first_unbounded(&self) -> Option<usize> {
+        self.clauses.iter().position(|(_, i)| {
+            !(i.lower().is_finite() && i.upper().is_finite())
+        })
+    }
+}
 impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
     type Data = Interval;
     type Tape = GenericVmShape<N>;
@@ -265,25 +561,575 @@ impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
         y: F,
         z: F,
     ) -> Result<(Interval, Option<&VmTrace>), Error> {
-        let x = x.into();
-        let y = y.into();
-        let z = z.into();
+        self.eval_vars(tape, &[x.into(), y.into(), z.into()])
+    }
+
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[Interval],
+    ) -> Result<(Interval, Option<&VmTrace>), Error> {
+        let tape = tape.0.as_ref();
+        check_vars(vars, tape.var_count())?;
+        #[cfg(feature = "stats")]
+        crate::stats::record_eval(Flavor::Interval, tape.len(), 1);
+        self.eval.resize_slots(tape);
+
+        let ext = self.extended_div;
+        let mut simplify = false;
+        let mut v = SlotArray(&mut self.eval.slots);
+        let mut choices = self.eval.choices.choices_mut().cursor();
+        for op in tape.iter_asm() {
+            if let Some(choice) = interval_op(op, &mut v, vars, ext) {
+                choices.push(choice);
+                simplify |= choice != Choice::Both;
+            }
+        }
+        Ok((
+            self.eval.slots[0],
+            if simplify {
+                Some(&self.eval.choices)
+            } else {
+                None
+            },
+        ))
+    }
+}
+
+/// Executes a single clause of interval evaluation
+///
+/// This is shared by [`VmIntervalEval`], [`VmIntervalGradEval`], and
+/// [`VmIntervalEval64`].  Returns the choice made by the clause, if it's a
+/// choice (`min`, `max`, `and`, or `or`) operation.
+#[inline(always)]
+fn interval_op<T: IntervalOps>(
+    op: RegOp,
+    v: &mut SlotArray<T>,
+    vars: &[T],
+    extended_div: bool,
+) -> Option<Choice> {
+    match op {
+        RegOp::Input(out, i) => {
+            v[out] = vars[i as usize];
+        }
+        RegOp::NegReg(out, arg) => {
+            v[out] = -v[arg];
+        }
+        RegOp::AbsReg(out, arg) => {
+            v[out] = v[arg].abs();
+        }
+        RegOp::RecipReg(out, arg) => {
+            v[out] = v[arg].recip();
+        }
+        RegOp::SqrtReg(out, arg) => {
+            v[out] = v[arg].sqrt();
+        }
+        RegOp::SquareReg(out, arg) => {
+            v[out] = v[arg].square();
+        }
+        RegOp::FloorReg(out, arg) => {
+            v[out] = v[arg].floor();
+        }
+        RegOp::CeilReg(out, arg) => {
+            v[out] = v[arg].ceil();
+        }
+        RegOp::RoundReg(out, arg) => {
+            v[out] = v[arg].round();
+        }
+        RegOp::SinReg(out, arg) => {
+            v[out] = v[arg].sin();
+        }
+        RegOp::CosReg(out, arg) => {
+            v[out] = v[arg].cos();
+        }
+        RegOp::TanReg(out, arg) => {
+            v[out] = v[arg].tan();
+        }
+        RegOp::AsinReg(out, arg) => {
+            v[out] = v[arg].asin();
+        }
+        RegOp::AcosReg(out, arg) => {
+            v[out] = v[arg].acos();
+        }
+        RegOp::AtanReg(out, arg) => {
+            v[out] = v[arg].atan();
+        }
+        RegOp::ExpReg(out, arg) => {
+            v[out] = v[arg].exp();
+        }
+        RegOp::LnReg(out, arg) => {
+            v[out] = v[arg].ln();
+        }
+        RegOp::NotReg(out, arg) => {
+            v[out] = v[arg].not();
+        }
+        RegOp::CopyReg(out, arg) => v[out] = v[arg],
+        RegOp::AddRegImm(out, arg, imm) => {
+            v[out] = v[arg] + imm.into();
+        }
+        RegOp::MulRegImm(out, arg, imm) => {
+            v[out] = v[arg].mul_imm(imm);
+        }
+        RegOp::DivRegImm(out, arg, imm) => {
+            v[out] = v[arg] / imm.into();
+        }
+        RegOp::DivImmReg(out, arg, imm) => {
+            let imm: T = imm.into();
+            v[out] = if extended_div {
+                imm.div_extended(v[arg])
+            } else {
+                imm / v[arg]
+            };
+        }
+        RegOp::AtanRegImm(out, arg, imm) => {
+            v[out] = v[arg].atan2(imm.into());
+        }
+        RegOp::AtanImmReg(out, arg, imm) => {
+            let imm: T = imm.into();
+            v[out] = imm.atan2(v[arg]);
+        }
+        RegOp::AtanRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs].atan2(v[rhs]);
+        }
+        RegOp::SubImmReg(out, arg, imm) => {
+            v[out] = T::from(imm) - v[arg];
+        }
+        RegOp::SubRegImm(out, arg, imm) => {
+            v[out] = v[arg] - imm.into();
+        }
+        RegOp::MinRegImm(out, arg, imm) => {
+            let (value, choice) = v[arg].min_choice(imm.into());
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::MaxRegImm(out, arg, imm) => {
+            let (value, choice) = v[arg].max_choice(imm.into());
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::AndRegReg(out, lhs, rhs) => {
+            let (value, choice) = v[lhs].and_choice(v[rhs]);
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::AndRegImm(out, arg, imm) => {
+            let (value, choice) = v[arg].and_choice(imm.into());
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::OrRegReg(out, lhs, rhs) => {
+            let (value, choice) = v[lhs].or_choice(v[rhs]);
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::OrRegImm(out, arg, imm) => {
+            let (value, choice) = v[arg].or_choice(imm.into());
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::ModRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs].rem_euclid(v[rhs]);
+        }
+        RegOp::ModRegImm(out, arg, imm) => {
+            v[out] = v[arg].rem_euclid(imm.into());
+        }
+        RegOp::ModImmReg(out, arg, imm) => {
+            v[out] = T::from(imm).rem_euclid(v[arg]);
+        }
+        RegOp::AddRegReg(out, lhs, rhs) => v[out] = v[lhs] + v[rhs],
+        RegOp::MulRegReg(out, lhs, rhs) => v[out] = v[lhs] * v[rhs],
+        RegOp::DivRegReg(out, lhs, rhs) => {
+            v[out] = if extended_div {
+                v[lhs].div_extended(v[rhs])
+            } else {
+                v[lhs] / v[rhs]
+            };
+        }
+        RegOp::SubRegReg(out, lhs, rhs) => v[out] = v[lhs] - v[rhs],
+        RegOp::CompareRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs].compare(v[rhs]);
+        }
+        RegOp::CompareRegImm(out, arg, imm) => {
+            v[out] = v[arg].compare(imm.into());
+        }
+        RegOp::CompareImmReg(out, arg, imm) => {
+            v[out] = T::from(imm).compare(v[arg]);
+        }
+        RegOp::MinRegReg(out, lhs, rhs) => {
+            let (value, choice) = v[lhs].min_choice(v[rhs]);
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::MaxRegReg(out, lhs, rhs) => {
+            let (value, choice) = v[lhs].max_choice(v[rhs]);
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::CopyImm(out, imm) => {
+            v[out] = imm.into();
+        }
+        RegOp::Load(out, mem) => {
+            v[out] = v[mem];
+        }
+        RegOp::Store(out, mem) => {
+            v[mem] = v[out];
+        }
+    }
+    None
+}
+
+/// Interval types which can be evaluated by [`interval_op`]
+///
+/// Most operations forward to inherent methods of the same name on
+/// [`Interval`], [`Interval64`], and [`IntervalGrad`].
+trait IntervalOps:
+    Copy
+    + From<f32>
+    + std::ops::Neg<Output = Self>
+    + std::ops::Add<Output = Self>
+    + std::ops::Sub<Output = Self>
+    + std::ops::Mul<Output = Self>
+    + std::ops::Div<Output = Self>
+{
+    fn abs(self) -> Self;
+    fn recip(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn square(self) -> Self;
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn round(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn asin(self) -> Self;
+    fn acos(self) -> Self;
+    fn atan(self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn not(self) -> Self;
+    fn mul_imm(self, imm: f32) -> Self;
+    fn div_extended(self, rhs: Self) -> Self;
+    fn atan2(self, x: Self) -> Self;
+    fn rem_euclid(self, rhs: Self) -> Self;
+    fn compare(self, rhs: Self) -> Self;
+    fn min_choice(self, rhs: Self) -> (Self, Choice);
+    fn max_choice(self, rhs: Self) -> (Self, Choice);
+    fn and_choice(self, rhs: Self) -> (Self, Choice);
+    fn or_choice(self, rhs: Self) -> (Self, Choice);
+}
+
+/// Implements the [`IntervalOps`] methods which forward to inherent methods
+///
+/// Calls use explicit paths (rather than method syntax), which pick the
+/// inherent method instead of recursing into the trait.
+macro_rules! interval_ops_forward {
+    ($t:ty) => {
+        fn abs(self) -> Self {
+            <$t>::abs(self)
+        }
+        fn recip(self) -> Self {
+            <$t>::recip(self)
+        }
+        fn sqrt(self) -> Self {
+            <$t>::sqrt(self)
+        }
+        fn square(self) -> Self {
+            <$t>::square(self)
+        }
+        fn sin(self) -> Self {
+            <$t>::sin(self)
+        }
+        fn cos(self) -> Self {
+            <$t>::cos(self)
+        }
+        fn tan(self) -> Self {
+            <$t>::tan(self)
+        }
+        fn asin(self) -> Self {
+            <$t>::asin(self)
+        }
+        fn acos(self) -> Self {
+            <$t>::acos(self)
+        }
+        fn atan(self) -> Self {
+            <$t>::atan(self)
+        }
+        fn exp(self) -> Self {
+            <$t>::exp(self)
+        }
+        fn ln(self) -> Self {
+            <$t>::ln(self)
+        }
+        fn atan2(self, x: Self) -> Self {
+            <$t>::atan2(self, x)
+        }
+        fn min_choice(self, rhs: Self) -> (Self, Choice) {
+            <$t>::min_choice(self, rhs)
+        }
+        fn max_choice(self, rhs: Self) -> (Self, Choice) {
+            <$t>::max_choice(self, rhs)
+        }
+        fn and_choice(self, rhs: Self) -> (Self, Choice) {
+            <$t>::and_choice(self, rhs)
+        }
+        fn or_choice(self, rhs: Self) -> (Self, Choice) {
+            <$t>::or_choice(self, rhs)
+        }
+    };
+}
+
+impl IntervalOps for Interval {
+    interval_ops_forward!(Interval);
+    fn floor(self) -> Self {
+        Interval::floor(&self)
+    }
+    fn ceil(self) -> Self {
+        Interval::ceil(&self)
+    }
+    fn round(self) -> Self {
+        Interval::round(&self)
+    }
+    fn rem_euclid(self, rhs: Self) -> Self {
+        Interval::rem_euclid(&self, rhs)
+    }
+    fn not(self) -> Self {
+        UnaryOpcode::Not.eval_interval(self)
+    }
+    fn mul_imm(self, imm: f32) -> Self {
+        self * imm
+    }
+    fn div_extended(self, rhs: Self) -> Self {
+        Interval::div_extended(self, rhs).hull()
+    }
+    fn compare(self, rhs: Self) -> Self {
+        BinaryOpcode::Compare.eval_interval(self, rhs)
+    }
+}
+
+impl IntervalOps for IntervalGrad {
+    interval_ops_forward!(IntervalGrad);
+    fn floor(self) -> Self {
+        IntervalGrad::floor(self)
+    }
+    fn ceil(self) -> Self {
+        IntervalGrad::ceil(self)
+    }
+    fn round(self) -> Self {
+        IntervalGrad::round(self)
+    }
+    fn rem_euclid(self, rhs: Self) -> Self {
+        IntervalGrad::rem_euclid(self, rhs)
+    }
+    fn not(self) -> Self {
+        UnaryOpcode::Not.eval_interval(self.v).into()
+    }
+    fn mul_imm(self, imm: f32) -> Self {
+        self * imm.into()
+    }
+    fn div_extended(self, rhs: Self) -> Self {
+        IntervalGrad::div_extended(self, rhs)
+    }
+    fn compare(self, rhs: Self) -> Self {
+        BinaryOpcode::Compare.eval_interval(self.v, rhs.v).into()
+    }
+}
+
+impl IntervalOps for Interval64 {
+    interval_ops_forward!(Interval64);
+    fn floor(self) -> Self {
+        Interval64::floor(&self)
+    }
+    fn ceil(self) -> Self {
+        Interval64::ceil(&self)
+    }
+    fn round(self) -> Self {
+        Interval64::round(&self)
+    }
+    fn rem_euclid(self, rhs: Self) -> Self {
+        Interval64::rem_euclid(&self, rhs)
+    }
+    fn not(self) -> Self {
+        if !self.contains(0.0) {
+            Interval64::new(0.0, 0.0)
+        } else if self.lower() == 0.0 && self.upper() == 0.0 {
+            Interval64::new(1.0, 1.0)
+        } else {
+            Interval64::new(0.0, 1.0)
+        }
+    }
+    fn mul_imm(self, imm: f32) -> Self {
+        self * imm as f64
+    }
+    fn div_extended(self, rhs: Self) -> Self {
+        Interval64::div_extended(self, rhs).hull()
+    }
+    fn compare(self, rhs: Self) -> Self {
+        if self.has_nan() || rhs.has_nan() {
+            f64::NAN.into()
+        } else if self.upper() < rhs.lower() {
+            Interval64::from(-1.0)
+        } else if self.lower() > rhs.upper() {
+            Interval64::from(1.0)
+        } else {
+            Interval64::new(-1.0, 1.0)
+        }
+    }
+}
+
+/// VM-based tracing evaluator for intervals with partial derivatives
+///
+/// The inputs are intervals for `x`, `y`, and `z` (any derivatives in the
+/// input arguments are ignored); the result contains bounds on the function's
+/// value and its partial derivatives over that region.
+///
+/// This evaluator is only implemented for the VM, but can be used with any
+/// [`GenericVmShape`].
+#[derive(Default)]
+pub struct VmIntervalGradEval<const N: usize> {
+    eval: TracingVmEval<IntervalGrad>,
+    extended_div: bool,
+
+    /// Input values, with seeded partial derivatives
+    vars: Vec<IntervalGrad>,
+}
+
+impl<const N: usize> VmIntervalGradEval<N> {
+    /// Enables or disables extended interval division
+    ///
+    /// See [`VmIntervalEval::set_extended_div`] for details; when enabled,
+    /// division uses [`IntervalGrad::div_extended`].
+    pub fn set_extended_div(&mut self, enabled: bool) {
+        self.extended_div = enabled;
+    }
+}
+
+impl<const N: usize> TracingEvaluator for VmIntervalGradEval<N> {
+    type Data = IntervalGrad;
+    type Tape = GenericVmShape<N>;
+    type Trace = VmTrace;
+    type TapeStorage = ();
+
+    fn eval<F: Into<IntervalGrad>>(
+        &mut self,
+        tape: &Self::Tape,
+        x: F,
+        y: F,
+        z: F,
+    ) -> Result<(IntervalGrad, Option<&VmTrace>), Error> {
+        self.eval_vars(tape, &[x.into(), y.into(), z.into()])
+    }
+
+    /// Evaluates the tape with one value per variable slot
+    ///
+    /// The partial derivatives of each input are seeded here, so only the
+    /// values of `vars` are used.  Slots are matched to axes by name (see
+    /// [`Vars`](crate::eval::Vars)): the slots named `X`, `Y`, and `Z` have a
+    /// unit derivative along that axis, and any other slots are treated as
+    /// constants.
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[IntervalGrad],
+    ) -> Result<(IntervalGrad, Option<&VmTrace>), Error> {
+        let tape = tape.0.as_ref();
+        check_vars(vars, tape.var_count())?;
+        self.eval.resize_slots(tape);
+
+        // Seed the partial derivatives of each input
+        let (zero, one) = (Interval::from(0.0), Interval::from(1.0));
+        self.vars.clear();
+        self.vars.extend(vars.iter().enumerate().map(|(i, v)| {
+            let axis = tape.input_axis(i);
+            let d = |j| if axis == Some(j) { one } else { zero };
+            IntervalGrad::new(v.v, d(0), d(1), d(2))
+        }));
+
+        let ext = self.extended_div;
+        let mut simplify = false;
+        let mut v = SlotArray(&mut self.eval.slots);
+        let mut choices = self.eval.choices.choices_mut().cursor();
+        for op in tape.iter_asm() {
+            if let Some(choice) = interval_op(op, &mut v, &self.vars, ext) {
+                choices.push(choice);
+                simplify |= choice != Choice::Both;
+            }
+        }
+        Ok((
+            self.eval.slots[0],
+            if simplify {
+                Some(&self.eval.choices)
+            } else {
+                None
+            },
+        ))
+    }
+}
+
+/// VM-based tracing evaluator for forward-mode differentiation
+///
+/// Each input is a [`Dual`] carrying `K` caller-supplied tangents (e.g. a ray
+/// direction, or the derivative of each coordinate with respect to some shape
+/// parameter); each tangent of the result is the derivative along the
+/// corresponding seed.  Choices are traced like [`VmPointEval`].
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::{MathShape, TracingEvaluator},
+///     types::Dual,
+///     vm::{VmDualEval, VmShape},
+/// };
+///
+/// let (x, y, _) = Tree::axes();
+/// let shape = VmShape::from_tree(&(x.square() + y.square()).sqrt());
+/// let mut eval = VmDualEval::<255, 2>::new();
+///
+/// // Derivatives along the X axis and along the diagonal (1, 1)
+/// let (out, _) = eval
+///     .eval(
+///         &shape,
+///         Dual::new(3.0, [1.0, 1.0]),
+///         Dual::new(4.0, [0.0, 1.0]),
+///         Dual::new(0.0, [0.0, 0.0]),
+///     )
+///     .unwrap();
+/// assert_eq!(out.v, 5.0);
+/// assert_eq!(out.d, [0.6, 1.4]);
+/// ```
+#[derive(Default)]
+pub struct VmDualEval<const N: usize, const K: usize>(TracingVmEval<Dual<K>>);
+impl<const N: usize, const K: usize> TracingEvaluator for VmDualEval<N, K> {
+    type Data = Dual<K>;
+    type Tape = GenericVmShape<N>;
+    type Trace = VmTrace;
+    type TapeStorage = ();
+
+    fn eval<F: Into<Dual<K>>>(
+        &mut self,
+        tape: &Self::Tape,
+        x: F,
+        y: F,
+        z: F,
+    ) -> Result<(Dual<K>, Option<&VmTrace>), Error> {
+        self.eval_vars(tape, &[x.into(), y.into(), z.into()])
+    }
+
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[Dual<K>],
+    ) -> Result<(Dual<K>, Option<&VmTrace>), Error> {
         let tape = tape.0.as_ref();
-        self.check_arguments(tape.var_count())?;
+        check_vars(vars, tape.var_count())?;
         self.0.resize_slots(tape);
 
         let mut simplify = false;
         let mut v = SlotArray(&mut self.0.slots);
-        let mut choices = self.0.choices.as_mut_slice().iter_mut();
+        let mut choices = self.0.choices.choices_mut().cursor();
         for op in tape.iter_asm() {
             match op {
                 RegOp::Input(out, i) => {
-                    v[out] = match i {
-                        0 => x,
-                        1 => y,
-                        2 => z,
-                        _ => panic!("Invalid input: {}", i),
-                    }
+                    v[out] = vars[i as usize];
                 }
                 RegOp::NegReg(out, arg) => {
                     v[out] = -v[arg];
@@ -334,40 +1180,34 @@ impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
                     v[out] = v[arg].ln();
                 }
                 RegOp::NotReg(out, arg) => {
-                    v[out] = if !v[arg].contains(0.0) {
-                        Interval::new(0.0, 0.0)
-                    } else if v[arg].lower() == 0.0 && v[arg].upper() == 0.0 {
-                        Interval::new(1.0, 1.0)
-                    } else {
-                        Interval::new(0.0, 1.0)
-                    };
+                    v[out] = v[arg].logical_not();
                 }
                 RegOp::CopyReg(out, arg) => v[out] = v[arg],
                 RegOp::AddRegImm(out, arg, imm) => {
                     v[out] = v[arg] + imm.into();
                 }
                 RegOp::MulRegImm(out, arg, imm) => {
-                    v[out] = v[arg] * imm;
+                    v[out] = v[arg] * imm.into();
                 }
                 RegOp::DivRegImm(out, arg, imm) => {
                     v[out] = v[arg] / imm.into();
                 }
                 RegOp::DivImmReg(out, arg, imm) => {
-                    let imm: Interval = imm.into();
+                    let imm = Dual::<K>::from(imm);
                     v[out] = imm / v[arg];
                 }
                 RegOp::AtanRegImm(out, arg, imm) => {
                     v[out] = v[arg].atan2(imm.into());
                 }
                 RegOp::AtanImmReg(out, arg, imm) => {
-                    let imm: Interval = imm.into();
+                    let imm = Dual::<K>::from(imm);
                     v[out] = imm.atan2(v[arg]);
                 }
                 RegOp::AtanRegReg(out, lhs, rhs) => {
                     v[out] = v[lhs].atan2(v[rhs]);
                 }
                 RegOp::SubImmReg(out, arg, imm) => {
-                    v[out] = Interval::from(imm) - v[arg];
+                    v[out] = Dual::from(imm) - v[arg];
                 }
                 RegOp::SubRegImm(out, arg, imm) => {
                     v[out] = v[arg] - imm.into();
@@ -375,37 +1215,37 @@ impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
                 RegOp::MinRegImm(out, arg, imm) => {
                     let (value, choice) = v[arg].min_choice(imm.into());
                     v[out] = value;
-                    *choices.next().unwrap() |= choice;
+                    choices.push(choice);
                     simplify |= choice != Choice::Both;
                 }
                 RegOp::MaxRegImm(out, arg, imm) => {
                     let (value, choice) = v[arg].max_choice(imm.into());
                     v[out] = value;
-                    *choices.next().unwrap() |= choice;
+                    choices.push(choice);
                     simplify |= choice != Choice::Both;
                 }
                 RegOp::AndRegReg(out, lhs, rhs) => {
                     let (value, choice) = v[lhs].and_choice(v[rhs]);
                     v[out] = value;
-                    *choices.next().unwrap() |= choice;
+                    choices.push(choice);
                     simplify |= choice != Choice::Both;
                 }
                 RegOp::AndRegImm(out, arg, imm) => {
                     let (value, choice) = v[arg].and_choice(imm.into());
                     v[out] = value;
-                    *choices.next().unwrap() |= choice;
+                    choices.push(choice);
                     simplify |= choice != Choice::Both;
                 }
                 RegOp::OrRegReg(out, lhs, rhs) => {
                     let (value, choice) = v[lhs].or_choice(v[rhs]);
                     v[out] = value;
-                    *choices.next().unwrap() |= choice;
+                    choices.push(choice);
                     simplify |= choice != Choice::Both;
                 }
                 RegOp::OrRegImm(out, arg, imm) => {
                     let (value, choice) = v[arg].or_choice(imm.into());
                     v[out] = value;
-                    *choices.next().unwrap() |= choice;
+                    choices.push(choice);
                     simplify |= choice != Choice::Both;
                 }
                 RegOp::ModRegReg(out, lhs, rhs) => {
@@ -415,55 +1255,31 @@ impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
                     v[out] = v[arg].rem_euclid(imm.into());
                 }
                 RegOp::ModImmReg(out, arg, imm) => {
-                    v[out] = Interval::from(imm).rem_euclid(v[arg]);
+                    v[out] = Dual::from(imm).rem_euclid(v[arg]);
                 }
                 RegOp::AddRegReg(out, lhs, rhs) => v[out] = v[lhs] + v[rhs],
                 RegOp::MulRegReg(out, lhs, rhs) => v[out] = v[lhs] * v[rhs],
                 RegOp::DivRegReg(out, lhs, rhs) => v[out] = v[lhs] / v[rhs],
                 RegOp::SubRegReg(out, lhs, rhs) => v[out] = v[lhs] - v[rhs],
                 RegOp::CompareRegReg(out, lhs, rhs) => {
-                    v[out] = if v[lhs].has_nan() || v[rhs].has_nan() {
-                        f32::NAN.into()
-                    } else if v[lhs].upper() < v[rhs].lower() {
-                        Interval::from(-1.0)
-                    } else if v[lhs].lower() > v[rhs].upper() {
-                        Interval::from(1.0)
-                    } else {
-                        Interval::new(-1.0, 1.0)
-                    };
+                    v[out] = v[lhs].compare(v[rhs]);
                 }
                 RegOp::CompareRegImm(out, arg, imm) => {
-                    v[out] = if v[arg].has_nan() || imm.is_nan() {
-                        f32::NAN.into()
-                    } else if v[arg].upper() < imm {
-                        Interval::from(-1.0)
-                    } else if v[arg].lower() > imm {
-                        Interval::from(1.0)
-                    } else {
-                        Interval::new(-1.0, 1.0)
-                    };
+                    v[out] = v[arg].compare(imm.into());
                 }
                 RegOp::CompareImmReg(out, arg, imm) => {
-                    v[out] = if v[arg].has_nan() || imm.is_nan() {
-                        f32::NAN.into()
-                    } else if imm < v[arg].lower() {
-                        Interval::from(-1.0)
-                    } else if imm > v[arg].upper() {
-                        Interval::from(1.0)
-                    } else {
-                        Interval::new(-1.0, 1.0)
-                    };
+                    v[out] = Dual::from(imm).compare(v[arg]);
                 }
                 RegOp::MinRegReg(out, lhs, rhs) => {
                     let (value, choice) = v[lhs].min_choice(v[rhs]);
                     v[out] = value;
-                    *choices.next().unwrap() |= choice;
+                    choices.push(choice);
                     simplify |= choice != Choice::Both;
                 }
                 RegOp::MaxRegReg(out, lhs, rhs) => {
                     let (value, choice) = v[lhs].max_choice(v[rhs]);
                     v[out] = value;
-                    *choices.next().unwrap() |= choice;
+                    choices.push(choice);
                     simplify |= choice != Choice::Both;
                 }
                 RegOp::CopyImm(out, imm) => {
@@ -488,298 +1304,305 @@ impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
     }
 }
 
-/// VM-based tracing evaluator for single points
-#[derive(Default)]
-pub struct VmPointEval<const N: usize>(TracingVmEval<f32>);
-impl<const N: usize> TracingEvaluator for VmPointEval<N> {
-    type Data = f32;
-    type Tape = GenericVmShape<N>;
-    type Trace = VmTrace;
-    type TapeStorage = ();
-
-    fn eval<F: Into<f32>>(
+/// Executes a single clause of point evaluation
+///
+/// Returns the choice made by the clause, if it's a choice (`min`, `max`,
+/// `and`, or `or`) operation.
+#[inline(always)]
+fn point_op(op: RegOp, v: &mut SlotArray<f32>, vars: &[f32]) -> Option<Choice> {
+    match op {
+        RegOp::Input(out, i) => {
+            v[out] = vars[i as usize];
+        }
+        RegOp::NegReg(out, arg) => {
+            v[out] = -v[arg];
+        }
+        RegOp::AbsReg(out, arg) => {
+            v[out] = v[arg].abs();
+        }
+        RegOp::RecipReg(out, arg) => {
+            v[out] = 1.0 / v[arg];
+        }
+        RegOp::SqrtReg(out, arg) => {
+            v[out] = v[arg].sqrt();
+        }
+        RegOp::SquareReg(out, arg) => {
+            let s = v[arg];
+            v[out] = s * s;
+        }
+        RegOp::FloorReg(out, arg) => {
+            v[out] = v[arg].floor();
+        }
+        RegOp::CeilReg(out, arg) => {
+            v[out] = v[arg].ceil();
+        }
+        RegOp::RoundReg(out, arg) => {
+            v[out] = v[arg].round();
+        }
+        RegOp::SinReg(out, arg) => {
+            v[out] = v[arg].sin();
+        }
+        RegOp::CosReg(out, arg) => {
+            v[out] = v[arg].cos();
+        }
+        RegOp::TanReg(out, arg) => {
+            v[out] = v[arg].tan();
+        }
+        RegOp::AsinReg(out, arg) => {
+            v[out] = v[arg].asin();
+        }
+        RegOp::AcosReg(out, arg) => {
+            v[out] = v[arg].acos();
+        }
+        RegOp::AtanReg(out, arg) => {
+            v[out] = v[arg].atan();
+        }
+        RegOp::ExpReg(out, arg) => {
+            v[out] = v[arg].exp();
+        }
+        RegOp::LnReg(out, arg) => {
+            v[out] = v[arg].ln();
+        }
+        RegOp::NotReg(out, arg) => v[out] = (v[arg] == 0.0).into(),
+        RegOp::CopyReg(out, arg) => {
+            v[out] = v[arg];
+        }
+        RegOp::AddRegImm(out, arg, imm) => {
+            v[out] = v[arg] + imm;
+        }
+        RegOp::MulRegImm(out, arg, imm) => {
+            v[out] = v[arg] * imm;
+        }
+        RegOp::DivRegImm(out, arg, imm) => {
+            v[out] = v[arg] / imm;
+        }
+        RegOp::DivImmReg(out, arg, imm) => {
+            v[out] = imm / v[arg];
+        }
+        RegOp::AtanRegImm(out, arg, imm) => {
+            v[out] = v[arg].atan2(imm);
+        }
+        RegOp::AtanImmReg(out, arg, imm) => {
+            v[out] = imm.atan2(v[arg]);
+        }
+        RegOp::AtanRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs].atan2(v[rhs]);
+        }
+        RegOp::SubImmReg(out, arg, imm) => {
+            v[out] = imm - v[arg];
+        }
+        RegOp::SubRegImm(out, arg, imm) => {
+            v[out] = v[arg] - imm;
+        }
+        RegOp::MinRegImm(out, arg, imm) => {
+            let a = v[arg];
+            let (choice, value) = if a < imm {
+                (Choice::Left, a)
+            } else if imm < a {
+                (Choice::Right, imm)
+            } else {
+                (
+                    Choice::Both,
+                    if a.is_nan() || imm.is_nan() {
+                        f32::NAN
+                    } else {
+                        imm
+                    },
+                )
+            };
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::MaxRegImm(out, arg, imm) => {
+            let a = v[arg];
+            let (choice, value) = if a > imm {
+                (Choice::Left, a)
+            } else if imm > a {
+                (Choice::Right, imm)
+            } else {
+                (
+                    Choice::Both,
+                    if a.is_nan() || imm.is_nan() {
+                        f32::NAN
+                    } else {
+                        imm
+                    },
+                )
+            };
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::AndRegImm(out, arg, imm) => {
+            let a = v[arg];
+            let (choice, value) = if a == 0.0 {
+                (Choice::Left, a)
+            } else {
+                (Choice::Right, imm)
+            };
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::OrRegImm(out, arg, imm) => {
+            let a = v[arg];
+            let (choice, value) = if a != 0.0 {
+                (Choice::Left, a)
+            } else {
+                (Choice::Right, imm)
+            };
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::ModRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs].rem_euclid(v[rhs]);
+        }
+        RegOp::ModRegImm(out, arg, imm) => {
+            v[out] = v[arg].rem_euclid(imm);
+        }
+        RegOp::ModImmReg(out, arg, imm) => {
+            v[out] = imm.rem_euclid(v[arg]);
+        }
+        RegOp::AddRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs] + v[rhs];
+        }
+        RegOp::MulRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs] * v[rhs];
+        }
+        RegOp::DivRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs] / v[rhs];
+        }
+        RegOp::CompareRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs]
+                .partial_cmp(&v[rhs])
+                .map(|c| c as i8 as f32)
+                .unwrap_or(f32::NAN)
+        }
+        RegOp::CompareRegImm(out, arg, imm) => {
+            v[out] = v[arg]
+                .partial_cmp(&imm)
+                .map(|c| c as i8 as f32)
+                .unwrap_or(f32::NAN)
+        }
+        RegOp::CompareImmReg(out, arg, imm) => {
+            v[out] = imm
+                .partial_cmp(&v[arg])
+                .map(|c| c as i8 as f32)
+                .unwrap_or(f32::NAN)
+        }
+        RegOp::SubRegReg(out, lhs, rhs) => {
+            v[out] = v[lhs] - v[rhs];
+        }
+        RegOp::MinRegReg(out, lhs, rhs) => {
+            let a = v[lhs];
+            let b = v[rhs];
+            let (choice, value) = if a < b {
+                (Choice::Left, a)
+            } else if b < a {
+                (Choice::Right, b)
+            } else {
+                (
+                    Choice::Both,
+                    if a.is_nan() || b.is_nan() {
+                        f32::NAN
+                    } else {
+                        b
+                    },
+                )
+            };
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::MaxRegReg(out, lhs, rhs) => {
+            let a = v[lhs];
+            let b = v[rhs];
+            let (choice, value) = if a > b {
+                (Choice::Left, a)
+            } else if b > a {
+                (Choice::Right, b)
+            } else {
+                (
+                    Choice::Both,
+                    if a.is_nan() || b.is_nan() {
+                        f32::NAN
+                    } else {
+                        b
+                    },
+                )
+            };
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::AndRegReg(out, lhs, rhs) => {
+            let a = v[lhs];
+            let b = v[rhs];
+            let (choice, value) = if a == 0.0 {
+                (Choice::Left, a)
+            } else {
+                (Choice::Right, b)
+            };
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::OrRegReg(out, lhs, rhs) => {
+            let a = v[lhs];
+            let b = v[rhs];
+            let (choice, value) = if a != 0.0 {
+                (Choice::Left, a)
+            } else {
+                (Choice::Right, b)
+            };
+            v[out] = value;
+            return Some(choice);
+        }
+        RegOp::CopyImm(out, imm) => {
+            v[out] = imm;
+        }
+        RegOp::Load(out, mem) => {
+            v[out] = v[mem];
+        }
+        RegOp::Store(out, mem) => {
+            v[mem] = v[out];
+        }
+    }
+    None
+}
+
+/// VM-based tracing evaluator for single points
+#[derive(Default)]
+pub struct VmPointEval<const N: usize>(TracingVmEval<f32>);
+impl<const N: usize> TracingEvaluator for VmPointEval<N> {
+    type Data = f32;
+    type Tape = GenericVmShape<N>;
+    type Trace = VmTrace;
+    type TapeStorage = ();
+
+    fn eval<F: Into<f32>>(
         &mut self,
         tape: &Self::Tape,
         x: F,
         y: F,
         z: F,
     ) -> Result<(f32, Option<&VmTrace>), Error> {
-        let x = x.into();
-        let y = y.into();
-        let z = z.into();
+        self.eval_vars(tape, &[x.into(), y.into(), z.into()])
+    }
+
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[f32],
+    ) -> Result<(f32, Option<&VmTrace>), Error> {
         let tape = tape.0.as_ref();
-        self.check_arguments(tape.var_count())?;
+        check_vars(vars, tape.var_count())?;
+        #[cfg(feature = "stats")]
+        crate::stats::record_eval(Flavor::Point, tape.len(), 1);
         self.0.resize_slots(tape);
 
-        let mut choices = self.0.choices.as_mut_slice().iter_mut();
+        let mut choices = self.0.choices.choices_mut().cursor();
         let mut simplify = false;
         let mut v = SlotArray(&mut self.0.slots);
         for op in tape.iter_asm() {
-            match op {
-                RegOp::Input(out, i) => {
-                    v[out] = match i {
-                        0 => x,
-                        1 => y,
-                        2 => z,
-                        _ => panic!("Invalid input: {}", i),
-                    }
-                }
-                RegOp::NegReg(out, arg) => {
-                    v[out] = -v[arg];
-                }
-                RegOp::AbsReg(out, arg) => {
-                    v[out] = v[arg].abs();
-                }
-                RegOp::RecipReg(out, arg) => {
-                    v[out] = 1.0 / v[arg];
-                }
-                RegOp::SqrtReg(out, arg) => {
-                    v[out] = v[arg].sqrt();
-                }
-                RegOp::SquareReg(out, arg) => {
-                    let s = v[arg];
-                    v[out] = s * s;
-                }
-                RegOp::FloorReg(out, arg) => {
-                    v[out] = v[arg].floor();
-                }
-                RegOp::CeilReg(out, arg) => {
-                    v[out] = v[arg].ceil();
-                }
-                RegOp::RoundReg(out, arg) => {
-                    v[out] = v[arg].round();
-                }
-                RegOp::SinReg(out, arg) => {
-                    v[out] = v[arg].sin();
-                }
-                RegOp::CosReg(out, arg) => {
-                    v[out] = v[arg].cos();
-                }
-                RegOp::TanReg(out, arg) => {
-                    v[out] = v[arg].tan();
-                }
-                RegOp::AsinReg(out, arg) => {
-                    v[out] = v[arg].asin();
-                }
-                RegOp::AcosReg(out, arg) => {
-                    v[out] = v[arg].acos();
-                }
-                RegOp::AtanReg(out, arg) => {
-                    v[out] = v[arg].atan();
-                }
-                RegOp::ExpReg(out, arg) => {
-                    v[out] = v[arg].exp();
-                }
-                RegOp::LnReg(out, arg) => {
-                    v[out] = v[arg].ln();
-                }
-                RegOp::NotReg(out, arg) => v[out] = (v[arg] == 0.0).into(),
-                RegOp::CopyReg(out, arg) => {
-                    v[out] = v[arg];
-                }
-                RegOp::AddRegImm(out, arg, imm) => {
-                    v[out] = v[arg] + imm;
-                }
-                RegOp::MulRegImm(out, arg, imm) => {
-                    v[out] = v[arg] * imm;
-                }
-                RegOp::DivRegImm(out, arg, imm) => {
-                    v[out] = v[arg] / imm;
-                }
-                RegOp::DivImmReg(out, arg, imm) => {
-                    v[out] = imm / v[arg];
-                }
-                RegOp::AtanRegImm(out, arg, imm) => {
-                    v[out] = v[arg].atan2(imm.into());
-                }
-                RegOp::AtanImmReg(out, arg, imm) => {
-                    v[out] = imm.atan2(v[arg]);
-                }
-                RegOp::AtanRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs].atan2(v[rhs]);
-                }
-                RegOp::SubImmReg(out, arg, imm) => {
-                    v[out] = imm - v[arg];
-                }
-                RegOp::SubRegImm(out, arg, imm) => {
-                    v[out] = v[arg] - imm;
-                }
-                RegOp::MinRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a < imm {
-                        (Choice::Left, a)
-                    } else if imm < a {
-                        (Choice::Right, imm)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || imm.is_nan() {
-                                f32::NAN
-                            } else {
-                                imm
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a > imm {
-                        (Choice::Left, a)
-                    } else if imm > a {
-                        (Choice::Right, imm)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || imm.is_nan() {
-                                f32::NAN
-                            } else {
-                                imm
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a == 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, imm)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a != 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, imm)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::ModRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs].rem_euclid(v[rhs]);
-                }
-                RegOp::ModRegImm(out, arg, imm) => {
-                    v[out] = v[arg].rem_euclid(imm);
-                }
-                RegOp::ModImmReg(out, arg, imm) => {
-                    v[out] = imm.rem_euclid(v[arg]);
-                }
-                RegOp::AddRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] + v[rhs];
-                }
-                RegOp::MulRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] * v[rhs];
-                }
-                RegOp::DivRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] / v[rhs];
-                }
-                RegOp::CompareRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs]
-                        .partial_cmp(&v[rhs])
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::CompareRegImm(out, arg, imm) => {
-                    v[out] = v[arg]
-                        .partial_cmp(&imm)
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::CompareImmReg(out, arg, imm) => {
-                    v[out] = imm
-                        .partial_cmp(&v[arg])
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::SubRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] - v[rhs];
-                }
-                RegOp::MinRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a < b {
-                        (Choice::Left, a)
-                    } else if b < a {
-                        (Choice::Right, b)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || b.is_nan() {
-                                f32::NAN
-                            } else {
-                                b
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a > b {
-                        (Choice::Left, a)
-                    } else if b > a {
-                        (Choice::Right, b)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || b.is_nan() {
-                                f32::NAN
-                            } else {
-                                b
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a == 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, b)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a != 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, b)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::CopyImm(out, imm) => {
-                    v[out] = imm;
-                }
-                RegOp::Load(out, mem) => {
-                    v[out] = v[mem];
-                }
-                RegOp::Store(out, mem) => {
-                    v[mem] = v[out];
-                }
+            if let Some(choice) = point_op(op, &mut v, vars) {
+                choices.push(choice);
+                simplify |= choice != Choice::Both;
             }
         }
         Ok((
@@ -814,42 +1637,117 @@ impl<T: From<f32> + Clone> BulkVmEval<T> {
 }
 
 /// VM-based bulk evaluator for arrays of points, yielding point values
+///
+/// Points are evaluated eight at a time with SIMD instructions.
+///
+/// In addition to the [`BulkEvaluator`] API, this evaluator can record the
+/// choices made across a batch of points (see
+/// [`eval_traced`](Self::eval_traced)).
 #[derive(Default)]
-pub struct VmFloatSliceEval<const N: usize>(BulkVmEval<f32>);
-impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
-    type Data = f32;
-    type Tape = GenericVmShape<N>;
-    type TapeStorage = ();
+pub struct VmFloatSliceEval<const N: usize> {
+    eval: BulkVmEval<f32x8>,
 
-    fn eval(
+    /// Choices accumulated across every point, if tracing
+    choices: VmTrace,
+}
+impl<const N: usize> VmFloatSliceEval<N> {
+    /// Evaluates many points, also recording the choices that they make
+    ///
+    /// Each `min` / `max` / `and` / `or` clause records the union of the
+    /// choices made at every point, e.g. [`Choice::Both`] if some points pick
+    /// the left-hand input and others pick the right.  The resulting trace can
+    /// be passed to [`Shape::simplify`] to build a tape which is valid for
+    /// these points; unlike a trace from interval evaluation, it's **not**
+    /// guaranteed to be valid anywhere else (e.g. elsewhere in a tile where
+    /// the points were sampled).
+    ///
+    /// As with other tracing evaluators, the trace is `None` if no
+    /// simplification is possible (including if the slices are empty).
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::{EzShape, MathShape, Shape},
+    ///     vm::VmShape,
+    /// };
+    ///
+    /// let (x, y, _z) = Tree::axes();
+    /// let shape = VmShape::from_tree(&x.min(y));
+    /// let mut eval = VmShape::new_float_slice_eval();
+    /// let tape = shape.ez_float_slice_tape();
+    ///
+    /// // X is always less than Y at these points, so the `min` picks X
+    /// let (out, trace) =
+    ///     eval.eval_traced(&tape, &[0.0, 1.0], &[2.0, 3.0], &[0.0, 0.0])?;
+    /// assert_eq!(out, [0.0, 1.0]);
+    /// let next = shape.ez_simplify(trace.unwrap())?;
+    /// assert!(next.size() < shape.size());
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn eval_traced(
         &mut self,
-        tape: &Self::Tape,
+        tape: &GenericVmShape<N>,
         xs: &[f32],
         ys: &[f32],
         zs: &[f32],
-    ) -> Result<&[f32], Error> {
+    ) -> Result<(&[f32], Option<&VmTrace>), Error> {
         let tape = tape.0.as_ref();
         self.check_arguments(xs, ys, zs, tape.var_count())?;
-        self.0.resize_slots(tape, xs.len());
-        assert_eq!(xs.len(), ys.len());
-        assert_eq!(ys.len(), zs.len());
+        self.choices.resize(tape.choice_count(), Choice::Unknown);
+        self.choices.fill(Choice::Unknown);
+        self.run(tape, &[xs, ys, zs], xs.len(), true);
+        let simplify = !xs.is_empty()
+            && self.choices.choices().iter().any(|c| c != Choice::Both);
+        Ok((
+            self.result(xs.len()),
+            if simplify { Some(&self.choices) } else { None },
+        ))
+    }
 
-        let size = xs.len();
+    /// Evaluates the tape, leaving the result in slot 0
+    ///
+    /// Each slot is padded to a whole number of SIMD vectors; values in the
+    /// padding are unspecified.
+    /// `vars` has one (strided) slice per input, each of length `n`.
+    ///
+    /// If `trace` is set, choices are accumulated into `self.choices`, which
+    /// must be sized for the tape and filled with [`Choice::Unknown`].
+    fn run<'a, S: Copy + Into<Strided<'a, f32>>>(
+        &mut self,
+        tape: &VmData<N>,
+        vars: &[S],
+        n: usize,
+        trace: bool,
+    ) {
+        #[cfg(feature = "stats")]
+        crate::stats::record_eval(Flavor::FloatSlice, tape.len(), n);
+        let size = n.div_ceil(simd::LANES);
+        self.eval.resize_slots(tape, size);
 
-        let mut v = SlotArray(&mut self.0.slots);
+        // Number of valid lanes in SIMD vector `i`
+        let lanes = |i: usize| (n - i * simd::LANES).min(simd::LANES);
+        let choices = self.choices.choices_mut();
+        let mut c = 0;
+
+        let mut v = SlotArray(&mut self.eval.slots);
         for op in tape.iter_asm() {
             match op {
                 RegOp::Input(out, i) => {
-                    v[out][0..size].copy_from_slice(match i {
-                        0 => xs,
-                        1 => ys,
-                        2 => zs,
-                        _ => panic!("Invalid input: {}", i),
-                    })
+                    let s: Strided<f32> = vars[i as usize].into();
+                    debug_assert_eq!(s.len(), n);
+                    let o: &mut [f32] = bytemuck::cast_slice_mut(&mut v[out]);
+                    match s.as_slice() {
+                        Some(s) => o[..s.len()].copy_from_slice(s),
+                        None => {
+                            for (o, s) in o.iter_mut().zip(s.iter()) {
+                                *o = *s;
+                            }
+                        }
+                    }
                 }
                 RegOp::NegReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = -v[arg][i];
+                        v[out][i] = simd::neg(v[arg][i]);
                     }
                 }
                 RegOp::AbsReg(out, arg) => {
@@ -859,7 +1757,7 @@ impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
                 }
                 RegOp::RecipReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = 1.0 / v[arg][i];
+                        v[out][i] = f32x8::ONE / v[arg][i];
                     }
                 }
                 RegOp::SqrtReg(out, arg) => {
@@ -875,62 +1773,62 @@ impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
                 }
                 RegOp::FloorReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].floor();
+                        v[out][i] = simd::lanes(v[arg][i], f32::floor);
                     }
                 }
                 RegOp::CeilReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].ceil();
+                        v[out][i] = simd::lanes(v[arg][i], f32::ceil);
                     }
                 }
                 RegOp::RoundReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].round();
+                        v[out][i] = simd::lanes(v[arg][i], f32::round);
                     }
                 }
                 RegOp::SinReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].sin();
+                        v[out][i] = simd::lanes(v[arg][i], f32::sin);
                     }
                 }
                 RegOp::CosReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].cos();
+                        v[out][i] = simd::lanes(v[arg][i], f32::cos);
                     }
                 }
                 RegOp::TanReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].tan();
+                        v[out][i] = simd::lanes(v[arg][i], f32::tan);
                     }
                 }
                 RegOp::AsinReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].asin();
+                        v[out][i] = simd::lanes(v[arg][i], f32::asin);
                     }
                 }
                 RegOp::AcosReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].acos();
+                        v[out][i] = simd::lanes(v[arg][i], f32::acos);
                     }
                 }
                 RegOp::AtanReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].atan();
+                        v[out][i] = simd::lanes(v[arg][i], f32::atan);
                     }
                 }
                 RegOp::ExpReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].exp();
+                        v[out][i] = simd::lanes(v[arg][i], f32::exp);
                     }
                 }
                 RegOp::LnReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].ln();
+                        v[out][i] = simd::lanes(v[arg][i], f32::ln);
                     }
                 }
                 RegOp::NotReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = (v[arg][i] == 0.0).into();
+                        v[out][i] = simd::not(v[arg][i]);
                     }
                 }
                 RegOp::CopyReg(out, arg) => {
@@ -954,26 +1852,29 @@ impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
                     }
                 }
                 RegOp::DivImmReg(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
                         v[out][i] = imm / v[arg][i];
                     }
                 }
                 RegOp::AtanRegImm(out, arg, imm) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].atan2(imm);
+                        v[out][i] = simd::lanes(v[arg][i], |a| a.atan2(imm));
                     }
                 }
                 RegOp::AtanImmReg(out, arg, imm) => {
                     for i in 0..size {
-                        v[out][i] = imm.atan2(v[arg][i]);
+                        v[out][i] = simd::lanes(v[arg][i], |a| imm.atan2(a));
                     }
                 }
                 RegOp::AtanRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                        v[out][i] =
+                            simd::lanes2(v[lhs][i], v[rhs][i], f32::atan2);
                     }
                 }
                 RegOp::SubImmReg(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
                         v[out][i] = imm - v[arg][i];
                     }
@@ -984,64 +1885,77 @@ impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
                     }
                 }
                 RegOp::CompareImmReg(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
-                        v[out][i] = imm
-                            .partial_cmp(&v[arg][i])
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN)
+                        v[out][i] = simd::compare(imm, v[arg][i]);
                     }
                 }
                 RegOp::CompareRegImm(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
-                        v[out][i] = v[arg][i]
-                            .partial_cmp(&imm)
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN)
+                        v[out][i] = simd::compare(v[arg][i], imm);
                     }
                 }
                 RegOp::MinRegImm(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
-                        v[out][i] = if v[arg][i].is_nan() || imm.is_nan() {
-                            f32::NAN
-                        } else {
-                            v[arg][i].min(imm)
-                        };
+                        if trace {
+                            let k = simd::min_choice(v[arg][i], imm, lanes(i));
+                            choices.or(c, k);
+                        }
+                        v[out][i] = simd::min(v[arg][i], imm);
                     }
+                    c += 1;
                 }
                 RegOp::MaxRegImm(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
-                        v[out][i] = if v[arg][i].is_nan() || imm.is_nan() {
-                            f32::NAN
-                        } else {
-                            v[arg][i].max(imm)
-                        };
+                        if trace {
+                            let k = simd::max_choice(v[arg][i], imm, lanes(i));
+                            choices.or(c, k);
+                        }
+                        v[out][i] = simd::max(v[arg][i], imm);
                     }
+                    c += 1;
                 }
                 RegOp::AndRegImm(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
-                        v[out][i] =
-                            if v[arg][i] == 0.0 { v[arg][i] } else { imm };
+                        if trace {
+                            let k = simd::and_choice(v[arg][i], imm, lanes(i));
+                            choices.or(c, k);
+                        }
+                        v[out][i] = simd::and(v[arg][i], imm);
                     }
+                    c += 1;
                 }
                 RegOp::OrRegImm(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
-                        v[out][i] =
-                            if v[arg][i] != 0.0 { v[arg][i] } else { imm };
+                        if trace {
+                            let k = simd::or_choice(v[arg][i], imm, lanes(i));
+                            choices.or(c, k);
+                        }
+                        v[out][i] = simd::or(v[arg][i], imm);
                     }
+                    c += 1;
                 }
                 RegOp::ModRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                        v[out][i] =
+                            simd::lanes2(v[lhs][i], v[rhs][i], f32::rem_euclid);
                     }
                 }
                 RegOp::ModRegImm(out, arg, imm) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].rem_euclid(imm);
+                        v[out][i] =
+                            simd::lanes(v[arg][i], |a| a.rem_euclid(imm));
                     }
                 }
                 RegOp::ModImmReg(out, arg, imm) => {
                     for i in 0..size {
-                        v[out][i] = imm.rem_euclid(v[arg][i]);
+                        v[out][i] =
+                            simd::lanes(v[arg][i], |a| imm.rem_euclid(a));
                     }
                 }
                 RegOp::AddRegReg(out, lhs, rhs) => {
@@ -1066,51 +1980,64 @@ impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
                 }
                 RegOp::CompareRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = v[lhs][i]
-                            .partial_cmp(&v[rhs][i])
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN)
+                        v[out][i] = simd::compare(v[lhs][i], v[rhs][i]);
                     }
                 }
                 RegOp::MinRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = if v[lhs][i].is_nan() || v[rhs][i].is_nan()
-                        {
-                            f32::NAN
-                        } else {
-                            v[lhs][i].min(v[rhs][i])
-                        };
+                        if trace {
+                            let k = simd::min_choice(
+                                v[lhs][i],
+                                v[rhs][i],
+                                lanes(i),
+                            );
+                            choices.or(c, k);
+                        }
+                        v[out][i] = simd::min(v[lhs][i], v[rhs][i]);
                     }
+                    c += 1;
                 }
                 RegOp::MaxRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = if v[lhs][i].is_nan() || v[rhs][i].is_nan()
-                        {
-                            f32::NAN
-                        } else {
-                            v[lhs][i].max(v[rhs][i])
-                        };
+                        if trace {
+                            let k = simd::max_choice(
+                                v[lhs][i],
+                                v[rhs][i],
+                                lanes(i),
+                            );
+                            choices.or(c, k);
+                        }
+                        v[out][i] = simd::max(v[lhs][i], v[rhs][i]);
                     }
+                    c += 1;
                 }
                 RegOp::AndRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = if v[lhs][i] == 0.0 {
-                            v[lhs][i]
-                        } else {
-                            v[rhs][i]
-                        };
+                        if trace {
+                            let k = simd::and_choice(
+                                v[lhs][i],
+                                v[rhs][i],
+                                lanes(i),
+                            );
+                            choices.or(c, k);
+                        }
+                        v[out][i] = simd::and(v[lhs][i], v[rhs][i]);
                     }
+                    c += 1;
                 }
                 RegOp::OrRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = if v[lhs][i] != 0.0 {
-                            v[lhs][i]
-                        } else {
-                            v[rhs][i]
-                        };
+                        if trace {
+                            let k =
+                                simd::or_choice(v[lhs][i], v[rhs][i], lanes(i));
+                            choices.or(c, k);
+                        }
+                        v[out][i] = simd::or(v[lhs][i], v[rhs][i]);
                     }
+                    c += 1;
                 }
                 RegOp::CopyImm(out, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
                         v[out][i] = imm;
                     }
@@ -1127,15 +2054,16 @@ impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
                 }
             }
         }
-        Ok(&self.0.slots[0])
+    }
+
+    /// Returns the result of the most recent evaluation
+    fn result(&self, size: usize) -> &[f32] {
+        &bytemuck::cast_slice(&self.eval.slots[0])[..size]
     }
 }
 
-/// VM-based bulk evaluator for arrays of points, yielding gradient values
-#[derive(Default)]
-pub struct VmGradSliceEval<const N: usize>(BulkVmEval<Grad>);
-impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
-    type Data = Grad;
+impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
+    type Data = f32;
     type Tape = GenericVmShape<N>;
     type TapeStorage = ();
 
@@ -1145,25 +2073,100 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
         xs: &[f32],
         ys: &[f32],
         zs: &[f32],
-    ) -> Result<&[Grad], Error> {
+    ) -> Result<&[f32], Error> {
         let tape = tape.0.as_ref();
         self.check_arguments(xs, ys, zs, tape.var_count())?;
-        self.0.resize_slots(tape, xs.len());
-        assert_eq!(xs.len(), ys.len());
-        assert_eq!(ys.len(), zs.len());
+        self.run(tape, &[xs, ys, zs], xs.len(), false);
+        Ok(self.result(xs.len()))
+    }
 
-        let size = xs.len();
-        let mut v = SlotArray(&mut self.0.slots);
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[&[f32]],
+    ) -> Result<&[f32], Error> {
+        let tape = tape.0.as_ref();
+        let n = check_var_slices(vars, tape.var_count())?;
+        self.run(tape, vars, n, false);
+        Ok(self.result(n))
+    }
+
+    fn eval_strided(
+        &mut self,
+        tape: &Self::Tape,
+        xs: Strided<f32>,
+        ys: Strided<f32>,
+        zs: Strided<f32>,
+        mut out: StridedMut<f32>,
+    ) -> Result<(), Error> {
+        let tape = tape.0.as_ref();
+        self.check_strided_arguments(&xs, &ys, &zs, &out)?;
+        if tape.var_count() > 3 {
+            return Err(Error::BadVarSlice(3, tape.var_count()));
+        }
+        self.run(tape, &[xs, ys, zs], xs.len(), false);
+        for (i, v) in self.result(xs.len()).iter().enumerate() {
+            out[i] = *v;
+        }
+        Ok(())
+    }
+}
+
+/// VM-based bulk evaluator for arrays of points, yielding gradient values
+///
+/// Points are evaluated eight at a time with SIMD instructions.
+#[derive(Default)]
+pub struct VmGradSliceEval<const N: usize> {
+    eval: BulkVmEval<GradX8>,
+
+    /// Output array, unpacked from slot 0 after evaluation
+    out: Vec<Grad>,
+}
+impl<const N: usize> VmGradSliceEval<N> {
+    /// Evaluates the tape, unpacking the result into `self.out`
+    ///
+    /// `vars` has one (strided) slice per input, each of length `n`.  The
+    /// first three inputs are treated as the `X`, `Y`, and `Z` axes when
+    /// seeding partial derivatives; later inputs have no partial derivatives.
+    fn run<'a, S: Copy + Into<Strided<'a, f32>>>(
+        &mut self,
+        tape: &VmData<N>,
+        vars: &[S],
+        n: usize,
+    ) {
+        #[cfg(feature = "stats")]
+        crate::stats::record_eval(Flavor::GradSlice, tape.len(), n);
+        let size = n.div_ceil(simd::LANES);
+        self.eval.resize_slots(tape, size);
+
+        let mut v = SlotArray(&mut self.eval.slots);
         for op in tape.iter_asm() {
             match op {
                 RegOp::Input(out, j) => {
-                    for i in 0..size {
-                        v[out][i] = match j {
-                            0 => Grad::new(xs[i], 1.0, 0.0, 0.0),
-                            1 => Grad::new(ys[i], 0.0, 1.0, 0.0),
-                            2 => Grad::new(zs[i], 0.0, 0.0, 1.0),
-                            _ => panic!("Invalid input: {}", i),
+                    let s: Strided<f32> = vars[j as usize].into();
+                    debug_assert_eq!(s.len(), n);
+                    let d: [f32x8; 3] = std::array::from_fn(|k| {
+                        if k == j as usize {
+                            f32x8::ONE
+                        } else {
+                            f32x8::ZERO
                         }
+                    });
+                    for i in 0..size {
+                        let v_: [f32; simd::LANES] = std::array::from_fn(|k| {
+                            let k = i * simd::LANES + k;
+                            if k < s.len() {
+                                s[k]
+                            } else {
+                                0.0
+                            }
+                        });
+                        v[out][i] = GradX8 {
+                            v: v_.into(),
+                            dx: d[0],
+                            dy: d[1],
+                            dz: d[2],
+                        };
                     }
                 }
                 RegOp::NegReg(out, arg) => {
@@ -1177,7 +2180,7 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
                     }
                 }
                 RegOp::RecipReg(out, arg) => {
-                    let one: Grad = 1.0.into();
+                    let one = GradX8::from(1.0);
                     for i in 0..size {
                         v[out][i] = one / v[arg][i];
                     }
@@ -1195,62 +2198,62 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
                 }
                 RegOp::FloorReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].floor();
+                        v[out][i] = v[arg][i].lanes(|g| g.floor());
                     }
                 }
                 RegOp::CeilReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].ceil();
+                        v[out][i] = v[arg][i].lanes(|g| g.ceil());
                     }
                 }
                 RegOp::RoundReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].round();
+                        v[out][i] = v[arg][i].lanes(|g| g.round());
                     }
                 }
                 RegOp::SinReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].sin();
+                        v[out][i] = v[arg][i].lanes(Grad::sin);
                     }
                 }
                 RegOp::CosReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].cos();
+                        v[out][i] = v[arg][i].lanes(Grad::cos);
                     }
                 }
                 RegOp::TanReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].tan();
+                        v[out][i] = v[arg][i].lanes(Grad::tan);
                     }
                 }
                 RegOp::AsinReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].asin();
+                        v[out][i] = v[arg][i].lanes(Grad::asin);
                     }
                 }
                 RegOp::AcosReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].acos();
+                        v[out][i] = v[arg][i].lanes(Grad::acos);
                     }
                 }
                 RegOp::AtanReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].atan();
+                        v[out][i] = v[arg][i].lanes(Grad::atan);
                     }
                 }
                 RegOp::ExpReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].exp();
+                        v[out][i] = v[arg][i].lanes(Grad::exp);
                     }
                 }
                 RegOp::LnReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = v[arg][i].ln();
+                        v[out][i] = v[arg][i].lanes(Grad::ln);
                     }
                 }
                 RegOp::NotReg(out, arg) => {
                     for i in 0..size {
-                        v[out][i] = f32::from(v[arg][i].v == 0.0).into();
+                        v[out][i] = GradX8::constant(simd::not(v[arg][i].v));
                     }
                 }
                 RegOp::CopyReg(out, arg) => {
@@ -1259,22 +2262,25 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
                     }
                 }
                 RegOp::AddRegImm(out, arg, imm) => {
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
-                        v[out][i] = v[arg][i] + imm.into();
+                        v[out][i] = v[arg][i] + imm;
                     }
                 }
                 RegOp::MulRegImm(out, arg, imm) => {
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
-                        v[out][i] = v[arg][i] * imm.into();
+                        v[out][i] = v[arg][i] * imm;
                     }
                 }
                 RegOp::DivRegImm(out, arg, imm) => {
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
-                        v[out][i] = v[arg][i] / imm.into();
+                        v[out][i] = v[arg][i] / imm;
                     }
                 }
                 RegOp::DivImmReg(out, arg, imm) => {
-                    let imm = Grad::from(imm);
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
                         v[out][i] = imm / v[arg][i];
                     }
@@ -1282,84 +2288,74 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
                 RegOp::AtanRegImm(out, arg, imm) => {
                     let imm = Grad::from(imm);
                     for i in 0..size {
-                        v[out][i] = v[arg][i].atan2(imm);
+                        v[out][i] = v[arg][i].lanes(|g| g.atan2(imm));
                     }
                 }
                 RegOp::AtanImmReg(out, arg, imm) => {
                     let imm = Grad::from(imm);
                     for i in 0..size {
-                        v[out][i] = imm.atan2(v[arg][i]);
+                        v[out][i] = v[arg][i].lanes(|g| imm.atan2(g));
                     }
                 }
                 RegOp::AtanRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                        v[out][i] = v[lhs][i].lanes2(v[rhs][i], Grad::atan2);
                     }
                 }
                 RegOp::SubImmReg(out, arg, imm) => {
-                    let imm: Grad = imm.into();
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
                         v[out][i] = imm - v[arg][i];
                     }
                 }
                 RegOp::SubRegImm(out, arg, imm) => {
-                    let imm: Grad = imm.into();
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
                         v[out][i] = v[arg][i] - imm;
                     }
                 }
                 RegOp::CompareImmReg(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
-                        let p = imm
-                            .partial_cmp(&v[arg][i].v)
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN);
-                        v[out][i] = Grad::new(p, 0.0, 0.0, 0.0);
+                        v[out][i] =
+                            GradX8::constant(simd::compare(imm, v[arg][i].v));
                     }
                 }
                 RegOp::CompareRegImm(out, arg, imm) => {
+                    let imm = f32x8::splat(imm);
                     for i in 0..size {
-                        let p = v[arg][i]
-                            .v
-                            .partial_cmp(&imm)
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN);
-                        v[out][i] = Grad::new(p, 0.0, 0.0, 0.0);
+                        v[out][i] =
+                            GradX8::constant(simd::compare(v[arg][i].v, imm));
                     }
                 }
                 RegOp::MinRegImm(out, arg, imm) => {
-                    let imm: Grad = imm.into();
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
-                        v[out][i] = if v[arg][i].v.is_nan() || imm.v.is_nan() {
-                            f32::NAN.into()
-                        } else {
-                            v[arg][i].min(imm)
-                        };
+                        v[out][i] = v[arg][i].min(imm);
                     }
                 }
                 RegOp::MaxRegImm(out, arg, imm) => {
-                    let imm: Grad = imm.into();
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
-                        v[out][i] = if v[arg][i].v.is_nan() || imm.v.is_nan() {
-                            f32::NAN.into()
-                        } else {
-                            v[arg][i].max(imm)
-                        };
+                        v[out][i] = v[arg][i].max(imm);
                     }
                 }
                 RegOp::ModRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                        v[out][i] =
+                            v[lhs][i].lanes2(v[rhs][i], |a, b| a.rem_euclid(b));
                     }
                 }
                 RegOp::ModRegImm(out, arg, imm) => {
+                    let imm = Grad::from(imm);
                     for i in 0..size {
-                        v[out][i] = v[arg][i].rem_euclid(imm.into());
+                        v[out][i] = v[arg][i].lanes(|g| g.rem_euclid(imm));
                     }
                 }
                 RegOp::ModImmReg(out, arg, imm) => {
+                    let imm = Grad::from(imm);
                     for i in 0..size {
-                        v[out][i] = Grad::from(imm).rem_euclid(v[arg][i]);
+                        v[out][i] = v[arg][i].lanes(|g| imm.rem_euclid(g));
                     }
                 }
                 RegOp::AddRegReg(out, lhs, rhs) => {
@@ -1372,40 +2368,26 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
                         v[out][i] = v[lhs][i] * v[rhs][i];
                     }
                 }
-                RegOp::AndRegReg(out, lhs, rhs) => {
+                RegOp::AndRegImm(out, arg, imm) => {
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
-                        v[out][i] = if v[lhs][i].v == 0.0 {
-                            v[lhs][i]
-                        } else {
-                            v[rhs][i]
-                        };
+                        v[out][i] = v[arg][i].and(imm);
                     }
                 }
-                RegOp::AndRegImm(out, arg, imm) => {
+                RegOp::AndRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = if v[arg][i].v == 0.0 {
-                            v[arg][i]
-                        } else {
-                            imm.into()
-                        };
+                        v[out][i] = v[lhs][i].and(v[rhs][i]);
                     }
                 }
-                RegOp::OrRegReg(out, lhs, rhs) => {
+                RegOp::OrRegImm(out, arg, imm) => {
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
-                        v[out][i] = if v[lhs][i].v != 0.0 {
-                            v[lhs][i]
-                        } else {
-                            v[rhs][i]
-                        };
+                        v[out][i] = v[arg][i].or(imm);
                     }
                 }
-                RegOp::OrRegImm(out, arg, imm) => {
+                RegOp::OrRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] = if v[arg][i].v != 0.0 {
-                            v[arg][i]
-                        } else {
-                            imm.into()
-                        };
+                        v[out][i] = v[lhs][i].or(v[rhs][i]);
                     }
                 }
                 RegOp::DivRegReg(out, lhs, rhs) => {
@@ -1420,36 +2402,24 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
                 }
                 RegOp::CompareRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        let p = v[lhs][i]
-                            .v
-                            .partial_cmp(&v[rhs][i].v)
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN);
-                        v[out][i] = Grad::new(p, 0.0, 0.0, 0.0);
+                        v[out][i] = GradX8::constant(simd::compare(
+                            v[lhs][i].v,
+                            v[rhs][i].v,
+                        ));
                     }
                 }
                 RegOp::MinRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] =
-                            if v[lhs][i].v.is_nan() || v[rhs][i].v.is_nan() {
-                                f32::NAN.into()
-                            } else {
-                                v[lhs][i].min(v[rhs][i])
-                            };
+                        v[out][i] = v[lhs][i].min(v[rhs][i]);
                     }
                 }
                 RegOp::MaxRegReg(out, lhs, rhs) => {
                     for i in 0..size {
-                        v[out][i] =
-                            if v[lhs][i].v.is_nan() || v[rhs][i].v.is_nan() {
-                                f32::NAN.into()
-                            } else {
-                                v[lhs][i].max(v[rhs][i])
-                            };
+                        v[out][i] = v[lhs][i].max(v[rhs][i]);
                     }
                 }
                 RegOp::CopyImm(out, imm) => {
-                    let imm: Grad = imm.into();
+                    let imm = GradX8::from(imm);
                     for i in 0..size {
                         v[out][i] = imm;
                     }
@@ -1466,15 +2436,795 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
                 }
             }
         }
-        Ok(&self.0.slots[0])
+
+        self.out.clear();
+        self.out.extend((0..n).map(|i| {
+            self.eval.slots[0][i / simd::LANES].lane(i % simd::LANES)
+        }));
+    }
+}
+
+impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
+    type Data = Grad;
+    type Tape = GenericVmShape<N>;
+    type TapeStorage = ();
+
+    fn eval(
+        &mut self,
+        tape: &Self::Tape,
+        xs: &[f32],
+        ys: &[f32],
+        zs: &[f32],
+    ) -> Result<&[Grad], Error> {
+        let tape = tape.0.as_ref();
+        self.check_arguments(xs, ys, zs, tape.var_count())?;
+        self.run(tape, &[xs, ys, zs], xs.len());
+        Ok(&self.out)
+    }
+
+    fn eval_vars(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[&[f32]],
+    ) -> Result<&[Grad], Error> {
+        let tape = tape.0.as_ref();
+        let n = check_var_slices(vars, tape.var_count())?;
+        self.run(tape, vars, n);
+        Ok(&self.out)
+    }
+
+    fn eval_strided(
+        &mut self,
+        tape: &Self::Tape,
+        xs: Strided<f32>,
+        ys: Strided<f32>,
+        zs: Strided<f32>,
+        mut out: StridedMut<Grad>,
+    ) -> Result<(), Error> {
+        let tape = tape.0.as_ref();
+        self.check_strided_arguments(&xs, &ys, &zs, &out)?;
+        if tape.var_count() > 3 {
+            return Err(Error::BadVarSlice(3, tape.var_count()));
+        }
+        self.run(tape, &[xs, ys, zs], xs.len());
+        for (i, v) in self.out.iter().enumerate() {
+            out[i] = *v;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    crate::grad_slice_tests!(VmShape);
-    crate::interval_tests!(VmShape);
-    crate::float_slice_tests!(VmShape);
-    crate::point_tests!(VmShape);
+    crate::conformance_tests!(VmShape);
+
+    #[test]
+    fn test_extended_div() {
+        use crate::{context::Tree, eval::EzShape};
+        let (x, y, _z) = Tree::axes();
+        let shape = VmShape::from_tree(&(y / x.clone() - 0.5));
+        let tape = shape.ez_interval_tape();
+
+        let mut eval = VmShape::new_interval_eval();
+        let i = eval.eval_xy(&tape, [0.0, 1.0], [1.0, 2.0]);
+        assert!(i.has_nan());
+
+        // With extended division, the result is known to be positive
+        eval.set_extended_div(true);
+        let i = eval.eval_xy(&tape, [0.0, 1.0], [1.0, 2.0]);
+        assert_eq!(i, Interval::new(0.5, f32::INFINITY));
+        let i = eval.eval_xy(&tape, [-1.0, 1.0], [1.0, 2.0]);
+        assert_eq!(i.lower(), -f32::INFINITY);
+        assert_eq!(i.upper(), f32::INFINITY);
+
+        // Division by an immediate also uses extended division
+        let shape = VmShape::from_tree(&(1.0 / x));
+        let i = eval.eval_x(&shape.ez_interval_tape(), [-2.0, 0.0]);
+        assert_eq!(i, Interval::new(-f32::INFINITY, -0.5));
+    }
+
+    #[test]
+    fn test_dual() {
+        use crate::context::Tree;
+        let (x, y, z) = Tree::axes();
+        let shapes = [
+            (x.square() + y.square() + z.square()).sqrt() - 0.5,
+            x.clone() * y.clone() + z.sin() * 2.0,
+            (x.clone() - 0.2).max(y.clone() / (z.clone() + 3.0)),
+            x.clone().atan2(y.clone() + 0.1) + (z.exp() * x.clone()).abs(),
+        ];
+        let points = [(0.3, -0.2, 0.7), (-1.0, 0.5, 0.25), (0.1, 0.9, -0.4)];
+        let ray = [0.48, -0.6, 0.64];
+
+        let mut deval = VmDualEval::<255, 4>::new();
+        let mut peval = VmPointEval::<255>::new();
+        let mut geval = VmGradSliceEval::<255>::new();
+        for t in shapes {
+            let shape = VmShape::from_tree(&t);
+            for (px, py, pz) in points {
+                // Seeds are the three axes, then a ray direction
+                let (out, trace) = deval
+                    .eval(
+                        &shape,
+                        Dual::new(px, [1.0, 0.0, 0.0, ray[0]]),
+                        Dual::new(py, [0.0, 1.0, 0.0, ray[1]]),
+                        Dual::new(pz, [0.0, 0.0, 1.0, ray[2]]),
+                    )
+                    .unwrap();
+                let trace = trace.cloned();
+                let (v, ptrace) = peval.eval(&shape, px, py, pz).unwrap();
+                assert_eq!(out.v, v);
+                assert!(trace.as_ref() == ptrace);
+
+                let g = geval.eval(&shape, &[px], &[py], &[pz]).unwrap()[0];
+                let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+                assert!(close(out.d[0], g.dx), "{out:?} != {g:?}");
+                assert!(close(out.d[1], g.dy), "{out:?} != {g:?}");
+                assert!(close(out.d[2], g.dz), "{out:?} != {g:?}");
+                let dir = g.dx * ray[0] + g.dy * ray[1] + g.dz * ray[2];
+                assert!(close(out.d[3], dir), "{} != {dir}", out.d[3]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_interval_grad() {
+        use crate::context::Tree;
+        let (x, y, z) = Tree::axes();
+        let shapes = [
+            (x.square() + y.square() + z.square()).sqrt() - 0.5,
+            x.clone() * y.clone() + z.sin() * 2.0,
+            (x.clone() - 0.2).max(y.clone() / (z.clone() + 3.0)),
+            x.clone().atan2(y.clone() + 0.1) + (z.exp() * x.clone()).abs(),
+        ];
+        let regions = [
+            ([-1.0, 1.0], [-1.0, 1.0], [-1.0, 1.0]),
+            ([0.5, 1.0], [0.1, 0.3], [-0.5, 0.5]),
+            ([-0.3, -0.1], [0.2, 0.9], [0.0, 0.25]),
+        ];
+        let mut ieval = VmIntervalGradEval::<255>::default();
+        let mut geval = VmGradSliceEval::<255>::default();
+        for t in shapes {
+            let shape = VmShape::from_tree(&t);
+            for (rx, ry, rz) in regions {
+                let (out, _) = ieval.eval(&shape, rx, ry, rz).unwrap();
+
+                let mut xs = vec![];
+                let mut ys = vec![];
+                let mut zs = vec![];
+                for i in 0..=8 {
+                    for j in 0..=8 {
+                        for k in 0..=8 {
+                            let f = |r: [f32; 2], i: usize| {
+                                r[0] + (r[1] - r[0]) * i as f32 / 8.0
+                            };
+                            xs.push(f(rx, i));
+                            ys.push(f(ry, j));
+                            zs.push(f(rz, k));
+                        }
+                    }
+                }
+                let gs = geval.eval(&shape, &xs, &ys, &zs).unwrap();
+                for g in gs {
+                    for (i, v) in
+                        [g.v, g.dx, g.dy, g.dz].into_iter().enumerate()
+                    {
+                        let b = [out.v, out.dx, out.dy, out.dz][i];
+                        // Intervals aren't rounded outwards, so allow for
+                        // a little floating-point error
+                        let eps = 1e-5 * (1.0 + v.abs());
+                        assert!(
+                            b.has_nan()
+                                || v.is_nan()
+                                || (v >= b.lower() - eps
+                                    && v <= b.upper() + eps),
+                            "{v} is not in {b:?} ({i}, {out})"
+                        );
+                    }
+                }
+            }
+        }
+
+        // Monotonicity and Lipschitz bounds
+        let shape = VmShape::from_tree(&(x.clone() * 2.0 - y.clone() + 1.0));
+        let (out, _) = ieval
+            .eval(&shape, [0.0, 1.0], [0.0, 1.0], [0.0, 1.0])
+            .unwrap();
+        assert_eq!(out.monotonic(0), Some(true));
+        assert_eq!(out.monotonic(1), Some(false));
+        assert_eq!(out.dz, Interval::from(0.0));
+        assert!((out.lipschitz() - 5f32.sqrt()).abs() < 1e-6);
+
+        // Min and max pick one side's derivative when the choice is known
+        let shape = VmShape::from_tree(&x.min(y));
+        let (out, trace) = ieval
+            .eval(&shape, [0.0, 1.0], [2.0, 3.0], [0.0, 0.0])
+            .unwrap();
+        assert_eq!(out.dx, Interval::from(1.0));
+        assert_eq!(out.dy, Interval::from(0.0));
+        assert!(trace.is_some());
+        let (out, trace) = ieval
+            .eval(&shape, [0.0, 2.0], [1.0, 3.0], [0.0, 0.0])
+            .unwrap();
+        assert_eq!(out.dx, Interval::new(0.0, 1.0));
+        assert_eq!(out.dy, Interval::new(0.0, 1.0));
+        assert!(trace.is_none());
+
+        // Derivatives are seeded by name, not by slot index
+        let mut ctx = Context::new();
+        let y = ctx.y();
+        let t = ctx.var("T").unwrap();
+        let sum = ctx.mul(y, 2.0).unwrap();
+        let sum = ctx.add(sum, t).unwrap();
+        let shape = VmShape::new_with_inputs(&ctx, sum, &["T", "Y"]).unwrap();
+        let (out, _) = ieval
+            .eval_vars(&shape, &[[0.0, 1.0].into(), [1.0, 2.0].into()])
+            .unwrap();
+        assert_eq!(out.v, Interval::new(2.0, 5.0));
+        assert_eq!(out.dx, Interval::from(0.0));
+        assert_eq!(out.dy, Interval::from(2.0));
+        assert_eq!(out.dz, Interval::from(0.0));
+
+        // Extended division applies to interval gradients as well
+        let (x, _, _) = Tree::axes();
+        let shape = VmShape::from_tree(&(1.0 / x));
+        let r = [0.0, 1.0];
+        let (out, _) = ieval.eval(&shape, r, r, r).unwrap();
+        assert!(out.v.has_nan());
+        ieval.set_extended_div(true);
+        let (out, _) = ieval.eval(&shape, r, r, r).unwrap();
+        assert_eq!(out.v, Interval::new(1.0, f32::INFINITY));
+        assert_eq!(out.dx, Interval::new(-f32::INFINITY, -1.0));
+        assert_eq!(out.dy, Interval::from(0.0));
+    }
+
+    #[test]
+    fn test_lipschitz_bound() {
+        use crate::context::Tree;
+        let (x, y, z) = Tree::axes();
+        let t = (x.square() + y.square() + z.square()).sqrt() - 0.5;
+        let sphere = VmShape::from_tree(&t);
+
+        // A distance field has a gradient magnitude of 1 (away from the
+        // center), but interval arithmetic overestimates it
+        let b = sphere.lipschitz_bound([0.5, 1.0], [0.5, 1.0], [0.5, 1.0]);
+        let b = b.unwrap();
+        assert!((1.0..4.0).contains(&b), "{b}");
+
+        // The bound holds between sampled points in the region
+        let shape = VmShape::from_tree(&(x.clone() * y.clone() + z.sin()));
+        let r = [-1.0, 2.0];
+        let bound = shape.lipschitz_bound(r, r, r).unwrap();
+        let mut eval = VmShape::new_point_eval();
+        let pts = (0..64)
+            .map(|i| {
+                let f =
+                    |k: f32| -1.0 + 3.0 * ((i as f32 * k).sin() * 0.5 + 0.5);
+                [f(0.7), f(1.3), f(2.9)]
+            })
+            .collect::<Vec<_>>();
+        for a in &pts {
+            for b in &pts {
+                let (va, _) = eval.eval(&shape, a[0], a[1], a[2]).unwrap();
+                let (vb, _) = eval.eval(&shape, b[0], b[1], b[2]).unwrap();
+                let dist =
+                    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum::<f32>().sqrt();
+                assert!((va - vb).abs() <= bound * dist + 1e-5);
+            }
+        }
+
+        // Derivatives of sqrt are unbounded at zero
+        let r = [-1.0, 1.0];
+        assert!(sphere.lipschitz_bound(r, r, r).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_convert() {
+        use crate::context::Tree;
+        let (x, y, z) = Tree::axes();
+        let t = (x.square() + y.square() + z.square()).sqrt() - 0.5;
+        let t = t.min(x.clone() * y.clone() - z.clone()).max(x + y * z);
+        let shape = VmShape::from_tree(&t);
+
+        let small: GenericVmShape<3> = shape.convert().unwrap();
+        assert_eq!(small.ssa().tape.len(), shape.ssa().tape.len());
+        assert!(small.size() > shape.size());
+        let back: VmShape = small.convert().unwrap();
+        assert_eq!(
+            back.data().iter_asm().collect::<Vec<_>>(),
+            shape.data().iter_asm().collect::<Vec<_>>()
+        );
+
+        let mut a = VmShape::new_point_eval();
+        let mut b = GenericVmShape::<3>::new_point_eval();
+        for p in [[0.0, 0.0, 0.0], [1.0, -0.5, 0.25], [-0.3, 2.0, 1.0]] {
+            let (va, _) = a.eval(&shape, p[0], p[1], p[2]).unwrap();
+            let (vb, _) = b.eval(&small, p[0], p[1], p[2]).unwrap();
+            assert_eq!(va, vb);
+        }
+
+        #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+        {
+            use crate::{eval::EzShape, jit::JitShape};
+            let jit: JitShape = shape.convert().unwrap();
+            let mut eval = JitShape::new_point_eval();
+            let (v, _) =
+                eval.eval(&jit.ez_point_tape(), 1.0, -0.5, 0.25).unwrap();
+            let (expected, _) = a.eval(&shape, 1.0, -0.5, 0.25).unwrap();
+            assert_eq!(v, expected);
+            let back: VmShape = jit.convert().unwrap();
+            assert_eq!(back.ssa().tape.len(), shape.ssa().tape.len());
+
+            // JIT functions can't read named inputs
+            let mut ctx = Context::new();
+            let a = ctx.var("a").unwrap();
+            let d = ctx.var("d").unwrap();
+            let sum = ctx.add(a, d).unwrap();
+            let named =
+                VmShape::new_with_inputs(&ctx, sum, &["a", "b", "c", "d"])
+                    .unwrap();
+            assert!(matches!(
+                named.convert::<JitShape>(),
+                Err(Error::JitInputs)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_new_many() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let mut nodes = vec![];
+        for i in 0..32 {
+            let a = ctx.sub(x, i as f64).unwrap();
+            let b = ctx.mul(a, y).unwrap();
+            nodes.push(ctx.min(a, b).unwrap());
+        }
+        let shapes = VmShape::new_many(&ctx, &nodes).unwrap();
+        assert_eq!(shapes.len(), nodes.len());
+
+        let mut eval = VmShape::new_point_eval();
+        for (i, s) in shapes.iter().enumerate() {
+            let (v, _) = eval.eval(s, 10.0, 2.0, 0.0).unwrap();
+            let expected = ctx.eval_xyz(nodes[i], 10.0, 2.0, 0.0).unwrap();
+            assert_eq!(v, expected as f32);
+        }
+
+        // Errors are propagated
+        let v = ctx.var("v").unwrap();
+        nodes.push(v);
+        assert!(matches!(
+            VmShape::new_many(&ctx, &nodes),
+            Err(Error::UnknownVariable(..))
+        ));
+    }
+
+    #[test]
+    fn test_many_live_values() {
+        // Every term is used by both sums, so they're all live at once
+        const N: usize = 2000;
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let terms = (0..N)
+            .map(|i| {
+                let a = ctx.add(x, i as f64).unwrap();
+                ctx.mul(a, y).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let mut a = terms[0];
+        let mut b = terms[N - 1];
+        for i in 1..N {
+            a = ctx.add(a, terms[i]).unwrap();
+            b = ctx.max(b, terms[N - 1 - i]).unwrap();
+        }
+        let root = ctx.sub(a, b).unwrap();
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let m = shape.data().metrics();
+        assert_eq!(m.peak_registers, 255);
+        assert!(m.stores > 0 && m.loads > 0);
+
+        let expected = ctx.eval_xyz(root, 0.5, 0.25, 0.0).unwrap() as f32;
+        let mut eval = VmShape::new_point_eval();
+        let (v, _) = eval.eval(&shape, 0.5, 0.25, 0.0).unwrap();
+        assert_eq!(v, expected);
+
+        // Tapes with very few registers spill much more, but still work
+        let small: GenericVmShape<3> = shape.convert().unwrap();
+        let mut eval = GenericVmShape::<3>::new_point_eval();
+        let (v, _) = eval.eval(&small, 0.5, 0.25, 0.0).unwrap();
+        assert_eq!(v, expected);
+        assert!(small.data().metrics().loads > m.loads);
+    }
+
+    #[test]
+    fn test_named_inputs() {
+        use crate::eval::EzShape;
+
+        // A 4D expression, with inputs in a different order from creation
+        let mut ctx = Context::new();
+        let vs = ["a", "b", "c", "d"].map(|n| ctx.var(n).unwrap());
+        let ab = ctx.mul(vs[0], vs[1]).unwrap();
+        let cd = ctx.sub(vs[2], vs[3]).unwrap();
+        let root = ctx.min(ab, cd).unwrap();
+        let inputs = ["d", "c", "b", "a"];
+        let shape = VmShape::new_with_inputs(&ctx, root, &inputs).unwrap();
+
+        let vars = shape.ez_point_tape().vars();
+        assert_eq!(vars.iter().map(|v| v.name).collect::<Vec<_>>(), inputs);
+        assert!(vars.iter().all(|v| v.used));
+        let args = vars
+            .bind([("a", 2.0), ("b", 3.0), ("c", 10.0), ("d", 1.0)])
+            .unwrap();
+        assert_eq!(args, [1.0, 10.0, 3.0, 2.0]);
+
+        let mut eval = VmShape::new_point_eval();
+        let (v, trace) = eval.eval_vars(&shape, &args).unwrap();
+        assert_eq!(v, 6.0);
+        let trace = trace.unwrap().clone();
+
+        let mut eval = VmShape::new_interval_eval();
+        let (i, _) = eval
+            .eval_vars(
+                &shape,
+                &[1.0.into(), 10.0.into(), [0.0, 1.0].into(), 2.0.into()],
+            )
+            .unwrap();
+        assert_eq!(i, Interval::new(0.0, 2.0));
+
+        let mut eval = VmShape::new_float_slice_eval();
+        let out = eval
+            .eval_vars(
+                &shape,
+                &[&[1.0, 0.0], &[10.0, 0.0], &[3.0; 2], &[2.0; 2]],
+            )
+            .unwrap();
+        assert_eq!(out, [6.0, 0.0]);
+        assert!(matches!(
+            eval.eval_vars(&shape, &[&[1.0], &[10.0], &[3.0]]),
+            Err(Error::BadVarSlice(3, 4))
+        ));
+        assert!(matches!(
+            eval.eval_vars(&shape, &[&[1.0], &[10.0], &[3.0], &[]]),
+            Err(Error::MismatchedSlices)
+        ));
+
+        // Only the first three inputs have partial derivatives
+        let mut eval = VmShape::new_grad_slice_eval();
+        let g = eval
+            .eval_vars(&shape, &[&[1.0], &[10.0], &[3.0], &[2.0]])
+            .unwrap()[0];
+        assert_eq!(g, Grad::new(6.0, 0.0, 0.0, 2.0));
+
+        // Simplification preserves the input names
+        let next = shape
+            .simplify_inner(
+                trace.choices(),
+                Default::default(),
+                &mut Default::default(),
+            )
+            .unwrap();
+        let vars = next.vars();
+        let used: Vec<_> =
+            vars.iter().filter(|v| v.used).map(|v| v.name).collect();
+        assert_eq!(used, ["b", "a"]);
+        assert_eq!(vars.len(), 4);
+
+        // The X, Y, Z API only works for three inputs
+        let mut eval = VmShape::new_point_eval();
+        assert!(matches!(
+            eval.eval(&shape, 1.0, 2.0, 3.0),
+            Err(Error::BadVarSlice(3, 4))
+        ));
+
+        assert!(matches!(
+            VmShape::new_with_inputs(&ctx, root, &["a", "b", "c"]),
+            Err(Error::UnknownVariable(..))
+        ));
+        assert!(matches!(
+            VmShape::new_with_inputs(&ctx, root, &["a", "b", "c", "d", "a"]),
+            Err(Error::DuplicateName)
+        ));
+    }
+
+    #[test]
+    fn test_plan_2d() {
+        use crate::{context::Tree, eval::EzShape};
+        let (x, y, z) = Tree::axes();
+        let t = (x.square() + y.square() + z.clone() * 2.0).sqrt() - 0.5;
+        let t = t.min(y.clone() - z);
+        let shape = VmShape::from_tree(&t);
+        assert_eq!(shape.var_count(), 3);
+
+        let planned = shape.plan_2d(0.25).unwrap();
+        assert_eq!(planned.var_count(), 2);
+        assert!(planned.size() < shape.size());
+        let vars = planned.ez_point_tape().vars();
+        assert_eq!(vars.iter().map(|v| v.name).collect::<Vec<_>>(), ["X", "Y"]);
+
+        let mut eval = VmShape::new_point_eval();
+        for (px, py) in [(0.0, 0.0), (1.0, -0.5), (-0.3, 2.0)] {
+            let (expected, _) = eval.eval(&shape, px, py, 0.25).unwrap();
+            let (v, _) = eval.eval_vars(&planned, &[px, py]).unwrap();
+            assert_eq!(v, expected);
+
+            // The `z` argument is ignored
+            let (v, _) = eval.eval(&planned, px, py, 100.0).unwrap();
+            assert_eq!(v, expected);
+        }
+
+        // Shapes with named inputs can't be planned
+        let mut ctx = Context::new();
+        let a = ctx.var("a").unwrap();
+        let shape = VmShape::new_with_inputs(&ctx, a, &["a"]).unwrap();
+        assert!(shape.plan_2d(0.0).is_none());
+    }
+
+    #[test]
+    fn test_eval_i_traced() {
+        use crate::context::Tree;
+        let (x, y, z) = Tree::axes();
+        let t = (x.square() + y.square()).sqrt().min(z.clone().ln() - x);
+        let shape = VmShape::from_tree(&t);
+        let mut eval = VmShape::new_interval_eval();
+        let i = Interval::new(-1.0, 1.0);
+        let report = eval.eval_i_traced(&shape, &[i, i, i]).unwrap();
+        assert_eq!(report.clauses.len(), shape.data().len());
+        let (v, _) = eval.eval(&shape, i, i, i).unwrap();
+        assert!(report.value.has_nan() && v.has_nan());
+
+        // `ln` of an interval containing negative values produces `NaN`
+        let (op, r) = report.clauses[report.first_unbounded().unwrap()];
+        assert!(matches!(op, RegOp::LnReg(..)));
+        assert!(r.has_nan());
+
+        let z = Interval::new(1.0, 2.0);
+        let report = eval.eval_i_traced(&shape, &[i, i, z]).unwrap();
+        assert!(report.first_unbounded().is_none());
+        let (v, _) = eval.eval(&shape, i, i, z).unwrap();
+        assert_eq!(report.value, v);
+    }
+
+    #[test]
+    fn test_float_slice_traced() {
+        use crate::{context::Tree, eval::EzShape};
+        let (x, y, z) = Tree::axes();
+        let t = x.clone().min(y.clone()).max(z.clone() - 1.0);
+        let t = t.and(x.clone() - 0.5).or(y.clone() + z.clone()).min(1.5);
+        let shape = VmShape::from_tree(&t);
+        let mut eval = VmShape::new_float_slice_eval();
+        let mut point = VmShape::new_point_eval();
+
+        // Lengths which don't fill the final SIMD vector exercise masking
+        let mut simplified = 0;
+        for n in [1, 7, 8, 13, 100] {
+            let xs =
+                (0..n).map(|i| (i as f32 * 0.37).sin()).collect::<Vec<_>>();
+            let ys =
+                (0..n).map(|i| (i as f32 * 0.11).cos()).collect::<Vec<_>>();
+            let mut zs = vec![0.25; n];
+            zs[n / 2] = f32::NAN;
+
+            // The combined trace is the union of every point's choices
+            let mut expected = vec![Choice::Unknown; shape.choice_count()];
+            for i in 0..n {
+                point.eval(&shape, xs[i], ys[i], zs[i]).unwrap();
+                for (e, c) in
+                    expected.iter_mut().zip(point.0.choices.choices().iter())
+                {
+                    *e |= c;
+                }
+            }
+            let (out, trace) = eval.eval_traced(&shape, &xs, &ys, &zs).unwrap();
+            let out = out.to_vec();
+            let trace = trace.cloned();
+            if expected.iter().all(|c| *c == Choice::Both) {
+                assert!(trace.is_none());
+                continue;
+            }
+            let trace = trace.unwrap();
+            assert_eq!(trace.choices(), expected.as_slice(), "n = {n}");
+
+            // The untraced result is the same, and so are results from a
+            // tape simplified with the trace (at the sampled points)
+            let plain = eval.eval(&shape, &xs, &ys, &zs).unwrap();
+            assert_eq!(
+                bytemuck::cast_slice::<_, u32>(plain),
+                bytemuck::cast_slice::<_, u32>(&out)
+            );
+            let next = shape.ez_simplify(&trace).unwrap();
+            assert!(next.size() < shape.size());
+            simplified += 1;
+            let simple = eval.eval(&next, &xs, &ys, &zs).unwrap();
+            for (a, b) in simple.iter().zip(&out) {
+                assert!(a == b || (a.is_nan() && b.is_nan()), "{a} != {b}");
+            }
+        }
+
+        assert_eq!(simplified, 5);
+
+        let (out, trace) = eval.eval_traced(&shape, &[], &[], &[]).unwrap();
+        assert!(out.is_empty() && trace.is_none());
+    }
+
+    #[test]
+    fn test_specialize() {
+        use crate::context::Tree;
+        let (x, y, z) = Tree::axes();
+        let t = (x.square() + y.square() + z.square()).sqrt() - 0.5;
+        let shape = VmShape::from_tree(&t.max(y.clone() * 2.0 - x));
+        let frozen = shape.specialize("Y", 0.25).unwrap();
+        assert!(frozen.size() < shape.size());
+        assert!(shape.specialize("W", 0.0).is_none());
+
+        let mut eval = VmShape::new_point_eval();
+        for (px, pz) in [(0.0, 0.0), (1.0, -0.5), (-0.3, 2.0)] {
+            let (expected, _) = eval.eval(&shape, px, 0.25, pz).unwrap();
+            let (v, _) = eval.eval(&frozen, px, 100.0, pz).unwrap();
+            assert_eq!(v, expected);
+        }
+
+        // Named inputs can be specialized by name
+        let mut ctx = Context::new();
+        let a = ctx.var("a").unwrap();
+        let b = ctx.var("b").unwrap();
+        let root = ctx.mul(a, b).unwrap();
+        let shape = VmShape::new_with_inputs(&ctx, root, &["a", "b"]).unwrap();
+        let frozen = shape.specialize("a", 3.0).unwrap();
+        let (v, _) = eval.eval_vars(&frozen, &[0.0, 2.0]).unwrap();
+        assert_eq!(v, 6.0);
+        assert!(shape.specialize("X", 0.0).is_none());
+    }
+
+    #[test]
+    fn test_serde() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let mut out = ctx.z();
+        for i in 0..8 {
+            let a = ctx.sub(x, i as f64).unwrap();
+            let b = ctx.mul(a, y).unwrap();
+            let c = ctx.min(a, b).unwrap();
+            out = ctx.max(out, c).unwrap();
+        }
+
+        // Use a small register count so that the tape spills to memory
+        let data = VmData::<4>::new(&ctx, out).unwrap();
+        assert!(data.slot_count() > 4);
+        let json = serde_json::to_value(&data).unwrap();
+
+        let loaded: VmData<4> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded.len(), data.len());
+        assert!(loaded.iter_asm().eq(data.iter_asm()));
+
+        let shape = GenericVmShape::from(loaded);
+        let mut eval = GenericVmShape::<4>::new_point_eval();
+        let (v, trace) = eval.eval(&shape, 3.5, 2.0, -1.0).unwrap();
+        let expected = ctx.eval_xyz(out, 3.5, 2.0, -1.0).unwrap();
+        assert_eq!(v, expected as f32);
+
+        // Simplification works on a deserialized tape
+        let next = shape
+            .simplify_inner(
+                trace.unwrap().choices(),
+                Default::default(),
+                &mut Default::default(),
+            )
+            .unwrap();
+        let (v, _) = eval.eval(&next, 3.5, 2.0, -1.0).unwrap();
+        assert_eq!(v, expected as f32);
+
+        // Registers must fit in the target register count
+        assert!(serde_json::from_value::<VmData<3>>(json.clone()).is_err());
+
+        // Mismatched choice count
+        let mut bad = json.clone();
+        bad["ssa"]["choice_count"] = 3.into();
+        assert!(serde_json::from_value::<VmData<4>>(bad).is_err());
+
+        // Empty SSA tape
+        let mut bad = json.clone();
+        bad["ssa"]["tape"] = serde_json::Value::Array(vec![]);
+        assert!(serde_json::from_value::<VmData<4>>(bad).is_err());
+
+        // Missing memory slots
+        let mut bad = json.clone();
+        bad["asm"]["slot_count"] = 4.into();
+        assert!(serde_json::from_value::<VmData<4>>(bad).is_err());
+    }
+
+    /// Allocator which counts allocations made by the current thread
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    fn alloc_count() -> usize {
+        ALLOCS.with(|a| a.get())
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCS.try_with(|a| a.set(a.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+        unsafe fn realloc(
+            &self,
+            ptr: *mut u8,
+            layout: std::alloc::Layout,
+            new_size: usize,
+        ) -> *mut u8 {
+            let _ = ALLOCS.try_with(|a| a.set(a.get() + 1));
+            std::alloc::System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAlloc = CountingAlloc;
+
+    fn check_simplify_no_alloc<const N: usize>(named: bool) {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let mut out = ctx.z();
+        for i in 0..8 {
+            let a = ctx.sub(x, i as f64).unwrap();
+            let b = ctx.mul(a, y).unwrap();
+            let c = ctx.min(a, b).unwrap();
+            out = ctx.max(out, c).unwrap();
+        }
+        let shape = if named {
+            // Input names are shared with simplified tapes, not copied
+            GenericVmShape::<N>::new_with_inputs(&ctx, out, &["X", "Y", "Z"])
+        } else {
+            GenericVmShape::<N>::new(&ctx, out)
+        }
+        .unwrap();
+
+        let mut eval = GenericVmShape::<N>::new_interval_eval();
+        let traces: Vec<VmTrace> = [(-1.0, 0.0), (2.5, 3.0), (7.0, 8.0)]
+            .into_iter()
+            .map(|(lo, hi)| {
+                let x = Interval::new(lo, hi);
+                let y = Interval::new(1.0, 2.0);
+                let z = Interval::new(-1.0, 0.0);
+                let (_, trace) = eval.eval(&shape, x, y, z).unwrap();
+                trace.unwrap().clone()
+            })
+            .collect();
+
+        let mut workspace = VmWorkspace::default();
+        let mut storage = Vec::with_capacity(1);
+        for pass in 0..2 {
+            // The first pass grows storage to fit; the second must reuse it
+            let before = alloc_count();
+            for trace in &traces {
+                let s = storage.pop().unwrap_or_default();
+                let next = shape.simplify(trace, s, &mut workspace).unwrap();
+                assert!(next.size() < shape.size());
+                storage.extend(next.recycle());
+            }
+            if pass == 0 {
+                assert!(alloc_count() > before);
+            } else {
+                assert_eq!(alloc_count(), before);
+            }
+        }
+    }
+
+    #[test]
+    fn test_simplify_no_alloc() {
+        for named in [false, true] {
+            check_simplify_no_alloc::<255>(named);
+            check_simplify_no_alloc::<4>(named);
+        }
+    }
 }
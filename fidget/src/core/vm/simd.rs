@@ -0,0 +1,329 @@
+//! SIMD types for bulk evaluation
+//!
+//! Bulk evaluators store each slot as an array of 8-lane vectors (padded at
+//! the end), so that every operation runs on 8 points at a time.  Arithmetic
+//! uses the `wide` crate, which compiles to SSE / AVX / NEON / WASM SIMD
+//! instructions (depending on target features), with a scalar fallback.
+//!
+//! Transcendental functions, rounding, and modulo are evaluated lane-by-lane
+//! with the standard library, so that results exactly match the other
+//! evaluators.
+use crate::{types::Grad, vm::Choice};
+use wide::{f32x8, CmpEq, CmpGt, CmpLt, CmpNe};
+
+/// Number of points evaluated by each SIMD operation
+pub(super) const LANES: usize = 8;
+
+/// Applies a scalar function to every lane
+#[inline]
+pub(super) fn lanes(x: f32x8, f: impl Fn(f32) -> f32) -> f32x8 {
+    x.to_array().map(f).into()
+}
+
+/// Applies a scalar function to every pair of lanes
+#[inline]
+pub(super) fn lanes2(a: f32x8, b: f32x8, f: impl Fn(f32, f32) -> f32) -> f32x8 {
+    let a = a.to_array();
+    let b = b.to_array();
+    std::array::from_fn(|i| f(a[i], b[i])).into()
+}
+
+/// Negates every lane (including zeros and NaNs, by flipping the sign bit)
+#[inline]
+pub(super) fn neg(x: f32x8) -> f32x8 {
+    x ^ f32x8::splat(-0.0)
+}
+
+/// Compares lanes, returning -1, 0, or 1 (or NaN if either input is NaN)
+#[inline]
+pub(super) fn compare(a: f32x8, b: f32x8) -> f32x8 {
+    let nan = a.is_nan() | b.is_nan();
+    a.cmp_lt(b).blend(
+        f32x8::splat(-1.0),
+        a.cmp_gt(b)
+            .blend(f32x8::ONE, nan.blend(f32x8::splat(f32::NAN), f32x8::ZERO)),
+    )
+}
+
+/// Lane-wise minimum, returning NaN if either input is NaN
+#[inline]
+pub(super) fn min(a: f32x8, b: f32x8) -> f32x8 {
+    (a.is_nan() | b.is_nan()).blend(f32x8::splat(f32::NAN), a.min(b))
+}
+
+/// Lane-wise maximum, returning NaN if either input is NaN
+#[inline]
+pub(super) fn max(a: f32x8, b: f32x8) -> f32x8 {
+    (a.is_nan() | b.is_nan()).blend(f32x8::splat(f32::NAN), a.max(b))
+}
+
+/// Logical and: returns `a` in lanes where it's zero, and `b` otherwise
+#[inline]
+pub(super) fn and(a: f32x8, b: f32x8) -> f32x8 {
+    a.cmp_eq(f32x8::ZERO).blend(a, b)
+}
+
+/// Logical or: returns `a` in lanes where it's non-zero, and `b` otherwise
+#[inline]
+pub(super) fn or(a: f32x8, b: f32x8) -> f32x8 {
+    a.cmp_ne(f32x8::ZERO).blend(a, b)
+}
+
+/// Combines per-lane choices across the first `n` lanes
+///
+/// `left` and `right` are lane masks for lanes which pick the left- and
+/// right-hand input; lanes in neither mask pick both.
+#[inline]
+fn choice(left: f32x8, right: f32x8, n: usize) -> Choice {
+    let valid = (1 << n) - 1;
+    let (l, r) = (left.move_mask() & valid, right.move_mask() & valid);
+    let mut out = Choice::Unknown;
+    if l != 0 {
+        out |= Choice::Left;
+    }
+    if r != 0 {
+        out |= Choice::Right;
+    }
+    if (l | r) != valid {
+        out |= Choice::Both;
+    }
+    out
+}
+
+/// Returns the union of choices made by `min` in the first `n` lanes
+///
+/// Choices match point evaluation: lanes which are equal (or `NaN`) pick
+/// both inputs.
+#[inline]
+pub(super) fn min_choice(a: f32x8, b: f32x8, n: usize) -> Choice {
+    choice(a.cmp_lt(b), b.cmp_lt(a), n)
+}
+
+/// Returns the union of choices made by `max` in the first `n` lanes
+#[inline]
+pub(super) fn max_choice(a: f32x8, b: f32x8, n: usize) -> Choice {
+    choice(a.cmp_gt(b), b.cmp_gt(a), n)
+}
+
+/// Returns the union of choices made by logical and in the first `n` lanes
+#[inline]
+pub(super) fn and_choice(a: f32x8, _b: f32x8, n: usize) -> Choice {
+    let left = a.cmp_eq(f32x8::ZERO);
+    choice(left, !left, n)
+}
+
+/// Returns the union of choices made by logical or in the first `n` lanes
+#[inline]
+pub(super) fn or_choice(a: f32x8, _b: f32x8, n: usize) -> Choice {
+    let left = a.cmp_ne(f32x8::ZERO);
+    choice(left, !left, n)
+}
+
+/// Logical not: returns 1 in lanes which are zero, and 0 otherwise
+#[inline]
+pub(super) fn not(a: f32x8) -> f32x8 {
+    a.cmp_eq(f32x8::ZERO).blend(f32x8::ONE, f32x8::ZERO)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Eight [`Grad`] values, stored as a structure of arrays
+///
+/// Operations use the same formulas (in the same order) as [`Grad`], so
+/// results are identical.
+#[derive(Copy, Clone, Default)]
+pub(super) struct GradX8 {
+    pub v: f32x8,
+    pub dx: f32x8,
+    pub dy: f32x8,
+    pub dz: f32x8,
+}
+
+impl From<f32> for GradX8 {
+    fn from(v: f32) -> Self {
+        Self {
+            v: v.into(),
+            dx: f32x8::ZERO,
+            dy: f32x8::ZERO,
+            dz: f32x8::ZERO,
+        }
+    }
+}
+
+impl GradX8 {
+    /// Builds a value with no partial derivatives
+    #[inline]
+    pub fn constant(v: f32x8) -> Self {
+        Self {
+            v,
+            dx: f32x8::ZERO,
+            dy: f32x8::ZERO,
+            dz: f32x8::ZERO,
+        }
+    }
+
+    /// Returns a single lane
+    #[inline]
+    pub fn lane(&self, i: usize) -> Grad {
+        Grad::new(
+            self.v.as_array_ref()[i],
+            self.dx.as_array_ref()[i],
+            self.dy.as_array_ref()[i],
+            self.dz.as_array_ref()[i],
+        )
+    }
+
+    /// Builds a value from a function which is called for each lane
+    #[inline]
+    fn from_fn(f: impl Fn(usize) -> Grad) -> Self {
+        let g: [Grad; LANES] = std::array::from_fn(f);
+        Self {
+            v: g.map(|g| g.v).into(),
+            dx: g.map(|g| g.dx).into(),
+            dy: g.map(|g| g.dy).into(),
+            dz: g.map(|g| g.dz).into(),
+        }
+    }
+
+    /// Applies a scalar function to every lane
+    #[inline]
+    pub fn lanes(self, f: impl Fn(Grad) -> Grad) -> Self {
+        Self::from_fn(|i| f(self.lane(i)))
+    }
+
+    /// Applies a scalar function to every pair of lanes
+    #[inline]
+    pub fn lanes2(self, rhs: Self, f: impl Fn(Grad, Grad) -> Grad) -> Self {
+        Self::from_fn(|i| f(self.lane(i), rhs.lane(i)))
+    }
+
+    /// Picks lanes from `t` where `mask` is set, and from `f` otherwise
+    #[inline]
+    fn select(mask: f32x8, t: Self, f: Self) -> Self {
+        Self {
+            v: mask.blend(t.v, f.v),
+            dx: mask.blend(t.dx, f.dx),
+            dy: mask.blend(t.dy, f.dy),
+            dz: mask.blend(t.dz, f.dz),
+        }
+    }
+
+    /// Absolute value
+    #[inline]
+    pub fn abs(self) -> Self {
+        Self::select(self.v.cmp_lt(f32x8::ZERO), -self, self)
+    }
+
+    /// Square root
+    #[inline]
+    pub fn sqrt(self) -> Self {
+        let v = self.v.sqrt();
+        let d = v * 2.0;
+        Self {
+            v,
+            dx: self.dx / d,
+            dy: self.dy / d,
+            dz: self.dz / d,
+        }
+    }
+
+    /// Minimum of two values, returning NaN if either value is NaN
+    #[inline]
+    pub fn min(self, rhs: Self) -> Self {
+        let nan = self.v.is_nan() | rhs.v.is_nan();
+        Self::select(
+            nan,
+            f32::NAN.into(),
+            Self::select(self.v.cmp_lt(rhs.v), self, rhs),
+        )
+    }
+
+    /// Maximum of two values, returning NaN if either value is NaN
+    #[inline]
+    pub fn max(self, rhs: Self) -> Self {
+        let nan = self.v.is_nan() | rhs.v.is_nan();
+        Self::select(
+            nan,
+            f32::NAN.into(),
+            Self::select(self.v.cmp_gt(rhs.v), self, rhs),
+        )
+    }
+
+    /// Logical and, based on the value of `self`
+    #[inline]
+    pub fn and(self, rhs: Self) -> Self {
+        Self::select(self.v.cmp_eq(f32x8::ZERO), self, rhs)
+    }
+
+    /// Logical or, based on the value of `self`
+    #[inline]
+    pub fn or(self, rhs: Self) -> Self {
+        Self::select(self.v.cmp_ne(f32x8::ZERO), self, rhs)
+    }
+}
+
+impl std::ops::Add<GradX8> for GradX8 {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            v: self.v + rhs.v,
+            dx: self.dx + rhs.dx,
+            dy: self.dy + rhs.dy,
+            dz: self.dz + rhs.dz,
+        }
+    }
+}
+
+impl std::ops::Sub<GradX8> for GradX8 {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            v: self.v - rhs.v,
+            dx: self.dx - rhs.dx,
+            dy: self.dy - rhs.dy,
+            dz: self.dz - rhs.dz,
+        }
+    }
+}
+
+impl std::ops::Mul<GradX8> for GradX8 {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            v: self.v * rhs.v,
+            dx: self.v * rhs.dx + rhs.v * self.dx,
+            dy: self.v * rhs.dy + rhs.v * self.dy,
+            dz: self.v * rhs.dz + rhs.v * self.dz,
+        }
+    }
+}
+
+impl std::ops::Div<GradX8> for GradX8 {
+    type Output = Self;
+    #[inline]
+    fn div(self, rhs: Self) -> Self {
+        let d = rhs.v * rhs.v;
+        Self {
+            v: self.v / rhs.v,
+            dx: (rhs.v * self.dx - self.v * rhs.dx) / d,
+            dy: (rhs.v * self.dy - self.v * rhs.dy) / d,
+            dz: (rhs.v * self.dz - self.v * rhs.dz) / d,
+        }
+    }
+}
+
+impl std::ops::Neg for GradX8 {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        Self {
+            v: neg(self.v),
+            dx: neg(self.dx),
+            dy: neg(self.dy),
+            dz: neg(self.dz),
+        }
+    }
+}
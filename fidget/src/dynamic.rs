@@ -0,0 +1,691 @@
+//! Shapes with an evaluation backend selected at runtime
+//!
+//! Each [`Shape`] implementation is its own type, so code which works with
+//! shapes is normally generic over `S: Shape`.  [`DynShape`] wraps either a
+//! [`VmShape`] or a [`JitShape`], with the choice made
+//! when the shape is built.  Applications can then write non-generic
+//! evaluation and rendering code, and pick the [`Backend`] from (for example)
+//! a configuration file.
+//!
+//! ```
+//! use fidget::{
+//!     compiler::Backend,
+//!     context::Tree,
+//!     dynamic::DynShape,
+//!     eval::{EzShape, Shape, TracingEvaluator},
+//! };
+//!
+//! let (x, y, _z) = Tree::axes();
+//! let tree = (x.square() + y.square()).sqrt() - 1.0;
+//! for backend in [Backend::Vm, Backend::Jit] {
+//!     // Falls back to the VM if the JIT isn't available
+//!     let shape = DynShape::from_tree_with(&tree, backend);
+//!     let mut eval = DynShape::new_point_eval();
+//!     let (v, _) = eval.eval(&shape.ez_point_tape(), 0.0, 0.0, 0.0)?;
+//!     assert_eq!(v, -1.0);
+//! }
+//! # Ok::<(), fidget::Error>(())
+//! ```
+//!
+//! Dispatch happens once per call to an evaluator, so the overhead is small
+//! for bulk evaluation and interval evaluation, but measurable for
+//! single-point evaluation in a tight loop.
+//!
+//! JIT shapes may also be given a compile budget (see
+//! [`DynShape::with_compile_budget`]), so that tapes which are too long to be
+//! worth compiling are interpreted instead.
+use crate::{
+    compiler::Backend,
+    context::{Context, Node, Tree},
+    eval::{
+        BulkEvaluator, MathShape, Shape, Strided, StridedMut, Tape,
+        TracingEvaluator, TransformedShape, Vars,
+    },
+    types::{Grad, Interval},
+    vm::{VmShape, VmTrace},
+    Error,
+};
+use nalgebra::Matrix4;
+
+#[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+use crate::jit::{JitBulkFn, JitShape, JitTracingFn, JitVmShape};
+
+/// A shape which uses either the VM or JIT backend, selected at runtime
+///
+/// Tile sizes (see [`Shape::tile_sizes_3d`]) and
+/// [`Shape::simplify_tree_during_meshing`] don't take a shape as an argument,
+/// so they can't depend on the selected backend; they use the values of the
+/// default backend (see [`Backend::default`]).
+#[derive(Clone)]
+pub enum DynShape {
+    /// Shape evaluated with the interpreter
+    Vm(VmShape),
+    /// Shape evaluated with the JIT compiler
+    ///
+    /// The second field is an optional compile budget (see
+    /// [`with_compile_budget`](DynShape::with_compile_budget)).
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    Jit(JitShape, Option<usize>),
+}
+
+impl DynShape {
+    /// Builds a new shape using the given backend
+    ///
+    /// If [`Backend::Jit`] is requested but the JIT isn't available (because
+    /// the `jit` feature is disabled, we're running on `wasm32`, or the CPU
+    /// doesn't support it), this falls back to [`Backend::Vm`]; use
+    /// [`backend`](Self::backend) to check which was selected.
+    pub fn new_with(
+        ctx: &Context,
+        node: Node,
+        backend: Backend,
+    ) -> Result<Self, Error> {
+        match backend {
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            Backend::Jit if crate::jit::is_supported() => {
+                JitShape::new(ctx, node).map(|s| DynShape::Jit(s, None))
+            }
+            _ => VmShape::new(ctx, node).map(DynShape::Vm),
+        }
+    }
+
+    /// Builds a new shape from a [`Tree`] using the given backend
+    ///
+    /// See [`new_with`](Self::new_with) for details on backend selection.
+    pub fn from_tree_with(t: &Tree, backend: Backend) -> Self {
+        let mut ctx = Context::new();
+        let node = ctx.import(t);
+        Self::new_with(&ctx, node, backend).unwrap()
+    }
+
+    /// Sets a compile budget, in clauses
+    ///
+    /// Compiling a tape takes time proportional to its length, which may be
+    /// longer than interpreting it if it's only evaluated a few times (e.g.
+    /// interval evaluation of a large shape at the top of a render).  With a
+    /// budget, tapes longer than `budget` clauses (see [`Shape::size`]) aren't
+    /// compiled; they're evaluated by the interpreter instead, using the same
+    /// register allocation.  Simplified shapes inherit the budget, so their
+    /// (shorter) tapes may still be compiled.
+    ///
+    /// `None` removes the budget.  This has no effect on VM shapes.  The
+    /// budget only exists on `DynShape`: a [`JitShape`] used directly always
+    /// compiles its tapes.
+    #[cfg_attr(
+        not(all(feature = "jit", not(target_arch = "wasm32"))),
+        allow(unused_variables)
+    )]
+    pub fn with_compile_budget(self, budget: Option<usize>) -> Self {
+        match self {
+            DynShape::Vm(s) => DynShape::Vm(s),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, _) => DynShape::Jit(s, budget),
+        }
+    }
+
+    /// Returns the backend used by this shape
+    pub fn backend(&self) -> Backend {
+        match self {
+            DynShape::Vm(..) => Backend::Vm,
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(..) => Backend::Jit,
+        }
+    }
+}
+
+impl MathShape for DynShape {
+    /// Builds a new shape using the default backend (see [`Backend::default`])
+    fn new(ctx: &Context, node: Node) -> Result<Self, Error> {
+        Self::new_with(ctx, node, Backend::default())
+    }
+}
+
+impl From<VmShape> for DynShape {
+    fn from(s: VmShape) -> Self {
+        DynShape::Vm(s)
+    }
+}
+
+#[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+impl From<JitShape> for DynShape {
+    fn from(s: JitShape) -> Self {
+        DynShape::Jit(s, None)
+    }
+}
+
+/// Storage for a [`DynShape`], reused during simplification
+///
+/// Storage from one backend is discarded if it's passed to a shape using the
+/// other backend.
+#[derive(Default)]
+pub enum DynStorage {
+    /// No storage has been reclaimed yet
+    #[default]
+    Empty,
+    /// Storage reclaimed from a VM shape
+    Vm(<VmShape as Shape>::Storage),
+    /// Storage reclaimed from a JIT shape
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    Jit(<JitShape as Shape>::Storage),
+}
+
+/// Workspace for simplifying a [`DynShape`]
+#[derive(Default)]
+pub struct DynWorkspace {
+    vm: <VmShape as Shape>::Workspace,
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    jit: <JitShape as Shape>::Workspace,
+}
+
+/// Storage for [`DynShape`] tapes
+///
+/// Only compiled JIT tapes have storage to reclaim; VM tapes (including JIT
+/// tapes that exceeded their compile budget) are reference-counted.
+#[derive(Default)]
+pub struct DynTapeStorage {
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    jit: Option<<JitShape as Shape>::TapeStorage>,
+}
+
+/// Declares a tape enum and evaluator struct for one evaluation flavor
+macro_rules! dyn_eval {
+    (
+        $(#[$tape_meta:meta])* $tape:ident($jit_tape:ty),
+        $(#[$eval_meta:meta])* $eval:ident($flavor:ident)
+    ) => {
+        $(#[$tape_meta])*
+        pub enum $tape {
+            /// Tape for the interpreter
+            Vm(VmShape),
+            /// Tape for the JIT compiler
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            Jit($jit_tape),
+            /// Tape for a JIT shape which exceeded its compile budget, which is
+            /// evaluated by the interpreter
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            JitFallback(JitVmShape),
+        }
+
+        impl Tape for $tape {
+            type Storage = DynTapeStorage;
+            fn recycle(self) -> DynTapeStorage {
+                match self {
+                    $tape::Vm(..) => DynTapeStorage::default(),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::Jit(t) => DynTapeStorage {
+                        jit: Some(t.recycle()),
+                    },
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::JitFallback(..) => DynTapeStorage::default(),
+                }
+            }
+
+            fn vars(&self) -> Vars {
+                match self {
+                    $tape::Vm(t) => Tape::vars(t),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::Jit(t) => t.vars(),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::JitFallback(t) => Tape::vars(t),
+                }
+            }
+        }
+
+        $(#[$eval_meta])*
+        ///
+        /// This contains an evaluator for each backend, and dispatches based
+        /// on the tape that's passed in.
+        #[derive(Default)]
+        pub struct $eval {
+            vm: <VmShape as Shape>::$flavor,
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            jit: <JitShape as Shape>::$flavor,
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            fallback: <JitVmShape as Shape>::$flavor,
+        }
+    };
+}
+
+/// Implements [`TracingEvaluator`] for a type declared with `dyn_eval!`
+macro_rules! dyn_tracing_eval {
+    ($tape:ident, $eval:ident, $data:ty) => {
+        impl TracingEvaluator for $eval {
+            type Data = $data;
+            type Tape = $tape;
+            type TapeStorage = DynTapeStorage;
+            type Trace = VmTrace;
+
+            fn eval<F: Into<$data>>(
+                &mut self,
+                tape: &$tape,
+                x: F,
+                y: F,
+                z: F,
+            ) -> Result<($data, Option<&VmTrace>), Error> {
+                match tape {
+                    $tape::Vm(t) => self.vm.eval(t, x, y, z),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::Jit(t) => self.jit.eval(t, x, y, z),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::JitFallback(t) => self.fallback.eval(t, x, y, z),
+                }
+            }
+
+            fn eval_vars(
+                &mut self,
+                tape: &$tape,
+                vars: &[$data],
+            ) -> Result<($data, Option<&VmTrace>), Error> {
+                match tape {
+                    $tape::Vm(t) => self.vm.eval_vars(t, vars),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::Jit(t) => self.jit.eval_vars(t, vars),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::JitFallback(t) => self.fallback.eval_vars(t, vars),
+                }
+            }
+        }
+    };
+}
+
+/// Implements [`BulkEvaluator`] for a type declared with `dyn_eval!`
+macro_rules! dyn_bulk_eval {
+    ($tape:ident, $eval:ident, $data:ty) => {
+        impl BulkEvaluator for $eval {
+            type Data = $data;
+            type Tape = $tape;
+            type TapeStorage = DynTapeStorage;
+
+            fn eval(
+                &mut self,
+                tape: &$tape,
+                x: &[f32],
+                y: &[f32],
+                z: &[f32],
+            ) -> Result<&[$data], Error> {
+                match tape {
+                    $tape::Vm(t) => self.vm.eval(t, x, y, z),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::Jit(t) => self.jit.eval(t, x, y, z),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::JitFallback(t) => self.fallback.eval(t, x, y, z),
+                }
+            }
+
+            fn eval_vars(
+                &mut self,
+                tape: &$tape,
+                vars: &[&[f32]],
+            ) -> Result<&[$data], Error> {
+                match tape {
+                    $tape::Vm(t) => self.vm.eval_vars(t, vars),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::Jit(t) => self.jit.eval_vars(t, vars),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::JitFallback(t) => self.fallback.eval_vars(t, vars),
+                }
+            }
+
+            fn eval_strided(
+                &mut self,
+                tape: &$tape,
+                x: Strided<f32>,
+                y: Strided<f32>,
+                z: Strided<f32>,
+                out: StridedMut<$data>,
+            ) -> Result<(), Error> {
+                match tape {
+                    $tape::Vm(t) => self.vm.eval_strided(t, x, y, z, out),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::Jit(t) => self.jit.eval_strided(t, x, y, z, out),
+                    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+                    $tape::JitFallback(t) => {
+                        self.fallback.eval_strided(t, x, y, z, out)
+                    }
+                }
+            }
+        }
+    };
+}
+
+dyn_eval!(
+    /// Tape for single-point evaluation of a [`DynShape`]
+    DynPointTape(JitTracingFn<f32>),
+    /// Single-point evaluator for a [`DynShape`]
+    DynPointEval(PointEval)
+);
+dyn_tracing_eval!(DynPointTape, DynPointEval, f32);
+
+dyn_eval!(
+    /// Tape for interval evaluation of a [`DynShape`]
+    DynIntervalTape(JitTracingFn<Interval>),
+    /// Interval evaluator for a [`DynShape`]
+    DynIntervalEval(IntervalEval)
+);
+dyn_tracing_eval!(DynIntervalTape, DynIntervalEval, Interval);
+
+dyn_eval!(
+    /// Tape for bulk evaluation of a [`DynShape`]
+    DynFloatSliceTape(JitBulkFn<f32>),
+    /// Bulk evaluator for a [`DynShape`]
+    DynFloatSliceEval(FloatSliceEval)
+);
+dyn_bulk_eval!(DynFloatSliceTape, DynFloatSliceEval, f32);
+
+dyn_eval!(
+    /// Tape for bulk gradient evaluation of a [`DynShape`]
+    DynGradSliceTape(JitBulkFn<Grad>),
+    /// Bulk gradient evaluator for a [`DynShape`]
+    DynGradSliceEval(GradSliceEval)
+);
+dyn_bulk_eval!(DynGradSliceTape, DynGradSliceEval, Grad);
+
+/// Checks whether a JIT shape's tapes should be interpreted instead of compiled
+#[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+fn over_budget(s: &JitShape, budget: Option<usize>) -> bool {
+    budget.is_some_and(|b| s.size() > b)
+}
+
+impl DynTapeStorage {
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    fn jit(self) -> <JitShape as Shape>::TapeStorage {
+        self.jit.unwrap_or_default()
+    }
+}
+
+impl Shape for DynShape {
+    type Trace = VmTrace;
+    type Storage = DynStorage;
+    type Workspace = DynWorkspace;
+    type TapeStorage = DynTapeStorage;
+
+    type PointEval = DynPointEval;
+    type IntervalEval = DynIntervalEval;
+    type FloatSliceEval = DynFloatSliceEval;
+    type GradSliceEval = DynGradSliceEval;
+
+    fn point_tape(&self, storage: DynTapeStorage) -> DynPointTape {
+        let _ = storage;
+        match self {
+            DynShape::Vm(s) => DynPointTape::Vm(s.point_tape(())),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, budget) => {
+                if over_budget(s, *budget) {
+                    DynPointTape::JitFallback(s.vm().point_tape(()))
+                } else {
+                    DynPointTape::Jit(s.point_tape(storage.jit()))
+                }
+            }
+        }
+    }
+
+    fn interval_tape(&self, storage: DynTapeStorage) -> DynIntervalTape {
+        let _ = storage;
+        match self {
+            DynShape::Vm(s) => DynIntervalTape::Vm(s.interval_tape(())),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, budget) => {
+                if over_budget(s, *budget) {
+                    DynIntervalTape::JitFallback(s.vm().interval_tape(()))
+                } else {
+                    DynIntervalTape::Jit(s.interval_tape(storage.jit()))
+                }
+            }
+        }
+    }
+
+    fn float_slice_tape(&self, storage: DynTapeStorage) -> DynFloatSliceTape {
+        let _ = storage;
+        match self {
+            DynShape::Vm(s) => DynFloatSliceTape::Vm(s.float_slice_tape(())),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, budget) => {
+                if over_budget(s, *budget) {
+                    DynFloatSliceTape::JitFallback(s.vm().float_slice_tape(()))
+                } else {
+                    DynFloatSliceTape::Jit(s.float_slice_tape(storage.jit()))
+                }
+            }
+        }
+    }
+
+    fn grad_slice_tape(&self, storage: DynTapeStorage) -> DynGradSliceTape {
+        let _ = storage;
+        match self {
+            DynShape::Vm(s) => DynGradSliceTape::Vm(s.grad_slice_tape(())),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, budget) => {
+                if over_budget(s, *budget) {
+                    DynGradSliceTape::JitFallback(s.vm().grad_slice_tape(()))
+                } else {
+                    DynGradSliceTape::Jit(s.grad_slice_tape(storage.jit()))
+                }
+            }
+        }
+    }
+
+    fn simplify(
+        &self,
+        trace: &VmTrace,
+        storage: DynStorage,
+        workspace: &mut DynWorkspace,
+    ) -> Result<Self, Error> {
+        match self {
+            DynShape::Vm(s) => {
+                let storage = match storage {
+                    DynStorage::Vm(s) => s,
+                    _ => Default::default(),
+                };
+                s.simplify(trace, storage, &mut workspace.vm)
+                    .map(DynShape::Vm)
+            }
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, budget) => {
+                let storage = match storage {
+                    DynStorage::Jit(s) => s,
+                    _ => Default::default(),
+                };
+                s.simplify(trace, storage, &mut workspace.jit)
+                    .map(|s| DynShape::Jit(s, *budget))
+            }
+        }
+    }
+
+    fn recycle(self) -> Option<DynStorage> {
+        match self {
+            DynShape::Vm(s) => Shape::recycle(s).map(DynStorage::Vm),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, _) => s.recycle().map(DynStorage::Jit),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            DynShape::Vm(s) => s.size(),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, _) => s.size(),
+        }
+    }
+
+    fn choice_count(&self) -> Option<usize> {
+        match self {
+            DynShape::Vm(s) => Shape::choice_count(s),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, _) => s.choice_count(),
+        }
+    }
+
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    fn tile_sizes_3d() -> &'static [usize] {
+        JitShape::tile_sizes_3d()
+    }
+
+    #[cfg(not(all(feature = "jit", not(target_arch = "wasm32"))))]
+    fn tile_sizes_3d() -> &'static [usize] {
+        VmShape::tile_sizes_3d()
+    }
+
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    fn tile_sizes_2d() -> &'static [usize] {
+        JitShape::tile_sizes_2d()
+    }
+
+    #[cfg(not(all(feature = "jit", not(target_arch = "wasm32"))))]
+    fn tile_sizes_2d() -> &'static [usize] {
+        VmShape::tile_sizes_2d()
+    }
+
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    fn simplify_tree_during_meshing(d: usize) -> bool {
+        JitShape::simplify_tree_during_meshing(d)
+    }
+
+    #[cfg(not(all(feature = "jit", not(target_arch = "wasm32"))))]
+    fn simplify_tree_during_meshing(d: usize) -> bool {
+        VmShape::simplify_tree_during_meshing(d)
+    }
+
+    fn plan_2d(&self, z: f32) -> Option<Self> {
+        match self {
+            DynShape::Vm(s) => Shape::plan_2d(s, z).map(DynShape::Vm),
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, budget) => {
+                s.plan_2d(z).map(|s| DynShape::Jit(s, *budget))
+            }
+        }
+    }
+
+    fn specialize(&self, var: &str, value: f32) -> Option<Self> {
+        match self {
+            DynShape::Vm(s) => {
+                Shape::specialize(s, var, value).map(DynShape::Vm)
+            }
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            DynShape::Jit(s, budget) => {
+                s.specialize(var, value).map(|s| DynShape::Jit(s, *budget))
+            }
+        }
+    }
+
+    type TransformedShape = TransformedShape<Self>;
+    fn apply_transform(self, mat: Matrix4<f32>) -> Self::TransformedShape {
+        TransformedShape::new(self, mat)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::eval::EzShape;
+
+    #[test]
+    fn test_dyn_shape() {
+        let (x, y, z) = Tree::axes();
+        let tree = x.clone().min(y.clone() * 2.0).max(z.clone() - x.sin());
+        let vm = DynShape::from_tree_with(&tree, Backend::Vm);
+        assert_eq!(vm.backend(), Backend::Vm);
+        let jit = DynShape::from_tree_with(&tree, Backend::Jit);
+        assert_eq!(jit.backend(), Backend::default());
+        assert_eq!(DynShape::from_tree(&tree).backend(), Backend::default());
+
+        let xs = [0.0, 1.0, -2.0, 0.5];
+        let ys = [1.0, -0.5, 3.0, 0.125];
+        let zs = [0.5, 2.0, -1.0, 0.0];
+
+        let mut point = DynShape::new_point_eval();
+        let mut interval = DynShape::new_interval_eval();
+        let mut float = DynShape::new_float_slice_eval();
+        let mut grad = DynShape::new_grad_slice_eval();
+        let mut results = vec![];
+        for shape in [&vm, &jit] {
+            let p = (0..xs.len())
+                .map(|i| {
+                    point
+                        .eval(&shape.ez_point_tape(), xs[i], ys[i], zs[i])
+                        .unwrap()
+                        .0
+                })
+                .collect::<Vec<_>>();
+            let (i, trace) = interval
+                .eval(
+                    &shape.ez_interval_tape(),
+                    [0.0, 1.0],
+                    [4.0, 5.0],
+                    [0.0, 1.0],
+                )
+                .unwrap();
+            let f = float
+                .eval(&shape.ez_float_slice_tape(), &xs, &ys, &zs)
+                .unwrap()
+                .to_vec();
+            let g = grad
+                .eval(&shape.ez_grad_slice_tape(), &xs, &ys, &zs)
+                .unwrap()
+                .to_vec();
+            assert_eq!(p, f);
+
+            // Simplifying keeps the same backend
+            let next = shape.ez_simplify(trace.unwrap()).unwrap();
+            assert_eq!(next.backend(), shape.backend());
+            assert!(next.size() < shape.size());
+            results.push((p, i, g));
+        }
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    #[test]
+    fn test_compile_budget() {
+        if !crate::jit::is_supported() {
+            return;
+        }
+        let (x, y, z) = Tree::axes();
+        let tree = x.clone().min(y.clone() * 2.0).max(z.clone() - x.sin());
+        let jit = DynShape::from_tree_with(&tree, Backend::Jit);
+        let size = jit.size();
+
+        // Within budget, so tapes are compiled
+        let shape = jit.clone().with_compile_budget(Some(size));
+        assert!(matches!(shape.ez_point_tape(), DynPointTape::Jit(..)));
+
+        // Over budget, so tapes are interpreted
+        let shape = jit.clone().with_compile_budget(Some(size - 1));
+        assert_eq!(shape.backend(), Backend::Jit);
+        let tape = shape.ez_interval_tape();
+        assert!(matches!(tape, DynIntervalTape::JitFallback(..)));
+        let mut eval = DynShape::new_interval_eval();
+        let (i, trace) = eval
+            .eval(&tape, [0.0, 1.0], [4.0, 5.0], [0.0, 1.0])
+            .unwrap();
+        let mut jit_eval = DynShape::new_interval_eval();
+        let (expected, _) = jit_eval
+            .eval(&jit.ez_interval_tape(), [0.0, 1.0], [4.0, 5.0], [0.0, 1.0])
+            .unwrap();
+        assert_eq!(i, expected);
+
+        let xs = [0.0, 1.0, -2.0, 0.5];
+        let ys = [1.0, -0.5, 3.0, 0.125];
+        let zs = [0.5, 2.0, -1.0, 0.0];
+        let tape = shape.ez_float_slice_tape();
+        assert!(matches!(tape, DynFloatSliceTape::JitFallback(..)));
+        let mut eval = DynShape::new_float_slice_eval();
+        let out = eval.eval(&tape, &xs, &ys, &zs).unwrap().to_vec();
+        let expected = eval
+            .eval(&jit.ez_float_slice_tape(), &xs, &ys, &zs)
+            .unwrap();
+        assert_eq!(out, expected);
+
+        // The simplified shape keeps the budget, and is short enough to compile
+        let next = shape.ez_simplify(trace.unwrap()).unwrap();
+        assert!(next.size() < size);
+        assert!(matches!(
+            next.ez_grad_slice_tape(),
+            DynGradSliceTape::Jit(..)
+        ));
+        let next = next.with_compile_budget(Some(0));
+        assert!(matches!(
+            next.ez_grad_slice_tape(),
+            DynGradSliceTape::JitFallback(..)
+        ));
+    }
+}
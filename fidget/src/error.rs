@@ -25,6 +25,20 @@ pub enum Error {
     #[error("unknown variable {0}")]
     UnknownVariable(String),
 
+    /// Expression has too many slots ({0}) to be stored in a tape
+    #[error("expression has too many slots ({0}) to be stored in a tape")]
+    TooManySlots(usize),
+    /// Expression has too many choices ({0}) to be stored in a tape
+    #[error("expression has too many choices ({0}) to be stored in a tape")]
+    TooManyChoices(usize),
+    /// Expression has too many inputs ({0}) to be stored in a tape
+    #[error("expression has too many inputs ({0}) to be stored in a tape")]
+    TooManyInputs(usize),
+
+    /// Tape is malformed (e.g. after deserializing untrusted data)
+    #[error("invalid tape: {0}")]
+    InvalidTape(&'static str),
+
     /// Empty file
     #[error("empty file")]
     EmptyFile,
@@ -41,14 +55,75 @@ pub enum Error {
     #[error("var slice length ({0}) does not match var count ({1})")]
     BadVarSlice(usize, usize),
 
+    /// Variable index is out of range
+    #[error("variable index ({0}) is out of range ({1} slots)")]
+    BadVarIndex(usize, usize),
+
+    /// Named variable bindings don't match a tape's variables
+    #[error(
+        "bad variable bindings (missing: {missing:?}, unknown: {extra:?})"
+    )]
+    BadBindings {
+        /// Variables which are used by the tape but were not given a value
+        missing: Vec<String>,
+        /// Names which do not match any variable in the tape
+        extra: Vec<String>,
+    },
+
+    /// Strided view has a stride of zero or extends past its buffer
+    #[error("strided view has a stride of zero or extends past its buffer")]
+    BadStride,
+
     /// This name is reserved for 3D coordinates
     #[error("this name is reserved for 3D coordinates")]
     ReservedName,
 
+    /// This name cannot be used for a named definition
+    #[error("`{0}` is not a valid name for a definition")]
+    BadName(String),
+
+    /// A value range is invalid or conflicts with a node's known range
+    #[error("value range is invalid or conflicts with the known range")]
+    BadRange,
+
+    /// Rewrite rule uses capture slot {0}, which isn't bound by its pattern
+    #[error(
+        "rewrite rule uses capture slot {0}, which isn't bound by its pattern"
+    )]
+    UnboundCapture(usize),
+
     /// This name has already been used
     #[error("this name has already been used")]
     DuplicateName,
 
+    /// Bounds are invalid (non-finite or without a positive size)
+    #[error("bounds must have a finite center and a positive, finite size")]
+    InvalidBounds,
+
+    /// Bounds are required but were not specified
+    #[error("bounds must be specified")]
+    MissingBounds,
+
+    /// A length is invalid (zero or non-finite)
+    #[error("length must be finite and non-zero")]
+    InvalidLength,
+
+    /// A view transform is invalid (non-finite or not invertible)
+    #[error("view transform must be finite and invertible")]
+    InvalidView,
+
+    /// Atlas configuration is invalid
+    #[error("invalid atlas configuration: {0}")]
+    InvalidAtlas(&'static str),
+
+    /// Texture configuration is invalid
+    #[error("invalid texture configuration: {0}")]
+    InvalidTexture(&'static str),
+
+    /// The job was cancelled (see [`CancelToken`](crate::exec::CancelToken))
+    #[error("the job was cancelled")]
+    Cancelled,
+
     /// io error; see inner code for details
     #[error("io error: {0}")]
     IoError(#[from] std::io::Error),
@@ -63,6 +138,16 @@ pub enum Error {
     #[error("Rhai evaluation error: {0}")]
     RhaiEvalError(#[from] rhai::EvalAltResult),
 
+    #[cfg(feature = "jit")]
+    /// The JIT is not supported on this CPU
+    #[error("the JIT is not supported on this CPU")]
+    JitUnsupported,
+
+    #[cfg(feature = "jit")]
+    /// The JIT only supports tapes with `X`, `Y`, and `Z` inputs
+    #[error("the JIT only supports tapes with `X`, `Y`, and `Z` inputs")]
+    JitInputs,
+
     #[cfg(feature = "jit")]
     /// Dynasm error; see inner code for details
     #[error("dynasm error: {0}")]
@@ -0,0 +1,642 @@
+//! Shared job scheduling, with priorities and cancellation
+//!
+//! By default, rendering and meshing each spin up their own worker threads.
+//! When an application runs several jobs at once (e.g. an interactive preview
+//! render alongside a background meshing job), those threads compete for the
+//! CPU.
+//!
+//! An [`Executor`] coordinates them.  Each job is run with
+//! [`Executor::run`] at a particular [`Priority`]; worker threads spawned by
+//! that job only do work while holding one of the executor's slots, so the
+//! number of busy threads never exceeds [`Executor::slots`].  Between units of
+//! work (tiles when rendering, octree cells when meshing), lower-priority
+//! workers give up their slots if higher-priority work is waiting, and all
+//! workers stop early if the job's [`CancelToken`] has been triggered.
+//!
+//! ```
+//! use fidget::{
+//!     context::Tree,
+//!     eval::MathShape,
+//!     exec::{CancelToken, Executor, Priority},
+//!     render::{BitRenderMode, RenderConfig},
+//!     vm::VmShape,
+//! };
+//!
+//! let (x, y, _) = Tree::axes();
+//! let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+//!
+//! let exec = Executor::new(4.try_into().unwrap());
+//! let cancel = CancelToken::new();
+//! let cfg = RenderConfig::<2> {
+//!     image_size: 64.into(),
+//!     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+//!     ..Default::default()
+//! };
+//! let image = exec.run(Priority::Interactive, &cancel, || {
+//!     cfg.run::<_, BitRenderMode>(shape.clone())
+//! })?;
+//! assert_eq!(image.len(), 64 * 64);
+//!
+//! // Cancelled jobs stop early and return an error
+//! cancel.cancel();
+//! let r = exec.run(Priority::Background, &cancel, || {
+//!     cfg.run::<_, BitRenderMode>(shape)
+//! });
+//! assert!(matches!(r, Err(fidget::Error::Cancelled)));
+//! # Ok::<(), fidget::Error>(())
+//! ```
+use crate::Error;
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, Weak,
+    },
+};
+
+/// Priority of a job run on an [`Executor`]
+///
+/// Workers from a higher-priority job preempt lower-priority workers at their
+/// next checkpoint.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Long-running work which should yield to everything else
+    Background,
+    /// Default priority
+    #[default]
+    Normal,
+    /// Latency-sensitive work, e.g. a preview render
+    Interactive,
+}
+
+impl Priority {
+    const COUNT: usize = 3;
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Shared flag used to cancel a job
+///
+/// Cloning a token produces a handle to the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<CancelData>);
+
+#[derive(Debug, Default)]
+struct CancelData {
+    cancelled: AtomicBool,
+
+    /// Executors which have run jobs with this token
+    ///
+    /// These are notified when the token is cancelled, so that workers which
+    /// are waiting for a slot can stop.
+    executors: Mutex<Vec<Weak<ExecutorData>>>,
+}
+
+impl CancelToken {
+    /// Builds a new token, which is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels every job using this token
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Release);
+        let executors = self.0.executors.lock().unwrap();
+        for e in executors.iter().filter_map(Weak::upgrade) {
+            // Take the lock before notifying, so that a worker can't miss the
+            // wakeup between checking the token and waiting.
+            let _state = e.state.lock().unwrap();
+            e.cond.notify_all();
+        }
+    }
+
+    /// Checks whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Records that a job with this token is running on the given executor
+    fn register(&self, exec: &Arc<ExecutorData>) {
+        let mut executors = self.0.executors.lock().unwrap();
+        executors.retain(|e| e.strong_count() > 0);
+        if !executors.iter().any(|e| std::ptr::eq(e.as_ptr(), &**exec)) {
+            executors.push(Arc::downgrade(exec));
+        }
+    }
+}
+
+/// Scheduler which limits the number of busy worker threads across jobs
+///
+/// The executor is cheap to clone; clones share the same set of slots.
+#[derive(Clone)]
+pub struct Executor(Arc<ExecutorData>);
+
+struct ExecutorData {
+    slots: usize,
+    state: Mutex<ExecutorState>,
+    cond: Condvar,
+
+    /// Total number of waiting workers (mirroring `ExecutorState::waiting`),
+    /// so that workers holding a slot can skip locking at every checkpoint
+    waiting: AtomicUsize,
+}
+
+#[derive(Default)]
+struct ExecutorState {
+    /// Number of workers currently holding a slot
+    active: usize,
+    /// Number of workers waiting for a slot, indexed by priority
+    waiting: [usize; Priority::COUNT],
+}
+
+impl ExecutorState {
+    fn waiting_above(&self, p: Priority) -> bool {
+        self.waiting[p.index() + 1..].iter().any(|n| *n > 0)
+    }
+}
+
+impl Executor {
+    /// Builds a new executor with the given number of slots
+    pub fn new(slots: std::num::NonZeroUsize) -> Self {
+        Self(Arc::new(ExecutorData {
+            slots: slots.get(),
+            state: Mutex::new(ExecutorState::default()),
+            cond: Condvar::new(),
+            waiting: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the maximum number of simultaneously busy worker threads
+    pub fn slots(&self) -> usize {
+        self.0.slots
+    }
+
+    /// Runs a job on this executor
+    ///
+    /// Rendering and meshing functions called within `f` (on the current
+    /// thread) use this executor, priority, and cancellation token; they
+    /// return [`Error::Cancelled`] if the token is cancelled before they
+    /// finish.  Jobs may be nested, in which case the innermost job is used.
+    pub fn run<T, F: FnOnce() -> T>(
+        &self,
+        priority: Priority,
+        cancel: &CancelToken,
+        f: F,
+    ) -> T {
+        cancel.register(&self.0);
+        let job = Job {
+            exec: self.clone(),
+            priority,
+            cancel: cancel.clone(),
+        };
+        let _guard = job.enter();
+        f()
+    }
+
+    /// Blocks until the caller can take a slot at the given priority
+    ///
+    /// If `holding` is set, the caller already has a slot, which it keeps
+    /// unless higher-priority work is waiting.  Returns early (without a slot)
+    /// if the token is cancelled.
+    fn acquire(&self, p: Priority, holding: &mut bool, cancel: &CancelToken) {
+        if *holding && self.0.waiting.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        let mut s = self.0.state.lock().unwrap();
+        if *holding {
+            if !s.waiting_above(p) {
+                return;
+            }
+            s.active -= 1;
+            *holding = false;
+            self.0.cond.notify_all();
+        }
+        s.waiting[p.index()] += 1;
+        self.0.waiting.fetch_add(1, Ordering::Release);
+        while s.active >= self.0.slots || s.waiting_above(p) {
+            if cancel.is_cancelled() {
+                s.waiting[p.index()] -= 1;
+                self.0.waiting.fetch_sub(1, Ordering::Release);
+                self.0.cond.notify_all();
+                return;
+            }
+            s = self.0.cond.wait(s).unwrap();
+        }
+        s.waiting[p.index()] -= 1;
+        self.0.waiting.fetch_sub(1, Ordering::Release);
+        s.active += 1;
+        *holding = true;
+    }
+
+    /// Releases a slot, if the caller holds one
+    fn release(&self, holding: &mut bool) {
+        if std::mem::take(holding) {
+            let mut s = self.0.state.lock().unwrap();
+            s.active -= 1;
+            self.0.cond.notify_all();
+        }
+    }
+}
+
+/// Handle to a job, which can be passed to worker threads
+#[derive(Clone)]
+pub(crate) struct Job {
+    exec: Executor,
+    priority: Priority,
+    cancel: CancelToken,
+}
+
+struct WorkerState {
+    job: Job,
+    holding: bool,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Vec<WorkerState>> = const { RefCell::new(vec![]) };
+}
+
+/// Guard which marks a thread as working on a [`Job`]
+///
+/// When dropped, releases the thread's slot (if held) and restores the
+/// previous job (if any).
+pub(crate) struct JobGuard {
+    // Guards must be dropped on the thread which created them
+    _phantom: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        if let Some(mut w) = CURRENT.with(|c| c.borrow_mut().pop()) {
+            w.job.exec.release(&mut w.holding);
+        }
+    }
+}
+
+impl Job {
+    /// Marks the current thread as working on this job
+    ///
+    /// Slots are taken lazily, at the first [`checkpoint`].
+    pub(crate) fn enter(self) -> JobGuard {
+        CURRENT.with(|c| {
+            c.borrow_mut().push(WorkerState {
+                job: self,
+                holding: false,
+            })
+        });
+        JobGuard {
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+/// Returns the job running on the current thread, if any
+///
+/// This should be passed to (and [entered](Job::enter) by) worker threads.
+pub(crate) fn current() -> Option<Job> {
+    CURRENT.with(|c| c.borrow().last().map(|w| w.job.clone()))
+}
+
+/// Enters the given job (if present), returning a guard
+pub(crate) fn enter(job: Option<Job>) -> Option<JobGuard> {
+    job.map(Job::enter)
+}
+
+/// Returns the number of worker threads to use for the current job
+///
+/// This is the requested count, limited to the current executor's slots.
+pub(crate) fn threads(requested: usize) -> usize {
+    CURRENT.with(|c| match c.borrow().last() {
+        Some(w) => requested.min(w.job.exec.slots()),
+        None => requested,
+    })
+}
+
+/// Checks whether the current job has been cancelled
+pub(crate) fn is_cancelled() -> bool {
+    CURRENT.with(|c| {
+        c.borrow()
+            .last()
+            .map(|w| w.job.cancel.is_cancelled())
+            .unwrap_or(false)
+    })
+}
+
+/// Called by worker threads before each unit of work
+///
+/// If the current thread is running a job, this takes a slot (blocking if none
+/// are available) or yields its existing slot to higher-priority work.
+///
+/// Returns [`Error::Cancelled`] if the job has been cancelled, in which case
+/// the worker should stop as soon as possible.
+pub(crate) fn checkpoint() -> Result<(), Error> {
+    CURRENT.with(|c| {
+        let mut c = c.borrow_mut();
+        let Some(w) = c.last_mut() else {
+            return Ok(());
+        };
+        if !w.job.cancel.is_cancelled() {
+            w.job
+                .exec
+                .acquire(w.job.priority, &mut w.holding, &w.job.cancel);
+        }
+        if w.job.cancel.is_cancelled() {
+            w.job.exec.release(&mut w.holding);
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    })
+}
+
+/// Runs `f` as a cancellable unit of work
+///
+/// Returns [`Error::Cancelled`] if the current job is cancelled before `f`
+/// starts or by the time it finishes (in which case its output may be
+/// incomplete, and is discarded).
+pub(crate) fn cancellable<T>(f: impl FnOnce() -> T) -> Result<T, Error> {
+    if is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    let out = f();
+    if is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    Ok(out)
+}
+
+/// Releases the current thread's slot while it's idle
+///
+/// This should be called before a worker sleeps, or before a thread blocks
+/// while waiting for its own workers to finish.
+/// The slot is taken again at the next [`checkpoint`].
+pub(crate) fn idle() {
+    CURRENT.with(|c| {
+        if let Some(w) = c.borrow_mut().last_mut() {
+            w.job.exec.release(&mut w.holding);
+        }
+    })
+}
+
+/// Runs `jobs` independent jobs on a pool of up to `threads` scoped threads
+///
+/// Each thread builds its own state with `init`, then repeatedly claims the
+/// next job index and calls `f`.  Returns `(index, output)` pairs in arbitrary
+/// order, or the first error.  On `wasm32`, jobs always run on the calling
+/// thread.
+///
+/// Worker threads run as part of the current [`Job`] (if any), so `f` may
+/// call [`checkpoint`].
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+pub(crate) fn run_indexed<W, T: Send, E: Send>(
+    jobs: usize,
+    threads: usize,
+    init: impl Fn() -> W + Sync,
+    f: impl Fn(&mut W, usize) -> Result<T, E> + Sync,
+) -> Result<Vec<(usize, T)>, E> {
+    let index = AtomicUsize::new(0);
+    let work = || {
+        let mut w = init();
+        let mut out = vec![];
+        loop {
+            let i = index.fetch_add(1, Ordering::Relaxed);
+            if i >= jobs {
+                break Ok(out);
+            }
+            out.push((i, f(&mut w, i)?));
+        }
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if threads.min(jobs) > 1 {
+        return std::thread::scope(|s| {
+            let handles: Vec<_> = (0..threads.min(jobs))
+                .map(|_| {
+                    let job = current();
+                    let work = &work;
+                    s.spawn(move || {
+                        let _guard = enter(job);
+                        work()
+                    })
+                })
+                .collect();
+            idle();
+            let mut out = vec![];
+            for h in handles {
+                out.extend(h.join().unwrap()?);
+            }
+            Ok(out)
+        });
+    }
+    work()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checkpoint_without_job() {
+        assert!(checkpoint().is_ok());
+        assert!(!is_cancelled());
+        assert_eq!(threads(7), 7);
+        assert!(current().is_none());
+    }
+
+    #[test]
+    fn slot_limit() {
+        let exec = Executor::new(2.try_into().unwrap());
+        let cancel = CancelToken::new();
+        let busy = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        exec.run(Priority::Normal, &cancel, || {
+            assert_eq!(threads(8), 2);
+            let job = current();
+            std::thread::scope(|s| {
+                for _ in 0..8 {
+                    let job = job.clone();
+                    let (busy, peak) = (&busy, &peak);
+                    s.spawn(move || {
+                        let _guard = enter(job);
+                        for _ in 0..10 {
+                            checkpoint().unwrap();
+                            let n = busy.fetch_add(1, Ordering::SeqCst) + 1;
+                            peak.fetch_max(n, Ordering::SeqCst);
+                            std::thread::sleep(
+                                std::time::Duration::from_micros(100),
+                            );
+                            busy.fetch_sub(1, Ordering::SeqCst);
+                            idle();
+                        }
+                    });
+                }
+            });
+        });
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert_eq!(exec.0.state.lock().unwrap().active, 0);
+    }
+
+    #[test]
+    fn preemption() {
+        let exec = Executor::new(1.try_into().unwrap());
+        let cancel = CancelToken::new();
+        let order = Mutex::new(vec![]);
+        let started = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            // Background job, which holds the only slot until the interactive
+            // job is waiting for it, then is preempted at its next checkpoint
+            s.spawn(|| {
+                exec.run(Priority::Background, &cancel, || {
+                    for i in 0..20 {
+                        checkpoint().unwrap();
+                        order.lock().unwrap().push(("bg", i));
+                        if i == 0 {
+                            started.store(true, Ordering::SeqCst);
+                            while exec.0.waiting.load(Ordering::SeqCst) == 0 {
+                                std::thread::yield_now();
+                            }
+                        }
+                    }
+                })
+            });
+            while !started.load(Ordering::SeqCst) {
+                std::thread::yield_now();
+            }
+            s.spawn(|| {
+                exec.run(Priority::Interactive, &cancel, || {
+                    for i in 0..3 {
+                        checkpoint().unwrap();
+                        order.lock().unwrap().push(("fg", i));
+                    }
+                })
+            });
+        });
+
+        // The interactive job runs to completion in one uninterrupted block,
+        // as soon as the background job reaches its next checkpoint.
+        let order = order.into_inner().unwrap();
+        assert_eq!(order.len(), 23);
+        assert_eq!(order[..4], [("bg", 0), ("fg", 0), ("fg", 1), ("fg", 2)]);
+        assert_eq!(order.last(), Some(&("bg", 19)));
+    }
+
+    #[test]
+    fn cancellation() {
+        let exec = Executor::new(1.try_into().unwrap());
+        let cancel = CancelToken::new();
+        exec.run(Priority::Normal, &cancel, || {
+            assert!(checkpoint().is_ok());
+            cancel.cancel();
+            assert!(is_cancelled());
+            assert!(matches!(checkpoint(), Err(Error::Cancelled)));
+        });
+        assert!(!is_cancelled());
+        assert_eq!(exec.0.state.lock().unwrap().active, 0);
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn cancel_render() {
+        use crate::{
+            context::Tree,
+            eval::MathShape,
+            render::{BitRenderMode, RenderConfig},
+            vm::VmShape,
+        };
+        let (x, y, _) = Tree::axes();
+        let shape =
+            VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+        let cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            bounds: Some(crate::shape::Bounds::centered(1.0)),
+            ..Default::default()
+        };
+
+        let exec = Executor::new(1.try_into().unwrap());
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let r = exec.run(Priority::Normal, &cancel, || {
+            crate::render::render2d::<_, BitRenderMode>(shape.clone(), &cfg)
+        });
+        assert!(matches!(r, Err(Error::Cancelled)));
+        let r = exec.run(Priority::Normal, &CancelToken::new(), || {
+            crate::render::render2d::<_, BitRenderMode>(shape, &cfg)
+        });
+        assert_eq!(r.unwrap().len(), 64 * 64);
+    }
+
+    #[cfg(feature = "mesh")]
+    #[test]
+    fn cancel_mesh() {
+        use crate::{
+            context::Tree,
+            eval::MathShape,
+            mesh::{Octree, Settings},
+            vm::VmShape,
+        };
+        let (x, y, z) = Tree::axes();
+        let sphere = (x.square() + y.square() + z.square()).sqrt() - 0.6;
+        let shape = VmShape::from_tree(&sphere);
+
+        let exec = Executor::new(2.try_into().unwrap());
+        for threads in [1, 4] {
+            let settings = Settings {
+                depth: 8,
+                threads: threads.try_into().unwrap(),
+                bounds: Some(crate::shape::Bounds::centered(1.0)),
+                ..Default::default()
+            };
+            let cancel = CancelToken::new();
+            let held = AtomicUsize::new(0);
+            let done = AtomicBool::new(false);
+            let r = std::thread::scope(|s| {
+                // Occupy every slot with a higher-priority job, so that the
+                // meshing job blocks at its first checkpoint
+                for _ in 0..exec.slots() {
+                    s.spawn(|| {
+                        exec.run(
+                            Priority::Interactive,
+                            &CancelToken::new(),
+                            || {
+                                checkpoint().unwrap();
+                                held.fetch_add(1, Ordering::SeqCst);
+                                while !done.load(Ordering::SeqCst) {
+                                    std::thread::yield_now();
+                                }
+                            },
+                        )
+                    });
+                }
+                while held.load(Ordering::SeqCst) < exec.slots() {
+                    std::thread::yield_now();
+                }
+                let h = s.spawn(|| {
+                    exec.run(Priority::Background, &cancel, || {
+                        Octree::build(&shape, settings)
+                    })
+                });
+                while exec.0.waiting.load(Ordering::SeqCst) == 0 {
+                    std::thread::yield_now();
+                }
+
+                // Cancelling the token wakes up the blocked workers
+                cancel.cancel();
+                let r = h.join().unwrap();
+                done.store(true, Ordering::SeqCst);
+                r
+            });
+            assert!(matches!(r, Err(Error::Cancelled)));
+            assert_eq!(exec.0.state.lock().unwrap().active, 0);
+
+            // A fresh token lets the job run to completion
+            let settings = Settings {
+                depth: 4,
+                ..settings
+            };
+            let r = exec.run(Priority::Normal, &CancelToken::new(), || {
+                Octree::build(&shape, settings)
+            });
+            assert!(r.is_ok());
+        }
+    }
+}
@@ -17,6 +17,10 @@ pub const SIMD_WIDTH: usize = 4;
 /// The arrays must be an even multiple of 4 floats, since we're using NEON and
 /// 128-bit wide operations for everything.
 ///
+/// Each loop iteration evaluates a single 4-wide vector.  The loop isn't
+/// unrolled, because tape registers already occupy `v8-v31`: a second set of
+/// lanes would need another 24 vector registers, which NEON doesn't have.
+///
 /// During evaluation, the following registers are used:
 ///
 /// | Register | Description                                          |
@@ -140,20 +144,24 @@ impl Assembler for FloatSliceAssembler {
     fn build_load(&mut self, dst_reg: u8, src_mem: u32) {
         assert!((dst_reg as usize) < REGISTER_LIMIT);
         let sp_offset = self.0.stack_pos(src_mem) + STACK_SIZE;
-        assert!(sp_offset < 65536);
-        dynasm!(self.0.ops
-            ; ldr Q(reg(dst_reg)), [sp, sp_offset]
-        )
+        if sp_offset < 65536 {
+            dynasm!(self.0.ops ; ldr Q(reg(dst_reg)), [sp, sp_offset])
+        } else {
+            self.0.load_offset(sp_offset);
+            dynasm!(self.0.ops ; ldr Q(reg(dst_reg)), [sp, x16])
+        }
     }
 
     /// Writes from `src_reg` to `dst_mem`
     fn build_store(&mut self, dst_mem: u32, src_reg: u8) {
         assert!((src_reg as usize) < REGISTER_LIMIT);
         let sp_offset = self.0.stack_pos(dst_mem) + STACK_SIZE;
-        assert!(sp_offset < 65536);
-        dynasm!(self.0.ops
-            ; str Q(reg(src_reg)), [sp, sp_offset]
-        )
+        if sp_offset < 65536 {
+            dynasm!(self.0.ops ; str Q(reg(src_reg)), [sp, sp_offset])
+        } else {
+            self.0.load_offset(sp_offset);
+            dynasm!(self.0.ops ; str Q(reg(src_reg)), [sp, x16])
+        }
     }
     /// Copies the given input to `out_reg`
     fn build_input(&mut self, out_reg: u8, src_arg: u8) {
@@ -435,11 +443,9 @@ impl Assembler for FloatSliceAssembler {
             ; ldr x22, [sp, 0x210]
             ; ldr x23, [sp, 0x218]
             ; ldr x24, [sp, 0x220]
-
-            // Fix up the stack
-            ; add sp, sp, self.0.mem_offset as u32
-            ; ret
         );
+        self.0.pop_stack();
+        dynasm!(self.0.ops ; ret);
 
         self.0.ops.finalize()
     }
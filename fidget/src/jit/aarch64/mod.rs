@@ -8,15 +8,37 @@
 //! This means that the input tape must be planned with a <= 24 register limit;
 //! any spills will live on the stack.
 //!
-//! Right now, we never call anything, so don't worry about saving stuff.
+//! Transcendental functions (`sin`, `exp`, `ln`, etc) are implemented by
+//! calling into Rust (`extern "C"` functions); tape registers are backed up to
+//! the stack around each call, and slice evaluators call the function once
+//! per SIMD lane.
 //!
 //! Within a single operation, you'll often need to make use of scratch
 //! registers.  `s3` / `v3` is used when loading immediates, and should not be
 //! used as a scratch register (this is the `IMM_REG` constant).  `s4-7`/`v4-7`
-//! are all available, and are callee-saved.
+//! are all available, and are caller-saved.
 //!
 //! For general-purpose registers, `x9-15` (also called `w9-15`) are reasonable
-//! choices; they are caller-saved, so we can trash them at will.
+//! choices; they are caller-saved, so we can trash them at will.  `x16` is
+//! reserved for stack offsets which don't fit into an immediate, when loading
+//! or storing spilled registers in large stack frames.
+//!
+//! # Calling convention
+//! Linux, macOS, and Windows all use the standard AAPCS64 calling convention
+//! (with minor differences that don't affect us), so the same code runs on
+//! each of them:
+//!
+//! - The lower 64 bits of `v8-15` (i.e. `d8-15`) are callee-saved, and are
+//!   stored to the stack in each function's prologue
+//! - `x19-28` are callee-saved; functions which call into Rust back up the
+//!   ones they use (`x20-24`) in their prologue
+//! - `x18` is reserved as a platform register on macOS and Windows, and is
+//!   never used
+//! - Stack frames are sized from the tape's spill slots, and frames larger
+//!   than a page are probed one page at a time (which Windows requires)
+//!
+//! Memory protection and instruction cache maintenance are platform-specific,
+//! and handled by the `Mmap` type.
 
 /// We can use registers `v8-15` (callee saved) and `v16-31` (caller saved)
 pub const REGISTER_LIMIT: usize = 24;
@@ -25,6 +47,13 @@ pub const IMM_REG: u8 = 3;
 /// `v4-7` are used for as temporary variables
 pub const OFFSET: u8 = 8;
 
+/// Checks whether the CPU supports every instruction that we emit
+///
+/// We only use NEON, which is part of the `aarch64` baseline.
+pub fn is_supported() -> bool {
+    true
+}
+
 pub mod float_slice;
 pub mod grad_slice;
 pub mod interval;
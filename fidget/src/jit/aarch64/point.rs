@@ -104,15 +104,23 @@ impl Assembler for PointAssembler {
     fn build_load(&mut self, dst_reg: u8, src_mem: u32) {
         assert!((dst_reg as usize) < REGISTER_LIMIT);
         let sp_offset = self.0.stack_pos(src_mem) + STACK_SIZE;
-        assert!(sp_offset <= 16384);
-        dynasm!(self.0.ops ; ldr S(reg(dst_reg)), [sp, sp_offset])
+        if sp_offset < 16384 {
+            dynasm!(self.0.ops ; ldr S(reg(dst_reg)), [sp, sp_offset])
+        } else {
+            self.0.load_offset(sp_offset);
+            dynasm!(self.0.ops ; ldr S(reg(dst_reg)), [sp, x16])
+        }
     }
     /// Writes from `src_reg` to `dst_mem`
     fn build_store(&mut self, dst_mem: u32, src_reg: u8) {
         assert!((src_reg as usize) < REGISTER_LIMIT);
         let sp_offset = self.0.stack_pos(dst_mem) + STACK_SIZE;
-        assert!(sp_offset <= 16384);
-        dynasm!(self.0.ops ; str S(reg(src_reg)), [sp, sp_offset])
+        if sp_offset < 16384 {
+            dynasm!(self.0.ops ; str S(reg(src_reg)), [sp, sp_offset])
+        } else {
+            self.0.load_offset(sp_offset);
+            dynasm!(self.0.ops ; str S(reg(src_reg)), [sp, x16])
+        }
     }
     /// Copies the given input to `out_reg`
     fn build_input(&mut self, out_reg: u8, src_arg: u8) {
@@ -452,12 +460,9 @@ impl Assembler for PointAssembler {
             ; ldp   d10, d11, [sp, 0x20]
             ; ldp   d12, d13, [sp, 0x30]
             ; ldp   d14, d15, [sp, 0x40]
-
-            // Fix up the stack
-            ; add sp, sp, (self.0.mem_offset as u32)
-
-            ; ret
         );
+        self.0.pop_stack();
+        dynasm!(self.0.ops ; ret);
 
         self.0.ops.finalize()
     }
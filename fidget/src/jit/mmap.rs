@@ -1,12 +1,25 @@
 #[cfg(target_os = "windows")]
-use windows::Win32::System::Memory::{
-    VirtualAlloc, VirtualFree, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE,
-    PAGE_EXECUTE_READWRITE,
+use windows::Win32::System::{
+    Diagnostics::Debug::FlushInstructionCache,
+    Memory::{
+        VirtualAlloc, VirtualFree, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE,
+        PAGE_EXECUTE_READWRITE,
+    },
+    Threading::GetCurrentProcess,
 };
 
 pub struct Mmap {
+    /// Writable view of the mapping
     ptr: *mut libc::c_void,
+    /// Executable view of the mapping
+    ///
+    /// This is the same as `ptr`, unless the mapping is dual-mapped (see
+    /// [`Mmap::new_dual`])
+    #[cfg(target_os = "linux")]
+    exec: *mut libc::c_void,
     len: usize,
+    /// Number of bytes of machine code, set by [`Mmap::finalize`]
+    code_len: usize,
 }
 
 // SAFETY: this is philosophically a `Vec<u8>`, so can be sent to other threads
@@ -22,7 +35,10 @@ impl Mmap {
     pub fn empty() -> Self {
         Self {
             ptr: std::ptr::null_mut::<libc::c_void>(),
+            #[cfg(target_os = "linux")]
+            exec: std::ptr::null_mut::<libc::c_void>(),
             len: 0,
+            code_len: 0,
         }
     }
 
@@ -30,7 +46,7 @@ impl Mmap {
     ///
     /// If `len == 0`, this will return an `Mmap` of size `PAGE_SIZE`; for a
     /// empty `Mmap` (which makes no system calls), use `Mmap::empty` instead.
-    #[cfg(not(target_os = "windows"))]
+    #[cfg(target_os = "macos")]
     pub fn new(len: usize) -> Result<Self, std::io::Error> {
         let len = len.max(1).next_multiple_of(Self::PAGE_SIZE);
 
@@ -48,7 +64,11 @@ impl Mmap {
         if ptr == libc::MAP_FAILED {
             Err(std::io::Error::last_os_error())
         } else {
-            Ok(Self { ptr, len })
+            Ok(Self {
+                ptr,
+                len,
+                code_len: 0,
+            })
         }
     }
 
@@ -68,7 +88,11 @@ impl Mmap {
         if ptr.is_null() {
             Err(std::io::Error::last_os_error())
         } else {
-            Ok(Self { ptr, len })
+            Ok(Self {
+                ptr,
+                len,
+                code_len: 0,
+            })
         }
     }
 
@@ -94,19 +118,31 @@ impl Mmap {
         }
     }
 
+    /// Returns the machine code written before the last call to `finalize`
+    pub fn code(&self) -> &[u8] {
+        &self.as_slice()[..self.code_len]
+    }
+
     /// Treats the memory-mapped data as a slice
     #[inline(always)]
     pub fn as_slice(&self) -> &[u8] {
         unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
     }
 
-    /// Returns the inner pointer
+    /// Returns a pointer to the executable view of the mapping
+    #[cfg(not(target_os = "linux"))]
     pub fn as_ptr(&self) -> *mut libc::c_void {
         self.ptr
     }
+
+    /// Returns a pointer to the executable view of the mapping
+    #[cfg(target_os = "linux")]
+    pub fn as_ptr(&self) -> *mut libc::c_void {
+        self.exec
+    }
 }
 
-#[cfg(any(target_os = "linux", target_os = "windows"))]
+#[cfg(target_os = "linux")]
 impl Mmap {
     #[cfg(target_arch = "aarch64")]
     fn flush_cache(&self, size: usize) {
@@ -171,7 +207,8 @@ impl Mmap {
     ///
     /// Note that you will still need to change the global W^X mode before
     /// evaluation, but that's on a per-thread (rather than per-mmap) basis.
-    pub fn finalize(&self, size: usize) {
+    pub fn finalize(&mut self, size: usize) {
+        self.code_len = size;
         unsafe {
             macos::sys_icache_invalidate(self.ptr, size);
         }
@@ -192,6 +229,13 @@ impl Mmap {
     }
 }
 
+/// Set when writable + executable mappings are forbidden
+///
+/// Once set, every new `Mmap` is dual-mapped (see [`Mmap::new_dual`]).
+#[cfg(target_os = "linux")]
+static DUAL_MAP: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
 #[cfg(target_os = "linux")]
 impl Mmap {
     pub const MMAP_PROT: i32 =
@@ -200,10 +244,97 @@ impl Mmap {
     pub const MMAP_FLAGS: i32 = libc::MAP_PRIVATE | libc::MAP_ANON;
     pub const PAGE_SIZE: usize = 4096;
 
+    /// Builds a new `Mmap` that can hold at least `len` bytes.
+    ///
+    /// By default, this is a single writable and executable mapping.  Hardened
+    /// systems (e.g. SELinux without `execmem`, or systemd's
+    /// `MemoryDenyWriteExecute`) forbid such mappings; if the kernel refuses
+    /// one, we switch to dual-mapping for the rest of the process.
+    ///
+    /// If `len == 0`, this will return an `Mmap` of size `PAGE_SIZE`; for a
+    /// empty `Mmap` (which makes no system calls), use `Mmap::empty` instead.
+    pub fn new(len: usize) -> Result<Self, std::io::Error> {
+        use std::sync::atomic::Ordering;
+        let len = len.max(1).next_multiple_of(Self::PAGE_SIZE);
+        if !DUAL_MAP.load(Ordering::Relaxed) {
+            let ptr = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    Self::MMAP_PROT,
+                    Self::MMAP_FLAGS,
+                    -1,
+                    0,
+                )
+            };
+            if ptr != libc::MAP_FAILED {
+                return Ok(Self {
+                    ptr,
+                    exec: ptr,
+                    len,
+                    code_len: 0,
+                });
+            }
+            let err = std::io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EACCES | libc::EPERM) => {
+                    DUAL_MAP.store(true, Ordering::Relaxed)
+                }
+                _ => return Err(err),
+            }
+        }
+        Self::new_dual(len)
+    }
+
+    /// Builds a new `Mmap` with separate writable and executable views
+    ///
+    /// Both views map the same anonymous file (from `memfd_create`), so no
+    /// page is ever writable and executable at the same time.  Code is written
+    /// through the writable view, and run from the executable view (returned
+    /// by [`Mmap::as_ptr`]).
+    pub fn new_dual(len: usize) -> Result<Self, std::io::Error> {
+        let len = len.max(1).next_multiple_of(Self::PAGE_SIZE);
+        let fd = unsafe {
+            libc::memfd_create(c"fidget-jit".as_ptr(), libc::MFD_CLOEXEC)
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let map = |prot| unsafe {
+            libc::mmap(std::ptr::null_mut(), len, prot, libc::MAP_SHARED, fd, 0)
+        };
+        let out = if unsafe { libc::ftruncate(fd, len as libc::off_t) } != 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            let ptr = map(libc::PROT_READ | libc::PROT_WRITE);
+            if ptr == libc::MAP_FAILED {
+                Err(std::io::Error::last_os_error())
+            } else {
+                let exec = map(libc::PROT_READ | libc::PROT_EXEC);
+                if exec == libc::MAP_FAILED {
+                    let err = std::io::Error::last_os_error();
+                    unsafe { libc::munmap(ptr, len) };
+                    Err(err)
+                } else {
+                    Ok(Self {
+                        ptr,
+                        exec,
+                        len,
+                        code_len: 0,
+                    })
+                }
+            }
+        };
+        // The mappings keep the file alive, so we can close it immediately
+        unsafe { libc::close(fd) };
+        out
+    }
+
     /// Flushes caches in preparation for evaluation
     ///
     /// The former is a no-op on systems with coherent D/I-caches (i.e. x86).
-    pub fn finalize(&self, size: usize) {
+    pub fn finalize(&mut self, size: usize) {
+        self.code_len = size;
         self.flush_cache(size);
     }
 }
@@ -212,8 +343,18 @@ impl Mmap {
 impl Mmap {
     pub const PAGE_SIZE: usize = 4096;
 
-    pub fn finalize(&self, size: usize) {
-        self.flush_cache(size);
+    /// Flushes caches in preparation for evaluation
+    ///
+    /// On `aarch64`, Windows doesn't guarantee that cache maintenance
+    /// instructions (or reads of `ctr_el0`) are allowed in user mode, so we
+    /// use the system call instead; it's cheap on systems with coherent
+    /// D/I-caches (i.e. x86).
+    pub fn finalize(&mut self, size: usize) {
+        self.code_len = size;
+        unsafe {
+            FlushInstructionCache(GetCurrentProcess(), Some(self.ptr), size)
+                .expect("failed to flush instruction cache");
+        }
     }
 }
 
@@ -224,6 +365,12 @@ impl Drop for Mmap {
             unsafe {
                 libc::munmap(self.ptr, self.len as libc::size_t);
             }
+            #[cfg(target_os = "linux")]
+            if self.exec != self.ptr {
+                unsafe {
+                    libc::munmap(self.exec, self.len as libc::size_t);
+                }
+            }
         }
     }
 }
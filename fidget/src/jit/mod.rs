@@ -22,17 +22,30 @@
 //! assert_eq!(r, 0.1 + 0.3);
 //! # Ok::<(), fidget::Error>(())
 //! ```
+//!
+//! Each tape owns a region of executable memory.  Allocating that memory is a
+//! system call, so it's recycled through [`Shape::TapeStorage`]: a tape's
+//! memory is returned by [`Tape::recycle`] and passed to the next tape (e.g.
+//! [`Shape::point_tape`]), which reuses it unless the new function is much
+//! larger.  The renderers and mesher keep a per-thread list of recycled
+//! storage, so steady-state rendering rarely allocates.
+//!
+//! With the `jit-validate` feature enabled, every JIT evaluation is also
+//! checked against the VM (interpreting the same planned tape), and a
+//! mismatch panics with a dump of the inputs, both results, and the tape.
+//! This is much slower than normal evaluation; it's meant for developing the
+//! JIT backends and for tracking down miscompilations in bug reports.
 
 use crate::{
-    compiler::RegOp,
+    compiler::{Backend, Flavor, RegOp, SsaTape, TapeMetrics},
     context::{Context, Node},
     eval::{
         BulkEvaluator, MathShape, Shape, Tape, TracingEvaluator,
-        TransformedShape,
+        TransformedShape, Vars,
     },
     jit::mmap::Mmap,
     types::{Grad, Interval},
-    vm::{Choice, GenericVmShape, VmData, VmTrace, VmWorkspace},
+    vm::{Choice, GenericVmShape, SsaShape, VmData, VmTrace, VmWorkspace},
     Error,
 };
 use dynasmrt::{
@@ -40,8 +53,13 @@ use dynasmrt::{
     DynasmError, DynasmLabelApi, TargetKind,
 };
 use nalgebra::Matrix4;
+use std::sync::Arc;
 
 mod mmap;
+mod perf;
+
+#[cfg(feature = "jit-validate")]
+mod validate;
 
 // Evaluators
 mod float_slice;
@@ -59,6 +77,12 @@ compile_error!(
     please disable the `jit` feature"
 );
 
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+compile_error!(
+    "The `jit` module only builds on `x86_64` and `aarch64`; \
+    please disable the `jit` feature"
+);
+
 #[cfg(target_arch = "aarch64")]
 mod aarch64;
 #[cfg(target_arch = "aarch64")]
@@ -69,6 +93,20 @@ mod x86_64;
 #[cfg(target_arch = "x86_64")]
 use x86_64 as arch;
 
+pub use perf::enable_perf_map;
+
+/// Checks whether JIT evaluation is supported on this CPU
+///
+/// On `x86_64`, the JIT requires AVX2, which is detected at runtime; on
+/// `aarch64`, it's always supported.  If this returns `false`, building a
+/// [`JitShape`] with [`MathShape::new`] returns [`Error::JitUnsupported`],
+/// and building a tape panics.  Use the VM (or
+/// [`DynShape`](crate::dynamic::DynShape), which falls back to the VM
+/// automatically) instead.
+pub fn is_supported() -> bool {
+    arch::is_supported()
+}
+
 /// Number of registers available when executing natively
 const REGISTER_LIMIT: usize = arch::REGISTER_LIMIT;
 
@@ -297,6 +335,14 @@ pub(crate) struct AssemblerData<T> {
     /// don't save them.
     saved_callee_regs: bool,
 
+    /// Immediate values, as `(bits, offset)` pairs
+    ///
+    /// Each offset is the position of a RIP-relative `disp32` field which
+    /// should point to the value in the constant pool; they're patched in
+    /// [`write_constants`](Self::write_constants).
+    #[cfg(target_arch = "x86_64")]
+    constants: Vec<(u32, usize)>,
+
     _p: std::marker::PhantomData<*const T>,
 }
 
@@ -306,6 +352,8 @@ impl<T> AssemblerData<T> {
             ops: MmapAssembler::from(mmap),
             mem_offset: 0,
             saved_callee_regs: false,
+            #[cfg(target_arch = "x86_64")]
+            constants: vec![],
             _p: std::marker::PhantomData,
         }
     }
@@ -317,25 +365,107 @@ impl<T> AssemblerData<T> {
             + stack_size;
 
         // Round up to the nearest multiple of 16 bytes, for alignment
-        self.mem_offset = ((mem + 15) / 16) * 16;
+        self.mem_offset = mem.div_ceil(16) * 16;
         self.push_stack();
     }
 
+    /// Reserves `self.mem_offset` bytes on the stack
+    ///
+    /// Frames larger than a page are reserved one page at a time, touching
+    /// each page as we go, so that we can't skip over the guard page at the
+    /// end of the stack (and because Windows only grows the stack when its
+    /// guard page is touched).
     #[cfg(target_arch = "aarch64")]
     fn push_stack(&mut self) {
-        assert!(self.mem_offset < 4096);
+        for _ in 0..self.mem_offset / Mmap::PAGE_SIZE {
+            dynasm!(self.ops
+                ; sub sp, sp, 1, lsl 12 // 4096 bytes
+                ; str xzr, [sp]
+            );
+        }
         dynasm!(self.ops
-            ; sub sp, sp, self.mem_offset as u32
+            ; sub sp, sp, (self.mem_offset % Mmap::PAGE_SIZE) as u32
         );
     }
 
+    /// Reserves `self.mem_offset` bytes on the stack, probing each page
     #[cfg(target_arch = "x86_64")]
     fn push_stack(&mut self) {
+        for _ in 0..self.mem_offset / Mmap::PAGE_SIZE {
+            dynasm!(self.ops
+                ; sub rsp, Mmap::PAGE_SIZE as i32
+                ; mov QWORD [rsp], 0
+            );
+        }
+        dynasm!(self.ops
+            ; sub rsp, (self.mem_offset % Mmap::PAGE_SIZE) as i32
+        );
+    }
+
+    /// Releases the stack space reserved by [`push_stack`](Self::push_stack)
+    #[cfg(target_arch = "aarch64")]
+    fn pop_stack(&mut self) {
+        if self.mem_offset < Mmap::PAGE_SIZE {
+            dynasm!(self.ops
+                ; add sp, sp, self.mem_offset as u32
+            );
+        } else {
+            self.load_offset(self.mem_offset as u32);
+            dynasm!(self.ops
+                ; add sp, sp, x16, uxtx
+            );
+        }
+    }
+
+    /// Loads a stack offset into `x16`
+    ///
+    /// This is used to reach spill slots which are out of range for an
+    /// immediate offset.
+    #[cfg(target_arch = "aarch64")]
+    fn load_offset(&mut self, offset: u32) {
         dynasm!(self.ops
-            ; sub rsp, self.mem_offset as i32
+            ; movz w16, offset >> 16, lsl 16
+            ; movk w16, offset & 0xFFFF
         );
     }
 
+    /// Records that the instruction just emitted loads the given immediate
+    ///
+    /// The instruction must use RIP-relative addressing, and its displacement
+    /// must be the final field in the instruction.
+    #[cfg(target_arch = "x86_64")]
+    fn record_constant(&mut self, imm: f32) {
+        self.constants
+            .push((imm.to_bits(), self.ops.offset().0 - 4));
+    }
+
+    /// Writes the constant pool, patching every instruction that refers to it
+    ///
+    /// Each unique value is only written once, no matter how many times it's
+    /// used.  This must be called after the function's final `ret`.
+    #[cfg(target_arch = "x86_64")]
+    fn write_constants(&mut self) {
+        let mut constants = std::mem::take(&mut self.constants);
+        constants.sort_unstable();
+        self.ops.align(4, 0xCC); // int3
+        let mut prev = None;
+        for (bits, disp) in constants {
+            let pos = match prev {
+                Some((b, pos)) if b == bits => pos,
+                _ => {
+                    let pos = self.ops.offset().0;
+                    self.ops.push_u32(bits);
+                    prev = Some((bits, pos));
+                    pos
+                }
+            };
+            // RIP-relative addresses are relative to the end of the
+            // instruction, which is the end of the displacement field.
+            let rel = pos as i64 - (disp as i64 + 4);
+            self.ops.patch_u32(disp, i32::try_from(rel).unwrap() as u32);
+        }
+    }
+
     fn stack_pos(&self, slot: u32) -> u32 {
         assert!(slot >= REGISTER_LIMIT as u32);
         (slot - REGISTER_LIMIT as u32) * std::mem::size_of::<T>() as u32
@@ -619,6 +749,14 @@ impl MmapAssembler {
         Ok(self.mmap)
     }
 
+    /// Overwrites a previously written `u32`
+    #[cfg(target_arch = "x86_64")]
+    fn patch_u32(&mut self, offset: usize, value: u32) {
+        for (i, b) in value.to_le_bytes().iter().enumerate() {
+            self.mmap.write(offset + i, *b);
+        }
+    }
+
     /// Doubles the size of the internal `Mmap` and copies over data
     fn expand_mmap(&mut self) {
         let mut next = Mmap::new(self.mmap.len() * 2).unwrap();
@@ -827,37 +965,121 @@ fn build_asm_fn_with_storage<A: Assembler>(
 }
 
 /// Shape for use with a JIT evaluator
+///
+/// JIT functions read their inputs from the `X`, `Y`, and `Z` slots, so there's
+/// no equivalent of
+/// [`VmShape::new_with_inputs`](crate::vm::GenericVmShape::new_with_inputs);
+/// use a [`VmShape`](crate::vm::VmShape) for tapes with other inputs.
+/// Converting such a tape with [`SsaShape::convert`] returns
+/// [`Error::JitInputs`].
+///
+/// A `JitShape` always compiles its tapes, no matter how long they are.  To
+/// interpret large tapes instead of compiling them, wrap the shape in a
+/// [`DynShape`](crate::dynamic::DynShape) and set a budget with
+/// [`DynShape::with_compile_budget`](crate::dynamic::DynShape::with_compile_budget).
 #[derive(Clone)]
 pub struct JitShape(GenericVmShape<REGISTER_LIMIT>);
 
+impl JitShape {
+    /// Estimates the cost of evaluating this tape with the JIT
+    ///
+    /// See [`RegTape::estimated_cost`](crate::compiler::RegTape::estimated_cost)
+    /// for details.
+    pub fn estimated_cost(&self, flavor: Flavor) -> f64 {
+        self.0.data().estimated_cost(flavor, Backend::Jit)
+    }
+
+    /// Computes summary metrics for this tape
+    ///
+    /// The tape is planned with the JIT's register limit (which depends on
+    /// the target architecture); see [`RegTape::metrics`](crate::compiler::RegTape::metrics) for details.
+    pub fn metrics(&self) -> TapeMetrics {
+        self.0.data().metrics()
+    }
+
+    /// Borrows the inner VM shape, which is planned with the JIT's register
+    /// limit
+    ///
+    /// This can be evaluated by the interpreter without re-planning the tape.
+    pub(crate) fn vm(&self) -> &JitVmShape {
+        &self.0
+    }
+}
+
+/// VM shape planned with the JIT's register limit
+pub(crate) type JitVmShape = GenericVmShape<REGISTER_LIMIT>;
+
+impl SsaShape for JitShape {
+    fn ssa(&self) -> &SsaTape {
+        self.0.ssa()
+    }
+    fn from_ssa(ssa: SsaTape) -> Result<Self, Error> {
+        if !is_supported() {
+            return Err(Error::JitUnsupported);
+        }
+        // JIT functions only read the X, Y, and Z slots
+        if ssa.inputs.is_some() {
+            return Err(Error::JitInputs);
+        }
+        GenericVmShape::from_ssa(ssa).map(JitShape)
+    }
+}
+
 impl JitShape {
     fn tracing_tape<A: Assembler>(
         &self,
         storage: Mmap,
+        flavor: Flavor,
     ) -> JitTracingFn<A::Data> {
+        assert!(is_supported(), "JIT is not supported on this CPU");
         let f = build_asm_fn_with_storage::<A>(self.0.data(), storage);
+        perf::record(flavor, &self.0, f.code());
         let ptr = f.as_ptr();
         JitTracingFn {
             mmap: f,
             var_count: self.0.var_count(),
+            vars: self.0.data().vars(),
             choice_count: self.0.choice_count(),
-            fn_trace: unsafe { std::mem::transmute(ptr) },
+            #[cfg(feature = "stats")]
+            tape_len: self.0.size(),
+            #[cfg(feature = "jit-validate")]
+            vm: self.0.clone(),
+            fn_trace: unsafe {
+                std::mem::transmute::<*mut libc::c_void, TracingFnPtr<A::Data>>(
+                    ptr,
+                )
+            },
         }
     }
-    fn bulk_tape<A: Assembler>(&self, storage: Mmap) -> JitBulkFn<A::Data> {
+    fn bulk_tape<A: Assembler>(
+        &self,
+        storage: Mmap,
+        flavor: Flavor,
+    ) -> JitBulkFn<A::Data> {
+        assert!(is_supported(), "JIT is not supported on this CPU");
         let f = build_asm_fn_with_storage::<A>(self.0.data(), storage);
+        perf::record(flavor, &self.0, f.code());
         let ptr = f.as_ptr();
         JitBulkFn {
             mmap: f,
             var_count: self.0.data().var_count(),
-            fn_bulk: unsafe { std::mem::transmute(ptr) },
+            vars: self.0.data().vars(),
+            #[cfg(feature = "stats")]
+            tape_len: self.0.size(),
+            #[cfg(feature = "jit-validate")]
+            vm: self.0.clone(),
+            fn_bulk: unsafe {
+                std::mem::transmute::<*mut libc::c_void, BulkFnPtr<A::Data>>(
+                    ptr,
+                )
+            },
         }
     }
 }
 
 impl Shape for JitShape {
     type Trace = VmTrace;
-    type Storage = VmData<REGISTER_LIMIT>;
+    type Storage = Arc<VmData<REGISTER_LIMIT>>;
     type Workspace = VmWorkspace<REGISTER_LIMIT>;
 
     type TapeStorage = Mmap;
@@ -868,19 +1090,28 @@ impl Shape for JitShape {
     type GradSliceEval = JitGradSliceEval;
 
     fn point_tape(&self, storage: Mmap) -> JitTracingFn<f32> {
-        self.tracing_tape::<point::PointAssembler>(storage)
+        self.tracing_tape::<point::PointAssembler>(storage, Flavor::Point)
     }
 
     fn interval_tape(&self, storage: Mmap) -> JitTracingFn<Interval> {
-        self.tracing_tape::<interval::IntervalAssembler>(storage)
+        self.tracing_tape::<interval::IntervalAssembler>(
+            storage,
+            Flavor::Interval,
+        )
     }
 
     fn float_slice_tape(&self, storage: Mmap) -> JitBulkFn<f32> {
-        self.bulk_tape::<float_slice::FloatSliceAssembler>(storage)
+        self.bulk_tape::<float_slice::FloatSliceAssembler>(
+            storage,
+            Flavor::FloatSlice,
+        )
     }
 
     fn grad_slice_tape(&self, storage: Mmap) -> JitBulkFn<Grad> {
-        self.bulk_tape::<grad_slice::GradSliceAssembler>(storage)
+        self.bulk_tape::<grad_slice::GradSliceAssembler>(
+            storage,
+            Flavor::GradSlice,
+        )
     }
 
     fn simplify(
@@ -890,7 +1121,7 @@ impl Shape for JitShape {
         workspace: &mut Self::Workspace,
     ) -> Result<Self, Error> {
         self.0
-            .simplify_inner(trace.as_slice(), storage, workspace)
+            .simplify_inner(trace.choices(), storage, workspace)
             .map(JitShape)
     }
 
@@ -911,10 +1142,22 @@ impl Shape for JitShape {
         d % 8 == 4
     }
 
+    fn plan_2d(&self, z: f32) -> Option<Self> {
+        self.0.plan_2d(z).map(JitShape)
+    }
+
+    fn specialize(&self, var: &str, value: f32) -> Option<Self> {
+        self.0.specialize(var, value).map(JitShape)
+    }
+
     fn size(&self) -> usize {
         self.0.size()
     }
 
+    fn choice_count(&self) -> Option<usize> {
+        Some(self.0.choice_count())
+    }
+
     type TransformedShape = TransformedShape<Self>;
     fn apply_transform(self, mat: Matrix4<f32>) -> Self::TransformedShape {
         TransformedShape::new(self, mat)
@@ -948,28 +1191,77 @@ macro_rules! jit_fn {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Function pointer type stored in a [`JitTracingFn`]
+type TracingFnPtr<T> = jit_fn!(
+    unsafe fn(
+        *const T, // vars
+        *mut u8,  // choices
+        *mut u8,  // simplify (single boolean)
+    ) -> T
+);
+
+/// Function pointer type stored in a [`JitBulkFn`]
+type BulkFnPtr<T> = jit_fn!(
+    unsafe fn(
+        *const *const f32, // vars
+        *mut T,            // out
+        u64,               // size
+    ) -> T
+);
+
+////////////////////////////////////////////////////////////////////////////////
+
 /// Evaluator for a JIT-compiled tracing function
 ///
 /// Users are unlikely to use this directly, but it's public because it's an
 /// associated type on [`JitShape`].
 #[derive(Default)]
 struct JitTracingEval {
+    /// Unpacked choices, written by the JIT function
+    scratch: Vec<Choice>,
+    /// Packed trace, populated from `scratch` when simplification is possible
     choices: VmTrace,
 }
 
 /// Handle to an owned function pointer for tracing evaluation
 pub struct JitTracingFn<T> {
-    #[allow(unused)]
     mmap: Mmap,
     choice_count: usize,
     var_count: usize,
-    fn_trace: jit_fn!(
-        unsafe fn(
-            *const T, // vars
-            *mut u8,  // choices
-            *mut u8,  // simplify (single boolean)
-        ) -> T
-    ),
+    vars: Vars,
+    /// Length of the source tape, used for evaluation statistics
+    #[cfg(feature = "stats")]
+    tape_len: usize,
+    /// Planned tape, used to check results against the VM
+    #[cfg(feature = "jit-validate")]
+    vm: JitVmShape,
+    fn_trace: TracingFnPtr<T>,
+}
+
+impl<T> JitTracingFn<T> {
+    /// Returns the generated machine code
+    ///
+    /// This is useful for inspecting code generation (e.g. when diagnosing a
+    /// performance regression).  Fidget doesn't include a disassembler, but the
+    /// bytes can be written to a file and disassembled with external tools,
+    /// e.g. `objdump -D -b binary -m i386:x86-64 code.bin` on `x86_64` or
+    /// `objdump -D -b binary -m aarch64 code.bin` on `aarch64`.
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::{EzShape, MathShape},
+    ///     jit::JitShape,
+    /// };
+    ///
+    /// let shape = JitShape::from_tree(&(Tree::x() + Tree::y()));
+    /// let tape = shape.ez_point_tape();
+    /// assert!(!tape.code().is_empty());
+    /// // std::fs::write("code.bin", tape.code())?;
+    /// ```
+    pub fn code(&self) -> &[u8] {
+        self.mmap.code()
+    }
 }
 
 impl<T> Tape for JitTracingFn<T> {
@@ -977,6 +1269,9 @@ impl<T> Tape for JitTracingFn<T> {
     fn recycle(self) -> Self::Storage {
         self.mmap
     }
+    fn vars(&self) -> Vars {
+        self.vars.clone()
+    }
 }
 
 // SAFETY: there is no mutable state in a `JitTracingFn`, and the pointer
@@ -997,20 +1292,21 @@ impl JitTracingEval {
         let y = y.into();
         let z = z.into();
         let mut simplify = 0;
-        self.choices.resize(tape.choice_count, Choice::Unknown);
+        self.scratch.resize(tape.choice_count, Choice::Unknown);
         assert!(tape.var_count <= 3);
-        self.choices.fill(Choice::Unknown);
+        self.scratch.fill(Choice::Unknown);
         let vars = [x, y, z];
         let out = unsafe {
             (tape.fn_trace)(
                 vars.as_ptr(),
-                self.choices.as_mut_ptr() as *mut u8,
+                self.scratch.as_mut_ptr() as *mut u8,
                 &mut simplify,
             )
         };
         (
             out,
             if simplify != 0 {
+                self.choices.choices_mut().copy_from_slice(&self.scratch);
                 Some(&self.choices)
             } else {
                 None
@@ -1035,7 +1331,13 @@ impl TracingEvaluator for JitIntervalEval {
         y: F,
         z: F,
     ) -> Result<(Self::Data, Option<&Self::Trace>), Error> {
-        Ok(self.0.eval(tape, x, y, z))
+        #[cfg(feature = "stats")]
+        crate::stats::record_eval(Flavor::Interval, tape.tape_len, 1);
+        let vars: [Interval; 3] = [x.into(), y.into(), z.into()];
+        let (out, trace) = self.0.eval(tape, vars[0], vars[1], vars[2]);
+        #[cfg(feature = "jit-validate")]
+        validate::check_interval(&tape.vm, vars, out);
+        Ok((out, trace))
     }
 }
 
@@ -1055,7 +1357,13 @@ impl TracingEvaluator for JitPointEval {
         y: F,
         z: F,
     ) -> Result<(Self::Data, Option<&Self::Trace>), Error> {
-        Ok(self.0.eval(tape, x, y, z))
+        #[cfg(feature = "stats")]
+        crate::stats::record_eval(Flavor::Point, tape.tape_len, 1);
+        let vars: [f32; 3] = [x.into(), y.into(), z.into()];
+        let (out, trace) = self.0.eval(tape, vars[0], vars[1], vars[2]);
+        #[cfg(feature = "jit-validate")]
+        validate::check_point(&tape.vm, vars, out);
+        Ok((out, trace))
     }
 }
 
@@ -1063,16 +1371,25 @@ impl TracingEvaluator for JitPointEval {
 
 /// Handle to an owned function pointer for bulk evaluation
 pub struct JitBulkFn<T> {
-    #[allow(unused)]
     mmap: Mmap,
     var_count: usize,
-    fn_bulk: jit_fn!(
-        unsafe fn(
-            *const *const f32, // vars
-            *mut T,            // out
-            u64,               // size
-        ) -> T
-    ),
+    vars: Vars,
+    /// Length of the source tape, used for evaluation statistics
+    #[cfg(feature = "stats")]
+    tape_len: usize,
+    /// Planned tape, used to check results against the VM
+    #[cfg(feature = "jit-validate")]
+    vm: JitVmShape,
+    fn_bulk: BulkFnPtr<T>,
+}
+
+impl<T> JitBulkFn<T> {
+    /// Returns the generated machine code
+    ///
+    /// See [`JitTracingFn::code`] for details.
+    pub fn code(&self) -> &[u8] {
+        self.mmap.code()
+    }
 }
 
 impl<T> Tape for JitBulkFn<T> {
@@ -1080,6 +1397,9 @@ impl<T> Tape for JitBulkFn<T> {
     fn recycle(self) -> Self::Storage {
         self.mmap
     }
+    fn vars(&self) -> Vars {
+        self.vars.clone()
+    }
 }
 
 /// Bulk evaluator for JIT functions
@@ -1099,7 +1419,9 @@ impl<T> Default for JitBulkEval<T> {
 unsafe impl<T> Send for JitBulkFn<T> {}
 unsafe impl<T> Sync for JitBulkFn<T> {}
 
-impl<T: From<f32> + Copy + SimdSize> JitBulkEval<T> {
+impl<T: From<f32> + Copy + SimdSize + PartialEq + std::fmt::Debug>
+    JitBulkEval<T>
+{
     /// Evaluate multiple points
     fn eval(
         &mut self,
@@ -1112,6 +1434,9 @@ impl<T: From<f32> + Copy + SimdSize> JitBulkEval<T> {
         let n = xs.len();
         self.out.resize(n, f32::NAN.into());
         self.out.fill(f32::NAN.into());
+        #[cfg(feature = "jit-validate")]
+        self.out
+            .extend([T::from(validate::SENTINEL); validate::GUARD]);
 
         // Special case for when we have fewer items than the native SIMD size,
         // in which case the input slices can't be used as workspace (because
@@ -1140,7 +1465,7 @@ impl<T: From<f32> + Copy + SimdSize> JitBulkEval<T> {
                     T::SIMD_SIZE as u64,
                 );
             }
-            self.out.copy_from_slice(&tmp[0..n]);
+            self.out[0..n].copy_from_slice(&tmp[0..n]);
         } else {
             // Our vectorized function only accepts sets of a particular width,
             // so we'll find the biggest multiple, then do an extra operation to
@@ -1168,6 +1493,8 @@ impl<T: From<f32> + Copy + SimdSize> JitBulkEval<T> {
                 }
             }
         }
+        #[cfg(feature = "jit-validate")]
+        validate::check_guard(&tape.vm, &mut self.out, n);
         &self.out
     }
 }
@@ -1188,7 +1515,12 @@ impl BulkEvaluator for JitFloatSliceEval {
         zs: &[f32],
     ) -> Result<&[Self::Data], Error> {
         self.check_arguments(xs, ys, zs, tape.var_count)?;
-        Ok(self.0.eval(tape, xs, ys, zs))
+        #[cfg(feature = "stats")]
+        crate::stats::record_eval(Flavor::FloatSlice, tape.tape_len, xs.len());
+        let out = self.0.eval(tape, xs, ys, zs);
+        #[cfg(feature = "jit-validate")]
+        validate::check_float_slice(&tape.vm, xs, ys, zs, out);
+        Ok(out)
     }
 }
 
@@ -1208,12 +1540,20 @@ impl BulkEvaluator for JitGradSliceEval {
         zs: &[f32],
     ) -> Result<&[Self::Data], Error> {
         self.check_arguments(xs, ys, zs, tape.var_count)?;
-        Ok(self.0.eval(tape, xs, ys, zs))
+        #[cfg(feature = "stats")]
+        crate::stats::record_eval(Flavor::GradSlice, tape.tape_len, xs.len());
+        let out = self.0.eval(tape, xs, ys, zs);
+        #[cfg(feature = "jit-validate")]
+        validate::check_grad_slice(&tape.vm, xs, ys, zs, out);
+        Ok(out)
     }
 }
 
 impl MathShape for JitShape {
     fn new(ctx: &Context, node: Node) -> Result<Self, Error> {
+        if !is_supported() {
+            return Err(Error::JitUnsupported);
+        }
         GenericVmShape::new(ctx, node).map(JitShape)
     }
 }
@@ -1223,8 +1563,157 @@ impl MathShape for JitShape {
 #[cfg(test)]
 mod test {
     use super::*;
-    crate::grad_slice_tests!(JitShape);
-    crate::interval_tests!(JitShape);
-    crate::float_slice_tests!(JitShape);
-    crate::point_tests!(JitShape);
+    crate::conformance_tests!(JitShape);
+
+    #[test]
+    fn test_is_supported() {
+        // The default backend is only the JIT if this CPU supports it
+        assert_eq!(Backend::default() == Backend::Jit, is_supported());
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let r = JitShape::new(&ctx, x);
+        assert_eq!(r.is_ok(), is_supported());
+        if !is_supported() {
+            assert!(matches!(r, Err(Error::JitUnsupported)));
+        }
+    }
+
+    #[test]
+    fn test_tape_storage_reuse() {
+        use crate::context::Tree;
+        let (x, y, z) = Tree::axes();
+        let shape = JitShape::from_tree(&x.min(y).max(z));
+        let tape = shape.point_tape(Default::default());
+        let ptr = tape.mmap.as_ptr();
+
+        // A tape built from recycled storage reuses the same mapping
+        let tape = shape.float_slice_tape(tape.recycle());
+        assert_eq!(tape.mmap.as_ptr(), ptr);
+        let tape = shape.point_tape(tape.recycle());
+        assert_eq!(tape.mmap.as_ptr(), ptr);
+        let mut eval = JitShape::new_point_eval();
+        assert_eq!(eval.eval(&tape, 1.0, 2.0, 0.5).unwrap().0, 1.0);
+    }
+
+    #[test]
+    fn test_large_stack_frame() {
+        use crate::{context::Tree, eval::EzShape, vm::VmShape};
+        let (x, y, _z) = Tree::axes();
+
+        // Every term stays live until the second sum, so the tape spills
+        // thousands of values into a stack frame that's many pages long.
+        let terms: Vec<Tree> =
+            (0..2000).map(|i| x.clone() + i as f64).collect();
+        let a = terms.iter().fold(y.clone(), |acc, t| acc + t.clone());
+        let b = terms
+            .iter()
+            .rev()
+            .fold(y.clone(), |acc, t| acc * 0.5 + t.clone() * t.clone());
+        let t = a.min(b);
+        let jit = JitShape::from_tree(&t);
+        let vm = VmShape::from_tree(&t);
+        assert!(jit.0.data().slot_count() > 2000);
+
+        let (x, y) = (0.5, 0.25);
+        let mut eval = JitShape::new_point_eval();
+        let (out, _) = eval.eval(&jit.ez_point_tape(), x, y, 0.0).unwrap();
+        let mut vm_eval = VmShape::new_point_eval();
+        let (expected, _) =
+            vm_eval.eval(&vm.ez_point_tape(), x, y, 0.0).unwrap();
+        assert_eq!(out, expected);
+
+        let mut eval = JitShape::new_interval_eval();
+        let (out, _) = eval
+            .eval(&jit.ez_interval_tape(), [0.0, 1.0], [0.0, 0.5], [0.0, 0.0])
+            .unwrap();
+        let mut vm_eval = VmShape::new_interval_eval();
+        let (expected, _) = vm_eval
+            .eval(&vm.ez_interval_tape(), [0.0, 1.0], [0.0, 0.5], [0.0, 0.0])
+            .unwrap();
+        assert_eq!(out, expected);
+
+        let xs = [0.0, 0.5, 1.0, -1.5, 2.0];
+        let ys = [0.25, 0.125, -3.0, 1.0, 0.0];
+        let zs = [0.0; 5];
+        let mut eval = JitShape::new_float_slice_eval();
+        let out = eval
+            .eval(&jit.ez_float_slice_tape(), &xs, &ys, &zs)
+            .unwrap();
+        let mut vm_eval = VmShape::new_float_slice_eval();
+        let expected = vm_eval
+            .eval(&vm.ez_float_slice_tape(), &xs, &ys, &zs)
+            .unwrap();
+        assert_eq!(out, expected);
+
+        let mut eval = JitShape::new_grad_slice_eval();
+        let out = eval.eval(&jit.ez_grad_slice_tape(), &xs, &ys, &zs).unwrap();
+        let mut vm_eval = VmShape::new_grad_slice_eval();
+        let expected = vm_eval
+            .eval(&vm.ez_grad_slice_tape(), &xs, &ys, &zs)
+            .unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_constant_pool() {
+        use crate::context::Tree;
+        let (x, _y, _z) = Tree::axes();
+        let t = (0..100)
+            .fold(x.clone(), |acc, i| acc.max((x.clone() + i as f64) * 2.5));
+        let shape = JitShape::from_tree(&t);
+        let needle = 2.5f32.to_le_bytes();
+        let count =
+            |code: &[u8]| code.windows(4).filter(|w| *w == needle).count();
+
+        // The constant is used 100 times, but only stored once
+        let tape = shape.interval_tape(Default::default());
+        assert_eq!(count(tape.code()), 1);
+        let mut eval = JitShape::new_interval_eval();
+        let (out, _) = eval
+            .eval(&tape, [1.0, 1.0], [0.0, 0.0], [0.0, 0.0])
+            .unwrap();
+        assert_eq!(out, 250.0.into());
+
+        let tape = shape.float_slice_tape(tape.recycle());
+        assert_eq!(count(tape.code()), 1);
+        let mut eval = JitShape::new_float_slice_eval();
+        let out = eval.eval(&tape, &[1.0; 9], &[0.0; 9], &[0.0; 9]).unwrap();
+        assert_eq!(out, [250.0; 9]);
+    }
+
+    #[test]
+    fn test_code() {
+        use crate::context::Tree;
+        let (x, y, z) = Tree::axes();
+        let shape = JitShape::from_tree(&x.min(y).max(z));
+        let tape = shape.point_tape(Default::default());
+        let code = tape.code();
+        assert!(!code.is_empty());
+        assert!(code.len() < tape.mmap.len());
+
+        let tape = shape.grad_slice_tape(tape.recycle());
+        assert!(!tape.code().is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_dual_map() {
+        use crate::context::Tree;
+        let (x, y, _z) = Tree::axes();
+        let shape = JitShape::from_tree(&(x.square() + y.min(1.0)));
+
+        // Storage is big enough that the tape doesn't need to reallocate it
+        let mmap = Mmap::new_dual(1 << 16).unwrap();
+        let tape = shape.point_tape(mmap);
+        assert_ne!(tape.mmap.as_ptr(), tape.mmap.as_slice().as_ptr() as _);
+        let mut eval = JitShape::new_point_eval();
+        assert_eq!(eval.eval(&tape, 2.0, 3.0, 0.0).unwrap().0, 5.0);
+
+        let mmap = Mmap::new_dual(1 << 16).unwrap();
+        let tape = shape.float_slice_tape(mmap);
+        let mut eval = JitShape::new_float_slice_eval();
+        let out = eval.eval(&tape, &[2.0; 9], &[0.5; 9], &[0.0; 9]).unwrap();
+        assert_eq!(out, [4.5; 9]);
+    }
 }
@@ -0,0 +1,120 @@
+//! Symbol maps for profiling JIT code
+use super::JitVmShape;
+use crate::{compiler::Flavor, Error};
+use std::{
+    fmt::Write as _,
+    fs::File,
+    hash::{DefaultHasher, Hasher},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PERF_MAP: Mutex<Option<File>> = Mutex::new(None);
+
+/// Starts writing a symbol map for JIT functions, returning its path
+///
+/// Once enabled, every JIT function is recorded in `/tmp/perf-<pid>.map`,
+/// which is read by `perf` on Linux and by [`samply`](https://github.com/mstange/samply)
+/// on Linux and macOS.  Functions are named by flavor, a hash of the tape, and
+/// the tape's length (e.g. `fidget FloatSlice 3f2a9c0e51b7d468 (42 clauses)`),
+/// so profiles show which shapes are expensive instead of anonymous regions of
+/// JIT code.  Identical tapes have the same hash within a build of Fidget.
+///
+/// Tape memory is recycled, so a later function may reuse an earlier
+/// function's addresses; profiles are most accurate for long-lived tapes.
+///
+/// This isn't supported on Windows, and returns [`Error::IoError`] there.
+pub fn enable_perf_map() -> Result<PathBuf, Error> {
+    if cfg!(not(unix)) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "perf maps are only supported on Unix platforms",
+        )
+        .into());
+    }
+    let path = PathBuf::from(format!("/tmp/perf-{}.map", std::process::id()));
+    let f = File::options().create(true).append(true).open(&path)?;
+    *PERF_MAP.lock().unwrap() = Some(f);
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(path)
+}
+
+/// Records a JIT function in the symbol map, if enabled
+pub(super) fn record(flavor: Flavor, vm: &JitVmShape, code: &[u8]) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(f) = PERF_MAP.lock().unwrap().as_mut() {
+        let _ = write_entry(f, flavor, vm, code);
+    }
+}
+
+/// Writes a single line of the symbol map
+fn write_entry<W: std::io::Write>(
+    w: &mut W,
+    flavor: Flavor,
+    vm: &JitVmShape,
+    code: &[u8],
+) -> std::io::Result<()> {
+    let mut h = HashWriter(DefaultHasher::new());
+    for op in vm.data().iter_asm() {
+        write!(h, "{op:?};").unwrap();
+    }
+    let hash = h.0.finish();
+    writeln!(
+        w,
+        "{:x} {:x} fidget {flavor:?} {hash:016x} ({} clauses)",
+        code.as_ptr() as usize,
+        code.len(),
+        vm.size(),
+    )
+}
+
+/// Adapter to hash formatted text without allocating
+struct HashWriter(DefaultHasher);
+
+impl std::fmt::Write for HashWriter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::{MathShape, Shape},
+        jit::JitShape,
+    };
+
+    #[test]
+    fn test_perf_map() {
+        // Write to a buffer, rather than enabling the global map (which would
+        // record every tape built by other tests)
+        let shape = JitShape::from_tree(&(Tree::x() * 2.0 + Tree::y()));
+        let tape = shape.float_slice_tape(Default::default());
+        let mut out = vec![];
+        write_entry(&mut out, Flavor::FloatSlice, shape.vm(), tape.code())
+            .unwrap();
+        let line = String::from_utf8(out).unwrap();
+
+        let addr = tape.code().as_ptr() as usize;
+        let len = tape.code().len();
+        assert!(
+            line.starts_with(&format!("{addr:x} {len:x} fidget FloatSlice "))
+        );
+        assert!(line.ends_with(&format!("({} clauses)\n", shape.size())));
+
+        // Identical tapes have identical names
+        let mut again = vec![];
+        write_entry(&mut again, Flavor::FloatSlice, shape.vm(), tape.code())
+            .unwrap();
+        assert_eq!(line.as_bytes(), again);
+    }
+}
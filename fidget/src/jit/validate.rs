@@ -0,0 +1,280 @@
+//! Cross-checking JIT evaluation against the VM
+//!
+//! This module is only built with the `jit-validate` feature.  Every JIT tape
+//! keeps a handle to the planned VM tape that it was built from, and every
+//! evaluator call is checked by interpreting that tape:
+//!
+//! - Tracing evaluation (points and intervals) is checked on every call
+//! - Bulk evaluation is checked at up to [`SAMPLES`] points per call,
+//!   including the last point (which may be handled by the ragged tail path)
+//! - Bulk output buffers are followed by [`GUARD`] sentinel values, which are
+//!   checked after every call to catch writes past the end of the output
+//!
+//! Scratch memory (register spills) lives on the stack, which is probed page
+//! by page when the function is entered, so an out-of-bounds spill hits the
+//! operating system's guard page rather than silently corrupting memory.
+//!
+//! Results are compared to within rounding error.  A handful of cases are
+//! legitimately backend-dependent and aren't reported:
+//!
+//! - Partial derivatives, when the value is `NaN`
+//! - Any point where the VM sees a tie in a `min` / `max` clause (where either
+//!   branch's derivative is valid) or a modulo result on the edge of its
+//!   range (where rounding can wrap the result to either end)
+//! - Intervals with `NaN` inputs
+//!
+//! On a mismatch, this panics with a diagnostic dump: the inputs, both
+//! results, and the planned tape (with the VM's value for every clause, when
+//! evaluating at a single point).
+use super::JitVmShape;
+use crate::{
+    compiler::{Flavor, RegOp},
+    eval::{BulkEvaluator, Shape, TracingEvaluator},
+    types::{Grad, Interval},
+    vm::{Choice, VmDebugger},
+};
+use std::fmt::{Debug, Write};
+
+/// Maximum number of points checked per bulk evaluation
+pub(super) const SAMPLES: usize = 16;
+
+/// Number of sentinel values written after each bulk output buffer
+pub(super) const GUARD: usize = 8;
+
+/// Sentinel value stored in guard slots
+///
+/// This is an arbitrary finite value which is unlikely to be a real result.
+pub(super) const SENTINEL: f32 = -1.2345679e-21;
+
+/// Comparison between JIT and VM results
+pub(super) trait Check: Copy + Debug {
+    /// Checks whether two results are equal (to within rounding)
+    fn matches(&self, other: &Self) -> bool;
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a.is_nan() && b.is_nan())
+        || a == b
+        || (a - b).abs() <= 1e-6
+        || (a - b).abs() <= 1e-5 * a.abs().max(b.abs())
+}
+
+impl Check for f32 {
+    fn matches(&self, other: &Self) -> bool {
+        close(*self, *other)
+    }
+}
+
+impl Check for Interval {
+    fn matches(&self, other: &Self) -> bool {
+        close(self.lower(), other.lower()) && close(self.upper(), other.upper())
+    }
+}
+
+impl Check for Grad {
+    fn matches(&self, other: &Self) -> bool {
+        (self.v.is_nan() && other.v.is_nan())
+            || (close(self.v, other.v)
+                && close(self.dx, other.dx)
+                && close(self.dy, other.dy)
+                && close(self.dz, other.dz))
+    }
+}
+
+/// Checks the result of single-point evaluation
+pub(super) fn check_point(vm: &JitVmShape, vars: [f32; 3], out: f32) {
+    let tape = vm.point_tape(());
+    let mut eval = JitVmShape::new_point_eval();
+    let (expected, _) = eval.eval(&tape, vars[0], vars[1], vars[2]).unwrap();
+    if !out.matches(&expected) && !is_ambiguous(vm, vars) {
+        fail(vm, Flavor::Point, &vars, out, expected, Some(vars));
+    }
+}
+
+/// Checks the result of single-interval evaluation
+pub(super) fn check_interval(
+    vm: &JitVmShape,
+    vars: [Interval; 3],
+    out: Interval,
+) {
+    let tape = vm.interval_tape(());
+    let mut eval = JitVmShape::new_interval_eval();
+    let (expected, _) = eval.eval(&tape, vars[0], vars[1], vars[2]).unwrap();
+    let has_nan = vars
+        .iter()
+        .any(|v| v.lower().is_nan() || v.upper().is_nan());
+    if !out.matches(&expected) && !has_nan {
+        fail(vm, Flavor::Interval, &vars, out, expected, None);
+    }
+}
+
+/// Checks a sample of the results of bulk float evaluation
+pub(super) fn check_float_slice(
+    vm: &JitVmShape,
+    xs: &[f32],
+    ys: &[f32],
+    zs: &[f32],
+    out: &[f32],
+) {
+    let tape = vm.float_slice_tape(());
+    let eval = JitVmShape::new_float_slice_eval();
+    check_bulk(vm, Flavor::FloatSlice, eval, &tape, xs, ys, zs, out);
+}
+
+/// Checks a sample of the results of bulk gradient evaluation
+pub(super) fn check_grad_slice(
+    vm: &JitVmShape,
+    xs: &[f32],
+    ys: &[f32],
+    zs: &[f32],
+    out: &[Grad],
+) {
+    let tape = vm.grad_slice_tape(());
+    let eval = JitVmShape::new_grad_slice_eval();
+    check_bulk(vm, Flavor::GradSlice, eval, &tape, xs, ys, zs, out);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_bulk<E: BulkEvaluator>(
+    vm: &JitVmShape,
+    flavor: Flavor,
+    mut eval: E,
+    tape: &E::Tape,
+    xs: &[f32],
+    ys: &[f32],
+    zs: &[f32],
+    out: &[E::Data],
+) where
+    E::Data: Check,
+{
+    let n = xs.len();
+    let samples: Vec<usize> = (0..n)
+        .step_by(n.div_ceil(SAMPLES).max(1))
+        .chain(n.checked_sub(1))
+        .collect();
+    let pick = |vs: &[f32]| samples.iter().map(|&i| vs[i]).collect::<Vec<_>>();
+    let (sx, sy, sz) = (pick(xs), pick(ys), pick(zs));
+    let expected = eval.eval(tape, &sx, &sy, &sz).unwrap();
+    for (j, &i) in samples.iter().enumerate() {
+        let vars = [sx[j], sy[j], sz[j]];
+        if !out[i].matches(&expected[j]) && !is_ambiguous(vm, vars) {
+            fail(vm, flavor, &vars, out[i], expected[j], Some(vars));
+        }
+    }
+}
+
+/// Checks that a bulk output buffer's guard slots are intact, then removes
+/// them (leaving `n` items in the buffer)
+pub(super) fn check_guard<T: PartialEq + From<f32> + Debug>(
+    vm: &JitVmShape,
+    out: &mut Vec<T>,
+    n: usize,
+) {
+    if out[n..].iter().any(|v| *v != T::from(SENTINEL)) {
+        let mut s = format!(
+            "JIT bulk evaluation wrote past the end of its output \
+             ({n} items)\n  guard: {:?}\n",
+            &out[n..]
+        );
+        dump_tape(&mut s, vm, None);
+        panic!("{s}");
+    }
+    out.truncate(n);
+}
+
+/// Checks whether evaluation at the given point hits a tie or a modulo edge
+///
+/// At such points, results may differ between backends without either one
+/// being wrong.
+fn is_ambiguous(vm: &JitVmShape, point: [f32; 3]) -> bool {
+    let Ok(mut dbg) = VmDebugger::new(vm, &point) else {
+        return false;
+    };
+    while let Some(op) = dbg.next_clause() {
+        let divisor = match op {
+            RegOp::ModRegReg(_, _, d) | RegOp::ModImmReg(_, d, _) => {
+                Some(dbg.registers()[d as usize])
+            }
+            RegOp::ModRegImm(_, _, imm) => Some(imm),
+            _ => None,
+        };
+        dbg.step();
+        if dbg.last_choice() == Some(Choice::Both) {
+            return true;
+        }
+        if let Some(d) = divisor.map(f32::abs) {
+            let r = dbg.output(dbg.pc() - 1).unwrap();
+            if r <= d * 1e-5 || d - r <= d * 1e-5 {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn fail<I: Debug, T: Debug>(
+    vm: &JitVmShape,
+    flavor: Flavor,
+    inputs: &I,
+    jit: T,
+    expected: T,
+    point: Option<[f32; 3]>,
+) -> ! {
+    let mut s = format!(
+        "JIT / VM mismatch in {flavor:?} evaluation\n  \
+         inputs: {inputs:?}\n  JIT:    {jit:?}\n  VM:     {expected:?}\n"
+    );
+    dump_tape(&mut s, vm, point);
+    panic!("{s}");
+}
+
+/// Writes the planned tape to `s`, with the VM's value for every clause if
+/// `point` is provided
+fn dump_tape(s: &mut String, vm: &JitVmShape, point: Option<[f32; 3]>) {
+    let mut dbg = point.and_then(|p| VmDebugger::new(vm, &p).ok());
+    writeln!(s, "tape ({} clauses):", vm.size()).unwrap();
+    for (i, op) in vm.data().iter_asm().enumerate() {
+        write!(s, "  {i:>5}: {op:?}").unwrap();
+        if let Some(dbg) = dbg.as_mut() {
+            dbg.step();
+            write!(s, " = {}", dbg.output(i).unwrap()).unwrap();
+        }
+        writeln!(s).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Tree, eval::MathShape, jit::JitShape};
+
+    #[test]
+    fn test_validate() {
+        let (x, y, z) = Tree::axes();
+        let shape = JitShape::from_tree(&(x.square() + y.sin() - z.max(0.5)));
+        let vm = shape.vm();
+
+        // JIT evaluators are checked internally, so these all pass
+        let tape = shape.float_slice_tape(Default::default());
+        let xs: Vec<f32> = (0..37).map(|i| i as f32 / 10.0).collect();
+        let mut eval = JitShape::new_float_slice_eval();
+        let out = eval.eval(&tape, &xs, &xs, &xs).unwrap().to_vec();
+        check_float_slice(vm, &xs, &xs, &xs, &out);
+
+        // A wrong result is caught, and the dump includes every clause
+        let r = std::panic::catch_unwind(|| {
+            check_point(vm, [1.0, 2.0, 3.0], 0.0);
+        });
+        let msg = r.unwrap_err().downcast::<String>().unwrap();
+        assert!(msg.contains("mismatch in Point"));
+        assert_eq!(msg.matches(" = ").count(), vm.size());
+
+        let mut out = vec![1.0, 2.0, SENTINEL];
+        check_guard(vm, &mut out, 2);
+        assert_eq!(out, [1.0, 2.0]);
+        let r = std::panic::catch_unwind(|| {
+            check_guard(vm, &mut vec![1.0, 2.0, 3.0], 2);
+        });
+        assert!(r.is_err());
+    }
+}
@@ -359,11 +359,12 @@ impl Assembler for FloatSliceAssembler {
         );
     }
     fn load_imm(&mut self, imm: f32) -> u8 {
+        // `dynasm` always encodes `vbroadcastss` from memory as a 128-bit
+        // operation, so use the (equivalent) integer broadcast instead
         dynasm!(self.0.ops
-            ; mov eax, imm.to_bits() as i32
-            ; vmovd Rx(IMM_REG), eax
-            ; vbroadcastss Ry(IMM_REG), Rx(IMM_REG)
+            ; vpbroadcastd Ry(IMM_REG), [rip + 0]
         );
+        self.0.record_constant(imm);
         IMM_REG.wrapping_sub(OFFSET)
     }
     fn finalize(mut self, out_reg: u8) -> Result<Mmap, Error> {
@@ -384,6 +385,7 @@ impl Assembler for FloatSliceAssembler {
             ; ret
         );
 
+        self.0.write_constants();
         self.0.ops.finalize()
     }
 }
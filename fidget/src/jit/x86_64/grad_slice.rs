@@ -468,12 +468,10 @@ impl Assembler for GradSliceAssembler {
         self.0.ops.commit_local().unwrap();
     }
     fn load_imm(&mut self, imm: f32) -> u8 {
-        let imm_u32 = imm.to_bits();
         dynasm!(self.0.ops
-            ; vpxor Rx(IMM_REG), Rx(IMM_REG), Rx(IMM_REG)
-            ; mov eax, imm_u32 as i32
-            ; vmovd Rx(IMM_REG), eax
+            ; vmovss Rx(IMM_REG), [rip + 0]
         );
+        self.0.record_constant(imm);
         IMM_REG.wrapping_sub(OFFSET)
     }
     fn finalize(mut self, out_reg: u8) -> Result<Mmap, Error> {
@@ -493,6 +491,7 @@ impl Assembler for GradSliceAssembler {
             ; ret
         );
 
+        self.0.write_constants();
         self.0.ops.finalize()
     }
 }
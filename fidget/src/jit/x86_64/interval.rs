@@ -746,12 +746,10 @@ impl Assembler for IntervalAssembler {
         self.0.ops.commit_local().unwrap();
     }
     fn load_imm(&mut self, imm: f32) -> u8 {
-        let imm_u32 = imm.to_bits();
         dynasm!(self.0.ops
-            ; mov eax, imm_u32 as i32
-            ; vmovd Rx(IMM_REG), eax
-            ; vbroadcastss Rx(IMM_REG), Rx(IMM_REG)
+            ; vbroadcastss Rx(IMM_REG), [rip + 0]
         );
+        self.0.record_constant(imm);
         IMM_REG.wrapping_sub(OFFSET)
     }
     fn finalize(mut self, out_reg: u8) -> Result<Mmap, Error> {
@@ -769,6 +767,7 @@ impl Assembler for IntervalAssembler {
             ; emms
             ; ret
         );
+        self.0.write_constants();
         self.0.ops.finalize()
     }
 }
@@ -854,6 +853,7 @@ impl IntervalAssembler {
             dynasm!(self.0.ops
                 ; mov [rbp - 0x8], r12
                 ; mov [rbp - 0x10], r13
+                ; mov [rbp - 0x18], r14
             );
             self.0.saved_callee_regs = true
         }
@@ -4,12 +4,29 @@
 //! tape must be planned with a <= 12 register limit; any spills will live on
 //! the stack.
 //!
-//! Right now, we never call anything, so don't worry about saving stuff.
+//! Transcendental functions (`sin`, `exp`, `ln`, etc) are implemented by
+//! calling into Rust.  Those functions are declared as `extern "sysv64"`, so
+//! the same calling convention is used on every OS (including Windows).  Tape
+//! registers are caller-saved, so they're backed up to the stack around each
+//! call; slice evaluators call the function once per SIMD lane.
 //!
 //! Within a single operation, you'll often need to make use of scratch
 //! registers.  `xmm0` is used when loading immediates, and should not be used
 //! as a scratch register (this is the `IMM_REG` constant).  `xmm1-3` are all
 //! available.
+//!
+//! Except in the point evaluator, immediates are loaded with RIP-relative
+//! addressing from a constant pool after the end of the function, which
+//! stores each unique value once.  This makes code for large tapes smaller
+//! than building each immediate with `mov` + `vmovd` + `vbroadcastss`.  The
+//! point evaluator only saves one byte per immediate by doing so, which isn't
+//! worth the size of the pool, so it still builds immediates inline.
+//!
+//! Bulk evaluation uses 256-bit AVX2 registers (`ymm`).  There's no AVX-512
+//! code path: `dynasm` can't encode EVEX instructions (`zmm` and mask
+//! registers), so it would have to be hand-assembled byte by byte.  Ragged
+//! tails are handled by [`JitBulkEval`](super::JitBulkEval), which evaluates
+//! the final full-width chunk a second time.
 
 /// We use `xmm4-15` (all caller-saved) for graph variables
 pub const REGISTER_LIMIT: usize = 12;
@@ -18,6 +35,14 @@ pub const IMM_REG: u8 = 0;
 /// `xmm1-3` are available for use as temporaries.
 pub const OFFSET: u8 = 4;
 
+/// Checks whether the CPU supports every instruction that we emit
+///
+/// Bulk evaluation uses 256-bit AVX2 operations, and the other assemblers use
+/// VEX-encoded (AVX) scalar and 128-bit operations, so AVX2 is required.
+pub fn is_supported() -> bool {
+    std::is_x86_feature_detected!("avx2")
+}
+
 pub mod float_slice;
 pub mod grad_slice;
 pub mod interval;
@@ -85,6 +85,9 @@
 //! - [`fidget::jit::JitShape`](crate::jit::JitShape) performs fast evaluation
 //!   by compiling shapes down to native code.
 //!
+//! [`fidget::dynamic::DynShape`](crate::dynamic::DynShape) wraps either of
+//! these, with the backend selected at runtime.
+//!
 //! The [`eval::Shape`](crate::eval::Shape) trait requires four different kinds
 //! of evaluation:
 //!
@@ -206,14 +209,15 @@
 //! let y = Tree::y();
 //! let tree = (x.square() + y.square()).sqrt() - 1.0;
 //! let cfg = RenderConfig::<2> {
-//!     image_size: 32,
+//!     image_size: 32.into(),
+//!     bounds: Some(fidget::shape::Bounds::centered(1.0)),
 //!     ..RenderConfig::default()
 //! };
 //! let shape = VmShape::from_tree(&tree);
 //! let out = cfg.run::<_, BitRenderMode>(shape)?;
 //! let mut iter = out.iter();
-//! for y in 0..cfg.image_size {
-//!     for x in 0..cfg.image_size {
+//! for y in 0..cfg.image_size.height {
+//!     for x in 0..cfg.image_size.width {
 //!         if *iter.next().unwrap() {
 //!             print!("XX");
 //!         } else {
@@ -249,6 +253,11 @@
 //!
 //! This is documented in the [`fidget::mesh`](mesh) module.
 //!
+//! # Quick start
+//! The [`fidget::pipeline`](pipeline) module wraps scripting, evaluation, and
+//! meshing with sensible defaults, going from a script to an STL file in three
+//! calls.
+//!
 //! # Feature flags
 #![doc = document_features::document_features!()]
 #![warn(missing_docs)]
@@ -260,6 +269,8 @@ pub use crate::core::*;
 mod error;
 pub use error::Error;
 
+pub mod exec;
+
 #[cfg(feature = "render")]
 pub mod render;
 
@@ -269,5 +280,16 @@ pub mod rhai;
 #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
 pub mod jit;
 
+pub mod dynamic;
+
 #[cfg(feature = "mesh")]
 pub mod mesh;
+
+#[cfg(feature = "bake")]
+pub mod bake;
+
+#[cfg(all(feature = "rhai", feature = "mesh"))]
+pub mod pipeline;
+
+#[cfg(feature = "stats")]
+pub mod stats;
@@ -28,9 +28,10 @@
 //! let shape = VmShape::from_tree(&tree);
 //! let settings = Settings {
 //!     depth: 4,
+//!     bounds: Some(fidget::shape::Bounds::centered(1.0)),
 //!     ..Default::default()
 //! };
-//! let o = Octree::build(&shape, settings);
+//! let o = Octree::build(&shape, settings)?;
 //! let mesh = o.walk_dual(settings);
 //!
 //! // Open a file to write, e.g.
@@ -40,7 +41,7 @@
 //! # Ok::<(), fidget::Error>(())
 //! ```
 
-use crate::shape::Bounds;
+use crate::{eval::SimplifyThreshold, shape::Bounds};
 
 mod builder;
 mod cell;
@@ -85,7 +86,11 @@ pub struct Settings {
     pub depth: u8,
 
     /// Bounds for meshing
-    pub bounds: Bounds<3>,
+    ///
+    /// This must be specified; building an octree returns
+    /// [`Error::MissingBounds`](crate::Error::MissingBounds) if it's `None`
+    /// (the default).
+    pub bounds: Option<Bounds<3>>,
 
     /// Number of threads to use
     ///
@@ -93,16 +98,36 @@ pub struct Settings {
     /// spin up _N_ threads to perform octree construction in parallel.
     #[cfg(not(target_arch = "wasm32"))]
     pub threads: std::num::NonZeroUsize,
+
+    /// Shapes below these thresholds aren't simplified during octree
+    /// construction
+    pub simplify_threshold: SimplifyThreshold,
+}
+
+impl Settings {
+    /// Checks that the bounds are specified and valid, returning them
+    ///
+    /// Returns [`Error::MissingBounds`](crate::Error::MissingBounds) if
+    /// `self.bounds` is `None`, or
+    /// [`Error::InvalidBounds`](crate::Error::InvalidBounds) if they're
+    /// invalid.
+    pub fn check(&self) -> Result<Bounds<3>, crate::Error> {
+        let bounds = self.bounds.ok_or(crate::Error::MissingBounds)?;
+        bounds.check()?;
+        Ok(bounds)
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             depth: 3,
-            bounds: Default::default(),
+            bounds: None,
 
             #[cfg(not(target_arch = "wasm32"))]
             threads: std::num::NonZeroUsize::new(8).unwrap(),
+
+            simplify_threshold: SimplifyThreshold::default(),
         }
     }
 }
@@ -110,7 +135,7 @@ impl Default for Settings {
 impl Settings {
     #[cfg(not(target_arch = "wasm32"))]
     fn threads(&self) -> usize {
-        self.threads.get()
+        crate::exec::threads(self.threads.get())
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -123,9 +123,8 @@ impl<'a> DcWorker<'a> {
         // Multi-thread copying!
         let vert_offsets_ref = &vert_offsets;
         std::thread::scope(|s| {
-            for ((tris, verts), (out_t, out_v)) in out
-                .into_iter()
-                .zip(out_tris.into_iter().zip(out_verts.into_iter()))
+            for ((tris, verts), (out_t, out_v)) in
+                out.into_iter().zip(out_tris.into_iter().zip(out_verts))
             {
                 s.spawn(move || {
                     out_t
@@ -139,10 +138,7 @@ impl<'a> DcWorker<'a> {
                             })
                         }))
                         .for_each(|(o, i)| *o = i);
-                    out_v
-                        .iter_mut()
-                        .zip(verts.into_iter())
-                        .for_each(|(o, i)| *o = i);
+                    out_v.iter_mut().zip(verts).for_each(|(o, i)| *o = i);
                 });
             }
         });
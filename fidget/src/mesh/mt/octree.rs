@@ -158,9 +158,15 @@ impl<S: Shape> OctreeWorker<S> {
         } else {
             let pool = &ThreadPool::new(settings.threads());
             let out: Vec<Octree> = std::thread::scope(|s| {
+                // Don't hold a slot while waiting on the workers
+                crate::exec::idle();
                 let mut handles = vec![];
                 for w in workers {
-                    handles.push(s.spawn(move || w.run(pool, settings)));
+                    let job = crate::exec::current();
+                    handles.push(s.spawn(move || {
+                        let _guard = crate::exec::enter(job);
+                        w.run(pool, settings)
+                    }));
                 }
                 handles.into_iter().map(|h| h.join().unwrap()).collect()
             });
@@ -172,6 +178,11 @@ impl<S: Shape> OctreeWorker<S> {
     pub fn run(mut self, threads: &ThreadPool, settings: Settings) -> Octree {
         let mut ctx = threads.start(self.thread_index);
         loop {
+            if crate::exec::checkpoint().is_err() {
+                self.drain(&mut ctx);
+                break;
+            }
+
             // First, check to see if anyone has finished a task and sent us
             // back the result.  Otherwise, keep going.
             match self.done.try_recv() {
@@ -249,6 +260,30 @@ impl<S: Shape> OctreeWorker<S> {
         self.octree.into()
     }
 
+    /// Discards all remaining work after the job has been cancelled
+    ///
+    /// Results sent back from other threads must still be received (so that
+    /// the thread pool's accounting stays correct), but are not processed, so
+    /// no further work is scheduled.
+    fn drain(&mut self, ctx: &mut ThreadContext) {
+        loop {
+            match self.done.try_recv() {
+                Ok(..) => {
+                    ctx.popped();
+                    continue;
+                }
+                Err(TryRecvError::Disconnected) => panic!(),
+                Err(TryRecvError::Empty) => (),
+            }
+            if self.queue.pop().is_some() {
+                continue;
+            }
+            if !ctx.sleep() {
+                break;
+            }
+        }
+    }
+
     fn reclaim(&mut self, task: Task<S>) {
         if let Ok(t) = Arc::try_unwrap(task.data) {
             self.reclaim_inner(t)
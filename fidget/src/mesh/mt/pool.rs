@@ -25,7 +25,7 @@ impl ThreadPool {
     /// # Panics
     /// If `index` is greater than the `n` used in the constructor, or if this
     /// is called from the same thread used to build the thread pool.
-    pub fn start(&self, index: usize) -> ThreadContext {
+    pub fn start(&self, index: usize) -> ThreadContext<'_> {
         // Record our current index
         let mut w = self.threads.write().unwrap();
         let thread_count = w.len();
@@ -150,7 +150,9 @@ impl ThreadContext<'_> {
             }
         } else {
             // There are other active threads, so park ourselves and wait for
-            // someone else to wake us up.
+            // someone else to wake us up.  If we're part of a job, then give up
+            // our slot so that the other threads can make progress.
+            crate::exec::idle();
             std::thread::park();
 
             // Someone has woken us up!  Check our counter and see whether we've
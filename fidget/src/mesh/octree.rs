@@ -10,7 +10,10 @@ use super::{
     types::{Axis, Corner, Edge},
     Mesh, Settings,
 };
-use crate::eval::{BulkEvaluator, Shape, Tape, TracingEvaluator};
+use crate::{
+    eval::{BulkEvaluator, Shape, Tape, TracingEvaluator},
+    Error,
+};
 use std::{num::NonZeroUsize, sync::Arc, sync::OnceLock};
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -86,11 +89,23 @@ pub struct Octree {
 impl Octree {
     /// Builds an octree to the given depth
     ///
-    /// The shape is evaluated on the region specified by `settings.bounds`.
-    pub fn build<S: Shape + Clone>(shape: &S, settings: Settings) -> Self {
+    /// The shape is evaluated on the region specified by `settings.bounds`;
+    /// returns [`Error::MissingBounds`] or [`Error::InvalidBounds`] if those
+    /// bounds are missing or invalid, or
+    /// [`Error::Cancelled`] if this is run as part of a
+    /// [job](crate::exec::Executor::run) which is cancelled.
+    pub fn build<S: Shape + Clone>(
+        shape: &S,
+        settings: Settings,
+    ) -> Result<Self, Error> {
+        let bounds = settings.check()?;
+        if crate::exec::is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
         // Transform the shape given our bounds
-        let t = settings.bounds.transform();
-        if t == nalgebra::Transform::identity() {
+        let t = bounds.transform();
+        let out = if t == nalgebra::Transform::identity() {
             Self::build_inner(shape, settings)
         } else {
             let shape = shape.clone().apply_transform(t.into());
@@ -103,7 +118,149 @@ impl Octree {
                 v.pos = q.coords;
             }
             out
+        };
+        if crate::exec::is_cancelled() {
+            return Err(Error::Cancelled);
         }
+        Ok(out)
+    }
+
+    /// Builds an octree to the given depth, with coarse previews along the way
+    ///
+    /// Unlike [`build`](Self::build), the octree is built one level at a time.
+    /// After each level from 1 up to `settings.depth - 1`, `preview` is called
+    /// with that depth and a mesh of the partial octree, treating the cells
+    /// which will be subdivided further as leaves.  This lets a GUI show a
+    /// refining preview during long meshing jobs.  The coarse levels are
+    /// reused by the full-depth build, so previews only add one leaf
+    /// evaluation per subdivided cell (and the cost of meshing).
+    ///
+    /// Cells in the final octree are collapsed in the same way as in
+    /// [`build`](Self::build), but previews are not collapsed.
+    ///
+    /// Returns the full-depth octree, or an error if the bounds in `settings`
+    /// are missing or invalid or the build is cancelled.
+    pub fn build_progressive<S: Shape + Clone, F: FnMut(u8, Mesh)>(
+        shape: &S,
+        settings: Settings,
+        mut preview: F,
+    ) -> Result<Self, Error> {
+        let bounds = settings.check()?;
+        if crate::exec::is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        // Transform the shape given our bounds
+        let t = bounds.transform();
+        if t == nalgebra::Transform::identity() {
+            Self::build_progressive_inner(shape, settings, t, &mut preview)
+        } else {
+            let shape = shape.clone().apply_transform(t.into());
+            Self::build_progressive_inner(&shape, settings, t, &mut preview)
+        }
+    }
+
+    /// Level-by-level octree build, used by [`build_progressive`]
+    ///
+    /// `t` is the transform from [-1, +1] back to model space, which is applied
+    /// to vertices in both the previews and final octree.
+    ///
+    /// [`build_progressive`]: Self::build_progressive
+    fn build_progressive_inner<S: Shape + Clone, F: FnMut(u8, Mesh)>(
+        shape: &S,
+        settings: Settings,
+        t: nalgebra::Transform3<f32>,
+        preview: &mut F,
+    ) -> Result<Self, Error> {
+        let to_model = |mut o: Octree| {
+            for v in &mut o.verts {
+                let p: nalgebra::Point3<f32> = v.pos.into();
+                v.pos = t.transform_point(&p).coords;
+            }
+            o
+        };
+
+        let mut out = OctreeBuilder::<S>::new();
+
+        // Cells which have been subdivided (with the index of their first
+        // child), in the order of subdivision.  Children are always subdivided
+        // after their parents, so this list is walked in reverse to collapse
+        // the octree from the bottom up once every level is built.
+        let mut branches = vec![];
+
+        let mut level = vec![(
+            CellIndex::default(),
+            Arc::new(EvalGroup::new(shape.clone())),
+        )];
+        for depth in 0..=settings.depth {
+            let show = depth > 0 && depth < settings.depth;
+            let results = crate::exec::run_indexed(
+                level.len(),
+                settings.threads(),
+                OctreeBuilder::<S>::new,
+                |w, i| {
+                    crate::exec::checkpoint()?;
+                    let (cell, eval) = &level[i];
+                    Ok::<_, Error>(w.eval_level(eval, *cell, settings, show))
+                },
+            )?;
+
+            let mut next = vec![];
+            let mut leafs = vec![];
+            for (i, r) in results {
+                let cell = level[i].0;
+                match r {
+                    LevelResult::Done(leaf) => {
+                        out.o[cell] = out.store_leaf(leaf).into();
+                    }
+                    LevelResult::Recurse(sub_eval, leaf) => {
+                        let index = out.o.cells.len();
+                        out.o.cells.resize(index + 8, Cell::Invalid.into());
+                        out.o[cell] = Cell::Branch { index, thread: 0 }.into();
+                        branches.push((cell, index));
+                        next.extend(
+                            Corner::iter().map(|c| {
+                                (cell.child(index, c), sub_eval.clone())
+                            }),
+                        );
+                        leafs.extend(leaf.map(|leaf| (cell, leaf)));
+                    }
+                }
+            }
+
+            if show {
+                let mut o = Octree {
+                    cells: out.o.cells.clone(),
+                    verts: vec![],
+                };
+                for (cell, leaf) in leafs {
+                    o[cell] = match leaf {
+                        LeafResult::Empty => Cell::Empty,
+                        LeafResult::Full => Cell::Full,
+                        LeafResult::Leaf(verts, hermite) => {
+                            let index = o.verts.len();
+                            o.verts.extend(verts);
+                            Cell::Leaf(Leaf {
+                                mask: hermite.mask,
+                                index,
+                            })
+                        }
+                    }
+                    .into();
+                }
+                preview(depth, to_model(o).walk_dual(settings));
+            }
+            level = next;
+        }
+
+        for (cell, index) in branches.into_iter().rev() {
+            let r = out.check_done(cell, index).unwrap();
+            out.record_branch(cell, r);
+        }
+        if crate::exec::is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(to_model(out.into()))
     }
 
     fn build_inner<S: Shape + Clone>(shape: &S, settings: Settings) -> Self {
@@ -337,6 +494,63 @@ impl<S: Shape> OctreeBuilder<S> {
         eval: &Arc<EvalGroup<S>>,
         cell: CellIndex,
         settings: Settings,
+    ) -> CellResult<S> {
+        match self.eval_interval(eval, cell, settings) {
+            CellResult::Recurse(eval)
+                if cell.depth == settings.depth as usize =>
+            {
+                let out = CellResult::Done(self.leaf(&eval, cell));
+                if let Ok(t) = Arc::try_unwrap(eval) {
+                    self.reclaim(t);
+                }
+                out
+            }
+            r => r,
+        }
+    }
+
+    /// Evaluates a single cell during a level-by-level build
+    ///
+    /// Unlike [`eval_cell`](Self::eval_cell), leaf data is returned instead of
+    /// being stored in the builder, because this may be run on a worker
+    /// thread.  If `preview` is set, cells which need to be subdivided are
+    /// also evaluated as leaves.
+    fn eval_level(
+        &mut self,
+        eval: &Arc<EvalGroup<S>>,
+        cell: CellIndex,
+        settings: Settings,
+        preview: bool,
+    ) -> LevelResult<S> {
+        match self.eval_interval(eval, cell, settings) {
+            CellResult::Done(Cell::Empty) => {
+                LevelResult::Done(LeafResult::Empty)
+            }
+            CellResult::Done(Cell::Full) => LevelResult::Done(LeafResult::Full),
+            CellResult::Done(..) => unreachable!(),
+            CellResult::Recurse(eval) => {
+                if cell.depth == settings.depth as usize {
+                    let out = LevelResult::Done(self.eval_leaf(&eval, cell));
+                    if let Ok(t) = Arc::try_unwrap(eval) {
+                        self.reclaim(t);
+                    }
+                    out
+                } else {
+                    let leaf = preview.then(|| self.eval_leaf(&eval, cell));
+                    LevelResult::Recurse(eval, leaf)
+                }
+            }
+        }
+    }
+
+    /// Evaluates a cell's interval, simplifying the tape if it's ambiguous
+    ///
+    /// Returns either an empty or full cell, or the tape to use for the cell.
+    fn eval_interval(
+        &mut self,
+        eval: &Arc<EvalGroup<S>>,
+        cell: CellIndex,
+        settings: Settings,
     ) -> CellResult<S> {
         let (i, r) = self
             .eval_interval
@@ -352,7 +566,9 @@ impl<S: Shape> OctreeBuilder<S> {
         } else if i.lower() > 0.0 {
             CellResult::Done(Cell::Empty)
         } else {
-            let sub_tape = if S::simplify_tree_during_meshing(cell.depth) {
+            let sub_tape = if S::simplify_tree_during_meshing(cell.depth)
+                && settings.simplify_threshold.should_simplify(&eval.shape)
+            {
                 let s = self.shape_storage.pop().unwrap_or_default();
                 r.map(|r| {
                     Arc::new(EvalGroup::new(
@@ -362,16 +578,7 @@ impl<S: Shape> OctreeBuilder<S> {
             } else {
                 None
             };
-            if cell.depth == settings.depth as usize {
-                let eval = sub_tape.unwrap_or_else(|| eval.clone());
-                let out = CellResult::Done(self.leaf(&eval, cell));
-                if let Ok(t) = Arc::try_unwrap(eval) {
-                    self.reclaim(t);
-                }
-                out
-            } else {
-                CellResult::Recurse(sub_tape.unwrap_or_else(|| eval.clone()))
-            }
+            CellResult::Recurse(sub_tape.unwrap_or_else(|| eval.clone()))
         }
     }
 
@@ -411,12 +618,18 @@ impl<S: Shape> OctreeBuilder<S> {
     }
 
     /// Recurse down the octree, building the given cell
+    ///
+    /// Returns `false` if the current job was cancelled, in which case the
+    /// cell may not be populated.
     fn recurse(
         &mut self,
         eval: &Arc<EvalGroup<S>>,
         cell: CellIndex,
         settings: Settings,
-    ) {
+    ) -> bool {
+        if crate::exec::checkpoint().is_err() {
+            return false;
+        }
         match self.eval_cell(eval, cell, settings) {
             CellResult::Done(c) => self.o[cell] = c.into(),
             CellResult::Recurse(sub_eval) => {
@@ -426,7 +639,9 @@ impl<S: Shape> OctreeBuilder<S> {
                 }
                 for i in Corner::iter() {
                     let cell = cell.child(index, i);
-                    self.recurse(&sub_eval, cell, settings);
+                    if !self.recurse(&sub_eval, cell, settings) {
+                        return false;
+                    }
                 }
 
                 if let Ok(t) = Arc::try_unwrap(sub_eval) {
@@ -434,20 +649,21 @@ impl<S: Shape> OctreeBuilder<S> {
                 }
 
                 let r = self.check_done(cell, index).unwrap();
-
-                self.o[cell] = match r {
-                    BranchResult::Empty => Cell::Empty,
-                    BranchResult::Full => Cell::Full,
-                    BranchResult::Branch(index) => {
-                        Cell::Branch { index, thread: 0 }
-                    }
-                    BranchResult::Leaf(pos, hermite) => {
-                        self.record_leaf(pos, hermite)
-                    }
-                }
-                .into();
+                self.record_branch(cell, r);
             }
         }
+        true
+    }
+
+    /// Records the result of [`check_done`](Self::check_done) into a cell
+    fn record_branch(&mut self, cell: CellIndex, r: BranchResult) {
+        self.o[cell] = match r {
+            BranchResult::Empty => Cell::Empty,
+            BranchResult::Full => Cell::Full,
+            BranchResult::Branch(index) => Cell::Branch { index, thread: 0 },
+            BranchResult::Leaf(pos, hermite) => self.record_leaf(pos, hermite),
+        }
+        .into();
     }
 
     /// Evaluates the given leaf
@@ -456,6 +672,16 @@ impl<S: Shape> OctreeBuilder<S> {
     /// `self.hermite`, and the leaf data to `self.leafs`.  Does **not** write
     /// anything to `self.o.cells`; the cell is returned instead.
     fn leaf(&mut self, eval: &EvalGroup<S>, cell: CellIndex) -> Cell {
+        let r = self.eval_leaf(eval, cell);
+        self.store_leaf(r)
+    }
+
+    /// Evaluates the given leaf, without recording anything in the builder
+    fn eval_leaf(
+        &mut self,
+        eval: &EvalGroup<S>,
+        cell: CellIndex,
+    ) -> LeafResult {
         let mut xs = [0.0; 8];
         let mut ys = [0.0; 8];
         let mut zs = [0.0; 8];
@@ -482,9 +708,9 @@ impl<S: Shape> OctreeBuilder<S> {
 
         // Early exit if the cell is completely empty or full
         if mask == 0 {
-            return Cell::Empty;
+            return LeafResult::Empty;
         } else if mask == 255 {
-            return Cell::Full;
+            return LeafResult::Full;
         }
 
         // Start and endpoints in 3D space for intersection searches
@@ -661,16 +887,33 @@ impl<S: Shape> OctreeBuilder<S> {
             hermite_cell.qef_err = err;
         }
 
+        let verts = verts
+            .into_iter()
+            .chain(
+                intersections
+                    .into_iter()
+                    .map(|pos| CellVertex { pos: cell.pos(pos) }),
+            )
+            .collect();
+        LeafResult::Leaf(verts, hermite_cell)
+    }
+
+    /// Stores the result of [`eval_leaf`](Self::eval_leaf)
+    ///
+    /// Writes the leaf vertex to `self.o.verts`, hermite data to
+    /// `self.hermite`, and the leaf data to `self.leafs`, returning the cell.
+    fn store_leaf(&mut self, r: LeafResult) -> Cell {
+        let (verts, hermite) = match r {
+            LeafResult::Empty => return Cell::Empty,
+            LeafResult::Full => return Cell::Full,
+            LeafResult::Leaf(verts, hermite) => (verts, hermite),
+        };
+
         // TODO: use self.record_leaf here?
         let vert_index = self.o.verts.len();
         self.o.verts.extend(verts);
-        self.o.verts.extend(
-            intersections
-                .into_iter()
-                .map(|pos| CellVertex { pos: cell.pos(pos) }),
-        );
 
-        let hermite_index = self.push_hermite(hermite_cell);
+        let hermite_index = self.push_hermite(hermite);
         debug_assert!(hermite_index > 0);
 
         let leaf_index = self.leafs.len();
@@ -679,7 +922,7 @@ impl<S: Shape> OctreeBuilder<S> {
             NonZeroUsize::new(hermite_index).unwrap(),
         ));
         Cell::Leaf(Leaf {
-            mask,
+            mask: hermite.mask,
             index: leaf_index,
         })
     }
@@ -936,6 +1179,24 @@ pub enum CellResult<S: Shape> {
     Recurse(Arc<EvalGroup<S>>),
 }
 
+/// Result of a leaf evaluation, before it's stored in the octree
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum LeafResult {
+    Empty,
+    Full,
+    /// Leaf vertices (followed by edge intersections) and hermite data
+    Leaf(arrayvec::ArrayVec<CellVertex, 16>, LeafHermiteData),
+}
+
+/// Result of a single cell evaluation during a level-by-level build
+pub(crate) enum LevelResult<S: Shape> {
+    /// The cell is complete
+    Done(LeafResult),
+    /// The cell should be subdivided, using the given tape; if requested, it
+    /// was also evaluated as a leaf for previewing.
+    Recurse(Arc<EvalGroup<S>>, Option<LeafResult>),
+}
+
 /// Result of a branch evaluation (8-fold division)
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum BranchResult {
@@ -1149,7 +1410,7 @@ mod test {
     use super::*;
     use crate::{
         context::Tree,
-        eval::{EzShape, MathShape},
+        eval::{EzShape, MathShape, SimplifyThreshold},
         mesh::types::{Edge, X, Y, Z},
         shape::Bounds,
         vm::VmShape,
@@ -1159,19 +1420,21 @@ mod test {
 
     const DEPTH0_SINGLE_THREAD: Settings = Settings {
         depth: 0,
-        bounds: Bounds {
+        bounds: Some(Bounds {
             center: Vector3::new(0.0, 0.0, 0.0),
             size: 1.0,
-        },
-        threads: unsafe { std::num::NonZeroUsize::new_unchecked(1) },
+        }),
+        threads: std::num::NonZeroUsize::new(1).unwrap(),
+        simplify_threshold: SimplifyThreshold::ALWAYS,
     };
     const DEPTH1_SINGLE_THREAD: Settings = Settings {
         depth: 1,
-        bounds: Bounds {
+        bounds: Some(Bounds {
             center: Vector3::new(0.0, 0.0, 0.0),
             size: 1.0,
-        },
-        threads: unsafe { std::num::NonZeroUsize::new_unchecked(1) },
+        }),
+        threads: std::num::NonZeroUsize::new(1).unwrap(),
+        simplify_threshold: SimplifyThreshold::ALWAYS,
     };
 
     fn sphere(center: [f32; 3], radius: f32) -> Tree {
@@ -1198,7 +1461,7 @@ mod test {
         let shape = VmShape::from_tree(&cube([-f, f], [-f, 0.3], [-f, 0.6]));
         // This should be a cube with a single edge running through the root
         // node of the octree, with an edge vertex at [0, 0.3, 0.6]
-        let octree = Octree::build(&shape, DEPTH0_SINGLE_THREAD);
+        let octree = Octree::build(&shape, DEPTH0_SINGLE_THREAD).unwrap();
         assert_eq!(octree.verts.len(), 5);
         let v = octree.verts[0].pos;
         let expected = nalgebra::Vector3::new(0.0, 0.3, 0.6);
@@ -1248,7 +1511,7 @@ mod test {
 
         // If we only build a depth-0 octree, then it's a leaf without any
         // vertices (since all the corners are empty)
-        let octree = Octree::build(&shape, DEPTH0_SINGLE_THREAD);
+        let octree = Octree::build(&shape, DEPTH0_SINGLE_THREAD).unwrap();
         assert_eq!(octree.cells.len(), 8); // we always build at least 8 cells
         assert_eq!(Cell::Empty, octree.cells[0].into(),);
         assert_eq!(octree.verts.len(), 0);
@@ -1258,7 +1521,7 @@ mod test {
         assert!(empty_mesh.triangles.is_empty());
 
         // Now, at depth-1, each cell should be a Leaf with one vertex
-        let octree = Octree::build(&shape, DEPTH1_SINGLE_THREAD);
+        let octree = Octree::build(&shape, DEPTH1_SINGLE_THREAD).unwrap();
         assert_eq!(octree.cells.len(), 16); // we always build at least 8 cells
         assert_eq!(
             Cell::Branch {
@@ -1285,11 +1548,48 @@ mod test {
         assert!(!sphere_mesh.triangles.is_empty());
     }
 
+    #[test]
+    fn test_build_progressive() {
+        let shape = VmShape::from_tree(&sphere([0.0; 3], 0.6));
+        for (threads, bounds) in [
+            (1, Bounds::centered(1.0)),
+            (4, Bounds::centered(1.0)),
+            (4, Bounds::new([0.5, 0.0, 0.0].into(), 2.0)),
+        ] {
+            let settings = Settings {
+                depth: 4,
+                threads: threads.try_into().unwrap(),
+                bounds: Some(bounds),
+                ..Default::default()
+            };
+            let mut depths = vec![];
+            let mut sizes = vec![];
+            let octree =
+                Octree::build_progressive(&shape, settings, |depth, mesh| {
+                    depths.push(depth);
+                    sizes.push(mesh.triangles.len());
+                })
+                .unwrap();
+            assert_eq!(depths, vec![1, 2, 3]);
+            assert!(sizes.windows(2).all(|w| w[0] < w[1]), "{sizes:?}");
+
+            let full = octree.walk_dual(settings);
+            let expected =
+                Octree::build(&shape, settings).unwrap().walk_dual(settings);
+            assert_eq!(full.triangles.len(), expected.triangles.len());
+            assert_eq!(full.vertices.len(), expected.vertices.len());
+            assert!(*sizes.last().unwrap() < full.triangles.len());
+            for v in &full.vertices {
+                assert!((v.norm() - 0.6).abs() < 0.05, "bad vertex {v}");
+            }
+        }
+    }
+
     #[test]
     fn test_sphere_verts() {
         let shape = VmShape::from_tree(&sphere([0.0; 3], 0.2));
 
-        let octree = Octree::build(&shape, DEPTH1_SINGLE_THREAD);
+        let octree = Octree::build(&shape, DEPTH1_SINGLE_THREAD).unwrap();
         let sphere_mesh = octree.walk_dual(DEPTH1_SINGLE_THREAD);
 
         let mut edge_count = 0;
@@ -1329,16 +1629,14 @@ mod test {
             let settings = Settings {
                 depth: 5,
                 threads: threads.try_into().unwrap(),
+                bounds: Some(Bounds::centered(1.0)),
                 ..Default::default()
             };
-            let octree = Octree::build(&shape, settings);
+            let octree = Octree::build(&shape, settings).unwrap();
             let sphere_mesh = octree.walk_dual(settings);
-            sphere_mesh
-                .write_stl(
-                    &mut std::fs::File::create(format!("sphere{threads}.stl"))
-                        .unwrap(),
-                )
-                .unwrap();
+            let mut stl = vec![];
+            sphere_mesh.write_stl(&mut stl).unwrap();
+            assert!(!stl.is_empty());
 
             if let Err(e) = check_for_vertex_dupes(&sphere_mesh) {
                 panic!("{e} (with {threads} threads)");
@@ -1354,7 +1652,7 @@ mod test {
         let shape =
             VmShape::from_tree(&cube([-0.1, 0.6], [-0.2, 0.75], [-0.3, 0.4]));
 
-        let octree = Octree::build(&shape, DEPTH1_SINGLE_THREAD);
+        let octree = Octree::build(&shape, DEPTH1_SINGLE_THREAD).unwrap();
         let mesh = octree.walk_dual(DEPTH1_SINGLE_THREAD);
         const EPSILON: f32 = 2.0 / u16::MAX as f32;
         assert!(!mesh.vertices.is_empty());
@@ -1403,7 +1701,8 @@ mod test {
                     let (x, y, z) = Tree::axes();
                     let f = x * dx + y * dy + z + offset;
                     let shape = VmShape::from_tree(&f);
-                    let octree = Octree::build(&shape, DEPTH0_SINGLE_THREAD);
+                    let octree =
+                        Octree::build(&shape, DEPTH0_SINGLE_THREAD).unwrap();
 
                     assert_eq!(octree.cells.len(), 8);
                     let pos = octree.verts[0].pos;
@@ -1447,7 +1746,7 @@ mod test {
                 eval.eval(&tape, corner.x, corner.y, corner.z).unwrap();
             assert!(v < 0.0, "bad corner value: {v}");
 
-            let octree = Octree::build(&shape, DEPTH0_SINGLE_THREAD);
+            let octree = Octree::build(&shape, DEPTH0_SINGLE_THREAD).unwrap();
             assert_eq!(octree.cells.len(), 8);
             assert_eq!(octree.verts.len(), 4);
 
@@ -1482,9 +1781,10 @@ mod test {
         let settings = Settings {
             depth: 2,
             threads: threads.try_into().unwrap(),
+            bounds: Some(Bounds::centered(1.0)),
             ..Default::default()
         };
-        let octree = Octree::build(&shape, settings);
+        let octree = Octree::build(&shape, settings).unwrap();
 
         let mesh = octree.walk_dual(settings);
         if mask != 0 && mask != 255 {
@@ -1551,9 +1851,10 @@ mod test {
             let settings = Settings {
                 depth: 1,
                 threads: threads.try_into().unwrap(),
+                bounds: Some(Bounds::centered(1.0)),
                 ..Default::default()
             };
-            let octree = Octree::build(&shape, settings);
+            let octree = Octree::build(&shape, settings).unwrap();
             assert_eq!(
                 octree.cells[0],
                 Cell::Empty.into(),
@@ -1572,9 +1873,10 @@ mod test {
             let settings = Settings {
                 depth: 5,
                 threads: threads.try_into().unwrap(),
+                bounds: Some(Bounds::centered(1.0)),
                 ..Default::default()
             };
-            let octree = Octree::build(&tape, settings);
+            let octree = Octree::build(&tape, settings).unwrap();
             let mesh = octree.walk_dual(settings);
             // Note: the model has duplicate vertices!
             if let Err(e) = check_for_edge_matching(&mesh) {
@@ -1667,10 +1969,12 @@ mod test {
         let settings = Settings {
             depth: 4,
             threads: 1.try_into().unwrap(),
+            bounds: Some(Bounds::centered(1.0)),
             ..Default::default()
         };
 
-        let octree = Octree::build(&shape, settings).walk_dual(settings);
+        let octree =
+            Octree::build(&shape, settings).unwrap().walk_dual(settings);
         for v in octree.vertices.iter() {
             let n = v.norm();
             assert!(n > 0.7 && n < 0.8, "invalid vertex at {v:?}: {n}");
@@ -1685,13 +1989,24 @@ mod test {
         let settings = Settings {
             depth: 4,
             threads: 1.try_into().unwrap(),
-            bounds: Bounds { size: 0.5, center },
+            bounds: Some(Bounds { size: 0.5, center }),
+            ..Default::default()
         };
 
-        let octree = Octree::build(&shape, settings).walk_dual(settings);
+        let octree =
+            Octree::build(&shape, settings).unwrap().walk_dual(settings);
         for v in octree.vertices.iter() {
             let n = (v - center).norm();
             assert!(n > 0.2 && n < 0.3, "invalid vertex at {v:?}: {n}");
         }
+
+        let settings = Settings {
+            bounds: None,
+            ..settings
+        };
+        assert!(matches!(
+            Octree::build(&shape, settings),
+            Err(Error::MissingBounds)
+        ));
     }
 }
@@ -79,8 +79,7 @@ impl QuadraticErrorSolver {
         const EIGENVALUE_CUTOFF_RELATIVE: f32 = 1e-12;
         let cutoff = svd.singular_values[0].abs() * EIGENVALUE_CUTOFF_RELATIVE;
         let start = (0..3)
-            .filter(|i| svd.singular_values[*i].abs() < cutoff)
-            .last()
+            .rfind(|i| svd.singular_values[*i].abs() < cutoff)
             .unwrap_or(0);
 
         // "Dual Contouring: The Secret Sauce" recomments a threshold of 0.1
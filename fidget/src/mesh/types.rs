@@ -240,7 +240,7 @@ impl Edge {
             _ => unreachable!("invalid edge index"),
         };
 
-        let u = u * ((self.0 % 4) % 2 != 0);
+        let u = u * !(self.0 % 4).is_multiple_of(2);
         let v = v * ((self.0 % 4) / 2 != 0);
 
         (u | v, t | u | v)
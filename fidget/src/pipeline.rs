@@ -0,0 +1,181 @@
+//! High-level pipeline from a script to a mesh
+//!
+//! This module wraps script evaluation ([`fidget::rhai`](crate::rhai)), shape
+//! construction ([`fidget::vm`](crate::vm) or `fidget::jit`), and meshing
+//! ([`fidget::mesh`](crate::mesh)) with reasonable defaults, so that producing
+//! an STL file takes three calls:
+//!
+//! ```
+//! use fidget::pipeline::Pipeline;
+//!
+//! let p = Pipeline::from_script("sphere(0, 0, 0, 0.6)")?;
+//! let mesh = p.depth(4).mesh()?;
+//!
+//! // Open a file to write, e.g.
+//! // let mut f = std::fs::File::create("out.stl")?;
+//! # let mut f = vec![];
+//! mesh.write_stl(&mut f)?;
+//! # Ok::<(), fidget::Error>(())
+//! ```
+//!
+//! Each stage can be configured with builder-style functions on [`Pipeline`];
+//! for anything more complicated, use the underlying modules directly.
+use crate::{
+    compiler::Backend,
+    context::Tree,
+    eval::MathShape,
+    mesh::{Mesh, Octree, Settings},
+    shape::Bounds,
+    vm::VmShape,
+    Error,
+};
+
+/// Builder for a script → shape → mesh pipeline
+///
+/// By default, this meshes the region `[-1, +1]` on every axis to an octree
+/// depth of 6, using the JIT backend if available (and the VM otherwise).
+#[derive(Clone)]
+pub struct Pipeline {
+    tree: Tree,
+    settings: Settings,
+    backend: Backend,
+}
+
+impl Pipeline {
+    /// Builds a pipeline from a [Rhai](https://rhai.rs) script
+    ///
+    /// The script is evaluated as an expression in terms of `x`, `y`, and `z`
+    /// (see [`fidget::rhai::eval`](crate::rhai::eval)).
+    pub fn from_script(script: &str) -> Result<Self, Error> {
+        let tree = crate::rhai::eval(script)?;
+        Ok(Self::from_tree(tree))
+    }
+
+    /// Builds a pipeline from a math tree
+    pub fn from_tree(tree: Tree) -> Self {
+        Self {
+            tree,
+            settings: Settings {
+                depth: 6,
+                bounds: Some(Bounds::centered(1.0)),
+                ..Settings::default()
+            },
+            backend: Backend::default(),
+        }
+    }
+
+    /// Sets the octree depth used for meshing
+    pub fn depth(mut self, depth: u8) -> Self {
+        self.settings.depth = depth;
+        self
+    }
+
+    /// Sets the region to be meshed
+    pub fn bounds(mut self, bounds: Bounds<3>) -> Self {
+        self.settings.bounds = Some(bounds);
+        self
+    }
+
+    /// Sets the number of threads used for meshing
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn threads(mut self, threads: std::num::NonZeroUsize) -> Self {
+        self.settings.threads = threads;
+        self
+    }
+
+    /// Sets the evaluation backend
+    ///
+    /// [`Backend::Jit`] is only available with the `jit` feature (and not on
+    /// `wasm32`) on CPUs which support it; selecting it otherwise falls back to
+    /// [`Backend::Vm`].
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Returns the meshing settings
+    pub fn settings(&self) -> Settings {
+        self.settings
+    }
+
+    /// Returns the math tree
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    /// Builds a mesh
+    ///
+    /// Returns [`Error::InvalidBounds`] if the bounds are invalid.
+    pub fn mesh(&self) -> Result<Mesh, Error> {
+        match self.backend {
+            #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+            Backend::Jit if crate::jit::is_supported() => {
+                let shape = crate::jit::JitShape::from_tree(&self.tree);
+                self.mesh_shape(&shape)
+            }
+            _ => self.mesh_shape(&VmShape::from_tree(&self.tree)),
+        }
+    }
+
+    fn mesh_shape<S: crate::eval::Shape>(
+        &self,
+        shape: &S,
+    ) -> Result<Mesh, Error> {
+        let octree = Octree::build(shape, self.settings)?;
+        Ok(octree.walk_dual(self.settings))
+    }
+
+    /// Builds a mesh and writes it as a binary STL
+    pub fn write_stl<W: std::io::Write>(
+        &self,
+        out: &mut W,
+    ) -> Result<(), Error> {
+        self.mesh()?.write_stl(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pipeline() {
+        let p = Pipeline::from_script("sphere(0, 0, 0, 0.6)").unwrap();
+        assert_eq!(p.settings().depth, 6);
+
+        let p = p.depth(4);
+        let mesh = p.mesh().unwrap();
+        assert!(!mesh.triangles.is_empty());
+        for v in &mesh.vertices {
+            assert!((v.norm() - 0.6).abs() < 0.05, "bad vertex {v:?}");
+        }
+
+        // Both backends produce the same mesh (though triangles may be in a
+        // different order, because meshing is multithreaded)
+        let vm = p.clone().backend(Backend::Vm).mesh().unwrap();
+        assert_eq!(vm.triangles.len(), mesh.triangles.len());
+
+        let mut stl = vec![];
+        p.write_stl(&mut stl).unwrap();
+        assert_eq!(stl.len(), 84 + 50 * mesh.triangles.len());
+
+        // Meshing happens in model space, regardless of bounds
+        let bounds = Bounds {
+            center: nalgebra::Vector3::new(0.5, 0.0, 0.0),
+            size: 2.0,
+        };
+        let mesh = p.bounds(bounds).mesh().unwrap();
+        assert!(!mesh.triangles.is_empty());
+        for v in &mesh.vertices {
+            assert!((v.norm() - 0.6).abs() < 0.15, "bad vertex {v:?}");
+        }
+
+        assert!(Pipeline::from_script("sphere(").is_err());
+        let bad = Bounds {
+            center: nalgebra::Vector3::zeros(),
+            size: 0.0,
+        };
+        let p = Pipeline::from_script("x").unwrap().bounds(bad);
+        assert!(matches!(p.mesh(), Err(Error::InvalidBounds)));
+    }
+}
@@ -0,0 +1,351 @@
+//! Baking of signed distance field atlases (e.g. for fonts and icons)
+use crate::{
+    eval::{BulkEvaluator, Shape},
+    shape::Bounds,
+    Error,
+};
+
+/// A single glyph to be baked into an [`Atlas`]
+///
+/// Each glyph has `C` channel shapes which share a single set of bounds.  For
+/// a plain SDF atlas, `C` is 1.  For a multi-channel SDF atlas, `C` is 3 (or 4,
+/// with a true distance field in the alpha channel) and the caller provides
+/// one shape per channel, e.g. built from edge-colored segments of the glyph's
+/// outline; the glyph is recovered at draw time as the median of the first
+/// three channels.
+pub struct Glyph<S, const C: usize = 1> {
+    /// Shapes to evaluate, one per output channel
+    pub shapes: [S; C],
+
+    /// Region of model space which is mapped onto this glyph's atlas cell
+    pub bounds: Bounds<2>,
+}
+
+impl<S> Glyph<S, 1> {
+    /// Builds a single-channel glyph
+    pub fn new(shape: S, bounds: Bounds<2>) -> Self {
+        Self {
+            shapes: [shape],
+            bounds,
+        }
+    }
+}
+
+/// Settings for [`bake_atlas`]
+pub struct AtlasConfig {
+    /// Width and height of each glyph's cell, in pixels
+    pub cell_size: usize,
+
+    /// Distance (in pixels) from the shape's boundary that is encoded
+    ///
+    /// A pixel which is exactly on the boundary is encoded as 127.5 (i.e. 128
+    /// after rounding); pixels that are `spread` units inside or outside of
+    /// the shape are encoded as 255 and 0 respectively.
+    pub spread: f32,
+
+    /// Number of cells per row of the atlas
+    ///
+    /// If this is `None`, the atlas is made as square as possible.
+    pub columns: Option<usize>,
+
+    /// Number of threads to use; 8 by default
+    #[cfg(not(target_arch = "wasm32"))]
+    pub threads: std::num::NonZeroUsize,
+}
+
+impl Default for AtlasConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 32,
+            spread: 4.0,
+            columns: None,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            threads: std::num::NonZeroUsize::new(8).unwrap(),
+        }
+    }
+}
+
+impl AtlasConfig {
+    #[cfg(target_arch = "wasm32")]
+    fn threads(&self) -> usize {
+        1
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn threads(&self) -> usize {
+        self.threads.get()
+    }
+}
+
+/// A baked distance field atlas
+///
+/// Pixels are stored in row-major order, with the top row first.
+pub struct Atlas<const C: usize = 1> {
+    /// Width of the atlas image, in pixels
+    pub width: usize,
+    /// Height of the atlas image, in pixels
+    pub height: usize,
+    /// Width and height of each cell, in pixels
+    pub cell_size: usize,
+    /// Pixel data, with `width * height` items
+    pub data: Vec<[u8; C]>,
+    /// Top-left pixel position of each glyph's cell, in glyph order
+    pub cells: Vec<[usize; 2]>,
+}
+
+impl<const C: usize> Atlas<C> {
+    /// Returns normalized texture coordinates `[u0, v0, u1, v1]` of a cell
+    ///
+    /// `(u0, v0)` is the top-left corner and `(u1, v1)` is the bottom-right.
+    pub fn uv(&self, glyph: usize) -> [f32; 4] {
+        let [x, y] = self.cells[glyph];
+        let w = self.width as f32;
+        let h = self.height as f32;
+        [
+            x as f32 / w,
+            y as f32 / h,
+            (x + self.cell_size) as f32 / w,
+            (y + self.cell_size) as f32 / h,
+        ]
+    }
+}
+
+/// Bakes a set of glyphs into a distance field atlas
+///
+/// Each glyph is sampled at pixel centers within its cell, using the shape's
+/// bulk float evaluator (one call per cell and channel).  Work is split
+/// between threads at the granularity of individual channels.
+///
+/// Returns an error if any glyph has invalid bounds or if the configuration has
+/// an empty cell size or non-positive spread.
+pub fn bake_atlas<S: Shape, const C: usize>(
+    glyphs: &[Glyph<S, C>],
+    config: &AtlasConfig,
+) -> Result<Atlas<C>, Error> {
+    if config.cell_size == 0 {
+        return Err(Error::InvalidAtlas("cell size must be non-zero"));
+    } else if !(config.spread > 0.0 && config.spread.is_finite()) {
+        return Err(Error::InvalidAtlas("spread must be positive and finite"));
+    } else if config.columns == Some(0) {
+        return Err(Error::InvalidAtlas("column count must be non-zero"));
+    }
+    for g in glyphs {
+        g.bounds.check()?;
+    }
+
+    let columns = config
+        .columns
+        .unwrap_or_else(|| (glyphs.len() as f64).sqrt().ceil() as usize)
+        .max(1);
+    let rows = glyphs.len().div_ceil(columns);
+    let n = config.cell_size;
+    let width = columns * n;
+    let height = rows * n;
+
+    let cells: Vec<[usize; 2]> = (0..glyphs.len())
+        .map(|i| [(i % columns) * n, (i / columns) * n])
+        .collect();
+
+    let out = crate::exec::run_indexed(
+        glyphs.len() * C,
+        config.threads(),
+        || AtlasWorker::new(config.cell_size),
+        |w, job| w.run(glyphs, config, job),
+    )?;
+
+    let mut data = vec![[0u8; C]; width * height];
+    for (job, values) in out {
+        let [x, y] = cells[job / C];
+        let c = job % C;
+        for (j, row) in values.chunks_exact(n).enumerate() {
+            let offset = (y + j) * width + x;
+            for (d, v) in data[offset..offset + n].iter_mut().zip(row) {
+                d[c] = *v;
+            }
+        }
+    }
+
+    Ok(Atlas {
+        width,
+        height,
+        cell_size: n,
+        data,
+        cells,
+    })
+}
+
+/// Per-thread evaluator and scratch buffers
+struct AtlasWorker<S: Shape> {
+    eval: S::FloatSliceEval,
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    zs: Vec<f32>,
+}
+
+impl<S: Shape> AtlasWorker<S> {
+    fn new(n: usize) -> Self {
+        Self {
+            eval: S::new_float_slice_eval(),
+            xs: vec![0.0; n * n],
+            ys: vec![0.0; n * n],
+            zs: vec![0.0; n * n],
+        }
+    }
+
+    /// Bakes a single `(glyph, channel)` job, returning its pixels
+    fn run<const C: usize>(
+        &mut self,
+        glyphs: &[Glyph<S, C>],
+        config: &AtlasConfig,
+        job: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let n = config.cell_size;
+        let glyph = &glyphs[job / C];
+
+        // Pixel size in model units, used to scale the distance spread
+        let lo = glyph.bounds.lower();
+        let hi = glyph.bounds.upper();
+        let pixel = (hi[0] - lo[0]) / n as f32;
+        let scale = 0.5 / (config.spread * pixel);
+
+        for j in 0..n {
+            for i in 0..n {
+                self.xs[j * n + i] = lo[0] + (i as f32 + 0.5) * pixel;
+                self.ys[j * n + i] = hi[1] - (j as f32 + 0.5) * pixel;
+            }
+        }
+
+        let tape = glyph.shapes[job % C].float_slice_tape(Default::default());
+        let values = self.eval.eval(&tape, &self.xs, &self.ys, &self.zs)?;
+        Ok(values
+            .iter()
+            .map(|d| {
+                let v = (0.5 - d * scale).clamp(0.0, 1.0);
+                // NaN maps to 0 (i.e. outside) through the saturating cast
+                (v * 255.0).round() as u8
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Tree, eval::MathShape, vm::VmShape};
+
+    fn circle(cx: f64, cy: f64, r: f64) -> VmShape {
+        let (x, y, _) = Tree::axes();
+        let t = ((x - cx).square() + (y - cy).square()).sqrt() - r;
+        VmShape::from_tree(&t)
+    }
+
+    #[test]
+    fn atlas_layout() {
+        let glyphs: Vec<_> = (0..5)
+            .map(|i| {
+                Glyph::new(circle(i as f64, 0.0, 0.5), Bounds::centered(1.0))
+            })
+            .collect();
+        let atlas = bake_atlas(&glyphs, &AtlasConfig::default()).unwrap();
+        assert_eq!(atlas.width, 96);
+        assert_eq!(atlas.height, 64);
+        assert_eq!(atlas.data.len(), 96 * 64);
+        assert_eq!(
+            atlas.cells,
+            vec![[0, 0], [32, 0], [64, 0], [0, 32], [32, 32]]
+        );
+        assert_eq!(atlas.uv(4), [1.0 / 3.0, 0.5, 2.0 / 3.0, 1.0]);
+
+        let cfg = AtlasConfig {
+            columns: Some(5),
+            ..Default::default()
+        };
+        let atlas = bake_atlas(&glyphs, &cfg).unwrap();
+        assert_eq!(atlas.width, 160);
+        assert_eq!(atlas.height, 32);
+    }
+
+    #[test]
+    fn atlas_values() {
+        let glyphs = [
+            Glyph::new(circle(0.0, 0.0, 0.5), Bounds::centered(1.0)),
+            Glyph::new(circle(0.0, 0.5, 0.5), Bounds::centered(1.0)),
+        ];
+        for threads in [1, 4] {
+            let cfg = AtlasConfig {
+                cell_size: 16,
+                spread: 2.0,
+                columns: None,
+                threads: threads.try_into().unwrap(),
+            };
+            let atlas = bake_atlas(&glyphs, &cfg).unwrap();
+            let px = |x: usize, y: usize| atlas.data[y * atlas.width + x][0];
+
+            // Center of the first glyph is deep inside; corners are outside
+            assert_eq!(px(8, 8), 255);
+            assert_eq!(px(0, 0), 0);
+            assert_eq!(px(15, 15), 0);
+
+            // The second glyph is shifted upwards (towards the top row)
+            assert_eq!(px(16 + 8, 4), 255);
+            assert_eq!(px(16 + 8, 15), 0);
+
+            // Pixels near the boundary are in the middle of the range
+            let d = px(12, 8);
+            assert!(d > 64 && d < 192, "bad boundary value {d}");
+        }
+    }
+
+    #[test]
+    fn atlas_multichannel() {
+        let glyphs = [Glyph {
+            shapes: [
+                circle(0.0, 0.0, 0.5),
+                circle(0.0, 0.0, 2.0),
+                circle(5.0, 0.0, 0.5),
+            ],
+            bounds: Bounds::centered(1.0),
+        }];
+        let atlas = bake_atlas(&glyphs, &AtlasConfig::default()).unwrap();
+        assert_eq!(atlas.data[0], [0, 255, 0]);
+        assert_eq!(atlas.data[16 * atlas.width + 16], [255, 255, 0]);
+    }
+
+    #[test]
+    fn atlas_errors() {
+        let glyphs = [Glyph::new(circle(0.0, 0.0, 0.5), Bounds::centered(1.0))];
+        for cfg in [
+            AtlasConfig {
+                cell_size: 0,
+                ..Default::default()
+            },
+            AtlasConfig {
+                spread: -1.0,
+                ..Default::default()
+            },
+            AtlasConfig {
+                columns: Some(0),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                bake_atlas(&glyphs, &cfg),
+                Err(Error::InvalidAtlas(..))
+            ));
+        }
+
+        let glyphs = [Glyph::new(
+            circle(0.0, 0.0, 0.5),
+            Bounds::new([0.0, 0.0].into(), 0.0),
+        )];
+        assert!(matches!(
+            bake_atlas(&glyphs, &AtlasConfig::default()),
+            Err(Error::InvalidBounds)
+        ));
+
+        let empty: [Glyph<VmShape>; 0] = [];
+        let atlas = bake_atlas(&empty, &AtlasConfig::default()).unwrap();
+        assert_eq!(atlas.data.len(), 0);
+    }
+}
@@ -0,0 +1,101 @@
+//! Background compilation of float slice tapes
+use crate::eval::{BulkEvaluator, Shape};
+use std::sync::{mpsc::Sender, Arc, OnceLock};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{channel, Receiver},
+    Mutex,
+};
+
+/// Float slice tape type for a shape
+pub(crate) type FloatTape<S> =
+    <<S as Shape>::FloatSliceEval as BulkEvaluator>::Tape;
+
+/// Slot which is filled in once a background build finishes
+///
+/// Both the compiler and the submitting [`RenderHandle`] hold a reference to
+/// the slot; if the handle's reference is the only one remaining but the slot
+/// is still empty, then the job was abandoned and must be resubmitted.
+///
+/// [`RenderHandle`]: crate::render::RenderHandle
+pub(crate) type Slot<S> = Arc<OnceLock<Arc<FloatTape<S>>>>;
+
+/// Handle to a pool of threads which build float slice tapes
+///
+/// Cloning the handle produces a new sender for the same pool.
+pub(crate) struct Compiler<S: Shape>(Sender<(S, Slot<S>)>);
+
+impl<S: Shape> Clone for Compiler<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<S: Shape> Compiler<S> {
+    /// Submits a shape to be built in the background
+    pub fn submit(&self, shape: S) -> Slot<S> {
+        let slot = Slot::<S>::default();
+        // If the pool has shut down, the slot's only reference will be the
+        // caller's (so it will be resubmitted later).
+        let _ = self.0.send((shape, slot.clone()));
+        slot
+    }
+}
+
+/// Runs `f` with a pool of `threads` background compiler threads
+///
+/// If `threads` is zero, `f` is called with `None`.  When `f` returns, any
+/// jobs which haven't been started are abandoned; this only waits for builds
+/// which are already in progress.
+pub(crate) fn with_compiler<S: Shape, T>(
+    threads: usize,
+    f: impl FnOnce(Option<&Compiler<S>>) -> T,
+) -> T {
+    if threads == 0 {
+        return f(None);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    unreachable!("background compilation is not supported on wasm32");
+
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::scope(|s| {
+        let (tx, rx) = channel();
+        let rx = Arc::new(Mutex::new(rx));
+        let done = Arc::new(AtomicBool::new(false));
+        for _ in 0..threads {
+            let rx = rx.clone();
+            let done = done.clone();
+            let job = crate::exec::current();
+            s.spawn(move || {
+                let _guard = crate::exec::enter(job);
+                run(&rx, &done)
+            });
+        }
+        let out = f(Some(&Compiler(tx)));
+        done.store(true, Ordering::Relaxed);
+        out
+    })
+}
+
+/// Builds tapes until the pool is shut down
+#[cfg(not(target_arch = "wasm32"))]
+fn run<S: Shape>(rx: &Mutex<Receiver<(S, Slot<S>)>>, done: &AtomicBool) {
+    loop {
+        // Don't hold a slot while waiting for work
+        crate::exec::idle();
+        let Ok((shape, slot)) = rx.lock().unwrap().recv() else {
+            break;
+        };
+        if done.load(Ordering::Relaxed) || crate::exec::checkpoint().is_err() {
+            break;
+        }
+        // Skip the build if the handle has already been dropped
+        if Arc::strong_count(&slot) > 1 {
+            let tape = shape.float_slice_tape(Default::default());
+            let _ = slot.set(Arc::new(tape));
+        }
+    }
+}
@@ -1,4 +1,13 @@
-use crate::{eval::Shape, render::RenderMode, shape::Bounds, Error};
+use crate::{
+    eval::{Shape, SimplifyThreshold},
+    render::{
+        progress::ProgressCounter, IncrementalRender, Layer, LayerRenderMode,
+        RenderCache, RenderMode, RenderProgress, RenderStats, ShadingMode,
+        TileImage,
+    },
+    shape::Bounds,
+    Error,
+};
 use nalgebra::{
     allocator::Allocator, Const, DefaultAllocator, DimNameAdd, DimNameSub,
     DimNameSum, U1,
@@ -6,9 +15,17 @@ use nalgebra::{
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Container to store render configuration (resolution, etc)
-pub struct RenderConfig<const N: usize> {
-    /// Image size (for a square output image)
-    pub image_size: usize,
+pub struct RenderConfig<const N: usize>
+where
+    nalgebra::Const<N>: nalgebra::DimNameAdd<nalgebra::U1>,
+    DefaultAllocator:
+        Allocator<f32, DimNameSum<Const<N>, U1>, DimNameSum<Const<N>, U1>>,
+{
+    /// Image size, in pixels
+    ///
+    /// A `usize` converts into a square image size.  For 3D rendering, the
+    /// depth of the rendered volume is the larger of the width and height.
+    pub image_size: ImageSize,
 
     /// Tile sizes to use during evaluation.
     ///
@@ -16,26 +33,262 @@ pub struct RenderConfig<const N: usize> {
     /// [`Shape::tile_sizes_3d`] to select this based on evaluator type.
     pub tile_sizes: Vec<usize>,
 
-    /// Bounds of the rendered image, in shape coordinates
-    pub bounds: Bounds<N>,
+    /// Bounds of the rendered image, in view coordinates
+    ///
+    /// This must be specified; rendering returns [`Error::MissingBounds`] if
+    /// it's `None` (the default).
+    pub bounds: Option<Bounds<N>>,
+
+    /// Transform from view coordinates to shape coordinates
+    ///
+    /// This is the identity by default, so `bounds` are in shape coordinates.
+    /// It may be any invertible affine or projective transform, which is
+    /// applied to the shape's inputs before rendering: for example, a GUI can
+    /// pan, zoom, and rotate the view without rebuilding the shape.
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::MathShape,
+    ///     render::{BitRenderMode, RenderConfig},
+    ///     vm::VmShape,
+    /// };
+    /// use nalgebra::{Rotation2, Transform2};
+    ///
+    /// // A tall, thin rectangle
+    /// let (x, y, _) = Tree::axes();
+    /// let shape = VmShape::from_tree(&(x.abs() - 0.1).max(y.abs() - 0.8));
+    ///
+    /// // Rotating the view by 90° makes it short and wide
+    /// let cfg = RenderConfig::<2> {
+    ///     image_size: 32.into(),
+    ///     view: Transform2::from_matrix_unchecked(
+    ///         Rotation2::new(std::f32::consts::FRAC_PI_2).to_homogeneous(),
+    ///     ),
+    ///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+    ///     ..Default::default()
+    /// };
+    /// let image = cfg.run::<_, BitRenderMode>(shape)?;
+    /// assert!(image[16 * 32 + 5]); // left of center
+    /// assert!(!image[5 * 32 + 16]); // above center
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub view: nalgebra::Transform<f32, nalgebra::TGeneral, N>,
 
     /// Number of threads to use; 8 by default
     #[cfg(not(target_arch = "wasm32"))]
     pub threads: std::num::NonZeroUsize,
+
+    /// Number of extra threads for building float tapes in the background
+    ///
+    /// When this is non-zero, float slice tapes for simplified shapes are
+    /// built by a separate pool of threads.  Until a tape is ready, tiles are
+    /// evaluated with the nearest ancestor's tape, which is longer but gives
+    /// the same result; the simplified tape is swapped in for later tiles
+    /// which reuse the same simplification.  This hides JIT compilation
+    /// latency from the rendering threads.
+    ///
+    /// This is 0 (disabled) by default.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub compile_threads: usize,
+
+    /// Policy for simplifying tapes during subdivision
+    pub simplify: SimplifyPolicy,
+
+    /// Shapes below these thresholds aren't simplified during subdivision
+    pub simplify_threshold: SimplifyThreshold,
+
+    /// Optional sink for render statistics (`None` by default)
+    pub stats: Option<RenderStats>,
+
+    /// Optional callback for render progress (`None` by default)
+    pub progress: Option<RenderProgress>,
+
+    /// Number of samples per pixel in 2D rendering
+    ///
+    /// With more than one sample, each pixel is evaluated at a grid of
+    /// positions (in the same batch as the rest of its tile), then the results
+    /// are combined with [`RenderMode::filter`], weighted according to
+    /// [`sample_filter`](Self::sample_filter).  Regions which are filled by
+    /// interval arithmetic are unaffected, so this only costs extra
+    /// evaluation near the shape's edges.
+    ///
+    /// This is [`SamplesPerPixel::One`] (no supersampling) by default, and is
+    /// ignored in 3D rendering.
+    pub samples_per_pixel: SamplesPerPixel,
+
+    /// Filter used to combine samples, if there's more than one per pixel
+    pub sample_filter: SampleFilter,
+}
+
+/// Width and height of a rendered image, in pixels
+///
+/// Pixels are always square: the larger of the two dimensions spans the
+/// render's [`bounds`](RenderConfig::bounds), and the smaller dimension shows
+/// a centered portion of them.
+///
+/// ```
+/// use fidget::render::ImageSize;
+///
+/// let size = ImageSize::new(640, 480);
+/// assert_eq!(size.width * size.height, 307200);
+/// assert_eq!(ImageSize::from(64), ImageSize::new(64, 64));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ImageSize {
+    /// Width of the image, in pixels
+    pub width: usize,
+    /// Height of the image, in pixels
+    pub height: usize,
+}
+
+impl ImageSize {
+    /// Builds a new image size
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height }
+    }
+}
+
+impl From<usize> for ImageSize {
+    /// Builds a square image size
+    fn from(size: usize) -> Self {
+        Self::new(size, size)
+    }
+}
+
+/// Policy for when to simplify tapes as tiles are subdivided
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SimplifyPolicy {
+    /// Simplify the tape for every tile that is subdivided
+    #[default]
+    Always,
+
+    /// Stop simplifying once tapes stop shrinking
+    ///
+    /// If simplifying a tile's tape removes less than `min_shrink` (as a
+    /// fraction of its length), then the tile's children (and their children,
+    /// etc) are evaluated with that tape without further simplification.
+    ///
+    /// This avoids spending more time simplifying small tiles than evaluating
+    /// them, at the cost of sometimes evaluating longer tapes than necessary.
+    Adaptive {
+        /// Minimum fraction of the tape that must be removed to keep going
+        min_shrink: f32,
+    },
+}
+
+impl SimplifyPolicy {
+    /// Checks whether simplification should stop below the current tile
+    ///
+    /// `before` and `after` are the tape lengths before and after
+    /// simplification.
+    pub(crate) fn is_settled(&self, before: usize, after: usize) -> bool {
+        match self {
+            SimplifyPolicy::Always => false,
+            SimplifyPolicy::Adaptive { min_shrink } => {
+                before.saturating_sub(after) as f32
+                    <= before as f32 * min_shrink
+            }
+        }
+    }
+}
+
+/// Number of samples per pixel, for supersampling in 2D rendering
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SamplesPerPixel {
+    /// A single sample at the center of each pixel
+    #[default]
+    One,
+    /// A 2×2 grid of samples
+    TwoByTwo,
+    /// A 4×4 grid of samples
+    FourByFour,
+}
+
+impl SamplesPerPixel {
+    /// Returns the number of samples along each axis
+    pub fn grid(&self) -> usize {
+        match self {
+            SamplesPerPixel::One => 1,
+            SamplesPerPixel::TwoByTwo => 2,
+            SamplesPerPixel::FourByFour => 4,
+        }
+    }
+}
+
+/// Filter used to combine samples when supersampling
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SampleFilter {
+    /// Samples are spread evenly over the pixel and weighted equally
+    #[default]
+    Box,
+    /// Samples are spread over a two-pixel-wide footprint (overlapping the
+    /// neighboring pixels), and weighted by their distance from the pixel's
+    /// center
+    ///
+    /// This is blurrier than [`SampleFilter::Box`], but has less aliasing.
+    Tent,
+}
+
+impl SampleFilter {
+    /// Returns the largest distance from a pixel's center to its samples
+    pub(crate) fn radius(&self) -> f32 {
+        match self {
+            SampleFilter::Box => 0.5,
+            SampleFilter::Tent => 1.0,
+        }
+    }
+
+    /// Returns sample offsets (in pixels) and weights (summing to 1) for an
+    /// `n × n` grid of samples
+    pub(crate) fn pattern(&self, n: usize) -> Vec<([f32; 2], f32)> {
+        let axis: Vec<(f32, f32)> = (0..n)
+            .map(|k| {
+                let t = (k as f32 + 0.5) / n as f32 - 0.5;
+                match self {
+                    SampleFilter::Box => (t, 1.0),
+                    SampleFilter::Tent => (t * 2.0, 1.0 - (t * 2.0).abs()),
+                }
+            })
+            .collect();
+        let total: f32 = axis.iter().map(|(_, w)| w).sum::<f32>().powi(2);
+        let mut out = vec![];
+        for (y, wy) in &axis {
+            for (x, wx) in &axis {
+                out.push(([*x, *y], wx * wy / total));
+            }
+        }
+        out
+    }
 }
 
-impl<const N: usize> Default for RenderConfig<N> {
+impl<const N: usize> Default for RenderConfig<N>
+where
+    nalgebra::Const<N>: nalgebra::DimNameAdd<nalgebra::U1>,
+    DefaultAllocator:
+        Allocator<f32, DimNameSum<Const<N>, U1>, DimNameSum<Const<N>, U1>>,
+{
     fn default() -> Self {
         Self {
-            image_size: 512,
+            image_size: ImageSize::from(512),
             tile_sizes: match N {
                 2 => vec![128, 32, 8],
                 _ => vec![128, 64, 32, 16, 8],
             },
-            bounds: Default::default(),
+            bounds: None,
+            view: nalgebra::Transform::identity(),
 
             #[cfg(not(target_arch = "wasm32"))]
             threads: std::num::NonZeroUsize::new(8).unwrap(),
+            #[cfg(not(target_arch = "wasm32"))]
+            compile_threads: 0,
+
+            simplify: SimplifyPolicy::Always,
+            simplify_threshold: SimplifyThreshold::default(),
+            stats: None,
+            progress: None,
+            samples_per_pixel: SamplesPerPixel::One,
+            sample_filter: SampleFilter::Box,
         }
     }
 }
@@ -55,24 +308,51 @@ where
     <nalgebra::Const<N> as DimNameAdd<nalgebra::Const<1>>>::Output:
         DimNameSub<nalgebra::Const<1>>,
 {
+    /// Checks that the bounds and view transform are valid
+    ///
+    /// Returns [`Error::MissingBounds`] if `self.bounds` is `None`,
+    /// [`Error::InvalidBounds`] if they're invalid, or [`Error::InvalidView`]
+    /// if `self.view` is non-finite or not invertible.
+    pub fn check(&self) -> Result<(), Error> {
+        self.bounds.ok_or(Error::MissingBounds)?.check()?;
+        if self.view.matrix().iter().all(|v| v.is_finite())
+            && self.view.clone().try_inverse().is_some()
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidView)
+        }
+    }
+
     /// Returns a `RenderConfig` where the image size is padded to an even
-    /// multiple of `tile_size`, and `mat` is populated based on image size.
+    /// multiple of `tile_size`, along with a matrix which transforms from
+    /// pixel to shape coordinates.
+    ///
+    /// # Panics
+    /// If `self.bounds` is `None`; callers should use [`check`](Self::check)
+    /// first.
     pub(crate) fn align(&self) -> (AlignedRenderConfig<N>, NPlusOneMatrix<N>) {
+        let ImageSize { width, height } = self.image_size;
+        let max = width.max(height);
         let mut tile_sizes: Vec<usize> = self
             .tile_sizes
             .iter()
-            .skip_while(|t| **t > self.image_size)
+            .skip_while(|t| **t > max)
             .cloned()
             .collect();
         if tile_sizes.is_empty() {
             tile_sizes.push(8);
         }
-        // Pad image size to an even multiple of tile size.
-        let image_size = (self.image_size + tile_sizes[0] - 1) / tile_sizes[0]
-            * tile_sizes[0];
 
-        // Compensate for the image size change
-        let scale = image_size as f32 / self.image_size as f32;
+        // Size along each axis (the depth of a 3D render matches the larger
+        // side), then padded to an even multiple of tile size.
+        let orig_image_size: [usize; N] = std::array::from_fn(|i| match i {
+            0 => width,
+            1 => height,
+            _ => max,
+        });
+        let image_size =
+            orig_image_size.map(|s| s.div_ceil(tile_sizes[0]) * tile_sizes[0]);
 
         // Look, I'm not any happier about this than you are.
         let v = nalgebra::Vector::<
@@ -87,27 +367,48 @@ where
                 >>::Output,
                 U1,
             >>::Buffer,
-        >::from_element(-1.0);
+        >::from_fn(|i, _| {
+            -(orig_image_size[i] as f32) / max as f32
+        });
 
         // Build a matrix which transforms from pixel coordinates to [-1, +1]
+        // along the largest axis (centering the smaller axes)
         let mut mat =
             nalgebra::Transform::<f32, nalgebra::TGeneral, N>::identity()
                 .matrix()
-                .append_scaling(2.0 / image_size as f32)
-                .append_scaling(scale)
+                .append_scaling(2.0 / max as f32)
                 .append_translation(&v);
 
-        // The bounds transform matrix goes from [-1, +1] to model coordinates
-        mat = self.bounds.transform().matrix() * mat;
+        // The bounds transform matrix goes from [-1, +1] to view coordinates,
+        // then the view matrix goes from view to model coordinates
+        let bounds = self.bounds.expect("render bounds must be specified");
+        mat = self.view.matrix() * bounds.transform().matrix() * mat;
+
+        // Each render gets its own progress counter
+        let tiles = image_size.iter().map(|s| s / tile_sizes[0]).product();
+        let progress = self
+            .progress
+            .clone()
+            .map(|p| ProgressCounter::new(p, tiles));
 
         (
             AlignedRenderConfig {
                 image_size,
-                orig_image_size: self.image_size,
+                orig_image_size,
                 tile_sizes,
 
                 #[cfg(not(target_arch = "wasm32"))]
                 threads: self.threads,
+                #[cfg(not(target_arch = "wasm32"))]
+                compile_threads: self.compile_threads,
+
+                simplify: self.simplify,
+                simplify_threshold: self.simplify_threshold,
+                stats: self.stats.clone(),
+                progress,
+                samples_per_pixel: self.samples_per_pixel,
+                sample_filter: self.sample_filter,
+                mat: mat.clone(),
             },
             mat,
         )
@@ -116,20 +417,34 @@ where
 
 ////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) struct AlignedRenderConfig<const N: usize>
 where
     nalgebra::Const<N>: nalgebra::DimNameAdd<nalgebra::U1>,
     DefaultAllocator:
         Allocator<f32, DimNameSum<Const<N>, U1>, DimNameSum<Const<N>, U1>>,
 {
-    pub image_size: usize,
-    pub orig_image_size: usize,
+    /// Image size along each axis, padded to a multiple of the tile size
+    pub image_size: [usize; N],
+    /// Image size along each axis, before padding
+    pub orig_image_size: [usize; N],
 
     pub tile_sizes: Vec<usize>,
 
     #[cfg(not(target_arch = "wasm32"))]
     pub threads: std::num::NonZeroUsize,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub compile_threads: usize,
+
+    pub simplify: SimplifyPolicy,
+    pub simplify_threshold: SimplifyThreshold,
+    pub stats: Option<RenderStats>,
+    pub progress: Option<ProgressCounter>,
+    pub samples_per_pixel: SamplesPerPixel,
+    pub sample_filter: SampleFilter,
+
+    /// Transform from pixel to model coordinates (also returned by `align`)
+    pub mat: NPlusOneMatrix<N>,
 }
 
 /// Type for a static `f32` matrix of size `N + 1`
@@ -166,6 +481,13 @@ where
         }
     }
 
+    /// Reports that a top-level tile is finished, if tracking progress
+    pub fn tile_done(&self) {
+        if let Some(p) = &self.progress {
+            p.tile_done();
+        }
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn threads(&self) -> usize {
         1
@@ -173,7 +495,17 @@ where
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn threads(&self) -> usize {
-        self.threads.get()
+        crate::exec::threads(self.threads.get())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn compile_threads(&self) -> usize {
+        0
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compile_threads(&self) -> usize {
+        self.compile_threads
     }
 }
 
@@ -207,26 +539,385 @@ impl RenderConfig<2> {
     ///
     /// Under the hood, this delegates to
     /// [`fidget::render::render2d`](crate::render::render2d())
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)), or [`Error::Cancelled`] if this is run as
+    /// part of a [job](crate::exec::Executor::run) which is cancelled.
     pub fn run<S: Shape, M: RenderMode + Sync>(
         &self,
         shape: S,
     ) -> Result<Vec<<M as RenderMode>::Output>, Error> {
-        Ok(crate::render::render2d::<S, M>(shape, self))
+        crate::render::render2d::<S, M>(shape, self)
+    }
+
+    /// Renders a stack of shapes in 2D, compositing them into one RGBA image
+    ///
+    /// Under the hood, this delegates to
+    /// [`fidget::render::render2d_layers`](crate::render::render2d_layers());
+    /// see that function for details on compositing.  Layer masks are rendered
+    /// with the mode `M`, e.g. [`BitRenderMode`](crate::render::BitRenderMode)
+    /// or [`AntialiasRenderMode`](crate::render::AntialiasRenderMode).
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::MathShape,
+    ///     render::{BitRenderMode, Layer, RenderConfig},
+    ///     vm::VmShape,
+    /// };
+    ///
+    /// let (x, y, _) = Tree::axes();
+    /// let circle = |cx: f64, r: f64| {
+    ///     let dx = x.clone() - cx;
+    ///     VmShape::from_tree(&((dx.square() + y.square()).sqrt() - r))
+    /// };
+    /// let cfg = RenderConfig::<2> {
+    ///     image_size: 64.into(),
+    ///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+    ///     ..Default::default()
+    /// };
+    /// let image = cfg.run_layers::<_, BitRenderMode>(&[
+    ///     Layer::new(circle(-0.25, 0.5), [255, 0, 0, 255]), // opaque red
+    ///     Layer::new(circle(0.25, 0.5), [0, 0, 255, 128]), // translucent blue
+    /// ])?;
+    /// let row = 32 * 64;
+    /// assert_eq!(image[row + 10], [255, 0, 0, 255]); // red only
+    /// assert_eq!(image[row + 32], [127, 0, 128, 255]); // blended
+    /// assert_eq!(image[row + 54], [0, 0, 255, 128]); // blue only
+    /// assert_eq!(image[0], [0; 4]); // background
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)), or [`Error::Cancelled`] if this is run as
+    /// part of a [job](crate::exec::Executor::run) which is cancelled.
+    pub fn run_layers<S: Shape, M: LayerRenderMode + Sync>(
+        &self,
+        layers: &[Layer<S>],
+    ) -> Result<Vec<[u8; 4]>, Error> {
+        crate::render::render2d_layers::<S, M>(layers, self)
+    }
+
+    /// Renders a shape in 2D, passing each tile to a callback when finished
+    ///
+    /// Under the hood, this delegates to
+    /// [`fidget::render::render2d_streaming`](crate::render::render2d_streaming());
+    /// tiles are passed to `on_tile` as [`TileImage`] values, which are
+    /// clipped and positioned in image coordinates.  This allows for
+    /// progressive display, without building a second copy of the image.
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::MathShape,
+    ///     render::{BitRenderMode, ImageSize, RenderConfig},
+    ///     vm::VmShape,
+    /// };
+    /// use std::sync::Mutex;
+    ///
+    /// let (x, y, _) = Tree::axes();
+    /// let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+    /// let cfg = RenderConfig::<2> {
+    ///     image_size: ImageSize::new(100, 60),
+    ///     tile_sizes: vec![32, 8],
+    ///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+    ///     ..Default::default()
+    /// };
+    /// let image = Mutex::new(vec![false; 100 * 60]);
+    /// cfg.run_streaming::<_, BitRenderMode>(shape.clone(), |tile| {
+    ///     let mut image = image.lock().unwrap();
+    ///     for (j, row) in tile.pixels.chunks(tile.width).enumerate() {
+    ///         let start = (tile.y + j) * 100 + tile.x;
+    ///         image[start..][..tile.width].copy_from_slice(row);
+    ///     }
+    /// })?;
+    /// assert_eq!(image.into_inner().unwrap(), cfg.run::<_, BitRenderMode>(shape)?);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)), or [`Error::Cancelled`] if this is run as
+    /// part of a [job](crate::exec::Executor::run) which is cancelled.
+    pub fn run_streaming<S: Shape, M: RenderMode + Sync>(
+        &self,
+        shape: S,
+        on_tile: impl Fn(TileImage<M::Output>) + Sync,
+    ) -> Result<(), Error> {
+        crate::render::render2d_streaming::<S, M>(shape, self, on_tile)
+    }
+
+    /// Renders a shape in 2D, warm-started from the previous render
+    ///
+    /// This is equivalent to [`run`](Self::run), but reuses simplifications
+    /// from the previous call with the same `cache` wherever a region's
+    /// choices are unchanged; see [`RenderCache`] for details.
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)), or [`Error::Cancelled`] if this is run as
+    /// part of a [job](crate::exec::Executor::run) which is cancelled.
+    pub fn run_cached<S: Shape, M: RenderMode + Sync>(
+        &self,
+        cache: &mut RenderCache<S>,
+    ) -> Result<Vec<<M as RenderMode>::Output>, Error> {
+        self.check()?;
+        if crate::exec::is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let out = cache.render::<M>(self);
+        if crate::exec::is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(out)
+    }
+
+    /// Starts an incremental, single-threaded 2D render
+    ///
+    /// The render does no work until [`IncrementalRender::step`] is called;
+    /// each call renders a limited number of tiles, so this can be driven
+    /// cooperatively (e.g. from `requestAnimationFrame` in a WebAssembly build
+    /// without threads).  The `threads` setting is ignored.
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)).
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::MathShape,
+    ///     render::{BitRenderMode, RenderConfig},
+    ///     vm::VmShape,
+    /// };
+    ///
+    /// let (x, y, _) = Tree::axes();
+    /// let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+    /// let cfg = RenderConfig::<2> {
+    ///     image_size: 256.into(),
+    ///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+    ///     ..Default::default()
+    /// };
+    /// let mut r = cfg.start::<_, BitRenderMode>(shape)?;
+    /// while !r.step(1) {
+    ///     // Draw the partial image, yield to the event loop, etc.
+    /// }
+    /// assert_eq!(r.tiles_done(), 4);
+    /// assert!(r.image()[128 * 256 + 128]);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn start<S: Shape, M: RenderMode>(
+        &self,
+        shape: S,
+    ) -> Result<IncrementalRender<S, M>, Error> {
+        self.check()?;
+        Ok(IncrementalRender::new(shape, self))
+    }
+
+    /// Converts from pixel coordinates to shape coordinates
+    ///
+    /// Pixel coordinates match the images returned by [`run`](Self::run):
+    /// `x` increases to the right and `y` increases downwards, with pixel
+    /// `(0, 0)` at the top-left corner.  The renderer samples each pixel at its
+    /// integer position, so converting that position returns the point at
+    /// which the shape was evaluated; fractional positions are also allowed.
+    ///
+    /// ```
+    /// use fidget::render::RenderConfig;
+    /// use nalgebra::Point2;
+    ///
+    /// let cfg = RenderConfig::<2> {
+    ///     image_size: 32.into(),
+    ///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+    ///     ..Default::default()
+    /// };
+    /// let p = cfg.pixel_to_shape(Point2::new(16.0, 15.0))?;
+    /// assert_eq!(p, Point2::new(0.0, 0.0));
+    ///
+    /// let p = cfg.shape_to_pixel(Point2::new(-1.0, 1.0))?;
+    /// assert_eq!(p, Point2::new(0.0, -1.0));
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)).
+    pub fn pixel_to_shape(
+        &self,
+        p: nalgebra::Point2<f32>,
+    ) -> Result<nalgebra::Point2<f32>, Error> {
+        self.check()?;
+        Ok(self.pixel_mat().transform_point(&p))
+    }
+
+    /// Converts from shape coordinates to pixel coordinates
+    ///
+    /// This is the inverse of [`pixel_to_shape`](Self::pixel_to_shape); the
+    /// result may be fractional or outside of the image.
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)).
+    pub fn shape_to_pixel(
+        &self,
+        p: nalgebra::Point2<f32>,
+    ) -> Result<nalgebra::Point2<f32>, Error> {
+        self.check()?;
+        let mat = self.pixel_mat().try_inverse().ok_or(Error::InvalidView)?;
+        Ok(mat.transform_point(&p))
+    }
+
+    /// Sets the view so that pixels map to shape coordinates through `mat`
+    ///
+    /// `mat` is a 2D affine (or projective) transform from pixel coordinates
+    /// (as used by [`pixel_to_shape`](Self::pixel_to_shape)) to shape
+    /// coordinates, e.g. from an interactive camera which pans, zooms, and
+    /// rotates, or which shows an off-center crop of the model.  This sets
+    /// `bounds` to `[-1, +1]` and `view` to match `mat`; the shape is
+    /// still transformed before rendering, so interval evaluation happens in
+    /// the transformed frame.
+    ///
+    /// The mapping depends on `image_size`, which should be set first.
+    ///
+    /// ```
+    /// use fidget::render::RenderConfig;
+    /// use nalgebra::{Matrix3, Point2};
+    ///
+    /// let mut cfg = RenderConfig::<2> {
+    ///     image_size: 32.into(),
+    ///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+    ///     ..Default::default()
+    /// };
+    /// // Each pixel is 0.5 units, with the top-left pixel at (10, 20)
+    /// let mat = Matrix3::new(0.5, 0.0, 10.0, 0.0, -0.5, 20.0, 0.0, 0.0, 1.0);
+    /// cfg.set_pixel_transform(mat)?;
+    /// let p = cfg.pixel_to_shape(Point2::new(4.0, 2.0))?;
+    /// assert!((p - Point2::new(12.0, 19.0)).norm() < 1e-5);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    ///
+    /// Returns [`Error::InvalidView`] (leaving the configuration unchanged) if
+    /// `mat` is non-finite or not invertible.
+    pub fn set_pixel_transform(
+        &mut self,
+        mat: nalgebra::Matrix3<f32>,
+    ) -> Result<(), Error> {
+        if !mat.iter().all(|v| v.is_finite()) || !mat.is_invertible() {
+            return Err(Error::InvalidView);
+        }
+        self.bounds = Some(Bounds::centered(1.0));
+        self.view = nalgebra::Transform2::identity();
+        // With the default bounds and view, this is always invertible
+        let base = self.pixel_mat().try_inverse().unwrap();
+        self.view = nalgebra::Transform2::from_matrix_unchecked(mat * base);
+        Ok(())
+    }
+
+    /// Returns a matrix from image pixel coordinates to shape coordinates
+    pub(crate) fn pixel_mat(&self) -> nalgebra::Matrix3<f32> {
+        let (_, mat) = self.align();
+        // The renderer's Y axis points up, so the last row is stored first
+        let height = self.image_size.height as f32;
+        let mut flip = nalgebra::Matrix3::identity();
+        flip[(1, 1)] = -1.0;
+        flip[(1, 2)] = height - 1.0;
+        mat * flip
     }
 }
 
 impl RenderConfig<3> {
-    /// High-level API for rendering shapes in 2D
+    /// High-level API for rendering shapes in 3D
     ///
     /// Under the hood, this delegates to
     /// [`fidget::render::render3d`](crate::render::render3d())
     ///
-    /// Returns a tuple of heightmap, RGB image.
+    /// Returns a tuple of heightmap, RGB image; returns an error if the
+    /// configuration is invalid (see [`check`](Self::check)), or
+    /// [`Error::Cancelled`] if this is run as part of a
+    /// [job](crate::exec::Executor::run) which is cancelled.
     pub fn run<S: Shape>(
         &self,
         shape: S,
     ) -> Result<(Vec<u32>, Vec<[u8; 3]>), Error> {
-        Ok(crate::render::render3d::<S>(shape, self))
+        crate::render::render3d::<S>(shape, self)
+    }
+
+    /// High-level API for rendering shapes in 3D with a custom
+    /// [`ShadingMode`]
+    ///
+    /// This is equivalent to [`run`](Self::run), but each visible pixel is
+    /// produced by [`ShadingMode::shade`] (instead of being colored by its
+    /// surface normal).  Returns a tuple of heightmap, shaded image.
+    pub fn run_shaded<S: Shape, M: ShadingMode>(
+        &self,
+        shape: S,
+    ) -> Result<(Vec<u32>, Vec<M::Output>), Error> {
+        crate::render::render3d_shaded::<S, M>(shape, self)
+    }
+
+    /// Converts from pixel coordinates to shape coordinates
+    ///
+    /// `x` and `y` match the images returned by [`run`](Self::run), with pixel
+    /// `(0, 0)` at the top-left corner, and `z` is a value from the heightmap.
+    /// For a pixel which hit the surface, converting its integer position and
+    /// height returns the point at which the renderer sampled the surface
+    /// (and its normal); fractional positions are also allowed.
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree, eval::MathShape, render::RenderConfig, vm::VmShape,
+    /// };
+    /// use nalgebra::Point3;
+    ///
+    /// let (x, y, z) = Tree::axes();
+    /// let sphere = (x.square() + y.square() + z.square()).sqrt() - 0.5;
+    /// let cfg = RenderConfig::<3> {
+    ///     image_size: 64.into(),
+    ///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+    ///     ..Default::default()
+    /// };
+    /// let (depth, _color) = cfg.run(VmShape::from_tree(&sphere))?;
+    ///
+    /// // Find the top of the sphere, near the center of the image.  The
+    /// // sample point is just inside the surface (within one voxel).
+    /// let d = depth[31 * 64 + 32];
+    /// let p = cfg.pixel_to_shape(Point3::new(32.0, 31.0, d as f32))?;
+    /// assert!(p.z < 0.5 && p.z >= 0.5 - 2.0 / 64.0);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)).
+    pub fn pixel_to_shape(
+        &self,
+        p: nalgebra::Point3<f32>,
+    ) -> Result<nalgebra::Point3<f32>, Error> {
+        self.check()?;
+        Ok(self.pixel_mat().transform_point(&p))
+    }
+
+    /// Converts from shape coordinates to pixel coordinates
+    ///
+    /// This is the inverse of [`pixel_to_shape`](Self::pixel_to_shape); the
+    /// result may be fractional or outside of the image.
+    ///
+    /// Returns an error if the configuration is invalid (see
+    /// [`check`](Self::check)).
+    pub fn shape_to_pixel(
+        &self,
+        p: nalgebra::Point3<f32>,
+    ) -> Result<nalgebra::Point3<f32>, Error> {
+        self.check()?;
+        let mat = self.pixel_mat().try_inverse().ok_or(Error::InvalidView)?;
+        Ok(mat.transform_point(&p))
+    }
+
+    /// Returns a matrix from image pixel coordinates to shape coordinates
+    fn pixel_mat(&self) -> nalgebra::Matrix4<f32> {
+        let (_, mat) = self.align();
+        // The renderer's Y axis points up, so the last row is stored first;
+        // heightmap values are one above the voxel which hit the surface.
+        let height = self.image_size.height as f32;
+        let mut flip = nalgebra::Matrix4::identity();
+        flip[(1, 1)] = -1.0;
+        flip[(1, 3)] = height - 1.0;
+        flip[(2, 3)] = -1.0;
+        mat * flip
     }
 }
 
@@ -235,18 +926,19 @@ impl RenderConfig<3> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use nalgebra::Point2;
+    use nalgebra::{Point2, Point3, Vector2};
 
     #[test]
     fn test_aligned_config() {
         // Simple alignment
         let config: RenderConfig<2> = RenderConfig {
-            image_size: 512,
+            image_size: 512.into(),
             tile_sizes: vec![64, 32],
+            bounds: Some(Bounds::centered(1.0)),
             ..Default::default()
         };
         let (aligned, mat) = config.align();
-        assert_eq!(aligned.image_size, config.image_size);
+        assert_eq!(aligned.image_size, [512, 512]);
         assert_eq!(aligned.tile_sizes, config.tile_sizes);
         assert_eq!(aligned.threads, config.threads);
         assert_eq!(
@@ -263,13 +955,14 @@ mod test {
         );
 
         let config: RenderConfig<2> = RenderConfig {
-            image_size: 575,
+            image_size: 575.into(),
             tile_sizes: vec![64, 32],
+            bounds: Some(Bounds::centered(1.0)),
             ..Default::default()
         };
         let (aligned, mat) = config.align();
-        assert_eq!(aligned.orig_image_size, 575);
-        assert_eq!(aligned.image_size, 576);
+        assert_eq!(aligned.orig_image_size, [575, 575]);
+        assert_eq!(aligned.image_size, [576, 576]);
         assert_eq!(aligned.tile_sizes, config.tile_sizes);
         assert_eq!(aligned.threads, config.threads);
         assert_eq!(
@@ -277,32 +970,105 @@ mod test {
             Point2::new(-1.0, -1.0)
         );
         assert_eq!(
-            mat.transform_point(&Point2::new(config.image_size as f32, 0.0)),
+            mat.transform_point(&Point2::new(
+                config.image_size.width as f32,
+                0.0
+            )),
             Point2::new(1.0, -1.0)
         );
         assert_eq!(
             mat.transform_point(&Point2::new(
-                config.image_size as f32,
-                config.image_size as f32
+                config.image_size.width as f32,
+                config.image_size.width as f32
             )),
             Point2::new(1.0, 1.0)
         );
     }
 
+    #[test]
+    fn test_non_square_config() {
+        use crate::{context::Tree, eval::MathShape, vm::VmShape};
+
+        // Each axis is padded separately, and the wider axis spans the bounds
+        let config = RenderConfig::<2> {
+            image_size: ImageSize::new(100, 30),
+            tile_sizes: vec![32, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let (aligned, mat) = config.align();
+        assert_eq!(aligned.orig_image_size, [100, 30]);
+        assert_eq!(aligned.image_size, [128, 32]);
+        let p = mat.transform_point(&Point2::new(0.0, 0.0));
+        assert!((p - Point2::new(-1.0, -0.3)).norm() < 1e-6);
+        let p = mat.transform_point(&Point2::new(100.0, 30.0));
+        assert!((p - Point2::new(1.0, 0.3)).norm() < 1e-6);
+
+        // Rendered pixels match the pixel-to-shape conversion
+        let (x, y, _) = Tree::axes();
+        let shape = VmShape::from_tree(&(x.square() + y.square() - 0.1));
+        let image = config
+            .run::<_, crate::render::BitRenderMode>(shape.clone())
+            .unwrap();
+        assert_eq!(image.len(), 100 * 30);
+        for row in 0..30 {
+            for col in 0..100 {
+                let pixel = Point2::new(col as f32, row as f32);
+                let p = config.pixel_to_shape(pixel).unwrap();
+                let f = p.x.powi(2) + p.y.powi(2) - 0.1;
+                if f.abs() > 1e-4 {
+                    assert_eq!(image[row * 100 + col], f < 0.0, "at {pixel}");
+                }
+            }
+        }
+
+        // The same goes for 3D rendering, which is as deep as it is wide
+        let config = RenderConfig::<3> {
+            image_size: ImageSize::new(20, 50),
+            tile_sizes: vec![16, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let (aligned, _) = config.align();
+        assert_eq!(aligned.orig_image_size, [20, 50, 50]);
+        assert_eq!(aligned.image_size, [32, 64, 64]);
+        let (x, y, z) = Tree::axes();
+        let shape =
+            VmShape::from_tree(&(x.square() + y.square() + z.square() - 0.25));
+        let (depth, _) = config.run(shape).unwrap();
+        assert_eq!(depth.len(), 20 * 50);
+        for row in 0..50 {
+            for col in 0..20 {
+                let pixel = Point2::new(col as f32, row as f32);
+                let p = config
+                    .pixel_to_shape(Point3::new(pixel.x, pixel.y, 0.0))
+                    .unwrap();
+                let f = p.x.powi(2) + p.y.powi(2) - 0.25;
+                if f.abs() > 1e-2 {
+                    assert_eq!(
+                        depth[row * 20 + col] > 0,
+                        f < 0.0,
+                        "at {pixel}"
+                    );
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_bounded_config() {
         // Simple alignment
         let config: RenderConfig<2> = RenderConfig {
-            image_size: 512,
+            image_size: 512.into(),
             tile_sizes: vec![64, 32],
-            bounds: Bounds {
+            bounds: Some(Bounds {
                 center: nalgebra::Vector2::new(0.5, 0.5),
                 size: 0.5,
-            },
+            }),
             ..RenderConfig::default()
         };
         let (aligned, mat) = config.align();
-        assert_eq!(aligned.image_size, config.image_size);
+        assert_eq!(aligned.image_size, [512, 512]);
         assert_eq!(aligned.tile_sizes, config.tile_sizes);
         assert_eq!(aligned.threads, config.threads);
         assert_eq!(
@@ -319,17 +1085,17 @@ mod test {
         );
 
         let config: RenderConfig<2> = RenderConfig {
-            image_size: 575,
+            image_size: 575.into(),
             tile_sizes: vec![64, 32],
-            bounds: Bounds {
+            bounds: Some(Bounds {
                 center: nalgebra::Vector2::new(0.5, 0.5),
                 size: 0.5,
-            },
+            }),
             ..RenderConfig::default()
         };
         let (aligned, mat) = config.align();
-        assert_eq!(aligned.orig_image_size, 575);
-        assert_eq!(aligned.image_size, 576);
+        assert_eq!(aligned.orig_image_size, [575, 575]);
+        assert_eq!(aligned.image_size, [576, 576]);
         assert_eq!(aligned.tile_sizes, config.tile_sizes);
         assert_eq!(aligned.threads, config.threads);
         assert_eq!(
@@ -337,15 +1103,203 @@ mod test {
             Point2::new(0.0, 0.0)
         );
         assert_eq!(
-            mat.transform_point(&Point2::new(config.image_size as f32, 0.0)),
+            mat.transform_point(&Point2::new(
+                config.image_size.width as f32,
+                0.0
+            )),
             Point2::new(1.0, 0.0)
         );
         assert_eq!(
             mat.transform_point(&Point2::new(
-                config.image_size as f32,
-                config.image_size as f32
+                config.image_size.width as f32,
+                config.image_size.width as f32
             )),
             Point2::new(1.0, 1.0)
         );
     }
+
+    #[test]
+    fn test_view_config() {
+        // Rotate by 90°, then shift by 1 unit along the X axis
+        let view = nalgebra::Transform2::from_matrix_unchecked(
+            nalgebra::Matrix3::new(
+                0.0, -1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+            ),
+        );
+        let config: RenderConfig<2> = RenderConfig {
+            image_size: 512.into(),
+            tile_sizes: vec![64, 32],
+            bounds: Some(Bounds {
+                center: nalgebra::Vector2::new(0.5, 0.5),
+                size: 0.5,
+            }),
+            view,
+            ..RenderConfig::default()
+        };
+        assert!(config.check().is_ok());
+        let (_aligned, mat) = config.align();
+        assert_eq!(
+            mat.transform_point(&Point2::new(0.0, 0.0)),
+            Point2::new(1.0, 0.0)
+        );
+        assert_eq!(
+            mat.transform_point(&Point2::new(512.0, 0.0)),
+            Point2::new(1.0, 1.0)
+        );
+        assert_eq!(
+            mat.transform_point(&Point2::new(512.0, 512.0)),
+            Point2::new(0.0, 1.0)
+        );
+
+        let config = RenderConfig::<2>::default();
+        assert!(matches!(config.check(), Err(Error::MissingBounds)));
+
+        let config: RenderConfig<2> = RenderConfig {
+            view: nalgebra::Transform2::from_matrix_unchecked(
+                nalgebra::Matrix3::zeros(),
+            ),
+            bounds: Some(Bounds::centered(1.0)),
+            ..RenderConfig::default()
+        };
+        assert!(matches!(config.check(), Err(Error::InvalidView)));
+
+        let config: RenderConfig<2> = RenderConfig {
+            view: nalgebra::Transform2::from_matrix_unchecked(
+                nalgebra::Matrix3::from_element(f32::NAN),
+            ),
+            bounds: Some(Bounds::centered(1.0)),
+            ..RenderConfig::default()
+        };
+        assert!(matches!(config.check(), Err(Error::InvalidView)));
+    }
+
+    #[test]
+    fn test_pixel_transform() {
+        use crate::{context::Tree, eval::MathShape, vm::VmShape};
+
+        // Rotated, scaled, and off-center, with a padded image size
+        let mat = nalgebra::Matrix3::new_rotation(0.3)
+            * nalgebra::Matrix3::new_nonuniform_scaling(&Vector2::new(
+                0.02, -0.03,
+            ))
+            * nalgebra::Matrix3::new_translation(&Vector2::new(-20.0, -10.0));
+        let mut config = RenderConfig::<2> {
+            image_size: 60.into(),
+            tile_sizes: vec![16, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        config.set_pixel_transform(mat).unwrap();
+        config.check().unwrap();
+
+        let (x, y, _) = Tree::axes();
+        let shape = VmShape::from_tree(&(x.square() + y.square() - 0.25));
+        let image = config
+            .run::<_, crate::render::BitRenderMode>(shape)
+            .unwrap();
+        let mut filled = 0;
+        for row in 0..60 {
+            for col in 0..60 {
+                let pixel = Point2::new(col as f32, row as f32);
+                let p = config.pixel_to_shape(pixel).unwrap();
+                assert!((p - mat.transform_point(&pixel)).norm() < 1e-4);
+                // Skip pixels which are too close to the edge to be sure of
+                // (because the transform is rounded differently)
+                let f = p.x.powi(2) + p.y.powi(2) - 0.25;
+                if f.abs() > 1e-4 {
+                    let inside = f < 0.0;
+                    assert_eq!(image[row * 60 + col], inside, "at {pixel}");
+                    filled += inside as usize;
+                }
+            }
+        }
+        assert!(filled > 0);
+
+        // Invalid matrices are rejected without changing the config
+        let before = config.view;
+        assert!(matches!(
+            config.set_pixel_transform(nalgebra::Matrix3::zeros()),
+            Err(Error::InvalidView)
+        ));
+        assert_eq!(config.view, before);
+    }
+
+    #[test]
+    fn test_pixel_to_shape() {
+        use crate::{context::Tree, eval::MathShape, vm::VmShape};
+
+        // A half-plane whose edge doesn't land on any pixel's sample point
+        let (x, y, _) = Tree::axes();
+        let shape = VmShape::from_tree(&(x - 0.3 + y * 0.1));
+        let config = RenderConfig::<2> {
+            image_size: 64.into(),
+            bounds: Some(Bounds {
+                center: nalgebra::Vector2::new(0.1, -0.2),
+                size: 0.75,
+            }),
+            view: nalgebra::Transform2::from_matrix_unchecked(
+                nalgebra::Rotation2::new(0.4).to_homogeneous(),
+            ),
+            ..Default::default()
+        };
+        let image = config
+            .run::<_, crate::render::BitRenderMode>(shape)
+            .unwrap();
+        for row in 0..64 {
+            for col in 0..64 {
+                let pixel = Point2::new(col as f32, row as f32);
+                let p = config.pixel_to_shape(pixel).unwrap();
+                assert_eq!(
+                    image[row * 64 + col],
+                    p.x - 0.3 + p.y * 0.1 < 0.0,
+                    "mismatch at {pixel:?} ({p:?})"
+                );
+                let q = config.shape_to_pixel(p).unwrap();
+                assert!((q - pixel).norm() < 1e-3);
+            }
+        }
+
+        // The sphere's surface is found at the heightmap's depth
+        let (x, y, z) = Tree::axes();
+        let shape = VmShape::from_tree(
+            &((x.square() + y.square() + z.square()).sqrt() - 0.5),
+        );
+        let config = RenderConfig::<3> {
+            image_size: 64.into(),
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let (depth, _) = config.run(shape).unwrap();
+        for row in 0..64 {
+            for col in 0..64 {
+                let d = depth[row * 64 + col];
+                if d == 0 {
+                    continue;
+                }
+                let pixel =
+                    nalgebra::Point3::new(col as f32, row as f32, d as f32);
+                let p = config.pixel_to_shape(pixel).unwrap();
+                // Samples are inside the surface, within one voxel of it
+                let r = p.coords.norm();
+                assert!(
+                    r < 0.5 && r > 0.5 - 2.0 / 64.0 * 3f32.sqrt(),
+                    "bad depth at {pixel:?} ({p:?})"
+                );
+                let q = config.shape_to_pixel(p).unwrap();
+                assert!((q - pixel).norm() < 1e-3);
+            }
+        }
+
+        let config = RenderConfig::<2> {
+            bounds: Some(Bounds {
+                center: nalgebra::Vector2::zeros(),
+                size: -1.0,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.pixel_to_shape(Point2::origin()),
+            Err(Error::InvalidBounds)
+        ));
+    }
 }
@@ -0,0 +1,255 @@
+//! Contour extraction with marching squares
+use crate::{
+    eval::{BulkEvaluator, Shape},
+    render::{FieldRenderMode, ImageSize, RenderConfig},
+    Error,
+};
+use nalgebra::Point2;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A single contour line, in model coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contour {
+    /// Points along the contour
+    ///
+    /// For a closed contour, the first point is not repeated at the end.
+    pub points: Vec<Point2<f32>>,
+
+    /// Whether the contour is a closed loop
+    ///
+    /// Contours are only open if they're clipped by the edge of the image.
+    pub closed: bool,
+}
+
+/// Key for a grid edge, as the pair of pixel indices at its ends
+type EdgeKey = (usize, usize);
+
+/// Extracts polylines along the shape's zero iso-level
+///
+/// The shape is sampled at every pixel of the image described by `config`
+/// (using interval culling, like [`RenderConfig::run`]), then contoured with
+/// marching squares on the grid of pixel centers.  Contours are stitched
+/// together across the whole image, so there are no breaks at tile
+/// boundaries.  Ambiguous (saddle) cells are resolved by the average of their
+/// four corners.
+///
+/// Contours are oriented consistently: walking along a contour, the shape's
+/// interior is always on the left (so the outlines of filled regions run
+/// counterclockwise, and the outlines of holes run clockwise).
+///
+/// Returns an error if the configuration is invalid (see
+/// [`RenderConfig::check`]), or [`Error::Cancelled`] if this is run as part
+/// of a [job](crate::exec::Executor::run) which is cancelled.
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::MathShape,
+///     render::{contours, RenderConfig},
+///     vm::VmShape,
+/// };
+///
+/// let (x, y, _) = Tree::axes();
+/// let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+/// let cfg = RenderConfig::<2> {
+///     image_size: 64.into(),
+///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+///     ..Default::default()
+/// };
+/// let lines = contours(shape, &cfg)?;
+/// assert_eq!(lines.len(), 1);
+/// assert!(lines[0].closed);
+/// for p in &lines[0].points {
+///     assert!((p.coords.norm() - 0.5).abs() < 0.01);
+/// }
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub fn contours<S: Shape>(
+    shape: S,
+    config: &RenderConfig<2>,
+) -> Result<Vec<Contour>, Error> {
+    let mut field = config.run::<_, FieldRenderMode>(shape.clone())?;
+    let ImageSize { width, height } = config.image_size;
+    let mat = config.pixel_mat();
+    let pixel = |i: usize| Point2::new((i % width) as f32, (i / width) as f32);
+
+    // Regions which were culled by interval arithmetic are filled with
+    // interval bounds, which have the right sign but aren't exact.  Evaluate
+    // every pixel next to an edge exactly, repeating in case that changes a
+    // pixel's sign (which could expose a new edge).
+    let tape = shape.float_slice_tape(Default::default());
+    let mut eval = S::new_float_slice_eval();
+    let mut exact = vec![false; field.len()];
+    loop {
+        let mut todo = BTreeSet::new();
+        for (a, b) in grid_edges(width, height) {
+            if (field[a] < 0.0) != (field[b] < 0.0) {
+                todo.extend([a, b].into_iter().filter(|&i| !exact[i]));
+            }
+        }
+        if todo.is_empty() {
+            break;
+        }
+        let pts: Vec<_> = todo
+            .iter()
+            .map(|&i| mat.transform_point(&pixel(i)))
+            .collect();
+        let xs: Vec<f32> = pts.iter().map(|p| p.x).collect();
+        let ys: Vec<f32> = pts.iter().map(|p| p.y).collect();
+        let zs = vec![0.0; pts.len()];
+        let out = eval.eval(&tape, &xs, &ys, &zs)?;
+        for (&i, &v) in todo.iter().zip(out) {
+            field[i] = v;
+            exact[i] = true;
+        }
+    }
+
+    // Build directed segments from each cell, from the edge where the contour
+    // enters the cell to the edge where it leaves
+    let mut next: BTreeMap<EdgeKey, EdgeKey> = BTreeMap::new();
+    let inside = |i: usize| field[i] < 0.0;
+    for row in 0..height.saturating_sub(1) {
+        for col in 0..width.saturating_sub(1) {
+            // Corners, walking around the cell
+            let i = row * width + col;
+            let corners = [i, i + 1, i + width + 1, i + width];
+            let mut crossings = vec![];
+            for k in 0..4 {
+                let (a, b) = (corners[k], corners[(k + 1) % 4]);
+                if inside(a) != inside(b) {
+                    // An "entry" goes from outside to inside along the walk
+                    crossings.push(((a.min(b), a.max(b)), inside(b)));
+                }
+            }
+            let n = crossings.len();
+            // In a saddle cell, each entry is paired with one of its
+            // neighboring exits, depending on whether the inside corners are
+            // connected through the cell's center.
+            let center_inside =
+                corners.iter().map(|&c| field[c]).sum::<f32>() / 4.0 < 0.0;
+            for (k, &(key, entry)) in crossings.iter().enumerate() {
+                if entry {
+                    let partner = if n == 2 || !center_inside {
+                        (k + 1) % n
+                    } else {
+                        (k + n - 1) % n
+                    };
+                    next.insert(key, crossings[partner].0);
+                }
+            }
+        }
+    }
+
+    // Convert an edge key into a point by interpolating along the edge
+    let point = |(a, b): EdgeKey| {
+        let (va, vb) = (field[a], field[b]);
+        let t = va / (va - vb);
+        let p = pixel(a) + (pixel(b) - pixel(a)) * t;
+        mat.transform_point(&p)
+    };
+
+    // Chains which start at the edge of the image are open; everything else
+    // is a closed loop
+    let targets: BTreeSet<EdgeKey> = next.values().cloned().collect();
+    let starts: Vec<EdgeKey> = next
+        .keys()
+        .filter(|k| !targets.contains(k))
+        .cloned()
+        .collect();
+    let mut out = vec![];
+    for start in starts {
+        let mut points = vec![point(start)];
+        let mut key = start;
+        while let Some(k) = next.remove(&key) {
+            points.push(point(k));
+            key = k;
+        }
+        out.push(Contour {
+            points,
+            closed: false,
+        });
+    }
+    while let Some((start, mut key)) = next.pop_first() {
+        let mut points = vec![point(start)];
+        while key != start {
+            points.push(point(key));
+            key = next.remove(&key).unwrap();
+        }
+        out.push(Contour {
+            points,
+            closed: true,
+        });
+    }
+    Ok(out)
+}
+
+/// Iterates over every horizontal and vertical pair of neighboring pixels
+fn grid_edges(width: usize, height: usize) -> impl Iterator<Item = EdgeKey> {
+    (0..width * height).flat_map(move |i| {
+        let right = (i % width + 1 < width).then_some((i, i + 1));
+        let down = (i + width < width * height).then_some((i, i + width));
+        right.into_iter().chain(down)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Tree, eval::MathShape, vm::VmShape};
+
+    /// Returns twice the signed area of a closed contour
+    fn area(c: &Contour) -> f32 {
+        let n = c.points.len();
+        (0..n)
+            .map(|i| {
+                let (a, b) = (c.points[i], c.points[(i + 1) % n]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum()
+    }
+
+    #[test]
+    fn contour_joins() {
+        // An annulus, offset so that its edges cross tile boundaries at
+        // awkward positions, plus a half-plane which is clipped by the image
+        let (x, y, _) = Tree::axes();
+        let r =
+            ((x.clone() - 0.1).square() + (y.clone() + 0.05).square()).sqrt();
+        let ring = (r.clone() - 0.6).max(0.3 - r);
+        let shape = VmShape::from_tree(&ring.min(0.8 - y));
+        let cfg = RenderConfig::<2> {
+            image_size: 100.into(),
+            tile_sizes: vec![32, 8],
+            bounds: Some(crate::shape::Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let mut lines = contours(shape, &cfg).unwrap();
+        assert_eq!(lines.len(), 3);
+        lines.sort_by(|a, b| area(a).total_cmp(&area(b)));
+
+        // The half-plane's edge runs across the image, and is open
+        let open: Vec<_> = lines.iter().filter(|c| !c.closed).collect();
+        assert_eq!(open.len(), 1);
+        assert!(open[0].points.iter().all(|p| (p.y - 0.8).abs() < 1e-3));
+        let xs = open[0].points.iter().map(|p| p.x);
+        assert!(xs.clone().fold(f32::INFINITY, f32::min) < -0.97);
+        assert!(xs.fold(f32::NEG_INFINITY, f32::max) > 0.97);
+
+        // The ring has an outer loop (counterclockwise) and an inner hole
+        // (clockwise), with every point on the circle
+        let holes: Vec<_> =
+            lines.iter().filter(|c| c.closed && area(c) < 0.0).collect();
+        let outer: Vec<_> =
+            lines.iter().filter(|c| c.closed && area(c) > 0.0).collect();
+        assert_eq!(holes.len(), 1);
+        assert_eq!(outer.len(), 1);
+        for (c, radius) in [(holes[0], 0.3), (outer[0], 0.6)] {
+            for p in &c.points {
+                let d = (p.coords - nalgebra::Vector2::new(0.1, -0.05)).norm();
+                assert!((d - radius).abs() < 2e-3, "{d} != {radius}");
+            }
+            let expected = 2.0 * std::f32::consts::PI * radius * radius;
+            assert!((area(c).abs() - expected).abs() < 0.02 * expected);
+        }
+    }
+}
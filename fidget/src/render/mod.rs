@@ -4,24 +4,55 @@
 //! [`RenderConfig::run`](RenderConfig::run); you can also use the lower-level
 //! functions ([`render2d`](render2d()) and [`render3d`](render3d())) for manual
 //! control over the input tape.
+//!
+//! Custom output types are supported by implementing [`RenderMode`] (for 2D
+//! rendering) or [`ShadingMode`] (for 3D rendering).
 use crate::eval::{BulkEvaluator, Shape, Tape, Trace, TracingEvaluator};
-use std::sync::Arc;
+use compile::{Compiler, Slot};
+use std::{collections::HashMap, sync::Arc};
 
+mod atlas;
+mod compile;
 mod config;
+mod contours;
+mod progress;
 mod render2d;
 mod render3d;
+mod stats;
 
-pub use config::RenderConfig;
+pub use atlas::{bake_atlas, Atlas, AtlasConfig, Glyph};
+pub use config::{
+    ImageSize, RenderConfig, SampleFilter, SamplesPerPixel, SimplifyPolicy,
+};
+pub use contours::{contours, Contour};
+pub use progress::RenderProgress;
 pub use render2d::render as render2d;
+pub use render2d::render_layers as render2d_layers;
+pub use render2d::render_streaming as render2d_streaming;
+pub use render2d::RenderCache;
 pub use render3d::render as render3d;
+pub use render3d::render_shaded as render3d_shaded;
+pub use stats::{RenderStats, TapeHistogram};
 
-pub use render2d::{BitRenderMode, DebugRenderMode, RenderMode, SdfRenderMode};
+pub use render2d::{
+    AntialiasRenderMode, BitRenderMode, BlendMode, DebugPixel, DebugRenderMode,
+    FieldRenderMode, IncrementalRender, IntervalAction, Layer, LayerRenderMode,
+    RenderMode, Sample, SdfPixelRenderMode, SdfRenderMode,
+    TapeHeatmapRenderMode, TapePixel, TileImage,
+};
+pub use render3d::{NormalShadingMode, OccupancyShadingMode, ShadingMode};
 
 /// A `RenderHandle` contains lazily-populated tapes for rendering
 ///
 /// The tapes are stored as `Arc<..>`, so it can be cheaply cloned.
 ///
 /// The most recent simplification is cached for reuse (if the trace matches).
+///
+/// With [warm starting](Self::set_warm_start) enabled, the handle instead
+/// keeps one simplification per region (see
+/// [`simplify_region`](Self::simplify_region)), so that a handle which is kept
+/// between renders can skip re-simplification wherever the choices are
+/// unchanged.
 pub struct RenderHandle<S: Shape> {
     shape: S,
 
@@ -29,9 +60,28 @@ pub struct RenderHandle<S: Shape> {
     f_tape: Option<Arc<<S::FloatSliceEval as BulkEvaluator>::Tape>>,
     g_tape: Option<Arc<<S::GradSliceEval as BulkEvaluator>::Tape>>,
 
+    /// Float slice tape which is being built in the background
+    f_pending: Option<Slot<S>>,
+
+    /// Nearest ancestor's float slice tape, used until `f_pending` is ready
+    f_fallback: Option<Arc<<S::FloatSliceEval as BulkEvaluator>::Tape>>,
+
     next: Option<(S::Trace, Box<Self>)>,
+
+    /// Per-region simplifications, if warm starting is enabled
+    regions: Option<RegionMap<S>>,
 }
 
+/// Map from region to its trace and simplified handle
+type RegionMap<S> =
+    HashMap<RegionKey, (<S as Shape>::Trace, Box<RenderHandle<S>>)>;
+
+/// Key identifying a region for [`RenderHandle::simplify_region`]
+///
+/// This is the region's subdivision depth, followed by the coordinates of its
+/// corner (padded with zeros for 2D regions).
+pub type RegionKey = [usize; 4];
+
 impl<S: Shape> Clone for RenderHandle<S> {
     fn clone(&self) -> Self {
         Self {
@@ -39,7 +89,10 @@ impl<S: Shape> Clone for RenderHandle<S> {
             i_tape: self.i_tape.clone(),
             f_tape: self.f_tape.clone(),
             g_tape: self.g_tape.clone(),
+            f_pending: None,
+            f_fallback: self.f_fallback.clone(),
             next: None,
+            regions: self.regions.as_ref().map(|_| HashMap::new()),
         }
     }
 }
@@ -57,10 +110,54 @@ where
             i_tape: None,
             f_tape: None,
             g_tape: None,
+            f_pending: None,
+            f_fallback: None,
             next: None,
+            regions: None,
         }
     }
 
+    /// Builds a handle for a simplified version of this handle's shape
+    ///
+    /// The child inherits our float slice tape (or our own fallback) as its
+    /// fallback for background compilation.
+    fn child(&self, shape: S) -> Self {
+        let mut next = RenderHandle::new(shape);
+        next.f_fallback =
+            self.f_tape.clone().or_else(|| self.f_fallback.clone());
+        next
+    }
+
+    /// Enables or disables warm starting
+    ///
+    /// When enabled, [`simplify_region`](Self::simplify_region) remembers the
+    /// trace and simplified shape for every region, and child handles are
+    /// warm-started as well.  Disabling it frees all cached simplifications
+    /// into the given storage.
+    pub fn set_warm_start(
+        &mut self,
+        enabled: bool,
+        shape_storage: &mut Vec<S::Storage>,
+        tape_storage: &mut Vec<S::TapeStorage>,
+    ) {
+        match (enabled, self.regions.take()) {
+            (true, regions) => {
+                self.regions = Some(regions.unwrap_or_default());
+            }
+            (false, Some(regions)) => {
+                for (_trace, h) in regions.into_values() {
+                    h.recycle(shape_storage, tape_storage);
+                }
+            }
+            (false, None) => (),
+        }
+    }
+
+    /// Checks whether warm starting is enabled
+    pub fn is_warm_start(&self) -> bool {
+        self.regions.is_some()
+    }
+
     /// Returns a tape for tracing interval evaluation
     pub fn i_tape(
         &mut self,
@@ -86,6 +183,42 @@ where
         })
     }
 
+    /// Returns a tape for bulk float evaluation, building it in the background
+    ///
+    /// If our own tape isn't ready, then it's submitted to the compiler and
+    /// the nearest ancestor's tape is returned instead.  Our shape was
+    /// simplified from the ancestor's using traces from this region, so the
+    /// ancestor's tape produces the same values here (just more slowly); our
+    /// own tape is swapped in once it's ready.
+    ///
+    /// Without a compiler or an ancestor's tape, this is equivalent to
+    /// [`f_tape`](Self::f_tape).
+    #[allow(clippy::unnecessary_unwrap)]
+    pub(crate) fn f_tape_background(
+        &mut self,
+        storage: &mut Vec<S::TapeStorage>,
+        compiler: Option<&Compiler<S>>,
+    ) -> &<S::FloatSliceEval as BulkEvaluator>::Tape {
+        if let Some(compiler) = compiler.filter(|_| self.f_tape.is_none()) {
+            if let Some(slot) = self.f_pending.take() {
+                if let Some(t) = slot.get() {
+                    self.f_tape = Some(t.clone());
+                    self.f_fallback = None;
+                } else if Arc::strong_count(&slot) > 1 {
+                    // Still being built
+                    self.f_pending = Some(slot);
+                }
+            }
+            if self.f_tape.is_none() && self.f_fallback.is_some() {
+                if self.f_pending.is_none() {
+                    self.f_pending = Some(compiler.submit(self.shape.clone()));
+                }
+                return self.f_fallback.as_ref().unwrap();
+            }
+        }
+        self.f_tape(storage)
+    }
+
     /// Returns a tape for bulk gradient evaluation
     pub fn g_tape(
         &mut self,
@@ -111,7 +244,7 @@ where
         tape_storage: &mut Vec<S::TapeStorage>,
     ) -> &mut Self {
         // Free self.next if it doesn't match our new set of choices
-        let mut trace_storage = if let Some(neighbor) = &self.next {
+        let trace_storage = if let Some(neighbor) = &self.next {
             if &neighbor.0 != trace {
                 let (trace, neighbor) = self.next.take().unwrap();
                 neighbor.recycle(shape_storage, tape_storage);
@@ -134,29 +267,75 @@ where
                 // Optimization: if the simplified shape isn't any shorter, then
                 // don't use it (this saves time spent generating tapes)
                 shape_storage.extend(next.recycle());
-                self
-            } else {
-                assert!(self.next.is_none());
-                if let Some(t) = trace_storage.as_mut() {
+                return self;
+            }
+            let trace = match trace_storage {
+                Some(mut t) => {
                     t.copy_from(trace);
-                } else {
-                    trace_storage = Some(trace.clone());
+                    t
                 }
-                self.next = Some((
-                    trace_storage.unwrap(),
-                    Box::new(RenderHandle {
-                        shape: next,
-                        i_tape: None,
-                        f_tape: None,
-                        g_tape: None,
-                        next: None,
-                    }),
-                ));
-                &mut self.next.as_mut().unwrap().1
+                None => trace.clone(),
+            };
+            self.next = Some((trace, Box::new(self.child(next))));
+        }
+        &mut self.next.as_mut().unwrap().1
+    }
+
+    /// Simplifies the shape with the trace from evaluating a particular region
+    ///
+    /// If warm starting is disabled, this is equivalent to
+    /// [`simplify`](Self::simplify).  Otherwise, the trace is compared against
+    /// the one recorded for this region by a previous call (e.g. during the
+    /// previous frame); if they match, the previous simplification (along with
+    /// its tapes and its own cached regions) is reused, which is much cheaper
+    /// than simplifying again.  If they don't match, the stale entry is
+    /// recycled and replaced.
+    ///
+    /// Reuse is only valid if the handle's shape is unchanged; callers must
+    /// build a new handle when the shape changes.
+    pub fn simplify_region(
+        &mut self,
+        region: RegionKey,
+        trace: &S::Trace,
+        workspace: &mut S::Workspace,
+        shape_storage: &mut Vec<S::Storage>,
+        tape_storage: &mut Vec<S::TapeStorage>,
+    ) -> &mut Self {
+        let Some(regions) = self.regions.as_mut() else {
+            return self.simplify(
+                trace,
+                workspace,
+                shape_storage,
+                tape_storage,
+            );
+        };
+        let hit = regions.get(&region).is_some_and(|(t, _)| t == trace);
+        if !hit {
+            let mut trace_storage = None;
+            if let Some((t, h)) = regions.remove(&region) {
+                h.recycle(shape_storage, tape_storage);
+                trace_storage = Some(t);
             }
-        } else {
-            return &mut self.next.as_mut().unwrap().1;
+            let s = shape_storage.pop().unwrap_or_default();
+            let next = self.shape.simplify(trace, s, workspace).unwrap();
+            let mut next = self.child(next);
+            next.regions = Some(HashMap::new());
+            let trace = match trace_storage {
+                Some(mut t) => {
+                    t.copy_from(trace);
+                    t
+                }
+                None => trace.clone(),
+            };
+            let regions = self.regions.as_mut().unwrap();
+            regions.insert(region, (trace, Box::new(next)));
         }
+        &mut self.regions.as_mut().unwrap().get_mut(&region).unwrap().1
+    }
+
+    /// Returns the length of the handle's shape
+    pub(crate) fn size(&self) -> usize {
+        self.shape.size()
     }
 
     /// Recycles the entire handle into the given storage vectors
@@ -169,6 +348,11 @@ where
         if let Some((_trace, shape)) = self.next.take() {
             shape.recycle(shape_storage, tape_storage);
         }
+        for (_region, (_trace, shape)) in
+            self.regions.take().into_iter().flatten()
+        {
+            shape.recycle(shape_storage, tape_storage);
+        }
 
         if let Some(i_tape) = self.i_tape.take() {
             if let Ok(i_tape) = Arc::try_unwrap(i_tape) {
@@ -190,3 +374,96 @@ where
         shape_storage.extend(self.shape.recycle());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Context, eval::MathShape, vm::VmShape};
+
+    #[test]
+    fn test_simplify_region() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let root = ctx.min(x, y).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+
+        let mut eval = VmShape::new_interval_eval();
+        let mut h = RenderHandle::new(shape);
+        let mut ws = Default::default();
+        let (mut ss, mut ts) = (vec![], vec![]);
+        let tape = h.i_tape(&mut ts);
+        let (_, a) =
+            eval.eval(tape, [0.0, 1.0], [2.0, 3.0], [0.0, 0.0]).unwrap();
+        let a = a.unwrap().clone();
+        let tape = h.i_tape(&mut ts);
+        let (_, b) =
+            eval.eval(tape, [2.0, 3.0], [0.0, 1.0], [0.0, 0.0]).unwrap();
+        let b = b.unwrap().clone();
+        assert!(a != b);
+
+        // Without warm starting, only the most recent simplification is kept
+        let ra = [1, 0, 0, 0];
+        let rb = [1, 1, 0, 0];
+        h.simplify_region(ra, &a, &mut ws, &mut ss, &mut ts)
+            .i_tape(&mut ts);
+        h.simplify_region(rb, &b, &mut ws, &mut ss, &mut ts);
+        let next = h.simplify_region(ra, &a, &mut ws, &mut ss, &mut ts);
+        assert!(next.i_tape.is_none());
+
+        // With warm starting, each region keeps its own simplification, which
+        // is reused (tapes and all) if the trace matches
+        h.set_warm_start(true, &mut ss, &mut ts);
+        assert!(h.is_warm_start());
+        h.simplify_region(ra, &a, &mut ws, &mut ss, &mut ts)
+            .i_tape(&mut ts);
+        let next = h.simplify_region(rb, &b, &mut ws, &mut ss, &mut ts);
+        assert!(next.is_warm_start());
+        let next = h.simplify_region(ra, &a, &mut ws, &mut ss, &mut ts);
+        assert!(next.i_tape.is_some());
+
+        // A different trace in the same region replaces the simplification
+        let next = h.simplify_region(ra, &b, &mut ws, &mut ss, &mut ts);
+        assert!(next.i_tape.is_none());
+
+        h.set_warm_start(false, &mut ss, &mut ts);
+        assert!(!h.is_warm_start());
+        assert_eq!(ss.len(), 2);
+    }
+
+    #[test]
+    fn test_f_tape_background() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let root = ctx.min(x, y).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+
+        let mut eval = VmShape::new_interval_eval();
+        let mut h = RenderHandle::new(shape);
+        let mut ws = Default::default();
+        let (mut ss, mut ts) = (vec![], vec![]);
+        let tape = h.i_tape(&mut ts);
+        let (_, trace) =
+            eval.eval(tape, [0.0, 1.0], [2.0, 3.0], [0.0, 0.0]).unwrap();
+        let trace = trace.unwrap().clone();
+        let _ = h.f_tape(&mut ts);
+
+        compile::with_compiler(1, |compiler| {
+            let next = h.simplify(&trace, &mut ws, &mut ss, &mut ts);
+            assert!(next.size() < 3);
+
+            // The parent's tape is used until the child's tape is ready
+            let t = next.f_tape_background(&mut ts, compiler) as *const _;
+            let f = next.f_fallback.as_deref().unwrap() as *const _;
+            assert_eq!(t, f);
+            assert!(next.f_pending.is_some());
+            while next.f_tape.is_none() {
+                std::thread::yield_now();
+                let _ = next.f_tape_background(&mut ts, compiler);
+            }
+            assert!(next.f_pending.is_none());
+            assert!(next.f_fallback.is_none());
+        });
+    }
+}
@@ -0,0 +1,100 @@
+//! Progress reporting during rendering
+use std::sync::{Arc, Mutex};
+
+/// Callback which is notified as a render progresses
+///
+/// The callback is called with `(done, total)` counts of top-level tiles,
+/// once after each tile is finished; the final call has `done == total`.
+/// Calls are serialized (even in a multithreaded render), so counts are
+/// always increasing.  The callback is called from render threads, so it
+/// should be quick (e.g. storing the counts for a UI thread to display).
+///
+/// This is cheap to clone; clones call the same underlying function, so a
+/// single callback can be reused across many renders.
+///
+/// Progress reporting pairs with cancellation: to abandon a render partway
+/// through, run it as a [job](crate::exec::Executor::run) and cancel its
+/// [`CancelToken`](crate::exec::CancelToken), which is checked between tiles.
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::MathShape,
+///     render::{BitRenderMode, RenderConfig, RenderProgress},
+///     vm::VmShape,
+/// };
+/// use std::sync::{Arc, Mutex};
+///
+/// let (x, y, _) = Tree::axes();
+/// let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+/// let last = Arc::new(Mutex::new((0, 0)));
+/// let cfg = RenderConfig::<2> {
+///     image_size: 64.into(),
+///     tile_sizes: vec![32, 8],
+///     progress: Some(RenderProgress::new({
+///         let last = last.clone();
+///         move |done, total| *last.lock().unwrap() = (done, total)
+///     })),
+///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+///     ..Default::default()
+/// };
+/// cfg.run::<_, BitRenderMode>(shape)?;
+///
+/// // The image is split into four top-level tiles
+/// assert_eq!(*last.lock().unwrap(), (4, 4));
+/// # Ok::<(), fidget::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct RenderProgress(Arc<dyn Fn(usize, usize) + Send + Sync>);
+
+impl RenderProgress {
+    /// Builds a new progress callback
+    pub fn new(f: impl Fn(usize, usize) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl PartialEq for RenderProgress {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for RenderProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderProgress").finish_non_exhaustive()
+    }
+}
+
+/// Tile counter for a single render, shared between its workers
+#[derive(Clone, Debug)]
+pub(crate) struct ProgressCounter {
+    sink: RenderProgress,
+    done: Arc<Mutex<usize>>,
+    total: usize,
+}
+
+impl PartialEq for ProgressCounter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.done, &other.done)
+    }
+}
+
+impl ProgressCounter {
+    /// Builds a new counter for a render with `total` top-level tiles
+    pub fn new(sink: RenderProgress, total: usize) -> Self {
+        Self {
+            sink,
+            done: Arc::default(),
+            total,
+        }
+    }
+
+    /// Records that a top-level tile is finished
+    pub fn tile_done(&self) {
+        // Call the sink with the lock held, so that calls are in order
+        let mut done = self.done.lock().unwrap();
+        *done += 1;
+        (self.sink.0)(*done, self.total);
+    }
+}
@@ -1,31 +1,303 @@
 //! 2D bitmap rendering / rasterization
-use super::RenderHandle;
+use super::{
+    compile::{with_compiler, Compiler},
+    stats::WorkerStats,
+    RenderHandle,
+};
 use crate::{
     eval::{BulkEvaluator, Shape, TracingEvaluator},
     render::config::{AlignedRenderConfig, Queue, RenderConfig, Tile},
-    types::Interval,
+    types::{Grad, Interval},
+    Error,
 };
-use nalgebra::Point2;
+use nalgebra::{Matrix3, Point2};
+use std::{collections::HashMap, sync::Mutex};
 
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Response type for [`RenderMode::interval`]
 pub enum IntervalAction<T> {
+    /// Fill every pixel in the region with the given value
     Fill(T),
+    /// Evaluate the region's four corners, then call [`RenderMode::pixel`] on
+    /// a bilinear interpolation of those values for every pixel
     Interpolate,
+    /// Subdivide the region (or evaluate every pixel, if this is the smallest
+    /// tile size)
     Recurse,
 }
 
-/// Configuration trait for rendering
+/// Configuration trait for 2D rendering
+///
+/// The renderer subdivides the image into square regions (using the tile sizes
+/// from [`RenderConfig`]), starting with the largest.  For each region, it
+/// evaluates the shape over the region's bounds with interval arithmetic, then
+/// calls [`interval`](Self::interval) to decide what to do with it.  Regions
+/// which reach the smallest tile size without being filled are evaluated
+/// pixel-by-pixel, calling [`pixel`](Self::pixel) on each sample (or
+/// [`sample`](Self::sample), for modes which need more than the shape's
+/// value).
+///
+/// Implementing this trait is all that's needed for a custom render mode:
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::MathShape,
+///     render::{IntervalAction, RenderConfig, RenderMode},
+///     types::Interval,
+///     vm::VmShape,
+/// };
+///
+/// /// Render mode which records the depth at which each pixel was resolved
+/// struct DepthMode;
+/// impl RenderMode for DepthMode {
+///     type Output = u8;
+///     fn interval(i: Interval, depth: usize) -> IntervalAction<u8> {
+///         if i.upper() < 0.0 || i.lower() > 0.0 {
+///             IntervalAction::Fill(depth as u8 + 1)
+///         } else {
+///             IntervalAction::Recurse
+///         }
+///     }
+///     fn pixel(_f: f32) -> u8 {
+///         u8::MAX
+///     }
+/// }
+///
+/// let (x, y, _) = Tree::axes();
+/// let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+/// let cfg = RenderConfig::<2> {
+///     image_size: 64.into(),
+///     tile_sizes: vec![16, 4],
+///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+///     ..Default::default()
+/// };
+/// let image = cfg.run::<_, DepthMode>(shape)?;
+/// assert_eq!(image[0], 1); // corners are resolved by the largest tiles
+/// assert!(image.contains(&u8::MAX)); // edges are resolved per-pixel
+/// # Ok::<(), fidget::Error>(())
+/// ```
 pub trait RenderMode {
     /// Type of output pixel
+    ///
+    /// Pixels which aren't written (e.g. because the render was cancelled)
+    /// have the default value.
     type Output: Default + Copy + Clone + Send;
 
-    /// Decide whether to subdivide or fill an interval
+    /// Decides whether to subdivide or fill a region
+    ///
+    /// `i` is the result of interval evaluation over the entire region, and
+    /// `depth` is the index of the region's tile size in
+    /// [`RenderConfig::tile_sizes`] (so 0 is the largest tiles).
     fn interval(i: Interval, depth: usize) -> IntervalAction<Self::Output>;
 
-    /// Per-pixel drawing
+    /// Decides whether to subdivide or fill a region, given the length of the
+    /// (possibly simplified) tape which was used to evaluate it
+    ///
+    /// This is called for every region; the default implementation ignores
+    /// `tape_len` and calls [`interval`](Self::interval).
+    fn interval_tape(
+        i: Interval,
+        depth: usize,
+        tape_len: usize,
+    ) -> IntervalAction<Self::Output> {
+        let _ = tape_len;
+        Self::interval(i, depth)
+    }
+
+    /// Converts a single sample of the shape into a pixel
+    ///
+    /// `f` is the shape's value at the pixel (or an interpolated value, if
+    /// [`interval`](Self::interval) returned
+    /// [`IntervalAction::Interpolate`]).
     fn pixel(f: f32) -> Self::Output;
+
+    /// Whether pixels are evaluated with partial derivatives
+    ///
+    /// If this is `true`, then [`pixel_grad`](Self::pixel_grad) is called
+    /// instead of [`pixel`](Self::pixel), and each region's interval is
+    /// evaluated over the full footprint of its pixels (extending half a pixel
+    /// past the outermost samples), so that regions which are filled don't
+    /// touch the shape's edge.
+    const GRADIENT: bool = false;
+
+    /// Converts a single sample of the shape and its partial derivatives into
+    /// a pixel
+    ///
+    /// This is only called if [`GRADIENT`](Self::GRADIENT) is `true`.  The
+    /// shape is evaluated in pixel coordinates, so `g.v / (g.dx, g.dy).norm()`
+    /// is the approximate distance to the edge in pixels.  Interpolated values
+    /// have derivatives estimated from the region's corners.
+    fn pixel_grad(g: Grad) -> Self::Output {
+        Self::pixel(g.v)
+    }
+
+    /// Converts a single sample into a pixel, with access to its position
+    /// and partial derivatives
+    ///
+    /// This is called for every pixel which isn't filled by
+    /// [`interval`](Self::interval).  The default implementation calls
+    /// [`pixel_grad`](Self::pixel_grad) if [`GRADIENT`](Self::GRADIENT) is
+    /// `true`, and [`pixel`](Self::pixel) otherwise; implementing it directly
+    /// allows for shading which depends on more than the shape's value.
+    ///
+    /// ```
+    /// use fidget::{
+    ///     context::Tree,
+    ///     eval::MathShape,
+    ///     render::{BitRenderMode, IntervalAction, RenderConfig, RenderMode, Sample},
+    ///     types::Interval,
+    ///     vm::VmShape,
+    /// };
+    ///
+    /// /// Render mode which draws diagonal hatching inside the shape
+    /// struct HatchMode;
+    /// impl RenderMode for HatchMode {
+    ///     type Output = bool;
+    ///     fn interval(i: Interval, _depth: usize) -> IntervalAction<bool> {
+    ///         if i.lower() > 0.0 {
+    ///             IntervalAction::Fill(false)
+    ///         } else {
+    ///             IntervalAction::Recurse
+    ///         }
+    ///     }
+    ///     fn pixel(f: f32) -> bool {
+    ///         f < 0.0
+    ///     }
+    ///     fn sample(s: &mut Sample) -> bool {
+    ///         let p = s.position();
+    ///         s.value() < 0.0 && (p.x + p.y).rem_euclid(0.2) < 0.1
+    ///     }
+    /// }
+    ///
+    /// let (x, y, _) = Tree::axes();
+    /// let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+    /// let cfg = RenderConfig::<2> {
+    ///     image_size: 64.into(),
+    ///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+    ///     ..Default::default()
+    /// };
+    /// let hatched = cfg.run::<_, HatchMode>(shape.clone())?;
+    /// let filled = cfg.run::<_, BitRenderMode>(shape)?;
+    /// let count = |image: &[bool]| image.iter().filter(|b| **b).count();
+    /// assert!(count(&hatched) > count(&filled) / 3);
+    /// assert!(count(&hatched) < count(&filled) * 2 / 3);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    fn sample(s: &mut Sample) -> Self::Output {
+        if Self::GRADIENT {
+            Self::pixel_grad(s.pixel_grad())
+        } else {
+            Self::pixel(s.value())
+        }
+    }
+
+    /// Combines weighted samples into a single pixel, when supersampling
+    ///
+    /// This is only called if [`RenderConfig::samples_per_pixel`] is more
+    /// than one.  Weights are positive and sum to 1.  The default
+    /// implementation returns the first sample with the largest weight, i.e.
+    /// output types which can't be blended aren't antialiased.
+    fn filter(samples: &[(Self::Output, f32)]) -> Self::Output {
+        samples
+            .iter()
+            .copied()
+            .reduce(|a, b| if b.1 > a.1 { b } else { a })
+            .map(|(v, _)| v)
+            .unwrap_or_default()
+    }
+}
+
+/// Weighted average of color samples, for [`RenderMode::filter`]
+fn average<const N: usize>(samples: &[([u8; N], f32)]) -> [u8; N] {
+    let mut out = [0.0f32; N];
+    for (v, w) in samples {
+        for (o, v) in out.iter_mut().zip(v) {
+            *o += *v as f32 * w;
+        }
+    }
+    out.map(|v| v.round() as u8)
+}
+
+/// A single sample of the shape, passed to [`RenderMode::sample`]
+///
+/// The sample's position and partial derivatives are computed on demand, so
+/// render modes which don't use them don't pay for them.  If
+/// [`RenderMode::GRADIENT`] is `true`, derivatives are evaluated in bulk along
+/// with the shape's value; otherwise, they're evaluated one pixel at a time
+/// the first time they're requested.
+pub struct Sample<'a> {
+    value: f32,
+    tape_len: usize,
+    pixel: Point2<f32>,
+    mat: &'a Matrix3<f32>,
+    grad: Option<Grad>,
+    eval_grad: &'a mut dyn FnMut(Point2<f32>) -> Grad,
+}
+
+impl<'a> Sample<'a> {
+    /// Returns the shape's value at this sample
+    ///
+    /// If the sample's region returned [`IntervalAction::Interpolate`], this
+    /// is an interpolated value.
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Returns the length of the (simplified) tape used to evaluate this
+    /// sample
+    pub fn tape_len(&self) -> usize {
+        self.tape_len
+    }
+
+    /// Returns the position of this sample in model coordinates
+    pub fn position(&self) -> Point2<f32> {
+        self.mat.transform_point(&self.pixel)
+    }
+
+    /// Returns the shape's value and partial derivatives with respect to
+    /// model coordinates
+    ///
+    /// The returned [`Grad`] has `dz = 0`.  Like
+    /// [`pixel_grad`](Self::pixel_grad), it may be estimated by interpolation.
+    pub fn grad(&mut self) -> Grad {
+        let g = self.pixel_grad();
+        let m = self.mat;
+        let det = m[(0, 0)] * m[(1, 1)] - m[(0, 1)] * m[(1, 0)];
+        Grad::new(
+            g.v,
+            (m[(1, 1)] * g.dx - m[(1, 0)] * g.dy) / det,
+            (m[(0, 0)] * g.dy - m[(0, 1)] * g.dx) / det,
+            0.0,
+        )
+    }
+
+    /// Returns the shape's value and partial derivatives with respect to
+    /// pixel coordinates
+    ///
+    /// `g.v / (g.dx, g.dy).norm()` is the approximate distance to the shape's
+    /// edge, in pixels.  This is the value passed to
+    /// [`RenderMode::pixel_grad`]; if [`RenderMode::GRADIENT`] is `true` and
+    /// the sample's region returned [`IntervalAction::Interpolate`], it's
+    /// estimated from the region's corners.
+    pub fn pixel_grad(&mut self) -> Grad {
+        *self
+            .grad
+            .get_or_insert_with(|| (self.eval_grad)(self.pixel))
+    }
+}
+
+/// Converts derivatives with respect to model coordinates (as returned by
+/// evaluating a transformed shape) into derivatives with respect to pixel
+/// coordinates
+fn to_pixel_grad(mat: &Matrix3<f32>, g: Grad) -> Grad {
+    Grad::new(
+        g.v,
+        g.dx * mat[(0, 0)] + g.dy * mat[(1, 0)],
+        g.dx * mat[(0, 1)] + g.dy * mat[(1, 1)],
+        0.0,
+    )
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -61,19 +333,31 @@ impl RenderMode for DebugRenderMode {
     }
 }
 
+/// Pixel type for [`DebugRenderMode`], recording how the pixel was resolved
 #[derive(Copy, Clone, Debug, Default)]
 pub enum DebugPixel {
+    /// Empty, filled by a top-level tile
     EmptyTile,
+    /// Filled, filled by a top-level tile
     FilledTile,
+    /// Empty, filled by a smaller tile
     EmptySubtile,
+    /// Filled, filled by a smaller tile
     FilledSubtile,
+    /// Empty, evaluated per-pixel
     Empty,
+    /// Filled, evaluated per-pixel
     Filled,
+    /// Not rendered
     #[default]
     Invalid,
 }
 
 impl DebugPixel {
+    /// Returns an RGBA color for visualization
+    ///
+    /// # Panics
+    /// If the pixel is [`DebugPixel::Invalid`]
     #[inline]
     pub fn as_debug_color(&self) -> [u8; 4] {
         match self {
@@ -87,6 +371,10 @@ impl DebugPixel {
         }
     }
 
+    /// Checks whether the pixel is inside the shape
+    ///
+    /// # Panics
+    /// If the pixel is [`DebugPixel::Invalid`]
     #[inline]
     pub fn is_filled(&self) -> bool {
         match self {
@@ -101,6 +389,119 @@ impl DebugPixel {
     }
 }
 
+/// Debug render mode which records the tape length used for every pixel
+///
+/// Each pixel records how it was resolved (as a [`DebugPixel`]) and the length
+/// of the simplified tape which was evaluated there: for regions which were
+/// proven empty or filled by interval arithmetic, that's the tape for the
+/// whole region, and for pixels which were evaluated individually, it's the
+/// tape for the smallest tile.  Drawing the result with
+/// [`TapePixel::as_heatmap_color`] shows where a shape is expensive to
+/// render, and whether tapes are being simplified as expected.
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::MathShape,
+///     render::{RenderConfig, TapeHeatmapRenderMode},
+///     vm::VmShape,
+/// };
+///
+/// let (x, y, _) = Tree::axes();
+/// let circle = |cx: f64| {
+///     let dx = x.clone() - cx;
+///     (dx.square() + y.square()).sqrt() - 0.3
+/// };
+/// let shape = VmShape::from_tree(&circle(-0.5).min(circle(0.5)));
+/// let cfg = RenderConfig::<2> {
+///     image_size: 64.into(),
+///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+///     ..Default::default()
+/// };
+/// let image = cfg.run::<_, TapeHeatmapRenderMode>(shape.clone())?;
+///
+/// // Near each circle, the `min` is simplified away
+/// let max = image.iter().map(|p| p.tape_len).max().unwrap();
+/// assert!(image[32 * 64 + 16].tape_len < max);
+/// let colors: Vec<[u8; 4]> =
+///     image.iter().map(|p| p.as_heatmap_color(max)).collect();
+/// assert_eq!(colors.len(), image.len());
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub struct TapeHeatmapRenderMode;
+
+impl RenderMode for TapeHeatmapRenderMode {
+    type Output = TapePixel;
+    fn interval(_i: Interval, _depth: usize) -> IntervalAction<TapePixel> {
+        unreachable!("interval_tape is implemented")
+    }
+    fn interval_tape(
+        i: Interval,
+        depth: usize,
+        tape_len: usize,
+    ) -> IntervalAction<TapePixel> {
+        match DebugRenderMode::interval(i, depth) {
+            IntervalAction::Fill(kind) => {
+                IntervalAction::Fill(TapePixel { kind, tape_len })
+            }
+            IntervalAction::Interpolate => IntervalAction::Interpolate,
+            IntervalAction::Recurse => IntervalAction::Recurse,
+        }
+    }
+    fn pixel(_f: f32) -> TapePixel {
+        unreachable!("sample is implemented")
+    }
+    fn sample(s: &mut Sample) -> TapePixel {
+        TapePixel {
+            kind: DebugRenderMode::pixel(s.value()),
+            tape_len: s.tape_len(),
+        }
+    }
+}
+
+/// Pixel type for [`TapeHeatmapRenderMode`]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TapePixel {
+    /// How the pixel was resolved
+    pub kind: DebugPixel,
+    /// Length of the tape which was evaluated for this pixel
+    pub tape_len: usize,
+}
+
+impl TapePixel {
+    /// Returns an RGBA color for visualization
+    ///
+    /// Hue shows the tape length on a log scale, from blue (the shortest
+    /// tapes) through green to red (tapes of length `max_len`).  Pixels in
+    /// regions which were proven empty are drawn dark, regions which were
+    /// proven filled are drawn at half brightness, and pixels which were
+    /// evaluated individually are drawn at full brightness.
+    ///
+    /// # Panics
+    /// If the pixel's kind is [`DebugPixel::Invalid`]
+    pub fn as_heatmap_color(&self, max_len: usize) -> [u8; 4] {
+        let t = if max_len > 1 {
+            ((self.tape_len.max(1) as f32).ln() / (max_len as f32).ln())
+                .clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let [r, g, b] = if t < 0.5 {
+            [0.0, t * 2.0, 1.0 - t * 2.0]
+        } else {
+            [t * 2.0 - 1.0, 2.0 - t * 2.0, 0.0]
+        };
+        let brightness = match self.kind {
+            DebugPixel::EmptyTile | DebugPixel::EmptySubtile => 0.25,
+            DebugPixel::FilledTile | DebugPixel::FilledSubtile => 0.5,
+            DebugPixel::Empty | DebugPixel::Filled => 1.0,
+            DebugPixel::Invalid => panic!(),
+        };
+        let f = |v: f32| (v * brightness * 255.0).round() as u8;
+        [f(r), f(g), f(b), 255]
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Renderer that emits `bool`
@@ -120,6 +521,116 @@ impl RenderMode for BitRenderMode {
     fn pixel(f: f32) -> bool {
         f < 0.0
     }
+    fn filter(samples: &[(bool, f32)]) -> bool {
+        samples
+            .iter()
+            .filter(|(v, _)| *v)
+            .map(|(_, w)| w)
+            .sum::<f32>()
+            >= 0.5
+    }
+}
+
+/// Render mode which emits the shape's raw field value at each pixel
+///
+/// Pixels near the shape's edge are evaluated exactly.  Regions which are
+/// entirely inside or outside the shape are filled with the bound of their
+/// interval which is closest to zero (i.e. the upper bound inside and the
+/// lower bound outside), which has the correct sign and is never further from
+/// zero than the true value.
+///
+/// Values are in model units (not pixels), and pixels which aren't written
+/// (e.g. because the render was cancelled) are 0.
+pub struct FieldRenderMode;
+
+impl RenderMode for FieldRenderMode {
+    type Output = f32;
+    fn interval(i: Interval, _depth: usize) -> IntervalAction<f32> {
+        if i.upper() < 0.0 {
+            IntervalAction::Fill(i.upper())
+        } else if i.lower() > 0.0 {
+            IntervalAction::Fill(i.lower())
+        } else {
+            IntervalAction::Recurse
+        }
+    }
+    fn pixel(f: f32) -> f32 {
+        f
+    }
+    fn filter(samples: &[(f32, f32)]) -> f32 {
+        samples.iter().map(|(v, w)| v * w).sum()
+    }
+}
+
+/// Anti-aliased render mode, which emits fractional pixel coverage
+///
+/// Each pixel's value is divided by the magnitude of its gradient to estimate
+/// the distance from the pixel's center to the shape's edge, which is then
+/// converted into coverage of a one-pixel-wide footprint: `u8::MAX` for pixels
+/// which are entirely inside the shape, 0 for pixels which are entirely
+/// outside, and intermediate values along the edge.
+///
+/// If the gradient is zero (or not finite), the pixel falls back to a binary
+/// inside / outside test, like [`BitRenderMode`].
+pub struct AntialiasRenderMode;
+
+impl RenderMode for AntialiasRenderMode {
+    type Output = u8;
+    const GRADIENT: bool = true;
+    fn interval(i: Interval, _depth: usize) -> IntervalAction<u8> {
+        if i.upper() < 0.0 {
+            IntervalAction::Fill(u8::MAX)
+        } else if i.lower() > 0.0 {
+            IntervalAction::Fill(0)
+        } else {
+            IntervalAction::Recurse
+        }
+    }
+    fn pixel(f: f32) -> u8 {
+        if f < 0.0 {
+            u8::MAX
+        } else {
+            0
+        }
+    }
+    fn pixel_grad(g: Grad) -> u8 {
+        let norm = g.dx.hypot(g.dy);
+        if norm > 0.0 && norm.is_finite() {
+            let coverage = (0.5 - g.v / norm).clamp(0.0, 1.0);
+            (coverage * u8::MAX as f32).round() as u8
+        } else {
+            Self::pixel(g.v)
+        }
+    }
+    fn filter(samples: &[(u8, f32)]) -> u8 {
+        let v: f32 = samples.iter().map(|(v, w)| *v as f32 * w).sum();
+        v.round() as u8
+    }
+}
+
+/// Render mode which can be used for the masks in [`render_layers`]
+///
+/// Each pixel of a layer's mask is converted into a coverage value, which
+/// scales the layer's alpha when it's composited.
+pub trait LayerRenderMode: RenderMode {
+    /// Returns the fraction of the pixel covered by the shape, in `[0, 1]`
+    fn coverage(p: Self::Output) -> f32;
+}
+
+impl LayerRenderMode for BitRenderMode {
+    fn coverage(p: bool) -> f32 {
+        if p {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl LayerRenderMode for AntialiasRenderMode {
+    fn coverage(p: u8) -> f32 {
+        p as f32 / u8::MAX as f32
+    }
 }
 
 /// Pixel-perfect render mode which mimicks many SDF demos on ShaderToy
@@ -157,6 +668,9 @@ impl RenderMode for SdfPixelRenderMode {
 
         [run(r), run(g), run(b)]
     }
+    fn filter(samples: &[([u8; 3], f32)]) -> [u8; 3] {
+        average(samples)
+    }
 }
 
 /// Fast rendering mode which mimicks many SDF demos on ShaderToy
@@ -177,6 +691,9 @@ impl RenderMode for SdfRenderMode {
     fn pixel(f: f32) -> [u8; 3] {
         SdfPixelRenderMode::pixel(f)
     }
+    fn filter(samples: &[([u8; 3], f32)]) -> [u8; 3] {
+        average(samples)
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -200,11 +717,12 @@ impl Scratch {
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Per-thread worker
-struct Worker<'a, S: Shape, M: RenderMode> {
-    config: &'a AlignedRenderConfig<2>,
+struct Worker<S: Shape, M: RenderMode> {
+    config: AlignedRenderConfig<2>,
     scratch: Scratch,
 
     eval_float_slice: S::FloatSliceEval,
+    eval_grad_slice: S::GradSliceEval,
     eval_interval: S::IntervalEval,
 
     /// Spare tape storage for reuse
@@ -216,22 +734,91 @@ struct Worker<'a, S: Shape, M: RenderMode> {
     /// Workspace for shape simplification
     workspace: S::Workspace,
 
+    /// Background compiler for float slice tapes, if enabled
+    compiler: Option<Compiler<S>>,
+
     image: Vec<M::Output>,
+
+    /// Sample offsets (in pixels) and weights within each pixel
+    samples: Vec<([f32; 2], f32)>,
+
+    /// Weighted samples for the current pixel, before filtering
+    filtered: Vec<(M::Output, f32)>,
+
+    /// Statistics for the current top-level tile
+    stats: WorkerStats,
 }
 
-impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
+impl<S: Shape, M: RenderMode> Worker<S, M> {
+    fn new(
+        config: AlignedRenderConfig<2>,
+        compiler: Option<Compiler<S>>,
+    ) -> Self {
+        let samples = config
+            .sample_filter
+            .pattern(config.samples_per_pixel.grid());
+        let scratch = Scratch::new(
+            config.tile_sizes.last().unwrap_or(&0).pow(2) * samples.len(),
+        );
+        Self {
+            scratch,
+            samples,
+            filtered: vec![],
+            image: vec![],
+            config,
+            eval_float_slice: S::FloatSliceEval::new(),
+            eval_grad_slice: S::GradSliceEval::new(),
+            eval_interval: S::IntervalEval::new(),
+            tape_storage: vec![],
+            shape_storage: vec![],
+            workspace: Default::default(),
+            compiler,
+            stats: WorkerStats::default(),
+        }
+    }
+
+    /// Renders a single top-level tile, returning its pixels
+    fn render_tile(
+        &mut self,
+        shape: &mut RenderHandle<S>,
+        tile: Tile<2>,
+    ) -> Vec<M::Output> {
+        self.image =
+            vec![M::Output::default(); self.config.tile_sizes[0].pow(2)];
+        self.render_tile_recurse(shape, 0, tile, false);
+        if let Some(sink) = &self.config.stats {
+            self.stats.flush(sink);
+        }
+        std::mem::take(&mut self.image)
+    }
+
+    /// Renders a tile, subdividing as needed
+    ///
+    /// If `settled` is true, then the tape is no longer simplified (see
+    /// [`SimplifyPolicy::Adaptive`](crate::render::SimplifyPolicy::Adaptive)).
     fn render_tile_recurse(
         &mut self,
         shape: &mut RenderHandle<S>,
         depth: usize,
         tile: Tile<2>,
+        settled: bool,
     ) {
         let tile_size = self.config.tile_sizes[depth];
+        if self.config.stats.is_some() {
+            self.stats.record(depth, shape.size());
+        }
 
         // Brute-force way to find the (interval) bounding box of the region
         let base = Point2::from(tile.corner).cast::<f32>();
-        let x = Interval::new(base.x, base.x + tile_size as f32);
-        let y = Interval::new(base.y, base.y + tile_size as f32);
+        let pad = if self.samples.len() > 1 {
+            self.config.sample_filter.radius()
+        } else if M::GRADIENT {
+            0.5
+        } else {
+            0.0
+        };
+        let x = Interval::new(base.x - pad, base.x + tile_size as f32);
+        let y = Interval::new(base.y - pad, base.y + tile_size as f32);
         let z = Interval::new(0.0, 0.0);
 
         let (i, simplify) = self
@@ -239,7 +826,7 @@ impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
             .eval(shape.i_tape(&mut self.tape_storage), x, y, z)
             .unwrap();
 
-        match M::interval(i, depth) {
+        match M::interval_tape(i, depth, shape.size()) {
             IntervalAction::Fill(fill) => {
                 for y in 0..tile_size {
                     let start = self.config.tile_to_offset(tile, 0, y);
@@ -253,24 +840,60 @@ impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
                 let zs = [0.0; 4];
                 let vs = self
                     .eval_float_slice
-                    .eval(shape.f_tape(&mut self.tape_storage), &xs, &ys, &zs)
+                    .eval(
+                        shape.f_tape_background(
+                            &mut self.tape_storage,
+                            self.compiler.as_ref(),
+                        ),
+                        &xs,
+                        &ys,
+                        &zs,
+                    )
                     .unwrap();
                 // Bilinear interpolation on a per-pixel basis
+                let tape_len = shape.size();
                 for y in 0..tile_size {
                     // Y interpolation
                     let y_frac = (y as f32 - 1.0) / (tile_size as f32);
                     let v0 = vs[0] * (1.0 - y_frac) + vs[1] * y_frac;
                     let v1 = vs[2] * (1.0 - y_frac) + vs[3] * y_frac;
 
-                    let mut i = self.config.tile_to_offset(tile, 0, y);
+                    let i = self.config.tile_to_offset(tile, 0, y);
                     for x in 0..tile_size {
                         // X interpolation
                         let x_frac = (x as f32 - 1.0) / (tile_size as f32);
                         let v = v0 * (1.0 - x_frac) + v1 * x_frac;
 
                         // Write out the pixel
-                        self.image[i] = M::pixel(v);
-                        i += 1;
+                        let grad = M::GRADIENT.then(|| {
+                            let dy = (vs[1] - vs[0]) * (1.0 - x_frac)
+                                + (vs[3] - vs[2]) * x_frac;
+                            Grad::new(
+                                v,
+                                (v1 - v0) / tile_size as f32,
+                                dy / tile_size as f32,
+                                0.0,
+                            )
+                        });
+                        self.image[i + x] = M::sample(&mut Sample {
+                            value: v,
+                            tape_len,
+                            pixel: Point2::new(
+                                (tile.corner[0] + x) as f32,
+                                (tile.corner[1] + y) as f32,
+                            ),
+                            mat: &self.config.mat,
+                            grad,
+                            eval_grad: &mut |p| {
+                                eval_pixel_grad(
+                                    &mut self.eval_grad_slice,
+                                    shape,
+                                    &mut self.tape_storage,
+                                    &self.config.mat,
+                                    p,
+                                )
+                            },
+                        });
                     }
                 }
                 return;
@@ -278,8 +901,13 @@ impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
             IntervalAction::Recurse => (), // keep going
         }
 
-        let sub_tape = if let Some(trace) = simplify.as_ref() {
-            shape.simplify(
+        let before = shape.size();
+        let sub_tape = if let Some(trace) = simplify.as_ref().filter(|_| {
+            !settled
+                && self.config.simplify_threshold.should_simplify(&shape.shape)
+        }) {
+            shape.simplify_region(
+                [depth, tile.corner[0], tile.corner[1], 0],
                 trace,
                 &mut self.workspace,
                 &mut self.shape_storage,
@@ -289,7 +917,9 @@ impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
             shape
         };
 
-        if let Some(next_tile_size) = self.config.tile_sizes.get(depth + 1) {
+        if let Some(&next_tile_size) = self.config.tile_sizes.get(depth + 1) {
+            let settled = settled
+                || self.config.simplify.is_settled(before, sub_tape.size());
             let n = tile_size / next_tile_size;
             for j in 0..n {
                 for i in 0..n {
@@ -300,6 +930,7 @@ impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
                             tile.corner[0] + i * next_tile_size,
                             tile.corner[1] + j * next_tile_size,
                         ]),
+                        settled,
                     );
                 }
             }
@@ -314,19 +945,63 @@ impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
         tile_size: usize,
         tile: Tile<2>,
     ) {
+        // Samples for each pixel are stored together
+        let tape_len = shape.size();
         let mut index = 0;
         for j in 0..tile_size {
             for i in 0..tile_size {
-                self.scratch.x[index] = (tile.corner[0] + i) as f32;
-                self.scratch.y[index] = (tile.corner[1] + j) as f32;
-                index += 1;
+                for ([dx, dy], _) in &self.samples {
+                    self.scratch.x[index] = (tile.corner[0] + i) as f32 + dx;
+                    self.scratch.y[index] = (tile.corner[1] + j) as f32 + dy;
+                    index += 1;
+                }
+            }
+        }
+
+        if M::GRADIENT {
+            let out = self
+                .eval_grad_slice
+                .eval(
+                    shape.g_tape(&mut self.tape_storage),
+                    &self.scratch.x,
+                    &self.scratch.y,
+                    &self.scratch.z,
+                )
+                .unwrap();
+            let mut index = 0;
+            for j in 0..tile_size {
+                let o = self.config.tile_to_offset(tile, 0, j);
+                for i in 0..tile_size {
+                    self.filtered.clear();
+                    for (_, weight) in &self.samples {
+                        let g = to_pixel_grad(&self.config.mat, out[index]);
+                        let v = M::sample(&mut Sample {
+                            value: g.v,
+                            tape_len,
+                            pixel: Point2::new(
+                                self.scratch.x[index],
+                                self.scratch.y[index],
+                            ),
+                            mat: &self.config.mat,
+                            grad: Some(g),
+                            eval_grad: &mut |_| unreachable!(),
+                        });
+                        self.filtered.push((v, *weight));
+                        index += 1;
+                    }
+                    self.image[o + i] = resolve::<M>(&self.filtered);
+                }
             }
+            return;
         }
 
         let out = self
             .eval_float_slice
             .eval(
-                shape.f_tape(&mut self.tape_storage),
+                shape.f_tape_background(
+                    &mut self.tape_storage,
+                    self.compiler.as_ref(),
+                ),
                 &self.scratch.x,
                 &self.scratch.y,
                 &self.scratch.z,
@@ -337,40 +1012,97 @@ impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
         for j in 0..tile_size {
             let o = self.config.tile_to_offset(tile, 0, j);
             for i in 0..tile_size {
-                self.image[o + i] = M::pixel(out[index]);
-                index += 1;
+                self.filtered.clear();
+                for (_, weight) in &self.samples {
+                    let v = M::sample(&mut Sample {
+                        value: out[index],
+                        tape_len,
+                        pixel: Point2::new(
+                            self.scratch.x[index],
+                            self.scratch.y[index],
+                        ),
+                        mat: &self.config.mat,
+                        grad: None,
+                        eval_grad: &mut |p| {
+                            eval_pixel_grad(
+                                &mut self.eval_grad_slice,
+                                shape,
+                                &mut self.tape_storage,
+                                &self.config.mat,
+                                p,
+                            )
+                        },
+                    });
+                    self.filtered.push((v, *weight));
+                    index += 1;
+                }
+                self.image[o + i] = resolve::<M>(&self.filtered);
             }
         }
     }
 }
 
+/// Combines the samples for a single pixel, skipping the filter if there's
+/// only one sample
+fn resolve<M: RenderMode>(samples: &[(M::Output, f32)]) -> M::Output {
+    match samples {
+        [(v, _)] => *v,
+        _ => M::filter(samples),
+    }
+}
+
+/// Evaluates the shape's partial derivatives at a single pixel, with respect
+/// to pixel coordinates
+fn eval_pixel_grad<S: Shape>(
+    eval: &mut S::GradSliceEval,
+    shape: &mut RenderHandle<S>,
+    storage: &mut Vec<S::TapeStorage>,
+    mat: &Matrix3<f32>,
+    p: Point2<f32>,
+) -> Grad {
+    let tape = shape.g_tape(storage);
+    let g = eval.eval(tape, &[p.x], &[p.y], &[0.0]).unwrap()[0];
+    to_pixel_grad(mat, g)
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 fn worker<S: Shape, M: RenderMode>(
     mut shape: RenderHandle<S>,
     queue: &Queue<2>,
     config: &AlignedRenderConfig<2>,
-) -> Vec<(Tile<2>, Vec<M::Output>)> {
-    let mut out = vec![];
-    let scratch = Scratch::new(config.tile_sizes.last().unwrap_or(&0).pow(2));
-
-    let mut w: Worker<S, M> = Worker {
-        scratch,
-        image: vec![],
-        config,
-        eval_float_slice: S::FloatSliceEval::new(),
-        eval_interval: S::IntervalEval::new(),
-        tape_storage: vec![],
-        shape_storage: vec![],
-        workspace: Default::default(),
-    };
+    compiler: Option<Compiler<S>>,
+    emit: &Emit<M::Output>,
+) {
+    let mut w: Worker<S, M> = Worker::new(config.clone(), compiler);
+    while let Some(tile) = queue.next() {
+        if crate::exec::checkpoint().is_err() {
+            break;
+        }
+        let pixels = w.render_tile(&mut shape, tile);
+        emit(tile, &pixels);
+        config.tile_done();
+    }
+}
+
+/// Per-thread worker for a warm-started render, with one handle per tile
+fn worker_cached<S: Shape, M: RenderMode>(
+    handles: &HashMap<[usize; 2], Mutex<RenderHandle<S>>>,
+    queue: &Queue<2>,
+    config: &AlignedRenderConfig<2>,
+    compiler: Option<Compiler<S>>,
+    emit: &Emit<M::Output>,
+) {
+    let mut w: Worker<S, M> = Worker::new(config.clone(), compiler);
     while let Some(tile) = queue.next() {
-        w.image = vec![M::Output::default(); config.tile_sizes[0].pow(2)];
-        w.render_tile_recurse(&mut shape, 0, tile);
-        let pixels = std::mem::take(&mut w.image);
-        out.push((tile, pixels))
+        if crate::exec::checkpoint().is_err() {
+            break;
+        }
+        let mut shape = handles[&tile.corner].lock().unwrap();
+        let pixels = w.render_tile(&mut shape, tile);
+        emit(tile, &pixels);
+        config.tile_done();
     }
-    out
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -384,90 +1116,593 @@ fn worker<S: Shape, M: RenderMode>(
 /// This function is parameterized by both shape type (which determines how we
 /// perform evaluation) and render mode (which tells us how to color in the
 /// resulting pixels).
+///
+/// Returns an error if the configuration is invalid (see
+/// [`RenderConfig::check`]), or [`Error::Cancelled`] if this is run as part of
+/// a [job](crate::exec::Executor::run) which is cancelled.
 pub fn render<S: Shape, M: RenderMode + Sync>(
     shape: S,
     config: &RenderConfig<2>,
-) -> Vec<M::Output> {
+) -> Result<Vec<M::Output>, Error> {
+    config.check()?;
+    crate::exec::cancellable(|| {
+        let (shape, config) = prepare(shape, config);
+        collect_image(&config, |emit| {
+            render_inner::<_, M>(shape, &config, emit)
+        })
+    })
+}
+
+/// Renders the given tape into a 2D image at Z = 0, streaming out tiles
+///
+/// This is equivalent to [`render`], but calls `on_tile` with each top-level
+/// tile as it's finished, instead of assembling a full image.  In a
+/// multithreaded render, `on_tile` is called from the render threads, and
+/// tiles are finished in no particular order.
+///
+/// Returns an error under the same conditions as [`render`].
+pub fn render_streaming<S: Shape, M: RenderMode + Sync>(
+    shape: S,
+    config: &RenderConfig<2>,
+    on_tile: impl Fn(TileImage<M::Output>) + Sync,
+) -> Result<(), Error> {
+    config.check()?;
+    crate::exec::cancellable(|| {
+        let (shape, config) = prepare(shape, config);
+        render_inner::<_, M>(shape, &config, &|tile, data| {
+            on_tile(TileImage::new(&config, tile, data))
+        })
+    })
+}
+
+/// Aligns the config and applies its transform to the shape
+fn prepare<S: Shape>(
+    shape: S,
+    config: &RenderConfig<2>,
+) -> (S::TransformedShape, AlignedRenderConfig<2>) {
     let (config, mat) = config.align();
-    assert!(config.image_size % config.tile_sizes[0] == 0);
+    for s in config.image_size {
+        assert!(s % config.tile_sizes[0] == 0);
+    }
     for i in 0..config.tile_sizes.len() - 1 {
         assert!(config.tile_sizes[i] % config.tile_sizes[i + 1] == 0);
     }
 
+    // The transform always maps to Z = 0, so we can specialize the shape
+    let shape = shape.plan_2d(0.0).unwrap_or(shape);
+
     // Convert to a 4x4 matrix and apply to the shape
     let mat = mat.insert_row(2, 0.0);
     let mat = mat.insert_column(2, 0.0);
-    let shape = shape.apply_transform(mat);
-
-    render_inner::<_, M>(shape, config)
+    (shape.apply_transform(mat), config)
 }
 
-fn render_inner<S: Shape, M: RenderMode + Sync>(
-    shape: S,
-    config: AlignedRenderConfig<2>,
-) -> Vec<M::Output> {
+/// Returns the list of top-level tiles for the given config
+fn tiles(config: &AlignedRenderConfig<2>) -> Vec<Tile<2>> {
     let mut tiles = vec![];
-    for i in 0..config.image_size / config.tile_sizes[0] {
-        for j in 0..config.image_size / config.tile_sizes[0] {
+    for i in 0..config.image_size[0] / config.tile_sizes[0] {
+        for j in 0..config.image_size[1] / config.tile_sizes[0] {
             tiles.push(config.new_tile([
                 i * config.tile_sizes[0],
                 j * config.tile_sizes[0],
             ]));
         }
     }
+    tiles
+}
 
-    let queue = Queue::new(tiles);
-    let threads = config.threads();
+/// A finished top-level tile, passed to the callback in [`render_streaming`]
+///
+/// Tiles which overlap the edge of the image are clipped, so every pixel is
+/// within the image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileImage<T> {
+    /// Column of the tile's left edge in the image
+    pub x: usize,
+    /// Row of the tile's top edge in the image (counting down from the top)
+    pub y: usize,
+    /// Width of the tile, in pixels
+    pub width: usize,
+    /// Height of the tile, in pixels
+    pub height: usize,
+    /// Pixels in the same order as a full image (row-major, top row first)
+    pub pixels: Vec<T>,
+}
 
+impl<T: Copy> TileImage<T> {
+    /// Clips and flips a tile's data to match the output image
+    fn new(config: &AlignedRenderConfig<2>, tile: Tile<2>, data: &[T]) -> Self {
+        let size = config.tile_sizes[0];
+        let [image_width, image_height] = config.orig_image_size;
+        let width = size.min(image_width - tile.corner[0]);
+        let height = size.min(image_height - tile.corner[1]);
+        let mut pixels = Vec::with_capacity(width * height);
+        for j in (0..height).rev() {
+            pixels.extend_from_slice(&data[j * size..][..width]);
+        }
+        Self {
+            x: tile.corner[0],
+            y: image_height - tile.corner[1] - height,
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Copies a rendered top-level tile into the (unpadded) output image
+fn blit<T: Copy>(
+    image: &mut [T],
+    config: &AlignedRenderConfig<2>,
+    tile: Tile<2>,
+    data: &[T],
+) {
+    let mut index = 0;
+    for j in 0..config.tile_sizes[0] {
+        let y = j + tile.corner[1];
+        for i in 0..config.tile_sizes[0] {
+            let x = i + tile.corner[0];
+            let [width, height] = config.orig_image_size;
+            if y < height && x < width {
+                let o = (height - y - 1) * width + x;
+                image[o] = data[index];
+            }
+            index += 1;
+        }
+    }
+}
+
+fn render_inner<S: Shape, M: RenderMode + Sync>(
+    shape: S,
+    config: &AlignedRenderConfig<2>,
+    emit: &Emit<M::Output>,
+) {
     let mut rh = RenderHandle::new(shape);
     let _ = rh.i_tape(&mut vec![]); // populate i_tape before cloning
+    with_compiler(config.compile_threads(), |compiler| {
+        if compiler.is_some() {
+            // Simplified shapes are evaluated with this tape until their own
+            // tapes are built in the background
+            let _ = rh.f_tape(&mut vec![]);
+        }
+        run_workers(config, || {
+            let rh = rh.clone();
+            let compiler = compiler.cloned();
+            |queue| worker::<S, M>(rh, queue, config, compiler, emit)
+        })
+    })
+}
+
+/// Callback which receives each top-level tile as it's finished
+type Emit<'a, T> = dyn Fn(Tile<2>, &[T]) + Sync + 'a;
+
+/// Runs workers on as many threads as requested
+///
+/// `make_work` is called once per thread to build that thread's worker.
+fn run_workers<F, W>(config: &AlignedRenderConfig<2>, mut make_work: F)
+where
+    F: FnMut() -> W,
+    W: FnOnce(&Queue<2>) + Send,
+{
+    let queue = Queue::new(tiles(config));
+    let threads = config.threads();
 
-    let out: Vec<_> = if threads == 1 {
-        worker::<S, M>(rh, &queue, &config).into_iter().collect()
+    if threads == 1 {
+        make_work()(&queue)
     } else {
         #[cfg(target_arch = "wasm32")]
         unreachable!("multithreaded rendering is not supported on wasm32");
 
         #[cfg(not(target_arch = "wasm32"))]
         std::thread::scope(|s| {
-            let mut handles = vec![];
+            // Don't hold a slot while waiting on the workers
+            crate::exec::idle();
             for _ in 0..threads {
-                let rh = rh.clone();
-                handles.push(s.spawn(|| worker::<S, M>(rh, &queue, &config)));
+                let work = make_work();
+                let job = crate::exec::current();
+                s.spawn(|| {
+                    let _guard = crate::exec::enter(job);
+                    work(&queue)
+                });
             }
-            let mut out = vec![];
-            for h in handles {
-                out.extend(h.join().unwrap().into_iter());
-            }
-            out
         })
+    }
+}
+
+/// Assembles an image from the tiles passed to the callback in `f`
+fn collect_image<T: Default + Copy + Send>(
+    config: &AlignedRenderConfig<2>,
+    f: impl FnOnce(&Emit<T>),
+) -> Vec<T> {
+    let image = vec![T::default(); config.orig_image_size.iter().product()];
+    let image = Mutex::new(image);
+    f(&|tile, data| blit(&mut image.lock().unwrap(), config, tile, data));
+    image.into_inner().unwrap()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A single shape in a stack of layers, for [`render_layers`]
+pub struct Layer<S> {
+    /// Shape to render
+    pub shape: S,
+
+    /// Color, with straight (non-premultiplied) alpha
+    pub color: [u8; 4],
+
+    /// How the layer's color is combined with the layers beneath it
+    pub blend: BlendMode,
+}
+
+impl<S> Layer<S> {
+    /// Builds a layer with [`BlendMode::Normal`] blending
+    pub fn new(shape: S, color: [u8; 4]) -> Self {
+        Self {
+            shape,
+            color,
+            blend: BlendMode::Normal,
+        }
+    }
+}
+
+/// Blend mode for a [`Layer`]
+///
+/// These are the separable blend modes from the
+/// [W3C compositing spec](https://www.w3.org/TR/compositing-1/#blending):
+/// wherever a layer is filled, its color is mixed with the color beneath it
+/// using the blend function, then composited with the "over" operator (so a
+/// translucent layer only partially applies its blend).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The layer's color replaces the color beneath it
+    #[default]
+    Normal,
+    /// Colors are multiplied, which darkens the result
+    Multiply,
+    /// Inverted colors are multiplied, which lightens the result
+    Screen,
+    /// Colors are added, saturating at full brightness
+    Add,
+}
+
+impl BlendMode {
+    /// Blends a pair of color channels (source on top of destination)
+    fn blend(&self, src: f32, dst: f32) -> f32 {
+        match self {
+            BlendMode::Normal => src,
+            BlendMode::Multiply => src * dst,
+            BlendMode::Screen => src + dst - src * dst,
+            BlendMode::Add => (src + dst).min(1.0),
+        }
+    }
+
+    /// Composites a straight-alpha color onto a premultiplied pixel
+    fn apply(&self, acc: &mut [f32; 4], color: [f32; 4]) {
+        let (sa, da) = (color[3], acc[3]);
+        for i in 0..3 {
+            let dst = if da > 0.0 { acc[i] / da } else { 0.0 };
+            acc[i] = (1.0 - da) * sa * color[i]
+                + sa * da * self.blend(color[i], dst)
+                + (1.0 - sa) * acc[i];
+        }
+        acc[3] = sa + da * (1.0 - sa);
+    }
+}
+
+/// Renders a stack of shapes into a single RGBA image
+///
+/// `layers` are ordered from bottom to top, and each has a color with straight
+/// (non-premultiplied) alpha and a [`BlendMode`].  Wherever a shape is filled,
+/// its color is blended with the layers beneath it; the result uses straight
+/// alpha against a transparent background.
+///
+/// Every layer uses the same transform, which is built from `config.bounds` and
+/// `config.view` (as in [`render`]).
+///
+/// Each layer's mask is rendered with the mode `M`:
+/// [`BitRenderMode`] gives hard edges, and [`AntialiasRenderMode`] gives
+/// smooth edges, by scaling each layer's alpha by its coverage of the pixel.
+///
+/// Layers are rendered together, one tile at a time, from top to bottom: once
+/// every pixel in a tile is fully covered by an opaque layer with
+/// [`BlendMode::Normal`], the layers beneath it are skipped.
+///
+/// Returns an error under the same conditions as [`render`].
+pub fn render_layers<S: Shape, M: LayerRenderMode + Sync>(
+    layers: &[Layer<S>],
+    config: &RenderConfig<2>,
+) -> Result<Vec<[u8; 4]>, Error> {
+    config.check()?;
+    crate::exec::cancellable(|| render_layers_inner::<S, M>(layers, config))
+}
+
+fn render_layers_inner<S: Shape, M: LayerRenderMode + Sync>(
+    layers: &[Layer<S>],
+    config: &RenderConfig<2>,
+) -> Vec<[u8; 4]> {
+    let mut aligned = None;
+    let mut handles = vec![];
+    for layer in layers {
+        let (shape, config) = prepare(layer.shape.clone(), config);
+        let mut rh = RenderHandle::new(shape);
+        let _ = rh.i_tape(&mut vec![]); // populate i_tape before cloning
+        let color = layer.color.map(|c| c as f32 / 255.0);
+        handles.push((rh, color, layer.blend));
+        aligned = Some(config);
+    }
+    let Some(config) = aligned else {
+        let size = config.image_size;
+        return vec![[0; 4]; size.width * size.height];
     };
+    with_compiler(config.compile_threads(), |compiler| {
+        if compiler.is_some() {
+            for (rh, ..) in &mut handles {
+                let _ = rh.f_tape(&mut vec![]);
+            }
+        }
+        collect_image(&config, |emit| {
+            run_workers(&config, || {
+                let layers = handles.clone();
+                let compiler = compiler.cloned();
+                |queue| {
+                    worker_layers::<_, M>(
+                        layers, queue, &config, compiler, emit,
+                    )
+                }
+            })
+        })
+    })
+}
 
-    let mut image = vec![M::Output::default(); config.orig_image_size.pow(2)];
-    for (tile, data) in out.iter() {
-        let mut index = 0;
-        for j in 0..config.tile_sizes[0] {
-            let y = j + tile.corner[1];
-            for i in 0..config.tile_sizes[0] {
-                let x = i + tile.corner[0];
-                if y < config.orig_image_size && x < config.orig_image_size {
-                    let o = (config.orig_image_size - y - 1)
-                        * config.orig_image_size
-                        + x;
-                    image[o] = data[index];
+/// Per-thread worker for [`render_layers`]
+fn worker_layers<S: Shape, M: LayerRenderMode>(
+    mut layers: Vec<(RenderHandle<S>, [f32; 4], BlendMode)>,
+    queue: &Queue<2>,
+    config: &AlignedRenderConfig<2>,
+    compiler: Option<Compiler<S>>,
+    emit: &Emit<[u8; 4]>,
+) {
+    let mut w: Worker<S, M> = Worker::new(config.clone(), compiler);
+    while let Some(tile) = queue.next() {
+        if crate::exec::checkpoint().is_err() {
+            break;
+        }
+        // Render masks from top to bottom, until every pixel is hidden
+        let mut hidden = vec![false; config.tile_sizes[0].pow(2)];
+        let mut masks = vec![];
+        for (i, (shape, color, blend)) in layers.iter_mut().enumerate().rev() {
+            if hidden.iter().all(|h| *h) {
+                break;
+            }
+            let coverage: Vec<f32> = w
+                .render_tile(shape, tile)
+                .into_iter()
+                .map(M::coverage)
+                .collect();
+            if *blend == BlendMode::Normal && color[3] >= 1.0 {
+                for (h, c) in hidden.iter_mut().zip(&coverage) {
+                    *h |= *c >= 1.0;
                 }
-                index += 1;
             }
+            masks.push((i, coverage));
         }
+
+        // Composite the visible layers from bottom to top, accumulating
+        // premultiplied color
+        let mut acc = vec![[0f32; 4]; hidden.len()];
+        for (i, coverage) in masks.into_iter().rev() {
+            let (_, color, blend) = &layers[i];
+            for (a, c) in acc.iter_mut().zip(coverage) {
+                if c > 0.0 {
+                    let [r, g, b, alpha] = *color;
+                    blend.apply(a, [r, g, b, alpha * c]);
+                }
+            }
+        }
+        let pixels = acc
+            .into_iter()
+            .map(|a| {
+                if a[3] == 0.0 {
+                    [0; 4]
+                } else {
+                    let f = |v: f32| (v * 255.0).round() as u8;
+                    [f(a[0] / a[3]), f(a[1] / a[3]), f(a[2] / a[3]), f(a[3])]
+                }
+            })
+            .collect::<Vec<_>>();
+        emit(tile, &pixels);
+        config.tile_done();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Persistent state for warm-started 2D rendering
+///
+/// A `RenderCache` owns a shape, and remembers the choices made in every
+/// region of the image (along with the resulting simplified shapes and their
+/// tapes) from one render to the next.  When rendering again with
+/// [`RenderConfig::run_cached`], each region's new trace is compared against
+/// the previous frame's trace; wherever they match, the previous
+/// simplification is reused instead of simplifying from scratch (and, for the
+/// JIT, recompiling).
+///
+/// This pays off when re-rendering the same shape with the same bounds and
+/// image size, e.g. when switching render modes or re-running after a change
+/// that turned out not to affect the model.  Changing the bounds, image size,
+/// or tile sizes starts over from a cold cache; changing the shape requires
+/// [`set_shape`](Self::set_shape).
+pub struct RenderCache<S: Shape> {
+    shape: S,
+
+    /// Aligned configuration and transform used to build `handles`
+    key: Option<(AlignedRenderConfig<2>, Matrix3<f32>)>,
+
+    /// Warm-started handles, one per top-level tile
+    handles: HashMap<[usize; 2], Mutex<RenderHandle<S::TransformedShape>>>,
+}
+
+impl<S: Shape> RenderCache<S> {
+    /// Builds a new (cold) cache for the given shape
+    pub fn new(shape: S) -> Self {
+        Self {
+            shape,
+            key: None,
+            handles: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached shape
+    pub fn shape(&self) -> &S {
+        &self.shape
+    }
+
+    /// Replaces the shape, discarding all cached simplifications
+    pub fn set_shape(&mut self, shape: S) {
+        self.shape = shape;
+        self.clear();
+    }
+
+    /// Discards all cached simplifications
+    pub fn clear(&mut self) {
+        self.key = None;
+        self.handles.clear();
+    }
+
+    pub(crate) fn render<M: RenderMode + Sync>(
+        &mut self,
+        config: &RenderConfig<2>,
+    ) -> Vec<M::Output> {
+        let (mut aligned, mat) = config.align();
+        // The statistics sink and progress callback don't affect the render,
+        // so they're not part of the cache key (but are used by this render's
+        // workers)
+        let stats = aligned.stats.take();
+        let progress = aligned.progress.take();
+        let key = Some((aligned, mat));
+        if self.key != key {
+            let (shape, config) = prepare(self.shape.clone(), config);
+            let mut rh = RenderHandle::new(shape);
+            let _ = rh.i_tape(&mut vec![]); // populate i_tape before cloning
+            if config.compile_threads() > 0 {
+                let _ = rh.f_tape(&mut vec![]);
+            }
+            rh.set_warm_start(true, &mut vec![], &mut vec![]);
+            self.handles = tiles(&config)
+                .into_iter()
+                .map(|t| (t.corner, Mutex::new(rh.clone())))
+                .collect();
+            self.key = key;
+        }
+        let config = &AlignedRenderConfig {
+            stats,
+            progress,
+            ..self.key.as_ref().unwrap().0.clone()
+        };
+        let handles = &self.handles;
+        with_compiler(config.compile_threads(), |compiler| {
+            collect_image(config, |emit| {
+                run_workers(config, || {
+                    let compiler = compiler.cloned();
+                    |queue| {
+                        worker_cached::<_, M>(
+                            handles, queue, config, compiler, emit,
+                        )
+                    }
+                })
+            })
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Single-threaded 2D render which runs in small, budgeted steps
+///
+/// This is built with [`RenderConfig::start`], then driven by repeated calls
+/// to [`step`](Self::step) (e.g. once per animation frame), so that a target
+/// without threads (such as WebAssembly in the browser) can render without
+/// blocking for the full duration of the render.
+///
+/// Tiles are rendered in the same order as a full render, and each finished
+/// tile is copied into the (partial) image immediately; pixels in unrendered
+/// tiles have their default value.
+pub struct IncrementalRender<S: Shape, M: RenderMode> {
+    shape: RenderHandle<S::TransformedShape>,
+    worker: Worker<S::TransformedShape, M>,
+    tiles: Vec<Tile<2>>,
+    next: usize,
+    image: Vec<M::Output>,
+}
+
+impl<S: Shape, M: RenderMode> IncrementalRender<S, M> {
+    pub(crate) fn new(shape: S, config: &RenderConfig<2>) -> Self {
+        let (shape, config) = prepare(shape, config);
+        Self {
+            shape: RenderHandle::new(shape),
+            tiles: tiles(&config),
+            next: 0,
+            image: vec![
+                M::Output::default();
+                config.orig_image_size.iter().product()
+            ],
+            worker: Worker::new(config, None),
+        }
+    }
+
+    /// Renders up to `budget` top-level tiles
+    ///
+    /// Returns `true` if the render is complete.
+    pub fn step(&mut self, budget: usize) -> bool {
+        for _ in 0..budget {
+            let Some(&tile) = self.tiles.get(self.next) else {
+                break;
+            };
+            let pixels = self.worker.render_tile(&mut self.shape, tile);
+            blit(&mut self.image, &self.worker.config, tile, &pixels);
+            self.worker.config.tile_done();
+            self.next += 1;
+        }
+        self.is_done()
+    }
+
+    /// Checks whether every tile has been rendered
+    pub fn is_done(&self) -> bool {
+        self.next >= self.tiles.len()
+    }
+
+    /// Returns the number of top-level tiles which have been rendered
+    pub fn tiles_done(&self) -> usize {
+        self.next
+    }
+
+    /// Returns the total number of top-level tiles in the image
+    pub fn tiles_total(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Returns the (possibly partial) image, in the same layout as
+    /// [`RenderConfig::run`]
+    pub fn image(&self) -> &[M::Output] {
+        &self.image
+    }
+
+    /// Unwraps the (possibly partial) image
+    pub fn into_image(self) -> Vec<M::Output> {
+        self.image
     }
-    image
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
-        eval::{MathShape, Shape},
+        context::Tree,
+        eval::{MathShape, Shape, SimplifyThreshold},
+        render::{
+            ImageSize, RenderStats, SampleFilter, SamplesPerPixel,
+            SimplifyPolicy, TapeHistogram,
+        },
         shape::Bounds,
         vm::{GenericVmShape, VmShape},
         Context,
@@ -486,8 +1721,8 @@ mod test {
         bounds: Bounds<2>,
     ) {
         let cfg = RenderConfig::<2> {
-            image_size: 32,
-            bounds,
+            image_size: 32.into(),
+            bounds: Some(bounds),
             ..RenderConfig::default()
         };
         let out = cfg.run::<_, BitRenderMode>(shape).unwrap();
@@ -510,7 +1745,7 @@ mod test {
     }
 
     fn render_and_compare<S: Shape>(shape: S, expected: &'static str) {
-        render_and_compare_with_bounds(shape, expected, Bounds::default())
+        render_and_compare_with_bounds(shape, expected, Bounds::centered(1.0))
     }
 
     fn check_hi<S: Shape + MathShape>() {
@@ -743,4 +1978,576 @@ mod test {
     fn render_quarter_jit() {
         check_quarter::<crate::jit::JitShape>();
     }
+
+    #[test]
+    fn render_incremental() {
+        let (ctx, root) = Context::from_text(HI.as_bytes()).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let cfg = RenderConfig::<2> {
+            image_size: 100.into(), // padded to 128, with a 4x4 grid of tiles
+            tile_sizes: vec![32, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let expected = cfg.run::<_, BitRenderMode>(shape.clone()).unwrap();
+
+        let mut r = cfg.start::<_, BitRenderMode>(shape).unwrap();
+        assert_eq!(r.tiles_total(), 16);
+        assert!(!r.is_done());
+        assert!(!r.step(5));
+        assert_eq!(r.tiles_done(), 5);
+        assert_ne!(r.image(), expected.as_slice());
+        assert!(!r.step(10));
+        assert!(r.step(10));
+        assert_eq!(r.tiles_done(), 16);
+        assert!(r.step(1));
+        assert_eq!(r.into_image(), expected);
+    }
+
+    #[test]
+    fn render_cached() {
+        let (ctx, root) = Context::from_text(HI.as_bytes()).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let mut cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let expected = cfg.run::<_, BitRenderMode>(shape.clone()).unwrap();
+
+        let mut cache = RenderCache::new(shape.clone());
+        for _ in 0..2 {
+            let out = cfg.run_cached::<_, BitRenderMode>(&mut cache).unwrap();
+            assert_eq!(out, expected);
+        }
+
+        // Changing the render mode reuses simplifications
+        let out = cfg.run_cached::<_, DebugRenderMode>(&mut cache).unwrap();
+        assert!(out.iter().zip(&expected).all(|(a, b)| a.is_filled() == *b));
+
+        // Changing the bounds starts over
+        cfg.bounds = Some(Bounds {
+            center: nalgebra::Vector2::new(0.5, 0.5),
+            size: 0.5,
+        });
+        let expected = cfg.run::<_, BitRenderMode>(shape).unwrap();
+        let out = cfg.run_cached::<_, BitRenderMode>(&mut cache).unwrap();
+        assert_eq!(out, expected);
+    }
+
+    fn check_compile_threads<S: Shape + MathShape>() {
+        let (ctx, root) = Context::from_text(HI.as_bytes()).unwrap();
+        let shape = S::new(&ctx, root).unwrap();
+        let mut cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 16, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let expected = cfg.run::<_, SdfRenderMode>(shape.clone()).unwrap();
+
+        // Background compilation must not change the image, no matter which
+        // tapes are ready in time
+        cfg.compile_threads = 2;
+        let out = cfg.run::<_, SdfRenderMode>(shape.clone()).unwrap();
+        assert_eq!(out, expected);
+
+        let mut cache = RenderCache::new(shape);
+        for _ in 0..2 {
+            let out = cfg.run_cached::<_, SdfRenderMode>(&mut cache).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn render_compile_threads_vm() {
+        check_compile_threads::<VmShape>();
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn render_compile_threads_jit() {
+        check_compile_threads::<crate::jit::JitShape>();
+    }
+
+    #[test]
+    fn render_antialias() {
+        let (x, y, _) = Tree::axes();
+        let shape =
+            VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+        let cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 16, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let bits = cfg.run::<_, BitRenderMode>(shape.clone()).unwrap();
+        let out = cfg.run::<_, AntialiasRenderMode>(shape).unwrap();
+
+        // Pixels away from the edge match the binary render, and edge pixels
+        // have fractional coverage
+        let mut partial = 0;
+        for (&a, &b) in out.iter().zip(&bits) {
+            match a {
+                0 => assert!(!b),
+                u8::MAX => assert!(b),
+                _ => partial += 1,
+            }
+        }
+        assert!(partial > 64);
+
+        // Total coverage matches the circle's area (radius = 16 pixels)
+        let area: f32 = out.iter().map(|&a| a as f32 / 255.0).sum();
+        let expected = std::f32::consts::PI * 16.0f32.powi(2);
+        assert!((area - expected).abs() < 2.0, "{area} != {expected}");
+    }
+
+    #[test]
+    fn render_sample() {
+        /// Records each sample's position and model-space derivatives
+        struct SampleMode;
+        impl RenderMode for SampleMode {
+            type Output = [f32; 5];
+            fn interval(
+                _i: Interval,
+                _depth: usize,
+            ) -> IntervalAction<[f32; 5]> {
+                IntervalAction::Recurse
+            }
+            fn pixel(_f: f32) -> [f32; 5] {
+                unreachable!()
+            }
+            fn sample(s: &mut Sample) -> [f32; 5] {
+                let p = s.position();
+                let g = s.grad();
+                [s.value(), p.x, p.y, g.dx, g.dy]
+            }
+        }
+
+        // A linear shape under a rotated, scaled view, so that pixel and
+        // model coordinates are quite different
+        let (x, y, _) = Tree::axes();
+        let shape = VmShape::from_tree(&(x * 3.0 - y * 2.0 + 1.0));
+        let mut cfg = RenderConfig::<2> {
+            image_size: ImageSize::new(40, 24),
+            tile_sizes: vec![16, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        cfg.set_pixel_transform(
+            Matrix3::new_rotation(0.3).append_scaling(0.05),
+        )
+        .unwrap();
+        let out = cfg.run::<_, SampleMode>(shape).unwrap();
+        for [v, px, py, dx, dy] in out {
+            assert!((v - (px * 3.0 - py * 2.0 + 1.0)).abs() < 1e-4);
+            assert!((dx - 3.0).abs() < 1e-4, "{dx}");
+            assert!((dy + 2.0).abs() < 1e-4, "{dy}");
+        }
+    }
+
+    #[test]
+    fn render_supersample() {
+        /// Binary coverage of each sample, averaged when supersampling
+        struct CoverageMode;
+        impl RenderMode for CoverageMode {
+            type Output = u8;
+            fn interval(i: Interval, _depth: usize) -> IntervalAction<u8> {
+                if i.upper() < 0.0 {
+                    IntervalAction::Fill(255)
+                } else if i.lower() > 0.0 {
+                    IntervalAction::Fill(0)
+                } else {
+                    IntervalAction::Recurse
+                }
+            }
+            fn pixel(f: f32) -> u8 {
+                if f < 0.0 {
+                    255
+                } else {
+                    0
+                }
+            }
+            fn filter(samples: &[(u8, f32)]) -> u8 {
+                AntialiasRenderMode::filter(samples)
+            }
+        }
+
+        let (x, y, _) = Tree::axes();
+        let shape =
+            VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+        let mut cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 16, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let bits = cfg.run::<_, CoverageMode>(shape.clone()).unwrap();
+        let expected = std::f32::consts::PI * 16.0f32.powi(2);
+        for samples in [SamplesPerPixel::TwoByTwo, SamplesPerPixel::FourByFour]
+        {
+            for filter in [SampleFilter::Box, SampleFilter::Tent] {
+                cfg.samples_per_pixel = samples;
+                cfg.sample_filter = filter;
+                let out = cfg.run::<_, CoverageMode>(shape.clone()).unwrap();
+
+                // Only pixels near the edge are changed, and some of them
+                // have fractional coverage
+                let mut partial = 0;
+                for (i, (&a, &b)) in out.iter().zip(&bits).enumerate() {
+                    let px = (i % 64) as f32 - 31.5;
+                    let py = (i / 64) as f32 - 31.5;
+                    if ((px * px + py * py).sqrt() - 16.0).abs() > 2.0 {
+                        assert_eq!(a, b);
+                    } else if a != 0 && a != 255 {
+                        partial += 1;
+                    }
+                }
+                assert!(partial > 64, "{samples:?} {filter:?}: {partial}");
+
+                // Total coverage matches the circle's area (to within the
+                // error from counting samples on a grid)
+                let area: f32 = out.iter().map(|&a| a as f32 / 255.0).sum();
+                assert!(
+                    (area - expected).abs() < 0.015 * expected,
+                    "{samples:?} {filter:?}: {area} != {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn render_tape_heatmap() {
+        let (x, y, _) = Tree::axes();
+        let circle = |cx: f64| {
+            let dx = x.clone() - cx;
+            (dx.square() + y.square()).sqrt() - 0.3
+        };
+        let shape = VmShape::from_tree(&circle(-0.5).min(circle(0.5)));
+        let cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let bits = cfg.run::<_, BitRenderMode>(shape.clone()).unwrap();
+        let out = cfg.run::<_, TapeHeatmapRenderMode>(shape.clone()).unwrap();
+
+        // Pixel kinds match the binary render, and many pixels evaluated near
+        // either circle use a simplified tape (without the `min`)
+        let full = shape.size();
+        let mut short = 0;
+        for (p, b) in out.iter().zip(&bits) {
+            assert_eq!(p.kind.is_filled(), *b);
+            assert!(p.tape_len > 0 && p.tape_len <= full);
+            if matches!(p.kind, DebugPixel::Empty | DebugPixel::Filled)
+                && p.tape_len < full
+            {
+                short += 1;
+            }
+            assert_eq!(p.as_heatmap_color(full)[3], 255);
+        }
+        assert!(short > 64);
+
+        // Top-level tiles are evaluated with the full tape
+        assert!(matches!(out[0].kind, DebugPixel::EmptyTile));
+        assert_eq!(out[0].tape_len, full);
+    }
+
+    #[test]
+    fn render_field() {
+        let (x, y, _) = Tree::axes();
+        let shape =
+            VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+        let cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 16, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let bits = cfg.run::<_, BitRenderMode>(shape.clone()).unwrap();
+        let out = cfg.run::<_, FieldRenderMode>(shape.clone()).unwrap();
+
+        // Every pixel has the correct sign, and is no further from zero than
+        // the true value (which it matches exactly near the edge)
+        let tape = shape.point_tape(());
+        let mut eval = VmShape::new_point_eval();
+        let mut exact = 0;
+        for (i, (&v, &b)) in out.iter().zip(&bits).enumerate() {
+            assert_eq!(v < 0.0, b);
+            let px = (i % 64) as f32;
+            let py = (63 - i / 64) as f32;
+            let (f, _) = eval
+                .eval(&tape, px / 32.0 - 1.0, py / 32.0 - 1.0, 0.0)
+                .unwrap();
+            assert!(v.abs() <= f.abs() + 1e-6, "{v} vs {f}");
+            if (v - f).abs() < 1e-6 {
+                exact += 1;
+            }
+        }
+        assert!(exact > 64);
+        assert!(exact < out.len());
+    }
+
+    #[test]
+    fn render_stats() {
+        let (ctx, root) = Context::from_text(HI.as_bytes()).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let stats = RenderStats::new();
+        let mut cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 16, 8],
+            stats: Some(stats.clone()),
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let expected = cfg.run::<_, BitRenderMode>(shape.clone()).unwrap();
+        let always = stats.tape_lengths();
+        assert_eq!(always.len(), 3);
+        assert_eq!(always[0].count(), 4);
+        assert_eq!(always[0].buckets().count(), 1);
+
+        // Adaptive simplification must not change the image, and evaluates
+        // the same set of tiles.  With this threshold, tapes are only
+        // simplified at the top level, so deeper tiles reuse those tapes.
+        stats.clear();
+        cfg.simplify = SimplifyPolicy::Adaptive { min_shrink: 1.0 };
+        let out = cfg.run::<_, BitRenderMode>(shape).unwrap();
+        assert_eq!(out, expected);
+        let adaptive = stats.tape_lengths();
+        for (a, b) in always.iter().zip(&adaptive) {
+            assert_eq!(a.count(), b.count());
+        }
+        let lens = |h: &TapeHistogram| h.buckets().map(|(r, _)| r).collect();
+        let a: Vec<_> = lens(&adaptive[1]);
+        let b: Vec<_> = lens(&adaptive[2]);
+        assert!(b.iter().all(|r| a.contains(r)));
+    }
+
+    #[test]
+    fn render_simplify_threshold() {
+        let (ctx, root) = Context::from_text(HI.as_bytes()).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let stats = RenderStats::new();
+        let mut cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 16, 8],
+            stats: Some(stats.clone()),
+            simplify_threshold: SimplifyThreshold::ALWAYS,
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let expected = cfg.run::<_, BitRenderMode>(shape.clone()).unwrap();
+        assert!(stats.tape_lengths()[2].buckets().count() > 1);
+
+        // Shapes below the threshold are never simplified, so every tile is
+        // evaluated with the original tape
+        stats.clear();
+        cfg.simplify_threshold.min_tape_len_for_simplify = shape.size() + 1;
+        let out = cfg.run::<_, BitRenderMode>(shape.clone()).unwrap();
+        assert_eq!(out, expected);
+        for h in stats.tape_lengths() {
+            let mut buckets = h.buckets();
+            assert!(buckets.next().unwrap().0.contains(&shape.size()));
+            assert!(buckets.next().is_none());
+        }
+    }
+
+    #[test]
+    fn render_streaming() {
+        let (x, y, _) = Tree::axes();
+        let shape = VmShape::from_tree(&(x.sin() + y * 2.0));
+        let cfg = RenderConfig::<2> {
+            image_size: ImageSize::new(70, 45),
+            tile_sizes: vec![32, 8],
+            threads: 4.try_into().unwrap(),
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let expected = cfg.run::<_, SdfRenderMode>(shape.clone()).unwrap();
+
+        // Every pixel is covered by exactly one (clipped) tile
+        let tiles = Mutex::new(vec![]);
+        cfg.run_streaming::<_, SdfRenderMode>(shape, |t| {
+            tiles.lock().unwrap().push(t)
+        })
+        .unwrap();
+        let tiles = tiles.into_inner().unwrap();
+        assert_eq!(tiles.len(), 6);
+        let mut image = vec![None; 70 * 45];
+        for t in &tiles {
+            assert_eq!(t.pixels.len(), t.width * t.height);
+            for (j, row) in t.pixels.chunks(t.width).enumerate() {
+                for (i, p) in row.iter().enumerate() {
+                    let o = &mut image[(t.y + j) * 70 + t.x + i];
+                    assert!(o.is_none());
+                    *o = Some(*p);
+                }
+            }
+        }
+        let image: Vec<_> = image.into_iter().map(Option::unwrap).collect();
+        assert_eq!(image, expected);
+    }
+
+    #[test]
+    fn render_layers() {
+        let (ctx, root) = Context::from_text(HI.as_bytes()).unwrap();
+        let hi = VmShape::new(&ctx, root).unwrap();
+        let (x, y, _) = crate::context::Tree::axes();
+        let circle = (x.square() + y.square()).sqrt() - 0.5;
+        let circle = VmShape::from_tree(&circle);
+        let cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let a = cfg.run::<_, BitRenderMode>(hi.clone()).unwrap();
+        let b = cfg.run::<_, BitRenderMode>(circle.clone()).unwrap();
+        assert!(a.iter().zip(&b).any(|(a, b)| *a && *b));
+
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let clear = [0, 255, 0, 128];
+        let out = cfg
+            .run_layers::<_, BitRenderMode>(&[
+                Layer::new(hi.clone(), red),
+                Layer::new(circle.clone(), blue),
+            ])
+            .unwrap();
+        for i in 0..out.len() {
+            let expected = match (a[i], b[i]) {
+                (_, true) => blue,
+                (true, false) => red,
+                (false, false) => [0; 4],
+            };
+            assert_eq!(out[i], expected, "mismatch at pixel {i}");
+        }
+
+        // Translucent layers are blended with what's underneath
+        let out = cfg
+            .run_layers::<_, BitRenderMode>(&[
+                Layer::new(hi.clone(), red),
+                Layer::new(circle.clone(), clear),
+            ])
+            .unwrap();
+        for i in 0..out.len() {
+            let expected = match (a[i], b[i]) {
+                (true, true) => [127, 128, 0, 255],
+                (false, true) => clear,
+                (true, false) => red,
+                (false, false) => [0; 4],
+            };
+            assert_eq!(out[i], expected, "mismatch at pixel {i}");
+        }
+
+        // Other blend modes mix with the layers beneath them (which aren't
+        // skipped, even though the top layer is opaque)
+        let orange = [255, 128, 0, 255];
+        let top = [128, 255, 255, 255];
+        let blended = |blend| {
+            let layers = [
+                Layer::new(hi.clone(), orange),
+                Layer {
+                    shape: circle.clone(),
+                    color: top,
+                    blend,
+                },
+            ];
+            cfg.run_layers::<_, BitRenderMode>(&layers).unwrap()
+        };
+        for (blend, mixed) in [
+            (BlendMode::Multiply, [128, 128, 0, 255]),
+            (BlendMode::Screen, [255, 255, 255, 255]),
+            (BlendMode::Add, [255, 255, 255, 255]),
+        ] {
+            let out = blended(blend);
+            for i in 0..out.len() {
+                let expected = match (a[i], b[i]) {
+                    (true, true) => mixed,
+                    (false, true) => top,
+                    (true, false) => orange,
+                    (false, false) => [0; 4],
+                };
+                assert_eq!(out[i], expected, "{blend:?} mismatch at pixel {i}");
+            }
+        }
+
+        let out = cfg.run_layers::<VmShape, BitRenderMode>(&[]).unwrap();
+        assert_eq!(out, vec![[0; 4]; 64 * 64]);
+
+        // Antialiased layers use each pixel's coverage as its alpha
+        let aa = cfg.run::<_, AntialiasRenderMode>(circle.clone()).unwrap();
+        let out = cfg
+            .run_layers::<_, AntialiasRenderMode>(&[Layer::new(circle, blue)])
+            .unwrap();
+        assert!(aa.iter().any(|c| *c != 0 && *c != u8::MAX));
+        for i in 0..out.len() {
+            let expected = if aa[i] == 0 {
+                [0; 4]
+            } else {
+                [0, 0, 255, aa[i]]
+            };
+            assert_eq!(out[i], expected, "antialias mismatch at pixel {i}");
+        }
+    }
+
+    #[test]
+    fn render_huge_union() {
+        use crate::eval::{EzShape, TracingEvaluator};
+        const N: usize = 100_000;
+
+        // Build a linear chain of small circles, which is the worst case for
+        // choice counts (every node but the first is a `min`)
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let mut root = None;
+        for i in 0..N {
+            let t = i as f64;
+            let dx = ctx.sub(x, (t * 0.37).cos() * 0.9).unwrap();
+            let dy = ctx.sub(y, (t * 0.61).sin() * 0.9).unwrap();
+            let dx2 = ctx.square(dx).unwrap();
+            let dy2 = ctx.square(dy).unwrap();
+            let r = ctx.add(dx2, dy2).unwrap();
+            let c = ctx.sub(r, 0.0001).unwrap();
+            root = Some(match root {
+                Some(prev) => ctx.min(prev, c).unwrap(),
+                None => c,
+            });
+        }
+        let root = root.unwrap();
+
+        // Planning
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = crate::compiler::SsaTape::new(&ctx, root).unwrap();
+        assert_eq!(tape.choice_count, N - 1);
+
+        // Simplification, in a small region near the first circle
+        let mut eval = VmShape::new_interval_eval();
+        let (cx, cy) = (0.9f32, 0.0f32);
+        let (out, trace) = eval
+            .eval(
+                &shape.ez_interval_tape(),
+                [cx - 0.001, cx + 0.001],
+                [cy - 0.001, cy + 0.001],
+                [0.0, 0.0],
+            )
+            .unwrap();
+        assert!(out.lower() < 0.0);
+        let next = shape.ez_simplify(trace.unwrap()).unwrap();
+        assert!(next.size() < shape.size() / 100);
+
+        // Rendering
+        let cfg = RenderConfig::<2> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..Default::default()
+        };
+        let out = cfg.run::<_, BitRenderMode>(shape).unwrap();
+        assert!(out.iter().any(|p| *p));
+        assert!(out.iter().any(|p| !*p));
+    }
 }
@@ -1,9 +1,14 @@
 //! 3D bitmap rendering / rasterization
-use super::RenderHandle;
+use super::{
+    compile::{with_compiler, Compiler},
+    stats::WorkerStats,
+    RenderHandle,
+};
 use crate::{
     eval::{BulkEvaluator, Shape, TracingEvaluator},
     render::config::{AlignedRenderConfig, Queue, RenderConfig, Tile},
-    types::Interval,
+    types::{Grad, Interval},
+    Error,
 };
 
 use nalgebra::Point3;
@@ -11,6 +16,101 @@ use std::collections::HashMap;
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Configuration trait for 3D rendering
+///
+/// The 3D renderer finds the depth of the surface at each pixel (as a
+/// heightmap), then evaluates the shape's gradient at the topmost filled voxel
+/// of each column.  That gradient is passed to [`shade`](Self::shade) to
+/// produce the pixel's color (or other value).
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::MathShape,
+///     render::{RenderConfig, ShadingMode},
+///     types::Grad,
+///     vm::VmShape,
+/// };
+///
+/// /// Shading mode which records whether the surface faces the camera
+/// struct FacingMode;
+/// impl ShadingMode for FacingMode {
+///     type Output = bool;
+///     fn shade(g: Grad) -> bool {
+///         g.dz > 0.0
+///     }
+/// }
+///
+/// let (x, y, z) = Tree::axes();
+/// let shape = VmShape::from_tree(
+///     &((x.square() + y.square() + z.square()).sqrt() - 0.5),
+/// );
+/// let cfg = RenderConfig::<3> {
+///     image_size: 64.into(),
+///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+///     ..Default::default()
+/// };
+/// let (depth, facing) = cfg.run_shaded::<_, FacingMode>(shape)?;
+/// assert!(depth[32 * 64 + 32] > 0);
+/// assert!(facing[32 * 64 + 32]);
+/// assert!(!facing[0]); // background pixels have the default value
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub trait ShadingMode {
+    /// Type of output pixel
+    ///
+    /// Pixels where the shape isn't visible have the default value.
+    type Output: Default + Copy + Clone + Send;
+
+    /// Converts a surface sample into a pixel
+    ///
+    /// The sample contains the shape's value and partial derivatives (with
+    /// respect to model coordinates) at the topmost filled voxel in the
+    /// pixel's column; the partial derivatives are a (non-normalized) surface
+    /// normal.
+    ///
+    /// If [`NEEDS_GRADIENT`](Self::NEEDS_GRADIENT) is `false`, the partial
+    /// derivatives are all zero.
+    fn shade(g: Grad) -> Self::Output;
+
+    /// Whether [`shade`](Self::shade) uses the sample's partial derivatives
+    ///
+    /// Modes which only need the surface's position (e.g. inside / outside
+    /// masks) should set this to `false`, so that the renderer can skip
+    /// gradient evaluation entirely; samples are then taken from the
+    /// (cheaper) float evaluation used to find each column's depth.
+    const NEEDS_GRADIENT: bool = true;
+}
+
+/// Shading mode which colors each pixel by its surface normal
+///
+/// This is the mode used by [`render3d`](crate::render::render3d()).  Pixels
+/// with a zero gradient are colored red.
+pub struct NormalShadingMode;
+
+impl ShadingMode for NormalShadingMode {
+    type Output = [u8; 3];
+    fn shade(g: Grad) -> [u8; 3] {
+        g.to_rgb().unwrap_or([255, 0, 0])
+    }
+}
+
+/// Shading mode which records whether each pixel is occupied
+///
+/// This doesn't need surface normals, so the renderer skips gradient
+/// evaluation; pixels where the shape is visible are `true`.
+pub struct OccupancyShadingMode;
+
+impl ShadingMode for OccupancyShadingMode {
+    type Output = bool;
+    const NEEDS_GRADIENT: bool = false;
+    fn shade(_g: Grad) -> bool {
+        true
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 struct Scratch {
     x: Vec<f32>,
     y: Vec<f32>,
@@ -36,7 +136,7 @@ impl Scratch {
 
 ////////////////////////////////////////////////////////////////////////////////
 
-struct Worker<'a, S: Shape> {
+struct Worker<'a, S: Shape, M: ShadingMode> {
     config: &'a AlignedRenderConfig<3>,
 
     /// Reusable workspace for evaluation, to minimize allocation
@@ -50,17 +150,28 @@ struct Worker<'a, S: Shape> {
     shape_storage: Vec<S::Storage>,
     workspace: S::Workspace,
 
+    /// Background compiler for float slice tapes, if enabled
+    compiler: Option<Compiler<S>>,
+
     /// Output images for this specific tile
     depth: Vec<u32>,
-    color: Vec<[u8; 3]>,
+    color: Vec<M::Output>,
+
+    /// Statistics for the current top-level tile
+    stats: WorkerStats,
 }
 
-impl<S: Shape> Worker<'_, S> {
+impl<S: Shape, M: ShadingMode> Worker<'_, S, M> {
+    /// Renders a tile, subdividing as needed
+    ///
+    /// If `settled` is true, then the tape is no longer simplified (see
+    /// [`SimplifyPolicy::Adaptive`](crate::render::SimplifyPolicy::Adaptive)).
     fn render_tile_recurse(
         &mut self,
         shape: &mut RenderHandle<S>,
         depth: usize,
         tile: Tile<3>,
+        settled: bool,
     ) {
         // Early exit if every single pixel is filled
         let tile_size = self.config.tile_sizes[depth];
@@ -77,6 +188,9 @@ impl<S: Shape> Worker<'_, S> {
         let y = Interval::new(base.y, base.y + tile_size as f32);
         let z = Interval::new(base.z, base.z + tile_size as f32);
 
+        if self.config.stats.is_some() {
+            self.stats.record(depth, shape.size());
+        }
         let (i, trace) = self
             .eval_interval
             .eval(shape.i_tape(&mut self.tape_storage), x, y, z)
@@ -97,7 +211,11 @@ impl<S: Shape> Worker<'_, S> {
         }
 
         // Calculate a simplified tape based on the trace
-        let sub_tape = if let Some(trace) = trace.as_ref() {
+        let before = shape.size();
+        let sub_tape = if let Some(trace) = trace.as_ref().filter(|_| {
+            !settled
+                && self.config.simplify_threshold.should_simplify(&shape.shape)
+        }) {
             shape.simplify(
                 trace,
                 &mut self.workspace,
@@ -110,6 +228,8 @@ impl<S: Shape> Worker<'_, S> {
 
         // Recurse!
         if let Some(next_tile_size) = self.config.tile_sizes.get(depth + 1) {
+            let settled = settled
+                || self.config.simplify.is_settled(before, sub_tape.size());
             let n = tile_size / next_tile_size;
 
             for j in 0..n {
@@ -123,6 +243,7 @@ impl<S: Shape> Worker<'_, S> {
                                 tile.corner[1] + j * next_tile_size,
                                 tile.corner[2] + k * next_tile_size,
                             ]),
+                            settled,
                         );
                     }
                 }
@@ -182,7 +303,10 @@ impl<S: Shape> Worker<'_, S> {
         let out = self
             .eval_float_slice
             .eval(
-                shape.f_tape(&mut self.tape_storage),
+                shape.f_tape_background(
+                    &mut self.tape_storage,
+                    self.compiler.as_ref(),
+                ),
                 &self.scratch.x[..index],
                 &self.scratch.y[..index],
                 &self.scratch.z[..index],
@@ -197,8 +321,9 @@ impl<S: Shape> Worker<'_, S> {
         for col in 0..self.scratch.columns.len() {
             // Find the first set pixel in the column
             let depth = depth.next().unwrap();
-            let k = match depth.iter().enumerate().find(|(_, d)| **d < 0.0) {
-                Some((i, _)) => i,
+            let (k, v) = match depth.iter().enumerate().find(|(_, d)| **d < 0.0)
+            {
+                Some((i, v)) => (i, *v),
                 None => continue,
             };
 
@@ -218,6 +343,12 @@ impl<S: Shape> Worker<'_, S> {
             assert!(self.depth[o] < z);
             self.depth[o] = z;
 
+            // Shade the pixel immediately if we don't need its gradient
+            if !M::NEEDS_GRADIENT {
+                self.color[o] = M::shade(Grad::new(v, 0.0, 0.0, 0.0));
+                continue;
+            }
+
             // Prepare to do gradient rendering of this point.
             // We step one voxel above the surface to reduce
             // glitchiness on edges and corners, where rendering
@@ -233,6 +364,10 @@ impl<S: Shape> Worker<'_, S> {
             grad += 1;
         }
 
+        // Gradient evaluation computes values and partial derivatives in a
+        // single pass, but is several times more expensive per point than
+        // float evaluation.  We only run it on surface voxels, rather than
+        // using it to find the surface in the first place.
         if grad > 0 {
             let out = self
                 .eval_grad_slice
@@ -245,7 +380,7 @@ impl<S: Shape> Worker<'_, S> {
                 .unwrap();
 
             for (index, o) in self.scratch.columns[0..grad].iter().enumerate() {
-                self.color[*o] = out[index].to_rgb().unwrap_or([255, 0, 0]);
+                self.color[*o] = M::shade(out[index]);
             }
         }
     }
@@ -253,35 +388,35 @@ impl<S: Shape> Worker<'_, S> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Default)]
-struct Image {
+struct Image<T> {
     depth: Vec<u32>,
-    color: Vec<[u8; 3]>,
+    color: Vec<T>,
 }
 
-impl Image {
+impl<T: Default + Clone> Image<T> {
     fn new(size: usize) -> Self {
         Self {
             depth: vec![0; size.pow(2)],
-            color: vec![[0; 3]; size.pow(2)],
+            color: vec![T::default(); size.pow(2)],
         }
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
-fn worker<S: Shape>(
+fn worker<S: Shape, M: ShadingMode>(
     mut shape: RenderHandle<S>,
     queues: &[Queue<3>],
     mut index: usize,
     config: &AlignedRenderConfig<3>,
-) -> HashMap<[usize; 2], Image> {
+    compiler: Option<Compiler<S>>,
+) -> HashMap<[usize; 2], Image<M::Output>> {
     let mut out = HashMap::new();
 
     // Calculate maximum evaluation buffer size
     let buf_size = *config.tile_sizes.last().unwrap();
     let scratch = Scratch::new(buf_size);
-    let mut w: Worker<S> = Worker {
+    let mut w: Worker<S, M> = Worker {
         scratch,
         depth: vec![],
         color: vec![],
@@ -294,6 +429,8 @@ fn worker<S: Shape>(
         tape_storage: vec![],
         shape_storage: vec![],
         workspace: Default::default(),
+        compiler,
+        stats: WorkerStats::default(),
     };
 
     // Every thread has a set of tiles assigned to it, which are in Z-sorted
@@ -303,6 +440,9 @@ fn worker<S: Shape>(
     let start = index;
     loop {
         while let Some(tile) = queues[index].next() {
+            if crate::exec::checkpoint().is_err() {
+                return out;
+            }
             let image = out
                 .remove(&[tile.corner[0], tile.corner[1]])
                 .unwrap_or_else(|| Image::new(config.tile_sizes[0]));
@@ -310,7 +450,10 @@ fn worker<S: Shape>(
             // Prepare to render, allocating space for a tile
             w.depth = image.depth;
             w.color = image.color;
-            w.render_tile_recurse(&mut shape, 0, tile);
+            w.render_tile_recurse(&mut shape, 0, tile, false);
+            if let Some(sink) = &config.stats {
+                w.stats.flush(sink);
+            }
 
             // Steal the tile, replacing it with an empty vec
             let depth = std::mem::take(&mut w.depth);
@@ -319,6 +462,7 @@ fn worker<S: Shape>(
                 [tile.corner[0], tile.corner[1]],
                 Image { depth, color },
             );
+            config.tile_done();
         }
         // Move on to the next thread's queue
         index = (index + 1) % queues.len();
@@ -339,29 +483,50 @@ fn worker<S: Shape>(
 /// transforms, etc.
 ///
 /// This function is parameterized by shape type, which determines how we
-/// perform evaluation.
+/// perform evaluation.  Pixels are colored by their surface normals; use
+/// [`render3d_shaded`](crate::render::render3d_shaded()) for other outputs.
+///
+/// Returns an error if the configuration is invalid (see
+/// [`RenderConfig::check`]), or [`Error::Cancelled`] if this is run as part of
+/// a [job](crate::exec::Executor::run) which is cancelled.
 pub fn render<S: Shape>(
     shape: S,
     config: &RenderConfig<3>,
-) -> (Vec<u32>, Vec<[u8; 3]>) {
-    let (config, mat) = config.align();
-    assert!(config.image_size % config.tile_sizes[0] == 0);
-    for i in 0..config.tile_sizes.len() - 1 {
-        assert!(config.tile_sizes[i] % config.tile_sizes[i + 1] == 0);
-    }
+) -> Result<(Vec<u32>, Vec<[u8; 3]>), Error> {
+    render_shaded::<S, NormalShadingMode>(shape, config)
+}
 
-    let shape = shape.apply_transform(mat);
-    render_inner(shape, config)
+/// Renders the given tape into a 3D image, using a custom [`ShadingMode`]
+///
+/// Returns a heightmap and an image of shaded pixels, or an error under the
+/// same conditions as [`render`].
+pub fn render_shaded<S: Shape, M: ShadingMode>(
+    shape: S,
+    config: &RenderConfig<3>,
+) -> Result<(Vec<u32>, Vec<M::Output>), Error> {
+    config.check()?;
+    crate::exec::cancellable(|| {
+        let (config, mat) = config.align();
+        for s in config.image_size {
+            assert!(s % config.tile_sizes[0] == 0);
+        }
+        for i in 0..config.tile_sizes.len() - 1 {
+            assert!(config.tile_sizes[i] % config.tile_sizes[i + 1] == 0);
+        }
+
+        let shape = shape.apply_transform(mat);
+        render_inner::<_, M>(shape, config)
+    })
 }
 
-pub fn render_inner<S: Shape>(
+pub fn render_inner<S: Shape, M: ShadingMode>(
     shape: S,
     config: AlignedRenderConfig<3>,
-) -> (Vec<u32>, Vec<[u8; 3]>) {
+) -> (Vec<u32>, Vec<M::Output>) {
     let mut tiles = vec![];
-    for i in 0..config.image_size / config.tile_sizes[0] {
-        for j in 0..config.image_size / config.tile_sizes[0] {
-            for k in (0..config.image_size / config.tile_sizes[0]).rev() {
+    for i in 0..config.image_size[0] / config.tile_sizes[0] {
+        for j in 0..config.image_size[1] / config.tile_sizes[0] {
+            for k in (0..config.image_size[2] / config.tile_sizes[0]).rev() {
                 tiles.push(config.new_tile([
                     i * config.tile_sizes[0],
                     j * config.tile_sizes[0],
@@ -383,45 +548,64 @@ pub fn render_inner<S: Shape>(
     let mut rh = RenderHandle::new(shape);
     let _ = rh.i_tape(&mut vec![]); // populate i_tape before cloning
 
-    // Special-case for single-threaded operation, to give simpler backtraces
-    let out: Vec<_> = if threads == 1 {
-        worker::<S>(rh, tile_queues.as_slice(), 0, &config)
+    let out: Vec<_> = with_compiler(config.compile_threads(), |compiler| {
+        if compiler.is_some() {
+            // Simplified shapes are evaluated with this tape until their own
+            // tapes are built in the background
+            let _ = rh.f_tape(&mut vec![]);
+        }
+        // Special-case for single-threaded operation, to give simpler
+        // backtraces
+        if threads == 1 {
+            worker::<S, M>(
+                rh,
+                tile_queues.as_slice(),
+                0,
+                &config,
+                compiler.cloned(),
+            )
             .into_iter()
             .collect()
-    } else {
-        #[cfg(target_arch = "wasm32")]
-        unreachable!("multithreaded rendering is not supported on wasm32");
-
-        #[cfg(not(target_arch = "wasm32"))]
-        std::thread::scope(|s| {
-            let config = &config;
-            let mut handles = vec![];
-            let queues = tile_queues.as_slice();
-            for i in 0..threads {
-                let rh = rh.clone();
-                handles
-                    .push(s.spawn(move || worker::<S>(rh, queues, i, config)));
-            }
-            let mut out = vec![];
-            for h in handles {
-                out.extend(h.join().unwrap().into_iter());
-            }
-            out
-        })
-    };
+        } else {
+            #[cfg(target_arch = "wasm32")]
+            unreachable!("multithreaded rendering is not supported on wasm32");
+
+            #[cfg(not(target_arch = "wasm32"))]
+            std::thread::scope(|s| {
+                // Don't hold a slot while waiting on the workers
+                crate::exec::idle();
+                let config = &config;
+                let mut handles = vec![];
+                let queues = tile_queues.as_slice();
+                for i in 0..threads {
+                    let rh = rh.clone();
+                    let compiler = compiler.cloned();
+                    let job = crate::exec::current();
+                    handles.push(s.spawn(move || {
+                        let _guard = crate::exec::enter(job);
+                        worker::<S, M>(rh, queues, i, config, compiler)
+                    }));
+                }
+                let mut out = vec![];
+                for h in handles {
+                    out.extend(h.join().unwrap());
+                }
+                out
+            })
+        }
+    });
 
-    let mut image_depth = vec![0; config.orig_image_size.pow(2)];
-    let mut image_color = vec![[0; 3]; config.orig_image_size.pow(2)];
+    let [width, height, _depth] = config.orig_image_size;
+    let mut image_depth = vec![0; width * height];
+    let mut image_color = vec![M::Output::default(); width * height];
     for (tile, patch) in out.iter() {
         let mut index = 0;
         for j in 0..config.tile_sizes[0] {
             let y = j + tile[1];
             for i in 0..config.tile_sizes[0] {
                 let x = i + tile[0];
-                if x < config.orig_image_size && y < config.orig_image_size {
-                    let o = (config.orig_image_size - y - 1)
-                        * config.orig_image_size
-                        + x;
+                if x < width && y < height {
+                    let o = (height - y - 1) * width + x;
                     if patch.depth[index] >= image_depth[o] {
                         image_color[o] = patch.color[index];
                         image_depth[o] = patch.depth[index];
@@ -437,7 +621,14 @@ pub fn render_inner<S: Shape>(
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{eval::MathShape, vm::VmShape, Context};
+    use crate::{
+        context::Tree,
+        eval::MathShape,
+        render::{RenderProgress, RenderStats, SimplifyPolicy},
+        shape::Bounds,
+        vm::VmShape,
+        Context,
+    };
 
     /// Make sure we don't crash if there's only a single tile
     #[test]
@@ -447,10 +638,154 @@ mod test {
         let shape = VmShape::new(&ctx, x).unwrap();
 
         let cfg = RenderConfig::<3> {
-            image_size: 128, // very small!
+            image_size: 128.into(), // very small!
+            bounds: Some(Bounds::centered(1.0)),
             ..RenderConfig::default()
         };
         let out = cfg.run(shape);
         assert!(out.is_ok());
     }
+
+    #[test]
+    fn test_shading_mode() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let x2 = ctx.square(x).unwrap();
+        let y2 = ctx.square(y).unwrap();
+        let z2 = ctx.square(z).unwrap();
+        let r = ctx.add(x2, y2).unwrap();
+        let r = ctx.add(r, z2).unwrap();
+        let r = ctx.sqrt(r).unwrap();
+        let root = ctx.sub(r, 0.5).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+
+        struct ValueMode;
+        impl ShadingMode for ValueMode {
+            type Output = Option<f32>;
+            fn shade(g: Grad) -> Option<f32> {
+                Some(g.v)
+            }
+        }
+
+        let cfg = RenderConfig::<3> {
+            image_size: 64.into(),
+            bounds: Some(Bounds::centered(1.0)),
+            ..RenderConfig::default()
+        };
+        let (depth, color) = cfg.run(shape.clone()).unwrap();
+        let (depth_, color_) = cfg
+            .run_shaded::<_, NormalShadingMode>(shape.clone())
+            .unwrap();
+        assert_eq!(depth, depth_);
+        assert_eq!(color, color_);
+
+        let (depth_, value) =
+            cfg.run_shaded::<_, ValueMode>(shape.clone()).unwrap();
+        assert_eq!(depth, depth_);
+        for (d, v) in depth.iter().zip(&value) {
+            // Samples are taken at the topmost filled voxel
+            assert_eq!(*d > 0, v.is_some());
+            assert!(v.unwrap_or(-1.0) < 0.0);
+        }
+
+        // Gradient-free modes see the same samples, without derivatives
+        struct FastValueMode;
+        impl ShadingMode for FastValueMode {
+            type Output = Option<f32>;
+            const NEEDS_GRADIENT: bool = false;
+            fn shade(g: Grad) -> Option<f32> {
+                assert_eq!([g.dx, g.dy, g.dz], [0.0; 3]);
+                Some(g.v)
+            }
+        }
+        let (depth_, value_) =
+            cfg.run_shaded::<_, FastValueMode>(shape.clone()).unwrap();
+        assert_eq!(depth, depth_);
+        assert_eq!(value, value_);
+
+        let (depth_, occupied) =
+            cfg.run_shaded::<_, OccupancyShadingMode>(shape).unwrap();
+        assert_eq!(depth, depth_);
+        for (d, o) in depth.iter().zip(&occupied) {
+            assert_eq!(*d > 0, *o);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_simplify() {
+        let (x, y, z) = Tree::axes();
+        let sphere = |cx: f32| {
+            ((x.clone() - cx).square() + y.square() + z.square()).sqrt() - 0.3
+        };
+        let shape = VmShape::from_tree(&sphere(-0.4).min(sphere(0.4)));
+
+        let stats = RenderStats::new();
+        let mut cfg = RenderConfig::<3> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 16, 8],
+            stats: Some(stats.clone()),
+            bounds: Some(Bounds::centered(1.0)),
+            ..RenderConfig::default()
+        };
+        let (depth, color) = cfg.run(shape.clone()).unwrap();
+        let lengths = stats.tape_lengths();
+        assert_eq!(lengths.len(), 3);
+        assert!(lengths[0].count() > 0);
+
+        cfg.simplify = SimplifyPolicy::Adaptive { min_shrink: 0.5 };
+        let (depth_, color_) = cfg.run(shape).unwrap();
+        assert_eq!(depth, depth_);
+        assert_eq!(color, color_);
+    }
+
+    #[test]
+    fn test_progress() {
+        let (x, y, z) = Tree::axes();
+        let shape = VmShape::from_tree(
+            &((x.square() + y.square() + z.square()).sqrt() - 0.5),
+        );
+        let calls = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let cfg = RenderConfig::<3> {
+            image_size: 64.into(),
+            tile_sizes: vec![16, 8],
+            threads: 4.try_into().unwrap(),
+            progress: Some(RenderProgress::new({
+                let calls = calls.clone();
+                move |done, total| calls.lock().unwrap().push((done, total))
+            })),
+            bounds: Some(Bounds::centered(1.0)),
+            ..RenderConfig::default()
+        };
+        cfg.run(shape).unwrap();
+
+        // Every tile is reported exactly once, in order, even though tiles are
+        // stolen between threads
+        let calls = calls.lock().unwrap();
+        let expected: Vec<_> = (1..=64).map(|i| (i, 64)).collect();
+        assert_eq!(*calls, expected);
+    }
+
+    #[test]
+    fn test_compile_threads() {
+        let (x, y, z) = Tree::axes();
+        let sphere = |cx: f32| {
+            ((x.clone() - cx).square() + y.square() + z.square()).sqrt() - 0.3
+        };
+        let shape = VmShape::from_tree(&sphere(-0.4).min(sphere(0.4)));
+
+        let mut cfg = RenderConfig::<3> {
+            image_size: 64.into(),
+            tile_sizes: vec![32, 16, 8],
+            bounds: Some(Bounds::centered(1.0)),
+            ..RenderConfig::default()
+        };
+        let (depth, color) = cfg.run(shape.clone()).unwrap();
+
+        cfg.compile_threads = 2;
+        let (depth_, color_) = cfg.run(shape).unwrap();
+        assert_eq!(depth, depth_);
+        assert_eq!(color, color_);
+    }
 }
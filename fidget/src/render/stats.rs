@@ -0,0 +1,179 @@
+//! Statistics collected during rendering
+use std::{
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+/// Histogram of tape lengths, with power-of-two buckets
+///
+/// Bucket `i` counts tapes with lengths in the range `2^(i-1)..2^i` (with
+/// bucket 0 counting empty tapes).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TapeHistogram {
+    counts: Vec<usize>,
+}
+
+impl TapeHistogram {
+    /// Records a single tape length
+    pub fn record(&mut self, len: usize) {
+        let b = (usize::BITS - len.leading_zeros()) as usize;
+        if self.counts.len() <= b {
+            self.counts.resize(b + 1, 0);
+        }
+        self.counts[b] += 1;
+    }
+
+    /// Returns the total number of tapes recorded
+    pub fn count(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Iterates over non-empty buckets, returning `(lengths, count)` tuples
+    pub fn buckets(&self) -> impl Iterator<Item = (Range<usize>, usize)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c > 0)
+            .map(|(i, c)| {
+                let r = if i == 0 { 0..1 } else { 1 << (i - 1)..1 << i };
+                (r, *c)
+            })
+    }
+
+    /// Adds every count from another histogram into this one
+    pub fn merge(&mut self, other: &Self) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+    }
+}
+
+/// Shared sink for statistics from one or more renders
+///
+/// This is cheap to clone; clones refer to the same underlying statistics, so
+/// a caller can keep one copy and pass another to
+/// [`RenderConfig::stats`](crate::render::RenderConfig::stats).  Render
+/// workers accumulate statistics locally and merge them in after each
+/// top-level tile.
+///
+/// ```
+/// use fidget::{
+///     context::Tree,
+///     eval::MathShape,
+///     render::{BitRenderMode, RenderConfig, RenderStats},
+///     vm::VmShape,
+/// };
+///
+/// let (x, y, _) = Tree::axes();
+/// let shape = VmShape::from_tree(&((x.square() + y.square()).sqrt() - 0.5));
+/// let stats = RenderStats::new();
+/// let cfg = RenderConfig::<2> {
+///     image_size: 64.into(),
+///     tile_sizes: vec![32, 8],
+///     stats: Some(stats.clone()),
+///     bounds: Some(fidget::shape::Bounds::centered(1.0)),
+///     ..Default::default()
+/// };
+/// cfg.run::<_, BitRenderMode>(shape)?;
+///
+/// // Four top-level tiles are evaluated at depth 0
+/// assert_eq!(stats.tape_lengths()[0].count(), 4);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RenderStats(Arc<Mutex<Vec<TapeHistogram>>>);
+
+impl PartialEq for RenderStats {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl RenderStats {
+    /// Builds a new (empty) statistics sink
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns histograms of tape lengths, indexed by subdivision depth
+    ///
+    /// Every tile which is evaluated with interval arithmetic records the
+    /// length of its (possibly simplified) tape at the tile's depth, so depth
+    /// 0 records the full tape once per top-level tile.
+    pub fn tape_lengths(&self) -> Vec<TapeHistogram> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Resets all statistics
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    fn merge(&self, lengths: &[TapeHistogram]) {
+        let mut out = self.0.lock().unwrap();
+        if out.len() < lengths.len() {
+            out.resize(lengths.len(), TapeHistogram::default());
+        }
+        for (a, b) in out.iter_mut().zip(lengths) {
+            a.merge(b);
+        }
+    }
+}
+
+/// Per-worker statistics, which are flushed into a [`RenderStats`]
+#[derive(Default)]
+pub(crate) struct WorkerStats {
+    tape_lengths: Vec<TapeHistogram>,
+}
+
+impl WorkerStats {
+    /// Records the tape length of a tile at the given depth
+    pub fn record(&mut self, depth: usize, len: usize) {
+        if self.tape_lengths.len() <= depth {
+            self.tape_lengths
+                .resize(depth + 1, TapeHistogram::default());
+        }
+        self.tape_lengths[depth].record(len);
+    }
+
+    /// Merges local statistics into the sink, then resets them
+    pub fn flush(&mut self, sink: &RenderStats) {
+        sink.merge(&self.tape_lengths);
+        self.tape_lengths.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tape_histogram() {
+        let mut h = TapeHistogram::default();
+        for len in [0, 1, 2, 3, 4, 7, 8, 100] {
+            h.record(len);
+        }
+        assert_eq!(h.count(), 8);
+        let b: Vec<_> = h.buckets().collect();
+        assert_eq!(
+            b,
+            [
+                (0..1, 1),
+                (1..2, 1),
+                (2..4, 2),
+                (4..8, 2),
+                (8..16, 1),
+                (64..128, 1)
+            ]
+        );
+
+        let mut g = TapeHistogram::default();
+        g.record(5);
+        g.merge(&h);
+        assert_eq!(g.count(), 9);
+        assert_eq!(g.buckets().nth(3), Some((4..8, 3)));
+    }
+}
@@ -77,7 +77,9 @@ impl Engine {
         let mut engine = rhai::Engine::new();
         engine
             .register_type::<Tree>()
-            .register_fn("remap_xyz", remap_xyz);
+            .register_fn("remap_xyz", remap_xyz)
+            .register_fn("to_cylindrical", to_cylindrical)
+            .register_fn("to_spherical", to_spherical);
 
         engine.build_type::<Axes>();
         engine.register_fn("axes", axes);
@@ -252,6 +254,14 @@ fn remap_xyz(shape: Tree, x: Tree, y: Tree, z: Tree) -> Tree {
     shape.remap_xyz(x, y, z)
 }
 
+fn to_cylindrical(shape: Tree) -> Tree {
+    shape.to_cylindrical()
+}
+
+fn to_spherical(shape: Tree) -> Tree {
+    shape.to_spherical()
+}
+
 fn draw(ctx: NativeCallContext, tree: Tree) {
     let ctx = ctx.tag().unwrap().clone_cast::<Arc<Mutex<ScriptContext>>>();
     ctx.lock().unwrap().shapes.push(DrawShape {
@@ -445,6 +455,29 @@ mod test {
         assert_eq!(ctx.eval_xyz(sum, 1.0, 3.0, 0.0).unwrap(), -2.0);
     }
 
+    #[test]
+    fn test_squared_primitives() {
+        let mut engine = Engine::new();
+        let mut ctx = Context::new();
+        let c = ctx.import(&engine.eval("circle_sq(1, 0, 2)").unwrap());
+        assert_eq!(ctx.eval_xyz(c, 1.0, 3.0, 0.0).unwrap(), 5.0);
+        let s = ctx.import(&engine.eval("sphere_sq(0, 0, 1, 0.5)").unwrap());
+        assert_eq!(ctx.eval_xyz(s, 0.0, 0.0, 1.0).unwrap(), -0.25);
+    }
+
+    #[test]
+    fn test_coordinate_remaps() {
+        let mut engine = Engine::new();
+        let t = engine.eval("to_cylindrical(x - 1)").unwrap();
+        let mut ctx = Context::new();
+        let c = ctx.import(&t);
+        assert_eq!(ctx.eval_xyz(c, 3.0, 4.0, 2.0).unwrap(), 4.0);
+
+        let t = engine.eval("(x - 1).to_spherical()").unwrap();
+        let s = ctx.import(&t);
+        assert_eq!(ctx.eval_xyz(s, 2.0, 3.0, 6.0).unwrap(), 6.0);
+    }
+
     #[test]
     fn test_no_comparison() {
         let mut engine = Engine::new();
@@ -0,0 +1,255 @@
+//! Evaluation counters, for tuning without an external profiler
+//!
+//! When the `stats` feature is enabled, the VM and JIT evaluators count every
+//! evaluation (grouped by [`Flavor`]), record the length of each evaluated
+//! tape, and time every call to [`Shape::simplify`](crate::eval::Shape).
+//! Counters are global and shared between threads; use [`snapshot`] to read
+//! them and [`reset`] to clear them.
+//!
+//! ```
+//! use fidget::{
+//!     compiler::Flavor,
+//!     context::Tree,
+//!     eval::{EzShape, MathShape, Shape, TracingEvaluator},
+//!     vm::VmShape,
+//! };
+//!
+//! let (x, y, _z) = Tree::axes();
+//! let shape = VmShape::from_tree(&x.min(y));
+//! let mut eval = VmShape::new_interval_eval();
+//! let tape = shape.ez_interval_tape();
+//!
+//! let before = fidget::stats::snapshot();
+//! eval.eval(&tape, [0.0, 1.0], [2.0, 3.0], [0.0, 0.0])?;
+//! let after = fidget::stats::snapshot();
+//!
+//! // Other threads may also be evaluating, so counts only go up
+//! let (a, b) = (before.flavor(Flavor::Interval), after.flavor(Flavor::Interval));
+//! assert!(b.calls > a.calls);
+//! # Ok::<(), fidget::Error>(())
+//! ```
+//!
+//! Counting adds a few atomic operations to every evaluator call, so the
+//! feature shouldn't be enabled in production builds.
+use crate::compiler::Flavor;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Number of buckets in [`FlavorStats::tape_len`]
+///
+/// Bucket `i` counts tapes with lengths in `[2^i, 2^(i + 1))`, except that
+/// bucket 0 also counts empty tapes and the last bucket also counts every
+/// longer tape.
+pub const TAPE_LEN_BUCKETS: usize = 24;
+
+/// Counters for one flavor of evaluation
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlavorStats {
+    /// Number of evaluator calls
+    pub calls: u64,
+    /// Number of points (or intervals) evaluated
+    ///
+    /// This is equal to `calls` for tracing evaluation, and is the total slice
+    /// length for bulk evaluation.
+    pub points: u64,
+    /// Histogram of tape lengths, with one entry per call
+    ///
+    /// See [`TAPE_LEN_BUCKETS`] for bucket boundaries.
+    pub tape_len: [u64; TAPE_LEN_BUCKETS],
+}
+
+/// Snapshot of every counter, returned by [`snapshot`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Single-point tracing evaluation
+    pub point: FlavorStats,
+    /// Single-interval tracing evaluation
+    pub interval: FlavorStats,
+    /// Bulk evaluation of many points
+    pub float_slice: FlavorStats,
+    /// Bulk evaluation of many points with partial derivatives
+    pub grad_slice: FlavorStats,
+    /// Number of calls to [`Shape::simplify`](crate::eval::Shape::simplify)
+    pub simplify_calls: u64,
+    /// Total time spent in simplification
+    ///
+    /// Timing isn't available on `wasm32`, so this is always zero there.
+    pub simplify_time: Duration,
+}
+
+impl Stats {
+    /// Returns the counters for the given flavor of evaluation
+    pub fn flavor(&self, flavor: Flavor) -> &FlavorStats {
+        match flavor {
+            Flavor::Point => &self.point,
+            Flavor::Interval => &self.interval,
+            Flavor::FloatSlice => &self.float_slice,
+            Flavor::GradSlice => &self.grad_slice,
+        }
+    }
+}
+
+/// Global counters for one flavor of evaluation
+struct Counters {
+    calls: AtomicU64,
+    points: AtomicU64,
+    tape_len: [AtomicU64; TAPE_LEN_BUCKETS],
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            calls: AtomicU64::new(0),
+            points: AtomicU64::new(0),
+            tape_len: [const { AtomicU64::new(0) }; TAPE_LEN_BUCKETS],
+        }
+    }
+
+    fn load(&self) -> FlavorStats {
+        FlavorStats {
+            calls: self.calls.load(Ordering::Relaxed),
+            points: self.points.load(Ordering::Relaxed),
+            tape_len: std::array::from_fn(|i| {
+                self.tape_len[i].load(Ordering::Relaxed)
+            }),
+        }
+    }
+
+    fn reset(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+        self.points.store(0, Ordering::Relaxed);
+        for b in &self.tape_len {
+            b.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+static COUNTERS: [Counters; 4] = [const { Counters::new() }; 4];
+static SIMPLIFY_CALLS: AtomicU64 = AtomicU64::new(0);
+static SIMPLIFY_NANOS: AtomicU64 = AtomicU64::new(0);
+
+fn counters(flavor: Flavor) -> &'static Counters {
+    &COUNTERS[match flavor {
+        Flavor::Point => 0,
+        Flavor::Interval => 1,
+        Flavor::FloatSlice => 2,
+        Flavor::GradSlice => 3,
+    }]
+}
+
+/// Returns the histogram bucket for a tape of the given length
+fn bucket(tape_len: usize) -> usize {
+    (tape_len.max(1).ilog2() as usize).min(TAPE_LEN_BUCKETS - 1)
+}
+
+/// Returns a snapshot of every counter
+///
+/// Counters are read individually, so a snapshot taken while other threads are
+/// evaluating may not be perfectly consistent.
+pub fn snapshot() -> Stats {
+    Stats {
+        point: counters(Flavor::Point).load(),
+        interval: counters(Flavor::Interval).load(),
+        float_slice: counters(Flavor::FloatSlice).load(),
+        grad_slice: counters(Flavor::GradSlice).load(),
+        simplify_calls: SIMPLIFY_CALLS.load(Ordering::Relaxed),
+        simplify_time: Duration::from_nanos(
+            SIMPLIFY_NANOS.load(Ordering::Relaxed),
+        ),
+    }
+}
+
+/// Resets every counter to zero
+pub fn reset() {
+    for c in &COUNTERS {
+        c.reset();
+    }
+    SIMPLIFY_CALLS.store(0, Ordering::Relaxed);
+    SIMPLIFY_NANOS.store(0, Ordering::Relaxed);
+}
+
+/// Records a single evaluator call
+pub(crate) fn record_eval(flavor: Flavor, tape_len: usize, points: usize) {
+    let c = counters(flavor);
+    c.calls.fetch_add(1, Ordering::Relaxed);
+    c.points.fetch_add(points as u64, Ordering::Relaxed);
+    c.tape_len[bucket(tape_len)].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Timer for a single call to [`Shape::simplify`](crate::eval::Shape)
+///
+/// The call is recorded when the timer is dropped.
+pub(crate) struct SimplifyTimer {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl SimplifyTimer {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+impl Drop for SimplifyTimer {
+    fn drop(&mut self) {
+        SIMPLIFY_CALLS.fetch_add(1, Ordering::Relaxed);
+        #[cfg(not(target_arch = "wasm32"))]
+        SIMPLIFY_NANOS.fetch_add(
+            self.start.elapsed().as_nanos() as u64,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::{BulkEvaluator, EzShape, MathShape, Shape, TracingEvaluator},
+        vm::VmShape,
+    };
+
+    #[test]
+    fn test_bucket() {
+        assert_eq!(bucket(0), 0);
+        assert_eq!(bucket(1), 0);
+        assert_eq!(bucket(2), 1);
+        assert_eq!(bucket(3), 1);
+        assert_eq!(bucket(1024), 10);
+        assert_eq!(bucket(usize::MAX), TAPE_LEN_BUCKETS - 1);
+    }
+
+    #[test]
+    fn test_stats() {
+        // Other tests run in parallel, so we can only check lower bounds
+        let (x, y, z) = Tree::axes();
+        let shape = VmShape::from_tree(&x.min(y).max(z));
+        let before = snapshot();
+
+        let mut eval = VmShape::new_float_slice_eval();
+        let tape = shape.ez_float_slice_tape();
+        eval.eval(&tape, &[0.0; 100], &[1.0; 100], &[2.0; 100])
+            .unwrap();
+
+        let mut eval = VmShape::new_point_eval();
+        let tape = shape.ez_point_tape();
+        let (_, trace) = eval.eval(&tape, 0.0, 1.0, 2.0).unwrap();
+        shape.ez_simplify(trace.unwrap()).unwrap();
+
+        let after = snapshot();
+        let (a, b) = (before.float_slice, after.float_slice);
+        assert!(b.calls > a.calls);
+        assert!(b.points >= a.points + 100);
+        let i = bucket(shape.size());
+        assert!(b.tape_len[i] > a.tape_len[i]);
+        assert!(after.point.calls > before.point.calls);
+        assert!(after.simplify_calls > before.simplify_calls);
+        assert!(after.simplify_time > before.simplify_time);
+    }
+}
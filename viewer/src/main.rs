@@ -125,15 +125,46 @@ where
                 egui::Color32::BLACK,
             );
             let render_start = std::time::Instant::now();
-            for s in out.shapes.iter() {
-                let tape = S::from_tree(&s.tree);
-                render(
-                    &render_config.mode,
-                    tape,
-                    render_config.image_size,
-                    s.color_rgb,
-                    &mut image.pixels,
-                );
+
+            // Compile every shape in parallel from a shared context
+            let mut ctx = fidget::Context::new();
+            let nodes: Vec<_> =
+                out.shapes.iter().map(|s| ctx.import(&s.tree)).collect();
+            let tapes = S::new_many(&ctx, &nodes)?;
+            if let RenderMode::TwoD(camera, TwoDMode::Color) =
+                &render_config.mode
+            {
+                // Composite every shape in a single pass
+                let layers: Vec<_> = out
+                    .shapes
+                    .iter()
+                    .zip(tapes)
+                    .map(|(s, tape)| {
+                        let [r, g, b] = s.color_rgb;
+                        fidget::render::Layer::new(tape, [r, g, b, u8::MAX])
+                    })
+                    .collect();
+                let config = config_2d::<S>(camera, render_config.image_size);
+                let out = fidget::render::render2d_layers::<
+                    _,
+                    fidget::render::AntialiasRenderMode,
+                >(&layers, &config)?;
+                // Edges are partially transparent, so blend onto the black
+                // background
+                for (p, c) in image.pixels.iter_mut().zip(&out) {
+                    let f = |v: u8| (v as u32 * c[3] as u32 / 255) as u8;
+                    *p = egui::Color32::from_rgb(f(c[0]), f(c[1]), f(c[2]));
+                }
+            } else {
+                for (s, tape) in out.shapes.iter().zip(tapes) {
+                    render(
+                        &render_config.mode,
+                        tape,
+                        render_config.image_size,
+                        s.color_rgb,
+                        &mut image.pixels,
+                    )?;
+                }
             }
             let dt = render_start.elapsed();
             let image = egui::ImageData::Color(std::sync::Arc::new(image));
@@ -148,31 +179,38 @@ where
     }
 }
 
+fn config_2d<S: fidget::eval::Shape>(
+    camera: &TwoDCamera,
+    image_size: usize,
+) -> RenderConfig<2> {
+    RenderConfig {
+        image_size: image_size.into(),
+        tile_sizes: S::tile_sizes_2d().to_vec(),
+        bounds: Some(fidget::shape::Bounds {
+            center: Vector2::new(camera.offset.x, camera.offset.y),
+            size: camera.scale,
+        }),
+        ..RenderConfig::default()
+    }
+}
+
 fn render<S: fidget::eval::Shape>(
     mode: &RenderMode,
     shape: S,
     image_size: usize,
     color: [u8; 3],
     pixels: &mut [egui::Color32],
-) {
+) -> Result<()> {
     match mode {
         RenderMode::TwoD(camera, mode) => {
-            let config = RenderConfig {
-                image_size,
-                tile_sizes: S::tile_sizes_2d().to_vec(),
-                bounds: fidget::shape::Bounds {
-                    center: Vector2::new(camera.offset.x, camera.offset.y),
-                    size: camera.scale,
-                },
-                ..RenderConfig::default()
-            };
+            let config = config_2d::<S>(camera, image_size);
 
             match mode {
                 TwoDMode::Color => {
                     let image = fidget::render::render2d::<
                         _,
                         fidget::render::BitRenderMode,
-                    >(shape, &config);
+                    >(shape, &config)?;
                     let c = egui::Color32::from_rgba_unmultiplied(
                         color[0],
                         color[1],
@@ -190,7 +228,7 @@ fn render<S: fidget::eval::Shape>(
                     let image = fidget::render::render2d::<
                         _,
                         fidget::render::SdfRenderMode,
-                    >(shape, &config);
+                    >(shape, &config)?;
                     for (p, i) in pixels.iter_mut().zip(&image) {
                         *p = egui::Color32::from_rgb(i[0], i[1], i[2]);
                     }
@@ -200,7 +238,7 @@ fn render<S: fidget::eval::Shape>(
                     let image = fidget::render::render2d::<
                         _,
                         fidget::render::DebugRenderMode,
-                    >(shape, &config);
+                    >(shape, &config)?;
                     for (p, i) in pixels.iter_mut().zip(&image) {
                         let c = i.as_debug_color();
                         *p = egui::Color32::from_rgb(c[0], c[1], c[2]);
@@ -210,15 +248,15 @@ fn render<S: fidget::eval::Shape>(
         }
         RenderMode::ThreeD(camera, mode) => {
             let config = RenderConfig {
-                image_size,
+                image_size: image_size.into(),
                 tile_sizes: S::tile_sizes_2d().to_vec(),
-                bounds: fidget::shape::Bounds {
+                bounds: Some(fidget::shape::Bounds {
                     center: Vector3::new(camera.offset.x, camera.offset.y, 0.0),
                     size: camera.scale,
-                },
+                }),
                 ..RenderConfig::default()
             };
-            let (depth, color) = fidget::render::render3d(shape, &config);
+            let (depth, color) = fidget::render::render3d(shape, &config)?;
             match mode {
                 ThreeDMode::Color => {
                     for (p, (&d, &c)) in
@@ -243,6 +281,7 @@ fn render<S: fidget::eval::Shape>(
             }
         }
     };
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -82,11 +82,11 @@ pub fn render_region(
         let center = corner.add_scalar(scale / 2.0);
 
         let cfg = RenderConfig::<2> {
-            image_size: image_size / workers_per_side,
-            bounds: Bounds {
+            image_size: (image_size / workers_per_side).into(),
+            bounds: Some(Bounds {
                 center,
                 size: scale / 2.0,
-            },
+            }),
             ..RenderConfig::default()
         };
 